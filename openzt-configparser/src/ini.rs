@@ -251,6 +251,24 @@ pub enum MergeMode {
     BasePriority,
 }
 
+///Determines how the values of a key present in both configurations are combined when merging
+///with `MergeMode::PatchPriority`.
+///
+///Some files repeat a key within a section, which is stored as several values for that key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyMode {
+    ///Patch values replace every existing value (default)
+    #[default]
+    ReplaceAll,
+    ///Patch values replace the first existing value, later repeats are kept
+    ReplaceFirst,
+    ///Patch values are added after the existing values
+    AppendDuplicate,
+    ///Values are matched by their first comma-separated field, patch values replace matching
+    ///existing values and the rest are added after them
+    KeyedByValue,
+}
+
 impl Ini {
     ///Creates a new `Map` of `Map<String, Map<String, Option<String>>>` type for the struct.
     ///All values in the Map are stored in `String` type.
@@ -1422,23 +1440,73 @@ impl Ini {
     ///// base.merge_with_priority(&patch, openzt_configparser::ini::MergeMode::BasePriority);
     ///```
     pub fn merge_with_priority(&mut self, other: &Ini, merge_mode: MergeMode) {
+        self.merge_with_options(other, merge_mode, DuplicateKeyMode::ReplaceAll);
+    }
+
+    ///Merges another Ini configuration into this one, choosing how repeated keys are combined.
+    ///
+    ///With `MergeMode::PatchPriority`, `duplicate_mode` decides how the values of a key present in
+    ///both configurations are combined. With `MergeMode::BasePriority` existing keys are left as they are.
+    ///
+    ///## Example
+    ///```rust
+    ///use openzt_configparser::ini::{DuplicateKeyMode, Ini, MergeMode};
+    ///
+    ///let mut base = Ini::new();
+    ///base.read(String::from("[section]\nkey=1, a\nkey=2, b"));
+    ///
+    ///let mut patch = Ini::new();
+    ///patch.read(String::from("[section]\nkey=2, c\nkey=3, d"));
+    ///
+    ///base.merge_with_options(&patch, MergeMode::PatchPriority, DuplicateKeyMode::KeyedByValue);
+    ///assert_eq!(base.get_vec("section", "key").unwrap(), vec!["1, a", "2, c", "3, d"]);
+    ///```
+    pub fn merge_with_options(&mut self, other: &Ini, merge_mode: MergeMode, duplicate_mode: DuplicateKeyMode) {
         for (section, section_map) in other.map.iter() {
             let target_section = self.map.entry(section.clone()).or_default();
 
-            match merge_mode {
-                MergeMode::PatchPriority => {
-                    // Patch values overwrite existing values
-                    target_section.extend(section_map.clone());
-                }
-                MergeMode::BasePriority => {
-                    // Preserve existing values, only add new keys
-                    for (key, value) in section_map.iter() {
-                        target_section.entry(key.clone()).or_insert_with(|| value.clone());
+            for (key, value) in section_map.iter() {
+                match target_section.get_mut(key) {
+                    None => {
+                        target_section.insert(key.clone(), value.clone());
                     }
+                    Some(existing) => {
+                        // Preserve existing values with BasePriority, only add new keys
+                        if merge_mode == MergeMode::PatchPriority {
+                            *existing = merge_values(existing.take(), value.clone(), duplicate_mode);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+///Private function to combine the values of a key present in both merged configurations
+fn merge_values(existing: Option<Vec<String>>, patch: Option<Vec<String>>, duplicate_mode: DuplicateKeyMode) -> Option<Vec<String>> {
+    let (mut existing, patch) = match (existing, patch) {
+        (Some(existing), Some(patch)) => (existing, patch),
+        (_, patch) => return patch,
+    };
+
+    match duplicate_mode {
+        DuplicateKeyMode::ReplaceAll => return Some(patch),
+        DuplicateKeyMode::ReplaceFirst => {
+            existing.splice(..existing.len().min(1), patch);
+        }
+        DuplicateKeyMode::AppendDuplicate => existing.extend(patch),
+        DuplicateKeyMode::KeyedByValue => {
+            let value_key = |value: &str| value.split(',').next().unwrap_or_default().trim().to_owned();
+            for value in patch {
+                let key = value_key(&value);
+                match existing.iter().position(|e| value_key(e) == key) {
+                    Some(index) => existing[index] = value,
+                    None => existing.push(value),
                 }
             }
         }
     }
+    Some(existing)
 }
 
 ///Private function to check if string contains newlines
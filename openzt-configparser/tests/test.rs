@@ -1,4 +1,4 @@
-use openzt_configparser::ini::{Ini, WriteOptions};
+use openzt_configparser::ini::{DuplicateKeyMode, Ini, MergeMode, WriteOptions};
 use std::error::Error;
 
 #[test]
@@ -487,3 +487,32 @@ fn get_keys() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn merge_duplicate_keys() -> Result<(), Box<dyn Error>> {
+    let mut patch = Ini::new_cs();
+    patch.read(String::from("[Section]\nName=2, Patched\nName=4, New\n"))?;
+
+    let merged = |duplicate_mode| -> Result<Vec<String>, Box<dyn Error>> {
+        let mut base = Ini::new_cs();
+        base.read(String::from("[Section]\nName=1, First\nName=2, Second\nName=3, Third\n"))?;
+        base.merge_with_options(&patch, MergeMode::PatchPriority, duplicate_mode);
+        Ok(base.get_vec("Section", "Name").unwrap())
+    };
+
+    assert_eq!(merged(DuplicateKeyMode::ReplaceAll)?, vec!["2, Patched", "4, New"]);
+    assert_eq!(merged(DuplicateKeyMode::ReplaceFirst)?, vec!["2, Patched", "4, New", "2, Second", "3, Third"]);
+    assert_eq!(
+        merged(DuplicateKeyMode::AppendDuplicate)?,
+        vec!["1, First", "2, Second", "3, Third", "2, Patched", "4, New"]
+    );
+    assert_eq!(merged(DuplicateKeyMode::KeyedByValue)?, vec!["1, First", "2, Patched", "3, Third", "4, New"]);
+
+    // Existing keys are left alone with BasePriority whatever the duplicate mode
+    let mut base = Ini::new_cs();
+    base.read(String::from("[Section]\nName=1, First\n"))?;
+    base.merge_with_options(&patch, MergeMode::BasePriority, DuplicateKeyMode::AppendDuplicate);
+    assert_eq!(base.get_vec("Section", "Name").unwrap(), vec!["1, First"]);
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Blocking client for the OpenZT console
+//!
+//! ```no_run
+//! use openzt_console::client::ConsoleClient;
+//!
+//! let mut client = ConsoleClient::connect("127.0.0.1:8080")?;
+//! println!("{}", client.call("help", &[])?);
+//! println!("{}", client.lua("return 1 + 1")?);
+//! # Ok::<(), openzt_console::client::ClientError>(())
+//! ```
+
+use std::fmt;
+use std::io::{self, BufReader};
+use std::net::{TcpStream, ToSocketAddrs};
+
+use serde_json::Value;
+
+use crate::protocol::{self, ConsoleError, Request, Response};
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The connection failed or the console sent something that isn't a valid response
+    Io(io::Error),
+    /// The console answered with an error
+    Console(ConsoleError),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "Console connection error: {}", e),
+            ClientError::Console(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(err: io::Error) -> Self {
+        ClientError::Io(err)
+    }
+}
+
+/// Connection to the console, sending one request at a time
+pub struct ConsoleClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+}
+
+impl ConsoleClient {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let writer = TcpStream::connect(addr)?;
+        writer.set_nodelay(true)?;
+        Ok(ConsoleClient {
+            reader: BufReader::new(writer.try_clone()?),
+            writer,
+            next_id: 1,
+        })
+    }
+
+    /// Call the console function `command` with `args` and return its output
+    pub fn call(&mut self, command: &str, args: &[Value]) -> Result<String, ClientError> {
+        let id = self.next_id();
+        self.send(Request::new(id, command, args.to_vec()))
+    }
+
+    /// Run `code` as Lua and return its output
+    pub fn lua(&mut self, code: &str) -> Result<String, ClientError> {
+        let id = self.next_id();
+        self.send(Request::lua(id, code))
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn send(&mut self, request: Request) -> Result<String, ClientError> {
+        protocol::write_frame(&mut self.writer, &request)?;
+        let payload = protocol::read_frame(&mut self.reader)?.ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Console closed the connection"))?;
+        let response: Response = protocol::decode_payload(&payload)?;
+        if response.id != request.id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Expected the response to request {}, got {}", request.id, response.id),
+            )
+            .into());
+        }
+        response.into_result().map_err(ClientError::Console)
+    }
+}
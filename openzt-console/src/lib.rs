@@ -0,0 +1,7 @@
+//! Protocol and client for the OpenZT Lua console
+//!
+//! The console server runs inside the OpenZT DLL; [`protocol`] defines the framed JSON
+//! messages it speaks and [`client`] is a blocking client for tools that talk to it.
+
+pub mod client;
+pub mod protocol;
@@ -1,7 +1,7 @@
 use clap::Parser;
 use nanospinner::Spinner;
-use std::io::{self, Read, Write};
-use std::net::TcpStream;
+use openzt_console::client::{ClientError, ConsoleClient};
+use std::io::{self, Write};
 use std::thread;
 use std::time::Duration;
 
@@ -62,23 +62,23 @@ fn main() -> io::Result<()> {
     }
 }
 
-fn connect_with_wait(host: &str, wait: bool) -> io::Result<TcpStream> {
+fn connect_with_wait(host: &str, wait: bool) -> io::Result<ConsoleClient> {
     if wait {
         loop {
-            match TcpStream::connect(host) {
-                Ok(stream) => return Ok(stream),
+            match ConsoleClient::connect(host) {
+                Ok(client) => return Ok(client),
                 Err(_) => {
                     thread::sleep(Duration::from_secs(1));
                 }
             }
         }
     } else {
-        TcpStream::connect(host)
+        ConsoleClient::connect(host)
     }
 }
 
-/// Wait for the console server to be ready by sending ping() until we get pong
-fn wait_for_ready(host: &str, wait: bool) -> io::Result<TcpStream> {
+/// Wait for the console server to be ready by calling ping() until we get pong
+fn wait_for_ready(host: &str, wait: bool) -> io::Result<ConsoleClient> {
     let spinner = if wait {
         Some(SpinnerHandle::new("Waiting for console to initialise..."))
     } else {
@@ -90,8 +90,8 @@ fn wait_for_ready(host: &str, wait: bool) -> io::Result<TcpStream> {
         if let Some(ref sp) = spinner {
             sp.update(&format!("Connecting to {}...", host));
         }
-        let mut stream = match connect_with_wait(host, wait) {
-            Ok(s) => s,
+        let mut client = match connect_with_wait(host, wait) {
+            Ok(client) => client,
             Err(e) => {
                 if wait {
                     continue;
@@ -105,72 +105,51 @@ fn wait_for_ready(host: &str, wait: bool) -> io::Result<TcpStream> {
         if let Some(ref sp) = spinner {
             sp.update("Waiting for server to respond...");
         }
-        if let Err(_) = stream.write_all(b"ping()") {
-            if wait {
-                thread::sleep(Duration::from_secs(1));
-                continue;
-            } else {
-                return Err(io::Error::new(io::ErrorKind::Other, "Failed to send ping"));
-            }
-        }
-
-        // Read response
-        let mut buffer = [0; 1024];
-        match stream.read(&mut buffer) {
-            Ok(size) => {
-                let response = String::from_utf8_lossy(&buffer[0..size]).trim().to_string();
-                if response == "pong" {
-                    // Server is ready
-                    if let Some(sp) = spinner {
-                        sp.success(&format!("Connected to {}", host));
-                    }
-                    return Ok(stream);
-                } else {
-                    // Unexpected response
-                    if wait {
-                        thread::sleep(Duration::from_secs(1));
-                        continue;
-                    } else {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            format!("Expected 'pong', got: {}", response)
-                        ));
-                    }
+        match client.call("ping", &[]) {
+            Ok(response) if response.trim() == "pong" => {
+                // Server is ready
+                if let Some(sp) = spinner {
+                    sp.success(&format!("Connected to {}", host));
                 }
+                return Ok(client);
             }
-            Err(_) => {
-                // Connection reset or other error
+            result => {
+                // Unexpected response, or the connection was reset
                 if wait {
                     thread::sleep(Duration::from_secs(1));
                     continue;
-                } else {
-                    return Err(io::Error::new(io::ErrorKind::Other, "Failed to read response"));
                 }
+                let message = match result {
+                    Ok(response) => format!("Expected 'pong', got: {}", response),
+                    Err(e) => e.to_string(),
+                };
+                if let Some(sp) = spinner {
+                    sp.fail(&message);
+                }
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
             }
         }
     }
 }
 
 fn run_oneshot(host: &str, command: &str, wait: bool) -> io::Result<()> {
-    let mut stream = wait_for_ready(host, wait)?;
-
-    // Send command
-    stream.write_all(command.as_bytes())?;
-
-    // Read response
-    let mut buffer = [0; 100024];
-    let size = stream.read(&mut buffer)?;
+    let mut client = wait_for_ready(host, wait)?;
 
-    if size > 0 {
-        let response = String::from_utf8_lossy(&buffer[0..size]);
-        print!("{}", response);
+    match client.lua(command) {
+        Ok(output) => {
+            print!("{}", output);
+            Ok(())
+        }
+        Err(ClientError::Io(e)) => Err(e),
+        Err(ClientError::Console(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
-
-    Ok(())
 }
 
 fn run_interactive(host: &str, wait: bool) -> io::Result<()> {
-    let mut stream = wait_for_ready(host, wait)?;
+    let mut client = wait_for_ready(host, wait)?;
     println!("Connected to server at {}", host);
 
     loop {
@@ -186,30 +165,11 @@ fn run_interactive(host: &str, wait: bool) -> io::Result<()> {
             continue;
         }
 
-        match stream.write(trimmed.as_bytes()) {
-            Ok(_) => {
-                // Message sent successfully
-            }
-            Err(err) => {
-                eprintln!("Error sending data to server: {}", err);
-                break;
-            }
-        }
-
-        let mut buffer = [0; 100024];
-        match stream.read(&mut buffer) {
-            Ok(size) => {
-                if size == 0 {
-                    // Connection closed by server
-                    break;
-                }
-
-                // Print server response
-                let response = String::from_utf8_lossy(&buffer[0..size]);
-                println!("Server response: {}", response);
-            }
-            Err(err) => {
-                eprintln!("Error reading data from server: {}", err);
+        match client.lua(trimmed) {
+            Ok(output) => println!("Server response: {}", output),
+            Err(ClientError::Console(e)) => eprintln!("Error: {}", e),
+            Err(ClientError::Io(e)) => {
+                eprintln!("Error talking to server: {}", e);
                 break;
             }
         }
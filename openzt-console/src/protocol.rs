@@ -0,0 +1,325 @@
+//! Framed JSON protocol spoken by the OpenZT console
+//!
+//! Every message is a frame: the length of the payload as a 4-byte big-endian integer,
+//! followed by the payload, a UTF-8 JSON object. Clients send [`Request`]s and the console
+//! answers each with a [`Response`] carrying the same `id`, in the order they were sent.
+//!
+//! ```text
+//! {"id": 1, "command": "get_legacy_attribute", "args": ["animal", "elephant", "m", "name_id"]}
+//! {"id": 1, "ok": "5001"}
+//! {"id": 2, "command": "lua", "args": ["return 1 +"]}
+//! {"id": 2, "error": {"kind": "command_failed", "message": "Lua error: ..."}}
+//! ```
+//!
+//! A request calls the console function named by `command` with `args`, which can be
+//! strings, numbers, booleans or null. The `lua` command runs its only argument as Lua code
+//! instead. Frames are limited to [`MAX_FRAME_LEN`], so the first byte of a frame is always
+//! 0; the console uses that to tell framed clients from older ones that send raw Lua text.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Largest payload of a frame, just under 16 MiB so the first byte of the length is 0
+pub const MAX_FRAME_LEN: usize = (1 << 24) - 1;
+
+/// Command that runs its argument as Lua code rather than calling a console function
+pub const LUA_COMMAND: &str = "lua";
+
+/// A command sent to the console
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Request {
+    /// Chosen by the client and echoed in the response, 0 is used for unreadable requests
+    pub id: u64,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<Value>,
+}
+
+impl Request {
+    pub fn new(id: u64, command: &str, args: Vec<Value>) -> Self {
+        Request {
+            id,
+            command: command.to_string(),
+            args,
+        }
+    }
+
+    /// A request that runs `code` as Lua
+    pub fn lua(id: u64, code: &str) -> Self {
+        Request::new(id, LUA_COMMAND, vec![Value::String(code.to_string())])
+    }
+
+    /// The Lua code the console runs for this request
+    pub fn to_lua(&self) -> Result<String, ConsoleError> {
+        if self.command == LUA_COMMAND {
+            return match self.args.as_slice() {
+                [Value::String(code)] => Ok(code.clone()),
+                _ => Err(ConsoleError::invalid_request("'lua' takes the code to run as its only argument")),
+            };
+        }
+        if !is_identifier(&self.command) {
+            return Err(ConsoleError::invalid_request(format!("'{}' is not a valid command name", self.command)));
+        }
+        let args = self.args.iter().map(lua_literal).collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("{}({})", self.command, args.join(", ")))
+    }
+}
+
+/// The console's answer to a [`Request`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Response {
+    pub id: u64,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+impl Response {
+    pub fn new(id: u64, result: Result<String, ConsoleError>) -> Self {
+        let outcome = match result {
+            Ok(output) => Outcome::Ok(output),
+            Err(error) => Outcome::Error(error),
+        };
+        Response { id, outcome }
+    }
+
+    pub fn into_result(self) -> Result<String, ConsoleError> {
+        match self.outcome {
+            Outcome::Ok(output) => Ok(output),
+            Outcome::Error(error) => Err(error),
+        }
+    }
+}
+
+/// Output of a command, or why it failed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok(String),
+    Error(ConsoleError),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The request couldn't be read or turned into Lua
+    InvalidRequest,
+    /// The command ran and raised an error
+    CommandFailed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConsoleError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl ConsoleError {
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        ConsoleError {
+            kind: ErrorKind::InvalidRequest,
+            message: message.into(),
+        }
+    }
+
+    pub fn command_failed(message: impl Into<String>) -> Self {
+        ConsoleError {
+            kind: ErrorKind::CommandFailed,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConsoleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ConsoleError {}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A JSON argument as a Lua literal
+fn lua_literal(value: &Value) -> Result<String, ConsoleError> {
+    match value {
+        Value::Null => Ok("nil".to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => {
+            let mut literal = String::with_capacity(s.len() + 2);
+            literal.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => literal.push_str("\\\""),
+                    '\\' => literal.push_str("\\\\"),
+                    '\n' => literal.push_str("\\n"),
+                    '\r' => literal.push_str("\\r"),
+                    c if c.is_ascii_control() => literal.push_str(&format!("\\{:03}", c as u8)),
+                    c => literal.push(c),
+                }
+            }
+            literal.push('"');
+            Ok(literal)
+        }
+        Value::Array(_) | Value::Object(_) => Err(ConsoleError::invalid_request(format!(
+            "Unsupported argument {}, only strings, numbers, booleans and null can be passed",
+            value
+        ))),
+    }
+}
+
+/// `message` as a frame
+pub fn encode_frame<T: Serialize>(message: &T) -> io::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(message)?;
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Message of {} bytes is larger than the {} byte frame limit", payload.len(), MAX_FRAME_LEN),
+        ));
+    }
+    let mut frame = Vec::with_capacity(payload.len() + 4);
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Parse a frame's payload
+pub fn decode_payload<'a, T: Deserialize<'a>>(payload: &'a [u8]) -> io::Result<T> {
+    serde_json::from_slice(payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_frame<W: Write, T: Serialize>(writer: &mut W, message: &T) -> io::Result<()> {
+    writer.write_all(&encode_frame(message)?)?;
+    writer.flush()
+}
+
+/// Read the payload of the next frame, `None` if the connection closed between frames
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let length = frame_length(length)?;
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+fn frame_length(length: [u8; 4]) -> io::Result<usize> {
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Frame of {} bytes is larger than the {} byte limit", length, MAX_FRAME_LEN),
+        ));
+    }
+    Ok(length)
+}
+
+/// Splits a byte stream that arrives in arbitrary chunks, such as over a WebSocket tunnel, into frames
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        FrameDecoder::default()
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// The payload of the next complete frame, if one has arrived
+    pub fn next_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let Some(length) = self.buffer.first_chunk::<4>() else {
+            return Ok(None);
+        };
+        let length = frame_length(*length)?;
+        if self.buffer.len() < length + 4 {
+            return Ok(None);
+        }
+        let payload = self.buffer[4..length + 4].to_vec();
+        self.buffer.drain(..length + 4);
+        Ok(Some(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_message_json() {
+        let request: Request = serde_json::from_str(r#"{"id": 3, "command": "help"}"#).unwrap();
+        assert_eq!(request, Request::new(3, "help", Vec::new()));
+
+        let ok = Response::new(3, Ok("pong".to_string()));
+        assert_eq!(serde_json::to_value(&ok).unwrap(), json!({"id": 3, "ok": "pong"}));
+        let error = Response::new(4, Err(ConsoleError::command_failed("Lua error: boom")));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({"id": 4, "error": {"kind": "command_failed", "message": "Lua error: boom"}})
+        );
+        assert_eq!(serde_json::from_value::<Response>(json!({"id": 3, "ok": "pong"})).unwrap(), ok);
+        assert_eq!(serde_json::from_value::<Response>(serde_json::to_value(&error).unwrap()).unwrap(), error);
+    }
+
+    #[test]
+    fn test_to_lua() {
+        assert_eq!(Request::new(1, "help", Vec::new()).to_lua().unwrap(), "help()");
+        assert_eq!(
+            Request::new(1, "set", vec![json!("a \"b\"\n\\"), json!(5), json!(-1.5), json!(true), json!(null)])
+                .to_lua()
+                .unwrap(),
+            r#"set("a \"b\"\n\\", 5, -1.5, true, nil)"#
+        );
+        assert_eq!(Request::new(1, "echo", vec![json!("tab\there")]).to_lua().unwrap(), r#"echo("tab\009here")"#);
+        assert_eq!(Request::lua(1, "return 1 + 1").to_lua().unwrap(), "return 1 + 1");
+
+        let invalid = |request: Request| request.to_lua().unwrap_err().kind;
+        assert_eq!(invalid(Request::new(1, "os.exit", Vec::new())), ErrorKind::InvalidRequest);
+        assert_eq!(invalid(Request::new(1, "1help", Vec::new())), ErrorKind::InvalidRequest);
+        assert_eq!(invalid(Request::new(1, "help", vec![json!([1])])), ErrorKind::InvalidRequest);
+        assert_eq!(invalid(Request::new(1, LUA_COMMAND, Vec::new())), ErrorKind::InvalidRequest);
+    }
+
+    #[test]
+    fn test_frames() {
+        let request = Request::lua(7, "ping()");
+        let frame = encode_frame(&request).unwrap();
+        assert_eq!(frame[0], 0);
+
+        let mut stream = frame.as_slice();
+        let payload = read_frame(&mut stream).unwrap().unwrap();
+        assert_eq!(decode_payload::<Request>(&payload).unwrap(), request);
+        assert!(read_frame(&mut stream).unwrap().is_none());
+
+        let mut decoder = FrameDecoder::new();
+        let mut two = frame.clone();
+        two.extend_from_slice(&frame);
+        decoder.push(&two[..3]);
+        assert!(decoder.next_frame().unwrap().is_none());
+        decoder.push(&two[3..frame.len() + 5]);
+        assert_eq!(decoder.next_frame().unwrap().unwrap(), payload);
+        assert!(decoder.next_frame().unwrap().is_none());
+        decoder.push(&two[frame.len() + 5..]);
+        assert_eq!(decoder.next_frame().unwrap().unwrap(), payload);
+
+        let mut oversized = FrameDecoder::new();
+        oversized.push(&[0xff, 0, 0, 0]);
+        assert!(oversized.next_frame().is_err());
+        assert!(read_frame(&mut &b"raw lua"[..]).is_err());
+    }
+}
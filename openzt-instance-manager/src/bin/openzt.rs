@@ -12,7 +12,7 @@ use openzt_instance_manager::id_resolver::resolve_instance_id;
 #[cfg(feature = "cli")]
 use clap::{Parser, Subcommand, Args};
 #[cfg(feature = "cli")]
-use miette::{miette, Result};
+use miette::Result;
 
 // Conditionally compile the CLI
 #[cfg(feature = "cli")]
@@ -23,11 +23,29 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
 
-    // Determine API URL: CLI flag > config file > default
+    openzt_instance_manager::output::init_styling(cli.global.no_color || config.output.theme == "mono");
+
+    // Determine table style: CLI flag > config file > modern default
+    let table_style = cli
+        .global
+        .table_style
+        .as_deref()
+        .and_then(|s| s.parse::<openzt_instance_manager::output::TableStyle>().ok())
+        .or_else(|| config.table_style())
+        .unwrap_or_default();
+    openzt_instance_manager::output::init_display(openzt_instance_manager::output::DisplaySettings {
+        date_format: config.output.date_format.clone(),
+        relative_times: config.output.relative_times,
+        table_style,
+    });
+
+    // Determine API URL: CLI flag > profile > config file > default
+    let profile = cli.global.profile.clone();
     let api_url = cli
         .global
         .api_url
-        .unwrap_or_else(|| config.api.base_url.clone());
+        .clone()
+        .unwrap_or_else(|| config.base_url_for(&profile));
 
     // Determine output format: CLI flag > config file > table default
     let output_format = cli
@@ -38,23 +56,67 @@ async fn main() -> Result<()> {
         .unwrap_or(openzt_instance_manager::output::OutputFormat::Table);
 
     // Create HTTP client
-    let client = openzt_instance_manager::client::InstanceClient::new(api_url);
+    let client = openzt_instance_manager::client::InstanceClient::new(api_url.clone());
+    let mut client = match client.with_network(&config.api.network_settings()) {
+        Ok(client) => client,
+        Err(e) => openzt_instance_manager::output::exit_with_error(
+            &format!("{:#}", e),
+            openzt_instance_manager::output::ErrorCode::Error,
+            output_format,
+        ),
+    };
+    // Logging in checks a new token, without the stored one
+    let unauthenticated = client.clone();
+    if let Some(token) = config.token_for(&profile) {
+        client = match client.with_token(token) {
+            Ok(client) => client,
+            Err(e) => openzt_instance_manager::output::exit_with_error(
+                &format!("Invalid token for profile '{}': {}", profile, e),
+                openzt_instance_manager::output::ErrorCode::Error,
+                output_format,
+            ),
+        };
+    }
+    if config.cache.enabled
+        && let Ok(cache_dir) = openzt_instance_manager::client_config::ClientConfig::cache_dir()
+    {
+        client = client.with_disk_cache(cache_dir, std::time::Duration::from_secs(config.cache.ttl_secs));
+    }
 
     // Execute the appropriate subcommand
     match cli.command {
-        Commands::Create { dll_path, config: instance_config } => {
-            cmd_create(&client, &dll_path, instance_config, output_format).await
+        Commands::Create { dll_path, from_file, config: instance_config, mods_dir, wait, no_wait, wait_timeout } => {
+            let defaults = &config.create;
+            let options = CreateOptions {
+                mods_dir: mods_dir.or_else(|| defaults.mods_dir.clone()),
+                wait: (wait || defaults.wait) && !no_wait,
+                wait_timeout: std::time::Duration::from_secs(wait_timeout.unwrap_or(defaults.wait_timeout_secs)),
+            };
+            cmd_create(&client, dll_path, from_file, instance_config, defaults, options, output_format).await
         }
-        Commands::List {} => cmd_list(&client, output_format).await,
-        Commands::Get { id } => cmd_get(&client, &id, output_format).await,
-        Commands::Delete { id, confirm } => cmd_delete(&client, &id, confirm, output_format).await,
-        Commands::Logs { id, log_type, follow, tail } => {
-            cmd_logs(&client, &id, &log_type, follow, tail, output_format).await
+        Commands::List { wide } => cmd_list(&client, wide, output_format).await,
+        Commands::Get { id, field } => cmd_get(&client, &id, field.as_deref(), output_format).await,
+        Commands::Export { id } => cmd_export(&client, &id, output_format).await,
+        Commands::Diff { left, right, all } => cmd_diff(&client, &left, &right, all, output_format).await,
+        Commands::Delete { id, confirm, purge } => cmd_delete(&client, &id, confirm, purge, output_format).await,
+        Commands::Restore { id } => cmd_restore(&client, &id, output_format).await,
+        Commands::Rename { id, name } => cmd_rename(&client, &id, name, output_format).await,
+        Commands::Label { id, labels, remove } => cmd_label(&client, &id, &labels, remove, output_format).await,
+        Commands::Logs { ids, all, log_type, follow, tail, since, until, timestamps } => {
+            let options = openzt_instance_manager::client::LogOptions { tail, since, until, timestamps };
+            cmd_logs(&client, &ids, all, &log_type, follow, options, output_format).await
         }
         Commands::Stop { id } => cmd_stop(&client, &id, output_format).await,
         Commands::Start { id } => cmd_start(&client, &id, output_format).await,
         Commands::Restart { id } => cmd_restart(&client, &id, output_format).await,
         Commands::Health {} => cmd_health(&client, output_format).await,
+        Commands::Events {} => cmd_events(&client, output_format).await,
+        Commands::Console { id, command } => cmd_console(&client, &id, &command, output_format).await,
+        Commands::PortForward { id, port, local_port, address } => {
+            cmd_port_forward(&client, &id, &port, local_port, &address, output_format).await
+        }
+        Commands::Login { token } => cmd_login(unauthenticated, &api_url, &profile, token, output_format).await,
+        Commands::Logout {} => cmd_logout(&profile, output_format),
     }
 }
 
@@ -73,7 +135,6 @@ struct Cli {
 
 #[cfg(feature = "cli")]
 #[derive(Args)]
-#[group(multiple = false)]
 struct GlobalArgs {
     /// API URL
     #[arg(long, global = true)]
@@ -82,6 +143,18 @@ struct GlobalArgs {
     /// Output format (table or json)
     #[arg(long, global = true, value_name = "FORMAT")]
     output: Option<String>,
+
+    /// Table border style
+    #[arg(long, global = true, value_parser = ["modern", "ascii", "markdown", "compact"])]
+    table_style: Option<String>,
+
+    /// Disable colored output (also honors the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Client config profile to use for the API URL and credentials
+    #[arg(long, global = true, default_value = openzt_instance_manager::client_config::DEFAULT_PROFILE)]
+    profile: String,
 }
 
 #[cfg(feature = "cli")]
@@ -89,36 +162,122 @@ struct GlobalArgs {
 enum Commands {
     /// Create a new instance
     Create {
-        /// Path to the openzt.dll file
-        dll_path: PathBuf,
+        /// Path to the openzt.dll file (overrides `dll` in --from-file)
+        #[arg(required_unless_present = "from_file")]
+        dll_path: Option<PathBuf>,
+
+        /// Create from an instance definition file (see `openzt export`)
+        #[arg(long)]
+        from_file: Option<PathBuf>,
 
         #[command(flatten)]
         config: InstanceConfigArgs,
+
+        /// Install the .ztd files of this directory in the instance
+        #[arg(long)]
+        mods_dir: Option<PathBuf>,
+
+        /// Wait for the instance's game to be running before returning
+        #[arg(long, conflicts_with = "no_wait")]
+        wait: bool,
+
+        /// Return once the container is started, even if `wait` is set in the config file
+        #[arg(long)]
+        no_wait: bool,
+
+        /// Seconds to wait for the instance with --wait
+        #[arg(long)]
+        wait_timeout: Option<u64>,
     },
 
     /// List all instances
-    List {},
+    List {
+        /// Show additional columns (container ID, CPU limit, Wine debug channels)
+        #[arg(short, long)]
+        wide: bool,
+    },
 
     /// Get instance details
     Get {
-        /// Instance ID (full UUID or short prefix)
+        /// Instance ID (full UUID, short prefix or name)
         id: String,
+
+        /// Print only this field (e.g. vnc_url, config.cpulimit)
+        #[arg(long)]
+        field: Option<String>,
     },
 
-    /// Delete an instance
+    /// Compare the configuration of two instances
+    Diff {
+        /// First instance ID (full UUID, short prefix or name)
+        left: String,
+
+        /// Second instance ID (full UUID, short prefix or name)
+        right: String,
+
+        /// Also compare fields that identify an instance (ID, ports, creation time)
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Print an instance's configuration as an instance definition file
+    Export {
+        /// Instance ID (full UUID, short prefix or name)
+        id: String,
+    },
+
+    /// Delete an instance, moving it to the server's trash
     Delete {
-        /// Instance ID (full UUID or short prefix)
+        /// Instance ID (full UUID, short prefix or name)
         id: String,
 
         /// Skip confirmation prompt
         #[arg(short, long)]
         confirm: bool,
+
+        /// Delete the instance for good instead, even if it is in the trash
+        #[arg(long)]
+        purge: bool,
+    },
+
+    /// Take a deleted instance out of the trash, stopped
+    Restore {
+        /// Instance ID (full UUID, short prefix or name)
+        id: String,
+    },
+
+    /// Name an instance, so commands accept the name in place of its ID
+    Rename {
+        /// Instance ID (full UUID, short prefix or name)
+        id: String,
+
+        /// New name (letters, digits, '-', '_' and '.'), or "" to remove the name
+        name: String,
+    },
+
+    /// Set or remove an instance's labels
+    Label {
+        /// Instance ID (full UUID, short prefix or name)
+        id: String,
+
+        /// Labels to set, as key=value
+        #[arg(value_name = "KEY=VALUE", required_unless_present = "remove")]
+        labels: Vec<String>,
+
+        /// Remove a label (may be repeated)
+        #[arg(long, value_name = "KEY")]
+        remove: Vec<String>,
     },
 
     /// Get instance logs
     Logs {
-        /// Instance ID (full UUID or short prefix)
-        id: String,
+        /// Instance IDs (full UUID, short prefix or name); several IDs interleave their logs
+        #[arg(required_unless_present = "all")]
+        ids: Vec<String>,
+
+        /// Show logs from all instances
+        #[arg(long, conflicts_with = "ids")]
+        all: bool,
 
         /// Log type to read (docker, openzt, integration-tests)
         #[arg(long, default_value = "openzt")]
@@ -128,29 +287,82 @@ enum Commands {
         #[arg(short, long)]
         follow: bool,
 
-        /// Number of lines to show
-        #[arg(short, long, default_value = "100")]
-        tail: usize,
+        /// Number of lines to show (0 = all available) [default: 100, or new lines only with --follow]
+        #[arg(short, long)]
+        tail: Option<u32>,
+
+        /// Only show lines since a time (RFC 3339, unix seconds, or relative like 10m, 2h, 1d)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only show lines before a time (RFC 3339, unix seconds, or relative like 10m, 2h, 1d)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Show a timestamp on each line
+        #[arg(long)]
+        timestamps: bool,
     },
 
     /// Check API health
     Health {},
 
+    /// Stream lifecycle events for all instances
+    Events {},
+
+    /// Open an interactive session with an instance's in-game console
+    Console {
+        /// Instance ID (full UUID, short prefix or name)
+        id: String,
+
+        /// Run a console command and exit (may be repeated)
+        #[arg(short, long)]
+        command: Vec<String>,
+    },
+
+    /// Forward a local TCP port to an instance port through the API server
+    PortForward {
+        /// Instance ID (full UUID, short prefix or name)
+        id: String,
+
+        /// Instance port to forward (console, vnc)
+        #[arg(long, default_value = "console")]
+        port: String,
+
+        /// Local port to listen on [default: the instance's published port]
+        #[arg(short, long)]
+        local_port: Option<u16>,
+
+        /// Local address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        address: String,
+    },
+
+    /// Store an API token for the current profile
+    Login {
+        /// API token (prompted for if omitted)
+        #[arg(long)]
+        token: Option<String>,
+    },
+
+    /// Remove the stored API token for the current profile
+    Logout {},
+
     /// Stop a running instance
     Stop {
-        /// Instance ID (full UUID or short prefix)
+        /// Instance ID (full UUID, short prefix or name)
         id: String,
     },
 
     /// Start a stopped instance
     Start {
-        /// Instance ID (full UUID or short prefix)
+        /// Instance ID (full UUID, short prefix or name)
         id: String,
     },
 
     /// Restart a running instance
     Restart {
-        /// Instance ID (full UUID or short prefix)
+        /// Instance ID (full UUID, short prefix or name)
         id: String,
     },
 }
@@ -161,39 +373,131 @@ struct InstanceConfigArgs {
     /// CPU limit in cores (e.g., 0.5 = 50%, 2.0 = 2 cores)
     #[arg(long)]
     cpulimit: Option<f64>,
+
+    /// Memory limit in MB
+    #[arg(long)]
+    memory_mb: Option<u64>,
+
+    /// Docker image to run the instance in
+    #[arg(long)]
+    image: Option<String>,
+
+    /// Wine debug channels (WINEDEBUG), e.g. "-all" or "+relay"
+    #[arg(long)]
+    wine_debug: Option<String>,
+}
+
+/// How `openzt create` installs mods and waits, after merging flags with the config file
+#[cfg(feature = "cli")]
+struct CreateOptions {
+    mods_dir: Option<PathBuf>,
+    wait: bool,
+    wait_timeout: std::time::Duration,
 }
 
 #[cfg(feature = "cli")]
 async fn cmd_create(
     client: &openzt_instance_manager::client::InstanceClient,
-    dll_path: &PathBuf,
+    dll_path: Option<PathBuf>,
+    from_file: Option<PathBuf>,
     config_args: InstanceConfigArgs,
+    defaults: &openzt_instance_manager::client_config::CreateConfig,
+    options: CreateOptions,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
-    use openzt_instance_manager::instance::InstanceConfig;
-    use openzt_instance_manager::output::{print_create_result, print_error};
+    use openzt_instance_manager::client::read_mods_dir;
+    use openzt_instance_manager::instance_file::InstanceFile;
+    use openzt_instance_manager::output::{
+        exit_with_error, print_create_result, upload_progress_bar, upload_progress_callback, ErrorCode,
+    };
+
+    // Start from the definition file, if any; command line arguments take precedence,
+    // and the `[create]` defaults of the config file fill in what neither sets
+    let mut definition = match &from_file {
+        Some(path) => match InstanceFile::load(path) {
+            Ok(definition) => definition,
+            Err(e) => exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format),
+        },
+        None => InstanceFile::default(),
+    };
+    if let Some(cpulimit) = config_args.cpulimit {
+        definition.config.cpulimit = Some(cpulimit);
+    }
+    if let Some(memory_mb) = config_args.memory_mb {
+        definition.config.memory_mb = Some(memory_mb);
+    }
+    if let Some(image) = config_args.image {
+        definition.config.image = Some(image);
+    }
+    if let Some(wine_debug) = config_args.wine_debug {
+        definition.config.wine_debug_level = Some(wine_debug);
+    }
+    definition.config = definition.config.or(&defaults.instance_config());
+
+    let mod_files = match &options.mods_dir {
+        Some(dir) => match read_mods_dir(dir) {
+            Ok(mod_files) => mod_files,
+            Err(e) => exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format),
+        },
+        None => Vec::new(),
+    };
+
+    let Some(dll_path) = dll_path.or(definition.dll.take()) else {
+        exit_with_error(
+            "No DLL given: pass a DLL path or set `dll` in the instance file",
+            ErrorCode::Error,
+            output_format,
+        );
+    };
 
     // Check if DLL file exists
     if !dll_path.exists() {
-        print_error(&format!("DLL file not found: {}", dll_path.display()));
-        std::process::exit(1);
+        exit_with_error(
+            &format!("DLL file not found: {}", dll_path.display()),
+            ErrorCode::Error,
+            output_format,
+        );
     }
 
-    // Build instance config
-    let instance_config = if config_args.cpulimit.is_some() {
-        Some(InstanceConfig {
-            wine_debug_level: None,
-            cpulimit: config_args.cpulimit,
-        })
-    } else {
-        None
-    };
+    // Fail before uploading the DLL if the server has no room for the instance
+    if let Some(problem) = exhausted_capacity(client).await {
+        exit_with_error(&problem, ErrorCode::ServerError, output_format);
+    }
 
     // Call the API
-    let response = client
-        .create_instance(dll_path, instance_config)
-        .await
-        .map_err(|e| miette!(e))?;
+    let progress = upload_progress_bar(output_format);
+    let on_progress = upload_progress_callback(&progress, "Creating instance...");
+    let result = client
+        .create_instance_with_progress(&dll_path, definition.instance_config(), &mod_files, Some(on_progress))
+        .await;
+
+    let mut response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            // Another instance may have taken the last free slot in the meantime
+            let unavailable = e
+                .downcast_ref::<openzt_instance_manager::client::ApiError>()
+                .is_some_and(|e| e.status == reqwest::StatusCode::SERVICE_UNAVAILABLE);
+            let message = match exhausted_capacity(client).await {
+                Some(problem) if unavailable => problem,
+                _ => format!("Failed to create instance: {}", e),
+            };
+            progress.finish_and_clear();
+            exit_with_error(&message, ErrorCode::from_error(&e), output_format)
+        }
+    };
+
+    if options.wait {
+        progress.set_message("Waiting for the game to start...");
+        match client.wait_until_running(&response.instance_id, options.wait_timeout).await {
+            Ok(instance) => response.status = instance.status,
+            Err(e) => {
+                progress.finish_and_clear();
+                exit_with_error(&format!("{:#}", e), ErrorCode::from_error(&e), output_format)
+            }
+        }
+    }
+    progress.finish_and_clear();
 
     // Print result
     let output_json = output_format == openzt_instance_manager::output::OutputFormat::Json;
@@ -202,19 +506,38 @@ async fn cmd_create(
     Ok(())
 }
 
+/// Why the server can't take another instance, with a hint on making room
+///
+/// `None` if it can, or if its capacity couldn't be checked.
+#[cfg(feature = "cli")]
+async fn exhausted_capacity(client: &openzt_instance_manager::client::InstanceClient) -> Option<String> {
+    let capacity = client.get_capacity().await.ok()?;
+    capacity.exhausted().map(|problem| {
+        format!(
+            "{}, free some with `openzt delete --purge <id>` (trashed instances keep their ports)",
+            problem
+        )
+    })
+}
+
 #[cfg(feature = "cli")]
 async fn cmd_list(
     client: &openzt_instance_manager::client::InstanceClient,
+    wide: bool,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
-    use openzt_instance_manager::output::print_instance_list;
-
-    let instances = client
-        .list_instances()
-        .await
-        .map_err(|e| miette!(e))?;
+    use openzt_instance_manager::output::{exit_with_error, print_instance_list, ErrorCode};
+
+    let instances = match client.list_instances().await {
+        Ok(instances) => instances,
+        Err(e) => exit_with_error(
+            &format!("Failed to list instances: {}", e),
+            ErrorCode::from_error(&e),
+            output_format,
+        ),
+    };
 
-    print_instance_list(&instances, output_format);
+    print_instance_list(&instances, output_format, wide);
 
     Ok(())
 }
@@ -223,65 +546,159 @@ async fn cmd_list(
 async fn cmd_get(
     client: &openzt_instance_manager::client::InstanceClient,
     id: &str,
+    field: Option<&str>,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
-    use openzt_instance_manager::output::{print_error, print_instance, print_resolution_error};
+    use openzt_instance_manager::instance_diff::flatten_instance;
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_field_value, print_instance, ErrorCode,
+    };
 
     // Resolve ID (handles both short and full UUIDs)
     let resolved_id = match resolve_instance_id(client, id).await {
         Ok(resolved) => resolved,
+        Err(e) => exit_with_resolution_error(&e, output_format),
+    };
+
+    let instance = match client.get_instance(&resolved_id).await {
+        Ok(instance) => instance,
         Err(e) => {
-            print_resolution_error(&e);
-            std::process::exit(1);
+            exit_with_error(&format!("Failed to get instance: {}", e), ErrorCode::from_error(&e), output_format);
         }
     };
 
-    match client.get_instance(&resolved_id).await {
-        Ok(instance) => print_instance(&instance, output_format),
-        Err(e) => {
-            print_error(&format!("Failed to get instance: {}", e));
-            std::process::exit(1);
+    let Some(field) = field else {
+        print_instance(&instance, output_format);
+        return Ok(());
+    };
+
+    let mut fields = flatten_instance(&instance);
+    match fields.remove(field) {
+        Some(value) => print_field_value(&value, output_format),
+        None => {
+            let available: Vec<&str> = fields.keys().map(String::as_str).collect();
+            exit_with_error(
+                &format!("Field '{}' is not set or does not exist. Available fields: {}", field, available.join(", ")),
+                ErrorCode::Error,
+                output_format,
+            );
         }
     }
 
     Ok(())
 }
 
+#[cfg(feature = "cli")]
+async fn cmd_export(
+    client: &openzt_instance_manager::client::InstanceClient,
+    id: &str,
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use openzt_instance_manager::instance_file::InstanceFile;
+    use openzt_instance_manager::output::{exit_with_error, exit_with_resolution_error, ErrorCode, OutputFormat};
+
+    // Resolve ID (handles both short and full UUIDs)
+    let resolved_id = match resolve_instance_id(client, id).await {
+        Ok(resolved) => resolved,
+        Err(e) => exit_with_resolution_error(&e, output_format),
+    };
+
+    let instance = match client.get_instance(&resolved_id).await {
+        Ok(instance) => instance,
+        Err(e) => exit_with_error(&format!("Failed to get instance: {}", e), ErrorCode::from_error(&e), output_format),
+    };
+
+    let definition = InstanceFile::from_instance(&instance);
+    let rendered = match output_format {
+        OutputFormat::Json => serde_json::to_string_pretty(&definition)
+            .map(|json| json + "\n")
+            .map_err(anyhow::Error::from),
+        OutputFormat::Table => definition.to_toml(),
+    };
+    match rendered {
+        Ok(rendered) => print!("{}", rendered),
+        Err(e) => exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format),
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+async fn cmd_diff(
+    client: &openzt_instance_manager::client::InstanceClient,
+    left: &str,
+    right: &str,
+    all: bool,
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use openzt_instance_manager::instance_diff::diff_instances;
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_instance_diff, ErrorCode,
+    };
+
+    let mut instances = Vec::with_capacity(2);
+    for id in [left, right] {
+        // Resolve ID (handles both short and full UUIDs)
+        let resolved_id = match resolve_instance_id(client, id).await {
+            Ok(resolved) => resolved,
+            Err(e) => exit_with_resolution_error(&e, output_format),
+        };
+
+        match client.get_instance(&resolved_id).await {
+            Ok(instance) => instances.push(instance),
+            Err(e) => {
+                exit_with_error(&format!("Failed to get instance: {}", e), ErrorCode::from_error(&e), output_format)
+            }
+        }
+    }
+
+    let diffs = diff_instances(&instances[0], &instances[1], all);
+    print_instance_diff(&instances[0], &instances[1], &diffs, output_format);
+
+    Ok(())
+}
+
 #[cfg(feature = "cli")]
 async fn cmd_delete(
     client: &openzt_instance_manager::client::InstanceClient,
     id: &str,
     confirm: bool,
+    purge: bool,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
-    use openzt_instance_manager::output::{confirm_action, print_error, print_resolution_error, print_success};
+    use openzt_instance_manager::output::{
+        confirm_action, exit_with_error, exit_with_resolution_error, print_success, ErrorCode,
+    };
 
     // Resolve ID (handles both short and full UUIDs)
     let resolved_id = match resolve_instance_id(client, id).await {
         Ok(resolved) => resolved,
-        Err(e) => {
-            print_resolution_error(&e);
-            std::process::exit(1);
-        }
+        Err(e) => exit_with_resolution_error(&e, output_format),
     };
 
     // Confirm unless --confirm flag was provided
     if !confirm {
-        if !confirm_action("delete instance", &format!("ID: {}", &resolved_id[..8])) {
+        let action = if purge { "purge instance" } else { "delete instance" };
+        if !confirm_action(action, &format!("ID: {}", &resolved_id[..8])) {
             openzt_instance_manager::output::print_info("Delete cancelled");
             return Ok(());
         }
     }
 
-    match client.delete_instance(&resolved_id).await {
+    let result = if purge {
+        client.purge_instance(&resolved_id).await
+    } else {
+        client.delete_instance(&resolved_id).await
+    };
+    match result {
         Ok(()) => {
             if output_format != openzt_instance_manager::output::OutputFormat::Json {
-                print_success(&format!("Deleted instance: {}", &resolved_id[..8]));
+                let verb = if purge { "Purged" } else { "Deleted" };
+                print_success(&format!("{} instance: {}", verb, &resolved_id[..8]));
             }
         }
         Err(e) => {
-            print_error(&format!("Failed to delete instance: {}", e));
-            std::process::exit(1);
+            exit_with_error(&format!("Failed to delete instance: {}", e), ErrorCode::from_error(&e), output_format);
         }
     }
 
@@ -291,59 +708,105 @@ async fn cmd_delete(
 #[cfg(feature = "cli")]
 async fn cmd_logs(
     client: &openzt_instance_manager::client::InstanceClient,
-    id: &str,
+    ids: &[String],
+    all: bool,
     log_type: &str,
     follow: bool,
-    tail: usize,
+    options: openzt_instance_manager::client::LogOptions,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
     use futures_util::StreamExt;
-    use openzt_instance_manager::instance::LogsResponse;
-    use openzt_instance_manager::output::{print_error, print_info, print_logs, print_resolution_error};
+    use openzt_instance_manager::instance::{parse_log_time, LogsResponse};
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, merge_log_lines, print_info, print_log_line, print_logs,
+        print_prefixed_log_line, ErrorCode, OutputFormat,
+    };
 
     // Validate log type
     if !matches!(log_type, "docker" | "openzt" | "integration-tests") {
-        print_error(&format!(
-            "Invalid log type: '{}'. Valid types are: docker, openzt, integration-tests",
-            log_type
-        ));
-        std::process::exit(1);
+        exit_with_error(
+            &format!(
+                "Invalid log type: '{}'. Valid types are: docker, openzt, integration-tests",
+                log_type
+            ),
+            ErrorCode::Error,
+            output_format,
+        );
     }
 
-    // Resolve ID (handles both short and full UUIDs)
-    let resolved_id = match resolve_instance_id(client, id).await {
-        Ok(resolved) => resolved,
-        Err(e) => {
-            print_resolution_error(&e);
-            std::process::exit(1);
+    // Validate time bounds locally for a clearer error than the server's 400
+    for value in [&options.since, &options.until].into_iter().flatten() {
+        if let Err(e) = parse_log_time(value, chrono::Utc::now()) {
+            exit_with_error(&e, ErrorCode::Error, output_format);
+        }
+    }
+
+    // Resolve IDs (handles both short and full UUIDs)
+    let resolved_ids: Vec<String> = if all {
+        match client.list_instances().await {
+            Ok(instances) => instances.into_iter().map(|instance| instance.id).collect(),
+            Err(e) => exit_with_error(
+                &format!("Failed to list instances: {}", e),
+                ErrorCode::from_error(&e),
+                output_format,
+            ),
         }
+    } else {
+        let mut resolved_ids = Vec::with_capacity(ids.len());
+        for id in ids {
+            match resolve_instance_id(client, id).await {
+                Ok(resolved) => resolved_ids.push(resolved),
+                Err(e) => exit_with_resolution_error(&e, output_format),
+            }
+        }
+        resolved_ids
     };
 
+    if resolved_ids.is_empty() {
+        print_info("No instances found");
+        return Ok(());
+    }
+
+    // A single instance keeps the plain output; several are interleaved with a per-instance prefix
+    let aggregate = all || resolved_ids.len() > 1;
+
     if follow {
-        let mut stream = client.stream_logs(&resolved_id, Some(log_type)).await
-            .map_err(|e| miette!(e))?;
-
-        print_info(&format!(
-            "Streaming {} logs for instance {} (Ctrl+C to stop)...",
-            log_type,
-            &resolved_id[..8]
-        ));
+        let mut streams = Vec::with_capacity(resolved_ids.len());
+        for (index, resolved_id) in resolved_ids.iter().enumerate() {
+            match client.stream_logs(resolved_id, Some(log_type), &options).await {
+                Ok(stream) => streams.push(stream.map(move |result| (index, result))),
+                Err(e) => exit_with_error(
+                    &format!("Failed to stream logs for instance {}: {}", &resolved_id[..8], e),
+                    ErrorCode::from_error(&e),
+                    output_format,
+                ),
+            }
+        }
+
+        if aggregate {
+            print_info(&format!(
+                "Streaming {} logs for {} instances (Ctrl+C to stop)...",
+                log_type,
+                resolved_ids.len()
+            ));
+        } else {
+            print_info(&format!(
+                "Streaming {} logs for instance {} (Ctrl+C to stop)...",
+                log_type,
+                &resolved_ids[0][..8]
+            ));
+        }
         println!();
 
-        while let Some(result) = stream.next().await {
+        let mut merged = futures_util::stream::select_all(streams);
+        while let Some((index, result)) = merged.next().await {
             match result {
-                Ok(chunk) => {
-                    // Parse SSE format from the chunk
-                    // SSE format: "data: <line>\n\n" or ": keep-alive\n\n"
-                    for line in chunk.lines() {
-                        if let Some(log_line) = line.strip_prefix("data: ") {
-                            if !log_line.is_empty() {
-                                println!("{}", log_line);
-                            }
-                        }
-                        // Ignore ": keep-alive" and other comment lines
-                    }
+                Ok(line) if !aggregate => print_log_line(&line, options.timestamps),
+                Ok(line) if output_format == OutputFormat::Json => {
+                    let entry = serde_json::json!({ "instance_id": resolved_ids[index], "line": line });
+                    println!("{}", entry);
                 }
+                Ok(line) => print_prefixed_log_line(&resolved_ids[index], index, &line, options.timestamps),
                 Err(e) => {
                     eprintln!("Error: {}", e);
                 }
@@ -351,19 +814,37 @@ async fn cmd_logs(
         }
         Ok(())
     } else {
-        match client.get_logs(&resolved_id, Some(log_type), Some(tail as u32)).await {
-            Ok(logs) => {
-                let response = LogsResponse {
+        let mut responses = Vec::with_capacity(resolved_ids.len());
+        for resolved_id in resolved_ids {
+            match client.get_logs(&resolved_id, Some(log_type), &options).await {
+                Ok(logs) => responses.push(LogsResponse {
                     instance_id: resolved_id,
                     log_type: log_type.to_string(),
                     logs,
-                };
-                let output_json = output_format == openzt_instance_manager::output::OutputFormat::Json;
-                print_logs(&response, output_json);
+                }),
+                Err(e) => exit_with_error(
+                    &format!("Failed to get logs for instance {}: {}", &resolved_id[..8], e),
+                    ErrorCode::from_error(&e),
+                    output_format,
+                ),
             }
-            Err(e) => {
-                print_error(&format!("Failed to get logs: {}", e));
-                std::process::exit(1);
+        }
+
+        let output_json = output_format == OutputFormat::Json;
+        if !aggregate {
+            print_logs(&responses[0], output_json, options.timestamps);
+        } else if output_json {
+            if let Ok(json) = serde_json::to_string_pretty(&responses) {
+                println!("{}", json);
+            }
+        } else {
+            let sources: Vec<&str> = responses.iter().map(|response| response.logs.as_str()).collect();
+            let lines = merge_log_lines(&sources);
+            if lines.is_empty() {
+                print_info("(no logs available)");
+            }
+            for (index, line) in lines {
+                print_prefixed_log_line(&responses[index].instance_id, index, line, options.timestamps);
             }
         }
         Ok(())
@@ -375,9 +856,12 @@ async fn cmd_health(
     client: &openzt_instance_manager::client::InstanceClient,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
-    use openzt_instance_manager::output::print_health;
+    use openzt_instance_manager::output::{exit_with_error, print_health, ErrorCode};
 
-    let healthy = client.health().await.map_err(|e| miette!(e))?;
+    let healthy = match client.health().await {
+        Ok(healthy) => healthy,
+        Err(e) => exit_with_error(&format!("{:#}", e), ErrorCode::ServerError, output_format),
+    };
 
     let output_json = output_format == openzt_instance_manager::output::OutputFormat::Json;
     print_health(healthy, output_json);
@@ -390,6 +874,352 @@ async fn cmd_health(
     Ok(())
 }
 
+#[cfg(feature = "cli")]
+async fn cmd_events(
+    client: &openzt_instance_manager::client::InstanceClient,
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use futures_util::StreamExt;
+    use openzt_instance_manager::output::{exit_with_error, print_event, print_info, print_warning, ErrorCode};
+
+    let mut stream = match client.stream_events().await {
+        Ok(stream) => stream,
+        Err(e) => exit_with_error(
+            &format!("Failed to stream events: {}", e),
+            ErrorCode::from_error(&e),
+            output_format,
+        ),
+    };
+
+    if output_format != openzt_instance_manager::output::OutputFormat::Json {
+        print_info("Streaming instance events (Ctrl+C to stop)...");
+    }
+
+    while let Some(result) = stream.next().await {
+        match result {
+            Ok(event) => print_event(&event, output_format),
+            Err(e) => print_warning(&format!("{:#}", e)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+async fn cmd_console(
+    client: &openzt_instance_manager::client::InstanceClient,
+    id: &str,
+    commands: &[String],
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use openzt_console::protocol::ConsoleError;
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_error, print_info, ErrorCode, OutputFormat,
+    };
+    use rustyline::error::ReadlineError;
+
+    // Resolve ID (handles both short and full UUIDs)
+    let resolved_id = match resolve_instance_id(client, id).await {
+        Ok(resolved) => resolved,
+        Err(e) => exit_with_resolution_error(&e, output_format),
+    };
+
+    let mut session = match client.open_console(&resolved_id).await {
+        Ok(session) => session,
+        Err(e) => exit_with_error(
+            &format!("Failed to connect to console: {:#}", e),
+            ErrorCode::from_error(&e),
+            output_format,
+        ),
+    };
+
+    // One-shot mode: run each --command in order and print its output
+    if !commands.is_empty() {
+        let mut results = Vec::with_capacity(commands.len());
+        for command in commands {
+            let output = match session.execute(command).await {
+                Ok(output) => output,
+                Err(e) => exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format),
+            };
+            if output_format == OutputFormat::Json {
+                results.push(serde_json::json!({ "command": command, "output": output }));
+            } else {
+                print!("{}", output);
+                if !output.is_empty() && !output.ends_with('\n') {
+                    println!();
+                }
+            }
+        }
+        if output_format == OutputFormat::Json
+            && let Ok(json) = serde_json::to_string_pretty(&results)
+        {
+            println!("{}", json);
+        }
+        session.close().await;
+        return Ok(());
+    }
+
+    let mut editor = match rustyline::DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => exit_with_error(&format!("Failed to start console: {}", e), ErrorCode::Error, output_format),
+    };
+    let history_file = openzt_instance_manager::client_config::ClientConfig::cache_dir()
+        .ok()
+        .map(|dir| dir.join("console_history"));
+    if let Some(history_file) = &history_file {
+        let _ = editor.load_history(history_file);
+    }
+
+    print_info(&format!(
+        "Connected to console of instance {} (type 'quit' or Ctrl+D to exit)",
+        &resolved_id[..8]
+    ));
+
+    let prompt = format!("{}> ", &resolved_id[..8]);
+    loop {
+        let line = match tokio::task::block_in_place(|| editor.readline(&prompt)) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                print_error(&format!("Failed to read input: {}", e));
+                break;
+            }
+        };
+
+        let command = line.trim();
+        if command.is_empty() {
+            continue;
+        }
+        if matches!(command, "quit" | "exit") {
+            break;
+        }
+        let _ = editor.add_history_entry(command);
+
+        match session.execute(command).await {
+            Ok(output) => {
+                print!("{}", output);
+                if !output.is_empty() && !output.ends_with('\n') {
+                    println!();
+                }
+            }
+            // The command failed but the console is still there
+            Err(e) if e.downcast_ref::<ConsoleError>().is_some() => print_error(&format!("{:#}", e)),
+            Err(e) => {
+                print_error(&format!("{:#}", e));
+                break;
+            }
+        }
+    }
+
+    if let Some(history_file) = &history_file {
+        if let Some(parent) = history_file.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = editor.save_history(history_file);
+    }
+    session.close().await;
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+async fn cmd_port_forward(
+    client: &openzt_instance_manager::client::InstanceClient,
+    id: &str,
+    port: &str,
+    local_port: Option<u16>,
+    address: &str,
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_info, print_success, print_warning, ErrorCode,
+    };
+
+    let remote_port = |instance: &openzt_instance_manager::instance::InstanceDetails| match port {
+        "console" => Some(instance.console_port),
+        "vnc" => Some(instance.vnc_port),
+        _ => None,
+    };
+
+    // Resolve ID (handles both short and full UUIDs)
+    let resolved_id = match resolve_instance_id(client, id).await {
+        Ok(resolved) => resolved,
+        Err(e) => exit_with_resolution_error(&e, output_format),
+    };
+
+    let instance = match client.get_instance(&resolved_id).await {
+        Ok(instance) => instance,
+        Err(e) => exit_with_error(&format!("Failed to get instance: {}", e), ErrorCode::from_error(&e), output_format),
+    };
+
+    let Some(published_port) = remote_port(&instance) else {
+        exit_with_error(
+            &format!("Invalid port: '{}'. Valid ports are: console, vnc", port),
+            ErrorCode::Error,
+            output_format,
+        );
+    };
+
+    let listen_addr = format!("{}:{}", address, local_port.unwrap_or(published_port));
+    let listener = match tokio::net::TcpListener::bind(&listen_addr).await {
+        Ok(listener) => listener,
+        Err(e) => exit_with_error(&format!("Failed to listen on {}: {}", listen_addr, e), ErrorCode::Error, output_format),
+    };
+
+    print_success(&format!(
+        "Forwarding {} -> instance {} {} port",
+        listener.local_addr().map(|a| a.to_string()).unwrap_or(listen_addr),
+        &resolved_id[..8],
+        port
+    ));
+    print_info("Press Ctrl+C to stop");
+
+    loop {
+        let (tcp, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                print_warning(&format!("Failed to accept connection: {}", e));
+                continue;
+            }
+        };
+
+        let client = client.clone();
+        let resolved_id = resolved_id.clone();
+        let port = port.to_string();
+        tokio::spawn(async move {
+            match client.open_tunnel(&resolved_id, &port).await {
+                Ok(socket) => {
+                    print_info(&format!("Connection from {}", peer));
+                    forward_connection(tcp, socket).await;
+                }
+                Err(e) => print_warning(&format!("{:#}", e)),
+            }
+        });
+    }
+}
+
+/// Copy data between a local TCP connection and a tunnel WebSocket until either side closes
+#[cfg(feature = "cli")]
+async fn forward_connection(mut tcp: tokio::net::TcpStream, mut socket: openzt_instance_manager::client::TunnelStream) {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        tokio::select! {
+            read = tcp.read(&mut buf) => match read {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if socket.send(Message::Binary(buf[..n].to_vec().into())).await.is_err() {
+                        break;
+                    }
+                }
+            },
+            message = socket.next() => match message {
+                Some(Ok(Message::Binary(data))) => {
+                    if tcp.write_all(&data).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = socket.close(None).await;
+}
+
+#[cfg(feature = "cli")]
+async fn cmd_login(
+    client: openzt_instance_manager::client::InstanceClient,
+    api_url: &str,
+    profile: &str,
+    token: Option<String>,
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use openzt_instance_manager::client::ApiError;
+    use openzt_instance_manager::client_config::ClientConfig;
+    use openzt_instance_manager::output::{exit_with_error, print_success, print_warning, ErrorCode, OutputFormat};
+
+    let token = match token {
+        Some(token) => token,
+        None => {
+            let term = console::Term::stderr();
+            let _ = term.write_str("API token: ");
+            match term.read_secure_line() {
+                Ok(token) => token,
+                Err(e) => exit_with_error(&format!("Failed to read token: {}", e), ErrorCode::Error, output_format),
+            }
+        }
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        exit_with_error("No token provided", ErrorCode::Error, output_format);
+    }
+
+    // Check the token against the server before storing it
+    let client = match client.with_token(token) {
+        Ok(client) => client,
+        Err(e) => exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format),
+    };
+    if let Err(e) = client.list_instances().await {
+        let rejected = e.chain().any(|cause| {
+            cause.downcast_ref::<ApiError>().is_some_and(|api| {
+                matches!(api.status.as_u16(), 401 | 403)
+            })
+        });
+        if rejected {
+            exit_with_error(&format!("Token rejected by {}: {}", api_url, e), ErrorCode::Error, output_format);
+        }
+        print_warning(&format!("Could not verify token: {}", e));
+    }
+
+    let mut config = match ClientConfig::load_for_update() {
+        Ok(config) => config,
+        Err(e) => exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format),
+    };
+    config.set_token(profile, api_url, token);
+    if let Err(e) = config.save() {
+        exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format);
+    }
+
+    if output_format == OutputFormat::Json {
+        println!("{}", serde_json::json!({ "profile": profile, "api_url": api_url, "logged_in": true }));
+    } else {
+        print_success(&format!("Logged in to {} (profile: {})", api_url, profile));
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn cmd_logout(profile: &str, output_format: openzt_instance_manager::output::OutputFormat) -> Result<()> {
+    use openzt_instance_manager::client_config::ClientConfig;
+    use openzt_instance_manager::output::{exit_with_error, print_info, print_success, ErrorCode, OutputFormat};
+
+    let mut config = match ClientConfig::load_for_update() {
+        Ok(config) => config,
+        Err(e) => exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format),
+    };
+
+    let removed = config.remove_token(profile);
+    if removed && let Err(e) = config.save() {
+        exit_with_error(&format!("{:#}", e), ErrorCode::Error, output_format);
+    }
+
+    if output_format == OutputFormat::Json {
+        println!("{}", serde_json::json!({ "profile": profile, "logged_out": removed }));
+    } else if removed {
+        print_success(&format!("Logged out (profile: {})", profile));
+    } else {
+        print_info(&format!("No stored token for profile '{}'", profile));
+    }
+
+    Ok(())
+}
+
 // Stub for when CLI feature is not enabled
 #[cfg(not(feature = "cli"))]
 fn main() {
@@ -404,15 +1234,14 @@ async fn cmd_stop(
     id: &str,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
-    use openzt_instance_manager::output::{print_error, print_resolution_error, print_success};
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_success, ErrorCode,
+    };
 
     // Resolve ID (handles both short and full UUIDs)
     let resolved_id = match resolve_instance_id(client, id).await {
         Ok(resolved) => resolved,
-        Err(e) => {
-            print_resolution_error(&e);
-            std::process::exit(1);
-        }
+        Err(e) => exit_with_resolution_error(&e, output_format),
     };
 
     match client.stop_instance(&resolved_id).await {
@@ -424,8 +1253,7 @@ async fn cmd_stop(
             }
         }
         Err(e) => {
-            print_error(&format!("Failed to stop instance: {}", e));
-            std::process::exit(1);
+            exit_with_error(&format!("Failed to stop instance: {}", e), ErrorCode::from_error(&e), output_format);
         }
     }
 
@@ -438,15 +1266,14 @@ async fn cmd_start(
     id: &str,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
-    use openzt_instance_manager::output::{print_error, print_resolution_error, print_success};
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_success, ErrorCode,
+    };
 
     // Resolve ID (handles both short and full UUIDs)
     let resolved_id = match resolve_instance_id(client, id).await {
         Ok(resolved) => resolved,
-        Err(e) => {
-            print_resolution_error(&e);
-            std::process::exit(1);
-        }
+        Err(e) => exit_with_resolution_error(&e, output_format),
     };
 
     match client.start_instance(&resolved_id).await {
@@ -458,8 +1285,7 @@ async fn cmd_start(
             }
         }
         Err(e) => {
-            print_error(&format!("Failed to start instance: {}", e));
-            std::process::exit(1);
+            exit_with_error(&format!("Failed to start instance: {}", e), ErrorCode::from_error(&e), output_format);
         }
     }
 
@@ -472,15 +1298,14 @@ async fn cmd_restart(
     id: &str,
     output_format: openzt_instance_manager::output::OutputFormat,
 ) -> Result<()> {
-    use openzt_instance_manager::output::{print_error, print_resolution_error, print_success};
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_success, ErrorCode,
+    };
 
     // Resolve ID (handles both short and full UUIDs)
     let resolved_id = match resolve_instance_id(client, id).await {
         Ok(resolved) => resolved,
-        Err(e) => {
-            print_resolution_error(&e);
-            std::process::exit(1);
-        }
+        Err(e) => exit_with_resolution_error(&e, output_format),
     };
 
     match client.restart_instance(&resolved_id).await {
@@ -492,8 +1317,123 @@ async fn cmd_restart(
             }
         }
         Err(e) => {
-            print_error(&format!("Failed to restart instance: {}", e));
-            std::process::exit(1);
+            exit_with_error(&format!("Failed to restart instance: {}", e), ErrorCode::from_error(&e), output_format);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+async fn cmd_restore(
+    client: &openzt_instance_manager::client::InstanceClient,
+    id: &str,
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_success, ErrorCode,
+    };
+
+    // Resolve ID (handles both short and full UUIDs)
+    let resolved_id = match resolve_instance_id(client, id).await {
+        Ok(resolved) => resolved,
+        Err(e) => exit_with_resolution_error(&e, output_format),
+    };
+
+    match client.restore_instance(&resolved_id).await {
+        Ok(response) => {
+            if output_format != openzt_instance_manager::output::OutputFormat::Json {
+                print_success(&format!("Restored instance: {}", &response.id[..8]));
+            } else {
+                println!("{}", serde_json::to_string_pretty(&response).unwrap());
+            }
+        }
+        Err(e) => {
+            exit_with_error(&format!("Failed to restore instance: {}", e), ErrorCode::from_error(&e), output_format);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+async fn cmd_rename(
+    client: &openzt_instance_manager::client::InstanceClient,
+    id: &str,
+    name: String,
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use openzt_instance_manager::instance::UpdateInstanceRequest;
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_instance, print_success, ErrorCode, OutputFormat,
+    };
+
+    // Resolve ID (handles short and full UUIDs and names)
+    let resolved_id = match resolve_instance_id(client, id).await {
+        Ok(resolved) => resolved,
+        Err(e) => exit_with_resolution_error(&e, output_format),
+    };
+
+    let update = UpdateInstanceRequest { name: Some(name), ..Default::default() };
+    match client.update_instance(&resolved_id, &update).await {
+        Ok(instance) if output_format == OutputFormat::Json => print_instance(&instance, output_format),
+        Ok(instance) => match &instance.name {
+            Some(name) => print_success(&format!("Renamed instance {} to {}", &instance.id[..8], name)),
+            None => print_success(&format!("Removed the name of instance {}", &instance.id[..8])),
+        },
+        Err(e) => {
+            exit_with_error(&format!("Failed to rename instance: {}", e), ErrorCode::from_error(&e), output_format);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+async fn cmd_label(
+    client: &openzt_instance_manager::client::InstanceClient,
+    id: &str,
+    labels: &[String],
+    remove: Vec<String>,
+    output_format: openzt_instance_manager::output::OutputFormat,
+) -> Result<()> {
+    use openzt_instance_manager::instance::UpdateInstanceRequest;
+    use openzt_instance_manager::output::{
+        exit_with_error, exit_with_resolution_error, print_info, print_instance, print_success, ErrorCode,
+        OutputFormat,
+    };
+
+    let mut update = UpdateInstanceRequest { remove_labels: remove, ..Default::default() };
+    for label in labels {
+        let Some((key, value)) = label.split_once('=') else {
+            exit_with_error(
+                &format!("Invalid label '{}': expected key=value", label),
+                ErrorCode::Error,
+                output_format,
+            );
+        };
+        update.labels.insert(key.to_string(), value.to_string());
+    }
+
+    // Resolve ID (handles short and full UUIDs and names)
+    let resolved_id = match resolve_instance_id(client, id).await {
+        Ok(resolved) => resolved,
+        Err(e) => exit_with_resolution_error(&e, output_format),
+    };
+
+    match client.update_instance(&resolved_id, &update).await {
+        Ok(instance) if output_format == OutputFormat::Json => print_instance(&instance, output_format),
+        Ok(instance) => {
+            print_success(&format!("Updated the labels of instance {}", &instance.id[..8]));
+            if instance.labels.is_empty() {
+                print_info("The instance has no labels");
+            }
+            for (key, value) in &instance.labels {
+                println!("  {}={}", key, value);
+            }
+        }
+        Err(e) => {
+            exit_with_error(&format!("Failed to update labels: {}", e), ErrorCode::from_error(&e), output_format);
         }
     }
 
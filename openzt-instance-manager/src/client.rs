@@ -3,21 +3,97 @@
 //! This module provides a convenient async client for interacting with
 //! the instance manager API endpoints.
 
-use crate::instance::{CreateInstanceResponse, InstanceConfig, InstanceDetails, LogsResponse, InstanceStatusResponse};
+use crate::instance::{
+    CapacityResponse, CreateInstanceResponse, InstanceConfig, InstanceDetails, InstanceEvent, InstanceStatusResponse, LogsResponse,
+    UpdateInstanceRequest,
+};
+use crate::client_transport::{connect_via_proxy, NetworkSettings, Transport};
+use crate::instance_cache::InstanceCache;
+use crate::test_run::ModFile;
 use anyhow::{anyhow, Context, Result};
 use base64::Engine;
 use futures_util::stream::Stream;
-use futures_util::StreamExt;
+use futures_util::{SinkExt, StreamExt};
+use openzt_console::protocol::{self, FrameDecoder, Request, Response};
 use reqwest::{Client, StatusCode};
 use serde::de::DeserializeOwned;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Error returned when the API server responds with a non-success status
+#[derive(Debug)]
+pub struct ApiError {
+    /// HTTP status returned by the server
+    pub status: StatusCode,
+    /// Error message extracted from the response body
+    pub message: String,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API error ({}): {}", self.status.as_u16(), self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Line selection options for log requests
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Trailing lines to return (0 = all available, `None` = server default)
+    pub tail: Option<u32>,
+    /// Only show lines since this time (RFC 3339, unix seconds, or e.g. "10m")
+    pub since: Option<String>,
+    /// Only show lines before this time (RFC 3339, unix seconds, or e.g. "10m")
+    pub until: Option<String>,
+    /// Ask the server to prefix Docker log lines with timestamps
+    pub timestamps: bool,
+}
+
+impl LogOptions {
+    /// Add these options to a request as query parameters
+    fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(tail) = self.tail {
+            request = request.query(&[("tail", tail)]);
+        }
+        if let Some(since) = &self.since {
+            request = request.query(&[("since", since)]);
+        }
+        if let Some(until) = &self.until {
+            request = request.query(&[("until", until)]);
+        }
+        if self.timestamps {
+            request = request.query(&[("timestamps", true)]);
+        }
+        request
+    }
+}
+
+/// Upload progress callback, called with `(bytes sent, total bytes)`
+pub type UploadProgress = Box<dyn Fn(u64, u64) + Send + Sync>;
+
+/// Size of the chunks request bodies are streamed in when reporting upload progress
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How often `wait_until_running` checks an instance's status
+const WAIT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// WebSocket connection returned by `InstanceClient::open_tunnel`
+pub type TunnelStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
 
 /// API client for the OpenZT Instance Manager
 #[derive(Clone)]
 pub struct InstanceClient {
     base_url: String,
     http_client: Client,
+    cache: Arc<InstanceCache>,
+    /// Authorization header sent with requests, kept for WebSocket connections
+    auth_header: Option<reqwest::header::HeaderValue>,
+    /// Proxy and CA certificates, kept for WebSocket connections
+    transport: Transport,
 }
 
 impl InstanceClient {
@@ -26,7 +102,54 @@ impl InstanceClient {
         Self {
             base_url: base_url.into(),
             http_client: Client::new(),
+            cache: Arc::new(InstanceCache::new()),
+            auth_header: None,
+            transport: Transport::default(),
+        }
+    }
+
+    /// Send `token` as a bearer token with every request
+    pub fn with_token(mut self, token: &str) -> Result<Self> {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+            .context("API token contains invalid characters")?;
+        value.set_sensitive(true);
+        self.auth_header = Some(value);
+
+        self.http_client = self.build_http_client()?;
+        Ok(self)
+    }
+
+    /// Reach the server through a proxy and trust extra CA certificates
+    ///
+    /// Proxies are also read from the standard environment variables, see
+    /// [`crate::client_transport`].
+    pub fn with_network(mut self, settings: &NetworkSettings) -> Result<Self> {
+        self.transport = Transport::new(&self.base_url, settings)?;
+        self.http_client = self.build_http_client()?;
+        Ok(self)
+    }
+
+    fn build_http_client(&self) -> Result<Client> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(auth) = &self.auth_header {
+            headers.insert(reqwest::header::AUTHORIZATION, auth.clone());
         }
+
+        self.transport
+            .configure(Client::builder().default_headers(headers))?
+            .build()
+            .context("Failed to build HTTP client")
+    }
+
+    /// The API base URL this client talks to
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Persist the instance list cache in `dir` so it can be reused by later invocations
+    pub fn with_disk_cache(mut self, dir: PathBuf, ttl: Duration) -> Self {
+        self.cache = Arc::new(InstanceCache::with_disk(dir, &self.base_url, ttl));
+        self
     }
 
     /// Get the full URL for an API endpoint
@@ -46,11 +169,35 @@ impl InstanceClient {
         Ok(response.status().is_success())
     }
 
+    /// Get the instance count and free ports
+    pub async fn get_capacity(&self) -> Result<CapacityResponse> {
+        let response = self
+            .http_client
+            .get(self.url("/api/capacity"))
+            .send()
+            .await
+            .context("Failed to get capacity")?;
+
+        self.handle_response(response).await
+    }
+
     /// Create a new instance with the given DLL file
     pub async fn create_instance(
         &self,
         dll_path: &Path,
         config: Option<InstanceConfig>,
+    ) -> Result<CreateInstanceResponse> {
+        self.create_instance_with_progress(dll_path, config, &[], None).await
+    }
+
+    /// Create a new instance with `mod_files` installed, reporting upload progress as
+    /// `(bytes sent, total bytes)`
+    pub async fn create_instance_with_progress(
+        &self,
+        dll_path: &Path,
+        config: Option<InstanceConfig>,
+        mod_files: &[ModFile],
+        on_progress: Option<UploadProgress>,
     ) -> Result<CreateInstanceResponse> {
         // Read and encode the DLL file
         let dll_bytes = std::fs::read(dll_path)
@@ -58,19 +205,38 @@ impl InstanceClient {
 
         let dll_base64 = base64::prelude::BASE64_STANDARD.encode(&dll_bytes);
 
-        let request = serde_json::json!({
+        let mut request = serde_json::json!({
             "openzt_dll": dll_base64,
             "config": config,
         });
+        if !mod_files.is_empty() {
+            request["mod_files"] = serde_json::to_value(mod_files)?;
+        }
+        let body = serde_json::to_vec(&request).context("Failed to encode create instance request")?;
+        let total = body.len() as u64;
+
+        // Stream the body in chunks so progress can be reported as it is sent
+        let chunks: Vec<Vec<u8>> = body.chunks(UPLOAD_CHUNK_SIZE).map(<[u8]>::to_vec).collect();
+        let mut sent = 0u64;
+        let stream = futures_util::stream::iter(chunks).map(move |chunk| {
+            sent += chunk.len() as u64;
+            if let Some(on_progress) = &on_progress {
+                on_progress(sent, total);
+            }
+            Ok::<_, std::io::Error>(chunk)
+        });
 
         let response = self
             .http_client
             .post(self.url("/api/instances"))
-            .json(&request)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::CONTENT_LENGTH, total)
+            .body(reqwest::Body::wrap_stream(stream))
             .send()
             .await
             .context("Failed to send create instance request")?;
 
+        self.cache.invalidate();
         self.handle_response(response).await
     }
 
@@ -83,7 +249,29 @@ impl InstanceClient {
             .await
             .context("Failed to list instances")?;
 
-        self.handle_response(response).await
+        let instances: Vec<InstanceDetails> = self.handle_response(response).await?;
+        self.cache.store(&self.base_url, &instances);
+        Ok(instances)
+    }
+
+    /// List all instances, reusing a previously fetched list when available
+    ///
+    /// Intended for ID resolution, where a slightly stale list is acceptable.
+    pub async fn list_instances_cached(&self) -> Result<Vec<InstanceDetails>> {
+        match self.cache.get(&self.base_url) {
+            Some(instances) => Ok(instances),
+            None => self.list_instances().await,
+        }
+    }
+
+    /// Whether cached listings may have been fetched by an earlier invocation
+    pub fn has_disk_cache(&self) -> bool {
+        self.cache.is_disk_backed()
+    }
+
+    /// Discard any cached instance list
+    pub fn invalidate_cache(&self) {
+        self.cache.invalidate();
     }
 
     /// Get details for a specific instance
@@ -98,7 +286,51 @@ impl InstanceClient {
         self.handle_response(response).await
     }
 
-    /// Delete an instance
+    /// Poll an instance until its game is running
+    ///
+    /// Fails if the instance ends up in a status it won't leave by itself, e.g. `error`,
+    /// or is still not running after `timeout`.
+    pub async fn wait_until_running(&self, id: &str, timeout: Duration) -> Result<InstanceDetails> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let instance = self.get_instance(id).await?;
+            match instance.status_code.as_str() {
+                // Degraded is running, with a sidecar down
+                "running" | "degraded" => return Ok(instance),
+                "creating" | "starting" => {}
+                _ => {
+                    let detail = instance.status_message.as_deref().unwrap_or(&instance.status);
+                    return Err(anyhow!("Instance {} is {}: {}", id, instance.status_code, detail));
+                }
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow!(
+                    "Instance {} still {} after {}s",
+                    id,
+                    instance.status_code,
+                    timeout.as_secs()
+                ));
+            }
+            tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Rename an instance or change its labels
+    pub async fn update_instance(&self, id: &str, update: &UpdateInstanceRequest) -> Result<InstanceDetails> {
+        let response = self
+            .http_client
+            .patch(self.url(&format!("/api/instances/{}", id)))
+            .json(update)
+            .send()
+            .await
+            .with_context(|| format!("Failed to update instance {}", id))?;
+
+        // Cached listings would still resolve the old name
+        self.cache.invalidate();
+        self.handle_response(response).await
+    }
+
+    /// Delete an instance, moving it to the trash if the server keeps one
     pub async fn delete_instance(&self, id: &str) -> Result<()> {
         let response = self
             .http_client
@@ -107,17 +339,50 @@ impl InstanceClient {
             .await
             .with_context(|| format!("Failed to delete instance {}", id))?;
 
+        self.cache.invalidate();
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            status => {
+                let message = self.extract_error(response).await;
+                Err(ApiError { status, message }.into())
+            }
+        }
+    }
+
+    /// Delete an instance for good, whether or not it is in the trash
+    pub async fn purge_instance(&self, id: &str) -> Result<()> {
+        let response = self
+            .http_client
+            .post(self.url(&format!("/api/instances/{}/purge", id)))
+            .send()
+            .await
+            .with_context(|| format!("Failed to purge instance {}", id))?;
+
+        self.cache.invalidate();
         match response.status() {
             StatusCode::NO_CONTENT => Ok(()),
             status => {
-                let error = self.extract_error(response).await;
-                Err(anyhow!("Failed to delete instance: {} - {}", status, error))
+                let message = self.extract_error(response).await;
+                Err(ApiError { status, message }.into())
             }
         }
     }
 
+    /// Take an instance out of the trash
+    pub async fn restore_instance(&self, id: &str) -> Result<InstanceStatusResponse> {
+        let response = self
+            .http_client
+            .post(self.url(&format!("/api/instances/{}/restore", id)))
+            .send()
+            .await
+            .with_context(|| format!("Failed to restore instance {}", id))?;
+
+        self.cache.invalidate();
+        self.handle_response(response).await
+    }
+
     /// Get logs for an instance
-    pub async fn get_logs(&self, id: &str, log_type: Option<&str>, tail: Option<u32>) -> Result<String> {
+    pub async fn get_logs(&self, id: &str, log_type: Option<&str>, options: &LogOptions) -> Result<String> {
         let mut request = self
             .http_client
             .get(self.url(&format!("/api/instances/{}/logs", id)));
@@ -125,10 +390,7 @@ impl InstanceClient {
         // Default to "openzt" for backward compatibility with existing client code
         let log_type = log_type.unwrap_or("openzt");
         request = request.query(&[("type", log_type)]);
-
-        if let Some(t) = tail {
-            request = request.query(&[("tail", t)]);
-        }
+        request = options.apply(request);
 
         let response = request
             .send()
@@ -139,11 +401,12 @@ impl InstanceClient {
         Ok(logs_response.logs)
     }
 
-    /// Stream logs for an instance (returns a stream of log lines)
+    /// Stream logs for an instance (returns a stream of log lines, one per SSE event)
     pub async fn stream_logs(
         &self,
         id: &str,
         log_type: Option<&str>,
+        options: &LogOptions,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
         let mut request = self
             .http_client
@@ -152,6 +415,7 @@ impl InstanceClient {
         // Default to "openzt" for backward compatibility with existing client code
         let log_type = log_type.unwrap_or("openzt");
         request = request.query(&[("type", log_type)]);
+        request = options.apply(request);
 
         let response = request
             .send()
@@ -160,21 +424,130 @@ impl InstanceClient {
 
         if response.status() != StatusCode::OK {
             let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Failed to stream logs ({}): {}", status, body));
+            let message = self.extract_error(response).await;
+            return Err(anyhow::Error::new(ApiError { status, message }).context("Failed to stream logs"));
         }
 
-        // Stream the response bytes and convert to SSE events
-        let byte_stream = response.bytes_stream();
-        let stream = byte_stream.map(|result| {
-            result
-                .map_err(|e| anyhow!("Stream error: {}", e))
-                .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
-        });
+        // SSE events may be split across chunks, so buffer until a full line arrives
+        let mut byte_stream = response.bytes_stream();
+        let stream = async_stream::stream! {
+            let mut buffer = String::new();
+            while let Some(chunk) = byte_stream.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        buffer.push_str(&String::from_utf8_lossy(&bytes));
+                        for line in drain_sse_data(&mut buffer) {
+                            if !line.is_empty() {
+                                yield Ok(line);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(anyhow!("Stream error: {}", e));
+                        break;
+                    }
+                }
+            }
+        };
 
         Ok(Box::pin(stream))
     }
 
+    /// Stream lifecycle events for all instances
+    pub async fn stream_events(&self) -> Result<Pin<Box<dyn Stream<Item = Result<InstanceEvent>> + Send>>> {
+        let response = self
+            .http_client
+            .get(self.url("/api/events"))
+            .send()
+            .await
+            .context("Failed to connect to event stream")?;
+
+        if response.status() != StatusCode::OK {
+            let status = response.status();
+            let message = self.extract_error(response).await;
+            return Err(anyhow::Error::new(ApiError { status, message }).context("Failed to stream events"));
+        }
+
+        // SSE events may be split across chunks, so buffer until a full line arrives
+        let mut byte_stream = response.bytes_stream();
+        let stream = async_stream::stream! {
+            let mut buffer = String::new();
+            while let Some(chunk) = byte_stream.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        buffer.push_str(&String::from_utf8_lossy(&bytes));
+                        for data in drain_sse_data(&mut buffer) {
+                            yield serde_json::from_str::<InstanceEvent>(&data)
+                                .with_context(|| format!("Invalid event: {}", data));
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(anyhow!("Stream error: {}", e));
+                        break;
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Open a WebSocket tunnel to an instance port ("console" or "vnc")
+    ///
+    /// Binary messages on the returned socket carry the raw TCP stream.
+    pub async fn open_tunnel(&self, id: &str, port: &str) -> Result<TunnelStream> {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let url = self.url(&format!("/api/instances/{}/tunnel?port={}", id, port));
+        let url = match url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some((_, rest)) => format!("ws://{}", rest),
+            None => url,
+        };
+
+        let mut request = url.into_client_request().context("Invalid tunnel URL")?;
+        if let Some(auth) = &self.auth_header {
+            request.headers_mut().insert(reqwest::header::AUTHORIZATION, auth.clone());
+        }
+
+        let connector = self.transport.tls_connector.clone().map(tokio_tungstenite::Connector::NativeTls);
+        let result = match &self.transport.proxy {
+            Some(proxy) => {
+                let host = request.uri().host().context("Tunnel URL has no host")?;
+                let port = request
+                    .uri()
+                    .port_u16()
+                    .unwrap_or(if request.uri().scheme_str() == Some("wss") { 443 } else { 80 });
+                let stream = connect_via_proxy(proxy, host, port).await.context("Failed to open tunnel")?;
+                tokio_tungstenite::client_async_tls_with_config(request, stream, None, connector).await
+            }
+            None => tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector).await,
+        };
+
+        match result {
+            Ok((socket, _)) => Ok(socket),
+            Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+                let status = response.status();
+                let body = response.body().as_deref().unwrap_or_default();
+                let message = serde_json::from_slice::<serde_json::Value>(body)
+                    .ok()
+                    .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+                    .unwrap_or_else(|| String::from_utf8_lossy(body).to_string());
+                Err(anyhow::Error::new(ApiError { status, message }).context("Failed to open tunnel"))
+            }
+            Err(e) => Err(anyhow!("Failed to open tunnel: {}", e)),
+        }
+    }
+
+    /// Open a session with an instance's in-game console
+    pub async fn open_console(&self, id: &str) -> Result<ConsoleSession> {
+        Ok(ConsoleSession {
+            socket: self.open_tunnel(id, "console").await?,
+            decoder: FrameDecoder::new(),
+            next_id: 1,
+        })
+    }
+
     /// Stop a running instance
     pub async fn stop_instance(&self, id: &str) -> Result<InstanceStatusResponse> {
         let response = self
@@ -221,8 +594,8 @@ impl InstanceClient {
                 .await
                 .context("Failed to parse response JSON")
         } else {
-            let error = self.extract_error(response).await;
-            Err(anyhow!("API error ({}): {}", status.as_u16(), error))
+            let message = self.extract_error(response).await;
+            Err(ApiError { status, message }.into())
         }
     }
 
@@ -243,10 +616,148 @@ impl InstanceClient {
     }
 }
 
+/// Read the .ztd files in `dir` to upload them with a create request, in file name order
+pub fn read_mods_dir(dir: &Path) -> Result<Vec<ModFile>> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("Failed to read mods directory: {}", dir.display()))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_ztd = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("ztd"));
+        if is_ztd && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let data = std::fs::read(&path).with_context(|| format!("Failed to read mod: {}", path.display()))?;
+            Ok(ModFile {
+                name: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                data: base64::prelude::BASE64_STANDARD.encode(&data),
+            })
+        })
+        .collect()
+}
+
+/// Session with an instance's OpenZT console, carried over the tunnel endpoint
+///
+/// Commands are sent as framed JSON requests (see [`openzt_console::protocol`]) one at a
+/// time, and the response with the same id is read before the next is sent.
+pub struct ConsoleSession {
+    socket: TunnelStream,
+    decoder: FrameDecoder,
+    next_id: u64,
+}
+
+impl ConsoleSession {
+    /// Run Lua code on the console and return its output
+    ///
+    /// If the code raises an error the returned error wraps a
+    /// [`openzt_console::protocol::ConsoleError`], the session can still be used.
+    pub async fn execute(&mut self, command: &str) -> Result<String> {
+        self.send(Request::lua(self.next_id, command)).await
+    }
+
+    /// Call the console function `command` with `args` and return its output
+    pub async fn call(&mut self, command: &str, args: Vec<serde_json::Value>) -> Result<String> {
+        self.send(Request::new(self.next_id, command, args)).await
+    }
+
+    async fn send(&mut self, request: Request) -> Result<String> {
+        use tokio_tungstenite::tungstenite::Message;
+
+        self.next_id += 1;
+        let frame = protocol::encode_frame(&request).context("Failed to encode console command")?;
+        self.socket
+            .send(Message::Binary(frame.into()))
+            .await
+            .context("Failed to send console command")?;
+
+        loop {
+            let payload = self
+                .decoder
+                .next_frame()
+                .context("Invalid console response")?;
+            if let Some(payload) = payload {
+                let response: Response =
+                    protocol::decode_payload(&payload).context("Invalid console response")?;
+                if response.id != request.id {
+                    return Err(anyhow!(
+                        "Expected the response to request {}, got {}",
+                        request.id,
+                        response.id
+                    ));
+                }
+                return response.into_result().map_err(anyhow::Error::new);
+            }
+            match self.socket.next().await {
+                Some(Ok(Message::Binary(data))) => self.decoder.push(&data),
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err(anyhow!("Console connection closed"));
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(anyhow!("Console connection error: {}", e)),
+            }
+        }
+    }
+
+    /// Close the session
+    pub async fn close(mut self) {
+        let _ = self.socket.close(None).await;
+    }
+}
+
+/// Remove complete lines from `buffer` and return the payloads of SSE `data:` lines
+///
+/// Comment lines (keep-alives), `event:` names, and blank separators are
+/// skipped; an incomplete trailing line is left in the buffer.
+fn drain_sse_data(buffer: &mut String) -> Vec<String> {
+    let mut data = Vec::new();
+    while let Some(newline) = buffer.find('\n') {
+        let line: String = buffer.drain(..=newline).collect();
+        let line = line.trim_end_matches(['\r', '\n']);
+        if let Some(payload) = line.strip_prefix("data:") {
+            data.push(payload.strip_prefix(' ').unwrap_or(payload).to_string());
+        }
+    }
+    data
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_drain_sse_data() {
+        let mut buffer = String::from(": keep-alive\n\nevent: running\ndata: {\"a\":1}\n\nevent: stopped\ndata: {\"b\"");
+        assert_eq!(drain_sse_data(&mut buffer), vec!["{\"a\":1}".to_string()]);
+        assert_eq!(buffer, "data: {\"b\"");
+
+        buffer.push_str(":2}\n\n");
+        assert_eq!(drain_sse_data(&mut buffer), vec!["{\"b\":2}".to_string()]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_mods_dir() {
+        let dir = std::env::temp_dir().join(format!("openzt-mods-dir-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested.ztd")).unwrap();
+        std::fs::write(dir.join("zebras.ztd"), b"zebras").unwrap();
+        std::fs::write(dir.join("Aviary.ZTD"), b"aviary").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"not a mod").unwrap();
+
+        let mods = read_mods_dir(&dir).unwrap();
+        let names: Vec<&str> = mods.iter().map(|mod_file| mod_file.name.as_str()).collect();
+        assert_eq!(names, ["Aviary.ZTD", "zebras.ztd"]);
+        assert_eq!(mods[1].data, base64::prelude::BASE64_STANDARD.encode(b"zebras"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(read_mods_dir(&dir).is_err());
+    }
+
     #[test]
     fn test_client_creation() {
         let client = InstanceClient::new("http://localhost:3000");
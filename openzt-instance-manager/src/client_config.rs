@@ -3,8 +3,11 @@
 //! This module handles loading and managing configuration from
 //! ~/.config/openzt-client/config.toml
 
+use crate::client_transport::NetworkSettings;
+use crate::instance::InstanceConfig;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 /// Default API URL
@@ -13,6 +16,21 @@ const DEFAULT_API_URL: &str = "http://localhost:3000";
 /// Default output format
 const DEFAULT_OUTPUT_FORMAT: &str = "table";
 
+/// Default table border style
+const DEFAULT_TABLE_STYLE: &str = "modern";
+
+/// Default color theme
+const DEFAULT_THEME: &str = "default";
+
+/// Profile used when --profile is not given
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Default lifetime of the on-disk instance list cache, in seconds
+const DEFAULT_CACHE_TTL_SECS: u64 = 30;
+
+/// Default time `openzt create --wait` waits for an instance to be running, in seconds
+const DEFAULT_WAIT_TIMEOUT_SECS: u64 = 300;
+
 /// Client configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientConfig {
@@ -25,6 +43,12 @@ pub struct ClientConfig {
     /// Instance creation defaults
     #[serde(default)]
     pub create: CreateConfig,
+    /// Instance list cache settings
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Named server profiles with stored credentials
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProfileConfig>,
 }
 
 impl Default for ClientConfig {
@@ -33,21 +57,57 @@ impl Default for ClientConfig {
             api: ApiConfig::default(),
             output: OutputConfig::default(),
             create: CreateConfig::default(),
+            cache: CacheConfig::default(),
+            profiles: BTreeMap::new(),
         }
     }
 }
 
+/// A named server profile (`[profiles.<name>]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// API base URL for this profile (falls back to `[api] base_url`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// API token sent as a bearer token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiConfig {
     /// Default API base URL
     #[serde(default = "default_api_url")]
     pub base_url: String,
+    /// Proxy URL, instead of HTTPS_PROXY/HTTP_PROXY/ALL_PROXY
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Comma-separated hosts reached without the proxy, instead of NO_PROXY
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    /// PEM file of CA certificates to trust next to the system's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
 }
 
 impl Default for ApiConfig {
     fn default() -> Self {
         Self {
             base_url: default_api_url(),
+            proxy: None,
+            no_proxy: None,
+            ca_bundle: None,
+        }
+    }
+}
+
+impl ApiConfig {
+    /// The proxy and TLS settings for the client
+    pub fn network_settings(&self) -> NetworkSettings {
+        NetworkSettings {
+            proxy: self.proxy.clone(),
+            no_proxy: self.no_proxy.clone(),
+            ca_bundle: self.ca_bundle.clone(),
         }
     }
 }
@@ -61,12 +121,28 @@ pub struct OutputConfig {
     /// Default output format (table or json)
     #[serde(default = "default_output_format")]
     pub format: String,
+    /// strftime format for absolute timestamps (times are shown in UTC)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+    /// Show creation times in `list` relative to now (e.g. "5m ago")
+    #[serde(default = "default_true")]
+    pub relative_times: bool,
+    /// Table border style (modern, ascii, markdown, compact)
+    #[serde(default = "default_table_style")]
+    pub table_style: String,
+    /// Color theme ("default", or "mono" to disable colors)
+    #[serde(default = "default_theme")]
+    pub theme: String,
 }
 
 impl Default for OutputConfig {
     fn default() -> Self {
         Self {
             format: default_output_format(),
+            date_format: None,
+            relative_times: true,
+            table_style: default_table_style(),
+            theme: default_theme(),
         }
     }
 }
@@ -75,20 +151,111 @@ fn default_output_format() -> String {
     DEFAULT_OUTPUT_FORMAT.to_string()
 }
 
+fn default_table_style() -> String {
+    DEFAULT_TABLE_STYLE.to_string()
+}
+
+fn default_theme() -> String {
+    DEFAULT_THEME.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Defaults of `openzt create`, used where neither a flag nor `--from-file` sets a value
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateConfig {
     /// Default RDP password for new instances
     #[serde(default)]
     pub rdp_password: Option<String>,
+    /// CPU limit in cores
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpulimit: Option<f64>,
+    /// Memory limit in MB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+    /// Docker image, the server's default if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Wine debug channels (WINEDEBUG)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wine_debug_level: Option<String>,
+    /// Directory whose .ztd files are installed in new instances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mods_dir: Option<PathBuf>,
+    /// Wait for new instances to be running before returning
+    #[serde(default)]
+    pub wait: bool,
+    /// How long to wait for a new instance to be running, in seconds
+    #[serde(default = "default_wait_timeout_secs")]
+    pub wait_timeout_secs: u64,
 }
 
 impl Default for CreateConfig {
     fn default() -> Self {
-        Self { rdp_password: None }
+        Self {
+            rdp_password: None,
+            cpulimit: None,
+            memory_mb: None,
+            image: None,
+            wine_debug_level: None,
+            mods_dir: None,
+            wait: false,
+            wait_timeout_secs: default_wait_timeout_secs(),
+        }
     }
 }
 
+impl CreateConfig {
+    /// The instance settings these defaults provide
+    pub fn instance_config(&self) -> InstanceConfig {
+        InstanceConfig {
+            wine_debug_level: self.wine_debug_level.clone(),
+            cpulimit: self.cpulimit,
+            image: self.image.clone(),
+            memory_mb: self.memory_mb,
+            ..Default::default()
+        }
+    }
+}
+
+fn default_wait_timeout_secs() -> u64 {
+    DEFAULT_WAIT_TIMEOUT_SECS
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Persist the instance list between invocations (used for ID resolution)
+    #[serde(default)]
+    pub enabled: bool,
+    /// How long a persisted instance list stays valid, in seconds
+    #[serde(default = "default_cache_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_cache_ttl_secs(),
+        }
+    }
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    DEFAULT_CACHE_TTL_SECS
+}
+
 impl ClientConfig {
+    /// Get the cache directory path
+    pub fn cache_dir() -> Result<PathBuf> {
+        let dirs = directories::ProjectDirs::from("com.openzt", "OpenZT", "openzt-client")
+            .context("Failed to determine cache directory")?;
+
+        Ok(dirs.cache_dir().to_path_buf())
+    }
+
     /// Get the config directory path
     pub fn config_dir() -> Result<PathBuf> {
         let dirs = directories::ProjectDirs::from("com.openzt", "OpenZT", "openzt-client")
@@ -127,7 +294,23 @@ impl ClientConfig {
         Ok(config)
     }
 
+    /// Load configuration for modification
+    ///
+    /// Unlike `load`, a config file that exists but cannot be parsed is an
+    /// error, so saving never silently replaces it with defaults.
+    pub fn load_for_update() -> Result<Self> {
+        let path = Self::config_file()?;
+        if path.exists() {
+            Self::load_from_path(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
     /// Save configuration to file
+    ///
+    /// The file may contain API tokens, so on Unix it is written readable by
+    /// the owner only.
     pub fn save(&self) -> Result<()> {
         let path = Self::config_file()?;
 
@@ -140,16 +323,80 @@ impl ClientConfig {
         let contents = toml::to_string_pretty(self)
             .context("Failed to serialize configuration")?;
 
-        std::fs::write(&path, contents)
+        write_private(&path, &contents)
             .with_context(|| format!("Failed to write config file: {}", path.display()))?;
 
         Ok(())
     }
 
+    /// Get a profile by name
+    pub fn profile(&self, name: &str) -> Option<&ProfileConfig> {
+        self.profiles.get(name)
+    }
+
+    /// API base URL for a profile, falling back to `[api] base_url`
+    pub fn base_url_for(&self, profile: &str) -> String {
+        self.profile(profile)
+            .and_then(|p| p.base_url.clone())
+            .unwrap_or_else(|| self.api.base_url.clone())
+    }
+
+    /// Stored API token for a profile
+    pub fn token_for(&self, profile: &str) -> Option<&str> {
+        self.profile(profile).and_then(|p| p.token.as_deref())
+    }
+
+    /// Store a token (and the URL it was issued for) in a profile
+    pub fn set_token(&mut self, profile: &str, base_url: &str, token: &str) {
+        let entry = self.profiles.entry(profile.to_string()).or_default();
+        entry.base_url = Some(base_url.to_string());
+        entry.token = Some(token.to_string());
+    }
+
+    /// Remove a profile's token, returning whether one was stored
+    pub fn remove_token(&mut self, profile: &str) -> bool {
+        let Some(entry) = self.profiles.get_mut(profile) else {
+            return false;
+        };
+        let removed = entry.token.take().is_some();
+        if entry.base_url.is_none() {
+            self.profiles.remove(profile);
+        }
+        removed
+    }
+
     /// Get the output format as an enum
     pub fn output_format(&self) -> Option<super::output::OutputFormat> {
         super::output::OutputFormat::from_str(&self.output.format)
     }
+
+    /// Get the table style from config
+    pub fn table_style(&self) -> Option<super::output::TableStyle> {
+        self.output.table_style.parse().ok()
+    }
+}
+
+/// Write a file that only the current user can read
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // Tighten files created before tokens were stored
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents.as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        std::fs::write(path, contents)
+    }
 }
 
 #[cfg(test)]
@@ -162,6 +409,35 @@ mod tests {
         assert_eq!(config.api.base_url, DEFAULT_API_URL);
         assert_eq!(config.output.format, DEFAULT_OUTPUT_FORMAT);
         assert!(config.create.rdp_password.is_none());
+        assert!(!config.create.wait);
+        assert_eq!(config.create.wait_timeout_secs, DEFAULT_WAIT_TIMEOUT_SECS);
+        assert!(!config.cache.enabled);
+        assert_eq!(config.cache.ttl_secs, DEFAULT_CACHE_TTL_SECS);
+        assert!(config.output.date_format.is_none());
+        assert!(config.output.relative_times);
+        assert_eq!(config.table_style(), Some(crate::output::TableStyle::Modern));
+        assert_eq!(config.output.theme, DEFAULT_THEME);
+    }
+
+    #[test]
+    fn test_profile_tokens() {
+        let mut config = ClientConfig::default();
+        assert_eq!(config.base_url_for(DEFAULT_PROFILE), DEFAULT_API_URL);
+        assert!(config.token_for(DEFAULT_PROFILE).is_none());
+
+        config.set_token("prod", "https://zt.example.com", "secret");
+        assert_eq!(config.base_url_for("prod"), "https://zt.example.com");
+        assert_eq!(config.token_for("prod"), Some("secret"));
+
+        let toml_string = toml::to_string_pretty(&config).unwrap();
+        let parsed: ClientConfig = toml::from_str(&toml_string).unwrap();
+        assert_eq!(parsed.token_for("prod"), Some("secret"));
+
+        // Logging out keeps the profile's URL
+        assert!(config.remove_token("prod"));
+        assert!(!config.remove_token("prod"));
+        assert_eq!(config.base_url_for("prod"), "https://zt.example.com");
+        assert!(config.token_for("prod").is_none());
     }
 
     #[test]
@@ -177,17 +453,50 @@ mod tests {
         let toml_content = r#"
             [api]
             base_url = "http://example.com:8080"
+            proxy = "http://proxy.corp:3128"
+            ca_bundle = "/etc/ssl/corp-ca.pem"
 
             [output]
             format = "json"
+            date_format = "%d/%m/%Y %H:%M"
+            relative_times = false
+            table_style = "markdown"
+            theme = "mono"
 
             [create]
             rdp_password = "secret123"
+            cpulimit = 1.5
+            memory_mb = 2048
+            mods_dir = "/srv/zoo/mods"
+            wait = true
+
+            [cache]
+            enabled = true
+            ttl_secs = 120
         "#;
 
         let config: ClientConfig = toml::from_str(toml_content).unwrap();
         assert_eq!(config.api.base_url, "http://example.com:8080");
+        let network = config.api.network_settings();
+        assert_eq!(network.proxy.as_deref(), Some("http://proxy.corp:3128"));
+        assert!(network.no_proxy.is_none());
+        assert_eq!(network.ca_bundle, Some(PathBuf::from("/etc/ssl/corp-ca.pem")));
         assert_eq!(config.output.format, "json");
+        assert_eq!(config.output.date_format.as_deref(), Some("%d/%m/%Y %H:%M"));
+        assert!(!config.output.relative_times);
+        assert_eq!(config.table_style(), Some(crate::output::TableStyle::Markdown));
+        assert_eq!(config.output.theme, "mono");
         assert_eq!(config.create.rdp_password, Some("secret123".to_string()));
+        assert_eq!(config.create.mods_dir, Some(PathBuf::from("/srv/zoo/mods")));
+        assert!(config.create.wait);
+        assert_eq!(config.create.wait_timeout_secs, DEFAULT_WAIT_TIMEOUT_SECS);
+
+        // Settings an instance file or flag already sets are kept
+        let defaults = config.create.instance_config();
+        let set = InstanceConfig { cpulimit: Some(4.0), ..Default::default() }.or(&defaults);
+        assert_eq!(set.cpulimit, Some(4.0));
+        assert_eq!(set.memory_mb, Some(2048));
+        assert!(config.cache.enabled);
+        assert_eq!(config.cache.ttl_secs, 120);
     }
 }
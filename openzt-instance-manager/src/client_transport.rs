@@ -0,0 +1,268 @@
+//! Proxy and TLS settings of the CLI client
+//!
+//! Requests and WebSocket tunnels go through the proxy in `[api] proxy`, or else the
+//! one in the standard environment variables: `HTTPS_PROXY` for an `https` API URL,
+//! `HTTP_PROXY` for an `http` one, then `ALL_PROXY` (upper or lower case). Hosts on
+//! `[api] no_proxy` (or `NO_PROXY`) are reached directly. Tunnels are opened with
+//! `CONNECT`, so they need an `http://` proxy.
+//!
+//! The certificates in `[api] ca_bundle` are trusted next to the system's, for
+//! servers with certificates from an internal CA.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use reqwest::Url;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Longest proxy response head accepted for a `CONNECT`
+const MAX_CONNECT_RESPONSE: usize = 8192;
+
+/// How the client reaches the API server, besides its URL
+#[derive(Debug, Clone, Default)]
+pub struct NetworkSettings {
+    /// Proxy URL, instead of the one in the environment
+    pub proxy: Option<String>,
+    /// Comma-separated hosts reached without the proxy, instead of `NO_PROXY`
+    pub no_proxy: Option<String>,
+    /// PEM file of CA certificates to trust next to the system's
+    pub ca_bundle: Option<PathBuf>,
+}
+
+/// Network settings resolved for one API URL
+#[derive(Clone, Default)]
+pub(crate) struct Transport {
+    /// Proxy requests to the API server go through
+    pub proxy: Option<Url>,
+    pub root_certificates: Vec<reqwest::Certificate>,
+    /// TLS connector for tunnels, trusting `root_certificates`
+    pub tls_connector: Option<native_tls::TlsConnector>,
+}
+
+impl Transport {
+    /// Resolve `settings` for requests to `base_url`, reading proxies from the environment
+    pub fn new(base_url: &str, settings: &NetworkSettings) -> Result<Self> {
+        let base_url = Url::parse(base_url).with_context(|| format!("Invalid API URL: {}", base_url))?;
+        let proxy = proxy_for(&base_url, settings, |name| std::env::var(name).ok())?;
+
+        let mut transport = Transport { proxy, ..Default::default() };
+        if let Some(path) = &settings.ca_bundle {
+            transport.load_ca_bundle(path)?;
+        }
+        Ok(transport)
+    }
+
+    fn load_ca_bundle(&mut self, path: &Path) -> Result<()> {
+        let bundle = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read CA bundle: {}", path.display()))?;
+        let certificates = pem_certificates(&bundle);
+        if certificates.is_empty() {
+            bail!("No certificates in CA bundle: {}", path.display());
+        }
+
+        let mut tls = native_tls::TlsConnector::builder();
+        for pem in certificates {
+            let invalid = || format!("Invalid certificate in CA bundle: {}", path.display());
+            self.root_certificates.push(reqwest::Certificate::from_pem(pem.as_bytes()).with_context(invalid)?);
+            tls.add_root_certificate(native_tls::Certificate::from_pem(pem.as_bytes()).with_context(invalid)?);
+        }
+        self.tls_connector = Some(tls.build().context("Failed to set up TLS for tunnels")?);
+        Ok(())
+    }
+
+    /// Apply these settings to an HTTP client
+    pub fn configure(&self, mut builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder> {
+        // The proxy is resolved here, so reqwest mustn't pick its own from the environment
+        builder = match &self.proxy {
+            Some(proxy) => builder.proxy(reqwest::Proxy::all(proxy.clone()).context("Invalid proxy URL")?),
+            None => builder.no_proxy(),
+        };
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        Ok(builder)
+    }
+}
+
+/// The proxy for requests to `base_url`, `env` looking up environment variables
+fn proxy_for(base_url: &Url, settings: &NetworkSettings, env: impl Fn(&str) -> Option<String>) -> Result<Option<Url>> {
+    let env = |name: &str| {
+        env(name)
+            .or_else(|| env(&name.to_lowercase()))
+            .filter(|value| !value.trim().is_empty())
+    };
+
+    let proxy = match &settings.proxy {
+        Some(proxy) => Some(proxy.clone()),
+        None if base_url.scheme() == "https" => env("HTTPS_PROXY").or_else(|| env("ALL_PROXY")),
+        None => env("HTTP_PROXY").or_else(|| env("ALL_PROXY")),
+    };
+    let Some(proxy) = proxy.filter(|proxy| !proxy.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    let no_proxy = settings.no_proxy.clone().or_else(|| env("NO_PROXY")).unwrap_or_default();
+    if base_url.host_str().is_some_and(|host| bypasses_proxy(host, &no_proxy)) {
+        return Ok(None);
+    }
+
+    // Like curl, a proxy without a scheme is an HTTP proxy
+    let proxy = proxy.trim();
+    let proxy = if proxy.contains("://") {
+        Url::parse(proxy)
+    } else {
+        Url::parse(&format!("http://{}", proxy))
+    };
+    proxy.map(Some).context("Invalid proxy URL")
+}
+
+/// Whether `host` is on a `NO_PROXY`-style list
+///
+/// Entries match the host itself and its subdomains; `*` matches every host.
+fn bypasses_proxy(host: &str, no_proxy: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+    no_proxy.split(',').map(str::trim).filter(|entry| !entry.is_empty()).any(|entry| {
+        let entry = entry.trim_start_matches('.').to_ascii_lowercase();
+        entry == "*" || host == entry || host.strip_suffix(&entry).is_some_and(|rest| rest.ends_with('.'))
+    })
+}
+
+/// The certificates of a PEM bundle, each with its BEGIN and END lines
+fn pem_certificates(bundle: &str) -> Vec<&str> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let mut certificates = Vec::new();
+    let mut rest = bundle;
+    while let Some(start) = rest.find(BEGIN) {
+        let Some(length) = rest[start..].find(END) else {
+            break;
+        };
+        let end = start + length + END.len();
+        certificates.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    certificates
+}
+
+/// Open a connection to `host:port` tunneled through an HTTP proxy with `CONNECT`
+pub(crate) async fn connect_via_proxy(proxy: &Url, host: &str, port: u16) -> Result<TcpStream> {
+    if proxy.scheme() != "http" {
+        bail!("Tunnels need an http:// proxy, not {}", proxy);
+    }
+    let proxy_host = proxy.host_str().context("Proxy URL has no host")?;
+    let proxy_port = proxy.port_or_known_default().unwrap_or(80);
+    let mut stream = TcpStream::connect((proxy_host, proxy_port))
+        .await
+        .with_context(|| format!("Failed to connect to proxy {}:{}", proxy_host, proxy_port))?;
+
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if !proxy.username().is_empty() {
+        let credentials = format!("{}:{}", proxy.username(), proxy.password().unwrap_or_default());
+        let credentials = base64::prelude::BASE64_STANDARD.encode(credentials);
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read the response head a byte at a time, what follows it belongs to the tunnel
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_CONNECT_RESPONSE {
+            bail!("Proxy response too long");
+        }
+        head.push(stream.read_u8().await.context("Proxy closed the connection")?);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.lines().next().unwrap_or_default();
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        bail!("Proxy refused the tunnel: {}", status_line);
+    }
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::net::TcpListener;
+
+    fn resolve(base_url: &str, settings: &NetworkSettings, env: &[(&str, &str)]) -> Option<String> {
+        let env: HashMap<String, String> = env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        proxy_for(&Url::parse(base_url).unwrap(), settings, |name| env.get(name).cloned())
+            .unwrap()
+            .map(|proxy| proxy.to_string())
+    }
+
+    #[test]
+    fn test_proxy_for() {
+        let none = NetworkSettings::default();
+        let env = [("HTTPS_PROXY", "proxy.corp:3128"), ("http_proxy", "http://plain.corp:8080")];
+        assert_eq!(resolve("https://zt.corp", &none, &env).as_deref(), Some("http://proxy.corp:3128/"));
+        assert_eq!(resolve("http://zt.corp", &none, &env).as_deref(), Some("http://plain.corp:8080/"));
+        assert_eq!(resolve("https://zt.corp", &none, &[("ALL_PROXY", "http://all:1")]).as_deref(), Some("http://all:1/"));
+        assert_eq!(resolve("https://zt.corp", &none, &[]), None);
+
+        // NO_PROXY
+        let env = [("HTTPS_PROXY", "proxy.corp:3128"), ("NO_PROXY", "localhost, .corp")];
+        assert_eq!(resolve("https://zt.corp", &none, &env), None);
+        assert!(resolve("https://zt.example.com", &none, &env).is_some());
+
+        // The config takes precedence over the environment
+        let configured = NetworkSettings {
+            proxy: Some("http://configured:3128".to_string()),
+            no_proxy: Some("internal".to_string()),
+            ca_bundle: None,
+        };
+        assert_eq!(resolve("https://zt.corp", &configured, &env).as_deref(), Some("http://configured:3128/"));
+        assert_eq!(resolve("https://zt.internal", &configured, &env), None);
+    }
+
+    #[test]
+    fn test_bypasses_proxy() {
+        assert!(bypasses_proxy("localhost", "localhost,127.0.0.1"));
+        assert!(bypasses_proxy("127.0.0.1", "localhost, 127.0.0.1"));
+        assert!(bypasses_proxy("zt.Example.com", "example.com"));
+        assert!(bypasses_proxy("zt.example.com", ".example.com"));
+        assert!(!bypasses_proxy("zt.notexample.com", "example.com"));
+        assert!(bypasses_proxy("anything", "*"));
+        assert!(!bypasses_proxy("anything", ""));
+    }
+
+    #[test]
+    fn test_pem_certificates() {
+        let bundle = "# Internal CA\n-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nBBBB\n-----END CERTIFICATE-----\n-----BEGIN CERTIFICATE-----\ncut";
+        let certificates = pem_certificates(bundle);
+        assert_eq!(certificates.len(), 2);
+        assert_eq!(certificates[1], "-----BEGIN CERTIFICATE-----\nBBBB\n-----END CERTIFICATE-----");
+        assert!(pem_certificates("not a certificate").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_via_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = Url::parse(&format!("http://user:secret@{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            for answer in ["HTTP/1.1 200 Connection established\r\n\r\ntunneled", "HTTP/1.1 403 Forbidden\r\n\r\n"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 1024];
+                let read = stream.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..read]);
+                assert!(request.starts_with("CONNECT zt.corp:443 HTTP/1.1\r\n"));
+                assert!(request.contains("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+                stream.write_all(answer.as_bytes()).await.unwrap();
+            }
+        });
+
+        // Data after the proxy's response is left on the stream
+        let mut stream = connect_via_proxy(&proxy, "zt.corp", 443).await.unwrap();
+        let mut tunneled = String::new();
+        stream.read_to_string(&mut tunneled).await.unwrap();
+        assert_eq!(tunneled, "tunneled");
+
+        let error = connect_via_proxy(&proxy, "zt.corp", 443).await.unwrap_err();
+        assert!(error.to_string().contains("403 Forbidden"));
+    }
+}
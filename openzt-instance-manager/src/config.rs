@@ -1,3 +1,6 @@
+use crate::instance::InstanceEventKind;
+use crate::ports::PortMode;
+use crate::restart::{default_backoff_secs, default_window_secs, RestartPolicy};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use anyhow::Result;
@@ -9,16 +12,49 @@ pub struct Config {
     pub docker: DockerConfig,
     pub instances: InstancesConfig,
     pub api: ApiConfig,
+    #[serde(default)]
+    pub test_runs: TestRunsConfig,
+    #[serde(default)]
+    pub dlls: DllsConfig,
+    #[serde(default)]
+    pub mods: ModsConfig,
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+    #[serde(default)]
+    pub recordings: RecordingsConfig,
+    #[serde(default)]
+    pub log_archive: LogArchiveConfig,
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     #[serde(default = "default_listen_address")]
     pub listen_address: SocketAddr,
+    /// Host name or address clients reach the server and its instances at
+    #[serde(default = "default_public_host")]
+    pub public_host: String,
+    /// `vnc_url` returned for instances, with `{host}`, `{port}` and `{id}` filled in
+    #[serde(default = "default_vnc_url_template")]
+    pub vnc_url_template: String,
+}
+
+impl ServerConfig {
+    /// URL clients connect to an instance's VNC server at
+    pub fn vnc_url(&self, instance_id: &str, vnc_port: u16) -> String {
+        self.vnc_url_template
+            .replace("{host}", &self.public_host)
+            .replace("{port}", &vnc_port.to_string())
+            .replace("{id}", instance_id)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortsConfig {
+    /// How host ports are chosen: "ranges", "shared", "random" or "ephemeral"
+    #[serde(default)]
+    pub mode: PortMode,
     #[serde(default = "default_vnc_start")]
     pub vnc_start: u16,
     #[serde(default = "default_vnc_end")]
@@ -27,6 +63,40 @@ pub struct PortsConfig {
     pub console_start: u16,
     #[serde(default = "default_console_end")]
     pub console_end: u16,
+    /// Single range split into consecutive (VNC, console) pairs in "shared" mode
+    #[serde(default = "default_shared_start")]
+    pub shared_start: u16,
+    #[serde(default = "default_shared_end")]
+    pub shared_end: u16,
+    /// Check that ports are free on the host before allocating them
+    #[serde(default)]
+    pub probe_availability: bool,
+    /// Seconds a new instance's ports are held before its container must have started
+    #[serde(default = "default_reservation_ttl_secs")]
+    pub reservation_ttl_secs: u64,
+    /// Ports within the ranges that are never handed out (claimed by other services)
+    #[serde(default)]
+    pub excluded: Vec<u16>,
+    /// Recreate stopped containers recovered with ports outside the ranges on new ones
+    #[serde(default = "default_republish_on_recovery")]
+    pub republish_on_recovery: bool,
+}
+
+impl PortsConfig {
+    /// Check that every excluded port falls within a range used by the current mode
+    pub fn validate(&self) -> Result<()> {
+        let in_range = |port: &u16| match self.mode {
+            PortMode::Shared => (self.shared_start..self.shared_end).contains(port),
+            _ => (self.vnc_start..self.vnc_end).contains(port) || (self.console_start..self.console_end).contains(port),
+        };
+
+        for port in &self.excluded {
+            if !in_range(port) {
+                anyhow::bail!("Excluded port {} is outside the configured port ranges", port);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,12 +115,137 @@ pub struct InstancesConfig {
     pub auto_cleanup_hours: u64,
     #[serde(default = "default_cpulimit")]
     pub default_cpulimit: f64,
+    /// File instance schedules are saved to, so they survive a restart
+    #[serde(default = "default_schedules_file")]
+    pub schedules_file: String,
+    /// Times an instance that crashed or ran out of memory is restarted automatically
+    /// within `restart_window_secs` before it is marked failed
+    #[serde(default)]
+    pub auto_restarts: u32,
+    #[serde(default = "default_window_secs")]
+    pub restart_window_secs: u64,
+    /// Seconds before the first automatic restart, doubled for each further one
+    #[serde(default = "default_backoff_secs")]
+    pub restart_backoff_secs: u64,
+    /// Hours a deleted instance is kept in the trash before it is purged; 0 deletes
+    /// instances right away
+    #[serde(default = "default_trash_retention_hours")]
+    pub trash_retention_hours: u64,
+    /// File the trashed instances are saved to, so they survive a restart
+    #[serde(default = "default_trash_file")]
+    pub trash_file: String,
+    /// File instance names and labels are saved to, so they survive a restart
+    #[serde(default = "default_metadata_file")]
+    pub metadata_file: String,
+    /// Seconds a started instance's game has to answer on its console before the
+    /// instance is marked as an error; 0 marks instances running as soon as their
+    /// container starts
+    #[serde(default = "default_ready_timeout_secs")]
+    pub ready_timeout_secs: u64,
+    /// Seconds a create request's `Idempotency-Key` returns the instance it created
+    #[serde(default = "default_idempotency_key_ttl_secs")]
+    pub idempotency_key_ttl_secs: u64,
+}
+
+impl InstancesConfig {
+    /// Restart policy of instances that don't set their own
+    pub fn restart_policy(&self) -> RestartPolicy {
+        RestartPolicy {
+            max_restarts: self.auto_restarts,
+            window_secs: self.restart_window_secs,
+            backoff_secs: self.restart_backoff_secs,
+        }
+    }
+
+    /// How long a started instance's game has to come up, `None` if it isn't waited for
+    pub fn ready_timeout(&self) -> Option<std::time::Duration> {
+        (self.ready_timeout_secs > 0).then(|| std::time::Duration::from_secs(self.ready_timeout_secs))
+    }
+
+    /// How long a deleted instance is kept in the trash
+    pub fn trash_retention(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.trash_retention_hours.min(i64::MAX as u64 / 3600) as i64)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRunsConfig {
+    /// Seconds a test run waits for results before it is abandoned
+    #[serde(default = "default_test_run_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DllsConfig {
+    /// Directory DLLs uploaded to `/api/dlls` are stored in
+    #[serde(default = "default_dll_dir")]
+    pub dir: String,
+    /// Seconds a DLL downloaded from a `dll_url` without `dll_sha256` is reused
+    #[serde(default = "default_dll_url_cache_secs")]
+    pub url_cache_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModsConfig {
+    /// Directory mods uploaded to `/api/mods` are stored in
+    #[serde(default = "default_mod_dir")]
+    pub dir: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplatesConfig {
+    /// Directory templates created through `/api/templates` are stored in
+    #[serde(default = "default_template_dir")]
+    pub dir: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingsConfig {
+    /// Directory display recordings are kept in, one subdirectory per instance
+    #[serde(default = "default_recording_dir")]
+    pub dir: String,
+    /// Frames per second recorded
+    #[serde(default = "default_recording_framerate")]
+    pub framerate: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogArchiveConfig {
+    /// Follow every instance's Docker logs into files that outlive the instance
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory the log files are kept in, one subdirectory per instance
+    #[serde(default = "default_log_archive_dir")]
+    pub dir: String,
+    /// Size in MB at which an instance's log file is rotated
+    #[serde(default = "default_log_archive_max_file_mb")]
+    pub max_file_mb: u64,
+    /// Log files kept per instance, the current one included
+    #[serde(default = "default_log_archive_max_files")]
+    pub max_files: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhooksConfig {
+    /// URLs instance events are POSTed to
+    #[serde(default)]
+    pub urls: Vec<String>,
+    /// Kinds of events sent
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<InstanceEventKind>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiConfig {
+    /// Require a bearer token on every request but `/health`
     #[serde(default)]
     pub enable_auth: bool,
+    /// Admin token accepted besides those issued through `/api/admin/tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
+    /// Directory tokens issued through `/api/admin/tokens` are stored in
+    #[serde(default = "default_tokens_dir")]
+    pub tokens_dir: String,
 }
 
 impl Default for Config {
@@ -61,6 +256,13 @@ impl Default for Config {
             docker: DockerConfig::default(),
             instances: InstancesConfig::default(),
             api: ApiConfig::default(),
+            test_runs: TestRunsConfig::default(),
+            dlls: DllsConfig::default(),
+            mods: ModsConfig::default(),
+            templates: TemplatesConfig::default(),
+            recordings: RecordingsConfig::default(),
+            log_archive: LogArchiveConfig::default(),
+            webhooks: WebhooksConfig::default(),
         }
     }
 }
@@ -69,6 +271,8 @@ impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             listen_address: default_listen_address(),
+            public_host: default_public_host(),
+            vnc_url_template: default_vnc_url_template(),
         }
     }
 }
@@ -76,10 +280,17 @@ impl Default for ServerConfig {
 impl Default for PortsConfig {
     fn default() -> Self {
         Self {
+            mode: PortMode::default(),
             vnc_start: default_vnc_start(),
             vnc_end: default_vnc_end(),
             console_start: default_console_start(),
             console_end: default_console_end(),
+            shared_start: default_shared_start(),
+            shared_end: default_shared_end(),
+            probe_availability: false,
+            reservation_ttl_secs: default_reservation_ttl_secs(),
+            excluded: Vec::new(),
+            republish_on_recovery: default_republish_on_recovery(),
         }
     }
 }
@@ -99,6 +310,77 @@ impl Default for InstancesConfig {
             max_instances: default_max_instances(),
             auto_cleanup_hours: default_auto_cleanup_hours(),
             default_cpulimit: default_cpulimit(),
+            schedules_file: default_schedules_file(),
+            auto_restarts: 0,
+            restart_window_secs: default_window_secs(),
+            restart_backoff_secs: default_backoff_secs(),
+            trash_retention_hours: default_trash_retention_hours(),
+            trash_file: default_trash_file(),
+            metadata_file: default_metadata_file(),
+            ready_timeout_secs: default_ready_timeout_secs(),
+            idempotency_key_ttl_secs: default_idempotency_key_ttl_secs(),
+        }
+    }
+}
+
+impl Default for TestRunsConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_test_run_timeout_secs(),
+        }
+    }
+}
+
+impl Default for DllsConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_dll_dir(),
+            url_cache_secs: default_dll_url_cache_secs(),
+        }
+    }
+}
+
+impl Default for ModsConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_mod_dir(),
+        }
+    }
+}
+
+impl Default for TemplatesConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_template_dir(),
+        }
+    }
+}
+
+impl Default for LogArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_log_archive_dir(),
+            max_file_mb: default_log_archive_max_file_mb(),
+            max_files: default_log_archive_max_files(),
+        }
+    }
+}
+
+impl Default for RecordingsConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_recording_dir(),
+            framerate: default_recording_framerate(),
+        }
+    }
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            events: default_webhook_events(),
         }
     }
 }
@@ -107,6 +389,8 @@ impl Default for ApiConfig {
     fn default() -> Self {
         Self {
             enable_auth: false,
+            admin_token: None,
+            tokens_dir: default_tokens_dir(),
         }
     }
 }
@@ -115,6 +399,14 @@ fn default_listen_address() -> SocketAddr {
     "0.0.0.0:3000".parse().unwrap()
 }
 
+fn default_public_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_vnc_url_template() -> String {
+    "vnc://{host}:{port}".to_string()
+}
+
 fn default_vnc_start() -> u16 {
     15900
 }
@@ -131,6 +423,22 @@ fn default_console_end() -> u16 {
     18181
 }
 
+fn default_shared_start() -> u16 {
+    15900
+}
+
+fn default_shared_end() -> u16 {
+    16100
+}
+
+fn default_reservation_ttl_secs() -> u64 {
+    600
+}
+
+fn default_republish_on_recovery() -> bool {
+    true
+}
+
 fn default_docker_image() -> String {
     "finn/winezt:latest".to_string()
 }
@@ -151,12 +459,129 @@ fn default_cpulimit() -> f64 {
     0.5  // Default: 50% of 1 CPU core
 }
 
+fn default_schedules_file() -> String {
+    "/var/lib/openzt-instance-manager/schedules.json".to_string()
+}
+
+fn default_trash_retention_hours() -> u64 {
+    72
+}
+
+fn default_trash_file() -> String {
+    "/var/lib/openzt-instance-manager/trash.json".to_string()
+}
+
+fn default_metadata_file() -> String {
+    "/var/lib/openzt-instance-manager/metadata.json".to_string()
+}
+
+fn default_ready_timeout_secs() -> u64 {
+    180
+}
+
+fn default_idempotency_key_ttl_secs() -> u64 {
+    86400
+}
+
+fn default_test_run_timeout_secs() -> u64 {
+    900
+}
+
+fn default_dll_dir() -> String {
+    "/var/lib/openzt-instance-manager/dlls".to_string()
+}
+
+fn default_dll_url_cache_secs() -> u64 {
+    3600
+}
+
+fn default_mod_dir() -> String {
+    "/var/lib/openzt-instance-manager/mods".to_string()
+}
+
+fn default_template_dir() -> String {
+    "/var/lib/openzt-instance-manager/templates".to_string()
+}
+
+fn default_recording_dir() -> String {
+    "/var/lib/openzt-instance-manager/recordings".to_string()
+}
+
+fn default_recording_framerate() -> u32 {
+    15
+}
+
+fn default_log_archive_dir() -> String {
+    "/var/lib/openzt-instance-manager/logs".to_string()
+}
+
+fn default_log_archive_max_file_mb() -> u64 {
+    10
+}
+
+fn default_log_archive_max_files() -> usize {
+    5
+}
+
+/// Shown instead of a secret in [`Config::redacted`]
+pub const REDACTED: &str = "<redacted>";
+
+/// Whether a config key holds a secret, like `admin_token` or `registry_password`
+///
+/// URLs count too: webhook URLs usually embed a token.
+fn is_secret_key(key: &str) -> bool {
+    ["token", "secret", "password", "api_key", "urls"]
+        .iter()
+        .any(|secret| key == *secret || key.ends_with(&format!("_{}", secret)))
+}
+
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) && !value.is_null() {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+impl Config {
+    /// The configuration as JSON with the values of secret keys replaced by [`REDACTED`]
+    ///
+    /// Secrets are recognized by their key, so new ones are hidden without changes here.
+    pub fn redacted(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        redact(&mut value);
+        value
+    }
+}
+
+fn default_webhook_events() -> Vec<InstanceEventKind> {
+    vec![
+        InstanceEventKind::Crashed,
+        InstanceEventKind::OomKilled,
+        InstanceEventKind::Restarting,
+        InstanceEventKind::Failed,
+    ]
+}
+
+fn default_tokens_dir() -> String {
+    "/var/lib/openzt-instance-manager/tokens".to_string()
+}
+
 pub fn load_config() -> Result<Config> {
     let config_path = "config.toml";
 
     if std::path::Path::new(config_path).exists() {
         let content = std::fs::read_to_string(config_path)?;
         let config: Config = toml::from_str(&content)?;
+        config.ports.validate()?;
         Ok(config)
     } else {
         // Write default config file
@@ -167,3 +592,55 @@ pub fn load_config() -> Result<Config> {
         Ok(default_config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excluded_ports_validated() {
+        let mut ports = PortsConfig {
+            excluded: vec![15900, 18100],
+            ..Default::default()
+        };
+        assert!(ports.validate().is_ok());
+
+        ports.excluded.push(13400);
+        assert!(ports.validate().is_err());
+
+        // Shared mode only checks the shared range
+        ports.mode = PortMode::Shared;
+        ports.excluded = vec![18100];
+        assert!(ports.validate().is_err());
+    }
+
+    #[test]
+    fn test_vnc_url() {
+        let mut server = ServerConfig {
+            public_host: "zt.example.com".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(server.vnc_url("abc", 15901), "vnc://zt.example.com:15901");
+
+        server.vnc_url_template = "wss://{host}/api/instances/{id}/tunnel?port=vnc".to_string();
+        assert_eq!(server.vnc_url("abc", 15901), "wss://zt.example.com/api/instances/abc/tunnel?port=vnc");
+    }
+
+    #[test]
+    fn test_redacted() {
+        let mut value = serde_json::json!({
+            "api": { "enable_auth": true, "admin_token": "s3cret", "tokens_file": "/tmp/tokens.json" },
+            "registries": [{ "host": "registry.example", "password": "hunter2" }],
+        });
+        redact(&mut value);
+        assert_eq!(value["api"]["enable_auth"], true);
+        assert_eq!(value["api"]["admin_token"], REDACTED);
+        assert_eq!(value["api"]["tokens_file"], "/tmp/tokens.json");
+        assert_eq!(value["registries"][0]["host"], "registry.example");
+        assert_eq!(value["registries"][0]["password"], REDACTED);
+
+        let config = Config::default().redacted();
+        assert_eq!(config["docker"]["image"], "finn/winezt:latest");
+        assert_eq!(config["ports"]["vnc_start"], 15900);
+    }
+}
@@ -0,0 +1,272 @@
+//! DLLs stored by the server
+//!
+//! `POST /api/dlls` stores a DLL once, under the SHA-256 hash of its contents, so
+//! instances and test runs can be created from it with `dll_id` instead of uploading
+//! the same build with every request. Each DLL is kept in the library directory as
+//! `<id>.dll` next to `<id>.json` with its name and upload time, so the library
+//! survives restarts.
+//!
+//! DLLs that create requests name with `dll_url` are downloaded into the library too.
+//! With the expected hash, a DLL that is already there is never downloaded again;
+//! without it, a URL is downloaded again once the last download is older than
+//! `[dlls] url_cache_secs`, as URLs like a release's "latest" asset change.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Largest DLL downloaded from a `dll_url`
+const MAX_DOWNLOAD_BYTES: usize = 100 * 1024 * 1024;
+
+/// How long a download from a `dll_url` may take
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadDllRequest {
+    /// Name shown when listing DLLs, e.g. a version or branch
+    pub name: String,
+    /// Base64-encoded DLL
+    pub data: String,
+}
+
+/// A DLL in the library, as returned by `GET /api/dlls`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DllEntry {
+    /// SHA-256 of the DLL, as hex
+    pub id: String,
+    pub name: String,
+    /// Size in bytes
+    pub size: u64,
+    pub uploaded_at: DateTime<Utc>,
+    /// URL the DLL was last downloaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloaded_at: Option<DateTime<Utc>>,
+}
+
+pub struct DllLibrary {
+    dir: PathBuf,
+    entries: HashMap<String, DllEntry>,
+}
+
+impl DllLibrary {
+    /// An empty library stored in `dir`, see [`DllLibrary::load`]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Read the DLLs already in the library directory, creating it if needed
+    ///
+    /// Returns the number of DLLs found.
+    pub fn load(&mut self) -> Result<usize> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create DLL library {}", self.dir.display()))?;
+
+        for dir_entry in std::fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let entry: DllEntry = match std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str(&content)?))
+            {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::warn!("Skipping DLL library entry {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if !self.dll_path(&entry.id).exists() {
+                tracing::warn!("Skipping DLL library entry {}: {}.dll is missing", path.display(), entry.id);
+                continue;
+            }
+            self.entries.insert(entry.id.clone(), entry);
+        }
+        Ok(self.entries.len())
+    }
+
+    /// Add a DLL, returning its entry and whether it wasn't in the library yet
+    ///
+    /// A DLL that is already stored keeps the name it was first uploaded with.
+    pub fn add(&mut self, name: &str, dll_bytes: &[u8]) -> Result<(DllEntry, bool)> {
+        let id = hash_dll(dll_bytes);
+        if let Some(entry) = self.entries.get(&id) {
+            return Ok((entry.clone(), false));
+        }
+
+        let entry = DllEntry {
+            id: id.clone(),
+            name: name.to_string(),
+            size: dll_bytes.len() as u64,
+            uploaded_at: Utc::now(),
+            source_url: None,
+            downloaded_at: None,
+        };
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create DLL library {}", self.dir.display()))?;
+        std::fs::write(self.dll_path(&id), dll_bytes).context("Failed to write DLL to the library")?;
+        self.write_entry(&entry)?;
+
+        tracing::info!("Added DLL {} ({}) to the library", id, name);
+        self.entries.insert(id, entry.clone());
+        Ok((entry, true))
+    }
+
+    /// Add a DLL downloaded from `url`, or record the download for a DLL already in the library
+    pub fn add_download(&mut self, url: &str, dll_bytes: &[u8]) -> Result<DllEntry> {
+        let (mut entry, _) = self.add(url, dll_bytes)?;
+        entry.source_url = Some(url.to_string());
+        entry.downloaded_at = Some(Utc::now());
+        self.write_entry(&entry)?;
+        self.entries.insert(entry.id.clone(), entry.clone());
+        Ok(entry)
+    }
+
+    /// The DLL last downloaded from `url`, if that was less than `max_age` ago
+    pub fn cached_download(&self, url: &str, max_age: Duration) -> Option<&DllEntry> {
+        let max_age = chrono::Duration::from_std(max_age).ok()?;
+        self.entries
+            .values()
+            .filter(|entry| entry.source_url.as_deref() == Some(url))
+            .filter_map(|entry| entry.downloaded_at.map(|downloaded_at| (entry, downloaded_at)))
+            .filter(|(_, downloaded_at)| Utc::now() - *downloaded_at < max_age)
+            .max_by_key(|(_, downloaded_at)| *downloaded_at)
+            .map(|(entry, _)| entry)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&DllEntry> {
+        self.entries.get(id)
+    }
+
+    /// Every DLL, oldest first
+    pub fn list(&self) -> Vec<DllEntry> {
+        let mut entries: Vec<DllEntry> = self.entries.values().cloned().collect();
+        entries.sort_by(|a, b| a.uploaded_at.cmp(&b.uploaded_at).then_with(|| a.id.cmp(&b.id)));
+        entries
+    }
+
+    /// Remove a DLL, returning whether it was in the library
+    ///
+    /// Instances already created from it keep running, they use a copy.
+    pub fn remove(&mut self, id: &str) -> Result<bool> {
+        if self.entries.remove(id).is_none() {
+            return Ok(false);
+        }
+        std::fs::remove_file(self.dll_path(id)).context("Failed to remove DLL from the library")?;
+        let _ = std::fs::remove_file(self.dir.join(format!("{}.json", id)));
+        Ok(true)
+    }
+
+    /// Where the DLL with `id` is stored
+    pub fn dll_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.dll", id))
+    }
+
+    fn write_entry(&self, entry: &DllEntry) -> Result<()> {
+        std::fs::write(self.dir.join(format!("{}.json", entry.id)), serde_json::to_string_pretty(entry)?)
+            .context("Failed to write DLL library entry")
+    }
+}
+
+/// Download a DLL, following redirects as GitHub release assets need
+pub async fn download_dll(url: &str) -> Result<Vec<u8>> {
+    let client = reqwest::Client::builder().timeout(DOWNLOAD_TIMEOUT).build()?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download DLL from {}", url))?
+        .error_for_status()
+        .with_context(|| format!("Failed to download DLL from {}", url))?;
+
+    let mut dll_bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.with_context(|| format!("Failed to download DLL from {}", url))? {
+        if dll_bytes.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+            anyhow::bail!("DLL at {} is larger than {} MB", url, MAX_DOWNLOAD_BYTES / (1024 * 1024));
+        }
+        dll_bytes.extend_from_slice(&chunk);
+    }
+
+    tracing::info!("Downloaded {} bytes from {}", dll_bytes.len(), url);
+    Ok(dll_bytes)
+}
+
+/// The ID a DLL is stored under
+pub fn hash_dll(dll_bytes: &[u8]) -> String {
+    Sha256::digest(dll_bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openzt-dll-library-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_add_and_reload() {
+        let dir = library_dir();
+        let mut library = DllLibrary::new(&dir);
+        assert_eq!(library.load().unwrap(), 0);
+
+        let (entry, added) = library.add("nightly", b"MZ nightly").unwrap();
+        assert!(added);
+        assert_eq!(entry.id, hash_dll(b"MZ nightly"));
+        assert_eq!(entry.size, 10);
+
+        // The same build is stored once, under its first name
+        let (again, added) = library.add("nightly-copy", b"MZ nightly").unwrap();
+        assert!(!added);
+        assert_eq!(again, entry);
+
+        library.add("release", b"MZ release").unwrap();
+        let mut reloaded = DllLibrary::new(&dir);
+        assert_eq!(reloaded.load().unwrap(), 2);
+        assert_eq!(reloaded.get(&entry.id), Some(&entry));
+        assert_eq!(std::fs::read(reloaded.dll_path(&entry.id)).unwrap(), b"MZ nightly");
+
+        assert!(reloaded.remove(&entry.id).unwrap());
+        assert!(!reloaded.remove(&entry.id).unwrap());
+        assert_eq!(reloaded.list().len(), 1);
+        assert!(!reloaded.dll_path(&entry.id).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cached_download() {
+        let dir = library_dir().with_extension("downloads");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut library = DllLibrary::new(&dir);
+        let url = "https://github.com/openzt1/openzt/releases/latest/download/openzt.dll";
+        let hour = Duration::from_secs(3600);
+
+        // Downloading a DLL that was uploaded before records where it came from
+        library.add("uploaded", b"MZ old").unwrap();
+        let old = library.add_download(url, b"MZ old").unwrap();
+        assert_eq!(old.name, "uploaded");
+        assert_eq!(library.cached_download(url, hour), Some(&old));
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let new = library.add_download(url, b"MZ new").unwrap();
+        assert_eq!(library.cached_download(url, hour), Some(&new));
+        assert_eq!(library.cached_download(url, Duration::ZERO), None);
+        assert_eq!(library.cached_download("https://example.com/openzt.dll", hour), None);
+
+        let mut reloaded = DllLibrary::new(&dir);
+        reloaded.load().unwrap();
+        assert_eq!(reloaded.cached_download(url, hour), Some(&new));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
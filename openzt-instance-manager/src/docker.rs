@@ -4,23 +4,111 @@ use bollard::{
         Config as ContainerConfig, CreateContainerOptions, RemoveContainerOptions,
         StartContainerOptions, StopContainerOptions, RestartContainerOptions,
         LogsOptions, ListContainersOptions, InspectContainerOptions, LogOutput,
+        RenameContainerOptions,
     },
     image::CreateImageOptions,
-    service::{PortBinding, ContainerSummary, ContainerInspectResponse},
+    service::{PortBinding, ContainerSummary, ContainerInspectResponse, HostConfig, MountPoint, MountPointTypeEnum},
     Docker,
 };
 use chrono::{DateTime, Utc};
 use futures_util::stream::{Stream, StreamExt};
 use std::collections::HashMap;
 use std::io::Write;
+use std::path::PathBuf;
 use std::pin::Pin;
 
-use crate::instance::{AppLogType, InstanceConfig, InstanceStatus};
+use crate::instance::{AppLogType, InstanceConfig, InstanceExit, InstanceStatus, SidecarSpec};
+use crate::recording::{self, RECORDINGS_MOUNT};
+use crate::test_run::{ModFile, RESULTS_MOUNT};
+
+/// Label on sidecar containers with the ID of their instance
+pub const SIDECAR_OF_LABEL: &str = "openzt.sidecar_of";
+
+/// Label on sidecar containers with the sidecar's name
+pub const SIDECAR_NAME_LABEL: &str = "openzt.sidecar";
+
+/// Zoo Tycoon's directory inside the container
+const GAME_DIR: &str = "/home/wineuser/.wine/drive_c/Program Files (x86)/Microsoft Games/Zoo Tycoon";
 
 pub struct DockerManager {
     docker: Docker,
 }
 
+/// What is mounted into a container and set in its environment
+#[derive(Debug, Clone, Default)]
+pub struct ContainerSetup {
+    /// Host path of the DLL, mounted read-only as res-openzt.dll
+    pub dll_path: String,
+    /// Host directory mounted read-only as the game's mods directory
+    pub mods_dir: Option<String>,
+    /// Host directory mounted writable at `test-results` in the game directory
+    pub results_dir: Option<String>,
+    /// Host directory mounted writable at `/recordings` for display recordings
+    pub recordings_dir: Option<String>,
+    /// Environment variables as `NAME=value`
+    pub env: Vec<String>,
+}
+
+/// Line selection for log requests
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Number of trailing lines to return (`None` = all available)
+    pub tail: Option<u32>,
+    /// Only return lines at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only return lines before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Prefix Docker log lines with their RFC 3339 timestamp
+    pub timestamps: bool,
+}
+
+impl LogFilter {
+    /// Value for Docker's `tail` option
+    fn docker_tail(&self) -> String {
+        self.tail.map(|n| n.to_string()).unwrap_or_else(|| "all".to_string())
+    }
+
+    /// Whether a time window is applied
+    fn has_time_window(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    /// Filter application log text by the leading timestamp on each line, then apply `tail`
+    ///
+    /// Lines without a parsable timestamp (e.g. wrapped messages) follow the
+    /// decision made for the preceding timestamped line.
+    pub fn apply_to_text(&self, text: &str) -> String {
+        let mut keep = true;
+        let mut lines: Vec<&str> = Vec::new();
+
+        for line in text.lines() {
+            if self.has_time_window()
+                && let Some(ts) = line
+                    .split_whitespace()
+                    .next()
+                    .and_then(|token| DateTime::parse_from_rfc3339(token).ok())
+            {
+                let ts = ts.with_timezone(&Utc);
+                keep = self.since.is_none_or(|since| ts >= since)
+                    && self.until.is_none_or(|until| ts < until);
+            }
+            if keep {
+                lines.push(line);
+            }
+        }
+
+        let start = match self.tail {
+            Some(n) => lines.len().saturating_sub(n as usize),
+            None => 0,
+        };
+        let mut output = lines[start..].join("\n");
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output
+    }
+}
+
 impl DockerManager {
     pub fn new() -> Result<Self> {
         let docker = Docker::connect_with_local_defaults()
@@ -71,8 +159,8 @@ impl DockerManager {
         image: &str,
         vnc_port: u16,
         console_port: u16,
-        dll_path: &str,
         instance_config: &InstanceConfig,
+        setup: &ContainerSetup,
     ) -> Result<String> {
         let options = Some(CreateContainerOptions {
             name: name.to_string(),
@@ -86,20 +174,8 @@ impl DockerManager {
 
         // Build port bindings
         let mut port_bindings = HashMap::new();
-        port_bindings.insert(
-            "5901/tcp".to_string(),
-            Some(vec![PortBinding {
-                host_ip: None,
-                host_port: Some(vnc_port.to_string()),
-            }]),
-        );
-        port_bindings.insert(
-            "8080/tcp".to_string(),
-            Some(vec![PortBinding {
-                host_ip: None,
-                host_port: Some(console_port.to_string()),
-            }]),
-        );
+        port_bindings.insert("5901/tcp".to_string(), Some(vec![host_port_binding(vnc_port)]));
+        port_bindings.insert("8080/tcp".to_string(), Some(vec![host_port_binding(console_port)]));
 
         // Build labels for persistence
         let mut labels = HashMap::new();
@@ -107,24 +183,48 @@ impl DockerManager {
         if let Some(cpulimit) = instance_config.cpulimit {
             labels.insert("openzt.cpulimit".to_string(), cpulimit.to_string());
         }
+        if let Some(image) = &instance_config.image {
+            labels.insert("openzt.image".to_string(), image.clone());
+        }
+        if let Some(memory_mb) = instance_config.memory_mb {
+            labels.insert("openzt.memory_mb".to_string(), memory_mb.to_string());
+        }
+        if !instance_config.env.is_empty() {
+            labels.insert("openzt.env".to_string(), serde_json::to_string(&instance_config.env)?);
+        }
+        if !instance_config.sidecars.is_empty() {
+            labels.insert("openzt.sidecars".to_string(), serde_json::to_string(&instance_config.sidecars)?);
+        }
+
+        let mut env = vec!["VNC_SERVER=yes".to_string()];
+        env.extend(instance_config.env.iter().map(|(name, value)| format!("{}={}", name, value)));
+        env.extend(setup.env.iter().cloned());
+
+        let mut binds = vec![format!("{}:{}/res-openzt.dll:ro", setup.dll_path, GAME_DIR)];
+        if let Some(mods_dir) = &setup.mods_dir {
+            binds.push(format!("{}:{}/mods:ro", mods_dir, GAME_DIR));
+        }
+        if let Some(results_dir) = &setup.results_dir {
+            binds.push(format!("{}:{}/{}", results_dir, GAME_DIR, RESULTS_MOUNT));
+        }
+        if let Some(recordings_dir) = &setup.recordings_dir {
+            binds.push(format!("{}:{}", recordings_dir, RECORDINGS_MOUNT));
+        }
 
         let config = ContainerConfig {
             image: Some(image.to_string()),
             hostname: Some(name.to_string()),
             labels: Some(labels),
-            env: Some(vec![
-                "VNC_SERVER=yes".to_string(),
-            ]),
+            env: Some(env),
             exposed_ports: Some(exposed_ports),
-            host_config: Some(bollard::service::HostConfig {
+            host_config: Some(HostConfig {
                 port_bindings: Some(port_bindings),
-                binds: Some(vec![
-                    format!("{}:/home/wineuser/.wine/drive_c/Program Files (x86)/Microsoft Games/Zoo Tycoon/res-openzt.dll:ro", dll_path),
-                ]),
+                binds: Some(binds),
                 ipc_mode: Some("host".to_string()),
                 // CPU limits (equivalent to --cpus=<value>)
                 nano_cpus: instance_config.cpulimit
                     .map(|cores| (cores * 1_000_000_000.0) as i64),
+                memory: instance_config.memory_mb.map(|mb| (mb * 1024 * 1024) as i64),
                 ..Default::default()
             }),
             ..Default::default()
@@ -134,6 +234,46 @@ impl DockerManager {
         Ok(result.id)
     }
 
+    /// Create a sidecar container in the network namespace of an instance's game container
+    ///
+    /// It is named after the game container and labelled with the instance's ID, which
+    /// is how recovery finds it.
+    pub async fn create_sidecar(
+        &self,
+        instance_id: &str,
+        container_name: &str,
+        game_container_id: &str,
+        spec: &SidecarSpec,
+    ) -> Result<String> {
+        self.ensure_image(&spec.image).await?;
+
+        let options = Some(CreateContainerOptions {
+            name: format!("{}-{}", container_name, spec.name),
+            platform: None,
+        });
+        let labels = HashMap::from([
+            (SIDECAR_OF_LABEL.to_string(), instance_id.to_string()),
+            (SIDECAR_NAME_LABEL.to_string(), spec.name.clone()),
+        ]);
+        let config = ContainerConfig {
+            image: Some(spec.image.clone()),
+            cmd: (!spec.command.is_empty()).then(|| spec.command.clone()),
+            env: Some(spec.env.iter().map(|(name, value)| format!("{}={}", name, value)).collect()),
+            labels: Some(labels),
+            host_config: Some(HostConfig {
+                network_mode: Some(format!("container:{}", game_container_id)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let result = self.docker
+            .create_container(options, config)
+            .await
+            .with_context(|| format!("Failed to create sidecar {}", spec.name))?;
+        Ok(result.id)
+    }
+
     pub async fn start_container(&self, container_id: &str) -> Result<()> {
         self.docker
             .start_container(container_id, None::<StartContainerOptions<String>>)
@@ -168,6 +308,102 @@ impl DockerManager {
         Ok(())
     }
 
+    /// Start recording the container's display to `name` in its recordings directory
+    ///
+    /// ffmpeg keeps running in the background until [`DockerManager::stop_recording`].
+    pub async fn start_recording(&self, container_id: &str, name: &str, framerate: u32) -> Result<()> {
+        let exec_options = bollard::exec::CreateExecOptions {
+            cmd: Some(recording::start_command(name, framerate)),
+            ..Default::default()
+        };
+        let exec = self.docker
+            .create_exec(container_id, exec_options)
+            .await
+            .context("Failed to create exec instance")?;
+        self.docker
+            .start_exec(&exec.id, Some(bollard::exec::StartExecOptions { detach: true, ..Default::default() }))
+            .await
+            .context("Failed to start recording")?;
+        Ok(())
+    }
+
+    /// Stop recording to `name`, returning once ffmpeg has finished writing it
+    pub async fn stop_recording(&self, container_id: &str, name: &str) -> Result<()> {
+        let exec_options = bollard::exec::CreateExecOptions {
+            cmd: Some(recording::stop_command(name)),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            ..Default::default()
+        };
+        let exec = self.docker
+            .create_exec(container_id, exec_options)
+            .await
+            .context("Failed to create exec instance")?;
+        if let bollard::exec::StartExecResults::Attached { mut output, .. } = self.docker
+            .start_exec(&exec.id, None)
+            .await
+            .context("Failed to stop recording")?
+        {
+            // The exec ends when ffmpeg has exited
+            while output.next().await.is_some() {}
+        }
+        Ok(())
+    }
+
+    /// IDs of the server's containers as they exit, from Docker's `die` events
+    ///
+    /// `oom` events are only logged: Docker reports the container's `die` right after,
+    /// and [`DockerManager::container_exit`] tells whether it was killed for its memory.
+    pub fn container_exits(&self) -> Pin<Box<dyn Stream<Item = Result<String>> + Send>> {
+        let filters = HashMap::from([
+            ("type".to_string(), vec!["container".to_string()]),
+            ("event".to_string(), vec!["die".to_string(), "oom".to_string()]),
+            ("label".to_string(), vec!["openzt.managed=true".to_string()]),
+        ]);
+        let options = bollard::system::EventsOptions::<String> {
+            filters,
+            ..Default::default()
+        };
+
+        Box::pin(self.docker.events(Some(options)).filter_map(|result| async move {
+            let event = match result {
+                Ok(event) => event,
+                Err(e) => return Some(Err(anyhow!("Docker event error: {}", e))),
+            };
+            let container_id = event.actor.and_then(|actor| actor.id)?;
+            match event.action.as_deref() {
+                Some("die") => Some(Ok(container_id)),
+                Some("oom") => {
+                    tracing::warn!("Container {} ran out of memory", container_id);
+                    None
+                }
+                _ => None,
+            }
+        }))
+    }
+
+    /// How a container exited, `None` if it is running
+    pub async fn container_exit(&self, container_id: &str) -> Result<Option<InstanceExit>> {
+        let inspect = self.docker
+            .inspect_container(container_id, None::<InspectContainerOptions>)
+            .await
+            .context("Failed to inspect container")?;
+        let state = inspect.state.ok_or_else(|| anyhow!("Missing state in inspect response"))?;
+        if state.running == Some(true) {
+            return Ok(None);
+        }
+
+        let exited_at = state.finished_at.as_deref()
+            .and_then(|finished_at| DateTime::parse_from_rfc3339(finished_at).ok())
+            .map(|finished_at| finished_at.with_timezone(&Utc))
+            .unwrap_or_else(Utc::now);
+        Ok(Some(InstanceExit {
+            exit_code: state.exit_code.unwrap_or_default(),
+            oom_killed: state.oom_killed.unwrap_or_default(),
+            exited_at,
+        }))
+    }
+
     pub async fn stop_and_remove_container(&self, container_id: &str) -> Result<()> {
         let options = RemoveContainerOptions {
             force: true,
@@ -185,12 +421,15 @@ impl DockerManager {
     pub async fn get_container_logs(
         &self,
         container_id: &str,
-        tail_lines: u32,
+        filter: &LogFilter,
     ) -> Result<String> {
         let options = LogsOptions::<String> {
             stdout: true,
             stderr: true,
-            tail: tail_lines.to_string(),
+            tail: filter.docker_tail(),
+            since: filter.since.map(|t| t.timestamp()).unwrap_or(0),
+            until: filter.until.map(|t| t.timestamp()).unwrap_or(0),
+            timestamps: filter.timestamps,
             ..Default::default()
         };
 
@@ -220,15 +459,20 @@ impl DockerManager {
     }
 
     /// Stream container logs as an async stream for SSE
+    ///
+    /// `filter.tail` selects how much history is sent before following;
+    /// `until` is ignored since the stream follows new output.
     pub fn stream_container_logs(
         &self,
         container_id: &str,
-        tail_lines: u32,
+        filter: &LogFilter,
     ) -> Pin<Box<dyn Stream<Item = Result<String>> + Send>> {
         let options = LogsOptions::<String> {
             stdout: true,
             stderr: true,
-            tail: tail_lines.to_string(),
+            tail: filter.docker_tail(),
+            since: filter.since.map(|t| t.timestamp()).unwrap_or(0),
+            timestamps: filter.timestamps,
             follow: true,
             ..Default::default()
         };
@@ -250,19 +494,22 @@ impl DockerManager {
         &self,
         container_id: &str,
         log_type: AppLogType,
-        tail_lines: u32,
+        filter: &LogFilter,
     ) -> Result<String> {
-        let log_path = format!(
-            "/home/wineuser/.wine/drive_c/Program Files (x86)/Microsoft Games/Zoo Tycoon/{}",
-            log_type.filename()
-        );
+        let log_path = format!("{}/{}", GAME_DIR, log_type.filename());
+
+        // Read the whole file when filtering by time; the tail is applied afterwards
+        let tail_arg = match filter.tail {
+            Some(n) if !filter.has_time_window() => n.to_string(),
+            _ => "+1".to_string(),
+        };
 
         // Use docker exec to run tail command inside container
         let exec_options = bollard::exec::CreateExecOptions {
             cmd: Some(vec![
                 "tail".to_string(),
                 "-n".to_string(),
-                tail_lines.to_string(),
+                tail_arg,
                 log_path.clone(),
             ]),
             attach_stdout: Some(true),
@@ -319,24 +566,31 @@ impl DockerManager {
             }
         }
 
+        if filter.has_time_window() {
+            result = filter.apply_to_text(&result);
+        }
+
         Ok(result)
     }
 
     /// Stream application logs using file following
+    ///
+    /// `tail` selects how many existing lines are sent before following
+    /// (`None` = all available).
     pub fn stream_app_logs(
         &self,
         container_id: &str,
         log_type: AppLogType,
+        tail: Option<u32>,
     ) -> Pin<Box<dyn Stream<Item = Result<String>> + Send>> {
-        let log_path = format!(
-            "/home/wineuser/.wine/drive_c/Program Files (x86)/Microsoft Games/Zoo Tycoon/{}",
-            log_type.filename()
-        );
+        let log_path = format!("{}/{}", GAME_DIR, log_type.filename());
 
         // Use docker exec with tail -f for streaming
         let exec_options = bollard::exec::CreateExecOptions {
             cmd: Some(vec![
                 "tail".to_string(),
+                "-n".to_string(),
+                tail.map(|n| n.to_string()).unwrap_or_else(|| "+1".to_string()),
                 "-f".to_string(),
                 log_path,
             ]),
@@ -393,24 +647,32 @@ impl DockerManager {
     }
 }
 
-/// Write base64-encoded DLL to a temporary file
-pub fn write_dll_to_temp(instance_id: &str, dll_base64: &str) -> Result<String> {
+/// Decode a base64-encoded DLL and check that it looks like one
+pub fn decode_dll(dll_base64: &str) -> Result<Vec<u8>> {
     let dll_bytes = base64::Engine::decode(&base64::prelude::BASE64_STANDARD, dll_base64)
         .context("Failed to decode base64 DLL")?;
+    validate_dll(&dll_bytes)?;
+    Ok(dll_bytes)
+}
 
-    // Validate PE header (basic check for Windows DLL)
+/// Validate PE header (basic check for Windows DLL)
+pub fn validate_dll(dll_bytes: &[u8]) -> Result<()> {
     if dll_bytes.len() < 2 {
         return Err(anyhow!("DLL data is too short"));
     }
     if &dll_bytes[0..2] != b"MZ" {
         return Err(anyhow!("Invalid DLL format: missing MZ header"));
     }
+    Ok(())
+}
 
+/// Write DLL to a temporary file
+pub fn write_dll_to_temp(instance_id: &str, dll_bytes: &[u8]) -> Result<String> {
     let temp_path = format!("/tmp/openzt-{}.dll", instance_id);
 
     let mut file = std::fs::File::create(&temp_path)
         .context("Failed to create temp DLL file")?;
-    file.write_all(&dll_bytes)
+    file.write_all(dll_bytes)
         .context("Failed to write DLL data")?;
 
     tracing::info!("Wrote DLL to {}", temp_path);
@@ -427,6 +689,88 @@ pub fn cleanup_dll_temp(instance_id: &str) {
     }
 }
 
+/// Directory an instance's mods are installed in
+fn instance_mods_dir(instance_id: &str) -> PathBuf {
+    PathBuf::from(format!("/tmp/openzt-mods-{}", instance_id))
+}
+
+/// Copy `(path, file name)` pairs of .ztd files to a directory for an instance and write
+/// the uploaded `(file name, contents)` pairs next to them, returning its path
+///
+/// Instances get copies so mods can be removed from the repository while they run.
+pub fn write_instance_mods(
+    instance_id: &str,
+    mods: &[(PathBuf, String)],
+    uploads: &[(String, Vec<u8>)],
+) -> Result<String> {
+    let dir = instance_mods_dir(instance_id);
+    std::fs::create_dir_all(&dir).context("Failed to create instance mods directory")?;
+    for (path, file_name) in mods {
+        std::fs::copy(path, dir.join(file_name)).with_context(|| format!("Failed to copy mod {}", file_name))?;
+    }
+    for (file_name, data) in uploads {
+        std::fs::write(dir.join(file_name), data).with_context(|| format!("Failed to write mod {}", file_name))?;
+    }
+
+    tracing::info!(
+        "Installed {} mod(s) for instance {} in {}",
+        mods.len() + uploads.len(), instance_id, dir.display()
+    );
+    Ok(dir.to_string_lossy().into_owned())
+}
+
+/// Clean up an instance's mods, if it has any
+pub fn cleanup_instance_mods(instance_id: &str) {
+    let dir = instance_mods_dir(instance_id);
+    if !dir.exists() {
+        return;
+    }
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        tracing::warn!("Failed to remove instance mods directory {}: {}", dir.display(), e);
+    }
+}
+
+/// Directory for a test run's mods and results on the host
+pub fn test_run_dir(run_id: &str) -> PathBuf {
+    PathBuf::from(format!("/tmp/openzt-testrun-{}", run_id))
+}
+
+/// Write a test run's mods to `<dir>/mods` and create `<dir>/results`, returning both paths
+pub fn write_test_run_files(run_id: &str, mods: &[ModFile]) -> Result<(String, String)> {
+    let dir = test_run_dir(run_id);
+    let mods_dir = dir.join("mods");
+    let results_dir = dir.join("results");
+    std::fs::create_dir_all(&mods_dir).context("Failed to create test run mods directory")?;
+    std::fs::create_dir_all(&results_dir).context("Failed to create test run results directory")?;
+
+    // The game runs as wineuser in the container and has to be able to write its results
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&results_dir, std::fs::Permissions::from_mode(0o777))
+            .context("Failed to make test run results directory writable")?;
+    }
+
+    for mod_file in mods {
+        mod_file.validate_name().map_err(|e| anyhow!(e))?;
+        let data = base64::Engine::decode(&base64::prelude::BASE64_STANDARD, &mod_file.data)
+            .with_context(|| format!("Failed to decode base64 mod {}", mod_file.name))?;
+        std::fs::write(mods_dir.join(&mod_file.name), data)
+            .with_context(|| format!("Failed to write mod {}", mod_file.name))?;
+    }
+
+    tracing::info!("Wrote {} mod(s) for test run {} to {}", mods.len(), run_id, dir.display());
+    Ok((mods_dir.to_string_lossy().into_owned(), results_dir.to_string_lossy().into_owned()))
+}
+
+/// Clean up a test run's mods and results
+pub fn cleanup_test_run_files(run_id: &str) {
+    let dir = test_run_dir(run_id);
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        tracing::warn!("Failed to remove test run directory {}: {}", dir.display(), e);
+    }
+}
+
 /// Holds information extracted from a container during recovery
 #[derive(Debug)]
 pub struct RecoveredInstanceInfo {
@@ -436,6 +780,57 @@ pub struct RecoveredInstanceInfo {
     pub status: InstanceStatus,
     pub created_at: DateTime<Utc>,
     pub config: InstanceConfig,
+    /// Labels the instance's config couldn't be read from, see [`incomplete_metadata`]
+    pub incomplete: Vec<String>,
+}
+
+/// Binding of a container port to `port` on the host
+fn host_port_binding(port: u16) -> PortBinding {
+    PortBinding {
+        host_ip: None,
+        // Port 0 lets Docker pick a free host port
+        host_port: (port != 0).then(|| port.to_string()),
+    }
+}
+
+/// A container's binds plus its volumes mounted without one, such as the
+/// anonymous volumes of the image, so a copy of the container mounts the same data
+fn preserved_binds(mut binds: Vec<String>, mounts: &[MountPoint]) -> Vec<String> {
+    for mount in mounts {
+        if mount.typ != Some(MountPointTypeEnum::VOLUME) {
+            continue;
+        }
+        let (Some(name), Some(destination)) = (&mount.name, &mount.destination) else {
+            continue;
+        };
+        if !binds.iter().any(|bind| bind.split(':').nth(1) == Some(destination.as_str())) {
+            binds.push(format!("{}:{}", name, destination));
+        }
+    }
+    binds
+}
+
+/// What's missing or unreadable in the labels the server puts on its containers
+///
+/// Containers created by hand or by older versions lack them, so their config is
+/// only partly known.
+pub fn incomplete_metadata(labels: Option<&HashMap<String, String>>) -> Vec<String> {
+    let empty = HashMap::new();
+    let labels = labels.unwrap_or(&empty);
+    let mut incomplete = Vec::new();
+    if labels.get("openzt.managed").map(String::as_str) != Some("true") {
+        incomplete.push("missing label openzt.managed".to_string());
+    }
+    let unreadable = |name: &str, readable: fn(&str) -> bool| {
+        labels.get(name).is_some_and(|value| !readable(value)).then(|| format!("unreadable label {}", name))
+    };
+    incomplete.extend(unreadable("openzt.cpulimit", |s| s.parse::<f64>().is_ok()));
+    incomplete.extend(unreadable("openzt.memory_mb", |s| s.parse::<u64>().is_ok()));
+    incomplete.extend(unreadable("openzt.env", |s| {
+        serde_json::from_str::<std::collections::BTreeMap<String, String>>(s).is_ok()
+    }));
+    incomplete.extend(unreadable("openzt.sidecars", |s| serde_json::from_str::<Vec<SidecarSpec>>(s).is_ok()));
+    incomplete
 }
 
 impl DockerManager {
@@ -477,15 +872,27 @@ impl DockerManager {
         let status = self.map_docker_status(&inspect.state.ok_or_else(|| anyhow!("Missing state"))?);
         let created_at = self.parse_created_timestamp(inspect.created.as_deref().ok_or_else(|| anyhow!("Missing created timestamp"))?)?;
 
-        // Extract cpulimit from labels (stored during creation)
-        let config = InstanceConfig {
-            cpulimit: inspect.config.as_ref()
+        // Extract the config from labels (stored during creation)
+        let label = |name: &str| {
+            inspect.config.as_ref()
                 .and_then(|c| c.labels.as_ref())
-                .and_then(|labels| labels.get("openzt.cpulimit"))
-                .and_then(|s| s.parse::<f64>().ok()),
+                .and_then(|labels| labels.get(name))
+        };
+        let config = InstanceConfig {
+            cpulimit: label("openzt.cpulimit").and_then(|s| s.parse::<f64>().ok()),
+            image: label("openzt.image").cloned(),
+            memory_mb: label("openzt.memory_mb").and_then(|s| s.parse::<u64>().ok()),
+            env: label("openzt.env")
+                .and_then(|s| serde_json::from_str(s).ok())
+                .unwrap_or_default(),
+            sidecars: label("openzt.sidecars")
+                .and_then(|s| serde_json::from_str(s).ok())
+                .unwrap_or_default(),
             ..Default::default()
         };
 
+        let incomplete = incomplete_metadata(inspect.config.as_ref().and_then(|c| c.labels.as_ref()));
+
         Ok(RecoveredInstanceInfo {
             container_id: container_id.to_string(),
             vnc_port,
@@ -493,9 +900,85 @@ impl DockerManager {
             status,
             created_at,
             config,
+            incomplete,
         })
     }
 
+    /// Recreate a stopped game container with new host ports
+    ///
+    /// Docker can't change the ports of an existing container, so it is replaced by
+    /// one with the same name, config, labels, binds and volumes. Returns the new
+    /// container's ID.
+    pub async fn republish_ports(&self, container_id: &str, vnc_port: u16, console_port: u16) -> Result<String> {
+        self.recreate_container(container_id, |host_config| {
+            let port_bindings = host_config.port_bindings.get_or_insert_with(HashMap::new);
+            port_bindings.insert("5901/tcp".to_string(), Some(vec![host_port_binding(vnc_port)]));
+            port_bindings.insert("8080/tcp".to_string(), Some(vec![host_port_binding(console_port)]));
+        })
+        .await
+    }
+
+    /// Recreate a stopped sidecar in the network namespace of its recreated game container
+    pub async fn reattach_sidecar(&self, container_id: &str, game_container_id: &str) -> Result<String> {
+        self.recreate_container(container_id, |host_config| {
+            host_config.network_mode = Some(format!("container:{}", game_container_id));
+        })
+        .await
+    }
+
+    /// Replace a stopped container by a copy whose host config is changed by `change`
+    async fn recreate_container(&self, container_id: &str, change: impl FnOnce(&mut HostConfig)) -> Result<String> {
+        let inspect = self.docker
+            .inspect_container(container_id, None::<InspectContainerOptions>)
+            .await?;
+        let name = inspect.name.as_deref()
+            .map(|name| name.trim_start_matches('/').to_string())
+            .ok_or_else(|| anyhow!("Container missing name"))?;
+
+        let mut host_config = inspect.host_config.unwrap_or_default();
+        change(&mut host_config);
+        let binds = host_config.binds.take().unwrap_or_default();
+        host_config.binds = Some(preserved_binds(binds, inspect.mounts.as_deref().unwrap_or_default()));
+        let mut config: ContainerConfig<String> = inspect.config.ok_or_else(|| anyhow!("Missing config"))?.into();
+        config.host_config = Some(host_config);
+
+        // The old container keeps its name until the new one exists
+        let replaced_name = format!("{}-replaced", name);
+        self.docker
+            .rename_container(container_id, RenameContainerOptions { name: replaced_name.as_str() })
+            .await
+            .context("Failed to rename container")?;
+
+        let options = Some(CreateContainerOptions { name: name.clone(), platform: None });
+        match self.docker.create_container(options, config).await {
+            Ok(result) => {
+                // Without `v`, so the volumes the new container uses are kept
+                let options = RemoveContainerOptions { force: true, ..Default::default() };
+                if let Err(e) = self.docker.remove_container(container_id, Some(options)).await {
+                    tracing::warn!("Failed to remove replaced container {}: {}", replaced_name, e);
+                }
+                Ok(result.id)
+            }
+            Err(e) => {
+                if let Err(e) = self.docker
+                    .rename_container(container_id, RenameContainerOptions { name: name.as_str() })
+                    .await
+                {
+                    tracing::warn!("Failed to rename container {} back to {}: {}", replaced_name, name, e);
+                }
+                Err(anyhow!(e).context("Failed to recreate container"))
+            }
+        }
+    }
+
+    /// Host ports currently bound to a container's VNC and console ports
+    pub async fn container_ports(&self, container_id: &str) -> Result<(u16, u16)> {
+        let inspect = self.docker
+            .inspect_container(container_id, None::<InspectContainerOptions>)
+            .await?;
+        self.extract_ports(&inspect)
+    }
+
     fn extract_ports(&self, inspect: &ContainerInspectResponse) -> Result<(u16, u16)> {
         // Try NetworkSettings first (for running containers)
         if let Some(network_settings) = &inspect.network_settings {
@@ -559,7 +1042,7 @@ impl DockerManager {
                     Some(status) => match status.as_ref() {
                         "exited" | "paused" => InstanceStatus::Stopped,
                         "created" => InstanceStatus::Creating,
-                        s => InstanceStatus::Error(format!("Container state: {}", s)),
+                        s => InstanceStatus::Unknown(format!("Container state: {}", s)),
                     },
                     None => InstanceStatus::Stopped,
                 }
@@ -611,3 +1094,86 @@ impl DockerManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_incomplete_metadata() {
+        let mut labels = HashMap::from([
+            ("openzt.managed".to_string(), "true".to_string()),
+            ("openzt.cpulimit".to_string(), "0.5".to_string()),
+            ("openzt.env".to_string(), r#"{"WINEDEBUG":"-all"}"#.to_string()),
+        ]);
+        assert!(incomplete_metadata(Some(&labels)).is_empty());
+
+        labels.insert("openzt.memory_mb".to_string(), "lots".to_string());
+        assert_eq!(incomplete_metadata(Some(&labels)), vec!["unreadable label openzt.memory_mb"]);
+        assert_eq!(incomplete_metadata(None), vec!["missing label openzt.managed"]);
+    }
+
+    #[test]
+    fn test_preserved_binds() {
+        let mount = |typ, name: &str, destination: &str| MountPoint {
+            typ: Some(typ),
+            name: Some(name.to_string()),
+            destination: Some(destination.to_string()),
+            ..Default::default()
+        };
+        let binds = vec!["/tmp/openzt-dll/res-openzt.dll:/game/res-openzt.dll:ro".to_string()];
+        let mounts = [
+            mount(MountPointTypeEnum::BIND, "", "/game/res-openzt.dll"),
+            mount(MountPointTypeEnum::VOLUME, "3f2a9c", "/home/wineuser/.wine"),
+            mount(MountPointTypeEnum::TMPFS, "", "/tmp"),
+        ];
+        assert_eq!(
+            preserved_binds(binds.clone(), &mounts),
+            vec![binds[0].clone(), "3f2a9c:/home/wineuser/.wine".to_string()]
+        );
+
+        // Volumes that are already bound aren't added twice
+        let binds = vec!["saves:/home/wineuser/.wine".to_string()];
+        assert_eq!(preserved_binds(binds.clone(), &[mount(MountPointTypeEnum::VOLUME, "saves", "/home/wineuser/.wine")]), binds);
+    }
+
+    const APP_LOG: &str = "\
+2025-06-01T10:00:00.000000Z  INFO openzt: first
+2025-06-01T11:00:00.000000Z  INFO openzt: second
+  continuation of second
+2025-06-01T12:00:00.000000Z  INFO openzt: third
+";
+
+    #[test]
+    fn test_apply_to_text_since_until() {
+        let filter = LogFilter {
+            since: Some(ts("2025-06-01T10:30:00Z")),
+            until: Some(ts("2025-06-01T12:00:00Z")),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.apply_to_text(APP_LOG),
+            "2025-06-01T11:00:00.000000Z  INFO openzt: second\n  continuation of second\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_to_text_tail() {
+        let filter = LogFilter {
+            tail: Some(1),
+            since: Some(ts("2025-06-01T00:00:00Z")),
+            ..Default::default()
+        };
+        assert_eq!(filter.apply_to_text(APP_LOG), "2025-06-01T12:00:00.000000Z  INFO openzt: third\n");
+    }
+
+    #[test]
+    fn test_docker_tail() {
+        assert_eq!(LogFilter { tail: Some(50), ..Default::default() }.docker_tail(), "50");
+        assert_eq!(LogFilter::default().docker_tail(), "all");
+    }
+}
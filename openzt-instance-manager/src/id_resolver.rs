@@ -8,6 +8,9 @@
 //! Short IDs of any length (1+ characters) are supported as long as they uniquely
 //! identify an instance. When multiple instances share the same prefix, an
 //! ambiguous match error is returned with helpful guidance.
+//!
+//! Instances named with `openzt rename` are also found by their name, which takes
+//! precedence over an ID prefix it happens to look like.
 
 use crate::client::InstanceClient;
 use crate::instance::InstanceDetails;
@@ -33,11 +36,21 @@ pub enum ResolutionError {
 }
 
 impl ResolutionError {
+    /// Get the exit code category for this error
+    pub fn error_code(&self) -> crate::output::ErrorCode {
+        use crate::output::ErrorCode;
+        match self {
+            ResolutionError::NotFound(_) => ErrorCode::NotFound,
+            ResolutionError::Ambiguous { .. } => ErrorCode::AmbiguousId,
+            ResolutionError::ApiError(e) => ErrorCode::from_error(e),
+        }
+    }
+
     /// Get a user-friendly error message
     pub fn message(&self) -> String {
         match self {
             ResolutionError::NotFound(prefix) => {
-                format!("No instance found with ID prefix '{}'", prefix)
+                format!("No instance found with name or ID prefix '{}'", prefix)
             }
             ResolutionError::Ambiguous { prefix, matches } => {
                 format!("Ambiguous ID prefix '{}' matches {} instances", prefix, matches.len())
@@ -49,18 +62,20 @@ impl ResolutionError {
     }
 }
 
-/// Full UUID length
-const UUID_LENGTH: usize = 36;
-
-/// Resolve a short ID or full UUID to a full instance ID.
+/// Resolve a short ID, full UUID or instance name to a full instance ID.
 ///
-/// This function accepts both short ID prefixes (any length 1+) and full UUIDs.
-/// For short IDs, it fetches all instances and finds matches starting with the prefix.
+/// This function accepts short ID prefixes (any length 1+), full UUIDs and names.
+/// For short IDs and names, it fetches all instances and finds the one with that
+/// name, or else the matches starting with the prefix.
 /// Empty strings will match all instances and result in an ambiguous match error.
 ///
+/// The instance list is taken from the client's cache when available. If a
+/// disk-cached list fails to resolve the prefix, the list is refetched once in
+/// case the cache predates a newly created instance.
+///
 /// # Arguments
 /// * `client` - The API client to use for fetching instances
-/// * `input` - The user-provided ID (short prefix or full UUID)
+/// * `input` - The user-provided ID (short prefix, full UUID or name)
 ///
 /// # Returns
 /// * `Ok(String)` - The full instance ID UUID
@@ -83,28 +98,48 @@ pub async fn resolve_instance_id(
 ) -> Result<String, ResolutionError> {
     let input = input.trim();
 
-    // If it's a full UUID length, treat as exact match (passthrough)
-    if input.len() == UUID_LENGTH {
+    // If it's a full UUID, treat as exact match (passthrough); names can't be UUIDs
+    if uuid::Uuid::parse_str(input).is_ok() {
         return Ok(input.to_string());
     }
 
-    // Fetch all instances to find matches
+    // Fetch all instances (or reuse the cached list) to find matches
     let instances = client
-        .list_instances()
+        .list_instances_cached()
         .await
         .map_err(ResolutionError::ApiError)?;
 
+    match match_prefix(instances, input) {
+        Err(ResolutionError::NotFound(_) | ResolutionError::Ambiguous { .. }) if client.has_disk_cache() => {
+            // The on-disk list may be stale; retry against the live list
+            client.invalidate_cache();
+            let instances = client
+                .list_instances()
+                .await
+                .map_err(ResolutionError::ApiError)?;
+            match_prefix(instances, input)
+        }
+        result => result,
+    }
+}
+
+/// Find the instance named `prefix`, or else the single instance whose ID starts with it
+pub fn match_prefix(instances: Vec<InstanceDetails>, prefix: &str) -> Result<String, ResolutionError> {
+    if let Some(named) = instances.iter().find(|instance| instance.name.as_deref() == Some(prefix)) {
+        return Ok(named.id.clone());
+    }
+
     // Find instances that start with the prefix
     let matching_instances: Vec<InstanceDetails> = instances
         .into_iter()
-        .filter(|instance| instance.id.starts_with(input))
+        .filter(|instance| instance.id.starts_with(prefix))
         .collect();
 
     match matching_instances.len() {
-        0 => Err(ResolutionError::NotFound(input.to_string())),
+        0 => Err(ResolutionError::NotFound(prefix.to_string())),
         1 => Ok(matching_instances[0].id.clone()),
         _ => Err(ResolutionError::Ambiguous {
-            prefix: input.to_string(),
+            prefix: prefix.to_string(),
             matches: matching_instances,
         }),
     }
@@ -184,24 +219,9 @@ pub fn suggest_min_length(instances: &[InstanceDetails]) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::instance::InstanceConfig;
-    use chrono::Utc;
 
     fn create_test_instance(id: &str) -> InstanceDetails {
-        InstanceDetails {
-            id: id.to_string(),
-            container_id: "container-123".to_string(),
-            rdp_port: 13390,
-            console_port: 13391,
-            rdp_url: "rdp://localhost:13390".to_string(),
-            status: "running".to_string(),
-            created_at: Utc::now(),
-            config: InstanceConfig {
-                rdp_password: None,
-                wine_debug_level: None,
-                cpulimit: None,
-            },
-        }
+        InstanceDetails::test_fixture(id)
     }
 
     #[test]
@@ -256,7 +276,7 @@ mod tests {
     #[test]
     fn test_single_char_id_works() {
         // Single character 'a' should uniquely identify the 'a1b2...' instance
-        let instances = vec![
+        let instances = [
             create_test_instance("a1b2c3d4-5e6f-7a8b-9c0d-123456789abc"),
             create_test_instance("b2c3d4e5-6f7a-8b9c-0d1e-234567890bcd"),
         ];
@@ -274,7 +294,7 @@ mod tests {
     #[test]
     fn test_two_char_id_works() {
         // Two character 'a1' should uniquely identify the 'a1b2...' instance
-        let instances = vec![
+        let instances = [
             create_test_instance("a1b2c3d4-5e6f-7a8b-9c0d-123456789abc"),
             create_test_instance("a2b3c4d5-6f7a-8b9c-0d1e-234567890bcd"),
             create_test_instance("b2c3d4e5-6f7a-8b9c-0d1e-234567890bcd"),
@@ -289,10 +309,42 @@ mod tests {
         assert!(matching[0].id.starts_with("a1b2"));
     }
 
+    #[test]
+    fn test_match_prefix() {
+        let instances = vec![
+            create_test_instance("a1b2c3d4-5e6f-7a8b-9c0d-123456789abc"),
+            create_test_instance("a2b3c4d5-6f7a-8b9c-0d1e-234567890bcd"),
+        ];
+
+        assert_eq!(
+            match_prefix(instances.clone(), "a1").ok(),
+            Some("a1b2c3d4-5e6f-7a8b-9c0d-123456789abc".to_string())
+        );
+        assert!(matches!(match_prefix(instances.clone(), "a"), Err(ResolutionError::Ambiguous { .. })));
+        assert!(matches!(match_prefix(instances, "ff"), Err(ResolutionError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_match_name() {
+        let mut named = create_test_instance("a2b3c4d5-6f7a-8b9c-0d1e-234567890bcd");
+        named.name = Some("a1".to_string());
+        let instances = vec![create_test_instance("a1b2c3d4-5e6f-7a8b-9c0d-123456789abc"), named];
+
+        // A name wins over the ID prefix it looks like
+        assert_eq!(
+            match_prefix(instances.clone(), "a1").ok(),
+            Some("a2b3c4d5-6f7a-8b9c-0d1e-234567890bcd".to_string())
+        );
+        assert_eq!(
+            match_prefix(instances, "a1b").ok(),
+            Some("a1b2c3d4-5e6f-7a8b-9c0d-123456789abc".to_string())
+        );
+    }
+
     #[test]
     fn test_ambiguous_short_id() {
         // Single character 'a' should match multiple instances starting with 'a'
-        let instances = vec![
+        let instances = [
             create_test_instance("a1b2c3d4-5e6f-7a8b-9c0d-123456789abc"),
             create_test_instance("a2b3c4d5-6f7a-8b9c-0d1e-234567890bcd"),
         ];
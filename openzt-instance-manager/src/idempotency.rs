@@ -0,0 +1,160 @@
+//! Idempotency keys of create requests
+//!
+//! A `POST /api/instances` with an `Idempotency-Key` header claims the key for the
+//! instance it creates. Retrying the request with the same key and body returns that
+//! instance instead of creating another, for `[instances] idempotency_key_ttl_secs`.
+//! A key is released again if its request fails, so the retry can create the instance.
+//! Keys are kept in memory only.
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Header a create request's idempotency key is sent in
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Longest key accepted, keys are usually UUIDs
+pub const MAX_KEY_LENGTH: usize = 255;
+
+#[derive(Debug)]
+struct Claimed {
+    instance_id: String,
+    /// SHA-256 of the request the key was first used with
+    fingerprint: String,
+    claimed_at: DateTime<Utc>,
+    /// Whether the request that claimed the key has returned the instance
+    completed: bool,
+}
+
+/// What became of an earlier request with the same key
+#[derive(Debug)]
+pub enum Claim {
+    /// The key is new, the request creates the instance
+    New(IdempotencyGuard),
+    /// The instance created for the key
+    Completed(String),
+    /// A request with the key is still creating its instance
+    InProgress,
+    /// The key was used for a different request
+    Mismatch,
+}
+
+/// The idempotency keys of recent create requests
+#[derive(Debug, Clone)]
+pub struct IdempotencyKeys {
+    keys: Arc<Mutex<HashMap<String, Claimed>>>,
+    ttl: chrono::Duration,
+}
+
+/// SHA-256 of a request, as hex
+pub fn fingerprint(request: &[u8]) -> String {
+    Sha256::digest(request).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl IdempotencyKeys {
+    /// Keys that are forgotten `ttl_secs` after they were first used
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            keys: Arc::default(),
+            ttl: chrono::Duration::seconds(ttl_secs.min(i64::MAX as u64 / 1000) as i64),
+        }
+    }
+
+    /// Claim `key` for creating `instance_id` at `now`, unless an earlier request did
+    pub fn claim(&self, key: &str, fingerprint: &str, instance_id: &str, now: DateTime<Utc>) -> Claim {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        keys.retain(|_, claimed| now - claimed.claimed_at < self.ttl);
+
+        match keys.get(key) {
+            Some(claimed) if claimed.fingerprint != fingerprint => Claim::Mismatch,
+            Some(claimed) if claimed.completed => Claim::Completed(claimed.instance_id.clone()),
+            Some(_) => Claim::InProgress,
+            None => {
+                keys.insert(
+                    key.to_string(),
+                    Claimed {
+                        instance_id: instance_id.to_string(),
+                        fingerprint: fingerprint.to_string(),
+                        claimed_at: now,
+                        completed: false,
+                    },
+                );
+                Claim::New(IdempotencyGuard {
+                    keys: self.clone(),
+                    key: key.to_string(),
+                    completed: false,
+                })
+            }
+        }
+    }
+}
+
+/// A claimed key, released when dropped unless its instance was created
+#[must_use = "the key is released as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct IdempotencyGuard {
+    keys: IdempotencyKeys,
+    key: String,
+    completed: bool,
+}
+
+impl IdempotencyGuard {
+    /// Keep the key for the instance the request created
+    pub fn complete(mut self) {
+        self.completed = true;
+        let mut keys = self.keys.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(claimed) = keys.get_mut(&self.key) {
+            claimed.completed = true;
+        }
+    }
+}
+
+impl Drop for IdempotencyGuard {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.keys.keys.lock().unwrap_or_else(PoisonError::into_inner).remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_claims() {
+        let keys = IdempotencyKeys::new(3600);
+        let now = at("2026-10-16T17:00:00Z");
+        let Claim::New(guard) = keys.claim("retry-me", "abc", "instance-1", now) else {
+            panic!("key should be new");
+        };
+        assert!(matches!(keys.claim("retry-me", "abc", "instance-2", now), Claim::InProgress));
+        guard.complete();
+
+        match keys.claim("retry-me", "abc", "instance-2", now) {
+            Claim::Completed(instance_id) => assert_eq!(instance_id, "instance-1"),
+            claim => panic!("unexpected {:?}", claim),
+        }
+        assert!(matches!(keys.claim("retry-me", "def", "instance-2", now), Claim::Mismatch));
+
+        // Forgotten once the TTL is over
+        let later = at("2026-10-16T18:00:00Z");
+        assert!(matches!(keys.claim("retry-me", "def", "instance-2", later), Claim::New(_)));
+    }
+
+    #[test]
+    fn test_failed_request_releases_key() {
+        let keys = IdempotencyKeys::new(3600);
+        let now = at("2026-10-16T17:00:00Z");
+        let Claim::New(guard) = keys.claim("retry-me", "abc", "instance-1", now) else {
+            panic!("key should be new");
+        };
+        drop(guard);
+        assert!(matches!(keys.claim("retry-me", "abc", "instance-2", now), Claim::New(_)));
+    }
+}
@@ -1,5 +1,11 @@
+use crate::metrics::CreationTimings;
+use crate::ports::{PortCapacity, LOCAL_HOST};
+use crate::restart::RestartPolicy;
+use crate::schedule::Schedule;
+use crate::test_run::ModFile;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Instance {
@@ -10,43 +16,254 @@ pub struct Instance {
     pub status: InstanceStatus,
     pub created_at: DateTime<Utc>,
     pub config: InstanceConfig,
+    /// Recovered with ports outside the configured ranges
+    #[serde(default)]
+    pub ports_outside_range: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+    /// How the container last exited without the server stopping it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_exit: Option<InstanceExit>,
+    /// When the instance was restarted automatically after crashing, within the restart window
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auto_restarts: Vec<DateTime<Utc>>,
+    /// Containers running next to the game container, as declared in `config.sidecars`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sidecars: Vec<Sidecar>,
+    /// How long creating the instance took, phase by phase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation: Option<CreationTimings>,
+    /// When the instance was deleted into the trash, where it is kept stopped until purged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trashed_at: Option<DateTime<Utc>>,
+    /// Unique name the CLI accepts in place of the instance's ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
 }
 
+/// A sidecar container of an instance
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub name: String,
+    pub container_id: String,
+    pub status: InstanceStatus,
+}
+
+/// A container exit the server didn't cause, as reported by Docker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceExit {
+    pub exit_code: i64,
+    /// Killed for exceeding its memory limit
+    #[serde(default)]
+    pub oom_killed: bool,
+    pub exited_at: DateTime<Utc>,
+}
+
+impl InstanceExit {
+    pub fn crashed(&self) -> bool {
+        self.oom_killed || self.exit_code != 0
+    }
+
+    pub fn describe(&self) -> String {
+        if self.oom_killed {
+            format!("Killed for running out of memory (exit code {})", self.exit_code)
+        } else {
+            format!("Exited with code {}", self.exit_code)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "status", content = "message")]
 pub enum InstanceStatus {
     Creating,
+    /// The container is running, the game isn't answering on its console yet
+    Starting,
     Running,
     Stopped,
     Error(String),
+    /// Crashed too often to be restarted automatically again
+    Failed,
+    /// Running container recovered on startup that the server can't fully account for,
+    /// e.g. with ports outside the configured ranges or missing labels
+    Adopted(String),
+    /// The container is gone or in a state the server doesn't know
+    Unknown(String),
+    /// Deleted, its stopped container kept until it is purged or restored
+    Trashed,
 }
 
 impl InstanceStatus {
     pub fn as_str(&self) -> &str {
         match self {
             InstanceStatus::Creating => "creating",
+            InstanceStatus::Starting => "starting",
             InstanceStatus::Running => "running",
             InstanceStatus::Stopped => "stopped",
             InstanceStatus::Error(msg) => &msg,
+            InstanceStatus::Failed => "failed",
+            InstanceStatus::Adopted(_) => "adopted",
+            InstanceStatus::Unknown(_) => "unknown",
+            InstanceStatus::Trashed => "trashed",
+        }
+    }
+
+    /// Machine-readable status, the same for every message
+    pub fn code(&self) -> &str {
+        match self {
+            InstanceStatus::Error(_) => "error",
+            status => status.as_str(),
+        }
+    }
+
+    /// Details for the statuses that carry them
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            InstanceStatus::Error(msg) | InstanceStatus::Adopted(msg) | InstanceStatus::Unknown(msg) => Some(msg),
+            _ => None,
+        }
+    }
+
+    /// Whether the instance's container is running
+    pub fn is_running(&self) -> bool {
+        matches!(self, InstanceStatus::Starting | InstanceStatus::Running | InstanceStatus::Adopted(_))
+    }
+
+    /// The status after Docker reported `status`
+    ///
+    /// Docker doesn't know what the server does: a starting or adopted instance stays
+    /// starting or adopted while it runs, and a failed or trashed one failed or trashed
+    /// while it is stopped.
+    pub fn refreshed(&self, status: InstanceStatus) -> InstanceStatus {
+        match (self, status) {
+            (InstanceStatus::Starting | InstanceStatus::Adopted(_), InstanceStatus::Running)
+            | (InstanceStatus::Failed | InstanceStatus::Trashed, InstanceStatus::Stopped) => self.clone(),
+            (_, status) => status,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct InstanceConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub wine_debug_level: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cpulimit: Option<f64>,  // CPU cores (e.g., 0.5 = 50%, 2.0 = 2 cores)
+    /// Docker image, the server's `[docker] image` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Memory limit in MB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+    /// Extra environment variables for the container
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Containers run next to the game container, sharing its network namespace
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sidecars: Vec<SidecarSpec>,
+    /// How the server restarts the instance after a crash, `[instances]`'s if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<RestartPolicy>,
 }
 
-#[derive(Debug, Deserialize)]
+/// A container declared to run next to an instance's game container, e.g. a log shipper
+///
+/// Sidecars share the game container's network namespace, so they reach the game on
+/// `localhost` and can serve on the instance's ports. They are started, stopped and
+/// deleted with the instance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarSpec {
+    pub name: String,
+    pub image: String,
+    /// Command to run instead of the image's default
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+impl InstanceConfig {
+    /// Check that sidecars have an image and unique names usable in container names
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, sidecar) in self.sidecars.iter().enumerate() {
+            let valid_name = !sidecar.name.is_empty()
+                && sidecar.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+            if !valid_name {
+                return Err(format!(
+                    "Invalid sidecar name '{}': use letters, digits, '-' and '_'",
+                    sidecar.name
+                ));
+            }
+            if sidecar.image.is_empty() {
+                return Err(format!("Sidecar {} has no image", sidecar.name));
+            }
+            if self.sidecars[..index].iter().any(|other| other.name == sidecar.name) {
+                return Err(format!("Sidecar {} is declared twice", sidecar.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// This config with the fields it leaves unset taken from `base`
+    pub fn or(mut self, base: &InstanceConfig) -> InstanceConfig {
+        self.wine_debug_level = self.wine_debug_level.or_else(|| base.wine_debug_level.clone());
+        self.cpulimit = self.cpulimit.or(base.cpulimit);
+        self.image = self.image.or_else(|| base.image.clone());
+        self.memory_mb = self.memory_mb.or(base.memory_mb);
+        for (name, value) in &base.env {
+            self.env.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        if self.sidecars.is_empty() {
+            self.sidecars = base.sidecars.clone();
+        }
+        self.restart_policy = self.restart_policy.or(base.restart_policy);
+        self
+    }
+}
+
+/// Where a create request's DLL comes from, one of `openzt_dll`, `dll_id` and `dll_url` is set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DllSource {
+    /// Base64-encoded DLL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub openzt_dll: Option<String>,
+    /// ID of a DLL uploaded to `/api/dlls`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dll_id: Option<String>,
+    /// URL the server downloads the DLL from, e.g. a GitHub release asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dll_url: Option<String>,
+    /// Expected SHA-256 of the DLL at `dll_url`, as hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dll_sha256: Option<String>,
+}
+
+impl DllSource {
+    pub fn is_empty(&self) -> bool {
+        self.openzt_dll.is_none() && self.dll_id.is_none() && self.dll_url.is_none()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateInstanceRequest {
-    pub openzt_dll: String,
+    /// Template from `/api/templates` providing whatever the request leaves unset
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(flatten)]
+    pub dll: DllSource,
+    /// Mods from `/api/mods` to install, as `name` (latest version) or `name@version`
     #[serde(default)]
     pub mods: Vec<String>,
+    /// Mods uploaded with the request, installed next to `mods`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mod_files: Vec<ModFile>,
     #[serde(default)]
     pub config: Option<InstanceConfig>,
+    /// When to start and stop the instance, it waits for a future `start_at` instead of starting
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,7 +275,89 @@ pub struct CreateInstanceResponse {
     pub status: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Body of `PATCH /api/instances/{id}`, what it leaves out is kept
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateInstanceRequest {
+    /// New name of the instance, an empty name removes it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Labels set, replacing the values of existing ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Labels removed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_labels: Vec<String>,
+}
+
+/// Longest instance name or label key accepted
+pub const MAX_NAME_LENGTH: usize = 63;
+
+impl UpdateInstanceRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = self.name.as_deref().filter(|name| !name.is_empty()) {
+            if !valid_name(name) {
+                return Err(format!(
+                    "Invalid name '{}': use up to {} letters, digits, '-', '_' and '.', starting with a letter or digit",
+                    name, MAX_NAME_LENGTH
+                ));
+            }
+            // Names are resolved like IDs, so they mustn't look like one
+            if uuid::Uuid::parse_str(name).is_ok() {
+                return Err(format!("Invalid name '{}': names can't be UUIDs", name));
+            }
+        }
+        for key in self.labels.keys().chain(&self.remove_labels) {
+            if !valid_name(key) {
+                return Err(format!(
+                    "Invalid label '{}': use up to {} letters, digits, '-', '_' and '.', starting with a letter or digit",
+                    key, MAX_NAME_LENGTH
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `name` is usable as an instance name or label key
+fn valid_name(name: &str) -> bool {
+    name.len() <= MAX_NAME_LENGTH
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Instance and port headroom reported by `GET /api/capacity`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityResponse {
+    pub instances: usize,
+    pub max_instances: usize,
+    /// Ports summed over all Docker hosts
+    pub ports: PortCapacity,
+    /// Ports per Docker host
+    pub hosts: BTreeMap<String, PortCapacity>,
+}
+
+impl CapacityResponse {
+    /// Why creating an instance would fail for lack of room, if it would
+    ///
+    /// New instances get their ports on the local Docker host.
+    pub fn exhausted(&self) -> Option<String> {
+        if self.instances >= self.max_instances {
+            return Some(format!("Maximum instances reached ({}/{})", self.instances, self.max_instances));
+        }
+        let ports = self.hosts.get(LOCAL_HOST).unwrap_or(&self.ports);
+        for (kind, available, total) in [
+            ("VNC", ports.vnc_available, ports.vnc_total),
+            ("console", ports.console_available, ports.console_total),
+        ] {
+            if available == 0 {
+                return Some(format!("0 {} ports free ({}/{} used)", kind, total, total));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstanceDetails {
     pub id: String,
     pub container_id: String,
@@ -66,25 +365,181 @@ pub struct InstanceDetails {
     pub console_port: u16,
     pub vnc_url: String,
     pub status: String,
+    /// Machine-readable status: `creating`, `starting`, `running`, `degraded`, `adopted`, `stopped`,
+    /// `error`, `failed`, `unknown` or `trashed`
+    #[serde(default)]
+    pub status_code: String,
+    /// Why the instance is in an `error`, `adopted` or `unknown` status
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_message: Option<String>,
     pub created_at: DateTime<Utc>,
     pub config: InstanceConfig,
+    /// Recovered with ports outside the configured ranges
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ports_outside_range: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_exit: Option<InstanceExit>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sidecars: Vec<SidecarDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation: Option<CreationTimings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trashed_at: Option<DateTime<Utc>>,
+    /// When a trashed instance is purged, unless it is restored first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purge_at: Option<DateTime<Utc>>,
+    /// Operation in flight on the instance, e.g. `stop` or `delete`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarDetails {
+    pub name: String,
+    pub container_id: String,
+    pub status: String,
 }
 
 impl From<Instance> for InstanceDetails {
     fn from(instance: Instance) -> Self {
+        // A running instance with a sidecar that isn't running is degraded
+        let (status, status_code) = match instance.status {
+            InstanceStatus::Running
+                if instance.sidecars.iter().any(|sidecar| !matches!(sidecar.status, InstanceStatus::Running)) =>
+            {
+                ("degraded".to_string(), "degraded".to_string())
+            }
+            ref status => (status.as_str().to_string(), status.code().to_string()),
+        };
+        let status_message = instance.status.message().map(str::to_string);
         Self {
             id: instance.id,
             container_id: instance.container_id,
             vnc_port: instance.vnc_port,
             console_port: instance.console_port,
             vnc_url: format!("vnc://localhost:{}", instance.vnc_port),
-            status: instance.status.as_str().to_string(),
+            status,
+            status_code,
+            status_message,
             created_at: instance.created_at,
             config: instance.config,
+            ports_outside_range: instance.ports_outside_range,
+            schedule: instance.schedule,
+            last_exit: instance.last_exit,
+            sidecars: instance
+                .sidecars
+                .into_iter()
+                .map(|sidecar| SidecarDetails {
+                    name: sidecar.name,
+                    container_id: sidecar.container_id,
+                    status: sidecar.status.as_str().to_string(),
+                })
+                .collect(),
+            creation: instance.creation,
+            trashed_at: instance.trashed_at,
+            purge_at: None,
+            operation: None,
+            name: instance.name,
+            labels: instance.labels,
+        }
+    }
+}
+
+#[cfg(test)]
+impl InstanceDetails {
+    /// A running instance with default config, for tests to change the fields they need
+    // Used by the client modules' tests, which the server binary doesn't build
+    #[allow(dead_code)]
+    pub fn test_fixture(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            container_id: "container-123".to_string(),
+            vnc_port: 15900,
+            console_port: 18081,
+            vnc_url: "vnc://localhost:15900".to_string(),
+            status: "running".to_string(),
+            status_code: "running".to_string(),
+            status_message: None,
+            created_at: Utc::now(),
+            config: InstanceConfig::default(),
+            ports_outside_range: false,
+            schedule: None,
+            last_exit: None,
+            sidecars: Vec::new(),
+            creation: None,
+            trashed_at: None,
+            purge_at: None,
+            operation: None,
+            name: None,
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+/// Kind of lifecycle event published on the `/api/events` stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceEventKind {
+    Creating,
+    /// The container started, the game isn't up yet
+    Starting,
+    Running,
+    Error,
+    Stopped,
+    Started,
+    Restarted,
+    Deleted,
+    /// The container exited with a non-zero code
+    Crashed,
+    /// The container was killed for exceeding its memory limit
+    OomKilled,
+    /// The server restarts a crashed instance after a backoff
+    Restarting,
+    /// The instance crashed too often and won't be restarted again
+    Failed,
+    /// The instance was deleted into the trash
+    Trashed,
+    /// The instance was taken out of the trash
+    Restored,
+}
+
+impl InstanceEventKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            InstanceEventKind::Creating => "creating",
+            InstanceEventKind::Starting => "starting",
+            InstanceEventKind::Running => "running",
+            InstanceEventKind::Error => "error",
+            InstanceEventKind::Stopped => "stopped",
+            InstanceEventKind::Started => "started",
+            InstanceEventKind::Restarted => "restarted",
+            InstanceEventKind::Deleted => "deleted",
+            InstanceEventKind::Crashed => "crashed",
+            InstanceEventKind::OomKilled => "oom_killed",
+            InstanceEventKind::Restarting => "restarting",
+            InstanceEventKind::Failed => "failed",
+            InstanceEventKind::Trashed => "trashed",
+            InstanceEventKind::Restored => "restored",
         }
     }
 }
 
+/// Lifecycle event for a single instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceEvent {
+    pub timestamp: DateTime<Utc>,
+    pub instance_id: String,
+    pub kind: InstanceEventKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AppLogType {
     #[serde(rename = "openzt")]
@@ -117,6 +572,36 @@ impl AppLogType {
     }
 }
 
+/// Parse a log time bound given as RFC 3339, unix seconds, or a relative
+/// duration before `now` (e.g. `30s`, `10m`, `2h`, `1d`)
+pub fn parse_log_time(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let input = input.trim();
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    if let Ok(secs) = input.parse::<i64>() {
+        return DateTime::from_timestamp(secs, 0)
+            .ok_or_else(|| format!("Timestamp out of range: {}", input));
+    }
+
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid time '{}': expected RFC 3339, unix seconds, or a duration like 10m", input))?;
+    let duration = match unit {
+        "s" => chrono::Duration::seconds(amount),
+        "m" => chrono::Duration::minutes(amount),
+        "h" => chrono::Duration::hours(amount),
+        "d" => chrono::Duration::days(amount),
+        _ => return Err(format!("Invalid duration unit in '{}': use s, m, h, or d", input)),
+    };
+
+    Ok(now - duration)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LogsResponse {
     pub instance_id: String,
@@ -129,3 +614,196 @@ pub struct InstanceStatusResponse {
     pub id: String,
     pub status: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_time() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T12:00:00Z").unwrap().with_timezone(&Utc);
+
+        assert_eq!(parse_log_time("10m", now).unwrap(), now - chrono::Duration::minutes(10));
+        assert_eq!(parse_log_time("2d", now).unwrap(), now - chrono::Duration::days(2));
+        assert_eq!(
+            parse_log_time("2025-05-31T08:30:00Z", now).unwrap().to_rfc3339(),
+            "2025-05-31T08:30:00+00:00"
+        );
+        assert_eq!(parse_log_time("1748779200", now).unwrap(), now);
+        assert!(parse_log_time("10y", now).is_err());
+        assert!(parse_log_time("yesterday", now).is_err());
+    }
+
+    #[test]
+    fn test_config_or() {
+        let template = InstanceConfig {
+            cpulimit: Some(2.0),
+            image: Some("finn/winezt:event".to_string()),
+            env: BTreeMap::from([
+                ("EVENT".to_string(), "tournament".to_string()),
+                ("LANG".to_string(), "en_US".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let request = InstanceConfig {
+            cpulimit: Some(1.0),
+            memory_mb: Some(2048),
+            env: BTreeMap::from([("LANG".to_string(), "de_DE".to_string())]),
+            ..Default::default()
+        };
+
+        let config = request.or(&template);
+        assert_eq!(config.cpulimit, Some(1.0));
+        assert_eq!(config.memory_mb, Some(2048));
+        assert_eq!(config.image.as_deref(), Some("finn/winezt:event"));
+        assert_eq!(config.env["EVENT"], "tournament");
+        assert_eq!(config.env["LANG"], "de_DE");
+    }
+
+    fn sidecar(name: &str) -> SidecarSpec {
+        SidecarSpec {
+            name: name.to_string(),
+            image: "fluent/fluent-bit:3.1".to_string(),
+            command: Vec::new(),
+            env: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_sidecars() {
+        let template = InstanceConfig {
+            sidecars: vec![sidecar("logs")],
+            ..Default::default()
+        };
+        assert_eq!(InstanceConfig::default().or(&template).sidecars, template.sidecars);
+        let request = InstanceConfig {
+            sidecars: vec![sidecar("relay")],
+            ..Default::default()
+        };
+        assert_eq!(request.clone().or(&template).sidecars, request.sidecars);
+
+        assert!(template.validate().is_ok());
+        let twice = InstanceConfig {
+            sidecars: vec![sidecar("logs"), sidecar("logs")],
+            ..Default::default()
+        };
+        assert!(twice.validate().is_err());
+        assert!(InstanceConfig { sidecars: vec![sidecar("../logs")], ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_degraded_status() {
+        let mut instance = Instance {
+            id: "instance".to_string(),
+            container_id: "game".to_string(),
+            vnc_port: 5901,
+            console_port: 8080,
+            status: InstanceStatus::Running,
+            created_at: Utc::now(),
+            config: InstanceConfig::default(),
+            ports_outside_range: false,
+            schedule: None,
+            last_exit: None,
+            auto_restarts: Vec::new(),
+            sidecars: vec![Sidecar {
+                name: "logs".to_string(),
+                container_id: "logs".to_string(),
+                status: InstanceStatus::Running,
+            }],
+            creation: None,
+            trashed_at: None,
+            name: None,
+            labels: BTreeMap::new(),
+        };
+        assert_eq!(InstanceDetails::from(instance.clone()).status, "running");
+
+        instance.sidecars[0].status = InstanceStatus::Stopped;
+        let details = InstanceDetails::from(instance.clone());
+        assert_eq!(details.status, "degraded");
+        assert_eq!(details.status_code, "degraded");
+        assert_eq!(details.sidecars[0].status, "stopped");
+
+        instance.status = InstanceStatus::Stopped;
+        assert_eq!(InstanceDetails::from(instance).status, "stopped");
+    }
+
+    #[test]
+    fn test_status_codes() {
+        let error = InstanceStatus::Error("Failed to start container".to_string());
+        assert_eq!(error.as_str(), "Failed to start container");
+        assert_eq!(error.code(), "error");
+        assert_eq!(error.message(), Some("Failed to start container"));
+
+        let adopted = InstanceStatus::Adopted("Ports outside the configured ranges".to_string());
+        assert_eq!(adopted.code(), "adopted");
+        assert!(adopted.is_running());
+        assert_eq!(adopted.refreshed(InstanceStatus::Running), adopted);
+        assert_eq!(adopted.refreshed(InstanceStatus::Stopped), InstanceStatus::Stopped);
+
+        assert_eq!(InstanceStatus::Failed.refreshed(InstanceStatus::Stopped), InstanceStatus::Failed);
+        assert_eq!(InstanceStatus::Starting.refreshed(InstanceStatus::Running), InstanceStatus::Starting);
+        assert_eq!(InstanceStatus::Starting.refreshed(InstanceStatus::Stopped), InstanceStatus::Stopped);
+        assert_eq!(InstanceStatus::Trashed.refreshed(InstanceStatus::Stopped), InstanceStatus::Trashed);
+        assert_eq!(InstanceStatus::Running.message(), None);
+    }
+
+    #[test]
+    fn test_update_request_validation() {
+        let rename = |name: &str| UpdateInstanceRequest { name: Some(name.to_string()), ..Default::default() };
+        assert!(rename("staging-1.2").validate().is_ok());
+        assert!(rename("").validate().is_ok());
+        assert!(rename("-staging").validate().is_err());
+        assert!(rename("my instance").validate().is_err());
+        assert!(rename(&"a".repeat(MAX_NAME_LENGTH + 1)).validate().is_err());
+        assert!(rename("ba4fc512-3d48-4f9e-9a1b-123456789abc").validate().is_err());
+
+        let label = |key: &str| UpdateInstanceRequest {
+            labels: BTreeMap::from([(key.to_string(), "any value".to_string())]),
+            ..Default::default()
+        };
+        assert!(label("team").validate().is_ok());
+        assert!(label("").validate().is_err());
+        let remove = UpdateInstanceRequest { remove_labels: vec!["a=b".to_string()], ..Default::default() };
+        assert!(remove.validate().is_err());
+    }
+
+    #[test]
+    fn test_capacity_exhausted() {
+        let ports = PortCapacity {
+            vnc_available: 3,
+            vnc_total: 100,
+            console_available: 3,
+            console_total: 100,
+        };
+        let mut capacity = CapacityResponse {
+            instances: 97,
+            max_instances: 100,
+            ports,
+            hosts: BTreeMap::from([(LOCAL_HOST.to_string(), ports)]),
+        };
+        assert_eq!(capacity.exhausted(), None);
+
+        capacity.hosts.get_mut(LOCAL_HOST).unwrap().vnc_available = 0;
+        assert_eq!(capacity.exhausted().as_deref(), Some("0 VNC ports free (100/100 used)"));
+
+        capacity.instances = 100;
+        assert_eq!(capacity.exhausted().as_deref(), Some("Maximum instances reached (100/100)"));
+    }
+
+    #[test]
+    fn test_instance_exit() {
+        let exited_at = Utc::now();
+        let oom = InstanceExit { exit_code: 137, oom_killed: true, exited_at };
+        assert!(oom.crashed());
+        assert_eq!(oom.describe(), "Killed for running out of memory (exit code 137)");
+
+        let crash = InstanceExit { exit_code: 3, oom_killed: false, exited_at };
+        assert!(crash.crashed());
+        assert_eq!(crash.describe(), "Exited with code 3");
+        assert!(!InstanceExit { exit_code: 0, oom_killed: false, exited_at }.crashed());
+
+        let json = serde_json::to_value(&oom).unwrap();
+        assert_eq!(json["oom_killed"], true);
+        assert_eq!(serde_json::from_value::<InstanceExit>(json).unwrap(), oom);
+    }
+}
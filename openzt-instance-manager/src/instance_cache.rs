@@ -0,0 +1,181 @@
+//! Instance list caching for the CLI
+//!
+//! Resolving a short ID requires the full instance list, and most commands
+//! resolve an ID before acting on it. This module keeps the fetched list in
+//! memory for the lifetime of a CLI invocation so repeated resolutions share a
+//! single round trip. An optional on-disk cache (keyed by server URL) lets
+//! back-to-back invocations skip the fetch entirely while it is still fresh.
+
+use crate::instance::InstanceDetails;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Cached instance list shared by a single `InstanceClient`
+#[derive(Default)]
+pub struct InstanceCache {
+    memory: Mutex<Option<Vec<InstanceDetails>>>,
+    disk: Option<DiskCache>,
+}
+
+/// On-disk cache location and freshness window
+struct DiskCache {
+    path: PathBuf,
+    ttl: Duration,
+}
+
+/// Serialized form of the on-disk cache file
+#[derive(Serialize, Deserialize)]
+struct CachedList {
+    base_url: String,
+    fetched_at: DateTime<Utc>,
+    instances: Vec<InstanceDetails>,
+}
+
+impl InstanceCache {
+    /// Create an in-memory only cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a cache that also persists the list to `dir`, keyed by `base_url`
+    pub fn with_disk(dir: PathBuf, base_url: &str, ttl: Duration) -> Self {
+        Self {
+            memory: Mutex::new(None),
+            disk: Some(DiskCache {
+                path: dir.join(cache_file_name(base_url)),
+                ttl,
+            }),
+        }
+    }
+
+    /// Whether entries may come from a previous invocation (and so may be stale)
+    pub fn is_disk_backed(&self) -> bool {
+        self.disk.is_some()
+    }
+
+    /// Return the cached list, loading it from disk if it is still fresh
+    pub fn get(&self, base_url: &str) -> Option<Vec<InstanceDetails>> {
+        if let Some(instances) = self.memory.lock().ok()?.as_ref() {
+            return Some(instances.clone());
+        }
+
+        let disk = self.disk.as_ref()?;
+        let contents = std::fs::read_to_string(&disk.path).ok()?;
+        let cached: CachedList = serde_json::from_str(&contents).ok()?;
+
+        if cached.base_url != base_url {
+            return None;
+        }
+        let age = Utc::now().signed_duration_since(cached.fetched_at).to_std().ok()?;
+        if age > disk.ttl {
+            return None;
+        }
+
+        if let Ok(mut memory) = self.memory.lock() {
+            *memory = Some(cached.instances.clone());
+        }
+        Some(cached.instances)
+    }
+
+    /// Store a freshly fetched list
+    pub fn store(&self, base_url: &str, instances: &[InstanceDetails]) {
+        if let Ok(mut memory) = self.memory.lock() {
+            *memory = Some(instances.to_vec());
+        }
+
+        let Some(disk) = &self.disk else {
+            return;
+        };
+
+        let cached = CachedList {
+            base_url: base_url.to_string(),
+            fetched_at: Utc::now(),
+            instances: instances.to_vec(),
+        };
+
+        // The disk cache is best-effort; failures only cost a round trip next time
+        if let Some(parent) = disk.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(contents) = serde_json::to_string(&cached) {
+            let _ = std::fs::write(&disk.path, contents);
+        }
+    }
+
+    /// Drop the cached list (after a create or delete changes it)
+    pub fn invalidate(&self) {
+        if let Ok(mut memory) = self.memory.lock() {
+            *memory = None;
+        }
+        if let Some(disk) = &self.disk {
+            let _ = std::fs::remove_file(&disk.path);
+        }
+    }
+}
+
+/// Build a filesystem-safe cache file name from a server URL
+fn cache_file_name(base_url: &str) -> String {
+    let key: String = base_url
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("instances-{}.json", key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_instance(id: &str) -> InstanceDetails {
+        InstanceDetails::test_fixture(id)
+    }
+
+    fn temp_cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openzt-cache-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_cache_file_name() {
+        assert_eq!(cache_file_name("http://localhost:3000/"), "instances-http___localhost_3000.json");
+        assert_ne!(cache_file_name("http://a:3000"), cache_file_name("http://b:3000"));
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        let cache = InstanceCache::new();
+        assert!(cache.get("http://localhost:3000").is_none());
+
+        cache.store("http://localhost:3000", &[create_test_instance("ba4fc512-3d48-4f9e-9a1b-123456789abc")]);
+        assert_eq!(cache.get("http://localhost:3000").unwrap().len(), 1);
+
+        cache.invalidate();
+        assert!(cache.get("http://localhost:3000").is_none());
+    }
+
+    #[test]
+    fn test_disk_cache_round_trip() {
+        let dir = temp_cache_dir("round-trip");
+        let url = "http://localhost:3000";
+
+        let writer = InstanceCache::with_disk(dir.clone(), url, Duration::from_secs(60));
+        writer.store(url, &[create_test_instance("ba4fc512-3d48-4f9e-9a1b-123456789abc")]);
+
+        // A new cache (i.e. a later invocation) picks up the stored list
+        let reader = InstanceCache::with_disk(dir.clone(), url, Duration::from_secs(60));
+        let instances = reader.get(url).unwrap();
+        assert_eq!(instances[0].id, "ba4fc512-3d48-4f9e-9a1b-123456789abc");
+
+        // An expired entry is ignored
+        let expired = InstanceCache::with_disk(dir.clone(), url, Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(expired.get(url).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
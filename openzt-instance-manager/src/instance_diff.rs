@@ -0,0 +1,140 @@
+//! Field-by-field comparison of two instances for `openzt diff`
+//!
+//! Instances are compared through their JSON form, flattened to dotted field
+//! paths (e.g. `config.cpulimit`), so new fields on `InstanceDetails` are
+//! picked up without changes here.
+
+use crate::instance::InstanceDetails;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Fields that identify an instance rather than describe its setup
+///
+/// These always differ between two instances, so they are only compared on request.
+const IDENTITY_FIELDS: &[&str] = &["id", "name", "container_id", "vnc_port", "console_port", "vnc_url", "created_at"];
+
+/// A field whose value differs between two instances (`None` = not set)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldDiff {
+    pub field: String,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+/// Compare two instances, returning the differing fields in field order
+pub fn diff_instances(left: &InstanceDetails, right: &InstanceDetails, include_identity: bool) -> Vec<FieldDiff> {
+    let left = flatten_instance(left);
+    let right = flatten_instance(right);
+
+    let mut fields: Vec<&String> = left.keys().chain(right.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter(|field| include_identity || !IDENTITY_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let (l, r) = (left.get(field), right.get(field));
+            (l != r).then(|| FieldDiff {
+                field: field.clone(),
+                left: l.cloned(),
+                right: r.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Flatten an instance into dotted field paths; unset (null) fields are omitted
+///
+/// The same paths are accepted by `openzt get --field`.
+pub fn flatten_instance(instance: &InstanceDetails) -> BTreeMap<String, Value> {
+    let mut fields = BTreeMap::new();
+    if let Ok(value) = serde_json::to_value(instance) {
+        flatten_value("", value, &mut fields);
+    }
+    fields
+}
+
+fn flatten_value(prefix: &str, value: Value, fields: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                flatten_value(&path, value, fields);
+            }
+        }
+        Value::Null => {}
+        // Arrays (e.g. mod lists) are compared as a whole
+        value => {
+            fields.insert(prefix.to_string(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceConfig;
+
+    fn create_test_instance(id: &str, vnc_port: u16, config: InstanceConfig) -> InstanceDetails {
+        InstanceDetails {
+            container_id: format!("container-{}", id),
+            vnc_port,
+            console_port: vnc_port + 1000,
+            vnc_url: format!("vnc://localhost:{}", vnc_port),
+            config,
+            ..InstanceDetails::test_fixture(id)
+        }
+    }
+
+    #[test]
+    fn test_diff_config_only() {
+        let a = create_test_instance("a", 15900, InstanceConfig { cpulimit: Some(1.5), ..Default::default() });
+        let b = create_test_instance(
+            "b",
+            15901,
+            InstanceConfig { wine_debug_level: Some("-all".to_string()), ..Default::default() },
+        );
+
+        let diffs = diff_instances(&a, &b, false);
+        assert_eq!(
+            diffs,
+            vec![
+                FieldDiff {
+                    field: "config.cpulimit".to_string(),
+                    left: Some(serde_json::json!(1.5)),
+                    right: None,
+                },
+                FieldDiff {
+                    field: "config.wine_debug_level".to_string(),
+                    left: None,
+                    right: Some(serde_json::json!("-all")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_identity_fields() {
+        let a = create_test_instance("a", 15900, InstanceConfig::default());
+        let b = create_test_instance("b", 15901, InstanceConfig::default());
+
+        assert!(diff_instances(&a, &b, false).is_empty());
+
+        let fields: Vec<String> = diff_instances(&a, &b, true).into_iter().map(|d| d.field).collect();
+        assert!(fields.contains(&"id".to_string()));
+        assert!(fields.contains(&"vnc_port".to_string()));
+    }
+
+    #[test]
+    fn test_flatten_instance_paths() {
+        let instance = create_test_instance("a", 15900, InstanceConfig { cpulimit: Some(2.0), ..Default::default() });
+        let fields = flatten_instance(&instance);
+
+        assert_eq!(fields.get("vnc_url"), Some(&serde_json::json!("vnc://localhost:15900")));
+        assert_eq!(fields.get("config.cpulimit"), Some(&serde_json::json!(2.0)));
+        // Unset fields are omitted rather than reported as null
+        assert!(!fields.contains_key("config.wine_debug_level"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Declarative instance definition files
+//!
+//! `openzt export <id>` writes an instance's configuration as TOML, and
+//! `openzt create --from-file <file>` creates an instance from it, so a
+//! reproducible environment definition can be kept alongside a project.
+//!
+//! ```toml
+//! dll = "target/i686-pc-windows-msvc/release/openzt.dll"
+//!
+//! [config]
+//! cpulimit = 2.0
+//! ```
+
+use crate::instance::{InstanceConfig, InstanceDetails};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Instance definition as stored in a file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceFile {
+    /// Path to the OpenZT DLL, relative to the definition file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dll: Option<PathBuf>,
+
+    /// Instance configuration sent on create
+    #[serde(default)]
+    pub config: InstanceConfig,
+}
+
+impl InstanceFile {
+    /// Build a definition from an existing instance
+    ///
+    /// The DLL an instance was created from is not kept by the server, so
+    /// `dll` is left unset and must be added or passed on the command line.
+    pub fn from_instance(instance: &InstanceDetails) -> Self {
+        Self {
+            dll: None,
+            config: instance.config.clone(),
+        }
+    }
+
+    /// Load a definition, resolving `dll` relative to the file's directory
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read instance file: {}", path.display()))?;
+        let mut file: InstanceFile = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse instance file: {}", path.display()))?;
+
+        if let Some(dll) = &file.dll
+            && dll.is_relative()
+            && let Some(dir) = path.parent()
+        {
+            file.dll = Some(dir.join(dll));
+        }
+
+        Ok(file)
+    }
+
+    /// Serialize the definition as TOML
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize instance file")
+    }
+
+    /// Instance configuration to send on create, or `None` if nothing is set
+    pub fn instance_config(&self) -> Option<InstanceConfig> {
+        if self.config == InstanceConfig::default() {
+            return None;
+        }
+        Some(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_from_instance() {
+        let instance = InstanceDetails {
+            config: InstanceConfig {
+                wine_debug_level: Some("-all".to_string()),
+                cpulimit: Some(1.5),
+                ..Default::default()
+            },
+            ..InstanceDetails::test_fixture("ba4fc512-3d48-4f9e-9a1b-123456789abc")
+        };
+
+        let file = InstanceFile::from_instance(&instance);
+        let parsed: InstanceFile = toml::from_str(&file.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, file);
+
+        let config = parsed.instance_config().unwrap();
+        assert_eq!(config.cpulimit, Some(1.5));
+        assert_eq!(config.wine_debug_level.as_deref(), Some("-all"));
+    }
+
+    #[test]
+    fn test_empty_config_sends_none() {
+        let file: InstanceFile = toml::from_str("").unwrap();
+        assert!(file.instance_config().is_none());
+    }
+
+    #[test]
+    fn test_unknown_fields_rejected() {
+        assert!(toml::from_str::<InstanceFile>("image = \"openzt:latest\"\n").is_err());
+    }
+
+    #[test]
+    fn test_load_resolves_relative_dll() {
+        let dir = std::env::temp_dir().join(format!("openzt-instance-file-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("instance.toml");
+        std::fs::write(&path, "dll = \"build/openzt.dll\"\n\n[config]\ncpulimit = 2.0\n").unwrap();
+
+        let file = InstanceFile::load(&path).unwrap();
+        assert_eq!(file.dll, Some(dir.join("build/openzt.dll")));
+        assert_eq!(file.config.cpulimit, Some(2.0));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -4,11 +4,25 @@
 //! Zoo Tycoon Docker instances, both for the API server and CLI client.
 
 pub mod config;
+pub mod dll_library;
 pub mod docker;
+pub mod idempotency;
 pub mod instance;
+pub mod log_archive;
+pub mod metrics;
+pub mod mod_repository;
+pub mod operations;
 pub mod ports;
+pub mod readiness;
+pub mod recording;
+pub mod restart;
 pub mod routes;
+pub mod schedule;
 pub mod state;
+pub mod templates;
+pub mod test_run;
+pub mod tokens;
+pub mod webhooks;
 
 // CLI-only modules (conditional compilation)
 #[cfg(feature = "cli")]
@@ -16,8 +30,16 @@ pub mod client;
 #[cfg(feature = "cli")]
 pub mod client_config;
 #[cfg(feature = "cli")]
+pub mod client_transport;
+#[cfg(feature = "cli")]
 pub mod id_resolver;
 #[cfg(feature = "cli")]
+pub mod instance_cache;
+#[cfg(feature = "cli")]
+pub mod instance_diff;
+#[cfg(feature = "cli")]
+pub mod instance_file;
+#[cfg(feature = "cli")]
 pub mod output;
 
 // Re-export commonly used types for external consumers
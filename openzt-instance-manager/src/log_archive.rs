@@ -0,0 +1,177 @@
+//! Per-instance log files kept by the server
+//!
+//! With `[log_archive] enabled`, the server follows each instance's Docker logs
+//! into `<dir>/<instance id>/docker.log`, rotated to `docker.log.1`, `docker.log.2`
+//! and so on once it reaches `max_file_mb`. The files are kept after the instance is
+//! deleted, so `GET /api/instances/:id/logs?type=docker&archived=true` still works
+//! for post-mortem debugging. Lines are stored with Docker's timestamps, which is
+//! also how following resumes without duplicates after a restart.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+const LOG_FILE: &str = "docker.log";
+
+/// Directory an instance's archived logs are kept in
+pub fn instance_dir(dir: &str, instance_id: &str) -> PathBuf {
+    Path::new(dir).join(instance_id)
+}
+
+/// An archived log file, rotated once it reaches its size limit
+pub struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingLog {
+    /// Open an instance's log for appending, creating its directory if needed
+    ///
+    /// At most `max_files` files are kept, the current one included.
+    pub fn open(dir: &str, instance_id: &str, max_bytes: u64, max_files: usize) -> Result<Self> {
+        let instance_dir = instance_dir(dir, instance_id);
+        std::fs::create_dir_all(&instance_dir)
+            .with_context(|| format!("Failed to create log archive directory {}", instance_dir.display()))?;
+        let path = instance_dir.join(LOG_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files: max_files.max(1),
+            file,
+            size,
+        })
+    }
+
+    /// Timestamp of the last line written, to resume following after it
+    pub fn last_timestamp(&self) -> Option<DateTime<Utc>> {
+        // The current file is empty right after rotating
+        [self.path.clone(), rotated_path(&self.path, 1)].iter().find_map(|path| {
+            let file = File::open(path).ok()?;
+            BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| line_timestamp(&line))
+                .last()
+        })
+    }
+
+    /// Append a line, which starts with its timestamp
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        let line = line.trim_end_matches('\n');
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line).with_context(|| format!("Failed to write to {}", self.path.display()))?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift `docker.log.N` to `docker.log.N+1`, dropping the oldest, and start a new file
+    fn rotate(&mut self) -> Result<()> {
+        let _ = std::fs::remove_file(rotated_path(&self.path, self.max_files - 1));
+        for n in (1..self.max_files - 1).rev() {
+            let _ = std::fs::rename(rotated_path(&self.path, n), rotated_path(&self.path, n + 1));
+        }
+        if self.max_files > 1 {
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))
+                .with_context(|| format!("Failed to rotate {}", self.path.display()))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
+
+/// The leading RFC 3339 timestamp Docker puts on each line
+pub fn line_timestamp(line: &str) -> Option<DateTime<Utc>> {
+    let token = line.split_whitespace().next()?;
+    DateTime::parse_from_rfc3339(token).ok().map(|ts| ts.with_timezone(&Utc))
+}
+
+/// An instance's archived logs, oldest first, or `None` if it has none
+pub fn read(dir: &str, instance_id: &str) -> Result<Option<String>> {
+    let path = instance_dir(dir, instance_id).join(LOG_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut rotated: Vec<(usize, PathBuf)> = std::fs::read_dir(instance_dir(dir, instance_id))?
+        .filter_map(|dir_entry| dir_entry.ok())
+        .filter_map(|dir_entry| {
+            let name = dir_entry.file_name().to_string_lossy().into_owned();
+            let n = name.strip_prefix(LOG_FILE)?.strip_prefix('.')?.parse().ok()?;
+            Some((n, dir_entry.path()))
+        })
+        .collect();
+    // Higher numbers are older
+    rotated.sort_by_key(|(n, _)| std::cmp::Reverse(*n));
+
+    let mut logs = String::new();
+    for path in rotated.into_iter().map(|(_, path)| path).chain(std::iter::once(path)) {
+        logs.push_str(&std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?);
+    }
+    Ok(Some(logs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive_dir() -> String {
+        let dir = std::env::temp_dir().join(format!("openzt-log-archive-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn line(n: u32) -> String {
+        format!("2026-10-16T17:00:{:02}.000000000Z line {}", n, n)
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = archive_dir();
+        assert_eq!(read(&dir, "instance").unwrap(), None);
+
+        // Each line is 38 bytes, so every file holds two
+        let mut log = RotatingLog::open(&dir, "instance", 100, 3).unwrap();
+        for n in 0..8 {
+            log.write_line(&line(n)).unwrap();
+        }
+        assert_eq!(log.last_timestamp(), line_timestamp(&line(7)));
+
+        // Three files are kept: lines 2-3, 4-5 and 6-7
+        let logs = read(&dir, "instance").unwrap().unwrap();
+        let expected: Vec<String> = (2..8).map(line).collect();
+        assert_eq!(logs.lines().collect::<Vec<_>>(), expected);
+        assert!(!instance_dir(&dir, "instance").join("docker.log.3").exists());
+
+        // Reopening continues the current file
+        let mut log = RotatingLog::open(&dir, "instance", 100, 3).unwrap();
+        log.write_line(&line(8)).unwrap();
+        assert!(read(&dir, "instance").unwrap().unwrap().starts_with(&line(4)));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
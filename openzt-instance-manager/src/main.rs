@@ -1,18 +1,45 @@
 mod config;
+mod dll_library;
 mod docker;
+mod idempotency;
 mod instance;
+mod log_archive;
+mod metrics;
+mod mod_repository;
+mod operations;
 mod ports;
+mod readiness;
+mod recording;
+mod restart;
 mod routes;
+mod schedule;
 mod state;
+mod templates;
+mod test_run;
+mod tokens;
+mod webhooks;
 
 use anyhow::Result;
 use axum::{http::Method, Router};
 use axum::extract::DefaultBodyLimit;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// How often expired port reservations are checked for
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often instance schedules are checked for starts and stops that are due
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often the trash is checked for instances past their retention
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How long to wait before watching Docker's events again when the stream ends
+const EXIT_WATCH_RETRY: Duration = Duration::from_secs(5);
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize tracing
@@ -42,17 +69,93 @@ async fn main() -> Result<()> {
         }
     }
 
+    match app_state.dlls.load() {
+        Ok(count) => tracing::info!("Loaded {} DLLs from the DLL library", count),
+        Err(e) => tracing::warn!("Failed to load the DLL library: {}", e),
+    }
+
+    match app_state.mods.load() {
+        Ok(count) => tracing::info!("Loaded {} mods from the mod repository", count),
+        Err(e) => tracing::warn!("Failed to load the mod repository: {}", e),
+    }
+
+    match app_state.templates.load() {
+        Ok(count) => tracing::info!("Loaded {} templates", count),
+        Err(e) => tracing::warn!("Failed to load templates: {}", e),
+    }
+
+    match app_state.tokens.load() {
+        Ok(count) => tracing::info!("Loaded {} API tokens", count),
+        Err(e) => tracing::warn!("Failed to load API tokens: {}", e),
+    }
+
+    let archived_instances: Vec<String> = if config.log_archive.enabled {
+        app_state.instances.keys().cloned().collect()
+    } else {
+        Vec::new()
+    };
     let state = Arc::new(RwLock::new(app_state));
 
+    // Log archive: follow recovered instances' logs, new instances start once created
+    for instance_id in archived_instances {
+        tokio::spawn(routes::archive_logs(state.clone(), instance_id));
+    }
+
+    // Reconciliation loop: release ports held by creates that never finished
+    let reconcile_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+        loop {
+            interval.tick().await;
+            reconcile_state.write().await.release_expired_reservations();
+        }
+    });
+
+    // Exit watcher: record crashes and OOM kills, restarting instances if configured
+    let exit_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            routes::watch_container_exits(&exit_state).await;
+            tokio::time::sleep(EXIT_WATCH_RETRY).await;
+        }
+    });
+
+    // Send instance events to the configured webhooks
+    tokio::spawn(webhooks::forward_events(state.clone()));
+
+    // Scheduler loop: start and stop instances on their schedules
+    let schedule_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULE_INTERVAL);
+        let mut since = chrono::Utc::now();
+        loop {
+            interval.tick().await;
+            let now = chrono::Utc::now();
+            routes::run_schedules(&schedule_state, since, now).await;
+            since = now;
+        }
+    });
+
+    // Trash loop: purge deleted instances once their retention is over
+    let trash_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            routes::purge_expired_trash(&trash_state, chrono::Utc::now()).await;
+        }
+    });
+
     // Build router with CORS support and increased body limit
     let app = Router::new()
         .merge(routes::create_router())
+        .layer(axum::middleware::from_fn_with_state(state.clone(), routes::authenticate))
         .with_state(state)
         .layer(DefaultBodyLimit::max(50 * 1024 * 1024)) // 50 MB limit
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
-                .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
+                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
                 .allow_headers(Any),
         );
 
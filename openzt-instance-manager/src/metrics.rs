@@ -0,0 +1,223 @@
+//! Instance creation metrics
+//!
+//! Creating an instance runs through phases (writing the DLL, checking the image,
+//! creating and starting the container, waiting for the game); the server times each one and classifies
+//! why creations fail. Instances keep their own timings in `creation`, and
+//! `GET /metrics` exposes histograms and counters over all creations in the
+//! Prometheus text format, to show where creation time goes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Histogram bucket bounds in seconds, from a DLL write to a slow image pull
+const BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreationPhase {
+    DllWrite,
+    ImageCheck,
+    ContainerCreate,
+    Start,
+    Sidecars,
+    /// Waiting for the game to answer on its console
+    Ready,
+}
+
+impl CreationPhase {
+    pub fn as_str(&self) -> &str {
+        match self {
+            CreationPhase::DllWrite => "dll_write",
+            CreationPhase::ImageCheck => "image_check",
+            CreationPhase::ContainerCreate => "container_create",
+            CreationPhase::Start => "start",
+            CreationPhase::Sidecars => "sidecars",
+            CreationPhase::Ready => "ready",
+        }
+    }
+}
+
+/// Why creating an instance failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreationFailure {
+    DllWrite,
+    /// The image couldn't be found or pulled
+    PullError,
+    ContainerCreate,
+    /// A host port was taken by something outside the manager
+    PortBind,
+    /// The container didn't start before its port reservation expired
+    StartTimeout,
+    Start,
+    Sidecars,
+    /// The game didn't answer on its console within `[instances] ready_timeout_secs`
+    ReadyTimeout,
+}
+
+impl CreationFailure {
+    /// Classify a phase's error, telling port conflicts apart from other errors
+    pub fn classify(phase: CreationPhase, error: &anyhow::Error) -> Self {
+        let message = format!("{:#}", error);
+        if message.contains("port is already allocated") || message.contains("address already in use") {
+            return CreationFailure::PortBind;
+        }
+        match phase {
+            CreationPhase::DllWrite => CreationFailure::DllWrite,
+            CreationPhase::ImageCheck => CreationFailure::PullError,
+            CreationPhase::ContainerCreate => CreationFailure::ContainerCreate,
+            CreationPhase::Start => CreationFailure::Start,
+            CreationPhase::Sidecars => CreationFailure::Sidecars,
+            CreationPhase::Ready => CreationFailure::ReadyTimeout,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            CreationFailure::DllWrite => "dll_write",
+            CreationFailure::PullError => "pull_error",
+            CreationFailure::ContainerCreate => "container_create",
+            CreationFailure::PortBind => "port_bind",
+            CreationFailure::StartTimeout => "start_timeout",
+            CreationFailure::Start => "start",
+            CreationFailure::Sidecars => "sidecars",
+            CreationFailure::ReadyTimeout => "ready_timeout",
+        }
+    }
+}
+
+/// How long an instance's creation phases took, and why creation failed if it did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CreationTimings {
+    /// Milliseconds per phase that ran
+    #[serde(default)]
+    pub phases_ms: BTreeMap<CreationPhase, u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<CreationFailure>,
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    /// Observations per bucket, not cumulative
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; BUCKETS.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(bucket) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, labels, bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, labels, self.count);
+        let labels = labels.trim_end_matches(',');
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CreationMetrics {
+    phases: BTreeMap<CreationPhase, Histogram>,
+    total: Histogram,
+    failures: HashMap<CreationFailure, u64>,
+}
+
+impl CreationMetrics {
+    pub fn record_phase(&mut self, phase: CreationPhase, duration: Duration) {
+        self.phases.entry(phase).or_default().observe(duration.as_secs_f64());
+    }
+
+    /// Record an instance that was created, `duration` after the create request
+    pub fn record_created(&mut self, duration: Duration) {
+        self.total.observe(duration.as_secs_f64());
+    }
+
+    pub fn record_failure(&mut self, failure: CreationFailure) {
+        *self.failures.entry(failure).or_default() += 1;
+    }
+
+    /// The metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP openzt_instance_creation_phase_seconds Time taken by each instance creation phase\n");
+        out.push_str("# TYPE openzt_instance_creation_phase_seconds histogram\n");
+        for (phase, histogram) in &self.phases {
+            let labels = format!("phase=\"{}\",", phase.as_str());
+            histogram.render(&mut out, "openzt_instance_creation_phase_seconds", &labels);
+        }
+
+        out.push_str("# HELP openzt_instance_creation_seconds Time from create request to running instance\n");
+        out.push_str("# TYPE openzt_instance_creation_seconds histogram\n");
+        self.total.render(&mut out, "openzt_instance_creation_seconds", "");
+
+        out.push_str("# HELP openzt_instance_creation_failures_total Instance creations that failed, by cause\n");
+        out.push_str("# TYPE openzt_instance_creation_failures_total counter\n");
+        let mut failures: Vec<_> = self.failures.iter().collect();
+        failures.sort();
+        for (failure, count) in failures {
+            let _ = writeln!(
+                out,
+                "openzt_instance_creation_failures_total{{cause=\"{}\"}} {}",
+                failure.as_str(),
+                count
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let bind = anyhow::anyhow!("Bind for 0.0.0.0:15900 failed: port is already allocated")
+            .context("Failed to start container");
+        assert_eq!(CreationFailure::classify(CreationPhase::Start, &bind), CreationFailure::PortBind);
+
+        let pull = anyhow::anyhow!("manifest unknown");
+        assert_eq!(CreationFailure::classify(CreationPhase::ImageCheck, &pull), CreationFailure::PullError);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut metrics = CreationMetrics::default();
+        metrics.record_phase(CreationPhase::Start, Duration::from_millis(300));
+        metrics.record_phase(CreationPhase::Start, Duration::from_secs(3));
+        metrics.record_created(Duration::from_secs(4));
+        metrics.record_failure(CreationFailure::PullError);
+
+        let text = metrics.render();
+        assert!(text.contains("openzt_instance_creation_phase_seconds_bucket{phase=\"start\",le=\"0.25\"} 0\n"));
+        assert!(text.contains("openzt_instance_creation_phase_seconds_bucket{phase=\"start\",le=\"0.5\"} 1\n"));
+        assert!(text.contains("openzt_instance_creation_phase_seconds_bucket{phase=\"start\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("openzt_instance_creation_phase_seconds_sum{phase=\"start\"} 3.3\n"));
+        assert!(text.contains("openzt_instance_creation_seconds_count{} 1\n"));
+        assert!(text.contains("openzt_instance_creation_failures_total{cause=\"pull_error\"} 1\n"));
+    }
+}
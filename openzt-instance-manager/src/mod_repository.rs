@@ -0,0 +1,249 @@
+//! Mods stored by the server
+//!
+//! `POST /api/mods` stores a .ztd under a name and version, and create requests list
+//! mods to install as `name` (the most recently uploaded version) or `name@version`.
+//! The server copies them into a directory mounted as the instance's mods directory,
+//! so a team shares one mod set instead of everyone keeping local copies. Each mod is
+//! kept as `<name>/<version>.ztd` in the repository directory, next to
+//! `<version>.json` with its size, hash and upload time.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadModRequest {
+    /// Mod name, also the .ztd's file name in the game's mods directory
+    pub name: String,
+    pub version: String,
+    /// Base64-encoded .ztd
+    pub data: String,
+}
+
+/// A mod in the repository, as returned by `GET /api/mods`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModEntry {
+    pub name: String,
+    pub version: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 of the .ztd, as hex
+    pub sha256: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+impl ModEntry {
+    /// File name the mod is installed as
+    pub fn file_name(&self) -> String {
+        format!("{}.ztd", self.name)
+    }
+}
+
+/// Why a mod couldn't be added to or found in the repository
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModError {
+    InvalidName(String),
+    AlreadyExists(String),
+    NotFound(String),
+}
+
+impl std::fmt::Display for ModError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModError::InvalidName(name) => write!(
+                f,
+                "Invalid mod name or version '{}': use letters, digits, '.', '-' and '_'",
+                name
+            ),
+            ModError::AlreadyExists(reference) => write!(f, "Mod {} already exists", reference),
+            ModError::NotFound(reference) => write!(f, "Mod {} not found", reference),
+        }
+    }
+}
+
+impl std::error::Error for ModError {}
+
+pub struct ModRepository {
+    dir: PathBuf,
+    entries: Vec<ModEntry>,
+}
+
+impl ModRepository {
+    /// An empty repository stored in `dir`, see [`ModRepository::load`]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Read the mods already in the repository directory, creating it if needed
+    ///
+    /// Returns the number of mods found.
+    pub fn load(&mut self) -> Result<usize> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create mod repository {}", self.dir.display()))?;
+
+        for mod_dir in std::fs::read_dir(&self.dir)? {
+            let mod_dir = mod_dir?.path();
+            if !mod_dir.is_dir() {
+                continue;
+            }
+            for file in std::fs::read_dir(&mod_dir)? {
+                let path = file?.path();
+                if path.extension().is_none_or(|extension| extension != "json") {
+                    continue;
+                }
+                let entry: ModEntry = match std::fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|content| Ok(serde_json::from_str(&content)?))
+                {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        tracing::warn!("Skipping mod repository entry {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
+                if !self.ztd_path(&entry).exists() {
+                    tracing::warn!("Skipping mod repository entry {}: the .ztd is missing", path.display());
+                    continue;
+                }
+                self.entries.push(entry);
+            }
+        }
+        Ok(self.entries.len())
+    }
+
+    /// Add a mod, a name and version can only be uploaded once
+    pub fn add(&mut self, name: &str, version: &str, ztd_bytes: &[u8]) -> Result<ModEntry> {
+        for part in [name, version] {
+            if !is_valid_part(part) {
+                return Err(ModError::InvalidName(part.to_string()).into());
+            }
+        }
+        if self.find(name, Some(version)).is_some() {
+            return Err(ModError::AlreadyExists(format!("{}@{}", name, version)).into());
+        }
+
+        let entry = ModEntry {
+            name: name.to_string(),
+            version: version.to_string(),
+            size: ztd_bytes.len() as u64,
+            sha256: Sha256::digest(ztd_bytes).iter().map(|byte| format!("{:02x}", byte)).collect(),
+            uploaded_at: Utc::now(),
+        };
+        let mod_dir = self.dir.join(name);
+        std::fs::create_dir_all(&mod_dir)
+            .with_context(|| format!("Failed to create {}", mod_dir.display()))?;
+        std::fs::write(self.ztd_path(&entry), ztd_bytes).context("Failed to write mod to the repository")?;
+        std::fs::write(mod_dir.join(format!("{}.json", version)), serde_json::to_string_pretty(&entry)?)
+            .context("Failed to write mod repository entry")?;
+
+        tracing::info!("Added mod {}@{} to the repository", name, version);
+        self.entries.push(entry.clone());
+        Ok(entry)
+    }
+
+    /// The mod a create request refers to as `name` or `name@version`
+    ///
+    /// Without a version, the most recently uploaded version is used.
+    pub fn resolve(&self, reference: &str) -> Result<&ModEntry, ModError> {
+        let (name, version) = match reference.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (reference, None),
+        };
+        self.find(name, version).ok_or_else(|| ModError::NotFound(reference.to_string()))
+    }
+
+    /// Every mod, by name and then upload time
+    pub fn list(&self) -> Vec<ModEntry> {
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.uploaded_at.cmp(&b.uploaded_at)));
+        entries
+    }
+
+    /// Remove a version of a mod, returning whether it was in the repository
+    ///
+    /// Instances already created with it keep running, they use a copy.
+    pub fn remove(&mut self, name: &str, version: &str) -> Result<bool> {
+        let Some(index) = self.entries.iter().position(|entry| entry.name == name && entry.version == version) else {
+            return Ok(false);
+        };
+        let entry = self.entries.remove(index);
+        std::fs::remove_file(self.ztd_path(&entry)).context("Failed to remove mod from the repository")?;
+        let mod_dir = self.dir.join(name);
+        let _ = std::fs::remove_file(mod_dir.join(format!("{}.json", version)));
+        // Only succeeds once the last version is gone
+        let _ = std::fs::remove_dir(&mod_dir);
+        Ok(true)
+    }
+
+    /// Where a mod's .ztd is stored
+    pub fn ztd_path(&self, entry: &ModEntry) -> PathBuf {
+        self.dir.join(&entry.name).join(format!("{}.ztd", entry.version))
+    }
+
+    fn find(&self, name: &str, version: Option<&str>) -> Option<&ModEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.name == name && version.is_none_or(|version| entry.version == version))
+            .max_by_key(|entry| entry.uploaded_at)
+    }
+}
+
+/// Names and versions become paths, so they're kept to characters that can't escape the repository
+fn is_valid_part(part: &str) -> bool {
+    !part.is_empty()
+        && !part.starts_with('.')
+        && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repository_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openzt-mod-repository-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_add_resolve_and_reload() {
+        let dir = repository_dir();
+        let mut repository = ModRepository::new(&dir);
+        assert_eq!(repository.load().unwrap(), 0);
+
+        repository.add("savanna-pack", "1.0.0", b"ztd 1").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let latest = repository.add("savanna-pack", "1.1.0", b"ztd 2").unwrap();
+        assert_eq!(latest.file_name(), "savanna-pack.ztd");
+
+        assert_eq!(repository.resolve("savanna-pack").unwrap().version, "1.1.0");
+        assert_eq!(repository.resolve("savanna-pack@1.0.0").unwrap().version, "1.0.0");
+        assert_eq!(
+            repository.resolve("savanna-pack@2.0.0"),
+            Err(ModError::NotFound("savanna-pack@2.0.0".to_string()))
+        );
+
+        let error = repository.add("savanna-pack", "1.0.0", b"ztd 3").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ModError>(),
+            Some(&ModError::AlreadyExists("savanna-pack@1.0.0".to_string()))
+        );
+        assert!(repository.add("../etc", "1.0.0", b"ztd").is_err());
+        assert!(repository.add("savanna-pack", "1.0/../..", b"ztd").is_err());
+
+        let mut reloaded = ModRepository::new(&dir);
+        assert_eq!(reloaded.load().unwrap(), 2);
+        assert_eq!(reloaded.resolve("savanna-pack").unwrap(), &latest);
+        assert_eq!(std::fs::read(reloaded.ztd_path(&latest)).unwrap(), b"ztd 2");
+
+        assert!(reloaded.remove("savanna-pack", "1.1.0").unwrap());
+        assert!(!reloaded.remove("savanna-pack", "1.1.0").unwrap());
+        assert_eq!(reloaded.resolve("savanna-pack").unwrap().version, "1.0.0");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
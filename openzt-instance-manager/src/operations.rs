@@ -0,0 +1,101 @@
+//! Serializing the operations on an instance
+//!
+//! Creating, starting, stopping, restarting, deleting, restoring and purging an
+//! instance each hold its operation lock while they run, so two of them never issue
+//! conflicting Docker calls for the same container. An operation that finds another
+//! one in flight fails with 409 Conflict instead of waiting for it; the scheduler and
+//! automatic restarts skip the instance.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// What an instance's operation lock is held for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Create,
+    Start,
+    Stop,
+    Restart,
+    Delete,
+    Restore,
+    Purge,
+    /// The whole test run an instance was created for
+    TestRun,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Operation::Create => "create",
+            Operation::Start => "start",
+            Operation::Stop => "stop",
+            Operation::Restart => "restart",
+            Operation::Delete => "delete",
+            Operation::Restore => "restore",
+            Operation::Purge => "purge",
+            Operation::TestRun => "test run",
+        }
+    }
+}
+
+/// The operation in flight on each instance
+#[derive(Debug, Clone, Default)]
+pub struct OperationLocks {
+    in_flight: Arc<Mutex<HashMap<String, Operation>>>,
+}
+
+impl OperationLocks {
+    /// Take instance `id`'s lock for `operation`, or return the operation holding it
+    pub fn try_lock(&self, id: &str, operation: Operation) -> Result<OperationGuard, Operation> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(holder) = in_flight.get(id) {
+            return Err(*holder);
+        }
+        in_flight.insert(id.to_string(), operation);
+        Ok(OperationGuard {
+            locks: self.clone(),
+            id: id.to_string(),
+        })
+    }
+
+    /// The operation in flight on instance `id`, if any
+    pub fn in_flight(&self, id: &str) -> Option<Operation> {
+        self.in_flight.lock().unwrap_or_else(PoisonError::into_inner).get(id).copied()
+    }
+}
+
+/// An instance's operation lock, released when dropped
+#[must_use = "the lock is released as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct OperationGuard {
+    locks: OperationLocks,
+    id: String,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        self.locks.in_flight.lock().unwrap_or_else(PoisonError::into_inner).remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conflicting_operations() {
+        let locks = OperationLocks::default();
+        let guard = locks.try_lock("a", Operation::Delete).unwrap();
+        assert_eq!(locks.try_lock("a", Operation::Start).unwrap_err(), Operation::Delete);
+        assert_eq!(locks.in_flight("a"), Some(Operation::Delete));
+
+        // Other instances aren't affected
+        let other = locks.try_lock("b", Operation::Start).unwrap();
+
+        drop(guard);
+        assert_eq!(locks.in_flight("a"), None);
+        assert!(locks.try_lock("a", Operation::Start).is_ok());
+        assert_eq!(locks.in_flight("b"), Some(Operation::Start));
+        drop(other);
+    }
+}
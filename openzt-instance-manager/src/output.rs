@@ -3,8 +3,14 @@
 //! This module provides utilities for formatting and displaying output
 //! in various formats (table, JSON) with colored terminal output.
 
-use crate::instance::{CreateInstanceResponse, InstanceDetails, LogsResponse};
-use console::{style, Color};
+use crate::client::UploadProgress;
+use crate::instance::{CreateInstanceResponse, InstanceDetails, InstanceEvent, InstanceEventKind, LogsResponse};
+use crate::instance_diff::FieldDiff;
+use chrono::{DateTime, Utc};
+use console::{style, Color, Term};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::OnceLock;
+use std::time::Duration;
 use tabled::{
     settings::{
         object::Rows,
@@ -35,24 +41,232 @@ impl OutputFormat {
     }
 }
 
+/// Error categories reported to scripts through the process exit code
+///
+/// | Exit code | Code          | Meaning                                         |
+/// |-----------|---------------|-------------------------------------------------|
+/// | 1         | `error`       | General failure (bad arguments, local I/O, ...) |
+/// | 2         | `not_found`   | Instance or resource does not exist             |
+/// | 3         | `ambiguous_id`| Short ID prefix matches more than one instance  |
+/// | 4         | `server_error`| API server failed or could not be reached       |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    Error,
+    NotFound,
+    AmbiguousId,
+    ServerError,
+}
+
+impl ErrorCode {
+    /// Process exit code for this error category
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Error => 1,
+            Self::NotFound => 2,
+            Self::AmbiguousId => 3,
+            Self::ServerError => 4,
+        }
+    }
+
+    /// Machine-readable identifier used in JSON error output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::NotFound => "not_found",
+            Self::AmbiguousId => "ambiguous_id",
+            Self::ServerError => "server_error",
+        }
+    }
+
+    /// Classify an error returned by `InstanceClient`
+    #[cfg(feature = "cli")]
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(api_error) = cause.downcast_ref::<crate::client::ApiError>() {
+                return if api_error.status == reqwest::StatusCode::NOT_FOUND {
+                    Self::NotFound
+                } else if api_error.status.is_server_error() {
+                    Self::ServerError
+                } else {
+                    Self::Error
+                };
+            }
+            if cause.downcast_ref::<reqwest::Error>().is_some() {
+                return Self::ServerError;
+            }
+        }
+        Self::Error
+    }
+}
+
+/// Report an error in the selected output format and exit with its code
+///
+/// Table output prints a styled message; JSON output prints
+/// `{"error": ..., "code": ...}` on stderr so wrappers can branch on it.
+pub fn exit_with_error(msg: &str, code: ErrorCode, format: OutputFormat) -> ! {
+    match format {
+        OutputFormat::Json => {
+            eprintln!("{}", serde_json::json!({ "error": msg, "code": code.as_str() }));
+        }
+        OutputFormat::Table => print_error(msg),
+    }
+    std::process::exit(code.exit_code());
+}
+
+/// Configure color and glyph output for this process
+///
+/// Colors are disabled by `--no-color` or a non-empty `NO_COLOR` environment
+/// variable; the `console` crate already disables them when a stream is not a
+/// terminal. Status glyphs are replaced by plain prefixes when the stream they
+/// are written to is not a terminal, so piped output stays easy to parse.
+pub fn init_styling(no_color: bool) {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    if no_color || no_color_env {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+/// Table border styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableStyle {
+    /// Unicode box drawing
+    #[default]
+    Modern,
+    /// Plain ASCII borders, for terminals without box drawing glyphs
+    Ascii,
+    /// GitHub-flavored markdown, for pasting into issues
+    Markdown,
+    /// No borders
+    Compact,
+}
+
+impl std::str::FromStr for TableStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "modern" => Ok(Self::Modern),
+            "ascii" => Ok(Self::Ascii),
+            "markdown" => Ok(Self::Markdown),
+            "compact" => Ok(Self::Compact),
+            _ => Err(format!("Invalid table style: '{}'", s)),
+        }
+    }
+}
+
+/// Display settings, set once by `init_display`
+static DISPLAY: OnceLock<DisplaySettings> = OnceLock::new();
+
+/// How tables and timestamps are displayed in table output
+#[derive(Debug, Clone)]
+pub struct DisplaySettings {
+    /// strftime format for absolute timestamps (`None` = per-view default)
+    pub date_format: Option<String>,
+    /// Show creation times in instance lists relative to now
+    pub relative_times: bool,
+    /// Border style for tables
+    pub table_style: TableStyle,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            date_format: None,
+            relative_times: true,
+            table_style: TableStyle::default(),
+        }
+    }
+}
+
+/// Configure table and timestamp display for this process
+///
+/// An invalid `date_format` is reported and ignored rather than failing
+/// every command that prints a time.
+pub fn init_display(mut settings: DisplaySettings) {
+    if let Some(date_format) = &settings.date_format
+        && chrono::format::StrftimeItems::new(date_format).any(|item| item == chrono::format::Item::Error)
+    {
+        print_warning(&format!("Ignoring invalid date_format in client config: '{}'", date_format));
+        settings.date_format = None;
+    }
+    let _ = DISPLAY.set(settings);
+}
+
+fn display() -> &'static DisplaySettings {
+    DISPLAY.get_or_init(DisplaySettings::default)
+}
+
+/// Apply the configured border style to a table
+fn apply_table_style(table: &mut Table) {
+    match display().table_style {
+        TableStyle::Modern => table.with(Style::modern()),
+        TableStyle::Ascii => table.with(Style::ascii()),
+        TableStyle::Markdown => table.with(Style::markdown()),
+        TableStyle::Compact => table.with(Style::blank()),
+    };
+}
+
+/// Format an absolute timestamp, using the configured date format or `default_format`
+fn format_absolute_time(time: DateTime<Utc>, default_format: &str) -> String {
+    let format = display().date_format.as_deref().unwrap_or(default_format);
+    time.format(format).to_string()
+}
+
+/// Format how long ago `time` was, e.g. "45s ago", "5m ago", "2d ago"
+pub fn format_relative_time(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let secs = now.signed_duration_since(time).num_seconds();
+    match secs {
+        // Small negative ages come from clock skew between client and server
+        ..=0 => "just now".to_string(),
+        1..60 => format!("{}s ago", secs),
+        60..3_600 => format!("{}m ago", secs / 60),
+        3_600..86_400 => format!("{}h ago", secs / 3_600),
+        86_400..31_536_000 => format!("{}d ago", secs / 86_400),
+        _ => format!("{}y ago", secs / 31_536_000),
+    }
+}
+
+/// Build the prefix for a status message written to stdout or stderr
+///
+/// Terminals get a colored glyph; anything else gets `fallback` (which may be empty).
+fn status_prefix(glyph: &str, fallback: &str, color: Color, stderr: bool) -> String {
+    let term = if stderr { Term::stderr() } else { Term::stdout() };
+    if term.is_term() {
+        let styled = style(glyph).fg(color);
+        let styled = if stderr { styled.for_stderr() } else { styled };
+        format!("{} ", styled)
+    } else {
+        fallback.to_string()
+    }
+}
+
 /// Print a success message with green checkmark
 pub fn print_success(msg: &str) {
-    println!("{} {}", style("✓").fg(Color::Green), msg);
+    println!("{}{}", status_prefix("✓", "", Color::Green, false), msg);
 }
 
 /// Print an error message with red X
 pub fn print_error(msg: &str) {
-    eprintln!("{} {}", style("✗").fg(Color::Red), style(msg).fg(Color::Red));
+    eprintln!(
+        "{}{}",
+        status_prefix("✗", "error: ", Color::Red, true),
+        style(msg).fg(Color::Red).for_stderr()
+    );
 }
 
 /// Print an info message with blue info icon
 pub fn print_info(msg: &str) {
-    println!("{} {}", style("ℹ").fg(Color::Cyan), style(msg).fg(Color::Cyan));
+    println!("{}{}", status_prefix("ℹ", "", Color::Cyan, false), style(msg).fg(Color::Cyan));
 }
 
 /// Print a warning message with yellow warning icon
 pub fn print_warning(msg: &str) {
-    eprintln!("{} {}", style("⚠").fg(Color::Yellow), style(msg).fg(Color::Yellow));
+    eprintln!(
+        "{}{}",
+        status_prefix("⚠", "warning: ", Color::Yellow, true),
+        style(msg).fg(Color::Yellow).for_stderr()
+    );
 }
 
 /// Print instance details
@@ -73,10 +287,14 @@ pub fn print_instance(instance: &InstanceDetails, format: OutputFormat) {
 fn print_instance_table(instance: &InstanceDetails) {
     println!();
     println!("  {} {}", style("ID:").fg(Color::Cyan), &instance.id[..8]);
+    if let Some(name) = &instance.name {
+        println!("  {} {}", style("Name:").fg(Color::Cyan), style(name).bold());
+    }
     println!(
-        "  {} {}",
+        "  {} {} {}",
         style("Created:").fg(Color::Cyan),
-        instance.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        format_absolute_time(instance.created_at, "%Y-%m-%d %H:%M:%S UTC"),
+        style(format!("({})", format_relative_time(instance.created_at, Utc::now()))).dim()
     );
     println!(
         "  {} {}",
@@ -85,14 +303,73 @@ fn print_instance_table(instance: &InstanceDetails) {
     );
     println!("  {} {}", style("VNC Port:").fg(Color::Cyan), instance.vnc_port);
     println!("  {} {}", style("Console:").fg(Color::Cyan), instance.console_port);
-    println!(
-        "  {} {}",
-        style("Status:").fg(Color::Cyan),
-        format_status(&instance.status)
-    );
+    if instance.ports_outside_range {
+        println!(
+            "  {}",
+            style("(ports are outside the manager's configured ranges)").fg(Color::Yellow)
+        );
+    }
+    print!("  {} {}", style("Status:").fg(Color::Cyan), format_status(&instance.status));
+    // An error's message is its status already
+    match &instance.status_message {
+        Some(message) if *message != instance.status => println!(" {}", style(format!("({})", message)).dim()),
+        _ => println!(),
+    }
+    if let Some(purge_at) = instance.purge_at {
+        println!(
+            "  {} {} {}",
+            style("Purged:").fg(Color::Cyan),
+            format_absolute_time(purge_at, "%Y-%m-%d %H:%M:%S UTC"),
+            style("(unless restored)").dim()
+        );
+    }
+    if let Some(last_exit) = &instance.last_exit {
+        println!(
+            "  {} {} {}",
+            style("Last Exit:").fg(Color::Cyan),
+            style(last_exit.describe()).fg(Color::Red),
+            style(format!("({})", format_relative_time(last_exit.exited_at, Utc::now()))).dim()
+        );
+    }
+    if let Some(creation) = &instance.creation {
+        let phases: Vec<String> = creation
+            .phases_ms
+            .iter()
+            .map(|(phase, ms)| format!("{} {}ms", phase.as_str(), ms))
+            .collect();
+        print!("  {} {}", style("Creation:").fg(Color::Cyan), phases.join(", "));
+        match creation.failure {
+            Some(failure) => println!(" {}", style(format!("(failed: {})", failure.as_str())).fg(Color::Red)),
+            None => println!(),
+        }
+    }
     if !instance.container_id.is_empty() {
         println!("  {} {}", style("Container:").fg(Color::Cyan), &instance.container_id[..12]);
     }
+    if let Some(cpulimit) = instance.config.cpulimit {
+        println!("  {} {} cores", style("CPU Limit:").fg(Color::Cyan), cpulimit);
+    }
+    if let Some(memory_mb) = instance.config.memory_mb {
+        println!("  {} {} MB", style("Memory Limit:").fg(Color::Cyan), memory_mb);
+    }
+    if let Some(wine_debug_level) = &instance.config.wine_debug_level {
+        println!("  {} {}", style("Wine Debug:").fg(Color::Cyan), wine_debug_level);
+    }
+    if let Some(image) = &instance.config.image {
+        println!("  {} {}", style("Image:").fg(Color::Cyan), image);
+    }
+    if !instance.labels.is_empty() {
+        let labels: Vec<String> = instance.labels.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        println!("  {} {}", style("Labels:").fg(Color::Cyan), labels.join(", "));
+    }
+    for sidecar in &instance.sidecars {
+        println!(
+            "  {} {} {}",
+            style("Sidecar:").fg(Color::Cyan),
+            sidecar.name,
+            format_status(&sidecar.status)
+        );
+    }
     println!();
 }
 
@@ -100,15 +377,15 @@ fn print_instance_table(instance: &InstanceDetails) {
 fn format_status(status: &str) -> String {
     match status {
         "running" => style(status).fg(Color::Green).bold().to_string(),
-        "creating" => style(status).fg(Color::Yellow).bold().to_string(),
-        "stopped" => style(status).fg(Color::Black).bold().to_string(),
+        "creating" | "starting" | "degraded" | "adopted" | "unknown" => style(status).fg(Color::Yellow).bold().to_string(),
+        "stopped" | "trashed" => style(status).fg(Color::Black).bold().to_string(),
         s if s.starts_with("error:") || s.starts_with("Error") => style(status).fg(Color::Red).bold().to_string(),
         _ => style(status).fg(Color::Red).bold().to_string(),
     }
 }
 
-/// Print a list of instances
-pub fn print_instance_list(instances: &[InstanceDetails], format: OutputFormat) {
+/// Print a list of instances (`wide` adds container and config columns to the table)
+pub fn print_instance_list(instances: &[InstanceDetails], format: OutputFormat, wide: bool) {
     if instances.is_empty() {
         print_info("No instances found");
         return;
@@ -121,21 +398,21 @@ pub fn print_instance_list(instances: &[InstanceDetails], format: OutputFormat)
             }
         }
         OutputFormat::Table => {
-            print_instance_list_table(instances);
+            print_instance_list_table(instances, wide);
         }
     }
 }
 
 /// Print instance list in table format
 #[cfg(not(feature = "cli"))]
-fn print_instance_list_table(instances: &[InstanceDetails]) {
+fn print_instance_list_table(instances: &[InstanceDetails], wide: bool) {
     // Stub for non-CLI builds
-    let _ = instances;
+    let _ = (instances, wide);
 }
 
 /// Print instance list in table format
 #[cfg(feature = "cli")]
-fn print_instance_list_table(instances: &[InstanceDetails]) {
+fn print_instance_list_table(instances: &[InstanceDetails], wide: bool) {
     #[derive(Tabled)]
     #[tabled(rename_all = "PASCAL")]
     struct InstanceRow {
@@ -153,23 +430,47 @@ fn print_instance_list_table(instances: &[InstanceDetails]) {
         vnc_url: String,
     }
 
+    #[derive(Tabled)]
+    #[tabled(rename_all = "PASCAL")]
+    struct WideInstanceRow {
+        #[tabled(inline)]
+        row: InstanceRow,
+        #[tabled(rename = "Container")]
+        container_id: String,
+        #[tabled(rename = "CPU Limit")]
+        cpulimit: String,
+        #[tabled(rename = "Wine Debug")]
+        wine_debug_level: String,
+    }
+
     // Calculate safe ID length to avoid duplicates
     let id_length = calculate_safe_id_length(instances);
+    let now = Utc::now();
 
-    let rows: Vec<InstanceRow> = instances
-        .iter()
-        .map(|i| InstanceRow {
-            id: i.id[..id_length.min(i.id.len())].to_string(),
-            created_at: i.created_at.format("%Y-%m-%d %H:%M").to_string(),
-            vnc_port: i.vnc_port,
-            console_port: i.console_port,
-            status: i.status.clone(),
-            vnc_url: i.vnc_url.clone(),
-        })
-        .collect();
-
-    let mut table = Table::new(rows);
-    table.with(Style::modern());
+    let rows = instances.iter().map(|i| InstanceRow {
+        id: i.id[..id_length.min(i.id.len())].to_string(),
+        created_at: if display().relative_times {
+            format_relative_time(i.created_at, now)
+        } else {
+            format_absolute_time(i.created_at, "%Y-%m-%d %H:%M")
+        },
+        vnc_port: i.vnc_port,
+        console_port: i.console_port,
+        status: i.status.clone(),
+        vnc_url: i.vnc_url.clone(),
+    });
+
+    let mut table = if wide {
+        Table::new(rows.zip(instances).map(|(row, i)| WideInstanceRow {
+            row,
+            container_id: i.container_id.chars().take(12).collect(),
+            cpulimit: i.config.cpulimit.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string()),
+            wine_debug_level: i.config.wine_debug_level.clone().unwrap_or_else(|| "-".to_string()),
+        }))
+    } else {
+        Table::new(rows)
+    };
+    apply_table_style(&mut table);
     table.with(Modify::new(Rows::new(1..)).with(Alignment::left()));
 
     println!();
@@ -177,8 +478,123 @@ fn print_instance_list_table(instances: &[InstanceDetails]) {
     println!();
 }
 
+/// Print the differences between two instances (`openzt diff`)
+pub fn print_instance_diff(left: &InstanceDetails, right: &InstanceDetails, diffs: &[FieldDiff], format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let json = serde_json::json!({
+                "left": left.id,
+                "right": right.id,
+                "differences": diffs,
+            });
+            if let Ok(json) = serde_json::to_string_pretty(&json) {
+                println!("{}", json);
+            }
+        }
+        OutputFormat::Table => {
+            if diffs.is_empty() {
+                print_success(&format!("No differences between {} and {}", &left.id[..8], &right.id[..8]));
+                return;
+            }
+
+            println!("{}", style(format!("--- {}", &left.id[..8])).fg(Color::Red));
+            println!("{}", style(format!("+++ {}", &right.id[..8])).fg(Color::Green));
+            for diff in diffs {
+                println!();
+                println!("  {}", style(&diff.field).fg(Color::Cyan).bold());
+                println!("  {}", style(format!("- {}", format_diff_value(diff.left.as_ref()))).fg(Color::Red));
+                println!("  {}", style(format!("+ {}", format_diff_value(diff.right.as_ref()))).fg(Color::Green));
+            }
+            println!();
+        }
+    }
+}
+
+/// Render a diffed field value (strings unquoted, unset fields as "(unset)")
+fn format_diff_value(value: Option<&serde_json::Value>) -> String {
+    match value {
+        None => "(unset)".to_string(),
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// Print a single field value for scripts (`openzt get --field`)
+///
+/// Strings are printed without quotes so the output can be used directly;
+/// `--output json` prints the JSON-encoded value instead.
+pub fn print_field_value(value: &serde_json::Value, format: OutputFormat) {
+    match (format, value) {
+        (OutputFormat::Table, serde_json::Value::String(s)) => println!("{}", s),
+        _ => println!("{}", value),
+    }
+}
+
+/// Print a single log line, rendering a leading RFC 3339 timestamp compactly
+/// when `timestamps` is set
+pub fn print_log_line(line: &str, timestamps: bool) {
+    println!("{}", format_log_line(line, timestamps));
+}
+
+/// Format a log line for display (see `print_log_line`)
+fn format_log_line(line: &str, timestamps: bool) -> String {
+    if !timestamps {
+        return line.to_string();
+    }
+
+    let (first, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    match chrono::DateTime::parse_from_rfc3339(first) {
+        Ok(ts) => format!(
+            "{} {}",
+            style(ts.with_timezone(&chrono::Utc).format("%Y-%m-%d %H:%M:%S")).dim(),
+            rest.trim_start()
+        ),
+        Err(_) => line.to_string(),
+    }
+}
+
+/// Colors cycled through to tell instances apart in aggregated logs
+const LOG_PREFIX_COLORS: [Color; 6] = [Color::Cyan, Color::Green, Color::Yellow, Color::Magenta, Color::Blue, Color::Red];
+
+/// Print a log line prefixed with a color-coded short instance ID
+///
+/// Used when `openzt logs` reads from several instances at once; `index` is
+/// the instance's position on the command line and picks the prefix color.
+pub fn print_prefixed_log_line(instance_id: &str, index: usize, line: &str, timestamps: bool) {
+    let color = LOG_PREFIX_COLORS[index % LOG_PREFIX_COLORS.len()];
+    let short_id = &instance_id[..instance_id.len().min(8)];
+    println!("{} {}", style(format!("{} |", short_id)).fg(color), format_log_line(line, timestamps));
+}
+
+/// Interleave log output from several instances by leading timestamp
+///
+/// Lines carrying an RFC 3339 timestamp are ordered by it; lines without one
+/// (e.g. wrapped messages) stay behind the line before them. Output without
+/// any timestamps keeps each source's lines together in the given order.
+/// Returns each line with the index of the source it came from.
+pub fn merge_log_lines<'a>(sources: &[&'a str]) -> Vec<(usize, &'a str)> {
+    let mut lines = Vec::new();
+    for (index, logs) in sources.iter().enumerate() {
+        let mut last = None;
+        for line in logs.lines() {
+            if let Some(ts) = line
+                .split_whitespace()
+                .next()
+                .and_then(|token| chrono::DateTime::parse_from_rfc3339(token).ok())
+            {
+                last = Some(ts);
+            }
+            lines.push((last, index, line));
+        }
+    }
+
+    // Stable sort, so lines sharing a timestamp keep their original order
+    lines.sort_by_key(|(ts, _, _)| *ts);
+    lines.into_iter().map(|(_, index, line)| (index, line)).collect()
+}
+
 /// Print logs output
-pub fn print_logs(logs_response: &LogsResponse, output_json: bool) {
+pub fn print_logs(logs_response: &LogsResponse, output_json: bool, timestamps: bool) {
     if output_json {
         if let Ok(json) = serde_json::to_string_pretty(logs_response) {
             println!("{}", json);
@@ -192,12 +608,84 @@ pub fn print_logs(logs_response: &LogsResponse, output_json: bool) {
         println!();
         if logs_response.logs.is_empty() {
             print_info("(no logs available)");
+        } else if timestamps {
+            for line in logs_response.logs.lines() {
+                print_log_line(line, true);
+            }
         } else {
             println!("{}", logs_response.logs);
         }
     }
 }
 
+/// Print a lifecycle event as a single line (one JSON object per line with `--output json`)
+pub fn print_event(event: &InstanceEvent, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            if let Ok(json) = serde_json::to_string(event) {
+                println!("{}", json);
+            }
+        }
+        OutputFormat::Table => {
+            let color = match event.kind {
+                InstanceEventKind::Running | InstanceEventKind::Started | InstanceEventKind::Restarted => Color::Green,
+                InstanceEventKind::Creating
+                | InstanceEventKind::Starting
+                | InstanceEventKind::Restarting
+                | InstanceEventKind::Restored => Color::Yellow,
+                InstanceEventKind::Error
+                | InstanceEventKind::Crashed
+                | InstanceEventKind::OomKilled
+                | InstanceEventKind::Failed => Color::Red,
+                InstanceEventKind::Stopped | InstanceEventKind::Deleted | InstanceEventKind::Trashed => Color::Magenta,
+            };
+            let mut line = format!(
+                "{} {} {:<10}",
+                style(event.timestamp.format("%Y-%m-%d %H:%M:%S")).dim(),
+                style(&event.instance_id[..8.min(event.instance_id.len())]).fg(Color::Cyan),
+                style(event.kind.as_str()).fg(color).bold()
+            );
+            if let Some(message) = &event.message {
+                line.push(' ');
+                line.push_str(message);
+            }
+            println!("{}", line);
+        }
+    }
+}
+
+/// Progress bar for uploads, drawn on stderr
+///
+/// Hidden with `--output json` and when stderr is not a terminal.
+pub fn upload_progress_bar(format: OutputFormat) -> ProgressBar {
+    if format == OutputFormat::Json || !Term::stderr().is_term() {
+        return ProgressBar::hidden();
+    }
+
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::with_template("Uploading [{bar:30.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> "),
+    );
+    bar
+}
+
+/// Upload callback that drives `bar`, switching to a spinner once the upload completes
+pub fn upload_progress_callback(bar: &ProgressBar, waiting_message: &'static str) -> UploadProgress {
+    let bar = bar.clone();
+    Box::new(move |sent, total| {
+        bar.set_length(total);
+        bar.set_position(sent);
+        if sent >= total {
+            // The server does its work (e.g. starting the container) after the body arrives
+            bar.set_style(ProgressStyle::default_spinner());
+            bar.set_message(waiting_message);
+            bar.enable_steady_tick(Duration::from_millis(100));
+        }
+    })
+}
+
 /// Print the result of creating an instance
 pub fn print_create_result(response: &CreateInstanceResponse, output_json: bool) {
     if output_json {
@@ -248,6 +736,23 @@ pub fn confirm_action(action: &str, target: &str) -> bool {
     matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
+/// Report an ID resolution error in the selected output format and exit
+#[cfg(feature = "cli")]
+pub fn exit_with_resolution_error(error: &ResolutionError, format: OutputFormat) -> ! {
+    let code = error.error_code();
+    match format {
+        OutputFormat::Json => {
+            let mut json = serde_json::json!({ "error": error.message(), "code": code.as_str() });
+            if let ResolutionError::Ambiguous { matches, .. } = error {
+                json["matches"] = matches.iter().map(|i| i.id.clone()).collect();
+            }
+            eprintln!("{}", json);
+        }
+        OutputFormat::Table => print_resolution_error(error),
+    }
+    std::process::exit(code.exit_code());
+}
+
 /// Print an ID resolution error with helpful context
 #[cfg(feature = "cli")]
 pub fn print_resolution_error(error: &ResolutionError) {
@@ -293,7 +798,7 @@ fn print_ambiguous_matches(matches: &[InstanceDetails]) {
         .collect();
 
     let mut table = Table::new(rows);
-    table.with(Style::modern());
+    apply_table_style(&mut table);
     table.with(Modify::new(Rows::new(1..)).with(Alignment::left()));
 
     println!("{}", table);
@@ -323,4 +828,79 @@ mod tests {
         assert_eq!(OutputFormat::from_str("JSON"), Some(OutputFormat::Json));
         assert_eq!(OutputFormat::from_str("invalid"), None);
     }
+
+    #[test]
+    fn test_format_log_line() {
+        console::set_colors_enabled(false);
+        assert_eq!(
+            format_log_line("2025-06-01T12:34:56.789012345Z wine: starting", true),
+            "2025-06-01 12:34:56 wine: starting"
+        );
+        assert_eq!(format_log_line("no timestamp here", true), "no timestamp here");
+        assert_eq!(
+            format_log_line("2025-06-01T12:34:56Z wine: starting", false),
+            "2025-06-01T12:34:56Z wine: starting"
+        );
+    }
+
+    #[test]
+    fn test_merge_log_lines() {
+        let a = "2025-06-01T12:00:00Z a1\n  continued\n2025-06-01T12:00:02Z a2";
+        let b = "2025-06-01T12:00:01Z b1\n2025-06-01T12:00:03Z b2";
+        let merged = merge_log_lines(&[a, b]);
+        assert_eq!(
+            merged,
+            vec![
+                (0, "2025-06-01T12:00:00Z a1"),
+                (0, "  continued"),
+                (1, "2025-06-01T12:00:01Z b1"),
+                (0, "2025-06-01T12:00:02Z a2"),
+                (1, "2025-06-01T12:00:03Z b2"),
+            ]
+        );
+
+        // Without timestamps each source stays together
+        assert_eq!(merge_log_lines(&["x", "y"]), vec![(0, "x"), (1, "y")]);
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        let now = Utc::now();
+        let ago = |secs: i64| format_relative_time(now - chrono::Duration::seconds(secs), now);
+        assert_eq!(ago(-5), "just now");
+        assert_eq!(ago(45), "45s ago");
+        assert_eq!(ago(5 * 60 + 59), "5m ago");
+        assert_eq!(ago(3 * 3_600), "3h ago");
+        assert_eq!(ago(2 * 86_400), "2d ago");
+        assert_eq!(ago(400 * 86_400), "1y ago");
+    }
+
+    #[test]
+    fn test_error_code_exit_codes() {
+        assert_eq!(ErrorCode::Error.exit_code(), 1);
+        assert_eq!(ErrorCode::NotFound.exit_code(), 2);
+        assert_eq!(ErrorCode::AmbiguousId.exit_code(), 3);
+        assert_eq!(ErrorCode::ServerError.exit_code(), 4);
+    }
+
+    #[test]
+    fn test_error_code_from_api_error() {
+        use crate::client::ApiError;
+        use reqwest::StatusCode;
+
+        let not_found = anyhow::Error::new(ApiError {
+            status: StatusCode::NOT_FOUND,
+            message: "Instance not found".to_string(),
+        })
+        .context("Failed to get instance");
+        assert_eq!(ErrorCode::from_error(&not_found), ErrorCode::NotFound);
+
+        let unavailable = anyhow::Error::new(ApiError {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            message: "No ports available".to_string(),
+        });
+        assert_eq!(ErrorCode::from_error(&unavailable), ErrorCode::ServerError);
+
+        assert_eq!(ErrorCode::from_error(&anyhow::anyhow!("bad input")), ErrorCode::Error);
+    }
 }
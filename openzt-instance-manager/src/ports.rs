@@ -1,5 +1,32 @@
-use std::collections::HashSet;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// How long a reserved pair may wait for its container to start by default
+pub const DEFAULT_RESERVATION_TTL: Duration = Duration::from_secs(600);
+
+/// How long a port that failed the bind probe is skipped before it is probed again
+pub const PROBE_RETRY: Duration = Duration::from_secs(60);
+
+/// Name of the Docker host the manager runs on
+pub const LOCAL_HOST: &str = "local";
+
+/// How host ports are chosen for new instances (`[ports] mode`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortMode {
+    /// Lowest free port from the separate VNC and console ranges
+    #[default]
+    Ranges,
+    /// Lowest free pair from a single range shared by both port kinds
+    Shared,
+    /// Random free ports from the configured ranges
+    Random,
+    /// Let Docker pick host ports and read them back once the container starts
+    Ephemeral,
+}
 
 #[derive(Debug, Clone)]
 pub struct PortPool {
@@ -7,6 +34,19 @@ pub struct PortPool {
     console_range: Range<u16>,
     allocated_vnc: HashSet<u16>,
     allocated_console: HashSet<u16>,
+    /// Ports found to be in use by other processes, with when they were last probed (only populated when probing)
+    external_vnc: HashMap<u16, Instant>,
+    external_console: HashMap<u16, Instant>,
+    /// Check that a port can be bound on the host before handing it out
+    probe: bool,
+    /// How long a port found in use is skipped before it is probed again
+    probe_retry: Duration,
+    /// Pairs handed out for an in-flight create, with the time they expire
+    reservations: HashMap<(u16, u16), Instant>,
+    reservation_ttl: Duration,
+    mode: PortMode,
+    /// Ports claimed by other services on the host (`[ports] excluded`)
+    excluded: HashSet<u16>,
 }
 
 impl PortPool {
@@ -16,36 +56,180 @@ impl PortPool {
             console_range,
             allocated_vnc: HashSet::new(),
             allocated_console: HashSet::new(),
+            external_vnc: HashMap::new(),
+            external_console: HashMap::new(),
+            probe: false,
+            probe_retry: PROBE_RETRY,
+            reservations: HashMap::new(),
+            reservation_ttl: DEFAULT_RESERVATION_TTL,
+            mode: PortMode::Ranges,
+            excluded: HashSet::new(),
         }
     }
 
+    /// Pool that carves a single range into consecutive (VNC, console) pairs
+    pub fn shared(range: Range<u16>) -> Self {
+        let mut pool = Self::new(range.clone(), range);
+        pool.mode = PortMode::Shared;
+        pool
+    }
+
+    /// Set the allocation mode; use `shared` to build a `PortMode::Shared` pool
+    pub fn with_mode(mut self, mode: PortMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Never hand out the given ports
+    pub fn with_excluded(mut self, ports: impl IntoIterator<Item = u16>) -> Self {
+        self.excluded.extend(ports);
+        self
+    }
+
+    /// Whether host ports are chosen by Docker rather than this pool
+    pub fn is_ephemeral(&self) -> bool {
+        self.mode == PortMode::Ephemeral
+    }
+
+    /// Enable bind-probing on allocation, skipping ports other processes are listening on
+    pub fn with_probing(mut self, probe: bool) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    /// Set how long a reserved pair is held before it is released unconfirmed
+    pub fn with_reservation_ttl(mut self, ttl: Duration) -> Self {
+        self.reservation_ttl = ttl;
+        self
+    }
+
     pub fn allocate_vnc(&mut self) -> Option<u16> {
-        for port in self.vnc_range.clone() {
-            if !self.allocated_vnc.contains(&port) {
-                self.allocated_vnc.insert(port);
-                return Some(port);
-            }
-        }
-        None
+        let candidates = self.candidates(&self.vnc_range);
+        allocate_from(candidates, &mut self.allocated_vnc, &mut self.external_vnc, self.probe.then_some(self.probe_retry))
     }
 
     pub fn allocate_console(&mut self) -> Option<u16> {
-        for port in self.console_range.clone() {
-            if !self.allocated_console.contains(&port) {
-                self.allocated_console.insert(port);
-                return Some(port);
-            }
+        let candidates = self.candidates(&self.console_range);
+        allocate_from(
+            candidates,
+            &mut self.allocated_console,
+            &mut self.external_console,
+            self.probe.then_some(self.probe_retry),
+        )
+    }
+
+    /// Ports of `range` in the order they should be tried
+    fn candidates(&self, range: &Range<u16>) -> Vec<u16> {
+        let mut ports: Vec<u16> = range.clone().filter(|port| !self.excluded.contains(port)).collect();
+        if self.mode == PortMode::Random {
+            ports.shuffle(&mut rand::rng());
         }
-        None
+        ports
     }
 
     /// Allocate both ports (VNC, Console) as a pair
+    ///
+    /// In ephemeral mode this returns `(0, 0)`, asking Docker to choose.
     pub fn allocate_pair(&mut self) -> Option<(u16, u16)> {
+        match self.mode {
+            PortMode::Ephemeral => return Some((0, 0)),
+            PortMode::Shared => return self.allocate_shared_pair(),
+            PortMode::Ranges | PortMode::Random => {}
+        }
+
         let vnc_port = self.allocate_vnc()?;
-        let console_port = self.allocate_console()?;
+        let Some(console_port) = self.allocate_console() else {
+            self.release_vnc(vnc_port);
+            return None;
+        };
         Some((vnc_port, console_port))
     }
 
+    /// Take the first slot of the shared range whose two ports are both free
+    fn allocate_shared_pair(&mut self) -> Option<(u16, u16)> {
+        let start = self.vnc_range.start;
+        let slots = self.vnc_range.len() / 2;
+        for slot in 0..slots as u16 {
+            let vnc_port = start + slot * 2;
+            let console_port = vnc_port + 1;
+            if self.shared_port_taken(vnc_port) || self.shared_port_taken(console_port) {
+                continue;
+            }
+            if self.probe {
+                let vnc_busy = port_in_use(vnc_port);
+                let console_busy = port_in_use(console_port);
+                let now = Instant::now();
+                record_probe(&mut self.external_vnc, vnc_port, vnc_busy, now);
+                record_probe(&mut self.external_console, console_port, console_busy, now);
+                if vnc_busy || console_busy {
+                    tracing::warn!("Ports {}-{} are in use by another process, skipping", vnc_port, console_port);
+                    continue;
+                }
+            }
+            self.allocated_vnc.insert(vnc_port);
+            self.allocated_console.insert(console_port);
+            return Some((vnc_port, console_port));
+        }
+        None
+    }
+
+    /// Whether a port of the shared range is held by either kind or was recently found in use by another process
+    fn shared_port_taken(&self, port: u16) -> bool {
+        self.allocated_vnc.contains(&port)
+            || self.allocated_console.contains(&port)
+            || recently_busy(&self.external_vnc, port, self.probe_retry)
+            || recently_busy(&self.external_console, port, self.probe_retry)
+            || self.excluded.contains(&port)
+    }
+
+    /// Allocate a pair for a container that has not started yet
+    ///
+    /// The pair is released by `release_expired` unless `confirm_pair` is
+    /// called within the reservation TTL.
+    pub fn reserve_pair(&mut self) -> Option<(u16, u16)> {
+        let pair = self.allocate_pair()?;
+        if self.is_ephemeral() {
+            // Nothing is held until Docker has assigned the ports
+            return Some(pair);
+        }
+        self.reservations.insert(pair, Instant::now() + self.reservation_ttl);
+        Some(pair)
+    }
+
+    /// Mark a reserved pair as in use by a running container
+    ///
+    /// Returns false if the reservation has already expired, in which case
+    /// the ports may have been handed out again.
+    pub fn confirm_pair(&mut self, vnc_port: u16, console_port: u16) -> bool {
+        self.reservations.remove(&(vnc_port, console_port)).is_some()
+    }
+
+    /// Release a reserved pair whose create failed
+    ///
+    /// Does nothing (and returns false) if the reservation already expired.
+    pub fn cancel_reservation(&mut self, vnc_port: u16, console_port: u16) -> bool {
+        if !self.confirm_pair(vnc_port, console_port) {
+            return false;
+        }
+        self.release_pair(vnc_port, console_port);
+        true
+    }
+
+    /// Release reservations that expired before `now`, returning the freed pairs
+    pub fn release_expired(&mut self, now: Instant) -> Vec<(u16, u16)> {
+        let expired: Vec<(u16, u16)> = self
+            .reservations
+            .iter()
+            .filter(|(_, expires)| **expires <= now)
+            .map(|(pair, _)| *pair)
+            .collect();
+
+        for &(vnc_port, console_port) in &expired {
+            self.cancel_reservation(vnc_port, console_port);
+        }
+        expired
+    }
+
     pub fn release_vnc(&mut self, port: u16) {
         self.allocated_vnc.remove(&port);
     }
@@ -61,27 +245,89 @@ impl PortPool {
     }
 
     pub fn vnc_available(&self) -> usize {
-        self.vnc_range.clone().count() - self.allocated_vnc.len()
+        if self.mode == PortMode::Shared {
+            return self.shared_pairs_available();
+        }
+        self.free_in(&self.vnc_range, &self.allocated_vnc, &self.external_vnc)
     }
 
     pub fn console_available(&self) -> usize {
-        self.console_range.clone().count() - self.allocated_console.len()
+        if self.mode == PortMode::Shared {
+            return self.shared_pairs_available();
+        }
+        self.free_in(&self.console_range, &self.allocated_console, &self.external_console)
+    }
+
+    fn free_in(&self, range: &Range<u16>, allocated: &HashSet<u16>, external: &HashMap<u16, Instant>) -> usize {
+        range
+            .clone()
+            .filter(|&port| !allocated.contains(&port) && !recently_busy(external, port, self.probe_retry) && !self.excluded.contains(&port))
+            .count()
+    }
+
+    /// Number of VNC ports the pool can hand out in total
+    pub fn vnc_total(&self) -> usize {
+        if self.mode == PortMode::Shared {
+            return self.shared_pairs_total();
+        }
+        self.vnc_range.len() - self.excluded_in(&self.vnc_range)
+    }
+
+    /// Number of console ports the pool can hand out in total
+    pub fn console_total(&self) -> usize {
+        if self.mode == PortMode::Shared {
+            return self.shared_pairs_total();
+        }
+        self.console_range.len() - self.excluded_in(&self.console_range)
+    }
+
+    fn excluded_in(&self, range: &Range<u16>) -> usize {
+        self.excluded.iter().filter(|port| range.contains(port)).count()
+    }
+
+    /// Pairs of the shared range not blocked by an excluded port
+    fn shared_pairs_total(&self) -> usize {
+        let start = self.vnc_range.start;
+        (0..(self.vnc_range.len() / 2) as u16)
+            .map(|slot| start + slot * 2)
+            .filter(|port| !self.excluded.contains(port) && !self.excluded.contains(&(port + 1)))
+            .count()
+    }
+
+    /// Free and total port counts
+    pub fn capacity(&self) -> PortCapacity {
+        PortCapacity {
+            vnc_available: self.vnc_available(),
+            vnc_total: self.vnc_total(),
+            console_available: self.console_available(),
+            console_total: self.console_total(),
+        }
+    }
+
+    fn shared_pairs_available(&self) -> usize {
+        let start = self.vnc_range.start;
+        (0..(self.vnc_range.len() / 2) as u16)
+            .map(|slot| start + slot * 2)
+            .filter(|&port| !self.shared_port_taken(port) && !self.shared_port_taken(port + 1))
+            .count()
     }
 
     /// Add an existing VNC port allocation (for recovery)
     pub fn add_existing_vnc(&mut self, port: u16) -> anyhow::Result<()> {
-        if !self.vnc_range.contains(&port) {
+        if !self.is_ephemeral() && !self.vnc_range.contains(&port) {
             return Err(anyhow::anyhow!("Port {} outside VNC range {:?}", port, self.vnc_range));
         }
+        self.external_vnc.remove(&port);
         self.allocated_vnc.insert(port);
         Ok(())
     }
 
     /// Add an existing console port allocation (for recovery)
     pub fn add_existing_console(&mut self, port: u16) -> anyhow::Result<()> {
-        if !self.console_range.contains(&port) {
+        if !self.is_ephemeral() && !self.console_range.contains(&port) {
             return Err(anyhow::anyhow!("Port {} outside console range {:?}", port, self.console_range));
         }
+        self.external_console.remove(&port);
         self.allocated_console.insert(port);
         Ok(())
     }
@@ -92,6 +338,149 @@ impl PortPool {
         self.add_existing_console(console_port)?;
         Ok(())
     }
+
+    /// Register a recovered container's ports, whatever they are
+    ///
+    /// Ports inside the configured ranges are marked allocated as with
+    /// `add_existing_pair`. Ports outside them are never handed out anyway, so
+    /// they are only reported: returns false if either port is out of range.
+    pub fn adopt_pair(&mut self, vnc_port: u16, console_port: u16) -> bool {
+        let vnc_in_range = self.add_existing_vnc(vnc_port).is_ok();
+        let console_in_range = self.add_existing_console(console_port).is_ok();
+        vnc_in_range && console_in_range
+    }
+}
+
+/// Free and total port counts for one host, or summed over all hosts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortCapacity {
+    pub vnc_available: usize,
+    pub vnc_total: usize,
+    pub console_available: usize,
+    pub console_total: usize,
+}
+
+impl std::ops::Add for PortCapacity {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            vnc_available: self.vnc_available + other.vnc_available,
+            vnc_total: self.vnc_total + other.vnc_total,
+            console_available: self.console_available + other.console_available,
+            console_total: self.console_total + other.console_total,
+        }
+    }
+}
+
+/// Port pools keyed by Docker host
+///
+/// Host ports only conflict on the same machine, so each host gets its own
+/// pool built from the configured ranges.
+#[derive(Debug, Clone)]
+pub struct HostPortPools {
+    /// Empty pool cloned for hosts seen for the first time
+    template: PortPool,
+    pools: BTreeMap<String, PortPool>,
+}
+
+impl HostPortPools {
+    /// Create the pools with only the local host registered
+    pub fn new(template: PortPool) -> Self {
+        let mut pools = BTreeMap::new();
+        pools.insert(LOCAL_HOST.to_string(), template.clone());
+        Self { template, pools }
+    }
+
+    /// Pool for `host`, registering the host if needed
+    pub fn host_mut(&mut self, host: &str) -> &mut PortPool {
+        self.pools
+            .entry(host.to_string())
+            .or_insert_with(|| self.template.clone())
+    }
+
+    pub fn local(&self) -> &PortPool {
+        &self.pools[LOCAL_HOST]
+    }
+
+    pub fn local_mut(&mut self) -> &mut PortPool {
+        self.host_mut(LOCAL_HOST)
+    }
+
+    /// Capacity of each registered host
+    pub fn host_capacity(&self) -> BTreeMap<String, PortCapacity> {
+        self.pools
+            .iter()
+            .map(|(host, pool)| (host.clone(), pool.capacity()))
+            .collect()
+    }
+
+    /// Capacity summed over all hosts
+    pub fn capacity(&self) -> PortCapacity {
+        self.pools
+            .values()
+            .map(PortPool::capacity)
+            .fold(PortCapacity::default(), |total, capacity| total + capacity)
+    }
+
+    /// Release expired reservations on every host, returning the freed pairs by host
+    pub fn release_expired(&mut self, now: Instant) -> Vec<(String, (u16, u16))> {
+        self.pools
+            .iter_mut()
+            .flat_map(|(host, pool)| {
+                pool.release_expired(now)
+                    .into_iter()
+                    .map(move |pair| (host.clone(), pair))
+            })
+            .collect()
+    }
+}
+
+/// Take the first free port of `candidates`, probing the host first if `probe_retry` is set
+///
+/// Ports that fail the probe are recorded in `external` and skipped until `probe_retry`
+/// has passed, then probed again, so a port that was only briefly in use comes back.
+fn allocate_from(
+    candidates: Vec<u16>,
+    allocated: &mut HashSet<u16>,
+    external: &mut HashMap<u16, Instant>,
+    probe_retry: Option<Duration>,
+) -> Option<u16> {
+    for port in candidates {
+        if allocated.contains(&port) || probe_retry.is_some_and(|retry| recently_busy(external, port, retry)) {
+            continue;
+        }
+        if probe_retry.is_some() {
+            let busy = port_in_use(port);
+            record_probe(external, port, busy, Instant::now());
+            if busy {
+                tracing::warn!("Port {} is in use by another process, skipping", port);
+                continue;
+            }
+        }
+        allocated.insert(port);
+        return Some(port);
+    }
+    None
+}
+
+/// Whether a port was found in use by another process less than `retry` ago
+fn recently_busy(external: &HashMap<u16, Instant>, port: u16, retry: Duration) -> bool {
+    external.get(&port).is_some_and(|probed| probed.elapsed() < retry)
+}
+
+/// Remember a port found in use, or forget it once it can be bound again
+fn record_probe(external: &mut HashMap<u16, Instant>, port: u16, busy: bool, now: Instant) {
+    if busy {
+        external.insert(port, now);
+    } else {
+        external.remove(&port);
+    }
+}
+
+/// Check whether a host port is already taken, by trying to bind it the way Docker would
+fn port_in_use(port: u16) -> bool {
+    std::net::TcpListener::bind(("0.0.0.0", port)).is_err()
 }
 
 #[cfg(test)]
@@ -121,4 +510,156 @@ mod tests {
         pool.release_pair(vnc, console);
         assert_eq!(pool.allocate_pair().unwrap(), (vnc, console));
     }
+
+    #[test]
+    fn test_pair_failure_releases_vnc() {
+        let mut pool = PortPool::new(5900..5902, 8081..8082);
+        pool.allocate_pair().unwrap();
+        assert!(pool.allocate_pair().is_none());
+        assert_eq!(pool.vnc_available(), 1);
+    }
+
+    #[test]
+    fn test_probe_skips_busy_port() {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let busy = listener.local_addr().unwrap().port();
+
+        // Without probing the busy port is handed out
+        let mut pool = PortPool::new(busy..busy + 1, 8081..8082);
+        assert_eq!(pool.allocate_vnc(), Some(busy));
+
+        // With probing it is skipped and recorded as externally used
+        let mut pool = PortPool::new(busy..busy + 1, 8081..8082).with_probing(true);
+        assert_eq!(pool.allocate_vnc(), None);
+        assert_eq!(pool.vnc_available(), 0);
+
+        // Recovering a container that owns the port reclaims it
+        pool.add_existing_vnc(busy).unwrap();
+        assert_eq!(pool.vnc_available(), 0);
+        pool.release_vnc(busy);
+        assert_eq!(pool.vnc_available(), 1);
+    }
+
+    #[test]
+    fn test_probe_retries_busy_port() {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let busy = listener.local_addr().unwrap().port();
+
+        let mut pool = PortPool::new(busy..busy + 1, 8081..8082).with_probing(true);
+        assert_eq!(pool.allocate_vnc(), None);
+
+        // Once the other process lets go, the port is probed again after the retry delay
+        drop(listener);
+        assert_eq!(pool.allocate_vnc(), None);
+        pool.probe_retry = Duration::ZERO;
+        assert_eq!(pool.vnc_available(), 1);
+        assert_eq!(pool.allocate_vnc(), Some(busy));
+    }
+
+    #[test]
+    fn test_excluded_ports_skipped() {
+        let mut pool = PortPool::new(5900..5903, 8081..8083).with_excluded([5900, 8082]);
+        assert_eq!(pool.vnc_total(), 2);
+        assert_eq!(pool.console_available(), 1);
+
+        assert_eq!(pool.allocate_pair(), Some((5901, 8081)));
+        assert!(pool.allocate_pair().is_none());
+
+        let mut shared = PortPool::shared(15900..15904).with_excluded([15901]);
+        assert_eq!(shared.vnc_total(), 1);
+        assert_eq!(shared.allocate_pair(), Some((15902, 15903)));
+    }
+
+    #[test]
+    fn test_adopt_pair_outside_range() {
+        let mut pool = PortPool::new(5900..5902, 8081..8083);
+        assert!(pool.adopt_pair(5900, 8081));
+
+        // The in-range VNC port is still taken; the stray console port is ignored
+        assert!(!pool.adopt_pair(5901, 9000));
+        assert_eq!(pool.vnc_available(), 0);
+        assert_eq!(pool.console_available(), 1);
+        assert!(pool.allocate_pair().is_none());
+    }
+
+    #[test]
+    fn test_shared_mode_pairs() {
+        let mut pool = PortPool::shared(15900..15905);
+        assert_eq!(pool.vnc_available(), 2);
+        assert_eq!(pool.allocate_pair(), Some((15900, 15901)));
+        assert_eq!(pool.allocate_pair(), Some((15902, 15903)));
+        // The odd port left over does not form a pair
+        assert_eq!(pool.allocate_pair(), None);
+
+        pool.release_pair(15900, 15901);
+        assert_eq!(pool.console_available(), 1);
+        assert_eq!(pool.allocate_pair(), Some((15900, 15901)));
+    }
+
+    #[test]
+    fn test_random_mode_stays_in_range() {
+        let mut pool = PortPool::new(5900..5910, 8081..8091).with_mode(PortMode::Random);
+        let mut seen = HashSet::new();
+        while let Some((vnc, console)) = pool.allocate_pair() {
+            assert!((5900..5910).contains(&vnc));
+            assert!((8081..8091).contains(&console));
+            assert!(seen.insert(vnc));
+        }
+        assert_eq!(seen.len(), 10);
+    }
+
+    #[test]
+    fn test_ephemeral_mode() {
+        let mut pool = PortPool::new(5900..5901, 8081..8082).with_mode(PortMode::Ephemeral);
+        assert_eq!(pool.reserve_pair(), Some((0, 0)));
+        assert_eq!(pool.reserve_pair(), Some((0, 0)));
+        assert!(pool.release_expired(Instant::now() + Duration::from_secs(3600)).is_empty());
+
+        // Ports assigned by Docker are recorded wherever they fall
+        pool.add_existing_pair(32768, 32769).unwrap();
+    }
+
+    #[test]
+    fn test_host_pools_are_independent() {
+        let mut pools = HostPortPools::new(PortPool::new(5900..5901, 8081..8082));
+        assert_eq!(pools.local_mut().allocate_pair(), Some((5900, 8081)));
+        assert_eq!(pools.local_mut().allocate_pair(), None);
+
+        // The same ports are free on another host
+        assert_eq!(pools.host_mut("docker-2").allocate_pair(), Some((5900, 8081)));
+
+        let capacity = pools.capacity();
+        assert_eq!(capacity.vnc_total, 2);
+        assert_eq!(capacity.vnc_available, 0);
+        assert_eq!(pools.host_capacity().len(), 2);
+
+        pools.local_mut().release_pair(5900, 8081);
+        assert_eq!(pools.capacity().console_available, 1);
+    }
+
+    #[test]
+    fn test_reservation_expires() {
+        let mut pool = PortPool::new(5900..5902, 8081..8083).with_reservation_ttl(Duration::from_secs(60));
+        let pair = pool.reserve_pair().unwrap();
+
+        assert!(pool.release_expired(Instant::now()).is_empty());
+        assert_eq!(pool.vnc_available(), 1);
+
+        assert_eq!(pool.release_expired(Instant::now() + Duration::from_secs(61)), vec![pair]);
+        assert_eq!(pool.vnc_available(), 2);
+
+        // A late confirm or failure must not touch ports that may have been reallocated
+        assert!(!pool.confirm_pair(pair.0, pair.1));
+        assert!(!pool.cancel_reservation(pair.0, pair.1));
+    }
+
+    #[test]
+    fn test_confirmed_reservation_kept() {
+        let mut pool = PortPool::new(5900..5902, 8081..8083).with_reservation_ttl(Duration::ZERO);
+        let pair = pool.reserve_pair().unwrap();
+        assert!(pool.confirm_pair(pair.0, pair.1));
+
+        assert!(pool.release_expired(Instant::now()).is_empty());
+        assert_eq!(pool.vnc_available(), 1);
+    }
 }
@@ -0,0 +1,72 @@
+//! Checking that an instance's game is up
+//!
+//! A container is running long before Wine has started Zoo Tycoon and OpenZT has opened
+//! its console. Started instances are `starting` until the console answers a request
+//! on the instance's console port, and only then `running`, so clients know when a
+//! session is usable. Instances whose game doesn't answer within
+//! `[instances] ready_timeout_secs` are marked as errors.
+
+use anyhow::{anyhow, bail, Result};
+use openzt_console::protocol::{self, FrameDecoder, Request, Response};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// How long a single probe waits for the console to answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Check that the console on `port` of the local host answers a request
+///
+/// Any response will do, even an error: it means the game is running the console.
+pub async fn probe_console(port: u16) -> Result<()> {
+    let probe = async {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await?;
+        stream.write_all(&protocol::encode_frame(&Request::lua(1, "return 1"))?).await?;
+
+        let mut decoder = FrameDecoder::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = stream.read(&mut buffer).await?;
+            if read == 0 {
+                bail!("Console closed the connection");
+            }
+            decoder.push(&buffer[..read]);
+            if let Some(payload) = decoder.next_frame()? {
+                let _: Response = protocol::decode_payload(&payload)?;
+                return Ok(());
+            }
+        }
+    };
+    tokio::time::timeout(PROBE_TIMEOUT, probe)
+        .await
+        .map_err(|_| anyhow!("Console didn't answer within {}s", PROBE_TIMEOUT.as_secs()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_probe_console() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut length = [0; 4];
+            stream.read_exact(&mut length).await.unwrap();
+            let mut payload = vec![0; u32::from_be_bytes(length) as usize];
+            stream.read_exact(&mut payload).await.unwrap();
+            let request: Request = protocol::decode_payload(&payload).unwrap();
+            let response = Response::new(request.id, Ok("1".to_string()));
+            stream.write_all(&protocol::encode_frame(&response).unwrap()).await.unwrap();
+        });
+        probe_console(port).await.unwrap();
+
+        // Nothing listening
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = closed.local_addr().unwrap().port();
+        drop(closed);
+        assert!(probe_console(port).await.is_err());
+    }
+}
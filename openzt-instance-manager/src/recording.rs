@@ -0,0 +1,168 @@
+//! Recordings of instance displays
+//!
+//! Every instance has its own directory under `[recordings] dir` mounted at
+//! `/recordings`. `POST /api/instances/:id/recording/start` runs ffmpeg in the
+//! container to record the game's X display there and `.../recording/stop` ends it,
+//! so playtest sessions can be replayed for bug triage. Recordings are Matroska
+//! files, which stay playable when the instance stops mid-recording, and are kept
+//! after the instance is deleted.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Where an instance's recordings directory is mounted in its container
+pub const RECORDINGS_MOUNT: &str = "/recordings";
+
+/// A recording, as returned by `GET /api/instances/:id/recordings`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub name: String,
+    /// Size in bytes
+    pub size: u64,
+    pub modified_at: DateTime<Utc>,
+    /// Whether ffmpeg is still writing it
+    pub active: bool,
+}
+
+/// Directory an instance's recordings are kept in on the host
+pub fn instance_dir(dir: &str, instance_id: &str) -> PathBuf {
+    Path::new(dir).join(instance_id)
+}
+
+/// Create an instance's recordings directory, returning its path
+pub fn prepare_instance_dir(dir: &str, instance_id: &str) -> Result<String> {
+    let path = instance_dir(dir, instance_id);
+    std::fs::create_dir_all(&path)
+        .with_context(|| format!("Failed to create recordings directory {}", path.display()))?;
+
+    // ffmpeg runs as wineuser in the container and has to be able to write there
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o777))
+            .context("Failed to make recordings directory writable")?;
+    }
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// File name for a recording started at `started_at`
+pub fn recording_name(started_at: DateTime<Utc>) -> String {
+    started_at.format("%Y%m%dT%H%M%SZ.mkv").to_string()
+}
+
+/// Whether `name` is a recording's file name, which can't escape the recordings directory
+pub fn is_valid_name(name: &str) -> bool {
+    name.ends_with(".mkv")
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+/// An instance's recordings, oldest first, with `active` marked as still being written
+pub fn list(dir: &str, instance_id: &str, active: Option<&str>) -> Result<Vec<Recording>> {
+    let path = instance_dir(dir, instance_id);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut recordings = Vec::new();
+    for dir_entry in std::fs::read_dir(&path)? {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name().to_string_lossy().into_owned();
+        if !is_valid_name(&name) {
+            continue;
+        }
+        let metadata = dir_entry.metadata()?;
+        recordings.push(Recording {
+            active: active == Some(name.as_str()),
+            name,
+            size: metadata.len(),
+            modified_at: metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now()),
+        });
+    }
+    // Names are start times, so they sort chronologically
+    recordings.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(recordings)
+}
+
+/// Command run in the container to record its display to `name`
+///
+/// ffmpeg's output goes to `<name>.log` next to the recording, for when it fails.
+pub fn start_command(name: &str, framerate: u32) -> Vec<String> {
+    let file = format!("{}/{}", RECORDINGS_MOUNT, name);
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        format!(
+            "exec ffmpeg -nostdin -loglevel error -f x11grab -framerate {} -i \"${{DISPLAY:-:0}}\" \
+             -c:v libx264 -preset ultrafast -pix_fmt yuv420p {} > {}.log 2>&1",
+            framerate, file, file
+        ),
+    ]
+}
+
+/// Command run in the container to stop recording to `name`, exiting once ffmpeg has
+///
+/// ffmpeg finishes writing the file when interrupted. The pattern is anchored so it
+/// doesn't match this command's own shell.
+pub fn stop_command(name: &str) -> Vec<String> {
+    let pattern = format!("^ffmpeg .*{}/{}", RECORDINGS_MOUNT, name);
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        format!(
+            "pkill -INT -f '{0}'; while pgrep -f '{0}' > /dev/null; do sleep 0.2; done",
+            pattern
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        let started_at = DateTime::parse_from_rfc3339("2026-10-16T17:05:09Z").unwrap().with_timezone(&Utc);
+        let name = recording_name(started_at);
+        assert_eq!(name, "20261016T170509Z.mkv");
+        assert!(is_valid_name(&name));
+        assert!(!is_valid_name("20261016T170509Z.mkv.log"));
+        assert!(!is_valid_name("../other-instance/20261016T170509Z.mkv"));
+        assert!(!is_valid_name(".mkv"));
+    }
+
+    #[test]
+    fn test_commands() {
+        let start = start_command("20261016T170509Z.mkv", 15);
+        assert!(start[2].starts_with("exec ffmpeg "));
+        assert!(start[2].contains("-framerate 15 "));
+        assert!(start[2].contains("/recordings/20261016T170509Z.mkv > /recordings/20261016T170509Z.mkv.log"));
+
+        let stop = stop_command("20261016T170509Z.mkv");
+        assert!(stop[2].starts_with("pkill -INT -f '^ffmpeg .*/recordings/20261016T170509Z.mkv';"));
+    }
+
+    #[test]
+    fn test_list() {
+        let dir = std::env::temp_dir().join(format!("openzt-recordings-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let dir = dir.to_string_lossy().into_owned();
+        assert!(list(&dir, "instance", None).unwrap().is_empty());
+
+        let path = PathBuf::from(prepare_instance_dir(&dir, "instance").unwrap());
+        std::fs::write(path.join("20261016T180000Z.mkv"), b"later").unwrap();
+        std::fs::write(path.join("20261016T170000Z.mkv"), b"first").unwrap();
+        std::fs::write(path.join("20261016T170000Z.mkv.log"), b"").unwrap();
+
+        let recordings = list(&dir, "instance", Some("20261016T180000Z.mkv")).unwrap();
+        let names: Vec<&str> = recordings.iter().map(|recording| recording.name.as_str()).collect();
+        assert_eq!(names, vec!["20261016T170000Z.mkv", "20261016T180000Z.mkv"]);
+        assert_eq!(recordings[0].size, 5);
+        assert!(!recordings[0].active);
+        assert!(recordings[1].active);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,117 @@
+//! Restarting crashed instances
+//!
+//! The server restarts instances whose container crashed or ran out of memory itself,
+//! instead of leaving it to Docker's restart policies, so it knows about every
+//! attempt. Restarts wait `backoff_secs`, doubled for each earlier restart still
+//! within `window_secs`. An instance that crashes again after `max_restarts` restarts
+//! within the window is given up on: its status becomes `failed` until it is started
+//! through the API.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Longest wait before a restart, however many came before it
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// How the server restarts an instance after it crashed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RestartPolicy {
+    /// Restarts within `window_secs` before the instance is given up on; 0 never restarts
+    pub max_restarts: u32,
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// Wait before the first restart in the window
+    #[serde(default = "default_backoff_secs")]
+    pub backoff_secs: u64,
+}
+
+/// What to do about an instance that crashed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartDecision {
+    /// Restart after `delay`, the `attempt`th restart within the window
+    Restart { attempt: u32, delay: Duration },
+    /// Too many restarts within the window
+    GiveUp,
+}
+
+pub fn default_window_secs() -> u64 {
+    3600
+}
+
+pub fn default_backoff_secs() -> u64 {
+    5
+}
+
+impl RestartPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_restarts > 0
+    }
+
+    /// Decide about a crash at `now`, given the times of earlier restarts
+    ///
+    /// Restarts outside the window are dropped from `restarts`, and a decided restart
+    /// is added.
+    pub fn decide(&self, restarts: &mut Vec<DateTime<Utc>>, now: DateTime<Utc>) -> RestartDecision {
+        let window = chrono::Duration::seconds(self.window_secs.min(i64::MAX as u64) as i64);
+        restarts.retain(|restarted_at| now - *restarted_at < window);
+        if restarts.len() >= self.max_restarts as usize {
+            return RestartDecision::GiveUp;
+        }
+
+        let doublings = restarts.len().min(16) as u32;
+        let delay = Duration::from_secs(self.backoff_secs.saturating_mul(1 << doublings)).min(MAX_BACKOFF);
+        restarts.push(now);
+        RestartDecision::Restart {
+            attempt: restarts.len() as u32,
+            delay,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_backoff_and_give_up() {
+        let policy = RestartPolicy {
+            max_restarts: 2,
+            window_secs: 600,
+            backoff_secs: 5,
+        };
+        let mut restarts = Vec::new();
+        assert_eq!(
+            policy.decide(&mut restarts, at("2026-10-16T17:00:00Z")),
+            RestartDecision::Restart { attempt: 1, delay: Duration::from_secs(5) }
+        );
+        assert_eq!(
+            policy.decide(&mut restarts, at("2026-10-16T17:01:00Z")),
+            RestartDecision::Restart { attempt: 2, delay: Duration::from_secs(10) }
+        );
+        assert_eq!(policy.decide(&mut restarts, at("2026-10-16T17:02:00Z")), RestartDecision::GiveUp);
+        assert_eq!(restarts.len(), 2);
+    }
+
+    #[test]
+    fn test_window() {
+        let policy = RestartPolicy {
+            max_restarts: 1,
+            window_secs: 600,
+            backoff_secs: 5,
+        };
+        let mut restarts = vec![at("2026-10-16T17:00:00Z")];
+        assert_eq!(policy.decide(&mut restarts.clone(), at("2026-10-16T17:09:59Z")), RestartDecision::GiveUp);
+
+        // The earlier restart has left the window
+        assert_eq!(
+            policy.decide(&mut restarts, at("2026-10-16T17:10:00Z")),
+            RestartDecision::Restart { attempt: 1, delay: Duration::from_secs(5) }
+        );
+        assert_eq!(restarts, vec![at("2026-10-16T17:10:00Z")]);
+    }
+}
@@ -1,126 +1,565 @@
 use super::{
+    dll_library::{download_dll, hash_dll, DllEntry, UploadDllRequest},
+    mod_repository::{ModEntry, ModError, UploadModRequest},
+    recording::{self, Recording},
+    restart::RestartDecision,
+    schedule::{Schedule, ScheduledAction},
+    templates::Template,
+    docker::{ContainerSetup, DockerManager, LogFilter},
+    idempotency::{fingerprint, Claim, IDEMPOTENCY_KEY_HEADER, MAX_KEY_LENGTH},
     instance::{
-        AppLogType, CreateInstanceRequest, CreateInstanceResponse, Instance,
-        InstanceDetails, InstanceStatus, LogsResponse, InstanceStatusResponse,
+        parse_log_time, AppLogType, CapacityResponse, CreateInstanceRequest, CreateInstanceResponse,
+        DllSource, Instance, InstanceConfig, InstanceDetails, InstanceEventKind, InstanceStatus, LogsResponse,
+        InstanceStatusResponse, Sidecar, SidecarSpec, UpdateInstanceRequest,
     },
+    metrics::{CreationFailure, CreationPhase},
+    operations::{Operation, OperationGuard},
+    readiness,
     state::AppState,
+    tokens::{ApiToken, Caller, CreateTokenRequest, CreateTokenResponse, TokenScope},
+    test_run::{CreateTestRunRequest, ModFile, TestRun, TestRunReport, TestRunStatus, RESULTS_FILE, RESULTS_WINDOWS_PATH},
 };
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        FromRequestParts, Path, Query, Request, State,
+    },
+    http::{header, request::Parts, HeaderMap, Method, StatusCode},
+    middleware::Next,
     response::{sse::{Event, Sse}, IntoResponse, Json, Response},
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use futures_util::stream::StreamExt;
 use serde::Deserialize;
 use std::convert::Infallible;
+use std::path::{Path as FsPath, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 pub fn create_router() -> Router<Arc<RwLock<AppState>>> {
     Router::new()
         .route("/health", get(health_check))
+        .route("/metrics", get(get_metrics))
+        .route("/api/admin/config", get(get_admin_config))
+        .route("/api/admin/tokens", post(create_token).get(list_tokens))
+        .route("/api/admin/tokens/{id}", delete(revoke_token))
         .route("/api/instances", post(create_instance).get(list_instances))
         .route(
             "/api/instances/{id}",
-            get(get_instance).delete(delete_instance),
+            get(get_instance).patch(update_instance).delete(delete_instance),
         )
         .route("/api/instances/{id}/logs", get(get_instance_logs))
         .route("/api/instances/{id}/logs/stream", get(stream_logs))
         .route("/api/instances/{id}/stop", post(stop_instance))
         .route("/api/instances/{id}/start", post(start_instance))
         .route("/api/instances/{id}/restart", post(restart_instance))
+        .route("/api/instances/{id}/restore", post(restore_instance))
+        .route("/api/instances/{id}/purge", post(purge_instance))
+        .route("/api/instances/{id}/tunnel", get(tunnel_instance_port))
+        .route(
+            "/api/instances/{id}/schedule",
+            get(get_schedule).put(set_schedule).delete(delete_schedule),
+        )
+        .route("/api/instances/{id}/recording/start", post(start_recording))
+        .route("/api/instances/{id}/recording/stop", post(stop_recording))
+        .route("/api/instances/{id}/recordings", get(list_recordings))
+        .route("/api/instances/{id}/recordings/{name}", get(download_recording))
+        .route("/api/events", get(stream_events))
+        .route("/api/capacity", get(get_capacity))
+        .route("/api/testruns", post(create_test_run).get(list_test_runs))
+        .route("/api/testruns/{id}", get(get_test_run))
+        .route("/api/dlls", post(upload_dll).get(list_dlls))
+        .route("/api/dlls/{id}", get(get_dll).delete(delete_dll))
+        .route("/api/mods", post(upload_mod).get(list_mods))
+        .route("/api/mods/{name}/{version}", delete(delete_mod))
+        .route("/api/templates", post(create_template).get(list_templates))
+        .route(
+            "/api/templates/{name}",
+            get(get_template).put(update_template).delete(delete_template),
+        )
+}
+
+/// How often a starting instance's console is checked for its game being up
+const READY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often a test run checks whether the game has written its results
+const RESULTS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long stopping a recording waits for ffmpeg to finish writing it
+const RECORDING_STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the log archive waits before following a stopped container's logs again
+const LOG_ARCHIVE_RETRY: Duration = Duration::from_secs(5);
+
+/// Target of the audit log, one line per change made through the API or request denied
+const AUDIT_TARGET: &str = "openzt_instance_manager::audit";
+
+/// Check a request's bearer token when `[api] enable_auth` is set, and audit it
+///
+/// The token's [`Caller`] is added to the request for the scope extractors
+/// ([`RequireOperator`], [`RequireAdmin`]).
+pub async fn authenticate(
+    State(state): State<Arc<RwLock<AppState>>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let caller = {
+        let state_guard = state.read().await;
+        if !state_guard.config.api.enable_auth || path == "/health" {
+            None
+        } else {
+            let secret = request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "));
+            let caller = match secret {
+                Some(secret) if state_guard.config.api.admin_token.as_deref() == Some(secret) => Some(Caller {
+                    name: "admin_token".to_string(),
+                    scope: TokenScope::Admin,
+                }),
+                Some(secret) => state_guard.tokens.authenticate(secret).map(|token| Caller {
+                    name: token.name.clone(),
+                    scope: token.scope,
+                }),
+                None => None,
+            };
+            if caller.is_none() {
+                tracing::warn!(target: AUDIT_TARGET, "{} {} -> 401 without a valid token", method, path);
+                return ApiError::Unauthorized.into_response();
+            }
+            caller
+        }
+    };
+
+    if let Some(caller) = &caller {
+        request.extensions_mut().insert(caller.clone());
+    }
+    let response = next.run(request).await;
+
+    let status = response.status();
+    if !matches!(method, Method::GET | Method::HEAD) || status == StatusCode::FORBIDDEN {
+        let by = caller
+            .map(|caller| format!("{} ({})", caller.name, caller.scope.as_str()))
+            .unwrap_or_else(|| "anonymous".to_string());
+        tracing::info!(target: AUDIT_TARGET, "{} {} -> {} by {}", method, path, status.as_u16(), by);
+    }
+    response
+}
+
+/// Reject the request unless its token's scope includes `required`
+///
+/// Requests carry no [`Caller`] when auth is disabled, and are let through.
+fn require_scope(parts: &Parts, required: TokenScope) -> Result<(), ApiError> {
+    match parts.extensions.get::<Caller>() {
+        Some(caller) if !caller.scope.includes(required) => Err(ApiError::Forbidden(format!(
+            "This needs a token with the {} scope, not {}",
+            required.as_str(),
+            caller.scope.as_str()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Extractor for handlers that change state, which `read-only` tokens can't call
+pub struct RequireOperator;
+
+impl<S: Send + Sync> FromRequestParts<S> for RequireOperator {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, ApiError> {
+        require_scope(parts, TokenScope::Operator).map(|_| RequireOperator)
+    }
+}
+
+/// Extractor for the `/api/admin` handlers
+pub struct RequireAdmin;
+
+impl<S: Send + Sync> FromRequestParts<S> for RequireAdmin {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, ApiError> {
+        require_scope(parts, TokenScope::Admin).map(|_| RequireAdmin)
+    }
 }
 
 async fn health_check() -> &'static str {
     "OK"
 }
 
+/// Instance creation metrics in the Prometheus text format
+async fn get_metrics(State(state): State<Arc<RwLock<AppState>>>) -> Response {
+    let body = state.read().await.metrics.render();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+/// The configuration the server is running with, secrets redacted
+async fn get_admin_config(_: RequireAdmin, State(state): State<Arc<RwLock<AppState>>>) -> Json<serde_json::Value> {
+    Json(state.read().await.config.redacted())
+}
+
+async fn get_capacity(State(state): State<Arc<RwLock<AppState>>>) -> Json<CapacityResponse> {
+    Json(capacity(&*state.read().await))
+}
+
+fn capacity(state: &AppState) -> CapacityResponse {
+    CapacityResponse {
+        instances: state.instances.len(),
+        max_instances: state.config.instances.max_instances,
+        ports: state.port_pools.capacity(),
+        hosts: state.port_pools.host_capacity(),
+    }
+}
+
 async fn create_instance(
+    _: RequireOperator,
     State(state): State<Arc<RwLock<AppState>>>,
-    Json(req): Json<CreateInstanceRequest>,
+    headers: HeaderMap,
+    Json(mut req): Json<CreateInstanceRequest>,
 ) -> Result<Json<CreateInstanceResponse>, ApiError> {
     let instance_id = Uuid::new_v4().to_string();
+
+    // A retried request gets the instance the first one with its key created
+    let idempotency = match idempotency_key(&headers)? {
+        Some(key) => {
+            let request = serde_json::to_vec(&req).map_err(|e| ApiError::Internal(e.to_string()))?;
+            let claim = state
+                .read()
+                .await
+                .idempotency_keys
+                .claim(&key, &fingerprint(&request), &instance_id, Utc::now());
+            match claim {
+                Claim::New(guard) => Some(guard),
+                Claim::Completed(id) => {
+                    tracing::info!("Create request with Idempotency-Key {} already created instance {}", key, id);
+                    return created_instance(&state, &id).await.map(Json);
+                }
+                Claim::InProgress => {
+                    return Err(ApiError::Conflict(
+                        "A request with this Idempotency-Key is still creating its instance".to_string(),
+                    ))
+                }
+                Claim::Mismatch => {
+                    return Err(ApiError::Conflict(
+                        "Idempotency-Key was already used for a different request".to_string(),
+                    ))
+                }
+            }
+        }
+        None => None,
+    };
     let container_name = format!("{}{}", state.read().await.config.docker.container_prefix, instance_id);
 
     tracing::info!("Creating instance {}", instance_id);
 
-    // Allocate ports
+    let schedule = req.schedule.clone().filter(|schedule| !schedule.is_empty());
+    if let Some(schedule) = &schedule {
+        schedule.validate().map_err(ApiError::BadRequest)?;
+    }
+    let mod_files = std::mem::take(&mut req.mod_files);
+    let (dll_source, mods, config) = apply_template(&state, req).await?;
+    config.validate().map_err(ApiError::BadRequest)?;
+    let dll = request_dll(&state, &dll_source).await?;
+    let mods_dir = install_mods(&state, &instance_id, &mods, &mod_files).await?;
+    let lock = lock_instance(&state, &instance_id, Operation::Create).await?;
+    let recordings_dir = {
+        let dir = state.read().await.config.recordings.dir.clone();
+        recording::prepare_instance_dir(&dir, &instance_id)
+            .inspect_err(|e| tracing::warn!("Instance {} can't record its display: {:#}", instance_id, e))
+            .ok()
+    };
+    let (vnc_port, console_port, dll_path) =
+        match register_instance(&state, &instance_id, &dll, config, schedule).await {
+            Ok(registered) => registered,
+            Err(e) => {
+                super::docker::cleanup_instance_mods(&instance_id);
+                return Err(e);
+            }
+        };
+    if let Some(guard) = idempotency {
+        guard.complete();
+    }
+
+    // Create Docker container (background task)
+    let state_clone = state.clone();
+    let instance_id_clone = instance_id.clone();
+    tokio::spawn(async move {
+        let _lock = lock;
+        if let Err(e) = create_container_task(
+            state_clone.clone(),
+            instance_id_clone.clone(),
+            container_name,
+            vnc_port,
+            console_port,
+            ContainerSetup {
+                dll_path,
+                mods_dir,
+                recordings_dir,
+                ..Default::default()
+            },
+        )
+        .await
+        {
+            fail_instance(&state_clone, &instance_id_clone, vnc_port, console_port, &e).await;
+        }
+    });
+
+    let vnc_url = state.read().await.config.server.vnc_url(&instance_id, vnc_port);
+    Ok(Json(CreateInstanceResponse {
+        instance_id,
+        vnc_port,
+        console_port,
+        vnc_url,
+        status: "creating".to_string(),
+    }))
+}
+
+/// A create request's `Idempotency-Key`, if it has one
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, ApiError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => Ok(Some(key.to_string())),
+        _ => Err(ApiError::BadRequest(format!(
+            "Idempotency-Key must be 1 to {} visible ASCII characters",
+            MAX_KEY_LENGTH
+        ))),
+    }
+}
+
+/// The response to the create request that created instance `id`, as it is now
+async fn created_instance(state: &Arc<RwLock<AppState>>, id: &str) -> Result<CreateInstanceResponse, ApiError> {
+    let state_guard = state.read().await;
+    let instance = state_guard.instances.get(id).ok_or_else(|| {
+        ApiError::Conflict(format!("Instance {} created with this Idempotency-Key has been deleted", id))
+    })?;
+    Ok(CreateInstanceResponse {
+        instance_id: instance.id.clone(),
+        vnc_port: instance.vnc_port,
+        console_port: instance.console_port,
+        vnc_url: state_guard.config.server.vnc_url(&instance.id, instance.vnc_port),
+        status: instance.status.as_str().to_string(),
+    })
+}
+
+/// The DLL, mods and config of a create request, with what it leaves unset taken from its template
+async fn apply_template(
+    state: &Arc<RwLock<AppState>>,
+    req: CreateInstanceRequest,
+) -> Result<(DllSource, Vec<String>, InstanceConfig), ApiError> {
+    let config = req.config.unwrap_or_default();
+    let Some(name) = &req.template else {
+        return Ok((req.dll, req.mods, config));
+    };
+    let template = state.read().await.templates.get(name).cloned().ok_or(ApiError::TemplateNotFound)?;
+    tracing::info!("Creating instance from template {}", name);
+
+    let dll = if req.dll.is_empty() { template.dll } else { req.dll };
+    let mods = if req.mods.is_empty() { template.mods } else { req.mods };
+    Ok((dll, mods, config.or(&template.config)))
+}
+
+/// The DLL a create request asked for: uploaded with the request, from the DLL library or downloaded
+async fn request_dll(state: &Arc<RwLock<AppState>>, source: &DllSource) -> Result<Vec<u8>, ApiError> {
+    match (&source.openzt_dll, &source.dll_id, &source.dll_url) {
+        (Some(dll_base64), None, None) => {
+            super::docker::decode_dll(dll_base64).map_err(|e| ApiError::InvalidDll(e.to_string()))
+        }
+        (None, Some(dll_id), None) => read_library_dll(state, dll_id).await,
+        (None, None, Some(dll_url)) => fetch_dll(state, dll_url, source.dll_sha256.as_deref()).await,
+        _ => Err(ApiError::BadRequest("Set exactly one of openzt_dll, dll_id and dll_url".to_string())),
+    }
+}
+
+async fn read_library_dll(state: &Arc<RwLock<AppState>>, dll_id: &str) -> Result<Vec<u8>, ApiError> {
+    let path = {
+        let state_guard = state.read().await;
+        state_guard.dlls.get(dll_id).ok_or(ApiError::DllNotFound)?;
+        state_guard.dlls.dll_path(dll_id)
+    };
+    std::fs::read(&path).map_err(|e| ApiError::Internal(format!("Failed to read DLL {}: {}", dll_id, e)))
+}
+
+/// The DLL at `url`, from the DLL library if it was downloaded before
+async fn fetch_dll(state: &Arc<RwLock<AppState>>, url: &str, sha256: Option<&str>) -> Result<Vec<u8>, ApiError> {
+    let sha256 = sha256.map(str::to_lowercase);
+    let cached = {
+        let state_guard = state.read().await;
+        let max_age = Duration::from_secs(state_guard.config.dlls.url_cache_secs);
+        match &sha256 {
+            Some(sha256) => state_guard.dlls.get(sha256).map(|entry| entry.id.clone()),
+            None => state_guard.dlls.cached_download(url, max_age).map(|entry| entry.id.clone()),
+        }
+    };
+    if let Some(dll_id) = cached {
+        tracing::info!("Using DLL {} from the library for {}", dll_id, url);
+        return read_library_dll(state, &dll_id).await;
+    }
+
+    let dll = download_dll(url).await.map_err(|e| ApiError::BadGateway(format!("{:#}", e)))?;
+    super::docker::validate_dll(&dll).map_err(|e| ApiError::InvalidDll(format!("{} from {}", e, url)))?;
+    let actual = hash_dll(&dll);
+    if let Some(sha256) = &sha256
+        && actual != *sha256
+    {
+        return Err(ApiError::BadRequest(format!(
+            "DLL from {} has SHA-256 {}, expected {}",
+            url, actual, sha256
+        )));
+    }
+
+    state.write().await.dlls.add_download(url, &dll)?;
+    Ok(dll)
+}
+
+/// Copy the mods a create request lists from the mod repository and write those it
+/// uploaded, returning the directory to mount
+///
+/// `None` if the request has no mods.
+async fn install_mods(
+    state: &Arc<RwLock<AppState>>,
+    instance_id: &str,
+    references: &[String],
+    uploads: &[ModFile],
+) -> Result<Option<String>, ApiError> {
+    if references.is_empty() && uploads.is_empty() {
+        return Ok(None);
+    }
+
+    let mut mods: Vec<(PathBuf, String)> = Vec::new();
+    {
+        let state_guard = state.read().await;
+        for reference in references {
+            let entry = state_guard.mods.resolve(reference).map_err(|e| ApiError::ModNotFound(e.to_string()))?;
+            if mods.iter().any(|(_, file_name)| *file_name == entry.file_name()) {
+                return Err(ApiError::BadRequest(format!("Mod {} is listed more than once", entry.name)));
+            }
+            mods.push((state_guard.mods.ztd_path(entry), entry.file_name()));
+        }
+    }
+    let mut uploaded: Vec<(String, Vec<u8>)> = Vec::new();
+    for mod_file in uploads {
+        mod_file.validate_name().map_err(ApiError::BadRequest)?;
+        let taken = |file_name: &str| file_name.eq_ignore_ascii_case(&mod_file.name);
+        if mods.iter().any(|(_, file_name)| taken(file_name)) || uploaded.iter().any(|(file_name, _)| taken(file_name)) {
+            return Err(ApiError::BadRequest(format!("Mod {} is listed more than once", mod_file.name)));
+        }
+        let data = base64::Engine::decode(&base64::prelude::BASE64_STANDARD, &mod_file.data)
+            .map_err(|e| ApiError::BadRequest(format!("Failed to decode base64 mod {}: {}", mod_file.name, e)))?;
+        uploaded.push((mod_file.name.clone(), data));
+    }
+
+    match super::docker::write_instance_mods(instance_id, &mods, &uploaded) {
+        Ok(dir) => Ok(Some(dir)),
+        Err(e) => {
+            super::docker::cleanup_instance_mods(instance_id);
+            Err(ApiError::Internal(format!("{:#}", e)))
+        }
+    }
+}
+
+/// Reserve ports for a new instance, write its DLL and record it as creating
+///
+/// Returns the reserved VNC and console ports and the path of the DLL.
+async fn register_instance(
+    state: &Arc<RwLock<AppState>>,
+    instance_id: &str,
+    dll: &[u8],
+    config: InstanceConfig,
+    schedule: Option<Schedule>,
+) -> Result<(u16, u16, String), ApiError> {
+    // Reserve ports until the container has started
     let (vnc_port, console_port) = {
         let mut state_guard = state.write().await;
-        state_guard
-            .port_pool
-            .allocate_pair()
-            .ok_or(ApiError::PortsExhausted)?
+        let reserved = state_guard.port_pools.local_mut().reserve_pair();
+        reserved.ok_or_else(|| {
+            let detail = capacity(&state_guard).exhausted().unwrap_or_else(|| "all ports are in use".to_string());
+            ApiError::PortsExhausted(detail)
+        })?
     };
 
     // Write DLL to temp file
-    let dll_path =
-        super::docker::write_dll_to_temp(&instance_id, &req.openzt_dll).map_err(|e| {
+    let started = Instant::now();
+    let dll_path = match super::docker::write_dll_to_temp(instance_id, dll) {
+        Ok(path) => path,
+        Err(e) => {
             tracing::error!("Failed to write DLL: {}", e);
-            ApiError::InvalidDll(e.to_string())
-        })?;
+            let mut state_guard = state.write().await;
+            state_guard.port_pools.local_mut().cancel_reservation(vnc_port, console_port);
+            let failure = Some(CreationFailure::DllWrite);
+            state_guard.record_creation_phase(instance_id, CreationPhase::DllWrite, started.elapsed(), failure);
+            return Err(ApiError::Internal(format!("Failed to write DLL: {}", e)));
+        }
+    };
+    let dll_write = started.elapsed();
 
     // Create instance record
     let instance = Instance {
-        id: instance_id.clone(),
+        id: instance_id.to_string(),
         container_id: String::new(),
         vnc_port,
         console_port,
         status: InstanceStatus::Creating,
         created_at: Utc::now(),
-        config: req.config.unwrap_or_default(),
+        config,
+        ports_outside_range: false,
+        schedule,
+        last_exit: None,
+        auto_restarts: Vec::new(),
+        sidecars: Vec::new(),
+        creation: None,
+        trashed_at: None,
+        name: None,
+        labels: Default::default(),
     };
 
     {
         let mut state_guard = state.write().await;
         if state_guard.instances.len() >= state_guard.config.instances.max_instances {
             // Release ports
-            state_guard.port_pool.release_pair(vnc_port, console_port);
+            state_guard.port_pools.local_mut().cancel_reservation(vnc_port, console_port);
+            super::docker::cleanup_dll_temp(instance_id);
             return Err(ApiError::MaxInstancesReached);
         }
-        state_guard.instances.insert(instance_id.clone(), instance);
+        let scheduled = instance.schedule.is_some();
+        state_guard.instances.insert(instance_id.to_string(), instance);
+        state_guard.record_creation_phase(instance_id, CreationPhase::DllWrite, dll_write, None);
+        state_guard.emit_event(instance_id, InstanceEventKind::Creating, None);
+        if scheduled {
+            state_guard.save_schedules();
+        }
     }
 
-    // Create Docker container (background task)
-    let state_clone = state.clone();
-    let instance_id_clone = instance_id.clone();
-    tokio::spawn(async move {
-        if let Err(e) = create_container_task(
-            state_clone.clone(),
-            instance_id_clone.clone(),
-            container_name,
-            vnc_port,
-            console_port,
-            dll_path.clone(),
-        )
-        .await
-        {
-            tracing::error!("Failed to create container for instance {}: {}", instance_id_clone, e);
+    Ok((vnc_port, console_port, dll_path))
+}
 
-            // Clean up temp DLL file
-            super::docker::cleanup_dll_temp(&instance_id_clone);
+/// Mark an instance whose container couldn't be created or started as failed
+async fn fail_instance(state: &Arc<RwLock<AppState>>, instance_id: &str, vnc_port: u16, console_port: u16, error: &anyhow::Error) {
+    tracing::error!("Failed to create container for instance {}: {}", instance_id, error);
 
-            // Update instance status to error and release ports
-            let mut state_guard = state_clone.write().await;
-            if let Some(instance) = state_guard.instances.get_mut(&instance_id_clone) {
-                instance.status = InstanceStatus::Error(e.to_string());
-            }
-            state_guard.port_pool.release_pair(vnc_port, console_port);
-        }
-    });
+    // Clean up temp DLL file and mods
+    super::docker::cleanup_dll_temp(instance_id);
+    super::docker::cleanup_instance_mods(instance_id);
 
-    Ok(Json(CreateInstanceResponse {
-        instance_id,
-        vnc_port,
-        console_port,
-        vnc_url: format!("vnc://localhost:{}", vnc_port),
-        status: "creating".to_string(),
-    }))
+    // Update instance status to error and release ports (unless the
+    // reservation already expired and they may belong to someone else)
+    let mut state_guard = state.write().await;
+    if let Some(instance) = state_guard.instances.get_mut(instance_id) {
+        instance.status = InstanceStatus::Error(error.to_string());
+    }
+    state_guard.port_pools.local_mut().cancel_reservation(vnc_port, console_port);
+    state_guard.emit_event(instance_id, InstanceEventKind::Error, Some(error.to_string()));
 }
 
 async fn create_container_task(
@@ -129,17 +568,10 @@ async fn create_container_task(
     container_name: String,
     vnc_port: u16,
     console_port: u16,
-    dll_path: String,
+    setup: ContainerSetup,
 ) -> anyhow::Result<()> {
     let docker_manager = super::docker::DockerManager::new()?;
 
-    // Ensure image exists
-    let image = {
-        let state_guard = state.read().await;
-        state_guard.config.docker.image.clone()
-    };
-    docker_manager.ensure_image(&image).await?;
-
     // Get instance config and apply default cpulimit if not set
     let instance_config = {
         let state_guard = state.read().await;
@@ -154,10 +586,21 @@ async fn create_container_task(
         config
     };
 
+    // Ensure image exists
+    let image = match &instance_config.image {
+        Some(image) => image.clone(),
+        None => state.read().await.config.docker.image.clone(),
+    };
+    timed_phase(&state, &instance_id, CreationPhase::ImageCheck, docker_manager.ensure_image(&image)).await?;
+
     // Create container
-    let container_id = match docker_manager
-        .create_container(&container_name, &image, vnc_port, console_port, &dll_path, &instance_config)
-        .await
+    let container_id = match timed_phase(
+        &state,
+        &instance_id,
+        CreationPhase::ContainerCreate,
+        docker_manager.create_container(&container_name, &image, vnc_port, console_port, &instance_config, &setup),
+    )
+    .await
     {
         Ok(id) => id,
         Err(e) => {
@@ -168,8 +611,21 @@ async fn create_container_task(
 
     tracing::info!("Created container {} for instance {}", container_id, instance_id);
 
+    // An instance scheduled to start later is left stopped until then
+    let deferred_start = state
+        .read()
+        .await
+        .instances
+        .get(&instance_id)
+        .and_then(|instance| instance.schedule.as_ref())
+        .is_some_and(|schedule| schedule.defers_start(Utc::now()));
+
     // Start container - clean up if this fails
-    if let Err(e) = docker_manager.start_container(&container_id).await {
+    if deferred_start {
+        tracing::info!("Container {} for instance {} waits for its scheduled start", container_id, instance_id);
+    } else if let Err(e) =
+        timed_phase(&state, &instance_id, CreationPhase::Start, docker_manager.start_container(&container_id)).await
+    {
         tracing::error!("Failed to start container {}: {}", container_id, e);
 
         // Clean up the failed container
@@ -180,22 +636,310 @@ async fn create_container_task(
         }
 
         return Err(e.context("Failed to start container"));
+    } else {
+        tracing::info!("Started container {} for instance {}", container_id, instance_id);
     }
 
-    tracing::info!("Started container {} for instance {}", container_id, instance_id);
+    // Sidecars join the game container's network namespace, so they come after it
+    let create = create_sidecars(
+        &docker_manager,
+        &instance_id,
+        &container_name,
+        &container_id,
+        &instance_config.sidecars,
+        !deferred_start,
+    );
+    let created = if instance_config.sidecars.is_empty() {
+        create.await
+    } else {
+        timed_phase(&state, &instance_id, CreationPhase::Sidecars, create).await
+    };
+    let sidecars = match created {
+        Ok(sidecars) => sidecars,
+        Err(e) => {
+            if let Err(cleanup_err) = docker_manager.stop_and_remove_container(&container_id).await {
+                tracing::error!("Failed to clean up container {}: {}", container_id, cleanup_err);
+            }
+            return Err(e);
+        }
+    };
 
     // Update instance status
-    {
+    let ephemeral = state.read().await.port_pools.local().is_ephemeral();
+    let (event, wait_for_game) = {
         let mut state_guard = state.write().await;
+        if !ephemeral && !state_guard.port_pools.local_mut().confirm_pair(vnc_port, console_port) {
+            drop(state_guard);
+            // The ports were released while we were starting; don't keep a
+            // container that may collide with a newer instance
+            if let Err(cleanup_err) = docker_manager.stop_and_remove_container(&container_id).await {
+                tracing::error!("Failed to clean up container {}: {}", container_id, cleanup_err);
+            }
+            return Err(anyhow::anyhow!("Port reservation expired before the container started"));
+        }
         if let Some(instance) = state_guard.instances.get_mut(&instance_id) {
             instance.container_id = container_id.clone();
-            instance.status = InstanceStatus::Running;
+            instance.status = InstanceStatus::Stopped;
+            instance.sidecars = sidecars;
+        }
+        let (event, wait_for_game) = if deferred_start {
+            (InstanceEventKind::Stopped, false)
+        } else if state_guard.mark_started(&instance_id) {
+            (InstanceEventKind::Starting, true)
+        } else {
+            (InstanceEventKind::Running, false)
+        };
+        // Instances waiting for their scheduled start or their game aren't counted as created yet
+        if !deferred_start && !wait_for_game {
+            record_created(&mut state_guard, &instance_id);
+        }
+        if !ephemeral || deferred_start {
+            state_guard.emit_event(&instance_id, event, None);
+        }
+        (event, wait_for_game)
+    };
+
+    if state.read().await.config.log_archive.enabled {
+        tokio::spawn(archive_logs(state.clone(), instance_id.clone()));
+    }
+
+    if ephemeral && !deferred_start {
+        refresh_ephemeral_ports(&state, &docker_manager, &instance_id, &container_id).await?;
+        state.read().await.emit_event(&instance_id, event, None);
+    }
+
+    if wait_for_game {
+        match timed_phase(&state, &instance_id, CreationPhase::Ready, wait_until_ready(&state, &instance_id)).await {
+            Ok(true) => record_created(&mut *state.write().await, &instance_id),
+            Ok(false) => {}
+            Err(e) => mark_not_ready(&state, &instance_id, &e).await,
         }
     }
 
     Ok(())
 }
 
+/// Record the time from an instance's create request to it running
+fn record_created(state: &mut AppState, instance_id: &str) {
+    if let Some(created_at) = state.instances.get(instance_id).map(|instance| instance.created_at) {
+        state.metrics.record_created((Utc::now() - created_at).to_std().unwrap_or_default());
+    }
+}
+
+/// Wait for a starting instance's game to answer on its console, then mark it running
+///
+/// Returns whether the instance was marked running; one that was stopped, crashed or
+/// deleted in the meantime is left alone. Fails once its ready deadline has passed.
+async fn wait_until_ready(state: &Arc<RwLock<AppState>>, id: &str) -> anyhow::Result<bool> {
+    loop {
+        let (console_port, deadline) = {
+            let state_guard = state.read().await;
+            match (state_guard.instances.get(id), state_guard.ready_deadlines.get(id)) {
+                (Some(instance), Some(deadline)) if matches!(instance.status, InstanceStatus::Starting) => {
+                    (instance.console_port, *deadline)
+                }
+                _ => return Ok(false),
+            }
+        };
+        match readiness::probe_console(console_port).await {
+            Ok(()) => break,
+            // A restart in the meantime moves the deadline
+            Err(e) if Instant::now() >= deadline && state.read().await.ready_deadlines.get(id) == Some(&deadline) => {
+                return Err(e.context("The game didn't answer on its console in time"));
+            }
+            Err(_) => tokio::time::sleep(READY_POLL_INTERVAL).await,
+        }
+    }
+
+    let mut state_guard = state.write().await;
+    state_guard.ready_deadlines.remove(id);
+    match state_guard.instances.get_mut(id) {
+        Some(instance) if matches!(instance.status, InstanceStatus::Starting) => instance.status = InstanceStatus::Running,
+        _ => return Ok(false),
+    }
+    tracing::info!("Instance {} is ready", id);
+    state_guard.emit_event(id, InstanceEventKind::Running, None);
+    Ok(true)
+}
+
+/// Mark a starting instance whose game didn't come up as an error
+async fn mark_not_ready(state: &Arc<RwLock<AppState>>, id: &str, error: &anyhow::Error) {
+    let message = format!("{:#}", error);
+    tracing::warn!("Instance {}: {}", id, message);
+    let mut state_guard = state.write().await;
+    state_guard.ready_deadlines.remove(id);
+    match state_guard.instances.get_mut(id) {
+        Some(instance) if matches!(instance.status, InstanceStatus::Starting) => {
+            instance.status = InstanceStatus::Error(message.clone());
+        }
+        _ => return,
+    }
+    state_guard.emit_event(id, InstanceEventKind::Error, Some(message));
+}
+
+/// Mark an instance running once its game is up, in the background
+fn watch_readiness(state: &Arc<RwLock<AppState>>, id: &str) {
+    let state = state.clone();
+    let id = id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = wait_until_ready(&state, &id).await {
+            mark_not_ready(&state, &id, &e).await;
+        }
+    });
+}
+
+/// Run a phase of creating an instance, recording how long it took and why it failed
+async fn timed_phase<T>(
+    state: &Arc<RwLock<AppState>>,
+    instance_id: &str,
+    phase: CreationPhase,
+    future: impl std::future::Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let started = Instant::now();
+    let result = future.await;
+    let failure = result.as_ref().err().map(|e| CreationFailure::classify(phase, e));
+    state.write().await.record_creation_phase(instance_id, phase, started.elapsed(), failure);
+    result
+}
+
+/// Record the host ports Docker assigned to an instance in ephemeral mode
+///
+/// Docker picks new ports each time the container starts, so this runs after
+/// every create, start and restart. Does nothing in the other port modes.
+async fn refresh_ephemeral_ports(
+    state: &Arc<RwLock<AppState>>,
+    docker_manager: &super::docker::DockerManager,
+    instance_id: &str,
+    container_id: &str,
+) -> anyhow::Result<()> {
+    if !state.read().await.port_pools.local().is_ephemeral() {
+        return Ok(());
+    }
+
+    let (vnc_port, console_port) = docker_manager.container_ports(container_id).await?;
+
+    let mut state_guard = state.write().await;
+    let Some(instance) = state_guard.instances.get_mut(instance_id) else {
+        return Ok(());
+    };
+    let previous = (instance.vnc_port, instance.console_port);
+    instance.vnc_port = vnc_port;
+    instance.console_port = console_port;
+
+    state_guard.port_pools.local_mut().release_pair(previous.0, previous.1);
+    state_guard.port_pools.local_mut().add_existing_pair(vnc_port, console_port)?;
+
+    tracing::info!("Instance {} bound to VNC: {}, Console: {}", instance_id, vnc_port, console_port);
+    Ok(())
+}
+
+/// Create an instance's sidecars, starting them unless `start` is false
+///
+/// Sidecars created before one fails are removed again.
+async fn create_sidecars(
+    docker_manager: &DockerManager,
+    instance_id: &str,
+    container_name: &str,
+    game_container_id: &str,
+    specs: &[SidecarSpec],
+    start: bool,
+) -> anyhow::Result<Vec<Sidecar>> {
+    let mut sidecars = Vec::new();
+    for spec in specs {
+        let container_id = match docker_manager
+            .create_sidecar(instance_id, container_name, game_container_id, spec)
+            .await
+        {
+            Ok(container_id) => container_id,
+            Err(e) => {
+                remove_sidecars(docker_manager, &sidecars).await;
+                return Err(e);
+            }
+        };
+        sidecars.push(Sidecar {
+            name: spec.name.clone(),
+            container_id: container_id.clone(),
+            status: InstanceStatus::Stopped,
+        });
+
+        if start {
+            if let Err(e) = docker_manager.start_container(&container_id).await {
+                remove_sidecars(docker_manager, &sidecars).await;
+                return Err(e.context(format!("Failed to start sidecar {}", spec.name)));
+            }
+            if let Some(sidecar) = sidecars.last_mut() {
+                sidecar.status = InstanceStatus::Running;
+            }
+        }
+        tracing::info!("Created sidecar {} ({}) for instance {}", spec.name, container_id, instance_id);
+    }
+    Ok(sidecars)
+}
+
+async fn remove_sidecars(docker_manager: &DockerManager, sidecars: &[Sidecar]) {
+    for sidecar in sidecars {
+        if let Err(e) = docker_manager.stop_and_remove_container(&sidecar.container_id).await {
+            tracing::warn!("Failed to remove sidecar container {}: {}", sidecar.container_id, e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SidecarAction {
+    Start,
+    Stop,
+    Restart,
+}
+
+/// Start, stop or restart an instance's sidecars along with its game container
+///
+/// Failures are recorded as the sidecar's status rather than failing the instance.
+async fn update_sidecars(state: &Arc<RwLock<AppState>>, docker_manager: &DockerManager, id: &str, action: SidecarAction) {
+    let sidecars: Vec<(String, String)> = match state.read().await.instances.get(id) {
+        Some(instance) => instance
+            .sidecars
+            .iter()
+            .map(|sidecar| (sidecar.name.clone(), sidecar.container_id.clone()))
+            .collect(),
+        None => return,
+    };
+
+    for (name, container_id) in sidecars {
+        let result = match action {
+            SidecarAction::Start => docker_manager.start_container(&container_id).await,
+            SidecarAction::Stop => docker_manager.stop_container(&container_id).await,
+            SidecarAction::Restart => docker_manager.restart_container(&container_id).await,
+        };
+        let status = match (result, action) {
+            (Ok(()), SidecarAction::Stop) => InstanceStatus::Stopped,
+            (Ok(()), _) => InstanceStatus::Running,
+            (Err(e), _) => {
+                tracing::warn!("Failed to {:?} sidecar {} of instance {}: {}", action, name, id, e);
+                InstanceStatus::Error(e.to_string())
+            }
+        };
+        let mut state_guard = state.write().await;
+        let sidecar = state_guard
+            .instances
+            .get_mut(id)
+            .and_then(|instance| instance.sidecars.iter_mut().find(|sidecar| sidecar.name == name));
+        if let Some(sidecar) = sidecar {
+            sidecar.status = status;
+        }
+    }
+}
+
+/// Refresh the status of an instance's sidecars from Docker
+async fn refresh_sidecar_statuses(docker_manager: &DockerManager, sidecars: &mut [Sidecar]) {
+    for sidecar in sidecars {
+        match docker_manager.refresh_instance_status(&sidecar.container_id).await {
+            Ok(Some(status)) => sidecar.status = status,
+            Ok(None) => sidecar.status = InstanceStatus::Unknown("Container deleted externally".to_string()),
+            Err(e) => tracing::warn!("Failed to refresh status of sidecar {}: {}", sidecar.name, e),
+        }
+    }
+}
+
 async fn list_instances(
     State(state): State<Arc<RwLock<AppState>>>,
 ) -> Result<Json<Vec<InstanceDetails>>, ApiError> {
@@ -213,16 +957,19 @@ async fn list_instances(
         let mut deleted_count = 0;
 
         for (id, container_id) in &instance_ids {
+            if let Some(inst) = state_guard.instances.get_mut(id) {
+                refresh_sidecar_statuses(&docker_manager, &mut inst.sidecars).await;
+            }
             match docker_manager.refresh_instance_status(container_id).await {
                 Ok(Some(status)) => {
                     if let Some(inst) = state_guard.instances.get_mut(id) {
-                        inst.status = status;
+                        inst.status = inst.status.refreshed(status);
                     }
                 }
                 Ok(None) => {
                     // Container was deleted externally
                     if let Some(inst) = state_guard.instances.get_mut(id) {
-                        inst.status = InstanceStatus::Error("Container deleted externally".to_string());
+                        inst.status = InstanceStatus::Unknown("Container deleted externally".to_string());
                     }
                     deleted_count += 1;
                 }
@@ -244,8 +991,7 @@ async fn list_instances(
     let instances: Vec<InstanceDetails> = state_guard
         .instances
         .values()
-        .cloned()
-        .map(Into::into)
+        .map(|instance| instance_details(&state_guard, instance))
         .collect();
     Ok(Json(instances))
 }
@@ -264,18 +1010,21 @@ async fn get_instance(
 
     // Refresh this instance's status
     if let Ok(docker_manager) = super::docker::DockerManager::new() {
+        if let Some(inst) = state.write().await.instances.get_mut(&id) {
+            refresh_sidecar_statuses(&docker_manager, &mut inst.sidecars).await;
+        }
         match docker_manager.refresh_instance_status(&container_id).await {
             Ok(Some(status)) => {
                 let mut state_guard = state.write().await;
                 if let Some(inst) = state_guard.instances.get_mut(&id) {
-                    inst.status = status;
+                    inst.status = inst.status.refreshed(status);
                 }
             }
             Ok(None) => {
                 // Container was deleted externally
                 let mut state_guard = state.write().await;
                 if let Some(inst) = state_guard.instances.get_mut(&id) {
-                    inst.status = InstanceStatus::Error("Container deleted externally".to_string());
+                    inst.status = InstanceStatus::Unknown("Container deleted externally".to_string());
                 }
             }
             Err(e) => {
@@ -284,67 +1033,670 @@ async fn get_instance(
         }
     }
 
-    // Return (possibly refreshed) instance
-    let state_guard = state.read().await;
-    state_guard
-        .instances
-        .get(&id)
-        .cloned()
-        .map(Into::into)
-        .ok_or(ApiError::NotFound)
-        .map(Json)
+    // Return (possibly refreshed) instance
+    let state_guard = state.read().await;
+    state_guard
+        .instances
+        .get(&id)
+        .map(|instance| instance_details(&state_guard, instance))
+        .ok_or(ApiError::NotFound)
+        .map(Json)
+}
+
+/// Rename an instance or change its labels
+async fn update_instance(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+    Json(update): Json<UpdateInstanceRequest>,
+) -> Result<Json<InstanceDetails>, ApiError> {
+    update.validate().map_err(ApiError::BadRequest)?;
+    let mut state_guard = state.write().await;
+    if !state_guard.instances.contains_key(&id) {
+        return Err(ApiError::NotFound);
+    }
+    if let Some(name) = update.name.as_deref().filter(|name| !name.is_empty())
+        && state_guard.instances.values().any(|other| other.id != id && other.name.as_deref() == Some(name))
+    {
+        return Err(ApiError::Conflict(format!("Another instance is already named {}", name)));
+    }
+
+    let instance = state_guard.instances.get_mut(&id).ok_or(ApiError::NotFound)?;
+    if let Some(name) = update.name {
+        instance.name = Some(name).filter(|name| !name.is_empty());
+    }
+    for key in &update.remove_labels {
+        instance.labels.remove(key);
+    }
+    instance.labels.extend(update.labels);
+    let instance = instance.clone();
+    state_guard.save_metadata();
+    tracing::info!("Updated the name and labels of instance {}", id);
+    Ok(Json(instance_details(&state_guard, &instance)))
+}
+
+/// An instance's details, with its URLs pointing at `[server] public_host`
+fn instance_details(state: &AppState, instance: &Instance) -> InstanceDetails {
+    let mut details = InstanceDetails::from(instance.clone());
+    details.vnc_url = state.config.server.vnc_url(&instance.id, instance.vnc_port);
+    details.purge_at = instance
+        .trashed_at
+        .map(|trashed_at| trashed_at + state.config.instances.trash_retention());
+    details.operation = state.operations.in_flight(&instance.id).map(|operation| operation.as_str().to_string());
+    details
+}
+
+/// Take instance `id`'s operation lock, failing with 409 Conflict while another operation runs
+async fn lock_instance(state: &Arc<RwLock<AppState>>, id: &str, operation: Operation) -> Result<OperationGuard, ApiError> {
+    state.read().await.operations.try_lock(id, operation).map_err(|in_flight| {
+        ApiError::Conflict(format!("Instance is busy, a {} is in progress", in_flight.as_str()))
+    })
+}
+
+/// Move an instance to the trash, or delete it right away without a trash retention
+async fn delete_instance(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let _lock = lock_instance(&state, &id, Operation::Delete).await?;
+    if state.read().await.config.instances.trash_retention_hours == 0 {
+        tracing::info!("Deleting instance {}", id);
+        remove_instance(&state, &id).await?;
+    } else {
+        tracing::info!("Moving instance {} to the trash", id);
+        trash_instance(&state, &id).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Stop an instance and flag it as trashed, keeping its container and ports until it is purged
+async fn trash_instance(state: &Arc<RwLock<AppState>>, id: &str) -> Result<(), ApiError> {
+    let (keep, running) = {
+        let state_guard = state.read().await;
+        let instance = state_guard.instances.get(id).ok_or(ApiError::NotFound)?;
+        if instance.trashed_at.is_some() {
+            return Err(ApiError::Conflict(
+                "Instance is already in the trash, purge it to delete it for good".to_string(),
+            ));
+        }
+        // An instance still being created has nothing worth keeping
+        let keep = !instance.container_id.is_empty() && !matches!(instance.status, InstanceStatus::Creating);
+        (keep, instance.status.is_running())
+    };
+    if !keep {
+        return remove_instance(state, id).await;
+    }
+
+    if running {
+        stop_instance_by_id(state, id.to_string()).await?;
+    }
+
+    let mut state_guard = state.write().await;
+    let instance = state_guard.instances.get_mut(id).ok_or(ApiError::NotFound)?;
+    instance.status = InstanceStatus::Trashed;
+    instance.trashed_at = Some(Utc::now());
+    state_guard.save_trash();
+    state_guard.emit_event(id, InstanceEventKind::Trashed, None);
+    Ok(())
+}
+
+/// Take an instance out of the trash, stopped
+async fn restore_instance(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<Json<InstanceStatusResponse>, ApiError> {
+    tracing::info!("Restoring instance {}", id);
+    let _lock = lock_instance(&state, &id, Operation::Restore).await?;
+    let mut state_guard = state.write().await;
+    let instance = state_guard.instances.get_mut(&id).ok_or(ApiError::NotFound)?;
+    if instance.trashed_at.take().is_none() {
+        return Err(ApiError::Conflict("Instance is not in the trash".to_string()));
+    }
+    if matches!(instance.status, InstanceStatus::Trashed) {
+        instance.status = InstanceStatus::Stopped;
+    }
+    let status = instance.status.as_str().to_string();
+    state_guard.save_trash();
+    state_guard.emit_event(&id, InstanceEventKind::Restored, None);
+    Ok(Json(InstanceStatusResponse { id, status }))
+}
+
+/// Delete an instance for good, whether or not it is in the trash
+async fn purge_instance(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    tracing::info!("Purging instance {}", id);
+    let _lock = lock_instance(&state, &id, Operation::Purge).await?;
+    remove_instance(&state, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Purge the instances that have been in the trash for longer than `[instances] trash_retention_hours`
+pub async fn purge_expired_trash(state: &Arc<RwLock<AppState>>, now: DateTime<Utc>) {
+    let expired = state.read().await.expired_trash(now);
+    for id in expired {
+        let _lock = match lock_instance(state, &id, Operation::Purge).await {
+            Ok(lock) => lock,
+            Err(e) => {
+                tracing::info!("Not purging instance {} yet: {:?}", id, e);
+                continue;
+            }
+        };
+        // Unless it was restored in the meantime
+        if state.read().await.instances.get(&id).is_none_or(|instance| instance.trashed_at.is_none()) {
+            continue;
+        }
+        tracing::info!("Purging instance {} from the trash", id);
+        if let Err(e) = remove_instance(state, &id).await {
+            tracing::warn!("Failed to purge instance {}: {:?}", id, e);
+        }
+    }
+}
+
+/// Instances in the trash can't be started until they are restored
+fn reject_trashed(instance: &Instance) -> Result<(), ApiError> {
+    match instance.trashed_at {
+        Some(_) => Err(ApiError::Conflict("Instance is in the trash, restore it first".to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Remove an instance's container and temp files and release its ports
+async fn remove_instance(state: &Arc<RwLock<AppState>>, id: &str) -> Result<(), ApiError> {
+    // Get instance details for cleanup
+    let (container_id, vnc_port, console_port, sidecars) = {
+        let state_guard = state.read().await;
+        let instance = state_guard.instances.get(id).ok_or(ApiError::NotFound)?;
+        (instance.container_id.clone(), instance.vnc_port, instance.console_port, instance.sidecars.clone())
+    };
+
+    // Stop and remove container, sidecars first
+    if !container_id.is_empty() {
+        state.write().await.stopping.insert(id.to_string());
+        let docker_manager = super::docker::DockerManager::new()?;
+        remove_sidecars(&docker_manager, &sidecars).await;
+        if let Err(e) = docker_manager.stop_and_remove_container(&container_id).await {
+            tracing::warn!("Failed to remove container {}: {}", container_id, e);
+        }
+    }
+
+    // Clean up temp DLL file and mods
+    super::docker::cleanup_dll_temp(id);
+    super::docker::cleanup_instance_mods(id);
+
+    // Remove instance and release ports
+    {
+        let mut state_guard = state.write().await;
+        let removed = state_guard.instances.remove(id);
+        state_guard.recordings.remove(id);
+        state_guard.stopping.remove(id);
+        state_guard.ready_deadlines.remove(id);
+        state_guard.port_pools.local_mut().release_pair(vnc_port, console_port);
+        if let Some(instance) = removed {
+            if instance.schedule.is_some() {
+                state_guard.save_schedules();
+            }
+            if instance.trashed_at.is_some() {
+                state_guard.save_trash();
+            }
+            if instance.name.is_some() || !instance.labels.is_empty() {
+                state_guard.save_metadata();
+            }
+        }
+        state_guard.emit_event(id, InstanceEventKind::Deleted, None);
+    }
+
+    Ok(())
+}
+
+/// Start a test run: an instance that runs the integration tests and is deleted once they finish
+async fn create_test_run(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Json(req): Json<CreateTestRunRequest>,
+) -> Result<(StatusCode, Json<TestRun>), ApiError> {
+    let run_id = Uuid::new_v4().to_string();
+    let instance_id = Uuid::new_v4().to_string();
+    let (container_name, default_timeout_secs) = {
+        let state_guard = state.read().await;
+        (
+            format!("{}{}", state_guard.config.docker.container_prefix, instance_id),
+            state_guard.config.test_runs.timeout_secs,
+        )
+    };
+
+    tracing::info!("Creating test run {} on instance {}", run_id, instance_id);
+
+    if let Some(config) = &req.config {
+        config.validate().map_err(ApiError::BadRequest)?;
+    }
+    let dll = request_dll(&state, &req.dll).await?;
+    let (mods_dir, results_dir) = super::docker::write_test_run_files(&run_id, &req.mods).map_err(|e| {
+        super::docker::cleanup_test_run_files(&run_id);
+        ApiError::BadRequest(format!("{:#}", e))
+    })?;
+
+    let lock = lock_instance(&state, &instance_id, Operation::TestRun).await?;
+    let (vnc_port, console_port, dll_path) =
+        match register_instance(&state, &instance_id, &dll, req.config.clone().unwrap_or_default(), None).await {
+            Ok(registered) => registered,
+            Err(e) => {
+                super::docker::cleanup_test_run_files(&run_id);
+                return Err(e);
+            }
+        };
+
+    // OpenZT reads these when the integration tests start
+    let mut env = vec![format!("OPENZT_TEST_RESULTS={}", RESULTS_WINDOWS_PATH)];
+    if let Some(filter) = &req.filter {
+        env.push(format!("OPENZT_TEST_FILTER={}", filter));
+    }
+
+    let run = TestRun {
+        id: run_id.clone(),
+        instance_id: instance_id.clone(),
+        status: TestRunStatus::Starting,
+        created_at: Utc::now(),
+        finished_at: None,
+        filter: req.filter.clone(),
+        report: None,
+        error: None,
+    };
+    state.write().await.test_runs.insert(run_id.clone(), run.clone());
+
+    let launch = TestRunLaunch {
+        run_id,
+        instance_id,
+        container_name,
+        vnc_port,
+        console_port,
+        setup: ContainerSetup {
+            dll_path,
+            mods_dir: Some(mods_dir),
+            results_dir: Some(results_dir.clone()),
+            recordings_dir: None,
+            env,
+        },
+        results_file: PathBuf::from(results_dir).join(RESULTS_FILE),
+        timeout: Duration::from_secs(req.timeout_secs.unwrap_or(default_timeout_secs)),
+    };
+    tokio::spawn(test_run_task(state.clone(), launch, lock));
+
+    Ok((StatusCode::ACCEPTED, Json(run)))
+}
+
+/// Everything the background task of a test run needs
+struct TestRunLaunch {
+    run_id: String,
+    instance_id: String,
+    container_name: String,
+    vnc_port: u16,
+    console_port: u16,
+    setup: ContainerSetup,
+    results_file: PathBuf,
+    timeout: Duration,
+}
+
+/// Create the test run's container, wait for the results, then delete the instance
+///
+/// The instance's operation lock is held throughout, so it can't be changed under the run.
+async fn test_run_task(state: Arc<RwLock<AppState>>, launch: TestRunLaunch, _lock: OperationGuard) {
+    let TestRunLaunch {
+        run_id,
+        instance_id,
+        ..
+    } = &launch;
+
+    let created = create_container_task(
+        state.clone(),
+        instance_id.clone(),
+        launch.container_name.clone(),
+        launch.vnc_port,
+        launch.console_port,
+        launch.setup.clone(),
+    )
+    .await;
+
+    let outcome = match created {
+        Ok(()) => {
+            if let Some(run) = state.write().await.test_runs.get_mut(run_id) {
+                run.status = TestRunStatus::Running;
+            }
+            let outcome = wait_for_results(&state, instance_id, &launch.results_file, launch.timeout).await;
+            if let Err(e) = remove_instance(&state, instance_id).await {
+                tracing::warn!("Failed to delete instance {} of test run {}: {:?}", instance_id, run_id, e);
+            }
+            outcome
+        }
+        Err(e) => {
+            fail_instance(&state, instance_id, launch.vnc_port, launch.console_port, &e).await;
+            let mut state_guard = state.write().await;
+            state_guard.instances.remove(instance_id);
+            state_guard.emit_event(instance_id, InstanceEventKind::Deleted, None);
+            Err(e)
+        }
+    };
+
+    super::docker::cleanup_test_run_files(run_id);
+
+    let mut state_guard = state.write().await;
+    let Some(run) = state_guard.test_runs.get_mut(run_id) else {
+        return;
+    };
+    match outcome {
+        Ok(report) => {
+            tracing::info!("Test run {} finished: {} passed, {} failed", run_id, report.passed, report.failed);
+            run.complete(report);
+        }
+        Err(e) => {
+            tracing::error!("Test run {} failed: {:#}", run_id, e);
+            run.fail(format!("{:#}", e));
+        }
+    }
+}
+
+/// Wait until the game has written its results, its container stops or `timeout` passes
+async fn wait_for_results(
+    state: &Arc<RwLock<AppState>>,
+    instance_id: &str,
+    results_file: &FsPath,
+    timeout: Duration,
+) -> anyhow::Result<TestRunReport> {
+    let docker_manager = super::docker::DockerManager::new()?;
+    let container_id = state
+        .read()
+        .await
+        .instances
+        .get(instance_id)
+        .map(|instance| instance.container_id.clone())
+        .ok_or_else(|| anyhow::anyhow!("Instance {} was deleted during the test run", instance_id))?;
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        // The file can be half written, it's read again on the next poll
+        if let Ok(Some(report)) = read_results(results_file) {
+            return Ok(report);
+        }
+
+        let running = matches!(
+            docker_manager.refresh_instance_status(&container_id).await,
+            Ok(Some(InstanceStatus::Running)) | Err(_)
+        );
+        if !running {
+            // The game may have written its results just before it exited
+            return read_results(results_file)?
+                .ok_or_else(|| anyhow::anyhow!("The game exited without writing test results"));
+        }
+
+        if Instant::now() >= deadline {
+            anyhow::bail!("No test results after {} seconds", timeout.as_secs());
+        }
+        tokio::time::sleep(RESULTS_POLL_INTERVAL).await;
+    }
+}
+
+/// The results the game wrote, `None` if there are none yet
+fn read_results(path: &FsPath) -> anyhow::Result<Option<TestRunReport>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow::Error::new(e).context("Failed to read test results")),
+    };
+    let report = serde_json::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid test results: {}", e))?;
+    Ok(Some(report))
+}
+
+async fn list_test_runs(State(state): State<Arc<RwLock<AppState>>>) -> Json<Vec<TestRun>> {
+    let state_guard = state.read().await;
+    let mut runs: Vec<TestRun> = state_guard.test_runs.values().cloned().collect();
+    runs.sort_by_key(|run| run.created_at);
+    Json(runs)
+}
+
+async fn get_test_run(
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<Json<TestRun>, ApiError> {
+    let state_guard = state.read().await;
+    state_guard
+        .test_runs
+        .get(&id)
+        .cloned()
+        .map(Json)
+        .ok_or(ApiError::TestRunNotFound)
+}
+
+/// Store a DLL in the DLL library, `201 Created` if it wasn't there yet
+async fn upload_dll(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Json(req): Json<UploadDllRequest>,
+) -> Result<(StatusCode, Json<DllEntry>), ApiError> {
+    if req.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("DLL name must not be empty".to_string()));
+    }
+    let dll = super::docker::decode_dll(&req.data).map_err(|e| ApiError::InvalidDll(e.to_string()))?;
+
+    let (entry, added) = state.write().await.dlls.add(&req.name, &dll)?;
+    let status = if added { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(entry)))
+}
+
+/// Store a mod in the mod repository
+async fn upload_mod(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Json(req): Json<UploadModRequest>,
+) -> Result<(StatusCode, Json<ModEntry>), ApiError> {
+    let ztd = base64::Engine::decode(&base64::prelude::BASE64_STANDARD, &req.data)
+        .map_err(|e| ApiError::BadRequest(format!("Failed to decode base64 mod: {}", e)))?;
+
+    let entry = state.write().await.mods.add(&req.name, &req.version, &ztd).map_err(|e| {
+        match e.downcast_ref::<ModError>() {
+            Some(ModError::AlreadyExists(_)) => ApiError::Conflict(e.to_string()),
+            Some(_) => ApiError::BadRequest(e.to_string()),
+            None => ApiError::Internal(format!("{:#}", e)),
+        }
+    })?;
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+async fn list_mods(State(state): State<Arc<RwLock<AppState>>>) -> Json<Vec<ModEntry>> {
+    Json(state.read().await.mods.list())
+}
+
+async fn delete_mod(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path((name, version)): Path<(String, String)>,
+) -> Result<StatusCode, ApiError> {
+    if state.write().await.mods.remove(&name, &version)? {
+        tracing::info!("Removed mod {}@{} from the repository", name, version);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::ModNotFound(ModError::NotFound(format!("{}@{}", name, version)).to_string()))
+    }
+}
+
+/// Store a new template, `409 Conflict` if the name is taken
+async fn create_template(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Json(template): Json<Template>,
+) -> Result<(StatusCode, Json<Template>), ApiError> {
+    template.validate().map_err(ApiError::BadRequest)?;
+    let mut state_guard = state.write().await;
+    if state_guard.templates.get(&template.name).is_some() {
+        return Err(ApiError::Conflict(format!("Template {} already exists", template.name)));
+    }
+    state_guard.templates.save(template.clone())?;
+    tracing::info!("Created template {}", template.name);
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+async fn list_templates(State(state): State<Arc<RwLock<AppState>>>) -> Json<Vec<Template>> {
+    Json(state.read().await.templates.list())
+}
+
+async fn get_template(
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(name): Path<String>,
+) -> Result<Json<Template>, ApiError> {
+    let state_guard = state.read().await;
+    state_guard.templates.get(&name).cloned().map(Json).ok_or(ApiError::TemplateNotFound)
+}
+
+/// Create or replace the template `name`
+async fn update_template(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(name): Path<String>,
+    Json(template): Json<Template>,
+) -> Result<Json<Template>, ApiError> {
+    if template.name != name {
+        return Err(ApiError::BadRequest(format!(
+            "Template name '{}' doesn't match the URL's '{}'",
+            template.name, name
+        )));
+    }
+    template.validate().map_err(ApiError::BadRequest)?;
+    state.write().await.templates.save(template.clone())?;
+    tracing::info!("Saved template {}", name);
+    Ok(Json(template))
+}
+
+async fn delete_template(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if state.write().await.templates.remove(&name)? {
+        tracing::info!("Deleted template {}", name);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::TemplateNotFound)
+    }
+}
+
+async fn create_token(
+    _: RequireAdmin,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Json(request): Json<CreateTokenRequest>,
+) -> Result<(StatusCode, Json<CreateTokenResponse>), ApiError> {
+    if request.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("Token name must not be empty".to_string()));
+    }
+    let created = state.write().await.tokens.create(&request.name, request.scope)?;
+    tracing::info!(
+        "Issued {} API token {} for {}",
+        request.scope.as_str(),
+        created.token.id,
+        request.name
+    );
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn list_tokens(_: RequireAdmin, State(state): State<Arc<RwLock<AppState>>>) -> Json<Vec<ApiToken>> {
+    Json(state.read().await.tokens.list())
 }
 
-async fn delete_instance(
+async fn revoke_token(
+    _: RequireAdmin,
     State(state): State<Arc<RwLock<AppState>>>,
     Path(id): Path<String>,
 ) -> Result<StatusCode, ApiError> {
-    tracing::info!("Deleting instance {}", id);
-
-    // Get instance details for cleanup
-    let (container_id, vnc_port, console_port) = {
-        let state_guard = state.read().await;
-        let instance = state_guard.instances.get(&id).ok_or(ApiError::NotFound)?;
-        (instance.container_id.clone(), instance.vnc_port, instance.console_port)
-    };
-
-    // Stop and remove container
-    if !container_id.is_empty() {
-        let docker_manager = super::docker::DockerManager::new()?;
-        if let Err(e) = docker_manager.stop_and_remove_container(&container_id).await {
-            tracing::warn!("Failed to remove container {}: {}", container_id, e);
-        }
+    if state.write().await.tokens.revoke(&id)? {
+        tracing::info!("Revoked API token {}", id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::TokenNotFound)
     }
+}
 
-    // Clean up temp DLL file
-    super::docker::cleanup_dll_temp(&id);
+async fn list_dlls(State(state): State<Arc<RwLock<AppState>>>) -> Json<Vec<DllEntry>> {
+    Json(state.read().await.dlls.list())
+}
 
-    // Remove instance and release ports
-    {
-        let mut state_guard = state.write().await;
-        state_guard.instances.remove(&id);
-        state_guard.port_pool.release_pair(vnc_port, console_port);
-    }
+async fn get_dll(
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<Json<DllEntry>, ApiError> {
+    let state_guard = state.read().await;
+    state_guard.dlls.get(&id).cloned().map(Json).ok_or(ApiError::DllNotFound)
+}
 
-    Ok(StatusCode::NO_CONTENT)
+async fn delete_dll(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if state.write().await.dlls.remove(&id)? {
+        tracing::info!("Removed DLL {} from the library", id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::DllNotFound)
+    }
 }
 
 #[derive(Deserialize)]
 struct LogsParams {
     #[serde(default = "default_log_type")]
     r#type: String,
+    /// Trailing lines to return; 0 means all available
     tail: Option<u32>,
+    /// RFC 3339 time, unix seconds, or relative duration (e.g. "10m")
+    since: Option<String>,
+    /// RFC 3339 time, unix seconds, or relative duration (e.g. "10m")
+    until: Option<String>,
+    /// Prefix Docker log lines with timestamps
+    #[serde(default)]
+    timestamps: bool,
+    /// Read Docker logs from the log archive, also for deleted instances
+    #[serde(default)]
+    archived: bool,
 }
 
 fn default_log_type() -> String {
     "openzt".to_string()
 }
 
+impl LogsParams {
+    /// Build a log filter, using `default_tail` when no tail was requested
+    fn to_filter(&self, default_tail: u32) -> Result<LogFilter, ApiError> {
+        let now = Utc::now();
+        let parse = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|v| parse_log_time(v, now))
+                .transpose()
+                .map_err(ApiError::BadRequest)
+        };
+
+        Ok(LogFilter {
+            tail: match self.tail.unwrap_or(default_tail) {
+                0 => None,
+                n => Some(n),
+            },
+            since: parse(&self.since)?,
+            until: parse(&self.until)?,
+            timestamps: self.timestamps,
+        })
+    }
+}
+
 async fn get_instance_logs(
     State(state): State<Arc<RwLock<AppState>>>,
     Path(id): Path<String>,
     Query(params): Query<LogsParams>,
 ) -> Result<Json<LogsResponse>, ApiError> {
+    if params.archived {
+        return get_archived_logs(&state, id, params).await;
+    }
+
     let state_guard = state.read().await;
     let instance = state_guard.instances.get(&id).ok_or(ApiError::NotFound)?;
     let container_id = &instance.container_id;
@@ -358,17 +1710,17 @@ async fn get_instance_logs(
     }
 
     let docker_manager = super::docker::DockerManager::new()?;
-    let tail = params.tail.unwrap_or(100);
+    let filter = params.to_filter(100)?;
 
     let logs = match params.r#type.as_str() {
         "docker" => {
-            docker_manager.get_container_logs(container_id, tail).await?
+            docker_manager.get_container_logs(container_id, &filter).await?
         }
         "openzt" => {
-            docker_manager.get_app_logs(container_id, AppLogType::Openzt, tail).await?
+            docker_manager.get_app_logs(container_id, AppLogType::Openzt, &filter).await?
         }
         "integration-tests" => {
-            docker_manager.get_app_logs(container_id, AppLogType::IntegrationTests, tail).await?
+            docker_manager.get_app_logs(container_id, AppLogType::IntegrationTests, &filter).await?
         }
         _ => {
             return Err(ApiError::Internal(format!(
@@ -385,6 +1737,106 @@ async fn get_instance_logs(
     }))
 }
 
+async fn get_archived_logs(
+    state: &Arc<RwLock<AppState>>,
+    id: String,
+    params: LogsParams,
+) -> Result<Json<LogsResponse>, ApiError> {
+    if params.r#type != "docker" {
+        return Err(ApiError::BadRequest("Only docker logs are archived".to_string()));
+    }
+    let filter = params.to_filter(100)?;
+
+    let dir = state.read().await.config.log_archive.dir.clone();
+    let archived = tokio::task::spawn_blocking(move || super::log_archive::read(&dir, &id).map(|logs| (id, logs)))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))??;
+    let (id, Some(archived)) = archived else {
+        return Err(ApiError::NotFound);
+    };
+
+    // Lines are archived with their timestamps, which are only shown when asked for
+    let mut logs = filter.apply_to_text(&archived);
+    if !filter.timestamps {
+        logs = logs
+            .lines()
+            .map(|line| match super::log_archive::line_timestamp(line) {
+                Some(_) => line.split_once(' ').map_or("", |(_, rest)| rest),
+                None => line,
+            })
+            .fold(String::new(), |mut logs, line| {
+                logs.push_str(line);
+                logs.push('\n');
+                logs
+            });
+    }
+
+    Ok(Json(LogsResponse {
+        instance_id: id,
+        log_type: params.r#type,
+        logs,
+    }))
+}
+
+/// Follow an instance's Docker logs into the log archive until the instance is deleted
+///
+/// Following starts again when the container stops and starts, resuming after the
+/// last archived line.
+pub async fn archive_logs(state: Arc<RwLock<AppState>>, instance_id: String) {
+    let config = state.read().await.config.log_archive.clone();
+    let mut log = match super::log_archive::RotatingLog::open(
+        &config.dir,
+        &instance_id,
+        config.max_file_mb * 1024 * 1024,
+        config.max_files,
+    ) {
+        Ok(log) => log,
+        Err(e) => {
+            tracing::warn!("Not archiving logs of instance {}: {:#}", instance_id, e);
+            return;
+        }
+    };
+    let mut last = log.last_timestamp();
+
+    loop {
+        let container_id = match state.read().await.instances.get(&instance_id) {
+            Some(instance) => instance.container_id.clone(),
+            None => break,
+        };
+        let docker_manager = match super::docker::DockerManager::new() {
+            Ok(docker_manager) => docker_manager,
+            Err(e) => {
+                tracing::warn!("Not archiving logs of instance {}: {}", instance_id, e);
+                return;
+            }
+        };
+
+        let filter = LogFilter {
+            since: last,
+            timestamps: true,
+            ..Default::default()
+        };
+        let mut stream = docker_manager.stream_container_logs(&container_id, &filter);
+        while let Some(Ok(chunk)) = stream.next().await {
+            for line in chunk.lines() {
+                // Docker only filters by whole seconds
+                let timestamp = super::log_archive::line_timestamp(line);
+                if timestamp.is_some_and(|timestamp| last.is_some_and(|last| timestamp <= last)) {
+                    continue;
+                }
+                if let Err(e) = log.write_line(line) {
+                    tracing::warn!("Failed to archive logs of instance {}: {:#}", instance_id, e);
+                }
+                last = timestamp.or(last);
+            }
+        }
+
+        // The container stopped or was removed
+        tokio::time::sleep(LOG_ARCHIVE_RETRY).await;
+    }
+    tracing::info!("Stopped archiving logs of deleted instance {}", instance_id);
+}
+
 async fn stream_logs(
     State(state): State<Arc<RwLock<AppState>>>,
     Path(id): Path<String>,
@@ -400,15 +1852,21 @@ async fn stream_logs(
 
     let docker_manager = super::docker::DockerManager::new()?;
 
+    // Without an explicit tail (or since), Docker logs start from new output
+    // and application logs show the last 10 lines (matching `tail -f`)
     let log_stream = match params.r#type.as_str() {
         "docker" => {
-            docker_manager.stream_container_logs(&container_id, 0)
+            let mut filter = params.to_filter(0)?;
+            if params.tail.is_none() && params.since.is_none() {
+                filter.tail = Some(0);
+            }
+            docker_manager.stream_container_logs(&container_id, &filter)
         }
         "openzt" => {
-            docker_manager.stream_app_logs(&container_id, AppLogType::Openzt)
+            docker_manager.stream_app_logs(&container_id, AppLogType::Openzt, params.to_filter(10)?.tail)
         }
         "integration-tests" => {
-            docker_manager.stream_app_logs(&container_id, AppLogType::IntegrationTests)
+            docker_manager.stream_app_logs(&container_id, AppLogType::IntegrationTests, params.to_filter(10)?.tail)
         }
         _ => {
             return Err(ApiError::Internal(format!(
@@ -439,11 +1897,478 @@ async fn stream_logs(
     ).into_response())
 }
 
+/// Stream lifecycle events for all instances as SSE
+///
+/// Each event is sent with the event kind as the SSE event name and the
+/// JSON-encoded `InstanceEvent` as data.
+async fn stream_events(
+    State(state): State<Arc<RwLock<AppState>>>,
+) -> Response {
+    let mut receiver = state.read().await.events.subscribe();
+
+    let sse_stream = async_stream::stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    yield Ok::<_, Infallible>(Event::default().event(event.kind.as_str()).data(data));
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event subscriber lagged, skipped {} events", skipped);
+                    yield Ok::<_, Infallible>(Event::default().comment(format!("skipped {} events", skipped)));
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Sse::new(sse_stream)
+        .keep_alive(axum::response::sse::KeepAlive::new().interval(Duration::from_secs(10)))
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct TunnelParams {
+    /// Instance port to tunnel to: "console" or "vnc"
+    #[serde(default = "default_tunnel_port")]
+    port: String,
+}
+
+fn default_tunnel_port() -> String {
+    "console".to_string()
+}
+
+/// Tunnel a TCP connection to an instance port over a WebSocket
+///
+/// Used by `openzt port-forward` so clients only need to reach the API
+/// server, not the published instance port range. Raw TCP bytes are carried
+/// in binary WebSocket messages in both directions.
+async fn tunnel_instance_port(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+    Query(params): Query<TunnelParams>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let host_port = {
+        let state_guard = state.read().await;
+        let instance = state_guard.instances.get(&id).ok_or(ApiError::NotFound)?;
+        match params.port.as_str() {
+            "console" => instance.console_port,
+            "vnc" => instance.vnc_port,
+            other => {
+                return Err(ApiError::BadRequest(format!(
+                    "Invalid port: '{}'. Valid ports are: console, vnc",
+                    other
+                )));
+            }
+        }
+    };
+
+    // Connect before upgrading so an unreachable port is reported as an HTTP error
+    let tcp = TcpStream::connect(("127.0.0.1", host_port))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to connect to instance {} port: {}", params.port, e)))?;
+
+    tracing::info!("Opening {} tunnel to instance {}", params.port, id);
+
+    Ok(ws.on_upgrade(move |socket| pipe_tunnel(socket, tcp)))
+}
+
+/// Copy data between a tunnel WebSocket and the instance TCP connection until either side closes
+async fn pipe_tunnel(mut socket: WebSocket, mut tcp: TcpStream) {
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        tokio::select! {
+            read = tcp.read(&mut buf) => match read {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if socket.send(Message::Binary(buf[..n].to_vec().into())).await.is_err() {
+                        break;
+                    }
+                }
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Binary(data))) => {
+                    if tcp.write_all(&data).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; text frames have no meaning here
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// An instance's schedule, empty if it has none
+async fn get_schedule(
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<Json<Schedule>, ApiError> {
+    let state_guard = state.read().await;
+    let instance = state_guard.instances.get(&id).ok_or(ApiError::NotFound)?;
+    Ok(Json(instance.schedule.clone().unwrap_or_default()))
+}
+
+/// Replace an instance's schedule
+async fn set_schedule(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+    Json(schedule): Json<Schedule>,
+) -> Result<Json<Schedule>, ApiError> {
+    schedule.validate().map_err(ApiError::BadRequest)?;
+    let mut state_guard = state.write().await;
+    let instance = state_guard.instances.get_mut(&id).ok_or(ApiError::NotFound)?;
+    instance.schedule = Some(schedule.clone()).filter(|schedule| !schedule.is_empty());
+    state_guard.save_schedules();
+    tracing::info!("Updated the schedule of instance {}", id);
+    Ok(Json(schedule))
+}
+
+async fn delete_schedule(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut state_guard = state.write().await;
+    let instance = state_guard.instances.get_mut(&id).ok_or(ApiError::NotFound)?;
+    instance.schedule = None;
+    state_guard.save_schedules();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Start and stop the instances whose schedule is due after `since` and up to `now`
+pub async fn run_schedules(state: &Arc<RwLock<AppState>>, since: DateTime<Utc>, now: DateTime<Utc>) {
+    let due: Vec<(String, ScheduledAction)> = {
+        let mut state_guard = state.write().await;
+        let mut due = Vec::new();
+        let mut changed = false;
+        for instance in state_guard.instances.values_mut() {
+            // Instances still being created run their schedule once they exist, and
+            // trashed ones once they are restored
+            if matches!(instance.status, InstanceStatus::Creating) || instance.trashed_at.is_some() {
+                continue;
+            }
+            let Some(schedule) = &mut instance.schedule else {
+                continue;
+            };
+            if let Some(action) = schedule.due(since, now) {
+                due.push((instance.id.clone(), action));
+            }
+            let before = schedule.clone();
+            schedule.clear_passed(now);
+            changed |= *schedule != before;
+            if schedule.is_empty() {
+                instance.schedule = None;
+            }
+        }
+        if changed {
+            state_guard.save_schedules();
+        }
+        due
+    };
+
+    for (id, action) in due {
+        tracing::info!("Scheduled {:?} of instance {}", action, id);
+        let result = async {
+            let _lock = match action {
+                ScheduledAction::Start => lock_instance(state, &id, Operation::Start).await?,
+                ScheduledAction::Stop => lock_instance(state, &id, Operation::Stop).await?,
+            };
+            match action {
+                ScheduledAction::Start => start_instance_by_id(state, id.clone()).await,
+                ScheduledAction::Stop => stop_instance_by_id(state, id.clone()).await,
+            }
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Scheduled {:?} of instance {} failed: {:?}", action, id, e);
+        }
+    }
+}
+
+/// Start recording an instance's display
+async fn start_recording(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Recording>), ApiError> {
+    let name = recording::recording_name(Utc::now());
+    let (container_id, framerate) = {
+        let mut state_guard = state.write().await;
+        let instance = state_guard.instances.get(&id).ok_or(ApiError::NotFound)?;
+        if !instance.status.is_running() {
+            return Err(ApiError::Conflict("Instance is not running".to_string()));
+        }
+        if !recording::instance_dir(&state_guard.config.recordings.dir, &id).exists() {
+            return Err(ApiError::Conflict(
+                "Instance was created without a recordings directory".to_string(),
+            ));
+        }
+        if let Some(active) = state_guard.recordings.get(&id) {
+            return Err(ApiError::Conflict(format!("Instance is already recording to {}", active)));
+        }
+        let container_id = instance.container_id.clone();
+        state_guard.recordings.insert(id.clone(), name.clone());
+        (container_id, state_guard.config.recordings.framerate)
+    };
+
+    let docker_manager = super::docker::DockerManager::new()?;
+    if let Err(e) = docker_manager.start_recording(&container_id, &name, framerate).await {
+        state.write().await.recordings.remove(&id);
+        return Err(ApiError::Internal(format!("{:#}", e)));
+    }
+
+    tracing::info!("Recording instance {} to {}", id, name);
+    Ok((
+        StatusCode::CREATED,
+        Json(Recording {
+            name,
+            size: 0,
+            modified_at: Utc::now(),
+            active: true,
+        }),
+    ))
+}
+
+/// Stop recording an instance's display, returning the finished recording
+async fn stop_recording(
+    _: RequireOperator,
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<Json<Recording>, ApiError> {
+    let (container_id, name, dir) = {
+        let mut state_guard = state.write().await;
+        let container_id = state_guard.instances.get(&id).ok_or(ApiError::NotFound)?.container_id.clone();
+        let name = state_guard
+            .recordings
+            .remove(&id)
+            .ok_or_else(|| ApiError::Conflict("Instance is not recording".to_string()))?;
+        (container_id, name, state_guard.config.recordings.dir.clone())
+    };
+
+    let docker_manager = super::docker::DockerManager::new()?;
+    match tokio::time::timeout(RECORDING_STOP_TIMEOUT, docker_manager.stop_recording(&container_id, &name)).await {
+        Ok(result) => result?,
+        Err(_) => tracing::warn!("ffmpeg recording {} of instance {} didn't exit in time", name, id),
+    }
+
+    tracing::info!("Stopped recording instance {} to {}", id, name);
+    recording::list(&dir, &id, None)?
+        .into_iter()
+        .find(|recording| recording.name == name)
+        .map(Json)
+        .ok_or_else(|| ApiError::Internal(format!("Recording {} wasn't written, see {}.log", name, name)))
+}
+
+/// An instance's recordings, also after the instance is deleted
+async fn list_recordings(
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<Recording>>, ApiError> {
+    let state_guard = state.read().await;
+    let dir = &state_guard.config.recordings.dir;
+    if !state_guard.instances.contains_key(&id) && !recording::instance_dir(dir, &id).exists() {
+        return Err(ApiError::NotFound);
+    }
+    let active = state_guard.recordings.get(&id).map(String::as_str);
+    Ok(Json(recording::list(dir, &id, active)?))
+}
+
+async fn download_recording(
+    State(state): State<Arc<RwLock<AppState>>>,
+    Path((id, name)): Path<(String, String)>,
+) -> Result<Response, ApiError> {
+    if !recording::is_valid_name(&name) {
+        return Err(ApiError::RecordingNotFound);
+    }
+    let path = recording::instance_dir(&state.read().await.config.recordings.dir, &id).join(&name);
+    let mut file = tokio::fs::File::open(&path).await.map_err(|_| ApiError::RecordingNotFound)?;
+
+    let body = async_stream::stream! {
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            match file.read(&mut buffer).await {
+                Ok(0) => break,
+                Ok(n) => yield Ok(buffer[..n].to_vec()),
+                Err(e) => {
+                    yield Err(e);
+                    break;
+                }
+            }
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "video/x-matroska".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}-{}\"", id, name)),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// Record exits of the server's containers until Docker's event stream ends
+pub async fn watch_container_exits(state: &Arc<RwLock<AppState>>) {
+    let docker_manager = match super::docker::DockerManager::new() {
+        Ok(docker_manager) => docker_manager,
+        Err(e) => {
+            tracing::warn!("Can't watch for container exits: {}", e);
+            return;
+        }
+    };
+
+    let mut exits = docker_manager.container_exits();
+    while let Some(exit) = exits.next().await {
+        match exit {
+            Ok(container_id) => handle_container_exit(state, &docker_manager, &container_id).await,
+            Err(e) => {
+                tracing::warn!("Stopped watching for container exits: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Record a running instance's container exiting on its own, restarting it if it crashed
+///
+/// Restarts follow the instance's [`RestartPolicy`]; an instance that crashed too often
+/// is marked failed instead.
+async fn handle_container_exit(
+    state: &Arc<RwLock<AppState>>,
+    docker_manager: &super::docker::DockerManager,
+    container_id: &str,
+) {
+    // Exits of containers the server is stopping or that weren't running aren't crashes
+    let expected = |state_guard: &AppState, instance: &Instance| {
+        !instance.status.is_running() || state_guard.stopping.contains(&instance.id)
+    };
+    let id = {
+        let state_guard = state.read().await;
+        match state_guard.instances.values().find(|instance| instance.container_id == container_id) {
+            Some(instance) if !expected(&state_guard, instance) => instance.id.clone(),
+            _ => return,
+        }
+    };
+
+    let exit = match docker_manager.container_exit(container_id).await {
+        Ok(Some(exit)) => exit,
+        // Already running again
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to check how instance {} exited: {}", id, e);
+            return;
+        }
+    };
+
+    let restart = {
+        let mut state_guard = state.write().await;
+        let default_policy = state_guard.config.instances.restart_policy();
+        // Checked again, the server may have stopped the instance in the meantime
+        match state_guard.instances.get(&id) {
+            Some(instance) if !expected(&state_guard, instance) => {}
+            _ => return,
+        }
+
+        let message = exit.describe();
+        let (status, kind) = if exit.oom_killed {
+            (InstanceStatus::Error(message.clone()), InstanceEventKind::OomKilled)
+        } else if exit.crashed() {
+            (InstanceStatus::Error(message.clone()), InstanceEventKind::Crashed)
+        } else {
+            (InstanceStatus::Stopped, InstanceEventKind::Stopped)
+        };
+        let Some(instance) = state_guard.instances.get_mut(&id) else {
+            return;
+        };
+        let policy = instance.config.restart_policy.unwrap_or(default_policy);
+        let decision = (exit.crashed() && policy.is_enabled())
+            .then(|| policy.decide(&mut instance.auto_restarts, exit.exited_at));
+        instance.status = match decision {
+            Some(RestartDecision::GiveUp) => InstanceStatus::Failed,
+            _ => status,
+        };
+        instance.last_exit = Some(exit.clone());
+        // The game's process is gone, and with it any recording
+        state_guard.recordings.remove(&id);
+        state_guard.emit_event(&id, kind, Some(message.clone()));
+
+        if exit.crashed() {
+            tracing::warn!("Instance {}: {}", id, message);
+        } else {
+            tracing::info!("Instance {} exited", id);
+        }
+
+        match decision {
+            Some(RestartDecision::Restart { attempt, delay }) => {
+                let message = format!(
+                    "Restarting in {}s ({} of {} restarts within {}s)",
+                    delay.as_secs(),
+                    attempt,
+                    policy.max_restarts,
+                    policy.window_secs
+                );
+                tracing::info!("Instance {}: {}", id, message);
+                state_guard.emit_event(&id, InstanceEventKind::Restarting, Some(message));
+                Some(delay)
+            }
+            Some(RestartDecision::GiveUp) => {
+                let message = format!(
+                    "Crashed after {} restarts within {}s, not restarting again",
+                    policy.max_restarts, policy.window_secs
+                );
+                tracing::warn!("Instance {}: {}", id, message);
+                state_guard.emit_event(&id, InstanceEventKind::Failed, Some(message));
+                None
+            }
+            None => None,
+        }
+    };
+
+    if let Some(delay) = restart {
+        let state = state.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _lock = match lock_instance(&state, &id, Operation::Start).await {
+                Ok(lock) => lock,
+                Err(e) => {
+                    tracing::warn!("Not restarting instance {}: {:?}", id, e);
+                    return;
+                }
+            };
+            // Unless it was started, stopped or deleted during the backoff
+            let crashed = state
+                .read()
+                .await
+                .instances
+                .get(&id)
+                .is_some_and(|instance| matches!(instance.status, InstanceStatus::Error(_)));
+            if !crashed {
+                return;
+            }
+            if let Err(e) = start_instance_by_id(&state, id.clone()).await {
+                tracing::warn!("Failed to restart instance {}: {:?}", id, e);
+            }
+        });
+    }
+}
+
 async fn stop_instance(
+    _: RequireOperator,
     State(state): State<Arc<RwLock<AppState>>>,
     Path(id): Path<String>,
 ) -> Result<Json<InstanceStatusResponse>, ApiError> {
     tracing::info!("Stopping instance {}", id);
+    let _lock = lock_instance(&state, &id, Operation::Stop).await?;
+    stop_instance_by_id(&state, id).await.map(Json)
+}
+
+async fn stop_instance_by_id(state: &Arc<RwLock<AppState>>, id: String) -> Result<InstanceStatusResponse, ApiError> {
 
     // Get container_id
     let container_id = {
@@ -452,10 +2377,10 @@ async fn stop_instance(
 
         // Check if already stopped
         if matches!(instance.status, InstanceStatus::Stopped) {
-            return Ok(Json(InstanceStatusResponse {
+            return Ok(InstanceStatusResponse {
                 id: id.clone(),
                 status: instance.status.as_str().to_string(),
-            }));
+            });
         }
 
         // Check if container exists
@@ -469,8 +2394,13 @@ async fn stop_instance(
     // Stop the container
     let docker_manager = super::docker::DockerManager::new()
         .map_err(|e| ApiError::Internal(e.to_string()))?;
-    docker_manager.stop_container(&container_id).await
-        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    // The container's exit is expected, not a crash
+    state.write().await.stopping.insert(id.clone());
+    update_sidecars(state, &docker_manager, &id, SidecarAction::Stop).await;
+    if let Err(e) = docker_manager.stop_container(&container_id).await {
+        state.write().await.stopping.remove(&id);
+        return Err(ApiError::Internal(e.to_string()));
+    }
 
     // Update instance status
     {
@@ -478,31 +2408,46 @@ async fn stop_instance(
         if let Some(instance) = state_guard.instances.get_mut(&id) {
             instance.status = InstanceStatus::Stopped;
         }
+        state_guard.stopping.remove(&id);
+        // Stopping the container ended any recording
+        state_guard.recordings.remove(&id);
+        state_guard.emit_event(&id, InstanceEventKind::Stopped, None);
     }
 
-    Ok(Json(InstanceStatusResponse {
+    Ok(InstanceStatusResponse {
         id,
         status: "stopped".to_string(),
-    }))
+    })
 }
 
 async fn start_instance(
+    _: RequireOperator,
     State(state): State<Arc<RwLock<AppState>>>,
     Path(id): Path<String>,
 ) -> Result<Json<InstanceStatusResponse>, ApiError> {
     tracing::info!("Starting instance {}", id);
+    let _lock = lock_instance(&state, &id, Operation::Start).await?;
+    // Started by hand, the instance gets its full restart budget again
+    if let Some(instance) = state.write().await.instances.get_mut(&id) {
+        instance.auto_restarts.clear();
+    }
+    start_instance_by_id(&state, id).await.map(Json)
+}
+
+async fn start_instance_by_id(state: &Arc<RwLock<AppState>>, id: String) -> Result<InstanceStatusResponse, ApiError> {
 
     // Get container_id
     let container_id = {
         let state_guard = state.read().await;
         let instance = state_guard.instances.get(&id).ok_or(ApiError::NotFound)?;
+        reject_trashed(instance)?;
 
         // Check if already running
-        if matches!(instance.status, InstanceStatus::Running) {
-            return Ok(Json(InstanceStatusResponse {
+        if instance.status.is_running() {
+            return Ok(InstanceStatusResponse {
                 id: id.clone(),
                 status: instance.status.as_str().to_string(),
-            }));
+            });
         }
 
         // Check if container exists
@@ -518,31 +2463,36 @@ async fn start_instance(
         .map_err(|e| ApiError::Internal(e.to_string()))?;
     docker_manager.start_container(&container_id).await
         .map_err(|e| ApiError::Internal(e.to_string()))?;
+    update_sidecars(state, &docker_manager, &id, SidecarAction::Start).await;
+    refresh_ephemeral_ports(state, &docker_manager, &id, &container_id).await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
 
     // Update instance status
-    {
+    let status = {
         let mut state_guard = state.write().await;
-        if let Some(instance) = state_guard.instances.get_mut(&id) {
-            instance.status = InstanceStatus::Running;
+        if state_guard.mark_started(&id) {
+            watch_readiness(state, &id);
         }
-    }
+        state_guard.emit_event(&id, InstanceEventKind::Started, None);
+        state_guard.instances.get(&id).map_or("running", |instance| instance.status.as_str()).to_string()
+    };
 
-    Ok(Json(InstanceStatusResponse {
-        id,
-        status: "running".to_string(),
-    }))
+    Ok(InstanceStatusResponse { id, status })
 }
 
 async fn restart_instance(
+    _: RequireOperator,
     State(state): State<Arc<RwLock<AppState>>>,
     Path(id): Path<String>,
 ) -> Result<Json<InstanceStatusResponse>, ApiError> {
     tracing::info!("Restarting instance {}", id);
+    let _lock = lock_instance(&state, &id, Operation::Restart).await?;
 
     // Get container_id
     let container_id = {
         let state_guard = state.read().await;
         let instance = state_guard.instances.get(&id).ok_or(ApiError::NotFound)?;
+        reject_trashed(instance)?;
 
         // Check if container exists
         if instance.container_id.is_empty() {
@@ -555,29 +2505,54 @@ async fn restart_instance(
     // Restart the container
     let docker_manager = super::docker::DockerManager::new()
         .map_err(|e| ApiError::Internal(e.to_string()))?;
-    docker_manager.restart_container(&container_id).await
-        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    // The container's exit is expected, not a crash
+    state.write().await.stopping.insert(id.clone());
+    let restarted = match docker_manager.restart_container(&container_id).await {
+        Ok(()) => {
+            // Sidecars have to join the game container's new network namespace
+            update_sidecars(&state, &docker_manager, &id, SidecarAction::Restart).await;
+            refresh_ephemeral_ports(&state, &docker_manager, &id, &container_id).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = restarted {
+        state.write().await.stopping.remove(&id);
+        return Err(ApiError::Internal(e.to_string()));
+    }
 
-    // Update instance status to running (restart ensures container is running)
-    {
+    // Update instance status (restart ensures container is running)
+    let status = {
         let mut state_guard = state.write().await;
-        if let Some(instance) = state_guard.instances.get_mut(&id) {
-            instance.status = InstanceStatus::Running;
+        if state_guard.mark_started(&id) {
+            watch_readiness(&state, &id);
         }
-    }
+        state_guard.stopping.remove(&id);
+        // Restarting the container ended any recording
+        state_guard.recordings.remove(&id);
+        state_guard.emit_event(&id, InstanceEventKind::Restarted, None);
+        state_guard.instances.get(&id).map_or("running", |instance| instance.status.as_str()).to_string()
+    };
 
-    Ok(Json(InstanceStatusResponse {
-        id,
-        status: "running".to_string(),
-    }))
+    Ok(Json(InstanceStatusResponse { id, status }))
 }
 
 #[derive(Debug)]
 pub enum ApiError {
     NotFound,
-    PortsExhausted,
+    TestRunNotFound,
+    DllNotFound,
+    TemplateNotFound,
+    RecordingNotFound,
+    TokenNotFound,
+    ModNotFound(String),
+    PortsExhausted(String),
     MaxInstancesReached,
     InvalidDll(String),
+    BadRequest(String),
+    Conflict(String),
+    BadGateway(String),
+    Unauthorized,
+    Forbidden(String),
     Internal(String),
 }
 
@@ -591,11 +2566,24 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, message): (StatusCode, String) = match self {
             ApiError::NotFound => (StatusCode::NOT_FOUND, "Instance not found".to_string()),
-            ApiError::PortsExhausted => (StatusCode::SERVICE_UNAVAILABLE, "No ports available".to_string()),
+            ApiError::TestRunNotFound => (StatusCode::NOT_FOUND, "Test run not found".to_string()),
+            ApiError::DllNotFound => (StatusCode::NOT_FOUND, "DLL not found".to_string()),
+            ApiError::TemplateNotFound => (StatusCode::NOT_FOUND, "Template not found".to_string()),
+            ApiError::RecordingNotFound => (StatusCode::NOT_FOUND, "Recording not found".to_string()),
+            ApiError::TokenNotFound => (StatusCode::NOT_FOUND, "Token not found".to_string()),
+            ApiError::ModNotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            ApiError::PortsExhausted(detail) => {
+                (StatusCode::SERVICE_UNAVAILABLE, format!("No ports available: {}", detail))
+            }
             ApiError::MaxInstancesReached => {
                 (StatusCode::SERVICE_UNAVAILABLE, "Maximum instances reached".to_string())
             }
             ApiError::InvalidDll(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            ApiError::BadGateway(msg) => (StatusCode::BAD_GATEWAY, msg),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Missing or invalid API token".to_string()),
+            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
@@ -0,0 +1,169 @@
+//! Scheduled instance start and stop
+//!
+//! An instance can be started and stopped at fixed times (`start_at`, `stop_at`) or
+//! on cron schedules (`start_cron`, `stop_cron`), e.g. to bring demo instances up
+//! before a weekend event and shut them down on Monday morning. The server checks
+//! the schedules of all instances periodically; fixed times are cleared once they
+//! have run. Schedules are saved to `[instances] schedules_file` so they survive a
+//! restart of the server.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// When an instance is started and stopped, all times in UTC
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_at: Option<DateTime<Utc>>,
+    /// Cron expression, `min hour day month weekday` or with a leading seconds field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_cron: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_cron: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledAction {
+    Start,
+    Stop,
+}
+
+impl Schedule {
+    pub fn is_empty(&self) -> bool {
+        *self == Schedule::default()
+    }
+
+    /// Check that the cron expressions parse
+    pub fn validate(&self) -> Result<(), String> {
+        for expression in [&self.start_cron, &self.stop_cron].into_iter().flatten() {
+            parse_cron(expression)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the instance should wait for `start_at` instead of starting when it is created
+    pub fn defers_start(&self, now: DateTime<Utc>) -> bool {
+        self.start_at.is_some_and(|start_at| start_at > now)
+    }
+
+    /// The action due after `since` and up to `now`, the one due last if both are
+    ///
+    /// Fixed times in the past are always due, so a start or stop missed while the
+    /// server was down still happens.
+    pub fn due(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> Option<ScheduledAction> {
+        let start = latest_due(self.start_at, self.start_cron.as_deref(), since, now);
+        let stop = latest_due(self.stop_at, self.stop_cron.as_deref(), since, now);
+        match (start, stop) {
+            (Some(start), Some(stop)) if stop >= start => Some(ScheduledAction::Stop),
+            (Some(_), _) => Some(ScheduledAction::Start),
+            (None, Some(_)) => Some(ScheduledAction::Stop),
+            (None, None) => None,
+        }
+    }
+
+    /// Clear the fixed times up to `now`, once they have run
+    pub fn clear_passed(&mut self, now: DateTime<Utc>) {
+        if self.start_at.is_some_and(|start_at| start_at <= now) {
+            self.start_at = None;
+        }
+        if self.stop_at.is_some_and(|stop_at| stop_at <= now) {
+            self.stop_at = None;
+        }
+    }
+}
+
+/// The latest of `at` and the cron's occurrences after `since`, if any is due by `now`
+fn latest_due(
+    at: Option<DateTime<Utc>>,
+    cron: Option<&str>,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let at = at.filter(|at| *at <= now);
+    let cron = cron
+        .and_then(|expression| parse_cron(expression).ok())
+        .and_then(|schedule| schedule.after(&since).take_while(|time| *time <= now).last());
+    at.max(cron)
+}
+
+/// Parse a cron expression, adding a seconds field to the common five-field form
+fn parse_cron(expression: &str) -> Result<cron::Schedule, String> {
+    let expression = expression.trim();
+    let with_seconds = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    cron::Schedule::from_str(&with_seconds).map_err(|e| format!("Invalid cron expression '{}': {}", expression, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_fixed_times() {
+        let mut schedule = Schedule {
+            start_at: Some(at("2026-10-16T17:00:00Z")),
+            stop_at: Some(at("2026-10-19T08:00:00Z")),
+            ..Default::default()
+        };
+        assert!(schedule.defers_start(at("2026-10-16T12:00:00Z")));
+
+        let since = at("2026-10-16T16:59:30Z");
+        assert_eq!(schedule.due(since, at("2026-10-16T16:59:45Z")), None);
+        assert_eq!(schedule.due(since, at("2026-10-16T17:00:00Z")), Some(ScheduledAction::Start));
+        schedule.clear_passed(at("2026-10-16T17:00:00Z"));
+        assert_eq!(schedule.start_at, None);
+
+        // Missed while the server was down
+        assert_eq!(
+            schedule.due(at("2026-10-20T00:00:00Z"), at("2026-10-20T00:00:30Z")),
+            Some(ScheduledAction::Stop)
+        );
+    }
+
+    #[test]
+    fn test_cron() {
+        let schedule = Schedule {
+            start_cron: Some("0 17 * * Fri".to_string()),
+            stop_cron: Some("0 0 8 * * Mon".to_string()),
+            ..Default::default()
+        };
+        assert!(schedule.validate().is_ok());
+        assert!(!schedule.defers_start(at("2026-10-16T12:00:00Z")));
+
+        // 2026-10-16 is a Friday
+        assert_eq!(
+            schedule.due(at("2026-10-16T16:59:30Z"), at("2026-10-16T17:00:00Z")),
+            Some(ScheduledAction::Start)
+        );
+        assert_eq!(schedule.due(at("2026-10-16T17:00:00Z"), at("2026-10-16T17:00:30Z")), None);
+        assert_eq!(
+            schedule.due(at("2026-10-19T07:59:45Z"), at("2026-10-19T08:00:15Z")),
+            Some(ScheduledAction::Stop)
+        );
+
+        // Both due in one check: the later one wins
+        assert_eq!(
+            schedule.due(at("2026-10-16T16:00:00Z"), at("2026-10-19T09:00:00Z")),
+            Some(ScheduledAction::Stop)
+        );
+    }
+
+    #[test]
+    fn test_invalid_cron() {
+        let schedule = Schedule {
+            stop_cron: Some("every monday".to_string()),
+            ..Default::default()
+        };
+        assert!(schedule.validate().is_err());
+    }
+}
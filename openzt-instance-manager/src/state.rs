@@ -1,43 +1,274 @@
 use super::{
     config::Config,
-    docker::DockerManager,
-    instance::Instance,
-    ports::PortPool,
+    dll_library::DllLibrary,
+    idempotency::IdempotencyKeys,
+    docker::{DockerManager, RecoveredInstanceInfo, SIDECAR_NAME_LABEL, SIDECAR_OF_LABEL},
+    instance::{Instance, InstanceEvent, InstanceEventKind, InstanceStatus, Sidecar},
+    metrics::{CreationFailure, CreationMetrics, CreationPhase},
+    mod_repository::ModRepository,
+    operations::OperationLocks,
+    schedule::Schedule,
+    ports::{HostPortPools, PortMode, PortPool, LOCAL_HOST},
+    templates::TemplateStore,
+    tokens::TokenStore,
+    test_run::TestRun,
 };
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
+/// Number of events buffered per subscriber before slow clients start skipping
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
 pub struct AppState {
     pub config: Config,
-    pub port_pool: PortPool,
+    pub port_pools: HostPortPools,
     pub instances: HashMap<String, Instance>,
+    pub test_runs: HashMap<String, TestRun>,
+    /// Name of the recording each recording instance is writing, by instance ID
+    pub recordings: HashMap<String, String>,
+    /// Instances whose container the server is stopping, so their exit isn't a crash
+    pub stopping: HashSet<String>,
+    /// When each starting instance's game has to have come up by
+    pub ready_deadlines: HashMap<String, Instant>,
+    /// The operation each instance is busy with, so conflicting ones are refused
+    pub operations: OperationLocks,
+    pub idempotency_keys: IdempotencyKeys,
+    pub metrics: CreationMetrics,
+    pub dlls: DllLibrary,
+    pub mods: ModRepository,
+    pub templates: TemplateStore,
+    pub tokens: TokenStore,
+    pub events: broadcast::Sender<InstanceEvent>,
 }
 
 impl AppState {
     pub fn new(config: Config) -> Self {
-        let port_pool = PortPool::new(
-            config.ports.vnc_start..config.ports.vnc_end,
-            config.ports.console_start..config.ports.console_end,
-        );
+        let port_pool = match config.ports.mode {
+            PortMode::Shared => PortPool::shared(config.ports.shared_start..config.ports.shared_end),
+            mode => PortPool::new(
+                config.ports.vnc_start..config.ports.vnc_end,
+                config.ports.console_start..config.ports.console_end,
+            )
+            .with_mode(mode),
+        }
+        .with_probing(config.ports.probe_availability)
+        .with_reservation_ttl(Duration::from_secs(config.ports.reservation_ttl_secs))
+        .with_excluded(config.ports.excluded.iter().copied());
+
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let dlls = DllLibrary::new(&config.dlls.dir);
+        let mods = ModRepository::new(&config.mods.dir);
+        let templates = TemplateStore::new(&config.templates.dir);
+        let tokens = TokenStore::new(&config.api.tokens_dir);
+        let idempotency_keys = IdempotencyKeys::new(config.instances.idempotency_key_ttl_secs);
 
         Self {
             config,
-            port_pool,
+            port_pools: HostPortPools::new(port_pool),
             instances: HashMap::new(),
+            test_runs: HashMap::new(),
+            recordings: HashMap::new(),
+            stopping: HashSet::new(),
+            ready_deadlines: HashMap::new(),
+            operations: OperationLocks::default(),
+            idempotency_keys,
+            metrics: CreationMetrics::default(),
+            dlls,
+            mods,
+            templates,
+            tokens,
+            events,
+        }
+    }
+
+    /// Mark a started instance as starting, or running if its game isn't waited for
+    ///
+    /// Returns whether to wait for the instance's game, see [`crate::readiness`].
+    pub fn mark_started(&mut self, instance_id: &str) -> bool {
+        let ready_timeout = self.config.instances.ready_timeout();
+        let Some(instance) = self.instances.get_mut(instance_id) else {
+            return false;
+        };
+        match ready_timeout {
+            Some(timeout) => {
+                instance.status = InstanceStatus::Starting;
+                self.ready_deadlines.insert(instance_id.to_string(), Instant::now() + timeout);
+                true
+            }
+            None => {
+                instance.status = InstanceStatus::Running;
+                false
+            }
+        }
+    }
+
+    /// Publish a lifecycle event to `/api/events` subscribers
+    pub fn emit_event(&self, instance_id: &str, kind: InstanceEventKind, message: Option<String>) {
+        // Sending only fails when nobody is subscribed, which is fine
+        let _ = self.events.send(InstanceEvent {
+            timestamp: Utc::now(),
+            instance_id: instance_id.to_string(),
+            kind,
+            message,
+        });
+    }
+
+    /// Record how long a creation phase of an instance took, and why it failed if it did
+    pub fn record_creation_phase(
+        &mut self,
+        instance_id: &str,
+        phase: CreationPhase,
+        duration: Duration,
+        failure: Option<CreationFailure>,
+    ) {
+        self.metrics.record_phase(phase, duration);
+        if let Some(instance) = self.instances.get_mut(instance_id) {
+            instance
+                .creation
+                .get_or_insert_with(Default::default)
+                .phases_ms
+                .insert(phase, duration.as_millis() as u64);
+        }
+        if let Some(failure) = failure {
+            self.record_creation_failure(instance_id, failure);
         }
     }
 
+    pub fn record_creation_failure(&mut self, instance_id: &str, failure: CreationFailure) {
+        self.metrics.record_failure(failure);
+        if let Some(instance) = self.instances.get_mut(instance_id) {
+            instance.creation.get_or_insert_with(Default::default).failure = Some(failure);
+        }
+    }
+
+    /// Release port reservations whose container never started
+    ///
+    /// Instances still waiting on an expired reservation are marked as failed.
+    pub fn release_expired_reservations(&mut self) {
+        for (host, (vnc_port, console_port)) in self.port_pools.release_expired(Instant::now()) {
+            tracing::warn!(
+                "Port reservation (VNC: {}, Console: {}) on {} expired before its container started",
+                vnc_port,
+                console_port,
+                host
+            );
+
+            // Instances are all placed on the local host for now
+            if host != LOCAL_HOST {
+                continue;
+            }
+
+            let stuck: Vec<String> = self
+                .instances
+                .values()
+                .filter(|inst| {
+                    inst.vnc_port == vnc_port
+                        && inst.console_port == console_port
+                        && matches!(inst.status, InstanceStatus::Creating)
+                })
+                .map(|inst| inst.id.clone())
+                .collect();
+
+            for id in stuck {
+                let message = "Port reservation expired before the container started".to_string();
+                if let Some(instance) = self.instances.get_mut(&id) {
+                    instance.status = InstanceStatus::Error(message.clone());
+                }
+                self.record_creation_failure(&id, CreationFailure::StartTimeout);
+                self.emit_event(&id, InstanceEventKind::Error, Some(message));
+            }
+        }
+    }
+
+    /// Save the schedules of all instances to `[instances] schedules_file`
+    pub fn save_schedules(&self) {
+        let schedules: HashMap<&String, &Schedule> = self
+            .instances
+            .iter()
+            .filter_map(|(id, instance)| instance.schedule.as_ref().map(|schedule| (id, schedule)))
+            .collect();
+        save_json(&self.config.instances.schedules_file, "schedules", &schedules);
+    }
+
+    /// The schedules saved by [`AppState::save_schedules`], by instance ID
+    fn load_schedules(&self) -> HashMap<String, Schedule> {
+        load_json(&self.config.instances.schedules_file, "schedules")
+    }
+
+    /// Save when the trashed instances were deleted to `[instances] trash_file`
+    pub fn save_trash(&self) {
+        let trash: HashMap<&String, &DateTime<Utc>> = self
+            .instances
+            .iter()
+            .filter_map(|(id, instance)| instance.trashed_at.as_ref().map(|trashed_at| (id, trashed_at)))
+            .collect();
+        save_json(&self.config.instances.trash_file, "trash", &trash);
+    }
+
+    /// When the instances saved by [`AppState::save_trash`] were deleted, by instance ID
+    fn load_trash(&self) -> HashMap<String, DateTime<Utc>> {
+        load_json(&self.config.instances.trash_file, "trash")
+    }
+
+    /// Save the names and labels of all instances to `[instances] metadata_file`
+    pub fn save_metadata(&self) {
+        let metadata: HashMap<&String, InstanceMetadata> = self
+            .instances
+            .iter()
+            .filter(|(_, instance)| instance.name.is_some() || !instance.labels.is_empty())
+            .map(|(id, instance)| {
+                let metadata = InstanceMetadata {
+                    name: instance.name.clone(),
+                    labels: instance.labels.clone(),
+                };
+                (id, metadata)
+            })
+            .collect();
+        save_json(&self.config.instances.metadata_file, "instance metadata", &metadata);
+    }
+
+    /// The names and labels saved by [`AppState::save_metadata`], by instance ID
+    fn load_metadata(&self) -> HashMap<String, InstanceMetadata> {
+        load_json(&self.config.instances.metadata_file, "instance metadata")
+    }
+
+    /// Trashed instances due to be purged at `now`
+    pub fn expired_trash(&self, now: DateTime<Utc>) -> Vec<String> {
+        let retention = self.config.instances.trash_retention();
+        self.instances
+            .values()
+            .filter(|instance| instance.trashed_at.is_some_and(|trashed_at| trashed_at + retention <= now))
+            .map(|instance| instance.id.clone())
+            .collect()
+    }
+
     /// Recover existing containers from Docker on startup
     pub async fn recover_instances(&mut self) -> anyhow::Result<usize> {
         let docker = DockerManager::new()?;
-        let prefix = &self.config.docker.container_prefix;
+        let prefix = self.config.docker.container_prefix.clone();
 
         tracing::info!("Scanning for containers with prefix '{}'", prefix);
 
-        let containers = docker.list_containers_with_prefix(prefix).await?;
+        let containers = docker.list_containers_with_prefix(&prefix).await?;
+        let mut schedules = self.load_schedules();
+        let mut trash = self.load_trash();
+        let mut metadata = self.load_metadata();
         let mut recovered_count = 0;
+        let mut sidecar_containers = Vec::new();
+        // Game containers recreated on new ports, by instance ID
+        let mut republished = HashMap::new();
 
         for container in containers {
+            // Sidecars are attached to their instance once all instances are recovered
+            if container.labels.as_ref().is_some_and(|labels| labels.contains_key(SIDECAR_OF_LABEL)) {
+                sidecar_containers.push(container);
+                continue;
+            }
+
             let name = container.names.as_ref()
                 .and_then(|n| n.first())
                 .ok_or_else(|| anyhow::anyhow!("Container missing name"))?;
@@ -57,25 +288,64 @@ impl AppState {
                 .ok_or_else(|| anyhow::anyhow!("Container missing ID"))?;
 
             match docker.inspect_container_for_recovery(&container_id).await {
-                Ok(info) => {
-                    // Register ports in pool
-                    if let Err(e) = self.port_pool.add_existing_pair(info.vnc_port, info.console_port) {
-                        tracing::error!("Failed to register ports for {}: {}, skipping", instance_id, e);
-                        continue;
+                Ok(mut info) => {
+                    // Register ports in pool, adopting the container even if
+                    // they fall outside the configured ranges
+                    let mut ports_outside_range = !self.port_pools.local_mut().adopt_pair(info.vnc_port, info.console_port);
+                    if ports_outside_range {
+                        tracing::warn!(
+                            "Instance {} uses ports outside the configured ranges (VNC: {}, Console: {})",
+                            instance_id, info.vnc_port, info.console_port
+                        );
                     }
 
-                    // Capture status for logging before moving
-                    let status = info.status.clone();
+                    // A stopped container can move to ports inside the ranges
+                    if ports_outside_range
+                        && info.status == InstanceStatus::Stopped
+                        && self.config.ports.republish_on_recovery
+                        && let Some((container_id, vnc_port, console_port)) =
+                            self.republish_ports(&docker, instance_id, &info).await
+                    {
+                        republished.insert(instance_id.to_string(), container_id.clone());
+                        info.container_id = container_id;
+                        info.vnc_port = vnc_port;
+                        info.console_port = console_port;
+                        ports_outside_range = false;
+                    }
+
+                    // A running container the server can't fully account for is adopted
+                    let mut problems = info.incomplete.clone();
+                    if ports_outside_range {
+                        problems.insert(0, "ports outside the configured ranges".to_string());
+                    }
+                    let trashed_at = trash.remove(instance_id);
+                    let status = match info.status {
+                        InstanceStatus::Running if !problems.is_empty() => {
+                            InstanceStatus::Adopted(format!("Recovered with {}", problems.join(", ")))
+                        }
+                        InstanceStatus::Stopped if trashed_at.is_some() => InstanceStatus::Trashed,
+                        status => status,
+                    };
 
                     // Reconstruct instance
+                    let InstanceMetadata { name, labels } = metadata.remove(instance_id).unwrap_or_default();
                     let instance = Instance {
                         id: instance_id.to_string(),
                         container_id: info.container_id,
                         vnc_port: info.vnc_port,
                         console_port: info.console_port,
-                        status: info.status,
+                        status: status.clone(),
                         created_at: info.created_at,
                         config: info.config,
+                        ports_outside_range,
+                        schedule: schedules.remove(instance_id),
+                        last_exit: None,
+                        auto_restarts: Vec::new(),
+                        sidecars: Vec::new(),
+                        creation: None,
+                        trashed_at,
+                        name,
+                        labels,
                     };
 
                     self.instances.insert(instance_id.to_string(), instance);
@@ -90,7 +360,108 @@ impl AppState {
             }
         }
 
+        for container in sidecar_containers {
+            let labels = container.labels.unwrap_or_default();
+            let (Some(instance_id), Some(name), Some(container_id)) =
+                (labels.get(SIDECAR_OF_LABEL), labels.get(SIDECAR_NAME_LABEL), container.id)
+            else {
+                continue;
+            };
+            let status = match container.state.as_deref() {
+                Some("running") => InstanceStatus::Running,
+                _ => InstanceStatus::Stopped,
+            };
+            // Sidecars share the network of the container they were created with
+            let container_id = match republished.get(instance_id) {
+                Some(game_container_id) if status == InstanceStatus::Stopped => {
+                    match docker.reattach_sidecar(&container_id, game_container_id).await {
+                        Ok(new_container_id) => new_container_id,
+                        Err(e) => {
+                            tracing::warn!("Failed to recreate sidecar {} of instance {}: {}", name, instance_id, e);
+                            container_id
+                        }
+                    }
+                }
+                _ => container_id,
+            };
+            match self.instances.get_mut(instance_id) {
+                Some(instance) => instance.sidecars.push(Sidecar {
+                    name: name.clone(),
+                    container_id,
+                    status,
+                }),
+                None => tracing::warn!("Sidecar container {} belongs to no recovered instance", container_id),
+            }
+        }
+
         tracing::info!("Recovered {} instances", recovered_count);
         Ok(recovered_count)
     }
+
+    /// Recreate a recovered stopped container on ports allocated from the pool
+    ///
+    /// Returns the new container's ID and ports, or None if it keeps its old ones.
+    async fn republish_ports(
+        &mut self,
+        docker: &DockerManager,
+        instance_id: &str,
+        info: &RecoveredInstanceInfo,
+    ) -> Option<(String, u16, u16)> {
+        let pool = self.port_pools.local_mut();
+        let Some((vnc_port, console_port)) = pool.allocate_pair() else {
+            tracing::warn!("No free ports to move instance {} to, keeping its ports", instance_id);
+            return None;
+        };
+
+        match docker.republish_ports(&info.container_id, vnc_port, console_port).await {
+            Ok(container_id) => {
+                self.port_pools.local_mut().release_pair(info.vnc_port, info.console_port);
+                tracing::info!(
+                    "Moved instance {} from ports {}/{} to {}/{}",
+                    instance_id, info.vnc_port, info.console_port, vnc_port, console_port
+                );
+                Some((container_id, vnc_port, console_port))
+            }
+            Err(e) => {
+                self.port_pools.local_mut().release_pair(vnc_port, console_port);
+                tracing::warn!("Failed to move instance {} to new ports: {:#}", instance_id, e);
+                None
+            }
+        }
+    }
+}
+
+/// An instance's name and labels as saved to `[instances] metadata_file`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct InstanceMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, String>,
+}
+
+/// Write `value` as JSON to `path`, logging failures
+fn save_json<T: Serialize>(path: &str, what: &str, value: &T) {
+    let result = serde_json::to_string_pretty(value)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| {
+            if let Some(parent) = std::path::Path::new(path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Ok(std::fs::write(path, json)?)
+        });
+    if let Err(e) = result {
+        tracing::warn!("Failed to save {} to {}: {}", what, path, e);
+    }
+}
+
+/// Read what [`save_json`] wrote to `path`, or the default if there is nothing valid
+fn load_json<T: DeserializeOwned + Default>(path: &str, what: &str) -> T {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring {} in {}: {}", what, path, e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
 }
@@ -0,0 +1,189 @@
+//! Named create templates stored by the server
+//!
+//! A template bundles what an instance is created with: the instance config (image,
+//! CPU and memory limits, environment), mods from the mod repository and a DLL from
+//! the DLL library or a URL. `POST /api/instances { "template": "tournament" }`
+//! creates an instance from it, so event setups are the same whoever runs the create
+//! command; fields set in the request override the template's. Each template is kept
+//! as `<name>.json` in the templates directory.
+
+use crate::instance::{DllSource, InstanceConfig};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// DLL reference, `dll_id` or `dll_url` with an optional `dll_sha256`
+    #[serde(flatten)]
+    pub dll: DllSource,
+    /// Mods from `/api/mods`, as `name` or `name@version`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mods: Vec<String>,
+    #[serde(default)]
+    pub config: InstanceConfig,
+}
+
+impl Template {
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && !self.name.starts_with('.')
+            && self.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+        if !valid_name {
+            return Err(format!(
+                "Invalid template name '{}': use letters, digits, '.', '-' and '_'",
+                self.name
+            ));
+        }
+        if self.dll.openzt_dll.is_some() {
+            return Err("Templates reference a DLL with dll_id or dll_url, not openzt_dll".to_string());
+        }
+        if self.dll.dll_id.is_some() && self.dll.dll_url.is_some() {
+            return Err("Set either dll_id or dll_url".to_string());
+        }
+        self.config.validate()
+    }
+}
+
+pub struct TemplateStore {
+    dir: PathBuf,
+    templates: BTreeMap<String, Template>,
+}
+
+impl TemplateStore {
+    /// An empty store kept in `dir`, see [`TemplateStore::load`]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            templates: BTreeMap::new(),
+        }
+    }
+
+    /// Read the templates already in the templates directory, creating it if needed
+    ///
+    /// Returns the number of templates found.
+    pub fn load(&mut self) -> Result<usize> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create templates directory {}", self.dir.display()))?;
+
+        for dir_entry in std::fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            match std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str::<Template>(&content)?))
+            {
+                Ok(template) => {
+                    self.templates.insert(template.name.clone(), template);
+                }
+                Err(e) => tracing::warn!("Skipping template {}: {}", path.display(), e),
+            }
+        }
+        Ok(self.templates.len())
+    }
+
+    /// Store a template, replacing the one with the same name
+    ///
+    /// Returns whether it replaced one.
+    pub fn save(&mut self, template: Template) -> Result<bool> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create templates directory {}", self.dir.display()))?;
+        std::fs::write(self.path(&template.name), serde_json::to_string_pretty(&template)?)
+            .with_context(|| format!("Failed to write template {}", template.name))?;
+        Ok(self.templates.insert(template.name.clone(), template).is_some())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.get(name)
+    }
+
+    /// Every template, by name
+    pub fn list(&self) -> Vec<Template> {
+        self.templates.values().cloned().collect()
+    }
+
+    /// Remove a template, returning whether there was one
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        if self.templates.remove(name).is_none() {
+            return Ok(false);
+        }
+        std::fs::remove_file(self.path(name)).with_context(|| format!("Failed to remove template {}", name))?;
+        Ok(true)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(name: &str) -> Template {
+        Template {
+            name: name.to_string(),
+            description: Some("Weekend tournament".to_string()),
+            dll: DllSource {
+                dll_id: Some("abc123".to_string()),
+                ..Default::default()
+            },
+            mods: vec!["savanna-pack@1.2.0".to_string()],
+            config: InstanceConfig {
+                cpulimit: Some(2.0),
+                memory_mb: Some(4096),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_template_json() {
+        let json = serde_json::to_value(template("tournament")).unwrap();
+        assert_eq!(json["dll_id"], "abc123");
+        assert!(json.get("dll_url").is_none());
+
+        let parsed: Template = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, template("tournament"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(template("tournament-2026").validate().is_ok());
+        assert!(template("../tournament").validate().is_err());
+
+        let mut inline = template("inline");
+        inline.dll.openzt_dll = Some("TVo=".to_string());
+        assert!(inline.validate().is_err());
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let dir = std::env::temp_dir().join(format!("openzt-templates-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut store = TemplateStore::new(&dir);
+        assert_eq!(store.load().unwrap(), 0);
+
+        assert!(!store.save(template("tournament")).unwrap());
+        let mut updated = template("tournament");
+        updated.mods.clear();
+        assert!(store.save(updated.clone()).unwrap());
+        store.save(template("demo")).unwrap();
+
+        let mut reloaded = TemplateStore::new(&dir);
+        assert_eq!(reloaded.load().unwrap(), 2);
+        assert_eq!(reloaded.get("tournament"), Some(&updated));
+        let names: Vec<String> = reloaded.list().into_iter().map(|template| template.name).collect();
+        assert_eq!(names, vec!["demo", "tournament"]);
+
+        assert!(reloaded.remove("demo").unwrap());
+        assert!(!reloaded.remove("demo").unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,202 @@
+//! Integration test runs on throwaway instances
+//!
+//! `POST /api/testruns` starts an instance with a DLL built with the
+//! `integration-tests` feature and the given mods. OpenZT runs its integration tests
+//! as the game starts and writes the results as JSON to the path in
+//! `OPENZT_TEST_RESULTS`, which the server points at a directory mounted from the host.
+//! Once the results are there the instance is deleted and the run keeps the report,
+//! available from `GET /api/testruns/{id}`.
+
+use crate::instance::{DllSource, InstanceConfig};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Directory in the game directory the results directory is mounted at
+pub const RESULTS_MOUNT: &str = "test-results";
+
+/// Results file, as the game sees it
+pub const RESULTS_WINDOWS_PATH: &str =
+    "C:\\Program Files (x86)\\Microsoft Games\\Zoo Tycoon\\test-results\\results.json";
+
+/// Results file name in the results directory
+pub const RESULTS_FILE: &str = "results.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTestRunRequest {
+    /// A DLL built with the `integration-tests` feature
+    #[serde(flatten)]
+    pub dll: DllSource,
+    /// Mods placed in the game's mods directory
+    #[serde(default)]
+    pub mods: Vec<ModFile>,
+    /// Only run tests matching these comma separated patterns, as `OPENZT_TEST_FILTER`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// Seconds to wait for results, the server's `[test_runs] timeout_secs` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<InstanceConfig>,
+}
+
+/// A mod uploaded with a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModFile {
+    /// File name in the mods directory, e.g. `my-mod.ztd`
+    pub name: String,
+    /// Base64-encoded file contents
+    pub data: String,
+}
+
+impl ModFile {
+    /// Check that `name` is a plain .ztd file name, so it can't escape the mods directory
+    pub fn validate_name(&self) -> Result<(), String> {
+        let valid = !self.name.is_empty()
+            && !self.name.starts_with('.')
+            && !self.name.contains(['/', '\\', ':'])
+            && self.name.to_lowercase().ends_with(".ztd");
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid mod file name '{}': expected a .ztd file name", self.name))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestRunStatus {
+    /// The instance is being created
+    Starting,
+    /// The game is running the tests
+    Running,
+    /// Every test passed
+    Passed,
+    /// At least one test failed
+    Failed,
+    /// The run didn't produce results, see `error`
+    Error,
+}
+
+/// A test run, as returned by `GET /api/testruns/{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRun {
+    pub id: String,
+    /// Instance the tests run on, deleted once the run finishes
+    pub instance_id: String,
+    pub status: TestRunStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<TestRunReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TestRun {
+    /// Finish the run with the report the game wrote
+    pub fn complete(&mut self, report: TestRunReport) {
+        self.status = if report.failed > 0 { TestRunStatus::Failed } else { TestRunStatus::Passed };
+        self.report = Some(report);
+        self.finished_at = Some(Utc::now());
+    }
+
+    /// Finish the run without results
+    pub fn fail(&mut self, error: String) {
+        self.status = TestRunStatus::Error;
+        self.error = Some(error);
+        self.finished_at = Some(Utc::now());
+    }
+}
+
+/// Results of the integration tests, as written by OpenZT to `OPENZT_TEST_RESULTS`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRunReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Tests not run because of the filter
+    pub filtered: usize,
+    pub duration_ms: u64,
+    pub suites: Vec<TestSuiteReport>,
+}
+
+/// The tests run from one test module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestSuiteReport {
+    pub module: String,
+    pub description: String,
+    pub tests: Vec<TestCaseReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCaseReport {
+    pub name: String,
+    pub status: TestCaseStatus,
+    /// The failure or skip reason
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestCaseStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_json() {
+        let json = r#"{
+            "passed": 1, "failed": 1, "skipped": 0, "filtered": 4, "duration_ms": 1600,
+            "suites": [{
+                "module": "dependency_resolution",
+                "description": "dependency resolution",
+                "tests": [
+                    {"name": "test_chain", "status": "passed", "duration_ms": 12},
+                    {"name": "test_cycle", "status": "failed", "message": "expected a", "duration_ms": 1500}
+                ]
+            }]
+        }"#;
+        let report: TestRunReport = serde_json::from_str(json).unwrap();
+        assert_eq!(report.suites[0].tests[1].status, TestCaseStatus::Failed);
+        assert_eq!(report.suites[0].tests[1].message.as_deref(), Some("expected a"));
+
+        let mut run = TestRun {
+            id: "run".to_string(),
+            instance_id: "instance".to_string(),
+            status: TestRunStatus::Running,
+            created_at: Utc::now(),
+            finished_at: None,
+            filter: None,
+            report: None,
+            error: None,
+        };
+        run.complete(report);
+        assert_eq!(run.status, TestRunStatus::Failed);
+        assert!(run.finished_at.is_some());
+    }
+
+    #[test]
+    fn test_mod_file_name() {
+        let mod_file = |name: &str| ModFile {
+            name: name.to_string(),
+            data: String::new(),
+        };
+        assert!(mod_file("my-mod.ztd").validate_name().is_ok());
+        assert!(mod_file("MyMod.ZTD").validate_name().is_ok());
+        assert!(mod_file("../zoo.ztd").validate_name().is_err());
+        assert!(mod_file("sub\\mod.ztd").validate_name().is_err());
+        assert!(mod_file(".ztd").validate_name().is_err());
+        assert!(mod_file("mod.zip").validate_name().is_err());
+    }
+}
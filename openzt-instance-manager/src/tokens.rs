@@ -0,0 +1,236 @@
+//! API tokens managed at runtime
+//!
+//! With `[api] enable_auth`, every request except `/health` needs a bearer token.
+//! `POST /api/admin/tokens` issues one with a scope: `read-only` tokens can only
+//! read, `operator` tokens can also create and manage instances, DLLs, mods and
+//! templates, and `admin` tokens can also manage tokens and read the configuration.
+//! Handlers that need more than `read-only` say so with the extractors in `routes`.
+//! A token's secret is returned once when it is created; the server only keeps its
+//! SHA-256 hash, as `<id>.json` in the tokens directory. `[api] admin_token` is an
+//! admin token set in the config, to issue the first tokens with.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Prefix of token secrets, so they are recognizable in config files and logs
+const SECRET_PREFIX: &str = "ozt_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenScope {
+    ReadOnly,
+    Operator,
+    Admin,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TokenScope::ReadOnly => "read-only",
+            TokenScope::Operator => "operator",
+            TokenScope::Admin => "admin",
+        }
+    }
+
+    /// Whether this scope grants everything `required` does
+    pub fn includes(&self, required: TokenScope) -> bool {
+        *self >= required
+    }
+}
+
+/// Who made an authenticated request, kept in the request's extensions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caller {
+    /// Name of the token, or `admin_token` for the token set in the config
+    pub name: String,
+    pub scope: TokenScope,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTokenRequest {
+    /// Who or what the token is for, e.g. `ci` or a user's name
+    pub name: String,
+    pub scope: TokenScope,
+}
+
+/// A token, as returned by `GET /api/admin/tokens`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scope: TokenScope,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Response to `POST /api/admin/tokens`, the only time the secret is shown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTokenResponse {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    /// Bearer token to authenticate with
+    pub secret: String,
+}
+
+/// A token as kept in the tokens directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    token: ApiToken,
+    /// SHA-256 of the secret, as hex
+    secret_sha256: String,
+}
+
+fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub struct TokenStore {
+    dir: PathBuf,
+    tokens: BTreeMap<String, StoredToken>,
+}
+
+impl TokenStore {
+    /// An empty store kept in `dir`, see [`TokenStore::load`]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            tokens: BTreeMap::new(),
+        }
+    }
+
+    /// Read the tokens already in the tokens directory, creating it if needed
+    ///
+    /// Returns the number of tokens found.
+    pub fn load(&mut self) -> Result<usize> {
+        self.create_dir()?;
+
+        for dir_entry in std::fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            match std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str::<StoredToken>(&content)?))
+            {
+                Ok(stored) => {
+                    self.tokens.insert(stored.token.id.clone(), stored);
+                }
+                Err(e) => tracing::warn!("Skipping token {}: {}", path.display(), e),
+            }
+        }
+        Ok(self.tokens.len())
+    }
+
+    /// Issue a new token, returning it with its secret
+    pub fn create(&mut self, name: &str, scope: TokenScope) -> Result<CreateTokenResponse> {
+        let bytes: [u8; 32] = rand::rng().random();
+        let secret = format!(
+            "{}{}",
+            SECRET_PREFIX,
+            bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()
+        );
+        let token = ApiToken {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            scope,
+            created_at: Utc::now(),
+        };
+        let stored = StoredToken {
+            token: token.clone(),
+            secret_sha256: hash_secret(&secret),
+        };
+
+        self.create_dir()?;
+        let path = self.path(&token.id);
+        std::fs::write(&path, serde_json::to_string_pretty(&stored)?)
+            .with_context(|| format!("Failed to write token {}", token.id))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to restrict permissions of {}", path.display()))?;
+        }
+        self.tokens.insert(token.id.clone(), stored);
+        Ok(CreateTokenResponse { token, secret })
+    }
+
+    /// The token with this secret, if there is one
+    pub fn authenticate(&self, secret: &str) -> Option<&ApiToken> {
+        let hash = hash_secret(secret);
+        self.tokens
+            .values()
+            .find(|stored| stored.secret_sha256 == hash)
+            .map(|stored| &stored.token)
+    }
+
+    /// Every token, oldest first
+    pub fn list(&self) -> Vec<ApiToken> {
+        let mut tokens: Vec<ApiToken> = self.tokens.values().map(|stored| stored.token.clone()).collect();
+        tokens.sort_by_key(|token| token.created_at);
+        tokens
+    }
+
+    /// Revoke a token, returning whether there was one
+    pub fn revoke(&mut self, id: &str) -> Result<bool> {
+        if self.tokens.remove(id).is_none() {
+            return Ok(false);
+        }
+        std::fs::remove_file(self.path(id)).with_context(|| format!("Failed to remove token {}", id))?;
+        Ok(true)
+    }
+
+    fn create_dir(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create tokens directory {}", self.dir.display()))
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes() {
+        assert!(TokenScope::ReadOnly.includes(TokenScope::ReadOnly));
+        assert!(!TokenScope::ReadOnly.includes(TokenScope::Operator));
+        assert!(TokenScope::Operator.includes(TokenScope::ReadOnly));
+        assert!(!TokenScope::Operator.includes(TokenScope::Admin));
+        assert!(TokenScope::Admin.includes(TokenScope::Operator));
+        assert_eq!(serde_json::to_value(TokenScope::ReadOnly).unwrap(), "read-only");
+    }
+
+    #[test]
+    fn test_store() {
+        let dir = std::env::temp_dir().join(format!("openzt-tokens-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut store = TokenStore::new(&dir);
+        assert_eq!(store.load().unwrap(), 0);
+        let created = store.create("ci", TokenScope::Operator).unwrap();
+        assert!(created.secret.starts_with(SECRET_PREFIX));
+        assert_eq!(store.authenticate(&created.secret), Some(&created.token));
+        assert_eq!(store.authenticate("ozt_wrong"), None);
+
+        // Only the hash is written to disk
+        let file = std::fs::read_to_string(dir.join(format!("{}.json", created.token.id))).unwrap();
+        assert!(!file.contains(&created.secret));
+
+        let mut reloaded = TokenStore::new(&dir);
+        assert_eq!(reloaded.load().unwrap(), 1);
+        assert_eq!(reloaded.list(), vec![created.token.clone()]);
+        assert!(reloaded.revoke(&created.token.id).unwrap());
+        assert!(!reloaded.revoke(&created.token.id).unwrap());
+        assert_eq!(reloaded.authenticate(&created.secret), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,69 @@
+//! Webhook notifications of instance events
+//!
+//! Events of the kinds in `[webhooks] events` (crashes, automatic restarts and
+//! instances given up on by default) are POSTed as JSON, as they appear on
+//! `/api/events`, to every URL in `[webhooks] urls`, e.g. a chat integration that
+//! pages whoever runs the event. Deliveries aren't retried.
+
+use crate::instance::InstanceEvent;
+use crate::state::AppState;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::RwLock;
+
+/// How long a webhook has to respond
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Forward events to the configured webhooks until the event channel closes
+pub async fn forward_events(state: Arc<RwLock<AppState>>) {
+    let (mut receiver, config) = {
+        let state_guard = state.read().await;
+        (state_guard.events.subscribe(), state_guard.config.webhooks.clone())
+    };
+    if config.urls.is_empty() {
+        return;
+    }
+    let client = match reqwest::Client::builder().timeout(DELIVERY_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to create webhook client: {}", e);
+            return;
+        }
+    };
+
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("Webhooks lagged, skipped {} events", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        if !config.events.contains(&event.kind) {
+            continue;
+        }
+        for url in &config.urls {
+            tokio::spawn(deliver(client.clone(), url.clone(), event.clone()));
+        }
+    }
+}
+
+async fn deliver(client: reqwest::Client, url: String, event: InstanceEvent) {
+    let result = client
+        .post(&url)
+        .json(&event)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = result {
+        tracing::warn!(
+            "Failed to notify webhook {} of {} for instance {}: {}",
+            url,
+            event.kind.as_str(),
+            event.instance_id,
+            e
+        );
+    }
+}
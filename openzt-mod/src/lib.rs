@@ -0,0 +1,11 @@
+//! Tools for OpenZT mod authors
+//!
+//! [`schema`] checks meta.toml and definition files with line-accurate
+//! diagnostics, and is also used by OpenZT when it loads a mod. [`packager`]
+//! validates a whole mod directory and packages it as a .ztd; the `openzt-mod`
+//! binary exposes it on the command line. [`signing`] signs mods and checks
+//! their signatures.
+
+pub mod packager;
+pub mod schema;
+pub mod signing;
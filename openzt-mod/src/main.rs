@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use openzt_mod::{
+    packager::{self, Report},
+    signing::{self, SigningKey},
+};
+use rand_core::{OsRng, RngCore};
+
+/// OpenZT mod tool - validate and package OpenZT mods
+#[derive(Parser, Debug)]
+#[command(name = "openzt-mod")]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Validate a mod directory and package it as a .ztd
+    Build {
+        /// Mod directory containing meta.toml
+        dir: PathBuf,
+
+        /// Output archive (default: <mod_id>.ztd in the current directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Sign the archive with a secret key file written by `keygen`
+        #[arg(long, value_name = "KEY_FILE")]
+        sign: Option<PathBuf>,
+    },
+    /// Validate a mod directory without packaging it
+    Check {
+        /// Mod directory containing meta.toml
+        dir: PathBuf,
+    },
+    /// Generate a key pair for signing mods
+    Keygen {
+        /// File to write the secret key to
+        key_file: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match &cli.command {
+        Command::Build { dir, output, sign } => build(dir, output.as_deref(), sign.as_deref()),
+        Command::Check { dir } => packager::validate(dir).map(|report| print_report(&report)),
+        Command::Keygen { key_file } => keygen(key_file),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn build(dir: &Path, output: Option<&Path>, key_file: Option<&Path>) -> anyhow::Result<bool> {
+    let signing_key = match key_file {
+        Some(key_file) => {
+            let text = std::fs::read_to_string(key_file).with_context(|| format!("Failed to read {}", key_file.display()))?;
+            Some(signing::parse_secret_key(&text).with_context(|| format!("Failed to read {}", key_file.display()))?)
+        }
+        None => None,
+    };
+
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None => {
+            // The mod ID is only known once meta.toml has been read
+            let report = packager::validate(dir)?;
+            let Some(mod_id) = report.mod_id.clone().filter(|_| !report.has_errors()) else {
+                return Ok(print_report(&report));
+            };
+            PathBuf::from(format!("{}.ztd", mod_id))
+        }
+    };
+
+    let report = packager::build(dir, &output, signing_key.as_ref())?;
+    if print_report(&report) {
+        println!("Wrote {} ({} files)", output.display(), report.file_count);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+fn keygen(key_file: &Path) -> anyhow::Result<bool> {
+    if key_file.exists() {
+        anyhow::bail!("{} already exists", key_file.display());
+    }
+
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    let key = SigningKey::from_bytes(&secret);
+    std::fs::write(key_file, signing::format_secret_key(&key)).with_context(|| format!("Failed to write {}", key_file.display()))?;
+
+    println!("Wrote secret key to {}, keep it private", key_file.display());
+    println!("Public key: {}", hex::encode(key.verifying_key().to_bytes()));
+    println!("Players trust mods signed with it by adding it to [mod_loading.trusted_keys] in openzt.toml");
+    Ok(true)
+}
+
+/// Print the diagnostics in a report, returning whether the mod is free of errors
+fn print_report(report: &Report) -> bool {
+    for diagnostic in &report.diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+
+    let errors = report.errors().count();
+    let warnings = report.warnings().count();
+    if errors > 0 {
+        eprintln!("{} errors, {} warnings", errors, warnings);
+        return false;
+    }
+    if warnings > 0 {
+        eprintln!("{} warnings", warnings);
+    }
+    true
+}
@@ -0,0 +1,506 @@
+//! Validate an unpacked mod directory and package it as a .ztd
+//!
+//! A mod directory has the same layout as the archive OpenZT loads: `meta.toml`
+//! at the root, definition files under `defs/`, Lua scripts under `scripts/` and
+//! everything patches or icons reference under `resources/`. Validation mirrors what the loader would reject
+//! so mistakes are caught before the mod reaches the game.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Context;
+use toml::{de::DeTable, Spanned, Table, Value};
+use walkdir::WalkDir;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::schema::{self, find, line_at};
+pub use crate::schema::{Diagnostic, Severity};
+use crate::signing::{self, Signature, SigningKey, SIGNATURE_FILE};
+
+const META_FILE: &str = "meta.toml";
+
+/// Directories of an openzt mod that OpenZT reads
+const OPENZT_DIRS: [&str; 3] = ["defs/", "resources/", "scripts/"];
+
+/// Result of validating a mod directory
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The mod ID from meta.toml, if it could be read
+    pub mod_id: Option<String>,
+    /// Number of files that go into the archive
+    pub file_count: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    fn error(&mut self, file: &str, line: Option<usize>, message: String, suggestion: Option<String>) {
+        self.push(Severity::Error, file, line, message, suggestion);
+    }
+
+    fn warning(&mut self, file: &str, message: String) {
+        self.push(Severity::Warning, file, None, message, None);
+    }
+
+    fn push(&mut self, severity: Severity, file: &str, line: Option<usize>, message: String, suggestion: Option<String>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: file.to_string(),
+            line,
+            message,
+            suggestion,
+        });
+    }
+}
+
+/// Validate the mod in `dir` without packaging it
+pub fn validate(dir: &Path) -> anyhow::Result<Report> {
+    let files = read_mod_dir(dir)?;
+    Ok(validate_files(&files))
+}
+
+/// Validate the mod in `dir` and write it to `output` as a .ztd
+///
+/// With a `signing_key` the archive gets a fresh signature.toml, replacing any in
+/// the directory. Nothing is written if validation finds errors; the report is
+/// returned either way.
+pub fn build(dir: &Path, output: &Path, signing_key: Option<&SigningKey>) -> anyhow::Result<Report> {
+    let mut files = read_mod_dir(dir)?;
+    // Don't package an earlier build written inside the mod directory
+    if let Ok(previous_build) = output.strip_prefix(dir) {
+        files.remove(&previous_build.to_string_lossy().replace('\\', "/"));
+    }
+    if signing_key.is_some() {
+        files.remove(SIGNATURE_FILE);
+    }
+    let mut report = validate_files(&files);
+    if report.has_errors() {
+        return Ok(report);
+    }
+
+    if let Some(key) = signing_key {
+        let signature = signing::sign(files.iter().map(|(name, data)| (name.as_str(), data.as_slice())), key);
+        files.insert(SIGNATURE_FILE.to_string(), signature.into_bytes());
+        report.file_count = files.len();
+    }
+
+    write_ztd(&files, output).with_context(|| format!("Failed to write {}", output.display()))?;
+    Ok(report)
+}
+
+/// Read every file in a mod directory, keyed by its '/'-separated path within the directory
+///
+/// Hidden files and directories (such as `.git`) are skipped.
+pub fn read_mod_dir(dir: &Path) -> anyhow::Result<BTreeMap<String, Vec<u8>>> {
+    if !dir.is_dir() {
+        anyhow::bail!("{} is not a directory", dir.display());
+    }
+
+    let mut files = BTreeMap::new();
+    let walker = WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'));
+    for entry in walker {
+        let entry = entry.with_context(|| format!("Failed to read mod directory {}", dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let name = entry
+            .path()
+            .strip_prefix(dir)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let data = std::fs::read(entry.path()).with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        files.insert(name, data);
+    }
+    Ok(files)
+}
+
+/// Validate the files of a mod, keyed by their '/'-separated paths
+pub fn validate_files(files: &BTreeMap<String, Vec<u8>>) -> Report {
+    let mut report = Report {
+        file_count: files.len(),
+        ..Default::default()
+    };
+
+    let ztd_type = match files.get(META_FILE) {
+        Some(data) => validate_meta(data, files, &mut report),
+        None => {
+            let suggestion = Some("add meta.toml with name, description, authors, mod_id and version".to_string());
+            report.error(
+                META_FILE,
+                None,
+                "missing, OpenZT only loads archives with a meta.toml at the root".to_string(),
+                suggestion,
+            );
+            None
+        }
+    };
+
+    let defs: Vec<_> = files.iter().filter(|(name, _)| name.starts_with("defs/")).collect();
+    if ztd_type.as_deref() == Some("legacy") && !defs.is_empty() {
+        report.warning(META_FILE, "ztd_type is \"legacy\", so the files in defs/ are not loaded".to_string());
+    }
+    for (name, data) in defs {
+        validate_def(name, data, files, &mut report);
+    }
+
+    // An openzt mod's files are only read through meta.toml, defs and scripts, nothing else is loaded as a legacy resource
+    if ztd_type.as_deref() == Some("openzt") {
+        for name in files
+            .keys()
+            .filter(|name| *name != META_FILE && *name != SIGNATURE_FILE && !OPENZT_DIRS.iter().any(|dir| name.starts_with(dir)))
+        {
+            report.warning(name, "outside defs/, resources/ and scripts/, not loaded when ztd_type is \"openzt\"".to_string());
+        }
+    }
+
+    if let Signature::Invalid(reason) = signing::verify(files.iter().map(|(name, data)| (name.as_str(), data.as_slice()))) {
+        report.warning(SIGNATURE_FILE, format!("{}, build with --sign to sign the mod again", reason));
+    }
+
+    report
+}
+
+fn decode<'d>(file: &str, data: &'d [u8], report: &mut Report) -> Option<&'d str> {
+    match std::str::from_utf8(data) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            let line = line_at(&String::from_utf8_lossy(data), e.valid_up_to());
+            report.error(file, Some(line), "not valid UTF-8".to_string(), Some("save the file as UTF-8".to_string()));
+            None
+        }
+    }
+}
+
+/// Check meta.toml and that its icon exists in the mod, returning its ztd_type if valid
+fn validate_meta(data: &[u8], files: &BTreeMap<String, Vec<u8>>, report: &mut Report) -> Option<String> {
+    let text = decode(META_FILE, data, report)?;
+    report.diagnostics.extend(schema::check_meta(META_FILE, text));
+
+    // Parse errors were reported by the schema check
+    let meta = text.parse::<Table>().ok()?;
+    match meta.get("mod_id").and_then(Value::as_str) {
+        Some("") => report.error(META_FILE, None, "'mod_id' must not be empty".to_string(), None),
+        Some(mod_id) => report.mod_id = Some(mod_id.to_string()),
+        None => {}
+    }
+
+    if let Some(icon) = meta.get("icon").and_then(Value::as_str)
+        && !files.contains_key(icon)
+    {
+        let suggestion = files
+            .keys()
+            .find(|name| name.eq_ignore_ascii_case(icon))
+            .map(|path| format!("did you mean '{}'?", path));
+        report.error(META_FILE, None, format!("'icon' refers to {}, which does not exist in the mod", icon), suggestion);
+    }
+
+    match meta.get("ztd_type") {
+        None => Some("combined".to_string()),
+        Some(ztd_type) => ztd_type.as_str().map(str::to_string),
+    }
+}
+
+/// Check a definition file, and that the files its icons and patches read exist in the mod
+fn validate_def(file: &str, data: &[u8], files: &BTreeMap<String, Vec<u8>>, report: &mut Report) {
+    let Some(text) = decode(file, data, report) else {
+        return;
+    };
+    report.diagnostics.extend(schema::check_def(file, text));
+
+    let Ok(def) = DeTable::parse(text) else {
+        return;
+    };
+    let def = def.get_ref();
+    let missing = |path: &Spanned<toml::de::DeValue>| path.get_ref().as_str().filter(|path| !files.contains_key(*path)).map(str::to_string);
+
+    for kind in ["habitats", "locations"] {
+        let Some(icons) = find(def, kind).and_then(|icons| icons.get_ref().as_table()) else {
+            continue;
+        };
+        for (icon_name, icon) in icons.iter() {
+            for key in ["icon_path", "icon_palette_path"] {
+                if let Some(path) = icon.get_ref().get(key)
+                    && let Some(missing_path) = missing(path)
+                {
+                    let message = format!(
+                        "'{}.{}.{}' refers to {}, which does not exist in the mod",
+                        kind,
+                        icon_name.get_ref(),
+                        key,
+                        missing_path
+                    );
+                    report.error(file, Some(line_at(text, path.span().start)), message, None);
+                }
+            }
+        }
+    }
+
+    if let Some(sprites) = find(def, "sprites").and_then(|sprites| sprites.get_ref().as_table()) {
+        for (sprite_name, sprite) in sprites.iter() {
+            let Some(frames) = sprite.get_ref().get("frames").and_then(|frames| frames.get_ref().as_array()) else {
+                continue;
+            };
+            for frame in frames.iter() {
+                if let Some(missing_path) = missing(frame) {
+                    let message = format!(
+                        "'sprites.{}.frames' refers to {}, which does not exist in the mod",
+                        sprite_name.get_ref(),
+                        missing_path
+                    );
+                    report.error(file, Some(line_at(text, frame.span().start)), message, None);
+                }
+            }
+        }
+    }
+
+    let Some(patches) = find(def, "patches").and_then(|patches| patches.get_ref().as_table()) else {
+        return;
+    };
+    for (patch_name, patch) in patches.iter() {
+        let patch = patch.get_ref();
+        let operation = patch.get("operation").and_then(|operation| operation.get_ref().as_str());
+
+        // Sources are resolved relative to resources/ in the archive, sources from other mods
+        // (mod:<mod_id>:<path>) can only be checked once that mod is loaded
+        if matches!(operation, Some("replace" | "merge"))
+            && let Some(source) = patch.get("source")
+            && let Some(name) = source.get_ref().as_str()
+            && !name.starts_with("mod:")
+            && !files.contains_key(&format!("resources/{}", name))
+        {
+            let message = format!(
+                "'patches.{}.source' refers to resources/{}, which does not exist in the mod",
+                patch_name.get_ref(),
+                name
+            );
+            let suggestion = closest_file(name, files).map(|path| format!("did you mean '{}'?", path));
+            report.error(file, Some(line_at(text, source.span().start)), message, suggestion);
+        }
+
+        if operation == Some("set_palette")
+            && let Some(palette) = patch.get("palette")
+            && let Some(name) = palette.get_ref().as_str()
+            && !name.to_lowercase().ends_with(".pal")
+        {
+            let message = format!("'patches.{}.palette' must be a .pal file, found '{}'", patch_name.get_ref(), name);
+            report.error(file, Some(line_at(text, palette.span().start)), message, None);
+        }
+    }
+}
+
+/// The file under resources/ whose name matches a missing patch source, ignoring case
+fn closest_file<'f>(source: &str, files: &'f BTreeMap<String, Vec<u8>>) -> Option<&'f str> {
+    let expected = format!("resources/{}", source).to_lowercase();
+    files
+        .keys()
+        .find(|name| name.to_lowercase() == expected)
+        .and_then(|name| name.strip_prefix("resources/"))
+}
+
+/// Write the files to a .ztd, Deflate-compressed like the game's own archives
+///
+/// Files of 4 GB or more are written as zip64 entries; the central directory switches
+/// to zip64 by itself once there are more than 65535 files.
+fn write_ztd(files: &BTreeMap<String, Vec<u8>>, output: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = output.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(File::create(output)?);
+    for (name, data) in files {
+        zip.start_file(name.as_str(), options.large_file(data.len() as u64 >= u32::MAX as u64))?;
+        zip.write_all(data)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const META: &str = "name = \"Moon Habitat\"\ndescription = \"A habitat\"\nauthors = [\"finn\"]\nmod_id = \"finn.moon\"\nversion = \"1.0.0\"\n";
+
+    fn mod_files(files: &[(&str, &str)]) -> BTreeMap<String, Vec<u8>> {
+        files.iter().map(|(name, data)| (name.to_string(), data.as_bytes().to_vec())).collect()
+    }
+
+    fn messages(report: &Report) -> Vec<String> {
+        report.errors().map(|diagnostic| diagnostic.to_string()).collect()
+    }
+
+    #[test]
+    fn test_valid_mod() {
+        let files = mod_files(&[
+            ("meta.toml", META),
+            (
+                "defs/moon.toml",
+                "[habitats.moon]\nname = \"Moon\"\nicon_path = \"resources/moon/N\"\nicon_palette_path = \"resources/moon/moon.pal\"\n\n[patches.elephant]\noperation = \"replace\"\ntarget = \"animals/elephant.ai\"\nsource = \"elephant.ai\"\n",
+            ),
+            ("resources/moon/N", ""),
+            ("resources/moon/moon.pal", ""),
+            ("resources/elephant.ai", ""),
+        ]);
+
+        let report = validate_files(&files);
+        assert_eq!(messages(&report), Vec::<String>::new());
+        assert_eq!(report.mod_id.as_deref(), Some("finn.moon"));
+        assert_eq!(report.file_count, 5);
+    }
+
+    #[test]
+    fn test_meta_errors() {
+        let report = validate_files(&mod_files(&[]));
+        assert_eq!(
+            messages(&report),
+            vec![
+                "error: meta.toml: missing, OpenZT only loads archives with a meta.toml at the root (add meta.toml with name, description, authors, mod_id and version)"
+            ]
+        );
+
+        let report = validate_files(&mod_files(&[("meta.toml", "name = \"Moon\"\nversion = \"1.0\"\n")]));
+        assert_eq!(report.errors().count(), 4);
+        assert_eq!(report.mod_id, None);
+
+        let report = validate_files(&mod_files(&[("meta.toml", "name = ")]));
+        assert_eq!(messages(&report).len(), 1);
+        assert_eq!(report.mod_id, None);
+
+        let meta = format!("{}icon = \"resources/icon.png\"\ntags = [\"habitats\"]\n", META);
+        let report = validate_files(&mod_files(&[("meta.toml", &meta), ("resources/Icon.png", "")]));
+        assert_eq!(
+            messages(&report),
+            vec!["error: meta.toml: 'icon' refers to resources/icon.png, which does not exist in the mod (did you mean 'resources/Icon.png'?)"]
+        );
+    }
+
+    #[test]
+    fn test_references() {
+        let def = "[locations.moon]\nname = \"Moon\"\nicon_path = \"resources/moon/N\"\nicon_palette_path = \"resources/moon/moon.pal\"\n\n\
+                   [sprites.moon]\ntarget = \"objects/moon/N\"\nframes = [\"resources/moon/0.png\", \"resources/moon/1.png\"]\n\n\
+                   [patches.a_merge]\noperation = \"merge\"\ntarget = \"animals/elephant.ai\"\nsource = \"elephant.ai\"\n\n\
+                   [patches.b_palette]\noperation = \"set_palette\"\ntarget = \"animals/elephant/N\"\npalette = \"elephant.ani\"\n\n\
+                   [patches.c_shared]\noperation = \"replace\"\ntarget = \"animals/lion.ai\"\nsource = \"mod:finn.framework:lion.ai\"\n";
+        let files = mod_files(&[
+            ("meta.toml", META),
+            ("defs/patches.toml", def),
+            ("resources/moon/N", ""),
+            ("resources/moon/0.png", ""),
+            ("resources/Elephant.ai", ""),
+        ]);
+
+        assert_eq!(
+            messages(&validate_files(&files)),
+            vec![
+                "error: defs/patches.toml:4: 'locations.moon.icon_palette_path' refers to resources/moon/moon.pal, which does not exist in the mod",
+                "error: defs/patches.toml:8: 'sprites.moon.frames' refers to resources/moon/1.png, which does not exist in the mod",
+                "error: defs/patches.toml:13: 'patches.a_merge.source' refers to resources/elephant.ai, which does not exist in the mod (did you mean 'Elephant.ai'?)",
+                "error: defs/patches.toml:18: 'patches.b_palette.palette' must be a .pal file, found 'elephant.ani'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ztd_type_warnings() {
+        let legacy = format!("{}ztd_type = \"legacy\"\n", META);
+        let report = validate_files(&mod_files(&[("meta.toml", &legacy), ("defs/moon.toml", "")]));
+        assert!(!report.has_errors());
+        assert_eq!(report.warnings().count(), 1);
+
+        let openzt = format!("{}ztd_type = \"openzt\"\n", META);
+        let report = validate_files(&mod_files(&[
+            ("meta.toml", &openzt),
+            ("animals/moon.ai", ""),
+            ("resources/moon.ai", ""),
+            ("scripts/moon.lua", ""),
+        ]));
+        let warnings: Vec<String> = report.warnings().map(|diagnostic| diagnostic.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec!["warning: animals/moon.ai: outside defs/, resources/ and scripts/, not loaded when ztd_type is \"openzt\""]
+        );
+    }
+
+    #[test]
+    fn test_build_writes_ztd() {
+        let dir = std::env::temp_dir().join(format!("openzt-mod-packager-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("resources").join("moon")).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::write(dir.join("meta.toml"), META).unwrap();
+        std::fs::write(dir.join("resources").join("moon").join("moon.pal"), [0u8; 64]).unwrap();
+        std::fs::write(dir.join(".git").join("HEAD"), "ref: refs/heads/main\n").unwrap();
+
+        let output = dir.join("out").join("moon.ztd");
+        let report = build(&dir, &output, None).unwrap();
+        assert!(!report.has_errors());
+
+        let mut archive = zip::ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!["meta.toml", "resources/moon/moon.pal"]);
+        assert_eq!(archive.by_name("resources/moon/moon.pal").unwrap().compression(), CompressionMethod::Deflated);
+
+        // Rebuilding leaves the previous build out of the archive
+        assert_eq!(build(&dir, &output, None).unwrap().file_count, 2);
+
+        // A mod with errors is not packaged
+        std::fs::remove_file(&output).unwrap();
+        std::fs::write(dir.join("meta.toml"), "name = \"Moon\"\n").unwrap();
+        assert!(build(&dir, &output, None).unwrap().has_errors());
+        assert!(!output.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_build_signs_mod() {
+        let dir = std::env::temp_dir().join(format!("openzt-mod-signing-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("resources")).unwrap();
+        std::fs::write(dir.join("meta.toml"), META).unwrap();
+        std::fs::write(dir.join("resources").join("moon.ai"), "[Moon]\n").unwrap();
+        // A stale signature left in the directory is replaced
+        std::fs::write(dir.join(SIGNATURE_FILE), "key = \"\"\n").unwrap();
+        assert_eq!(validate(&dir).unwrap().warnings().count(), 1);
+
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let output = dir.join("moon.ztd");
+        let report = build(&dir, &output, Some(&key)).unwrap();
+        assert!(!report.has_errors());
+        assert_eq!(report.file_count, 3);
+
+        let mut archive = zip::ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut files = BTreeMap::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let mut data = Vec::new();
+            std::io::Read::read_to_end(&mut file, &mut data).unwrap();
+            files.insert(file.name().to_string(), data);
+        }
+        let signature = signing::verify(files.iter().map(|(name, data)| (name.as_str(), data.as_slice())));
+        let public_key = hex::encode(key.verifying_key().to_bytes());
+        assert_eq!(signature, Signature::Valid { key: public_key });
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
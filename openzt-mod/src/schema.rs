@@ -0,0 +1,789 @@
+//! Schema checks for meta.toml and definition files
+//!
+//! The checks follow the structs OpenZT deserializes mod files into: keys the
+//! loader rejects are errors, keys it silently ignores are warnings. Every
+//! problem is reported with its line and, where possible, a suggestion such as
+//! the key that was probably meant, so a typo doesn't surface as a generic
+//! parse error once the mod is loaded.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+use toml::{
+    de::{DeTable, DeValue},
+    Spanned,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The mod would fail to load, or part of it would be skipped
+    Error,
+    /// The mod loads, but probably not as intended
+    Warning,
+}
+
+/// A problem found in one file of a mod
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// '/'-separated path within the mod
+    pub file: String,
+    /// 1-based line the problem was found on, if it can be pinned to one
+    pub line: Option<usize>,
+    pub message: String,
+    /// How to fix the problem, e.g. the key that was probably meant
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}", severity, self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// The expected type of a value
+enum Kind {
+    String,
+    Integer,
+    Boolean,
+    /// A string, number or boolean
+    Scalar,
+    /// A string in the format 'x.y.z'
+    Version,
+    Enum(&'static [&'static str]),
+    StringArray,
+    /// A table of string values, e.g. the keys of a set_keys patch
+    StringMap,
+    Table(&'static Schema),
+    TableArray(&'static Schema),
+    /// A table of named entries, e.g. `[habitats.swamp]`
+    NamedTables(&'static Schema),
+    /// Named patches, each checked against the schema of its `operation`
+    Patches,
+    /// Game string overrides, a table of strings by locale for each numeric string ID
+    Strings,
+    /// Mod settings, named tables whose `default` must match their `type`
+    Settings,
+}
+
+impl Kind {
+    fn describe(&self) -> Cow<'static, str> {
+        match self {
+            Kind::String => "a string".into(),
+            Kind::Integer => "an integer".into(),
+            Kind::Boolean => "true or false".into(),
+            Kind::Scalar => "a string, number or boolean".into(),
+            Kind::Version => "a version string".into(),
+            Kind::Enum(values) => format!("one of {}", values.join(", ")).into(),
+            Kind::StringArray => "an array of strings".into(),
+            Kind::StringMap => "a table of strings".into(),
+            Kind::Table(_) => "a table".into(),
+            Kind::TableArray(_) => "an array of tables".into(),
+            Kind::NamedTables(_) | Kind::Patches | Kind::Settings => "a table of named tables".into(),
+            Kind::Strings => "a table of string IDs".into(),
+        }
+    }
+}
+
+struct Field {
+    name: &'static str,
+    kind: Kind,
+    required: bool,
+}
+
+const fn required(name: &'static str, kind: Kind) -> Field {
+    Field { name, kind, required: true }
+}
+
+const fn optional(name: &'static str, kind: Kind) -> Field {
+    Field { name, kind, required: false }
+}
+
+struct Schema {
+    fields: &'static [Field],
+    /// Whether OpenZT rejects keys that aren't in `fields` rather than ignoring them
+    deny_unknown: bool,
+    /// Severity of a missing or invalid field, a warning where OpenZT skips the entry instead of failing
+    invalid: Severity,
+    /// At least one of these keys must be present
+    one_of: &'static [&'static str],
+}
+
+const fn strict(fields: &'static [Field]) -> Schema {
+    Schema {
+        fields,
+        deny_unknown: true,
+        invalid: Severity::Error,
+        one_of: &[],
+    }
+}
+
+const fn lenient(fields: &'static [Field]) -> Schema {
+    Schema {
+        fields,
+        deny_unknown: false,
+        invalid: Severity::Error,
+        one_of: &[],
+    }
+}
+
+// meta.toml
+
+const DEPENDENCY: Schema = Schema {
+    fields: &[
+        optional("mod_id", Kind::String),
+        optional("ztd_name", Kind::String),
+        optional("dll_name", Kind::String),
+        required("name", Kind::String),
+        optional("min_version", Kind::Version),
+        optional("optional", Kind::Boolean),
+        optional("ordering", Kind::Enum(&["before", "after", "none", "any"])),
+    ],
+    deny_unknown: false,
+    // Invalid dependencies are skipped with a warning when the mod is loaded
+    invalid: Severity::Warning,
+    one_of: &["mod_id", "ztd_name", "dll_name"],
+};
+
+const CONFLICT: Schema = strict(&[
+    required("mod_id", Kind::String),
+    optional("min_version", Kind::Version),
+    optional("max_version", Kind::Version),
+    optional("reason", Kind::String),
+]);
+
+const SETTING: Schema = strict(&[
+    required("type", Kind::Enum(&["integer", "float", "boolean", "string"])),
+    required("default", Kind::Scalar),
+    required("description", Kind::String),
+]);
+
+const META: Schema = strict(&[
+    required("name", Kind::String),
+    required("description", Kind::String),
+    required("authors", Kind::StringArray),
+    required("mod_id", Kind::String),
+    required("version", Kind::Version),
+    optional("min_openzt_version", Kind::Version),
+    optional("ztd_type", Kind::Enum(&["legacy", "combined", "openzt"])),
+    optional("link", Kind::String),
+    optional("homepage", Kind::String),
+    optional("license", Kind::String),
+    optional("icon", Kind::String),
+    optional("tags", Kind::StringArray),
+    optional("dependencies", Kind::TableArray(&DEPENDENCY)),
+    optional("conflicts", Kind::TableArray(&CONFLICT)),
+    optional("settings", Kind::Settings),
+]);
+
+// defs
+
+const ICON: Schema = lenient(&[
+    required("name", Kind::String),
+    required("icon_path", Kind::String),
+    required("icon_palette_path", Kind::String),
+]);
+
+const EXTENSION: Schema = lenient(&[
+    required("base", Kind::String),
+    optional("tags", Kind::StringArray),
+    optional("attributes", Kind::StringMap),
+]);
+
+const EXPANSION: Schema = lenient(&[required("name", Kind::String), optional("members", Kind::StringArray)]);
+
+const SPRITE: Schema = lenient(&[
+    required("target", Kind::String),
+    required("frames", Kind::StringArray),
+    optional("palette", Kind::String),
+    optional("animation_speed", Kind::Integer),
+    optional("offset_x", Kind::Integer),
+    optional("offset_y", Kind::Integer),
+]);
+
+const ENTITY_TYPE: Schema = lenient(&[
+    required("name", Kind::String),
+    optional("help", Kind::String),
+    required("cost", Kind::Integer),
+    optional("footprint_x", Kind::Integer),
+    optional("footprint_y", Kind::Integer),
+    required("icon", Kind::String),
+    required("animations", Kind::StringMap),
+    optional("subtypes", Kind::StringArray),
+    optional("habitat", Kind::String),
+    optional("location", Kind::String),
+    optional("expansion", Kind::String),
+    optional("characteristics", Kind::StringMap),
+]);
+
+const KEY_CHECK: Schema = lenient(&[required("section", Kind::String), required("key", Kind::String)]);
+
+const VALUE_CHECK: Schema = lenient(&[required("section", Kind::String), required("key", Kind::String), required("value", Kind::String)]);
+
+const CONDITION: Schema = strict(&[
+    optional("target", Kind::String),
+    optional("mod_loaded", Kind::String),
+    optional("mod_not_loaded", Kind::String),
+    optional("file_exists", Kind::String),
+    optional("key_exists", Kind::Table(&KEY_CHECK)),
+    optional("value_equals", Kind::Table(&VALUE_CHECK)),
+    optional("ztd_loaded", Kind::String),
+    optional("entity_exists", Kind::String),
+]);
+
+const PATCH_META: Schema = strict(&[
+    optional("on_error", Kind::Enum(&["continue", "abort", "abort_mod"])),
+    optional("priority", Kind::Integer),
+    optional("condition", Kind::Table(&CONDITION)),
+]);
+
+const DEF: Schema = strict(&[
+    optional("habitats", Kind::NamedTables(&ICON)),
+    optional("locations", Kind::NamedTables(&ICON)),
+    optional("scenery", Kind::NamedTables(&EXTENSION)),
+    optional("animals", Kind::NamedTables(&EXTENSION)),
+    optional("buildings", Kind::NamedTables(&EXTENSION)),
+    optional("fences", Kind::NamedTables(&EXTENSION)),
+    optional("walls", Kind::NamedTables(&EXTENSION)),
+    optional("paths", Kind::NamedTables(&EXTENSION)),
+    optional("food", Kind::NamedTables(&EXTENSION)),
+    optional("staff", Kind::NamedTables(&EXTENSION)),
+    optional("guests", Kind::NamedTables(&EXTENSION)),
+    optional("items", Kind::NamedTables(&EXTENSION)),
+    optional("patch_meta", Kind::Table(&PATCH_META)),
+    optional("patches", Kind::Patches),
+    optional("strings", Kind::Strings),
+    optional("expansions", Kind::NamedTables(&EXPANSION)),
+    optional("sprites", Kind::NamedTables(&SPRITE)),
+    optional("new_scenery", Kind::NamedTables(&ENTITY_TYPE)),
+    optional("new_animals", Kind::NamedTables(&ENTITY_TYPE)),
+]);
+
+// patches, each also has `operation`, `target` and `condition`
+
+const REPLACE: &[Field] = &[required("source", Kind::String), optional("priority", Kind::Integer)];
+const MERGE: &[Field] = &[
+    required("source", Kind::String),
+    optional("priority", Kind::Integer),
+    optional("merge_mode", Kind::Enum(&["patch_priority", "base_priority"])),
+    optional("duplicate_keys", Kind::Enum(&["replace_all", "replace_first", "append_duplicate", "keyed_by_value"])),
+];
+const DELETE: &[Field] = &[
+    optional("section", Kind::String),
+    optional("keys", Kind::StringArray),
+    optional("sections", Kind::StringArray),
+];
+const SET_PALETTE: &[Field] = &[required("palette", Kind::String)];
+const SET_KEY: &[Field] = &[
+    required("section", Kind::String),
+    required("key", Kind::String),
+    required("value", Kind::String),
+    optional("priority", Kind::Integer),
+];
+const SET_KEYS: &[Field] = &[
+    required("section", Kind::String),
+    required("keys", Kind::StringMap),
+    optional("priority", Kind::Integer),
+];
+const APPEND_VALUE: &[Field] = &[required("section", Kind::String), required("key", Kind::String), required("value", Kind::String)];
+const APPEND_VALUES: &[Field] = &[
+    required("section", Kind::String),
+    required("key", Kind::String),
+    required("values", Kind::StringArray),
+];
+const EDIT_LIST: &[Field] = &[
+    required("section", Kind::String),
+    required("key", Kind::String),
+    required("action", Kind::Enum(&["append", "prepend", "remove", "dedupe"])),
+    optional("values", Kind::StringArray),
+    optional("separator", Kind::String),
+];
+const REMOVE_KEY: &[Field] = &[
+    required("section", Kind::String),
+    required("key", Kind::String),
+    optional("priority", Kind::Integer),
+];
+const REMOVE_KEYS: &[Field] = &[
+    required("section", Kind::String),
+    required("keys", Kind::StringArray),
+    optional("priority", Kind::Integer),
+];
+const ADD_SECTION: &[Field] = &[
+    required("section", Kind::String),
+    optional("keys", Kind::StringMap),
+    optional("priority", Kind::Integer),
+    optional("on_exists", Kind::Enum(&["error", "merge", "skip", "replace"])),
+];
+const SECTION: &[Field] = &[required("section", Kind::String)];
+
+/// Patch operations with the fields specific to each
+const OPERATIONS: [(&str, &[Field]); 14] = [
+    ("replace", REPLACE),
+    ("merge", MERGE),
+    ("delete", DELETE),
+    ("set_palette", SET_PALETTE),
+    ("set_key", SET_KEY),
+    ("set_keys", SET_KEYS),
+    ("append_value", APPEND_VALUE),
+    ("append_values", APPEND_VALUES),
+    ("edit_list", EDIT_LIST),
+    ("remove_key", REMOVE_KEY),
+    ("remove_keys", REMOVE_KEYS),
+    ("add_section", ADD_SECTION),
+    ("clear_section", SECTION),
+    ("remove_section", SECTION),
+];
+
+const PATCH_COMMON: [Field; 3] = [
+    required("operation", Kind::String),
+    required("target", Kind::String),
+    optional("condition", Kind::Table(&CONDITION)),
+];
+
+/// Check meta.toml against the schema of OpenZT's mod metadata
+pub fn check_meta(file: &str, text: &str) -> Vec<Diagnostic> {
+    check_document(file, text, &META)
+}
+
+/// Check a file in defs/ against the schema of OpenZT's mod definitions
+pub fn check_def(file: &str, text: &str) -> Vec<Diagnostic> {
+    check_document(file, text, &DEF)
+}
+
+fn check_document(file: &str, text: &str, schema: &Schema) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        file,
+        text,
+        diagnostics: Vec::new(),
+    };
+
+    match DeTable::parse(text) {
+        Ok(document) => checker.check_table("", document.get_ref(), document.span(), schema),
+        Err(e) => {
+            let line = e.span().map(|span| line_at(text, span.start));
+            checker.push(Severity::Error, line, format!("invalid TOML: {}", e.message().trim()), None);
+        }
+    }
+
+    // Tables are iterated in key order, report in file order instead
+    checker.diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    checker.diagnostics
+}
+
+struct Checker<'a> {
+    file: &'a str,
+    text: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, severity: Severity, line: Option<usize>, message: String, suggestion: Option<String>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: self.file.to_string(),
+            line,
+            message,
+            suggestion,
+        });
+    }
+
+    fn report(&mut self, severity: Severity, span: Range<usize>, message: String, suggestion: Option<String>) {
+        let line = line_at(self.text, span.start);
+        self.push(severity, Some(line), message, suggestion);
+    }
+
+    fn check_table(&mut self, path: &str, table: &DeTable, span: Range<usize>, schema: &Schema) {
+        let fields: Vec<&Field> = schema.fields.iter().collect();
+        self.check_fields(path, table, span, &fields, schema);
+    }
+
+    /// Check a table's keys against `fields`, with everything else about the table from `schema`
+    fn check_fields(&mut self, path: &str, table: &DeTable, span: Range<usize>, fields: &[&Field], schema: &Schema) {
+        for (key, value) in table.iter() {
+            let key_path = join(path, key.get_ref());
+            match fields.iter().copied().find(|field| field.name == key.get_ref().as_ref()) {
+                Some(field) => self.check_value(&key_path, value, field, schema.invalid),
+                None => {
+                    let suggestion = closest(key.get_ref(), fields.iter().map(|field| field.name)).map(|name| format!("did you mean '{}'?", name));
+                    if schema.deny_unknown {
+                        self.report(Severity::Error, key.span(), format!("unknown key '{}'", key_path), suggestion);
+                    } else {
+                        self.report(Severity::Warning, key.span(), format!("unknown key '{}' is ignored", key_path), suggestion);
+                    }
+                }
+            }
+        }
+
+        for field in fields.iter().filter(|field| field.required && find(table, field.name).is_none()) {
+            let message = format!("missing required key '{}'", join(path, field.name));
+            self.report(
+                schema.invalid,
+                span.clone(),
+                message,
+                Some(format!("add '{}', {}", field.name, field.kind.describe())),
+            );
+        }
+
+        if !schema.one_of.is_empty() && !schema.one_of.iter().any(|name| find(table, name).is_some()) {
+            let message = if path.is_empty() {
+                "missing identifier".to_string()
+            } else {
+                format!("'{}' has no identifier", path)
+            };
+            self.report(schema.invalid, span, message, Some(format!("add one of {}", schema.one_of.join(", "))));
+        }
+    }
+
+    fn check_value(&mut self, path: &str, value: &Spanned<DeValue>, field: &Field, invalid: Severity) {
+        let span = value.span();
+        let mismatch = |kind: &Kind| format!("'{}' must be {}, found {}", path, kind.describe(), value.get_ref().type_str());
+
+        match (&field.kind, value.get_ref()) {
+            (Kind::String, DeValue::String(_)) | (Kind::Integer, DeValue::Integer(_)) | (Kind::Boolean, DeValue::Boolean(_)) => {}
+            (Kind::Scalar, DeValue::String(_) | DeValue::Integer(_) | DeValue::Float(_) | DeValue::Boolean(_)) => {}
+            (Kind::String, DeValue::Integer(_) | DeValue::Float(_) | DeValue::Boolean(_)) => {
+                self.report(invalid, span, mismatch(&field.kind), Some("put the value in quotes".to_string()))
+            }
+            (Kind::Version, DeValue::String(version)) => {
+                if !is_valid_version(version) {
+                    let suggestion = Some("use the format 'x.y.z', e.g. '1.0.0'".to_string());
+                    self.report(invalid, span, format!("invalid version '{}' for '{}'", version, path), suggestion);
+                }
+            }
+            (Kind::Enum(values), DeValue::String(value)) => {
+                if !values.contains(&value.as_ref()) {
+                    let suggestion = match closest(value, values.iter().copied()) {
+                        Some(closest) => format!("did you mean '{}'?", closest),
+                        None => format!("expected one of {}", values.join(", ")),
+                    };
+                    self.report(invalid, span, format!("invalid value '{}' for '{}'", value, path), Some(suggestion));
+                }
+            }
+            (Kind::StringArray, DeValue::Array(items)) => {
+                if let Some(item) = items.iter().find(|item| !item.get_ref().is_str()) {
+                    let message = format!("'{}' must only contain strings, found {}", path, item.get_ref().type_str());
+                    self.report(invalid, item.span(), message, None);
+                }
+            }
+            (Kind::StringMap, DeValue::Table(table)) => {
+                for (key, item) in table.iter().filter(|(_, item)| !item.get_ref().is_str()) {
+                    let message = format!("'{}' must be a string, found {}", join(path, key.get_ref()), item.get_ref().type_str());
+                    self.report(invalid, item.span(), message, Some("put the value in quotes".to_string()));
+                }
+            }
+            (Kind::Table(schema), DeValue::Table(table)) => self.check_table(path, table, span, schema),
+            (Kind::TableArray(schema), DeValue::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    let item_path = format!("{}[{}]", path, index);
+                    match item.get_ref() {
+                        DeValue::Table(table) => self.check_table(&item_path, table, item.span(), schema),
+                        other => self.report(invalid, item.span(), format!("'{}' must be a table, found {}", item_path, other.type_str()), None),
+                    }
+                }
+            }
+            (Kind::NamedTables(schema), DeValue::Table(entries)) => {
+                for (name, entry) in entries.iter() {
+                    let entry_path = join(path, name.get_ref());
+                    match entry.get_ref() {
+                        DeValue::Table(table) => self.check_table(&entry_path, table, entry.span(), schema),
+                        other => self.report(invalid, entry.span(), format!("'{}' must be a table, found {}", entry_path, other.type_str()), None),
+                    }
+                }
+            }
+            (Kind::Patches, DeValue::Table(patches)) => {
+                for (name, patch) in patches.iter() {
+                    let patch_path = join(path, name.get_ref());
+                    match patch.get_ref() {
+                        DeValue::Table(table) => self.check_patch(&patch_path, table, patch.span()),
+                        other => self.report(invalid, patch.span(), format!("'{}' must be a table, found {}", patch_path, other.type_str()), None),
+                    }
+                }
+            }
+            (Kind::Strings, DeValue::Table(strings)) => {
+                for (id, translations) in strings.iter() {
+                    let id_path = join(path, id.get_ref());
+                    if id.get_ref().parse::<u32>().is_err() {
+                        let message = format!("string ID '{}' in '{}' must be a number", id.get_ref(), path);
+                        self.report(
+                            invalid,
+                            id.span(),
+                            message,
+                            Some("use the ID from the game's string table, e.g. [strings.3383]".to_string()),
+                        );
+                    }
+                    let translations_field = optional(field.name, Kind::StringMap);
+                    self.check_value(&id_path, translations, &translations_field, invalid);
+                }
+            }
+            (Kind::Settings, DeValue::Table(settings)) => {
+                for (key, setting) in settings.iter() {
+                    let setting_path = join(path, key.get_ref());
+                    match setting.get_ref() {
+                        DeValue::Table(table) => self.check_setting(&setting_path, table, setting.span()),
+                        other => {
+                            let message = format!("'{}' must be a table, found {}", setting_path, other.type_str());
+                            self.report(invalid, setting.span(), message, None)
+                        }
+                    }
+                }
+            }
+            (kind, _) => self.report(invalid, span, mismatch(kind), None),
+        }
+    }
+
+    /// Check a mod setting, and that its default is a value of its type
+    fn check_setting(&mut self, path: &str, table: &DeTable, span: Range<usize>) {
+        self.check_table(path, table, span, &SETTING);
+
+        let (Some(setting_type), Some(default)) = (find(table, "type"), find(table, "default")) else {
+            return;
+        };
+        let matches = match (setting_type.get_ref().as_str(), default.get_ref()) {
+            (Some("integer"), DeValue::Integer(_)) | (Some("float"), DeValue::Integer(_) | DeValue::Float(_)) => true,
+            (Some("boolean"), DeValue::Boolean(_)) | (Some("string"), DeValue::String(_)) => true,
+            (Some("integer" | "float" | "boolean" | "string"), _) => false,
+            // An invalid type was already reported
+            _ => true,
+        };
+        if !matches {
+            let message = format!(
+                "'{}.default' must be a {}, found {}",
+                path,
+                setting_type.get_ref().as_str().unwrap_or_default(),
+                default.get_ref().type_str()
+            );
+            self.report(Severity::Error, default.span(), message, None);
+        }
+    }
+
+    /// Check a patch against the fields of its operation
+    fn check_patch(&mut self, path: &str, table: &DeTable, span: Range<usize>) {
+        let operations = OPERATIONS.iter().map(|(operation, _)| *operation);
+        let Some(operation) = find(table, "operation") else {
+            let suggestion = format!("add 'operation', one of {}", operations.collect::<Vec<_>>().join(", "));
+            self.report(Severity::Error, span, format!("patch '{}' has no 'operation'", path), Some(suggestion));
+            return;
+        };
+
+        let Some(name) = operation.get_ref().as_str() else {
+            let message = format!("'{}.operation' must be a string, found {}", path, operation.get_ref().type_str());
+            self.report(Severity::Error, operation.span(), message, None);
+            return;
+        };
+        let Some((_, fields)) = OPERATIONS.iter().find(|(operation, _)| *operation == name) else {
+            let suggestion = match closest(name, operations.clone()) {
+                Some(closest) => format!("did you mean '{}'?", closest),
+                None => format!("expected one of {}", operations.collect::<Vec<_>>().join(", ")),
+            };
+            self.report(
+                Severity::Error,
+                operation.span(),
+                format!("unknown patch operation '{}' in '{}'", name, path),
+                Some(suggestion),
+            );
+            return;
+        };
+
+        // Patch structs don't deny unknown fields, so a misspelled optional key is silently ignored
+        let fields: Vec<&Field> = PATCH_COMMON.iter().chain(fields.iter()).collect();
+        self.check_fields(path, table, span, &fields, &lenient(&[]));
+    }
+}
+
+pub(crate) fn find<'t, 'i>(table: &'t DeTable<'i>, key: &str) -> Option<&'t Spanned<DeValue<'i>>> {
+    table.iter().find(|(name, _)| name.get_ref() == key).map(|(_, value)| value)
+}
+
+/// 1-based line of a byte offset in `text`
+pub(crate) fn line_at(text: &str, offset: usize) -> usize {
+    text.bytes().take(offset).filter(|byte| *byte == b'\n').count() + 1
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) }
+}
+
+/// Whether a version string is in the 'x.y.z' format OpenZT expects
+fn is_valid_version(version: &str) -> bool {
+    let parts: Vec<&str> = version.split('.').collect();
+    parts.len() == 3 && parts.iter().all(|part| part.parse::<u32>().is_ok())
+}
+
+/// The candidate closest to a misspelled name, if any is close enough to be what was meant
+fn closest<'c>(name: &str, candidates: impl Iterator<Item = &'c str>) -> Option<&'c str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect()
+    }
+
+    #[test]
+    fn test_meta() {
+        let meta = "name = \"Moon\"\ndescription = 5\nauthors = \"finn\"\nmod_id = \"finn.moon\"\nversoin = \"1.0.0\"\nztd_type = \"opnezt\"\ntags = \"animals\"\n\n\
+                    [[dependencies]]\nname = \"Framework\"\nordering = \"later\"\n";
+
+        assert_eq!(
+            messages(&check_meta("meta.toml", meta)),
+            vec![
+                "error: meta.toml:1: missing required key 'version' (add 'version', a version string)",
+                "error: meta.toml:2: 'description' must be a string, found integer (put the value in quotes)",
+                "error: meta.toml:3: 'authors' must be an array of strings, found string",
+                "error: meta.toml:5: unknown key 'versoin' (did you mean 'version'?)",
+                "error: meta.toml:6: invalid value 'opnezt' for 'ztd_type' (did you mean 'openzt'?)",
+                "error: meta.toml:7: 'tags' must be an array of strings, found string",
+                "warning: meta.toml:9: 'dependencies[0]' has no identifier (add one of mod_id, ztd_name, dll_name)",
+                "warning: meta.toml:11: invalid value 'later' for 'dependencies[0].ordering' (expected one of before, after, none, any)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_meta_settings() {
+        let meta = "name = \"Moon\"\ndescription = \"A habitat\"\nauthors = [\"finn\"]\nmod_id = \"finn.moon\"\nversion = \"1.0.0\"\n\n\
+                    [settings.litter_size]\ntype = \"integer\"\ndefault = 2\ndescription = \"Babies per litter\"\n\n\
+                    [settings.speed]\ntype = \"float\"\ndefault = 1\ndescription = \"Walking speed\"\n\n\
+                    [settings.hard_mode]\ntype = \"boolean\"\ndefault = \"yes\"\ndescription = \"Harder\"\n\n\
+                    [settings.name]\ntype = \"strnig\"\ndefault = [1]\n";
+
+        assert_eq!(
+            messages(&check_meta("meta.toml", meta)),
+            vec![
+                "error: meta.toml:19: 'settings.hard_mode.default' must be a boolean, found string",
+                "error: meta.toml:22: missing required key 'settings.name.description' (add 'description', a string)",
+                "error: meta.toml:23: invalid value 'strnig' for 'settings.name.type' (did you mean 'string'?)",
+                "error: meta.toml:24: 'settings.name.default' must be a string, number or boolean, found array",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_version_and_parse_errors() {
+        let meta = "name = \"Moon\"\ndescription = \"A habitat\"\nauthors = [\"finn\"]\nmod_id = \"finn.moon\"\nversion = \"1.0\"\n";
+        assert_eq!(
+            messages(&check_meta("meta.toml", meta)),
+            vec!["error: meta.toml:5: invalid version '1.0' for 'version' (use the format 'x.y.z', e.g. '1.0.0')"]
+        );
+
+        let meta = format!("{}min_openzt_version = \"0.6\"\n", meta.replace("\"1.0\"", "\"1.0.0\""));
+        assert_eq!(
+            messages(&check_meta("meta.toml", &meta)),
+            vec!["error: meta.toml:6: invalid version '0.6' for 'min_openzt_version' (use the format 'x.y.z', e.g. '1.0.0')"]
+        );
+
+        let diagnostics = check_def("defs/moon.toml", "[patches.a]\noperation = \"merge\"\ntarget = \n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert!(diagnostics[0].message.starts_with("invalid TOML"));
+    }
+
+    #[test]
+    fn test_def_patches() {
+        let def = "[patch_meta]\non_error = \"abourt\"\n\n\
+                   [patches.a]\noperation = \"merge\"\ntarget = \"animals/elephant.ai\"\nsorce = \"elephant.ai\"\n\n\
+                   [patches.b]\noperation = \"set_kye\"\ntarget = \"animals/elephant.ai\"\n\n\
+                   [patches.c]\noperation = \"edit_list\"\ntarget = \"animals/elephant.ai\"\nsection = \"Section\"\nkey = \"Key\"\naction = \"append\"\nvalues = [1]\n\n\
+                   [habitats.moon]\nname = \"Moon\"\nicon_path = \"resources/moon/N\"\n";
+
+        assert_eq!(
+            messages(&check_def("defs/moon.toml", def)),
+            vec![
+                "error: defs/moon.toml:2: invalid value 'abourt' for 'patch_meta.on_error' (did you mean 'abort'?)",
+                "error: defs/moon.toml:4: missing required key 'patches.a.source' (add 'source', a string)",
+                "warning: defs/moon.toml:7: unknown key 'patches.a.sorce' is ignored (did you mean 'source'?)",
+                "error: defs/moon.toml:10: unknown patch operation 'set_kye' in 'patches.b' (did you mean 'set_key'?)",
+                "error: defs/moon.toml:19: 'patches.c.values' must only contain strings, found integer",
+                "error: defs/moon.toml:21: missing required key 'habitats.moon.icon_palette_path' (add 'icon_palette_path', a string)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_def_strings() {
+        let def = "[strings.3383]\ndefault = \"Marsh\"\nde = \"Sumpf\"\n\n[strings.swamp]\nen = \"Swamp\"\n\n[strings.120]\nen = 120\n";
+
+        assert_eq!(
+            messages(&check_def("defs/strings.toml", def)),
+            vec![
+                "error: defs/strings.toml:5: string ID 'swamp' in 'strings' must be a number (use the ID from the game's string table, e.g. [strings.3383])",
+                "error: defs/strings.toml:9: 'strings.120.en' must be a string, found integer (put the value in quotes)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_def_expansions() {
+        let def = "[expansions.lunar]\nname = \"Lunar Pack\"\nmembers = [\"moon_tree\", 3]\n\n[expansions.empty]\nmembers = []\n";
+
+        assert_eq!(
+            messages(&check_def("defs/expansions.toml", def)),
+            vec![
+                "error: defs/expansions.toml:3: 'expansions.lunar.members' must only contain strings, found integer",
+                "error: defs/expansions.toml:5: missing required key 'expansions.empty.name' (add 'name', a string)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_def_sprites() {
+        let def = "[sprites.moon_tree]\ntarget = \"objects/moontree/idle/N\"\nframes = [\"resources/moontree/idle_0.png\"]\nanimation_speed = \"fast\"\n\n[sprites.empty]\nframes = []\n";
+
+        assert_eq!(
+            messages(&check_def("defs/sprites.toml", def)),
+            vec![
+                "error: defs/sprites.toml:4: 'sprites.moon_tree.animation_speed' must be an integer, found string",
+                "error: defs/sprites.toml:6: missing required key 'sprites.empty.target' (add 'target', a string)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_def_new_entity_types() {
+        let def = "[new_scenery.moonrock]\nname = \"Moon Rock\"\ncost = \"150\"\nicon = \"objects/moonrock/icon/moonrock/moonrock\"\nanimations = { idle = \"objects/moonrock/idle\" }\n\n[new_animals.mooncow]\nname = \"Moon Cow\"\ncost = 900\nanimations = {}\n";
+
+        assert_eq!(
+            messages(&check_def("defs/entities.toml", def)),
+            vec![
+                "error: defs/entities.toml:3: 'new_scenery.moonrock.cost' must be an integer, found string",
+                "error: defs/entities.toml:7: missing required key 'new_animals.mooncow.icon' (add 'icon', a string)",
+            ]
+        );
+    }
+}
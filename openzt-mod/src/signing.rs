@@ -0,0 +1,158 @@
+//! Ed25519 signatures embedded in OpenZT mods
+//!
+//! A signed mod carries `signature.toml` at the root of its archive, holding the
+//! author's public key and a signature over every other file in the mod. The
+//! signed message lists the SHA-256 and path of each file in path order, so
+//! changing, adding or removing any file breaks the signature. OpenZT decides
+//! whether to trust the key from `trusted_keys` in openzt.toml.
+
+use anyhow::Context;
+use ed25519_dalek::{Signature as Ed25519Signature, Signer, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+use toml::{Table, Value};
+
+pub use ed25519_dalek::SigningKey;
+
+/// File at the root of a mod that holds its signature
+pub const SIGNATURE_FILE: &str = "signature.toml";
+
+/// First line of the signed message, so signatures can't be reused for other formats
+const MESSAGE_HEADER: &str = "openzt-mod-signature-v1\n";
+
+/// Result of checking the signature embedded in a mod
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signature {
+    /// The mod has no signature.toml
+    Unsigned,
+    /// The signature matches the mod's files; `key` is the hex-encoded public key that made it
+    Valid { key: String },
+    /// signature.toml is malformed or doesn't match the mod's files
+    Invalid(String),
+}
+
+/// The message a mod's signature covers: the SHA-256 and path of every file except signature.toml
+pub fn signed_message<'f>(files: impl IntoIterator<Item = (&'f str, &'f [u8])>) -> Vec<u8> {
+    let mut files: Vec<(&str, &[u8])> = files.into_iter().filter(|(name, _)| *name != SIGNATURE_FILE).collect();
+    files.sort_by_key(|(name, _)| *name);
+
+    let mut message = MESSAGE_HEADER.to_string();
+    for (name, data) in files {
+        message.push_str(&format!("{:x}  {}\n", Sha256::digest(data), name));
+    }
+    message.into_bytes()
+}
+
+/// Sign a mod's files, returning the contents of its signature.toml
+pub fn sign<'f>(files: impl IntoIterator<Item = (&'f str, &'f [u8])>, key: &SigningKey) -> String {
+    let signature = key.sign(&signed_message(files));
+    format!(
+        "# Ed25519 signature over every other file in this mod, written by openzt-mod\nkey = \"{}\"\nsignature = \"{}\"\n",
+        hex::encode(key.verifying_key().to_bytes()),
+        hex::encode(signature.to_bytes())
+    )
+}
+
+/// Check the signature embedded in a mod against its files
+pub fn verify<'f>(files: impl IntoIterator<Item = (&'f str, &'f [u8])>) -> Signature {
+    let files: Vec<(&str, &[u8])> = files.into_iter().collect();
+    let Some((_, signature_file)) = files.iter().find(|(name, _)| *name == SIGNATURE_FILE) else {
+        return Signature::Unsigned;
+    };
+
+    match read_signature_file(signature_file) {
+        Ok((key, signature)) => match key.verify(&signed_message(files.iter().copied()), &signature) {
+            Ok(()) => Signature::Valid {
+                key: hex::encode(key.to_bytes()),
+            },
+            Err(_) => Signature::Invalid("the signature does not match the mod's files".to_string()),
+        },
+        Err(e) => Signature::Invalid(format!("{:#}", e)),
+    }
+}
+
+fn read_signature_file(data: &[u8]) -> anyhow::Result<(VerifyingKey, Ed25519Signature)> {
+    let table = std::str::from_utf8(data)?.parse::<Table>().context("invalid signature.toml")?;
+    let field = |name: &str| {
+        table
+            .get(name)
+            .and_then(Value::as_str)
+            .with_context(|| format!("signature.toml is missing '{}'", name))
+    };
+
+    let key = parse_public_key(field("key")?)?;
+    let signature = decode_hex::<64>(field("signature")?).context("invalid 'signature' in signature.toml")?;
+    Ok((key, Ed25519Signature::from_bytes(&signature)))
+}
+
+/// Parse a hex-encoded Ed25519 public key, as written in signature.toml and openzt.toml
+pub fn parse_public_key(key: &str) -> anyhow::Result<VerifyingKey> {
+    let bytes = decode_hex::<32>(key).context("invalid public key")?;
+    VerifyingKey::from_bytes(&bytes).context("invalid public key")
+}
+
+/// Parse a secret key file written by `openzt-mod keygen`
+pub fn parse_secret_key(text: &str) -> anyhow::Result<SigningKey> {
+    let secret = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .unwrap_or_default();
+    Ok(SigningKey::from_bytes(&decode_hex::<32>(secret).context("invalid secret key")?))
+}
+
+/// The contents of a secret key file
+pub fn format_secret_key(key: &SigningKey) -> String {
+    format!(
+        "# openzt-mod signing key, keep this file private\n# public key: {}\n{}\n",
+        hex::encode(key.verifying_key().to_bytes()),
+        hex::encode(key.to_bytes())
+    )
+}
+
+fn decode_hex<const N: usize>(text: &str) -> anyhow::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    hex::decode_to_slice(text.trim(), &mut bytes).with_context(|| format!("expected {} hex digits", N * 2))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let mut files = vec![("meta.toml", b"name = \"Moon\"\n".as_slice()), ("defs/moon.toml", b"".as_slice())];
+        assert_eq!(verify(files.iter().copied()), Signature::Unsigned);
+
+        let signature = sign(files.iter().copied(), &key(1));
+        files.push((SIGNATURE_FILE, signature.as_bytes()));
+        let expected_key = hex::encode(key(1).verifying_key().to_bytes());
+        assert_eq!(verify(files.iter().copied()), Signature::Valid { key: expected_key });
+
+        // File order doesn't matter, contents and names do
+        files.reverse();
+        assert!(matches!(verify(files.iter().copied()), Signature::Valid { .. }));
+        files.push(("resources/extra.ai", b"".as_slice()));
+        assert!(matches!(verify(files.iter().copied()), Signature::Invalid(_)));
+    }
+
+    #[test]
+    fn test_malformed_signature_file() {
+        let files = [("meta.toml", b"".as_slice()), (SIGNATURE_FILE, b"key = \"abcd\"\n".as_slice())];
+        let Signature::Invalid(reason) = verify(files) else {
+            panic!("expected an invalid signature");
+        };
+        assert_eq!(reason, "invalid public key: expected 64 hex digits: Invalid string length");
+    }
+
+    #[test]
+    fn test_secret_key_round_trip() {
+        let text = format_secret_key(&key(7));
+        assert_eq!(parse_secret_key(&text).unwrap().to_bytes(), key(7).to_bytes());
+        assert!(parse_secret_key("# nothing here\n").is_err());
+    }
+}
@@ -49,8 +49,33 @@ SET RELEASE_FLAG=1
 SET WAIT_FLAG=1
 SET CARGO_ARGS=--features integration-tests
 SET INTEGRATION_TESTS_MODE=1
+REM Read by the test runner in the game process
+SET OPENZT_TEST_FILTER=
+SET OPENZT_TEST_LIST=
+SET OPENZT_TEST_RESULTS=
 SHIFT
-GOTO build
+
+:parse_integration_tests_flags
+IF "%~1"=="" GOTO build
+IF "%~1"=="--filter" (
+    SET OPENZT_TEST_FILTER=%~2
+    SHIFT
+    SHIFT
+    GOTO parse_integration_tests_flags
+)
+IF "%~1"=="--list" (
+    SET OPENZT_TEST_LIST=1
+    SHIFT
+    GOTO parse_integration_tests_flags
+)
+IF "%~1"=="--results" (
+    SET OPENZT_TEST_RESULTS=%~f2
+    SHIFT
+    SHIFT
+    GOTO parse_integration_tests_flags
+)
+echo Error: Unknown flag "%~1"
+exit /b 1
 
 :parse_flags
 SET RELEASE_FLAG=
@@ -370,6 +395,11 @@ echo   --test         Build the test DLL (openzt-test-dll)
 echo   --wait         Wait for Zoo Tycoon to exit before returning
 echo   -- ^<args^>      Forward additional arguments to cargo
 echo.
+echo Integration Tests Flags:
+echo   --filter ^<patterns^>  Only run tests whose module::name matches, comma separated, * and ? as wildcards
+echo   --list               List the selected tests without running them
+echo   --results ^<path^>     Also write the results to path, as JUnit XML if it ends in .xml, JSON otherwise
+echo.
 echo Note: command-console feature is enabled by default for non-test builds.
 echo.
 echo Examples:
@@ -382,6 +412,9 @@ echo   openzt.bat check                     Run cargo check on openzt
 echo   openzt.bat clippy                    Run cargo clippy on openzt
 echo   openzt.bat test                      Run cargo test on openzt
 echo   openzt.bat integration-tests         Run integration tests (builds release, displays results)
+echo   openzt.bat integration-tests --filter patch_rollback   Run only the patch rollback tests
+echo   openzt.bat integration-tests --list  List the integration tests
+echo   openzt.bat integration-tests --results results.xml   Run integration tests and write JUnit XML
 echo   openzt.bat docs                      Generate and open docs
 echo   openzt.bat console                   Open interactive Lua console
 echo   openzt.bat console --oneshot "help()"          Run single Lua command and exit
@@ -1,12 +1,14 @@
 use std::{
+    collections::VecDeque,
     error::Error,
     fmt,
-    io::{Read, Write},
+    io::{self, BufReader, Read, Write},
     net::{TcpListener, TcpStream},
-    sync::Mutex,
+    sync::{mpsc, Mutex},
     thread,
 };
 
+use openzt_console::protocol::{self, ConsoleError, Request, Response};
 use openzt_detour_macro::detour_mod;
 use std::sync::LazyLock;
 use tracing::{error, info};
@@ -104,48 +106,91 @@ static COMMAND_THREAD: LazyLock<Mutex<std::thread::JoinHandle<()>>> = LazyLock::
     }))
 });
 
-static COMMAND_RESULTS: LazyLock<Mutex<Vec<String>>> = LazyLock::new(|| Mutex::new(Vec::<String>::new()));
+/// Lua code waiting to run on the game thread
+struct QueuedCommand {
+    lua_code: String,
+    /// Where to send the result, `None` for commands from the TUI which only shows it there
+    reply: Option<mpsc::Sender<Result<String, String>>>,
+}
 
-static COMMAND_QUEUE: LazyLock<Mutex<Vec<String>>> = LazyLock::new(|| Mutex::new(Vec::<String>::new()));
+static COMMAND_QUEUE: LazyLock<Mutex<VecDeque<QueuedCommand>>> = LazyLock::new(|| Mutex::new(VecDeque::new()));
 
 /// Executes the next Lua code from the command queue on the game thread
 pub fn call_next_command() {
     let _unused = COMMAND_THREAD.lock().unwrap();
-    let Some(lua_code) = get_from_command_queue() else {
+    let Some(command) = get_from_command_queue() else {
         return;
     };
 
-    info!("Executing Lua: {}", lua_code.clone());
+    info!("Executing Lua: {}", command.lua_code);
 
-    let result = match crate::scripting::execute_lua(&lua_code) {
-        Ok(result) => result,
-        Err(err) => err,
-    };
+    let result = crate::scripting::execute_lua(&command.lua_code);
 
     #[cfg(feature = "tui")]
-    tui_console::add_command_output(result.clone());
+    tui_console::add_command_output(match &result {
+        Ok(result) | Err(result) => result.clone(),
+    });
 
-    let mut result_mutex = COMMAND_RESULTS.lock().unwrap();
-    result_mutex.push(result);
+    if let Some(reply) = command.reply {
+        // The client may have disconnected while the command was queued
+        let _ = reply.send(result);
+    }
 }
 
-pub fn get_next_result() -> Option<String> {
-    let mut data_mutex = COMMAND_RESULTS.lock().unwrap();
-    data_mutex.pop()
+pub fn add_to_command_queue(command: String) {
+    queue_command(command, None);
 }
 
-pub fn add_to_command_queue(command: String) {
-    info!("Adding Lua code to queue: {}", command);
-    let mut data_mutex = COMMAND_QUEUE.lock().unwrap();
-    data_mutex.push(command);
+fn queue_command(lua_code: String, reply: Option<mpsc::Sender<Result<String, String>>>) {
+    info!("Adding Lua code to queue: {}", lua_code);
+    COMMAND_QUEUE.lock().unwrap().push_back(QueuedCommand { lua_code, reply });
+}
+
+fn get_from_command_queue() -> Option<QueuedCommand> {
+    COMMAND_QUEUE.lock().unwrap().pop_front()
+}
+
+/// Run Lua code on the game thread and wait for its result
+fn run_command(lua_code: String) -> Result<String, String> {
+    let (sender, receiver) = mpsc::channel();
+    queue_command(lua_code, Some(sender));
+    receiver.recv().unwrap_or_else(|_| Err("Command was dropped before it ran".to_string()))
 }
 
-pub fn get_from_command_queue() -> Option<String> {
-    let mut data_mutex = COMMAND_QUEUE.lock().unwrap();
-    data_mutex.pop()
+/// Serve a client, speaking the framed protocol if its first byte is 0 and raw Lua text otherwise
+fn handle_client(stream: TcpStream) {
+    let mut first_byte = [0; 1];
+    match stream.peek(&mut first_byte) {
+        Ok(0) => {}
+        Ok(_) if first_byte[0] == 0 => {
+            if let Err(err) = handle_framed_client(stream) {
+                info!("Console connection closed: {}", err);
+            }
+        }
+        Ok(_) => handle_raw_client(stream),
+        Err(err) => info!("Error reading data: {}", err),
+    }
+}
+
+/// Answer each request frame with a response frame, see [`openzt_console::protocol`]
+fn handle_framed_client(stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    while let Some(payload) = protocol::read_frame(&mut reader)? {
+        let response = match protocol::decode_payload::<Request>(&payload) {
+            Ok(request) => {
+                let result = request.to_lua().and_then(|lua_code| run_command(lua_code).map_err(ConsoleError::command_failed));
+                Response::new(request.id, result)
+            }
+            Err(err) => Response::new(0, Err(ConsoleError::invalid_request(format!("Invalid request: {}", err)))),
+        };
+        protocol::write_frame(&mut writer, &response)?;
+    }
+    Ok(())
 }
 
-fn handle_client(mut stream: TcpStream) {
+/// Run each read as Lua code and write back its result or error as plain text
+fn handle_raw_client(mut stream: TcpStream) {
     let mut buffer = [0; 1024];
 
     loop {
@@ -158,16 +203,13 @@ fn handle_client(mut stream: TcpStream) {
 
                 // Received Lua code to execute
                 let received_string = String::from_utf8_lossy(&buffer[0..size]);
-                add_to_command_queue(received_string.to_string());
                 info!("Received Lua code: {}", received_string);
 
-                loop {
-                    if let Some(result) = get_next_result() {
-                        if let Err(err) = stream.write_all(result.as_bytes()) {
-                            info!("Error sending data: {}", err);
-                        }
-                        break;
-                    }
+                let result = match run_command(received_string.to_string()) {
+                    Ok(result) | Err(result) => result,
+                };
+                if let Err(err) = stream.write_all(result.as_bytes()) {
+                    info!("Error sending data: {}", err);
                 }
             }
             Err(err) => {
@@ -19,7 +19,10 @@ use crate::{
     bfentitytype::{ZTEntityType, ZTEntityTypeClass},
     command_console::CommandError,
     lua_fn,
-    resource_manager::{Handler, OPENZT_DIR0, RunStage, add_handler, lazyresourcemap, mod_config::get_openzt_config, modify_ztfile_as_animation, modify_ztfile_as_ini},
+    resource_manager::{
+        Handler, OPENZT_DIR0, RunStage, add_handler, lazyresourcemap, mod_config::get_openzt_config, modify_ztfile_as_animation, modify_ztfile_as_ini,
+        openzt_mods::ztd_registry::get_mod_ztd,
+    },
     string_registry::{add_string_to_registry, get_string_from_registry},
     util::{get_from_memory, get_string_from_memory, get_string_from_memory_bounded, save_to_memory},
     ztui::{BuyTab, Sex, get_random_sex, get_selected_sex},
@@ -232,6 +235,56 @@ fn expansion_name_exists(expansion_name: &str) -> Option<String> {
     None
 }
 
+/// An expansion declared in a mod's defs
+#[derive(Debug, Clone)]
+struct ModExpansion {
+    mod_id: String,
+    name: String,
+    /// Entity or archive names, as in the `[expansions]` section of openzt.toml
+    members: Vec<String>,
+}
+
+/// Expansions declared by OpenZT mods, in load order; created after those from openzt.toml
+static MOD_EXPANSIONS: LazyLock<Mutex<Vec<ModExpansion>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Register an expansion declared in a mod's defs
+///
+/// Mods can't add xpac*.cfg files, so this is how a mod groups its content into
+/// an expansion of its own. The expansion is created with the custom expansions
+/// from openzt.toml when the expansion dropdown is set up; when `members` is empty
+/// it holds every entity from the mod's archive.
+pub fn add_mod_expansion(mod_id: &str, name: &str, members: &[String]) -> anyhow::Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("Expansion name cannot be empty"));
+    }
+    let members = if members.is_empty() {
+        let archive = get_mod_ztd(mod_id).with_context(|| format!("Expansion '{}' has no members and mod {} has no archive", name, mod_id))?;
+        vec![archive]
+    } else {
+        members.to_vec()
+    };
+
+    let mut mod_expansions = MOD_EXPANSIONS.lock().unwrap();
+    if let Some(existing) = mod_expansions.iter().find(|expansion| expansion.name.eq_ignore_ascii_case(name)) {
+        return Err(anyhow!("Expansion '{}' is already declared by mod {}", name, existing.mod_id));
+    }
+    info!("Mod {} declared expansion '{}' with {} members", mod_id, name, members.len());
+    mod_expansions.push(ModExpansion {
+        mod_id: mod_id.to_string(),
+        name: name.to_string(),
+        members,
+    });
+    Ok(())
+}
+
+/// Forget the expansions declared by a mod (before the mod is reloaded)
+///
+/// Expansions already added to the dropdown stay until the game is restarted.
+pub fn remove_mod_expansions(mod_id: &str) {
+    MOD_EXPANSIONS.lock().unwrap().retain(|expansion| expansion.mod_id != mod_id);
+}
+
 /// Mutex containing all expansions
 static EXPANSION_ARRAY: LazyLock<Mutex<Vec<Expansion>>> = LazyLock::new(|| Mutex::new(Vec::new()));
 
@@ -417,13 +470,13 @@ fn create_custom_expansions() {
     info!("create_custom_expansions() - starting");
     info!("create_custom_expansions() - getting config");
     let config = get_openzt_config();
-    info!(
-        "create_custom_expansions() - got config, {} custom expansions to process",
-        config.expansions.custom.len()
-    );
+    let mut custom: Vec<(String, Vec<String>)> = config.expansions.custom.into_iter().collect();
+    let mod_expansions = MOD_EXPANSIONS.lock().unwrap().clone();
+    custom.extend(mod_expansions.into_iter().map(|expansion| (expansion.name, expansion.members)));
+    info!("create_custom_expansions() - got config, {} custom expansions to process", custom.len());
     let mut expansion_id = 0x5; // Start after official expansion IDs
 
-    for (expansion_name, items) in &config.expansions.custom {
+    for (expansion_name, items) in &custom {
         info!("create_custom_expansions() - processing expansion: '{}'", expansion_name);
 
         // Check if an expansion with this name already exists
@@ -0,0 +1,93 @@
+//! Keeping integration tests from affecting each other
+//!
+//! All tests run in one game process and share the resource map and the mod registry,
+//! so a test that adds a file or loads a mod would change what every later test sees.
+//! The runner captures that state once the harness is set up and puts it back after
+//! each test, so tests pass in any order. A test module can also have a setup function
+//! run before each of its tests and a teardown function run after each, see
+//! `integration_tests!`.
+
+use std::panic;
+
+use tracing::error;
+
+use super::{catch_test_panic, TestResult};
+use crate::resource_manager::{
+    lazyresourcemap::{self, ResourceMapSnapshot},
+    openzt_mods::{
+        loading::{self, ModIdSnapshot},
+        ztd_registry::{self, ZtdRegistrySnapshot},
+    },
+};
+
+/// Setup and teardown of a test module, run around each of its tests
+#[derive(Debug, Clone, Copy)]
+pub struct Hooks {
+    /// Run before each test, the test fails without running if this returns an error
+    pub setup: Option<fn() -> Result<(), String>>,
+    /// Run after each test, even if it failed
+    pub teardown: Option<fn()>,
+}
+
+impl Hooks {
+    pub const NONE: Hooks = Hooks { setup: None, teardown: None };
+}
+
+/// The global state tests can change: the resource map, loaded mod IDs and the ZTD registry
+pub struct GlobalState {
+    resources: ResourceMapSnapshot,
+    mod_ids: ModIdSnapshot,
+    ztd_registry: ZtdRegistrySnapshot,
+}
+
+impl GlobalState {
+    pub fn capture() -> Self {
+        GlobalState {
+            resources: lazyresourcemap::snapshot_for_tests(),
+            mod_ids: loading::snapshot_mod_ids_for_tests(),
+            ztd_registry: ztd_registry::snapshot_registry_for_tests(),
+        }
+    }
+
+    /// Put the state back as it was when captured
+    pub fn restore(&self) -> anyhow::Result<()> {
+        loading::restore_mod_ids_for_tests(&self.mod_ids);
+        ztd_registry::restore_registry_for_tests(&self.ztd_registry);
+        lazyresourcemap::restore_snapshot_for_tests(&self.resources)?;
+        Ok(())
+    }
+}
+
+/// Run a test between its module's setup and teardown, then restore `baseline`
+///
+/// A failing setup, teardown or restore fails the test, as the tests after it could
+/// otherwise fail for reasons that have nothing to do with them.
+pub fn run_isolated(name: &str, test_fn: fn() -> TestResult, hooks: &Hooks, baseline: &GlobalState) -> TestResult {
+    let setup = hooks
+        .setup
+        .map_or(Ok(()), |setup| panic::catch_unwind(setup).unwrap_or_else(|_| Err("panicked".to_string())));
+    let mut result = match setup {
+        Ok(()) => catch_test_panic(name, test_fn),
+        Err(e) => TestResult::fail(name, format!("Setup failed: {}", e)),
+    };
+
+    let mut cleanup_errors = Vec::new();
+    if let Some(teardown) = hooks.teardown
+        && panic::catch_unwind(teardown).is_err()
+    {
+        cleanup_errors.push("Teardown panicked".to_string());
+    }
+    if let Err(e) = baseline.restore() {
+        cleanup_errors.push(format!("Failed to restore global state: {:#}", e));
+    }
+
+    for cleanup_error in cleanup_errors {
+        error!("{}: {}", name, cleanup_error);
+        if result.passed {
+            let duration = result.duration;
+            result = TestResult::fail(name, cleanup_error);
+            result.duration = duration;
+        }
+    }
+    result
+}
@@ -384,6 +384,7 @@ fn test_patch_legacy_substitution_animal() -> TestResult {
     // Create patch with legacy substitution (uses default subtype, which may return either male or female)
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -396,6 +397,7 @@ fn test_patch_legacy_substitution_animal() -> TestResult {
             key: "cNameID".to_string(),
             value: "{legacy.animals.elephant.name_id}".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -445,6 +447,7 @@ fn test_patch_legacy_substitution_fence() -> TestResult {
     // Create patch with legacy substitution (explicit subtype)
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -457,6 +460,7 @@ fn test_patch_legacy_substitution_fence() -> TestResult {
             key: "cNameID".to_string(),
             value: "{legacy.fences.atltank.f.name_id}".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -505,6 +509,7 @@ fn test_patch_legacy_substitution_building() -> TestResult {
     // Create patch with legacy substitution
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -517,6 +522,7 @@ fn test_patch_legacy_substitution_building() -> TestResult {
             key: "cNameID".to_string(),
             value: "{legacy.buildings.restroom.name_id}".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -556,6 +562,7 @@ fn test_patch_multiple_legacy_variables() -> TestResult {
     // Create patch with multiple legacy variables (using default subtypes)
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -568,6 +575,7 @@ fn test_patch_multiple_legacy_variables() -> TestResult {
             key: "cAnimalID".to_string(),
             value: "{legacy.animals.elephant.name_id}".to_string(),
             condition: None,
+            priority: None,
         }),
     );
     patches.insert(
@@ -578,6 +586,7 @@ fn test_patch_multiple_legacy_variables() -> TestResult {
             key: "cBuildingID".to_string(),
             value: "{legacy.buildings.restroom.name_id}".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -624,6 +633,7 @@ fn test_patch_mixed_variable_types() -> TestResult {
     // Create patch with legacy variable (using default subtype, which may vary)
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -637,6 +647,7 @@ fn test_patch_mixed_variable_types() -> TestResult {
             key: "cLegacyID".to_string(),
             value: "{legacy.animals.elephant.name_id}".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -30,27 +30,27 @@ mod embedded_resources {
 
 /// Create an in-memory file map for the test mod (mimics ZIP structure)
 #[cfg(feature = "integration-tests")]
-pub fn create_test_mod_file_map() -> std::collections::HashMap<String, Box<[u8]>> {
+pub fn create_test_mod_file_map() -> std::collections::HashMap<String, std::sync::Arc<[u8]>> {
     use embedded_resources::*;
 
     let mut file_map = std::collections::HashMap::new();
 
     // Add meta.toml
-    file_map.insert("meta.toml".to_string(), META_TOML.as_bytes().to_vec().into_boxed_slice());
+    file_map.insert("meta.toml".to_string(), META_TOML.as_bytes().to_vec().into());
 
     // Add definition files
-    file_map.insert("defs/00-habitat-only.toml".to_string(), DEF_00_HABITAT.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("defs/01-location-only.toml".to_string(), DEF_01_LOCATION.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("defs/02-another-habitat.toml".to_string(), DEF_02_HABITAT.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("defs/Capitals-Test.toml".to_string(), DEF_CAPITALS.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("defs/50-mixed-content.toml".to_string(), DEF_50_MIXED.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("defs/51-second-mixed.toml".to_string(), DEF_51_MIXED.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("defs/98-early-patch.toml".to_string(), DEF_98_PATCH.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("defs/99-patches-only.toml".to_string(), DEF_99_PATCH.as_bytes().to_vec().into_boxed_slice());
+    file_map.insert("defs/00-habitat-only.toml".to_string(), DEF_00_HABITAT.as_bytes().to_vec().into());
+    file_map.insert("defs/01-location-only.toml".to_string(), DEF_01_LOCATION.as_bytes().to_vec().into());
+    file_map.insert("defs/02-another-habitat.toml".to_string(), DEF_02_HABITAT.as_bytes().to_vec().into());
+    file_map.insert("defs/Capitals-Test.toml".to_string(), DEF_CAPITALS.as_bytes().to_vec().into());
+    file_map.insert("defs/50-mixed-content.toml".to_string(), DEF_50_MIXED.as_bytes().to_vec().into());
+    file_map.insert("defs/51-second-mixed.toml".to_string(), DEF_51_MIXED.as_bytes().to_vec().into());
+    file_map.insert("defs/98-early-patch.toml".to_string(), DEF_98_PATCH.as_bytes().to_vec().into());
+    file_map.insert("defs/99-patches-only.toml".to_string(), DEF_99_PATCH.as_bytes().to_vec().into());
 
     // Add icon resources
-    file_map.insert("resources/test/icon".to_string(), ICON_DATA.to_vec().into_boxed_slice());
-    file_map.insert("resources/test/icon.pal".to_string(), ICON_PALETTE.to_vec().into_boxed_slice());
+    file_map.insert("resources/test/icon".to_string(), ICON_DATA.to_vec().into());
+    file_map.insert("resources/test/icon.pal".to_string(), ICON_PALETTE.to_vec().into());
 
     file_map
 }
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use std::io::Write;
+use std::time::{Duration, Instant};
 use tracing::{error, info};
 
 #[cfg(target_os = "windows")]
@@ -9,12 +10,15 @@ use crate::detour_mod;
 pub mod dependency_resolution;
 pub mod disabled_ztd;
 pub mod extensions;
+pub mod fixtures;
+pub mod isolation;
 pub mod legacy_attributes;
 pub mod loading_order;
 pub mod patch_conditions;
 pub mod patch_rollback;
 pub mod patch_source_resolution;
 pub mod permitted_archive_patterns;
+pub mod results;
 pub mod shortcuts;
 pub mod unified_loading_order;
 
@@ -24,6 +28,10 @@ pub struct TestResult {
     pub name: String,
     pub passed: bool,
     pub error: Option<String>,
+    /// Why the test was skipped, set by `skip`
+    pub skipped: Option<String>,
+    /// How long the test took, set by `catch_test_panic`
+    pub duration: Duration,
 }
 
 impl TestResult {
@@ -32,6 +40,8 @@ impl TestResult {
             name: name.to_string(),
             passed: true,
             error: None,
+            skipped: None,
+            duration: Duration::ZERO,
         }
     }
 
@@ -40,6 +50,8 @@ impl TestResult {
             name: name.to_string(),
             passed: false,
             error: Some(error),
+            skipped: None,
+            duration: Duration::ZERO,
         }
     }
 
@@ -48,15 +60,18 @@ impl TestResult {
             name: format!("{} (skipped: {})", name, reason),
             passed: true,
             error: None,
+            skipped: Some(reason.to_string()),
+            duration: Duration::ZERO,
         }
     }
 }
 
-/// Run a single test with panic catching
+/// Run a single test with panic catching, recording how long it took
 pub fn catch_test_panic(test_name: &str, test_fn: fn() -> TestResult) -> TestResult {
     use std::panic::{self, AssertUnwindSafe};
 
-    match panic::catch_unwind(AssertUnwindSafe(test_fn)) {
+    let start = Instant::now();
+    let mut result = match panic::catch_unwind(AssertUnwindSafe(test_fn)) {
         Ok(result) => result,
         Err(panic_info) => {
             let panic_msg = if let Some(msg) = panic_info.downcast_ref::<String>() {
@@ -68,10 +83,107 @@ pub fn catch_test_panic(test_name: &str, test_fn: fn() -> TestResult) -> TestRes
             };
             TestResult::fail(test_name, format!("PANIC: {}", panic_msg))
         }
+    };
+    result.duration = start.elapsed();
+    result
+}
+
+/// A registered test: its name and function
+pub type Test = (&'static str, fn() -> TestResult);
+
+/// Which tests to run, from the environment of the game process
+///
+/// `OPENZT_TEST_FILTER` is a comma separated list of patterns matched against
+/// `module::test_name`, e.g. `patch_rollback,test_cross_file`: a pattern with `*` or `?`
+/// is a glob, any other pattern matches names containing it. Without a filter every test
+/// runs. With `OPENZT_TEST_LIST=1` the selected tests are only listed, not run.
+#[derive(Debug, Default)]
+pub struct TestSelection {
+    patterns: Vec<String>,
+    pub list_only: bool,
+}
+
+impl TestSelection {
+    pub fn new(filter: &str, list_only: bool) -> Self {
+        TestSelection {
+            patterns: filter
+                .split(',')
+                .map(|pattern| pattern.trim().to_lowercase())
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+            list_only,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let filter = std::env::var("OPENZT_TEST_FILTER").unwrap_or_default();
+        let list_only = std::env::var("OPENZT_TEST_LIST").is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true"));
+        TestSelection::new(&filter, list_only)
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    /// Whether the test `name` in `module` is selected
+    pub fn matches(&self, module: &str, name: &str) -> bool {
+        let full_name = format!("{}::{}", module, name).to_lowercase();
+        self.patterns.is_empty()
+            || self.patterns.iter().any(|pattern| {
+                if pattern.contains(['*', '?']) {
+                    crate::resource_manager::openzt_mods::patches::glob_matches(pattern, &full_name)
+                } else {
+                    full_name.contains(pattern.as_str())
+                }
+            })
+    }
+}
+
+/// The test suites in the order they run: description, module, tests and the module's setup and teardown
+const SUITES: &[(&str, &str, &[Test], isolation::Hooks)] = &[
+    (
+        "dependency resolution",
+        "dependency_resolution",
+        dependency_resolution::TESTS,
+        dependency_resolution::HOOKS,
+    ),
+    ("patch rollback", "patch_rollback", patch_rollback::TESTS, patch_rollback::HOOKS),
+    ("loading order", "loading_order", loading_order::TESTS, loading_order::HOOKS),
+    (
+        "unified loading order",
+        "unified_loading_order",
+        unified_loading_order::TESTS,
+        unified_loading_order::HOOKS,
+    ),
+    ("legacy attributes", "legacy_attributes", legacy_attributes::TESTS, legacy_attributes::HOOKS),
+    ("disabled ZTD", "disabled_ztd", disabled_ztd::TESTS, disabled_ztd::HOOKS),
+    (
+        "permitted archive pattern",
+        "permitted_archive_patterns",
+        permitted_archive_patterns::TESTS,
+        permitted_archive_patterns::HOOKS,
+    ),
+    ("shortcut", "shortcuts", shortcuts::TESTS, shortcuts::HOOKS),
+    ("extension", "extensions", extensions::TESTS, extensions::HOOKS),
+    (
+        "patch source resolution",
+        "patch_source_resolution",
+        patch_source_resolution::TESTS,
+        patch_source_resolution::HOOKS,
+    ),
+    ("patch conditions", "patch_conditions", patch_conditions::TESTS, patch_conditions::HOOKS),
+];
+
+/// Time taken by a test, in the unit that keeps it readable
+fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() >= 1 {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
     }
 }
 
-/// Macro to generate the run_all_tests() function for integration test modules
+/// Macro to generate the TESTS list, HOOKS and run_all_tests() function for integration test modules
 ///
 /// Usage:
 /// ```rust
@@ -81,15 +193,46 @@ pub fn catch_test_panic(test_name: &str, test_fn: fn() -> TestResult) -> TestRes
 ///     test_optional_dependency_warning,
 /// ];
 /// ```
+///
+/// A module whose tests need the same files or state can name a setup function, run
+/// before each test, and a teardown function, run after each:
+/// ```ignore
+/// integration_tests![
+///     setup: create_target_files, teardown: remove_target_files;
+///     test_merge_from_archive,
+/// ];
+/// ```
 #[macro_export]
 macro_rules! integration_tests {
-    ( $( $test_fn:ident ),* $(,)? ) => {
+    ( @suite $setup:expr, $teardown:expr; $( $test_fn:ident ),* ) => {
+        /// The tests in this module, in the order they run
+        pub const TESTS: &[super::Test] = &[
+            $( (stringify!($test_fn), $test_fn), )*
+        ];
+
+        /// Setup and teardown run around each test in this module
+        pub const HOOKS: super::isolation::Hooks = super::isolation::Hooks {
+            setup: $setup,
+            teardown: $teardown,
+        };
+
         pub fn run_all_tests() -> Vec<super::TestResult> {
-            vec![
-                $( super::catch_test_panic(stringify!($test_fn), $test_fn), )*
-            ]
+            let baseline = super::isolation::GlobalState::capture();
+            TESTS.iter().map(|(name, test_fn)| super::isolation::run_isolated(name, *test_fn, &HOOKS, &baseline)).collect()
         }
     };
+    ( setup: $setup:ident, teardown: $teardown:ident; $( $test_fn:ident ),* $(,)? ) => {
+        $crate::integration_tests!(@suite Some($setup), Some($teardown); $( $test_fn ),*);
+    };
+    ( setup: $setup:ident; $( $test_fn:ident ),* $(,)? ) => {
+        $crate::integration_tests!(@suite Some($setup), None; $( $test_fn ),*);
+    };
+    ( teardown: $teardown:ident; $( $test_fn:ident ),* $(,)? ) => {
+        $crate::integration_tests!(@suite None, Some($teardown); $( $test_fn ),*);
+    };
+    ( $( $test_fn:ident ),* $(,)? ) => {
+        $crate::integration_tests!(@suite None, None; $( $test_fn ),*);
+    };
 }
 
 pub fn init() {
@@ -219,186 +362,84 @@ mod detour_zoo_main {
         write_log("=== OpenZT Integration Tests ===");
         write_log("");
 
-        // Run dependency resolution tests
-        write_log("Running dependency resolution tests...");
-        let dependency_results = super::dependency_resolution::run_all_tests();
-
-        let mut total_passed = 0;
-        let mut total_failed = 0;
-
-        for result in &dependency_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
-            }
-        }
-
-        write_log("");
-
-        // Run patch rollback tests
-        write_log("Running patch rollback tests...");
-        let patch_results = super::patch_rollback::run_all_tests();
-
-        for result in &patch_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
-            }
-        }
-
-        write_log("");
-
-        // Run loading order tests
-        write_log("Running loading order tests...");
-        let loading_results = super::loading_order::run_all_tests();
-
-        for result in &loading_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
-            }
-        }
-
-        write_log("");
-
-        // Run unified loading order tests
-        write_log("Running unified loading order tests...");
-        let unified_loading_results = super::unified_loading_order::run_all_tests();
-
-        for result in &unified_loading_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
-            }
-        }
-
-        write_log("");
-
-        // Run legacy attributes tests
-        write_log("Running legacy attributes tests...");
-        let legacy_attributes_results = super::legacy_attributes::run_all_tests();
-
-        for result in &legacy_attributes_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
-            }
-        }
-
-        write_log("");
-
-        // Run disabled ZTD tests
-        write_log("Running disabled ZTD tests...");
-        let disabled_ztd_results = super::disabled_ztd::run_all_tests();
-
-        for result in &disabled_ztd_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
-            }
+        let selection = super::TestSelection::from_env();
+        if selection.is_filtered() {
+            write_log(&format!("Filter: {}", std::env::var("OPENZT_TEST_FILTER").unwrap_or_default()));
+            write_log("");
         }
 
-        write_log("");
-
-        // Run permitted archive patterns tests
-        write_log("Running permitted archive pattern tests...");
-        let permitted_archive_patterns_results = super::permitted_archive_patterns::run_all_tests();
-
-        for result in &permitted_archive_patterns_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
+        if selection.list_only {
+            let mut listed = 0;
+            for (_, module, tests, _) in super::SUITES {
+                for (name, _) in tests.iter().filter(|(name, _)| selection.matches(module, name)) {
+                    write_log(&format!("{}::{}", module, name));
+                    listed += 1;
+                }
             }
+            write_log("");
+            write_log(&format!("{} tests", listed));
+            std::process::exit(0);
         }
 
-        write_log("");
-
-        // Run shortcut tests
-        write_log("Running shortcut tests...");
-        let shortcuts_results = super::shortcuts::run_all_tests();
+        // Every test starts from the state the harness set up above
+        let baseline = super::isolation::GlobalState::capture();
 
-        for result in &shortcuts_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
+        let start = std::time::Instant::now();
+        let mut total_passed = 0;
+        let mut total_failed = 0;
+        let mut total_filtered = 0;
+        let mut suite_reports = Vec::new();
+
+        for (description, module, tests, hooks) in super::SUITES {
+            let (selected, filtered): (Vec<&super::Test>, Vec<&super::Test>) = tests.iter().partition(|(name, _)| selection.matches(module, name));
+            total_filtered += filtered.len();
+            if selected.is_empty() {
+                continue;
             }
-        }
 
-        write_log("");
-
-        // Run extension tests
-        write_log("Running extension tests...");
-        let extensions_results = super::extensions::run_all_tests();
-
-        for result in &extensions_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
+            write_log(&format!("Running {} tests...", description));
+            let mut test_reports = Vec::new();
+            for (name, test_fn) in selected {
+                let result = super::isolation::run_isolated(name, *test_fn, hooks, &baseline);
+                test_reports.push(super::results::TestReport::new(name, &result));
+                let duration = super::format_duration(result.duration);
+                if result.passed {
+                    write_log(&format!("  ✓ {} ({})", result.name, duration));
+                    total_passed += 1;
+                } else {
+                    write_log(&format!(
+                        "  ✗ {} ({}) - {}",
+                        result.name,
+                        duration,
+                        result.error.as_ref().unwrap_or(&"Unknown error".to_string())
+                    ));
+                    total_failed += 1;
+                }
             }
+            suite_reports.push(super::results::SuiteReport {
+                module: module.to_string(),
+                description: description.to_string(),
+                tests: test_reports,
+            });
+            write_log("");
         }
 
-        write_log("");
-
-        // Run patch source resolution tests
-        write_log("Running patch source resolution tests...");
-        let patch_source_results = super::patch_source_resolution::run_all_tests();
-
-        for result in &patch_source_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
-            }
+        if total_filtered > 0 {
+            write_log(&format!("{} tests filtered out", total_filtered));
         }
+        let elapsed = start.elapsed();
+        write_log(&format!("Took {}", super::format_duration(elapsed)));
+        write_log(&format!("Results: {} passed, {} failed", total_passed, total_failed));
 
-        write_log("");
-
-        // Run patch conditions tests
-        write_log("Running patch conditions tests...");
-        let patch_conditions_results = super::patch_conditions::run_all_tests();
-
-        for result in &patch_conditions_results {
-            if result.passed {
-                write_log(&format!("  ✓ {}", result.name));
-                total_passed += 1;
-            } else {
-                write_log(&format!("  ✗ {} - {}", result.name, result.error.as_ref().unwrap_or(&"Unknown error".to_string())));
-                total_failed += 1;
+        // Machine-readable results for CI, when a path is given
+        if let Ok(results_path) = std::env::var("OPENZT_TEST_RESULTS") {
+            let report = super::results::RunReport::new(suite_reports, total_filtered, elapsed);
+            match report.write(std::path::Path::new(&results_path)) {
+                Ok(()) => write_log(&format!("Results written to {}", results_path)),
+                Err(e) => write_log(&format!("{:#}", e)),
             }
         }
 
-        write_log("");
-        write_log(&format!("Results: {} passed, {} failed", total_passed, total_failed));
-
         if total_failed > 0 {
             write_log("");
             write_log(&format!("FAILED - Check log at: {}", test_log_path));
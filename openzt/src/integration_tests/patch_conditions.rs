@@ -1,10 +1,11 @@
-//! Integration tests for `ztd_loaded` and `entity_exists` patch conditions.
+//! Integration tests for `ztd_loaded`, `entity_exists`, `file_exists` and `mod_not_loaded` patch conditions.
 
 use super::TestResult;
 use crate::mods::{ErrorHandling, Patch, PatchCondition, PatchMeta, SetKeyPatch};
 use crate::resource_manager::{
     lazyresourcemap::{add_ztfile, get_file, remove_resource},
     openzt_mods::legacy_attributes::{add_legacy_entity, LegacyEntityAttributes, LegacyEntityType, SubtypeAttributes},
+    openzt_mods::loading::{add_new_mod_id, set_enabled_mod_ids},
     openzt_mods::patches::apply_patches,
     openzt_mods::ztd_registry::{self, ZtdLoadStatus},
     ztfile::{ZTFile, ZTFileType},
@@ -67,6 +68,11 @@ crate::integration_tests![
     test_combined_ztd_loaded_and_entity_exists,
     test_combined_ztd_loaded_fails_blocks_patch,
     test_combined_entity_exists_fails_blocks_patch,
+    test_file_exists_passes_when_file_exists,
+    test_file_exists_skips_when_file_missing,
+    test_mod_not_loaded_passes_when_mod_absent,
+    test_mod_not_loaded_skips_when_mod_loaded,
+    test_mod_not_loaded_skips_when_mod_loads_later,
 ];
 
 // ============================================================================
@@ -90,6 +96,7 @@ fn test_ztd_loaded_passes_when_ztd_loaded_before() -> TestResult {
     // Create patch with ztd_loaded condition
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -104,11 +111,14 @@ fn test_ztd_loaded_passes_when_ztd_loaded_before() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: Some("base.ztd".to_string()),
                 entity_exists: None,
             }),
+            priority: None,
         }),
     );
 
@@ -152,6 +162,7 @@ fn test_ztd_loaded_skips_when_ztd_loaded_after() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -166,11 +177,14 @@ fn test_ztd_loaded_skips_when_ztd_loaded_after() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: Some("base.ztd".to_string()),
                 entity_exists: None,
             }),
+            priority: None,
         }),
     );
 
@@ -210,6 +224,7 @@ fn test_ztd_loaded_skips_when_ztd_disabled() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -224,11 +239,14 @@ fn test_ztd_loaded_skips_when_ztd_disabled() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: Some("base.ztd".to_string()),
                 entity_exists: None,
             }),
+            priority: None,
         }),
     );
 
@@ -267,6 +285,7 @@ fn test_ztd_loaded_skips_when_ztd_not_registered() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -281,11 +300,14 @@ fn test_ztd_loaded_skips_when_ztd_not_registered() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: Some("nonexistent.ztd".to_string()),
                 entity_exists: None,
             }),
+            priority: None,
         }),
     );
 
@@ -325,6 +347,7 @@ fn test_ztd_loaded_case_insensitive() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -339,11 +362,14 @@ fn test_ztd_loaded_case_insensitive() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: Some("mymod.ztd".to_string()), // lowercase
                 entity_exists: None,
             }),
+            priority: None,
         }),
     );
 
@@ -384,6 +410,7 @@ fn test_entity_exists_passes_when_entity_exists() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -398,11 +425,14 @@ fn test_entity_exists_passes_when_entity_exists() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: None,
                 entity_exists: Some("legacy.animals.elephant".to_string()),
             }),
+            priority: None,
         }),
     );
 
@@ -438,6 +468,7 @@ fn test_entity_exists_skips_when_entity_not_exists() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -452,11 +483,14 @@ fn test_entity_exists_skips_when_entity_not_exists() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: None,
                 entity_exists: Some("legacy.animals.dragon".to_string()), // doesn't exist
             }),
+            priority: None,
         }),
     );
 
@@ -508,6 +542,7 @@ fn test_entity_exists_all_entity_types() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -523,11 +558,14 @@ fn test_entity_exists_all_entity_types() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: None,
                 entity_exists: Some(format!("legacy.{}.lion", entity_types[0].1)),
             }),
+            priority: None,
         }),
     );
 
@@ -564,6 +602,7 @@ fn test_entity_exists_case_insensitive() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -578,11 +617,14 @@ fn test_entity_exists_case_insensitive() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: None,
                 entity_exists: Some("legacy.animals.elephant".to_string()), // lowercase
             }),
+            priority: None,
         }),
     );
 
@@ -623,6 +665,7 @@ fn test_entity_exists_invalid_format() -> TestResult {
 
         let patch_meta = PatchMeta {
             on_error: ErrorHandling::Continue,
+            priority: 0,
             condition: None,
         };
 
@@ -637,11 +680,14 @@ fn test_entity_exists_invalid_format() -> TestResult {
                 condition: Some(PatchCondition {
                     target: None,
                     mod_loaded: None,
+                    mod_not_loaded: None,
+                    file_exists: None,
                     key_exists: None,
                     value_equals: None,
                     ztd_loaded: None,
                     entity_exists: Some(invalid_format.to_string()),
                 }),
+                priority: None,
             }),
         );
 
@@ -685,6 +731,7 @@ fn test_entity_exists_invalid_entity_type() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -699,11 +746,14 @@ fn test_entity_exists_invalid_entity_type() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: None,
                 entity_exists: Some("legacy.dragons.elephant".to_string()), // invalid entity type
             }),
+            priority: None,
         }),
     );
 
@@ -748,6 +798,7 @@ fn test_combined_ztd_loaded_and_entity_exists() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -762,11 +813,14 @@ fn test_combined_ztd_loaded_and_entity_exists() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: Some("base.ztd".to_string()),
                 entity_exists: Some("legacy.animals.elephant".to_string()),
             }),
+            priority: None,
         }),
     );
 
@@ -806,6 +860,7 @@ fn test_combined_ztd_loaded_fails_blocks_patch() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -820,11 +875,14 @@ fn test_combined_ztd_loaded_fails_blocks_patch() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: Some("base.ztd".to_string()),                   // fails
                 entity_exists: Some("legacy.animals.elephant".to_string()), // passes
             }),
+            priority: None,
         }),
     );
 
@@ -864,6 +922,7 @@ fn test_combined_entity_exists_fails_blocks_patch() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -878,11 +937,14 @@ fn test_combined_entity_exists_fails_blocks_patch() -> TestResult {
             condition: Some(PatchCondition {
                 target: None,
                 mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: None,
                 key_exists: None,
                 value_equals: None,
                 ztd_loaded: Some("base.ztd".to_string()),                 // passes
                 entity_exists: Some("legacy.animals.dragon".to_string()), // fails
             }),
+            priority: None,
         }),
     );
 
@@ -905,3 +967,305 @@ fn test_combined_entity_exists_fails_blocks_patch() -> TestResult {
         }
     }
 }
+
+// ============================================================================
+// Tests for file_exists condition
+// ============================================================================
+
+fn test_file_exists_passes_when_file_exists() -> TestResult {
+    let test_name = "test_file_exists_passes_when_file_exists";
+    let test_file = "test_file_exists_passes.ini";
+    let required_file = "test_file_exists_required.ai";
+
+    if let Err(e) = create_test_ini_file(required_file, "[Section]\nKey = Value\n") {
+        return TestResult::fail(test_name, format!("Setup failed: {}", e));
+    }
+
+    if let Err(e) = create_test_ini_file(test_file, "[Section]\nKey = Original\n") {
+        return TestResult::fail(test_name, format!("Setup failed: {}", e));
+    }
+
+    let patch_meta = PatchMeta {
+        on_error: ErrorHandling::Continue,
+        priority: 0,
+        condition: None,
+    };
+
+    let mut patches = indexmap::IndexMap::new();
+    patches.insert(
+        "modify".to_string(),
+        Patch::SetKey(SetKeyPatch {
+            target: test_file.to_string(),
+            section: "Section".to_string(),
+            key: "Key".to_string(),
+            value: "Modified".to_string(),
+            condition: Some(PatchCondition {
+                target: None,
+                mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: Some(required_file.to_string()),
+                key_exists: None,
+                value_equals: None,
+                ztd_loaded: None,
+                entity_exists: None,
+            }),
+            priority: None,
+        }),
+    );
+
+    let file_map = std::collections::HashMap::new();
+    let _ = apply_patches(&patch_meta, &patches, &file_map, "mymod");
+
+    cleanup_test_file(required_file);
+
+    // Verify patch was applied
+    match read_test_file(test_file) {
+        Ok(content) => {
+            cleanup_test_file(test_file);
+            if content.contains("Key=Modified") || content.contains("Key = Modified") {
+                TestResult::pass(test_name)
+            } else {
+                TestResult::fail(test_name, format!("Patch should have been applied. Content: {}", content))
+            }
+        }
+        Err(e) => {
+            cleanup_test_file(test_file);
+            TestResult::fail(test_name, format!("Failed to read file: {}", e))
+        }
+    }
+}
+
+fn test_file_exists_skips_when_file_missing() -> TestResult {
+    let test_name = "test_file_exists_skips_when_file_missing";
+    let test_file = "test_file_exists_skips.ini";
+
+    if let Err(e) = create_test_ini_file(test_file, "[Section]\nKey = Original\n") {
+        return TestResult::fail(test_name, format!("Setup failed: {}", e));
+    }
+
+    let patch_meta = PatchMeta {
+        on_error: ErrorHandling::Continue,
+        priority: 0,
+        condition: None,
+    };
+
+    let mut patches = indexmap::IndexMap::new();
+    patches.insert(
+        "modify".to_string(),
+        Patch::SetKey(SetKeyPatch {
+            target: test_file.to_string(),
+            section: "Section".to_string(),
+            key: "Key".to_string(),
+            value: "Modified".to_string(),
+            condition: Some(PatchCondition {
+                target: None,
+                mod_loaded: None,
+                mod_not_loaded: None,
+                file_exists: Some("test_file_exists_missing.ai".to_string()),
+                key_exists: None,
+                value_equals: None,
+                ztd_loaded: None,
+                entity_exists: None,
+            }),
+            priority: None,
+        }),
+    );
+
+    let file_map = std::collections::HashMap::new();
+    let _ = apply_patches(&patch_meta, &patches, &file_map, "mymod");
+
+    // Verify patch was NOT applied
+    match read_test_file(test_file) {
+        Ok(content) => {
+            cleanup_test_file(test_file);
+            if (content.contains("Key=Original") || content.contains("Key = Original")) && !content.contains("Modified") {
+                TestResult::pass(test_name)
+            } else {
+                TestResult::fail(test_name, format!("Patch should have been skipped. Content: {}", content))
+            }
+        }
+        Err(e) => {
+            cleanup_test_file(test_file);
+            TestResult::fail(test_name, format!("Failed to read file: {}", e))
+        }
+    }
+}
+
+// ============================================================================
+// Tests for mod_not_loaded condition
+// ============================================================================
+
+fn test_mod_not_loaded_passes_when_mod_absent() -> TestResult {
+    let test_name = "test_mod_not_loaded_passes_when_mod_absent";
+    let test_file = "test_mod_not_loaded_passes.ini";
+
+    if let Err(e) = create_test_ini_file(test_file, "[Section]\nKey = Original\n") {
+        return TestResult::fail(test_name, format!("Setup failed: {}", e));
+    }
+
+    let patch_meta = PatchMeta {
+        on_error: ErrorHandling::Continue,
+        priority: 0,
+        condition: None,
+    };
+
+    let mut patches = indexmap::IndexMap::new();
+    patches.insert(
+        "modify".to_string(),
+        Patch::SetKey(SetKeyPatch {
+            target: test_file.to_string(),
+            section: "Section".to_string(),
+            key: "Key".to_string(),
+            value: "Modified".to_string(),
+            condition: Some(PatchCondition {
+                target: None,
+                mod_loaded: None,
+                mod_not_loaded: Some("test.never_loaded_mod".to_string()),
+                file_exists: None,
+                key_exists: None,
+                value_equals: None,
+                ztd_loaded: None,
+                entity_exists: None,
+            }),
+            priority: None,
+        }),
+    );
+
+    let file_map = std::collections::HashMap::new();
+    let _ = apply_patches(&patch_meta, &patches, &file_map, "mymod");
+
+    // Verify patch was applied
+    match read_test_file(test_file) {
+        Ok(content) => {
+            cleanup_test_file(test_file);
+            if content.contains("Key=Modified") || content.contains("Key = Modified") {
+                TestResult::pass(test_name)
+            } else {
+                TestResult::fail(test_name, format!("Patch should have been applied. Content: {}", content))
+            }
+        }
+        Err(e) => {
+            cleanup_test_file(test_file);
+            TestResult::fail(test_name, format!("Failed to read file: {}", e))
+        }
+    }
+}
+
+fn test_mod_not_loaded_skips_when_mod_loaded() -> TestResult {
+    let test_name = "test_mod_not_loaded_skips_when_mod_loaded";
+    let test_file = "test_mod_not_loaded_skips.ini";
+
+    // Setup: Mark the excluded mod as loaded
+    add_new_mod_id("test.loaded_mod");
+
+    if let Err(e) = create_test_ini_file(test_file, "[Section]\nKey = Original\n") {
+        return TestResult::fail(test_name, format!("Setup failed: {}", e));
+    }
+
+    let patch_meta = PatchMeta {
+        on_error: ErrorHandling::Continue,
+        priority: 0,
+        condition: None,
+    };
+
+    let mut patches = indexmap::IndexMap::new();
+    patches.insert(
+        "modify".to_string(),
+        Patch::SetKey(SetKeyPatch {
+            target: test_file.to_string(),
+            section: "Section".to_string(),
+            key: "Key".to_string(),
+            value: "Modified".to_string(),
+            condition: Some(PatchCondition {
+                target: None,
+                mod_loaded: None,
+                mod_not_loaded: Some("test.loaded_mod".to_string()),
+                file_exists: None,
+                key_exists: None,
+                value_equals: None,
+                ztd_loaded: None,
+                entity_exists: None,
+            }),
+            priority: None,
+        }),
+    );
+
+    let file_map = std::collections::HashMap::new();
+    let _ = apply_patches(&patch_meta, &patches, &file_map, "mymod");
+
+    // Verify patch was NOT applied
+    match read_test_file(test_file) {
+        Ok(content) => {
+            cleanup_test_file(test_file);
+            if (content.contains("Key=Original") || content.contains("Key = Original")) && !content.contains("Modified") {
+                TestResult::pass(test_name)
+            } else {
+                TestResult::fail(test_name, format!("Patch should have been skipped. Content: {}", content))
+            }
+        }
+        Err(e) => {
+            cleanup_test_file(test_file);
+            TestResult::fail(test_name, format!("Failed to read file: {}", e))
+        }
+    }
+}
+
+fn test_mod_not_loaded_skips_when_mod_loads_later() -> TestResult {
+    let test_name = "test_mod_not_loaded_skips_when_mod_loads_later";
+    let test_file = "test_mod_not_loaded_later.ini";
+
+    // Setup: The excluded mod comes after the patching mod, so it hasn't been loaded yet
+    set_enabled_mod_ids(&["mymod".to_string(), "test.later_mod".to_string()]);
+    add_new_mod_id("mymod");
+
+    if let Err(e) = create_test_ini_file(test_file, "[Section]\nKey = Original\n") {
+        return TestResult::fail(test_name, format!("Setup failed: {}", e));
+    }
+
+    let patch_meta = PatchMeta {
+        on_error: ErrorHandling::Continue,
+        priority: 0,
+        condition: None,
+    };
+
+    let mut patches = indexmap::IndexMap::new();
+    patches.insert(
+        "modify".to_string(),
+        Patch::SetKey(SetKeyPatch {
+            target: test_file.to_string(),
+            section: "Section".to_string(),
+            key: "Key".to_string(),
+            value: "Modified".to_string(),
+            condition: Some(PatchCondition {
+                target: None,
+                mod_loaded: None,
+                mod_not_loaded: Some("test.later_mod".to_string()),
+                file_exists: None,
+                key_exists: None,
+                value_equals: None,
+                ztd_loaded: None,
+                entity_exists: None,
+            }),
+            priority: None,
+        }),
+    );
+
+    let file_map = std::collections::HashMap::new();
+    let _ = apply_patches(&patch_meta, &patches, &file_map, "mymod");
+
+    // Verify patch was NOT applied
+    match read_test_file(test_file) {
+        Ok(content) => {
+            cleanup_test_file(test_file);
+            if (content.contains("Key=Original") || content.contains("Key = Original")) && !content.contains("Modified") {
+                TestResult::pass(test_name)
+            } else {
+                TestResult::fail(test_name, format!("Patch should have been skipped. Content: {}", content))
+            }
+        }
+        Err(e) => {
+            cleanup_test_file(test_file);
+            TestResult::fail(test_name, format!("Failed to read file: {}", e))
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
-use crate::mods::{AddSectionPatch, DeletePatch, ErrorHandling, OnExists, Patch, PatchMeta, SetKeyPatch};
+use crate::mods::{AddSectionPatch, DeletePatch, DuplicateKeyMode, ErrorHandling, MergeMode, MergePatch, OnExists, Patch, PatchMeta, SetKeyPatch, SetKeysPatch};
 use crate::resource_manager::{
     lazyresourcemap::{add_ztfile, check_file, get_file, remove_resource},
-    openzt_mods::patches::apply_patches,
+    openzt_mods::{patch_conflicts, patches::apply_patches},
     ztfile::{ZTFile, ZTFileType},
 };
 
@@ -43,6 +43,9 @@ crate::integration_tests![
     test_shadow_create_and_delete_in_same_batch,
     test_shadow_resources_get_file_fallback,
     test_shadow_resources_delete_file,
+    test_glob_target_patches_every_match,
+    test_higher_priority_patch_keeps_key,
+    test_merge_keeps_higher_priority_patch_key,
 ];
 
 fn test_continue_mode_applies_directly() -> TestResult {
@@ -57,6 +60,7 @@ fn test_continue_mode_applies_directly() -> TestResult {
     // Create patches with Continue error handling
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -69,6 +73,7 @@ fn test_continue_mode_applies_directly() -> TestResult {
             key: "Key".to_string(),
             value: "Modified".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -108,6 +113,7 @@ fn test_continue_mode_skips_failed_patches() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Continue,
+        priority: 0,
         condition: None,
     };
 
@@ -120,6 +126,7 @@ fn test_continue_mode_skips_failed_patches() -> TestResult {
             key: "Key".to_string(),
             value: "Modified".to_string(),
             condition: None,
+            priority: None,
         }),
     );
     patches.insert(
@@ -130,6 +137,7 @@ fn test_continue_mode_skips_failed_patches() -> TestResult {
             key: "Key".to_string(),
             value: "ShouldFail".to_string(),
             condition: None,
+            priority: None,
         }),
     );
     patches.insert(
@@ -140,6 +148,7 @@ fn test_continue_mode_skips_failed_patches() -> TestResult {
             key: "NewKey".to_string(),
             value: "AfterFailure".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -178,6 +187,7 @@ fn test_abort_mode_rolls_back_on_failure() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -190,6 +200,7 @@ fn test_abort_mode_rolls_back_on_failure() -> TestResult {
             key: "Key".to_string(),
             value: "Modified".to_string(),
             condition: None,
+            priority: None,
         }),
     );
     patches.insert(
@@ -200,6 +211,7 @@ fn test_abort_mode_rolls_back_on_failure() -> TestResult {
             key: "Key".to_string(),
             value: "ShouldNotApply".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -244,6 +256,7 @@ fn test_abort_mode_commits_on_success() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -256,6 +269,7 @@ fn test_abort_mode_commits_on_success() -> TestResult {
             key: "Key".to_string(),
             value: "Modified".to_string(),
             condition: None,
+            priority: None,
         }),
     );
     patches.insert(
@@ -266,6 +280,7 @@ fn test_abort_mode_commits_on_success() -> TestResult {
             key: "NewKey".to_string(),
             value: "NewValue".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -307,6 +322,7 @@ fn test_shadow_multiple_patches_same_file() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -319,6 +335,7 @@ fn test_shadow_multiple_patches_same_file() -> TestResult {
             key: "Key1".to_string(),
             value: "Modified1".to_string(),
             condition: None,
+            priority: None,
         }),
     );
     patches.insert(
@@ -329,6 +346,7 @@ fn test_shadow_multiple_patches_same_file() -> TestResult {
             keys: HashMap::new(),
             on_exists: OnExists::Skip,
             condition: None,
+            priority: None,
         }),
     );
     patches.insert(
@@ -339,6 +357,7 @@ fn test_shadow_multiple_patches_same_file() -> TestResult {
             key: "Key2".to_string(),
             value: "Value2".to_string(),
             condition: None,
+            priority: None,
         }),
     );
 
@@ -383,6 +402,7 @@ fn test_shadow_file_deletion() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -391,6 +411,9 @@ fn test_shadow_file_deletion() -> TestResult {
         "delete".to_string(),
         Patch::Delete(DeletePatch {
             target: test_file.to_string(),
+            section: None,
+            keys: Vec::new(),
+            sections: Vec::new(),
             condition: None,
         }),
     );
@@ -421,6 +444,7 @@ fn test_shadow_create_and_delete_in_same_batch() -> TestResult {
 
     let patch_meta = PatchMeta {
         on_error: ErrorHandling::Abort,
+        priority: 0,
         condition: None,
     };
 
@@ -433,12 +457,16 @@ fn test_shadow_create_and_delete_in_same_batch() -> TestResult {
             key: "Key".to_string(),
             value: "Value".to_string(),
             condition: None,
+            priority: None,
         }),
     );
     patches.insert(
         "delete".to_string(),
         Patch::Delete(DeletePatch {
             target: test_file.to_string(),
+            section: None,
+            keys: Vec::new(),
+            sections: Vec::new(),
             condition: None,
         }),
     );
@@ -523,3 +551,183 @@ fn test_shadow_resources_delete_file() -> TestResult {
         TestResult::fail(test_name, "File should not be in shadow map".to_string())
     }
 }
+
+fn test_glob_target_patches_every_match() -> TestResult {
+    let test_name = "test_glob_target_patches_every_match";
+    let matching = ["test_glob/first.ini", "test_glob/second.ini"];
+    let other = "test_glob/nested/third.ini";
+
+    // Setup
+    for file in matching.iter().chain([&other]) {
+        if let Err(e) = create_test_ini_file(file, "[Section]\nKey = Original\n") {
+            return TestResult::fail(test_name, format!("Setup failed: {}", e));
+        }
+    }
+    let cleanup = || {
+        for file in matching.iter().chain([&other]) {
+            cleanup_test_file(file);
+        }
+    };
+
+    // Shadow mode, so every expanded patch must succeed for the batch to commit
+    let patch_meta = PatchMeta {
+        on_error: ErrorHandling::Abort,
+        priority: 0,
+        condition: None,
+    };
+
+    let mut patches = indexmap::IndexMap::new();
+    patches.insert(
+        "modify_all".to_string(),
+        Patch::SetKey(SetKeyPatch {
+            target: "test_glob/*.ini".to_string(),
+            section: "Section".to_string(),
+            key: "Key".to_string(),
+            value: "Modified".to_string(),
+            condition: None,
+            priority: None,
+        }),
+    );
+
+    let file_map = HashMap::new();
+    if let Err(e) = apply_patches(&patch_meta, &patches, &file_map, "test_mod") {
+        cleanup();
+        return TestResult::fail(test_name, format!("Patches failed to apply: {}", e));
+    }
+
+    // Verify matching files were modified and the nested file was not
+    let modified = |file: &str| read_test_file(file).map(|content| content.contains("Key=Modified") || content.contains("Key = Modified"));
+    let result = match (modified(matching[0]), modified(matching[1]), modified(other)) {
+        (Ok(true), Ok(true), Ok(false)) => TestResult::pass(test_name),
+        (Ok(first), Ok(second), Ok(third)) => TestResult::fail(test_name, format!("Unexpected matches: first={}, second={}, nested={}", first, second, third)),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => TestResult::fail(test_name, format!("Failed to read file: {}", e)),
+    };
+
+    cleanup();
+    result
+}
+
+fn test_higher_priority_patch_keeps_key() -> TestResult {
+    let test_name = "test_higher_priority_patch_keeps_key";
+    let test_file = "test_priority.ini";
+    let (high_mod, low_mod) = ("test_priority_high", "test_priority_low");
+
+    // Setup
+    if let Err(e) = create_test_ini_file(test_file, "[Section]\nKey = Original\n") {
+        return TestResult::fail(test_name, format!("Setup failed: {}", e));
+    }
+    let cleanup = || {
+        cleanup_test_file(test_file);
+        patch_conflicts::release(high_mod, &["set_keys"]);
+        patch_conflicts::release(low_mod, &["set_keys"]);
+    };
+
+    let set_keys = |keys: &[(&str, &str)]| {
+        let mut patches = indexmap::IndexMap::new();
+        patches.insert(
+            "set_keys".to_string(),
+            Patch::SetKeys(SetKeysPatch {
+                target: test_file.to_string(),
+                section: "Section".to_string(),
+                keys: keys.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+                condition: None,
+                priority: None,
+            }),
+        );
+        patches
+    };
+    let patch_meta = |priority| PatchMeta {
+        on_error: ErrorHandling::Continue,
+        priority,
+        condition: None,
+    };
+
+    // The higher-priority mod loads first, the later mod also sets Key
+    let file_map = HashMap::new();
+    let high = apply_patches(&patch_meta(5), &set_keys(&[("Key", "High")]), &file_map, high_mod);
+    let low = apply_patches(&patch_meta(0), &set_keys(&[("Key", "Low"), ("Other", "Low")]), &file_map, low_mod);
+    if let Err(e) = high.and(low) {
+        cleanup();
+        return TestResult::fail(test_name, format!("Patches failed to apply: {}", e));
+    }
+
+    // Verify Key kept the higher-priority value while Other was still added
+    let has = |content: &str, key: &str, value: &str| content.contains(&format!("{}={}", key, value)) || content.contains(&format!("{} = {}", key, value));
+    let result = match read_test_file(test_file) {
+        Ok(content) if has(&content, "Key", "High") && has(&content, "Other", "Low") => TestResult::pass(test_name),
+        Ok(content) => TestResult::fail(test_name, format!("Unexpected content: {}", content)),
+        Err(e) => TestResult::fail(test_name, format!("Failed to read file: {}", e)),
+    };
+
+    cleanup();
+    result
+}
+
+fn test_merge_keeps_higher_priority_patch_key() -> TestResult {
+    let test_name = "test_merge_keeps_higher_priority_patch_key";
+    let test_file = "test_merge_priority.ini";
+    let (high_mod, low_mod) = ("test_merge_priority_high", "test_merge_priority_low");
+
+    // Setup
+    if let Err(e) = create_test_ini_file(test_file, "[Section]\nKey = Original\n") {
+        return TestResult::fail(test_name, format!("Setup failed: {}", e));
+    }
+    let cleanup = || {
+        cleanup_test_file(test_file);
+        patch_conflicts::release(high_mod, &["set_key"]);
+        patch_conflicts::release(low_mod, &["merge"]);
+    };
+
+    // The patch's own priority outranks the file's [patch_meta] priority of 0
+    let patch_meta = PatchMeta {
+        on_error: ErrorHandling::Continue,
+        priority: 0,
+        condition: None,
+    };
+    let mut high_patches = indexmap::IndexMap::new();
+    high_patches.insert(
+        "set_key".to_string(),
+        Patch::SetKey(SetKeyPatch {
+            target: test_file.to_string(),
+            section: "Section".to_string(),
+            key: "Key".to_string(),
+            value: "High".to_string(),
+            condition: None,
+            priority: Some(5),
+        }),
+    );
+    let mut low_patches = indexmap::IndexMap::new();
+    low_patches.insert(
+        "merge".to_string(),
+        Patch::Merge(MergePatch {
+            target: test_file.to_string(),
+            source: "merge.ini".to_string(),
+            merge_mode: MergeMode::PatchPriority,
+            duplicate_keys: DuplicateKeyMode::ReplaceAll,
+            condition: None,
+            priority: None,
+            kept_keys: Vec::new(),
+        }),
+    );
+
+    // The later mod merges a file that also sets Key
+    let mut low_files = HashMap::new();
+    low_files.insert("resources/merge.ini".to_string(), b"[Section]\nKey = Low\nOther = Low\n".to_vec().into());
+    let high = apply_patches(&patch_meta, &high_patches, &HashMap::new(), high_mod);
+    let low = apply_patches(&patch_meta, &low_patches, &low_files, low_mod);
+    if let Err(e) = high.and(low) {
+        cleanup();
+        return TestResult::fail(test_name, format!("Patches failed to apply: {}", e));
+    }
+
+    // Verify Key kept the higher-priority value while Other was still merged
+    let has = |content: &str, key: &str, value: &str| content.contains(&format!("{}={}", key, value)) || content.contains(&format!("{} = {}", key, value));
+    let result = match read_test_file(test_file) {
+        Ok(content) if has(&content, "Key", "High") && has(&content, "Other", "Low") => TestResult::pass(test_name),
+        Ok(content) => TestResult::fail(test_name, format!("Unexpected content: {}", content)),
+        Err(e) => TestResult::fail(test_name, format!("Failed to read file: {}", e)),
+    };
+
+    cleanup();
+    result
+}
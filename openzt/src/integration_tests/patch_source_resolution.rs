@@ -4,6 +4,7 @@
 //! under the `resources/` subdirectory.
 
 use super::TestResult;
+use super::fixtures::{self, ModFixture};
 
 const DEFS_TOML: &str = include_str!("../../resources/test/patch_source_resolution/defs/01-patches.toml");
 const MERGE_SOURCE: &str = include_str!("../../resources/test/patch_source_resolution/resources/test_merge_source.ai");
@@ -11,41 +12,49 @@ const REPLACE_SOURCE: &str = include_str!("../../resources/test/patch_source_res
 const MULTI_SOURCE: &str = include_str!("../../resources/test/patch_source_resolution/resources/test_multi_source.ai");
 
 crate::integration_tests![
+    setup: create_target_files;
     test_source_file_from_archive,
     test_merge_from_archive,
     test_replace_from_archive,
     test_missing_source_file_error,
     test_source_file_wrong_path_error,
+    test_merge_from_ztd_file,
 ];
 
-/// Helper function to create meta.toml content with a given mod_id
-fn create_meta_toml(mod_id: &str) -> String {
-    format!(
-        r#"name = "Patch Source Resolution Test"
-mod_id = "{}"
-version = "0.1.0"
-description = "Tests that patch source files can be read from .ztd archive"
-authors = ["OpenZT"]
-ztd_type = "openzt"
-"#,
-        mod_id
-    )
+/// Files the test mods patch, created before each test (the harness removes them after it)
+fn create_target_files() -> Result<(), String> {
+    use crate::resource_manager::lazyresourcemap::add_ztfile_from_memory;
+    use crate::resource_manager::ztfile::{ZTFile, ZTFileType};
+
+    let targets = [
+        ("animals/testsource.ai", b"[base]\nkey=value".as_slice()),
+        ("animals/testtarget.ai", b"[old]\noldkey=value".as_slice()),
+        ("animals/target.ai", b"[base]\nkey=value".as_slice()),
+    ];
+    for (file_name, content) in targets {
+        add_ztfile_from_memory(
+            "test_setup",
+            file_name.to_string(),
+            ZTFile::RawBytes(content.to_vec().into_boxed_slice(), ZTFileType::Ai, 0),
+        )
+        .map_err(|e| format!("Failed to create target file {}: {}", file_name, e))?;
+    }
+    Ok(())
+}
+
+/// The test mod with the given mod_id, its patches read their sources from resources/
+fn test_mod(mod_id: &str) -> ModFixture {
+    ModFixture::new(mod_id)
+        .with_meta(r#"name = "Patch Source Resolution Test""#)
+        .with_def("01-patches.toml", DEFS_TOML)
+        .with_resource("test_merge_source.ai", MERGE_SOURCE)
+        .with_resource("test_replace_source.ai", REPLACE_SOURCE)
+        .with_resource("test_multi_source.ai", MULTI_SOURCE)
 }
 
 /// Helper function to load a test mod with the given mod_id
 fn load_test_mod(mod_id: &str) -> Result<(), String> {
-    // Create test mod file map
-    let mut file_map = std::collections::HashMap::new();
-    file_map.insert("meta.toml".to_string(), create_meta_toml(mod_id).as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("defs/01-patches.toml".to_string(), DEFS_TOML.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("resources/test_merge_source.ai".to_string(), MERGE_SOURCE.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("resources/test_replace_source.ai".to_string(), REPLACE_SOURCE.as_bytes().to_vec().into_boxed_slice());
-    file_map.insert("resources/test_multi_source.ai".to_string(), MULTI_SOURCE.as_bytes().to_vec().into_boxed_slice());
-
-    // Load the test mod
-    crate::resource_manager::openzt_mods::loading::load_open_zt_mod_from_memory(file_map, mod_id, std::path::Path::new("dummy"))
-        .map(|_| ())
-        .map_err(|e| format!("Failed to load test mod: {}", e))
+    test_mod(mod_id).load().map(|_| ()).map_err(|e| format!("Failed to load test mod: {}", e))
 }
 
 fn test_source_file_from_archive() -> TestResult {
@@ -61,16 +70,6 @@ fn test_source_file_from_archive() -> TestResult {
 fn test_merge_from_archive() -> TestResult {
     let test_name = "test_merge_from_archive";
 
-    // First create the target file to merge into
-    use crate::resource_manager::lazyresourcemap::add_ztfile_from_memory;
-    use crate::resource_manager::ztfile::{ZTFile, ZTFileType};
-    add_ztfile_from_memory(
-        "test_setup",
-        "animals/testsource.ai".to_string(),
-        ZTFile::RawBytes(b"[base]\nkey=value".to_vec().into_boxed_slice(), ZTFileType::Ai, 0),
-    )
-    .expect("Failed to create target file");
-
     // Load the test mod with unique mod_id so patches are applied
     if let Err(e) = load_test_mod("patch_source_merge_test") {
         return TestResult::fail(test_name, format!("Failed to load mod: {}", e));
@@ -96,16 +95,6 @@ fn test_merge_from_archive() -> TestResult {
 fn test_replace_from_archive() -> TestResult {
     let test_name = "test_replace_from_archive";
 
-    // First create a target file to replace
-    use crate::resource_manager::lazyresourcemap::add_ztfile_from_memory;
-    use crate::resource_manager::ztfile::{ZTFile, ZTFileType};
-    add_ztfile_from_memory(
-        "test_setup",
-        "animals/testtarget.ai".to_string(),
-        ZTFile::RawBytes(b"[old]\noldkey=value".to_vec().into_boxed_slice(), ZTFileType::Ai, 0),
-    )
-    .expect("Failed to create target file");
-
     // Load the test mod with unique mod_id so patches are applied
     if let Err(e) = load_test_mod("patch_source_replace_test") {
         return TestResult::fail(test_name, format!("Failed to load mod: {}", e));
@@ -131,35 +120,11 @@ fn test_replace_from_archive() -> TestResult {
 fn test_missing_source_file_error() -> TestResult {
     let test_name = "test_missing_source_file_error";
 
-    // First create the target file so merge can proceed to source file check
-    use crate::resource_manager::lazyresourcemap::add_ztfile_from_memory;
-    use crate::resource_manager::ztfile::{ZTFile, ZTFileType};
-    add_ztfile_from_memory(
-        "test_setup",
-        "animals/target.ai".to_string(),
-        ZTFile::RawBytes(b"[base]\nkey=value".to_vec().into_boxed_slice(), ZTFileType::Ai, 0),
-    )
-    .expect("Failed to create target file");
-
     // Create a mod with a patch that references a non-existent source file
-    let mut file_map = std::collections::HashMap::new();
-    file_map.insert(
-        "meta.toml".to_string(),
-        r#"name = "Missing Source Test"
-mod_id = "missing_source_test"
-version = "0.1.0"
-description = "Test for missing source files"
-authors = ["OpenZT"]
-ztd_type = "openzt"
-"#
-        .as_bytes()
-        .to_vec()
-        .into_boxed_slice(),
-    );
-
-    file_map.insert(
-        "defs/01-patches.toml".to_string(),
-        r#"
+    let result = ModFixture::new("missing_source_test")
+        .with_def(
+            "01-patches.toml",
+            r#"
         [patches]
         [patches.bad_patch]
         operation = "merge"
@@ -168,13 +133,9 @@ ztd_type = "openzt"
 
         [patch_meta]
         on_error = "abort"
-    "#
-        .as_bytes()
-        .to_vec()
-        .into_boxed_slice(),
-    );
-
-    let result = crate::resource_manager::openzt_mods::loading::load_open_zt_mod_from_memory(file_map, "missing_source_test", std::path::Path::new("dummy"));
+    "#,
+        )
+        .load();
 
     // Should fail with appropriate error message
     match result {
@@ -187,35 +148,11 @@ ztd_type = "openzt"
 fn test_source_file_wrong_path_error() -> TestResult {
     let test_name = "test_source_file_wrong_path_error";
 
-    // First create the target file so merge can proceed to source file check
-    use crate::resource_manager::lazyresourcemap::add_ztfile_from_memory;
-    use crate::resource_manager::ztfile::{ZTFile, ZTFileType};
-    add_ztfile_from_memory(
-        "test_setup",
-        "animals/target.ai".to_string(),
-        ZTFile::RawBytes(b"[base]\nkey=value".to_vec().into_boxed_slice(), ZTFileType::Ai, 0),
-    )
-    .expect("Failed to create target file");
-
     // Create a mod where source file exists but not under resources/
-    let mut file_map = std::collections::HashMap::new();
-    file_map.insert(
-        "meta.toml".to_string(),
-        r#"name = "Wrong Path Test"
-mod_id = "wrong_path_test"
-version = "0.1.0"
-description = "Test for wrong path to source files"
-authors = ["OpenZT"]
-ztd_type = "openzt"
-"#
-        .as_bytes()
-        .to_vec()
-        .into_boxed_slice(),
-    );
-
-    file_map.insert(
-        "defs/01-patches.toml".to_string(),
-        r#"
+    let result = ModFixture::new("wrong_path_test")
+        .with_def(
+            "01-patches.toml",
+            r#"
         [patches]
         [patches.wrong_path]
         operation = "merge"
@@ -224,16 +161,11 @@ ztd_type = "openzt"
 
         [patch_meta]
         on_error = "abort"
-    "#
-        .as_bytes()
-        .to_vec()
-        .into_boxed_slice(),
-    );
-
-    // File exists but in wrong location (no resources/ prefix)
-    file_map.insert("patches/file.ai".to_string(), b"[wrong]\nkey=value".to_vec().into_boxed_slice());
-
-    let result = crate::resource_manager::openzt_mods::loading::load_open_zt_mod_from_memory(file_map, "wrong_path_test", std::path::Path::new("dummy"));
+    "#,
+        )
+        // File exists but in wrong location (no resources/ prefix)
+        .with_file("patches/file.ai", "[wrong]\nkey=value")
+        .load();
 
     // Should fail because file is not under resources/
     match result {
@@ -242,3 +174,30 @@ ztd_type = "openzt"
         Ok(_) => TestResult::fail(test_name, "Expected error but got success".to_string()),
     }
 }
+
+fn test_merge_from_ztd_file() -> TestResult {
+    let test_name = "test_merge_from_ztd_file";
+
+    // Write the test mod to a real archive and load it like one in /mods/
+    let dir = fixtures::temp_dir(test_name);
+    let path = match test_mod("patch_source_ztd_test").write_ztd(&dir) {
+        Ok(path) => path,
+        Err(e) => return TestResult::fail(test_name, format!("Failed to write archive: {:#}", e)),
+    };
+    let loaded = fixtures::load_ztd(&path);
+    let _ = std::fs::remove_dir_all(&dir);
+    if let Err(e) = loaded {
+        return TestResult::fail(test_name, format!("Failed to load archive: {:#}", e));
+    }
+
+    use crate::resource_manager::lazyresourcemap::get_file;
+    let Some((_, data)) = get_file("animals/testsource.ai") else {
+        return TestResult::fail(test_name, "Target file not found".to_string());
+    };
+    let content = String::from_utf8_lossy(&data);
+    if !content.contains("merged_from_archive") {
+        return TestResult::fail(test_name, format!("Merge content not found. Got: {}", content));
+    }
+
+    TestResult::pass(test_name)
+}
@@ -27,7 +27,7 @@ pub fn test_regex_pattern_matching() -> TestResult {
     // Verify the permitted list contains expected archives
     let permitted_list: Vec<&str> = PERMITTED_ARCHIVE_PATTERNS.iter()
         .filter(|(pattern, _)| pattern.is_match("xpac03.cfg"))
-        .flat_map(|(_, permitted)| permitted.iter().copied())
+        .flat_map(|(_, permitted)| permitted.iter().map(String::as_str))
         .collect();
 
     let expected_archives = vec!["zupdate/config2.ztd", "xpack1/config2.ztd", "xpack2/config3.ztd"];
@@ -0,0 +1,232 @@
+//! Machine-readable results of an integration test run
+//!
+//! When `OPENZT_TEST_RESULTS` is set, the runner writes every test's status, message and
+//! duration to that path once the run is done, so CI and the instance manager's test
+//! farm can read the results instead of scraping the test log. A path ending in `.xml`
+//! gets JUnit XML, any other path gets JSON.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use super::TestResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Result of one test as it is reported
+#[derive(Debug, Clone, Serialize)]
+pub struct TestReport {
+    pub name: String,
+    pub status: TestStatus,
+    /// The failure or skip reason
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub duration_ms: u64,
+    #[serde(skip)]
+    duration: Duration,
+}
+
+impl TestReport {
+    /// `result` of the test registered as `name`
+    pub fn new(name: &str, result: &TestResult) -> Self {
+        let (status, message) = if !result.passed {
+            (TestStatus::Failed, Some(result.error.clone().unwrap_or_else(|| "Unknown error".to_string())))
+        } else if let Some(reason) = &result.skipped {
+            (TestStatus::Skipped, Some(reason.clone()))
+        } else {
+            (TestStatus::Passed, None)
+        };
+        TestReport {
+            name: name.to_string(),
+            status,
+            message,
+            duration_ms: result.duration.as_millis() as u64,
+            duration: result.duration,
+        }
+    }
+}
+
+/// The tests run from one test module
+#[derive(Debug, Clone, Serialize)]
+pub struct SuiteReport {
+    pub module: String,
+    pub description: String,
+    pub tests: Vec<TestReport>,
+}
+
+impl SuiteReport {
+    fn count(&self, status: TestStatus) -> usize {
+        self.tests.iter().filter(|test| test.status == status).count()
+    }
+
+    fn duration(&self) -> Duration {
+        self.tests.iter().map(|test| test.duration).sum()
+    }
+}
+
+/// Results of a whole run
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Tests not run because of `OPENZT_TEST_FILTER`
+    pub filtered: usize,
+    pub duration_ms: u64,
+    pub suites: Vec<SuiteReport>,
+    #[serde(skip)]
+    duration: Duration,
+}
+
+impl RunReport {
+    pub fn new(suites: Vec<SuiteReport>, filtered: usize, duration: Duration) -> Self {
+        let count = |status| suites.iter().map(|suite| suite.count(status)).sum();
+        RunReport {
+            passed: count(TestStatus::Passed),
+            failed: count(TestStatus::Failed),
+            skipped: count(TestStatus::Skipped),
+            filtered,
+            duration_ms: duration.as_millis() as u64,
+            suites,
+            duration,
+        }
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_junit(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"OpenZT integration tests\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            self.passed + self.failed + self.skipped,
+            self.failed,
+            self.skipped,
+            self.duration.as_secs_f64()
+        );
+        for suite in &self.suites {
+            let _ = writeln!(
+                xml,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+                escape_xml(&suite.module),
+                suite.tests.len(),
+                suite.count(TestStatus::Failed),
+                suite.count(TestStatus::Skipped),
+                suite.duration().as_secs_f64()
+            );
+            for test in &suite.tests {
+                let _ = write!(
+                    xml,
+                    "    <testcase name=\"{}\" classname=\"integration_tests.{}\" time=\"{:.3}\"",
+                    escape_xml(&test.name),
+                    escape_xml(&suite.module),
+                    test.duration.as_secs_f64()
+                );
+                let message = escape_xml(test.message.as_deref().unwrap_or_default());
+                match test.status {
+                    TestStatus::Passed => xml.push_str("/>\n"),
+                    TestStatus::Failed => {
+                        let _ = writeln!(xml, ">\n      <failure message=\"{}\"/>\n    </testcase>", message);
+                    }
+                    TestStatus::Skipped => {
+                        let _ = writeln!(xml, ">\n      <skipped message=\"{}\"/>\n    </testcase>", message);
+                    }
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    /// Write the report to `path`, as JUnit XML if it ends in `.xml` and JSON otherwise
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let is_xml = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("xml"));
+        let content = if is_xml { self.to_junit() } else { self.to_json()? };
+        std::fs::write(path, content).with_context(|| format!("Failed to write test results to {}", path.display()))
+    }
+}
+
+/// `text` with the characters XML attribute values can't contain escaped
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> RunReport {
+        let mut passed = TestResult::pass("test_chain");
+        passed.duration = Duration::from_millis(12);
+        let mut failed = TestResult::fail("test_cycle", "expected <a> & \"b\"".to_string());
+        failed.duration = Duration::from_millis(1500);
+        let skipped = TestResult::skip("test_optional", "no game files");
+        let suite = SuiteReport {
+            module: "dependency_resolution".to_string(),
+            description: "dependency resolution".to_string(),
+            tests: vec![
+                TestReport::new("test_chain", &passed),
+                TestReport::new("test_cycle", &failed),
+                TestReport::new("test_optional", &skipped),
+            ],
+        };
+        RunReport::new(vec![suite], 4, Duration::from_millis(1600))
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json: serde_json::Value = serde_json::from_str(&report().to_json().unwrap()).unwrap();
+        assert_eq!(json["passed"], 1);
+        assert_eq!(json["failed"], 1);
+        assert_eq!(json["skipped"], 1);
+        assert_eq!(json["filtered"], 4);
+        let tests = &json["suites"][0]["tests"];
+        assert_eq!(tests[0], serde_json::json!({ "name": "test_chain", "status": "passed", "duration_ms": 12 }));
+        assert_eq!(tests[1]["message"], "expected <a> & \"b\"");
+        assert_eq!(tests[2]["status"], "skipped");
+        assert_eq!(tests[2]["message"], "no game files");
+    }
+
+    #[test]
+    fn test_to_junit() {
+        assert_eq!(
+            report().to_junit(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <testsuites name=\"OpenZT integration tests\" tests=\"3\" failures=\"1\" skipped=\"1\" time=\"1.600\">\n  \
+             <testsuite name=\"dependency_resolution\" tests=\"3\" failures=\"1\" skipped=\"1\" time=\"1.512\">\n    \
+             <testcase name=\"test_chain\" classname=\"integration_tests.dependency_resolution\" time=\"0.012\"/>\n    \
+             <testcase name=\"test_cycle\" classname=\"integration_tests.dependency_resolution\" time=\"1.500\">\n      \
+             <failure message=\"expected &lt;a&gt; &amp; &quot;b&quot;\"/>\n    \
+             </testcase>\n    \
+             <testcase name=\"test_optional\" classname=\"integration_tests.dependency_resolution\" time=\"0.000\">\n      \
+             <skipped message=\"no game files\"/>\n    \
+             </testcase>\n  \
+             </testsuite>\n\
+             </testsuites>\n"
+        );
+    }
+}
@@ -18,6 +18,9 @@ pub mod ztworldmgr;
 
 mod resource_manager;
 
+/// Mod discovery and load order resolution for launchers and mod managers, without starting the game
+pub use resource_manager::launcher;
+
 /// Centralized logging initialization
 pub mod logging;
 
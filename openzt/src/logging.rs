@@ -2,11 +2,21 @@
 //!
 //! This module provides a single entry point for initializing console and logging
 //! across all contexts: main app, integration tests, and reimplementation tests.
+//!
+//! `level` in the `[logging]` section of openzt.toml sets the level for everything,
+//! and `[logging.modules]` overrides it per subsystem, e.g. `resource_manager = "debug"`
+//! or `patches = "info"`. A module matches any log target containing its `::`-separated
+//! path, so `patches` matches `openzt::resource_manager::openzt_mods::patches`, and the
+//! most specific module wins. Each start moves the previous openzt.log to openzt.1.log,
+//! keeping `max_log_files` old logs.
 
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 use std::sync::Mutex;
-use tracing_subscriber::filter::LevelFilter;
+use tracing::Metadata;
+use tracing_subscriber::filter::{FilterFn, LevelFilter};
 
 #[cfg(target_os = "windows")]
 use windows::Win32::System::Console::{AllocConsole, FreeConsole};
@@ -73,9 +83,9 @@ fn init(
     let enable_ansi = enable_ansi_support::enable_ansi_support().is_ok();
 
     #[cfg(not(feature = "integration-tests"))]
-    let level_filter = config.level.to_level_filter();
+    let level_filter = ModuleLevels::new(config.level.to_level_filter(), &config.modules).into_filter();
     #[cfg(feature = "integration-tests")]
-    let level_filter = LevelFilter::TRACE; // Force TRACE level for integration tests
+    let level_filter = ModuleLevels::new(LevelFilter::TRACE, &IndexMap::new()).into_filter(); // Force TRACE level for integration tests
 
     #[cfg(not(feature = "integration-tests"))]
     let log_to_file = config.log_to_file;
@@ -94,7 +104,9 @@ fn init(
 
     // Set up file logging if enabled
     if log_to_file {
-        let log_path = crate::util::get_base_path().join("openzt.log");
+        let log_dir = crate::util::get_base_path();
+        rotate_logs(&log_dir, config.max_log_files);
+        let log_path = log_file_path(&log_dir, 0);
         match std::fs::File::create(&log_path) {
             Ok(log_file) => {
                 // Wrap in non-blocking writer
@@ -103,7 +115,7 @@ fn init(
                 let file_layer = tracing_subscriber::fmt::layer()
                     .with_ansi(false) // No ANSI codes in file
                     .with_writer(non_blocking)
-                    .with_filter(level_filter);
+                    .with_filter(level_filter.clone());
 
                 // Initialize with file layer and appropriate console layer
                 #[cfg(feature = "tui")]
@@ -112,7 +124,7 @@ fn init(
                         let tui_layer = tracing_subscriber::fmt::layer()
                             .with_ansi(false)
                             .with_writer(crate::tui_console::get_tui_writer)
-                            .with_filter(level_filter);
+                            .with_filter(level_filter.clone());
                         tracing_subscriber::registry()
                             .with(file_layer)
                             .with(tui_layer)
@@ -121,7 +133,7 @@ fn init(
                         let console_layer = tracing_subscriber::fmt::layer()
                             .with_ansi(enable_ansi)
                             .with_writer(std::io::stdout)
-                            .with_filter(level_filter);
+                            .with_filter(level_filter.clone());
                         tracing_subscriber::registry()
                             .with(file_layer)
                             .with(console_layer)
@@ -134,7 +146,7 @@ fn init(
                     let console_layer = tracing_subscriber::fmt::layer()
                         .with_ansi(enable_ansi)
                         .with_writer(std::io::stdout)
-                        .with_filter(level_filter);
+                        .with_filter(level_filter.clone());
                     tracing_subscriber::registry()
                         .with(file_layer)
                         .with(console_layer)
@@ -155,7 +167,7 @@ fn init(
                         let tui_layer = tracing_subscriber::fmt::layer()
                             .with_ansi(false)
                             .with_writer(crate::tui_console::get_tui_writer)
-                            .with_filter(level_filter);
+                            .with_filter(level_filter.clone());
                         tracing_subscriber::registry()
                             .with(tui_layer)
                             .init();
@@ -163,7 +175,7 @@ fn init(
                         let console_layer = tracing_subscriber::fmt::layer()
                             .with_ansi(enable_ansi)
                             .with_writer(std::io::stdout)
-                            .with_filter(level_filter);
+                            .with_filter(level_filter.clone());
                         tracing_subscriber::registry()
                             .with(console_layer)
                             .init();
@@ -175,7 +187,7 @@ fn init(
                     let console_layer = tracing_subscriber::fmt::layer()
                         .with_ansi(enable_ansi)
                         .with_writer(std::io::stdout)
-                        .with_filter(level_filter);
+                        .with_filter(level_filter.clone());
                     tracing_subscriber::registry()
                         .with(console_layer)
                         .init();
@@ -193,7 +205,7 @@ fn init(
                 let tui_layer = tracing_subscriber::fmt::layer()
                     .with_ansi(false)
                     .with_writer(crate::tui_console::get_tui_writer)
-                    .with_filter(level_filter);
+                    .with_filter(level_filter.clone());
                 tracing_subscriber::registry()
                     .with(tui_layer)
                     .init();
@@ -201,7 +213,7 @@ fn init(
                 let console_layer = tracing_subscriber::fmt::layer()
                     .with_ansi(enable_ansi)
                     .with_writer(std::io::stdout)
-                    .with_filter(level_filter);
+                    .with_filter(level_filter.clone());
                 tracing_subscriber::registry()
                     .with(console_layer)
                     .init();
@@ -213,7 +225,7 @@ fn init(
             let console_layer = tracing_subscriber::fmt::layer()
                 .with_ansi(enable_ansi)
                 .with_writer(std::io::stdout)
-                .with_filter(level_filter);
+                .with_filter(level_filter.clone());
             tracing_subscriber::registry()
                 .with(console_layer)
                 .init();
@@ -237,6 +249,14 @@ pub struct LoggingConfig {
     /// Log level (default: Warn)
     #[serde(default)]
     pub level: LogLevel,
+
+    /// Previous logs kept as openzt.1.log, openzt.2.log, ..., 0 to overwrite openzt.log (default: 3)
+    #[serde(default = "default_max_log_files")]
+    pub max_log_files: u32,
+
+    /// Log levels per subsystem, overriding `level`, e.g. `resource_manager = "debug"`
+    #[serde(default)]
+    pub modules: IndexMap<String, LogLevel>,
 }
 
 /// Log level setting for OpenZT logging
@@ -270,6 +290,8 @@ impl Default for LoggingConfig {
         LoggingConfig {
             log_to_file: true,
             level: LogLevel::Warn,
+            max_log_files: 3,
+            modules: IndexMap::new(),
         }
     }
 }
@@ -277,3 +299,106 @@ impl Default for LoggingConfig {
 fn default_true() -> bool {
     true
 }
+
+fn default_max_log_files() -> u32 {
+    3
+}
+
+/// Log levels per module, from `[logging.modules]`
+#[derive(Debug, Clone)]
+struct ModuleLevels {
+    default: LevelFilter,
+    /// Module path segments and level, most specific first
+    modules: Vec<(Vec<String>, LevelFilter)>,
+}
+
+impl ModuleLevels {
+    fn new(default: LevelFilter, modules: &IndexMap<String, LogLevel>) -> Self {
+        let mut modules: Vec<(Vec<String>, LevelFilter)> = modules
+            .iter()
+            .map(|(module, level)| (module.split("::").map(str::to_string).collect(), level.to_level_filter()))
+            .collect();
+        modules.sort_by_key(|(segments, _)| std::cmp::Reverse(segments.len()));
+        ModuleLevels { default, modules }
+    }
+
+    /// Level of the most specific module the target is in, or the default level
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let target: Vec<&str> = target.split("::").collect();
+        self.modules
+            .iter()
+            .find(|(segments, _)| target.windows(segments.len()).any(|window| window == segments.as_slice()))
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).fold(self.default, LevelFilter::max)
+    }
+
+    fn into_filter(self) -> FilterFn<impl Fn(&Metadata<'_>) -> bool + Clone> {
+        let max_level = self.max_level();
+        FilterFn::new(move |metadata: &Metadata<'_>| *metadata.level() <= self.level_for(metadata.target())).with_max_level_hint(max_level)
+    }
+}
+
+/// openzt.log, or openzt.<index>.log for older logs
+fn log_file_path(dir: &Path, index: u32) -> PathBuf {
+    match index {
+        0 => dir.join("openzt.log"),
+        index => dir.join(format!("openzt.{}.log", index)),
+    }
+}
+
+/// Move each log to the next index, dropping the oldest so `kept` old logs remain
+fn rotate_logs(dir: &Path, kept: u32) {
+    if kept == 0 {
+        return;
+    }
+    let _ = std::fs::remove_file(log_file_path(dir, kept));
+    for index in (0..kept).rev() {
+        let from = log_file_path(dir, index);
+        if from.exists()
+            && let Err(e) = std::fs::rename(&from, log_file_path(dir, index + 1))
+        {
+            eprintln!("Failed to rotate {}: {}", from.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_levels() {
+        let modules = IndexMap::from([
+            ("resource_manager".to_string(), LogLevel::Debug),
+            ("patches".to_string(), LogLevel::Info),
+            ("openzt::resource_manager::openzt_mods::patches".to_string(), LogLevel::Trace),
+        ]);
+        let levels = ModuleLevels::new(LevelFilter::WARN, &modules);
+
+        assert_eq!(levels.level_for("openzt::resource_manager::legacy_loading"), LevelFilter::DEBUG);
+        assert_eq!(levels.level_for("openzt::resource_manager::openzt_mods::patches"), LevelFilter::TRACE);
+        assert_eq!(levels.level_for("openzt::other::patches"), LevelFilter::INFO);
+        assert_eq!(levels.level_for("openzt::resource_manager_extra"), LevelFilter::WARN);
+        assert_eq!(levels.level_for("openzt::ztworldmgr"), LevelFilter::WARN);
+        assert_eq!(levels.max_level(), LevelFilter::TRACE);
+    }
+
+    #[test]
+    fn test_rotate_logs() {
+        let dir = std::env::temp_dir().join(format!("openzt-logging-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for run in 0..4 {
+            rotate_logs(&dir, 2);
+            std::fs::write(log_file_path(&dir, 0), format!("run {}", run)).unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string(log_file_path(&dir, 0)).unwrap(), "run 3");
+        assert_eq!(std::fs::read_to_string(log_file_path(&dir, 1)).unwrap(), "run 2");
+        assert_eq!(std::fs::read_to_string(log_file_path(&dir, 2)).unwrap(), "run 1");
+        assert!(!log_file_path(&dir, 3).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
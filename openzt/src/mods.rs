@@ -46,11 +46,63 @@ pub struct Meta {
     mod_id: String,
     #[serde(deserialize_with = "deserialize_version")]
     version: Version,
+    /// Oldest OpenZT release the mod works with; older releases refuse to load it
+    #[serde(default, deserialize_with = "deserialize_version_option")]
+    min_openzt_version: Option<Version>,
     #[serde(default)]
     ztd_type: ZtdType,
     link: Option<String>,
+    /// Website of the mod, where `link` is where it can be downloaded
+    homepage: Option<String>,
+    /// License the mod is distributed under, e.g. "CC-BY-4.0"
+    license: Option<String>,
+    /// Image shown for the mod, a path inside the mod
+    icon: Option<String>,
+    /// Categories to filter mod collections by, e.g. "animals" or "scenery"
+    #[serde(default)]
+    tags: Vec<String>,
     #[serde(default = "default_empty_dependencies", deserialize_with = "deserialize_dependencies")]
     dependencies: Vec<Dependencies>,
+    #[serde(default)]
+    conflicts: Vec<Conflict>,
+    /// Values players can change in openzt.toml, by key
+    #[serde(default)]
+    settings: IndexMap<String, ModSetting>,
+}
+
+impl Meta {
+    /// Whether the mod has a tag, ignoring case
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own| own.eq_ignore_ascii_case(tag))
+    }
+
+    /// One line describing the mod, for mod lists
+    pub fn format_summary(&self) -> String {
+        let mut summary = format!("{} {} - {}", self.mod_id, self.version, self.name);
+        if !self.tags.is_empty() {
+            summary.push_str(&format!(" [{}]", self.tags.join(", ")));
+        }
+        summary
+    }
+
+    /// Everything meta.toml says about the mod
+    pub fn format_info(&self) -> String {
+        let mut info = format!("{} {} ({})\n{}\n", self.name, self.version, self.mod_id, self.description);
+        info.push_str(&format!("authors: {}\n", self.authors.join(", ")));
+        let optional_fields = [
+            ("tags", (!self.tags.is_empty()).then(|| self.tags.join(", "))),
+            ("license", self.license.clone()),
+            ("homepage", self.homepage.clone()),
+            ("link", self.link.clone()),
+            ("icon", self.icon.clone()),
+        ];
+        for (field, value) in optional_fields {
+            if let Some(value) = value {
+                info.push_str(&format!("{}: {}\n", field, value));
+            }
+        }
+        info
+    }
 }
 
 fn default_empty_dependencies() -> Vec<Dependencies> {
@@ -293,12 +345,104 @@ pub struct Dependencies {
     ordering: Ordering,
 }
 
+/// A mod that cannot be loaded alongside this one
+///
+/// ```toml
+/// conflicts = [
+///     { mod_id = "finn.other_regions", max_version = "1.2.0", reason = "Both rewrite the region list" }
+/// ]
+/// ```
+#[derive(Deserialize, Clone, Debug, Getters)]
+#[serde(deny_unknown_fields)]
+#[get = "pub"]
+pub struct Conflict {
+    mod_id: String,
+    /// Lowest conflicting version (inclusive); any version if unset
+    #[serde(default, deserialize_with = "deserialize_version_option")]
+    min_version: Option<Version>,
+    /// Highest conflicting version (inclusive); any version if unset
+    #[serde(default, deserialize_with = "deserialize_version_option")]
+    max_version: Option<Version>,
+    reason: Option<String>,
+}
+
+impl Conflict {
+    /// Whether the conflict applies to the given version of the other mod
+    pub fn applies_to(&self, version: &Version) -> bool {
+        self.min_version.as_ref().is_none_or(|min| version >= min) && self.max_version.as_ref().is_none_or(|max| version <= max)
+    }
+}
+
+/// A value of a mod that players can change, used by its patches and scripts
+///
+/// ```toml
+/// [settings.litter_size]
+/// type = "integer"
+/// default = 2
+/// description = "Babies born per litter"
+/// ```
+#[derive(Deserialize, Clone, Debug, Getters)]
+#[serde(deny_unknown_fields)]
+#[get = "pub"]
+pub struct ModSetting {
+    #[serde(rename = "type")]
+    setting_type: SettingType,
+    default: Value,
+    description: String,
+}
+
+#[derive(Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingType {
+    Integer,
+    Float,
+    Boolean,
+    String,
+}
+
+impl SettingType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SettingType::Integer => "integer",
+            SettingType::Float => "float",
+            SettingType::Boolean => "boolean",
+            SettingType::String => "string",
+        }
+    }
+
+    /// `value` as a value of this type, integers are accepted for floats
+    pub fn coerce(&self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (SettingType::Integer, Value::Integer(_)) | (SettingType::Float, Value::Float(_)) => Some(value.clone()),
+            (SettingType::Boolean, Value::Boolean(_)) | (SettingType::String, Value::String(_)) => Some(value.clone()),
+            (SettingType::Float, Value::Integer(integer)) => Some(Value::Float(*integer as f64)),
+            _ => None,
+        }
+    }
+
+    /// Parse a value of this type typed in the console
+    pub fn parse(&self, text: &str) -> Result<Value, ParseError> {
+        let invalid = || ParseError::new(format!("'{}' is not a valid {}", text, self.name()));
+        match self {
+            SettingType::Integer => text.trim().parse().map(Value::Integer).map_err(|_| invalid()),
+            SettingType::Float => text.trim().parse().map(Value::Float).map_err(|_| invalid()),
+            SettingType::Boolean => text.trim().parse().map(Value::Boolean).map_err(|_| invalid()),
+            SettingType::String => Ok(Value::String(text.to_string())),
+        }
+    }
+}
+
+/// Load order constraint between a mod and one of its dependencies
 #[derive(Deserialize, Default, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum Ordering {
+    /// This mod loads before the dependency (e.g. a framework mod ahead of mods patching its output)
     Before,
+    /// This mod loads after the dependency
     After,
+    /// No ordering constraint, the dependency only has to be present; also accepted as "any"
     #[default]
+    #[serde(alias = "any")]
     None,
 }
 
@@ -331,6 +475,24 @@ pub struct ModDefinition {
     #[serde(default)]
     items: Option<HashMap<String, EntityExtension>>,
 
+    // Game string overrides, keyed by string ID and then locale
+    #[serde(default)]
+    strings: Option<HashMap<String, HashMap<String, String>>>,
+
+    // Expansion packs added to the game's expansion dropdown, keyed by an ID unique within the mod
+    #[serde(default)]
+    expansions: Option<HashMap<String, ExpansionDefinition>>,
+
+    // Animations converted from PNG frames, keyed by a name unique within the mod
+    #[serde(default)]
+    sprites: Option<HashMap<String, SpriteDefinition>>,
+
+    // New entity types, expanded into generated .ai files and .cfg entries, keyed by entity name
+    #[serde(default)]
+    new_scenery: Option<HashMap<String, EntityTypeDefinition>>,
+    #[serde(default)]
+    new_animals: Option<HashMap<String, EntityTypeDefinition>>,
+
     // Patch system - split into metadata and patches
     patch_meta: Option<PatchMeta>,
     patches: Option<IndexMap<String, Patch>>, // MUST use IndexMap for order preservation
@@ -449,6 +611,21 @@ impl ModDefinition {
         if let Some(ref ext) = self.items {
             len += ext.len();
         }
+        if let Some(ref strings) = self.strings {
+            len += strings.len();
+        }
+        if let Some(ref expansions) = self.expansions {
+            len += expansions.len();
+        }
+        if let Some(ref sprites) = self.sprites {
+            len += sprites.len();
+        }
+        if let Some(ref new_scenery) = self.new_scenery {
+            len += new_scenery.len();
+        }
+        if let Some(ref new_animals) = self.new_animals {
+            len += new_animals.len();
+        }
         len
     }
 }
@@ -461,6 +638,81 @@ pub struct IconDefinition {
     icon_palette_path: String,
 }
 
+/// An expansion pack declared by a mod, like the ones added by an xpac*.cfg
+#[derive(Deserialize, Debug, Clone, Getters)]
+#[get = "pub"]
+pub struct ExpansionDefinition {
+    /// Name shown in the expansion dropdown
+    name: String,
+    /// Entity names (e.g. "moon_tree") or archive names ending in ".ztd"; the mod's own archive when empty
+    #[serde(default)]
+    members: Vec<String>,
+}
+
+/// An animation converted from PNG frames when the mod is loaded
+#[derive(Deserialize, Debug, Clone, Getters)]
+#[get = "pub"]
+pub struct SpriteDefinition {
+    /// Animation to add or replace, e.g. "objects/moontree/idle/N"
+    target: String,
+    /// PNG files in the mod, one per frame
+    frames: Vec<String>,
+    /// Palette in the resource map to map the colors onto; one is generated at "<target>.pal" when absent
+    #[serde(default)]
+    palette: Option<String>,
+    /// Milliseconds between frames
+    #[serde(default = "default_animation_speed")]
+    animation_speed: u32,
+    /// Point of each frame placed at the entity's position, the center of the frame when absent
+    #[serde(default)]
+    offset_x: Option<u16>,
+    #[serde(default)]
+    offset_y: Option<u16>,
+}
+
+fn default_animation_speed() -> u32 {
+    100
+}
+
+/// A new scenery object or animal, expanded into the .ai file and .cfg entries the game reads
+#[derive(Deserialize, Debug, Clone, Getters)]
+#[get = "pub"]
+pub struct EntityTypeDefinition {
+    /// Name shown in game, registered as a new string
+    name: String,
+    /// Description shown in the buy menu's help, registered as a new string
+    #[serde(default)]
+    help: Option<String>,
+    cost: u32,
+    #[serde(default = "default_footprint")]
+    footprint_x: u32,
+    #[serde(default = "default_footprint")]
+    footprint_y: u32,
+    /// Animation of the buy menu icon, e.g. "objects/moonrock/icon/moonrock/moonrock"
+    icon: String,
+    /// Animation paths by name, e.g. `idle = "objects/moonrock/idle"`; "{subtype}" is replaced for each animal subtype
+    animations: IndexMap<String, String>,
+    /// Animal subtypes, m, f and j when absent
+    #[serde(default)]
+    subtypes: Option<Vec<String>>,
+    /// Habitat of an animal, the name of one of this mod's habitats
+    #[serde(default)]
+    habitat: Option<String>,
+    /// Location of an animal, the name of one of this mod's locations
+    #[serde(default)]
+    location: Option<String>,
+    /// Key of an expansion in the same def file to add the entity to
+    #[serde(default)]
+    expansion: Option<String>,
+    /// Extra keys for the .ai file's [Characteristics/Integers] section
+    #[serde(default)]
+    characteristics: IndexMap<String, String>,
+}
+
+fn default_footprint() -> u32 {
+    1
+}
+
 // ============================================================================
 // Extension System Data Structures
 // ============================================================================
@@ -501,6 +753,13 @@ pub struct PatchMeta {
     #[serde(default = "default_on_error")]
     pub on_error: ErrorHandling,
 
+    /// Priority of these patches when they write an INI key another mod also writes
+    ///
+    /// The higher priority wins regardless of load order; on equal priority the
+    /// later mod wins, subject to `patch_conflict_policy` in openzt.toml (default: 0)
+    #[serde(default)]
+    pub priority: i32,
+
     /// File-level conditions - if these fail, all patches are skipped
     #[serde(default)]
     pub condition: Option<PatchCondition>,
@@ -510,6 +769,7 @@ impl Default for PatchMeta {
     fn default() -> Self {
         PatchMeta {
             on_error: ErrorHandling::Continue,
+            priority: 0,
             condition: None,
         }
     }
@@ -538,6 +798,7 @@ pub enum Patch {
     SetKeys(SetKeysPatch),
     AppendValue(AppendValuePatch),
     AppendValues(AppendValuesPatch),
+    EditList(EditListPatch),
     RemoveKey(RemoveKeyPatch),
     RemoveKeys(RemoveKeysPatch),
     AddSection(AddSectionPatch),
@@ -556,6 +817,25 @@ fn default_merge_mode() -> MergeMode {
     MergeMode::PatchPriority
 }
 
+/// How a merge combines a key found in both files when it is repeated
+///
+/// Some .ai files repeat keys within a section (e.g. `cHabitat`). Only used with
+/// `merge_mode = "patch_priority"`; `base_priority` keeps existing keys as they are.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKeyMode {
+    /// The source's values replace every value of the key in the target
+    #[default]
+    ReplaceAll,
+    /// The source's values replace the first value, later repeats are kept
+    ReplaceFirst,
+    /// The source's values are added after the target's values
+    AppendDuplicate,
+    /// Values are matched by their first comma-separated field; matching values are
+    /// replaced and the rest are added after them
+    KeyedByValue,
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum OnExists {
@@ -579,6 +859,12 @@ pub struct PatchCondition {
     pub target: Option<String>,
     #[serde(default)]
     pub mod_loaded: Option<String>,
+    /// Skip unless this mod is absent from the load order, e.g. for a fallback superseded by another mod's version
+    #[serde(default)]
+    pub mod_not_loaded: Option<String>,
+    /// Check that a file exists in the resource system (e.g. one added by an expansion or another mod)
+    #[serde(default)]
+    pub file_exists: Option<String>,
     #[serde(default)]
     pub key_exists: Option<KeyCheck>,
     #[serde(default)]
@@ -610,6 +896,12 @@ pub struct ReplacePatch {
     pub source: String,
     #[serde(default)]
     pub condition: Option<PatchCondition>,
+    /// Priority of the keys this patch writes against other mods, instead of `[patch_meta] priority`
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Keys owned by a higher-priority mod, left as they are in the target (set while patching)
+    #[serde(skip)]
+    pub kept_keys: Vec<(String, String)>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -619,16 +911,54 @@ pub struct MergePatch {
     #[serde(default = "default_merge_mode")]
     pub merge_mode: MergeMode,
     #[serde(default)]
+    pub duplicate_keys: DuplicateKeyMode,
+    #[serde(default)]
     pub condition: Option<PatchCondition>,
+    /// Priority of the keys this patch writes against other mods, instead of `[patch_meta] priority`
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Keys owned by a higher-priority mod, left as they are in the target (set while patching)
+    #[serde(skip)]
+    pub kept_keys: Vec<(String, String)>,
 }
 
+/// Patch operation to delete a file, or parts of an INI file
+///
+/// With only `target` set, the whole file is removed. Setting `sections`
+/// removes those sections; `section` removes a single section, or only the
+/// listed `keys` within it. This lets a mod strip vanilla entries without
+/// shipping a full replacement of the file.
+/// Both forms may be combined; partial deletes require an INI-compatible target.
+///
+/// # Example
+/// ```toml
+/// [patches.strip_vanilla_behaviour]
+/// operation = "delete"
+/// target = "animals/elephant.ai"
+/// sections = ["cFootprint"]
+/// section = "Characteristics/Integers"
+/// keys = ["cPrefIconID"]
+/// ```
 #[derive(Deserialize, Debug, Clone)]
 pub struct DeletePatch {
     pub target: String,
     #[serde(default)]
+    pub section: Option<String>,
+    #[serde(default)]
+    pub keys: Vec<String>,
+    #[serde(default)]
+    pub sections: Vec<String>,
+    #[serde(default)]
     pub condition: Option<PatchCondition>,
 }
 
+impl DeletePatch {
+    /// Whether this patch removes the whole target file rather than keys or sections
+    pub fn deletes_file(&self) -> bool {
+        self.section.is_none() && self.keys.is_empty() && self.sections.is_empty()
+    }
+}
+
 /// Patch operation to change the palette file reference in an animation file
 ///
 /// This operation modifies the palette filename stored inside an animation file's
@@ -678,6 +1008,9 @@ pub struct SetKeyPatch {
     pub value: String,
     #[serde(default)]
     pub condition: Option<PatchCondition>,
+    /// Priority of the keys this patch writes against other mods, instead of `[patch_meta] priority`
+    #[serde(default)]
+    pub priority: Option<i32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -687,6 +1020,9 @@ pub struct SetKeysPatch {
     pub keys: HashMap<String, String>,
     #[serde(default)]
     pub condition: Option<PatchCondition>,
+    /// Priority of the keys this patch writes against other mods, instead of `[patch_meta] priority`
+    #[serde(default)]
+    pub priority: Option<i32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -709,6 +1045,53 @@ pub struct AppendValuesPatch {
     pub condition: Option<PatchCondition>,
 }
 
+/// Patch operation to edit a list stored in a single INI value (e.g. `a, b, c`)
+///
+/// Unlike `append_value`, which adds another line for a repeated key, this
+/// edits the items of one separated value, so several mods can each add
+/// their own entries to the same list without overwriting each other.
+///
+/// # Example
+/// ```toml
+/// [patches.add_languages]
+/// operation = "edit_list"
+/// target = "config/settings.ini"
+/// section = "Language"
+/// key = "Available"
+/// action = "append"
+/// values = ["klingon"]
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+pub struct EditListPatch {
+    pub target: String,
+    pub section: String,
+    pub key: String,
+    pub action: ListAction,
+    #[serde(default)]
+    pub values: Vec<String>,
+    #[serde(default = "default_list_separator")]
+    pub separator: String,
+    #[serde(default)]
+    pub condition: Option<PatchCondition>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ListAction {
+    /// Add `values` to the end of the list
+    Append,
+    /// Add `values` to the start of the list
+    Prepend,
+    /// Remove every occurrence of `values` from the list
+    Remove,
+    /// Drop repeated items, keeping the first occurrence
+    Dedupe,
+}
+
+fn default_list_separator() -> String {
+    ",".to_string()
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct RemoveKeyPatch {
     pub target: String,
@@ -716,6 +1099,9 @@ pub struct RemoveKeyPatch {
     pub key: String,
     #[serde(default)]
     pub condition: Option<PatchCondition>,
+    /// Priority of the keys this patch writes against other mods, instead of `[patch_meta] priority`
+    #[serde(default)]
+    pub priority: Option<i32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -725,6 +1111,9 @@ pub struct RemoveKeysPatch {
     pub keys: Vec<String>,
     #[serde(default)]
     pub condition: Option<PatchCondition>,
+    /// Priority of the keys this patch writes against other mods, instead of `[patch_meta] priority`
+    #[serde(default)]
+    pub priority: Option<i32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -737,6 +1126,9 @@ pub struct AddSectionPatch {
     pub on_exists: OnExists,
     #[serde(default)]
     pub condition: Option<PatchCondition>,
+    /// Priority of the keys this patch writes against other mods, instead of `[patch_meta] priority`
+    #[serde(default)]
+    pub priority: Option<i32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -790,6 +1182,11 @@ impl ModDefinition {
             staff: None,
             guests: None,
             items: None,
+            strings: None,
+            expansions: None,
+            sprites: None,
+            new_scenery: None,
+            new_animals: None,
             patch_meta,
             patches,
         }
@@ -810,6 +1207,13 @@ mod mod_loading_tests {
         assert_eq!(meta.version.minor, 0);
         assert_eq!(meta.version.patch, 0);
         assert_eq!(meta.link, Some("https://mywebsite.com/myfunmod".to_string()));
+        assert_eq!(meta.homepage, Some("https://mywebsite.com".to_string()));
+        assert_eq!(meta.license.as_deref(), Some("CC-BY-4.0"));
+        assert_eq!(meta.icon.as_deref(), Some("resources/icon.png"));
+        assert_eq!(meta.tags, vec!["Animals".to_string(), "scenery".to_string()]);
+        assert!(meta.has_tag("animals"));
+        assert!(!meta.has_tag("buildings"));
+        assert_eq!(meta.min_openzt_version, Some(Version { major: 0, minor: 1, patch: 0 }));
         assert_eq!(meta.dependencies.len(), 1);
         assert_eq!(meta.ztd_type, super::ZtdType::Combined);
         let dep = meta.dependencies[0].clone();
@@ -820,6 +1224,96 @@ mod mod_loading_tests {
         assert_eq!(dep.ordering(), &super::Ordering::Before);
     }
 
+    #[test]
+    fn test_format_meta() {
+        let meta: super::Meta = toml::from_str(include_str!("../resources/test/meta.toml")).unwrap();
+        assert_eq!(meta.format_summary(), "finn.my_fun_mod 1.0.0 - my fun mod [Animals, scenery]");
+        assert_eq!(
+            meta.format_info(),
+            "my fun mod 1.0.0 (finn.my_fun_mod)\na mod full of fun\nauthors: Finn\ntags: Animals, scenery\nlicense: CC-BY-4.0\n\
+             homepage: https://mywebsite.com\nlink: https://mywebsite.com/myfunmod\nicon: resources/icon.png\n"
+        );
+
+        let meta: super::Meta = toml::from_str("name = \"m\"\ndescription = \"d\"\nauthors = []\nmod_id = \"finn.m\"\nversion = \"1.0.0\"\n").unwrap();
+        assert_eq!(meta.format_summary(), "finn.m 1.0.0 - m");
+        assert_eq!(meta.format_info(), "m 1.0.0 (finn.m)\nd\nauthors: \n");
+    }
+
+    #[test]
+    fn test_parse_dependency_ordering() {
+        let parse = |ordering: &str| -> super::Ordering {
+            let dep: super::Dependencies =
+                toml::from_str(&format!("mod_id = \"finn.my_other_mod\"\nname = \"my other mod\"\nordering = \"{}\"", ordering)).unwrap();
+            dep.ordering().clone()
+        };
+        assert_eq!(parse("before"), super::Ordering::Before);
+        assert_eq!(parse("after"), super::Ordering::After);
+        assert_eq!(parse("none"), super::Ordering::None);
+        assert_eq!(parse("any"), super::Ordering::None);
+    }
+
+    #[test]
+    fn test_parse_conflicts() {
+        let meta: super::Meta = toml::from_str(
+            r#"
+            name = "my fun mod"
+            description = "a mod full of fun"
+            authors = ["Finn"]
+            mod_id = "finn.my_fun_mod"
+            version = "1.0.0"
+            conflicts = [
+                { mod_id = "finn.other_regions", min_version = "1.0.0", max_version = "1.2.0", reason = "Both rewrite the region list" },
+                { mod_id = "finn.any_version" }
+            ]
+        "#,
+        )
+        .unwrap();
+        assert_eq!(meta.conflicts.len(), 2);
+
+        let ranged = &meta.conflicts[0];
+        assert_eq!(ranged.reason().as_deref(), Some("Both rewrite the region list"));
+        assert!(ranged.applies_to(&Version { major: 1, minor: 2, patch: 0 }));
+        assert!(!ranged.applies_to(&Version { major: 1, minor: 2, patch: 1 }));
+        assert!(!ranged.applies_to(&Version { major: 0, minor: 9, patch: 0 }));
+
+        assert!(meta.conflicts[1].applies_to(&Version { major: 9, minor: 0, patch: 0 }));
+    }
+
+    #[test]
+    fn test_parse_settings() {
+        let meta: super::Meta = toml::from_str(
+            r#"
+            name = "my fun mod"
+            description = "a mod full of fun"
+            authors = ["Finn"]
+            mod_id = "finn.my_fun_mod"
+            version = "1.0.0"
+
+            [settings.litter_size]
+            type = "integer"
+            default = 2
+            description = "Babies born per litter"
+
+            [settings.speed]
+            type = "float"
+            default = 1
+            description = "Walking speed"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(meta.settings.keys().collect::<Vec<_>>(), vec!["litter_size", "speed"]);
+
+        let speed = meta.settings["speed"].setting_type();
+        assert_eq!(speed.coerce(&toml::Value::Integer(1)), Some(toml::Value::Float(1.0)));
+        assert_eq!(speed.coerce(&toml::Value::Boolean(true)), None);
+        assert_eq!(speed.parse(" 1.5").unwrap(), toml::Value::Float(1.5));
+        assert_eq!(super::SettingType::Boolean.parse("true").unwrap(), toml::Value::Boolean(true));
+        assert_eq!(
+            super::SettingType::Integer.parse("two").unwrap_err().to_string(),
+            "ParseError: 'two' is not a valid integer"
+        );
+    }
+
     #[test]
     fn test_parse_meta_zb() {
         // Test that empty dependency objects are skipped with a warning
@@ -909,7 +1403,7 @@ dependencies = [
         let patch_meta = mod_def.patch_meta.expect("patch_meta should be present");
         let patches = mod_def.patches.expect("patches should be present");
 
-        assert_eq!(patches.len(), 10);
+        assert_eq!(patches.len(), 11);
 
         // Test file-level config
         assert_eq!(patch_meta.on_error, super::ErrorHandling::Continue);
@@ -922,6 +1416,7 @@ dependencies = [
                 assert_eq!(patch.target, "animals/blckbuck.ai");
                 assert_eq!(patch.source, "resources/patches/blckbuck.ai");
                 assert_eq!(patch.merge_mode, super::MergeMode::PatchPriority);
+                assert_eq!(patch.duplicate_keys, super::DuplicateKeyMode::ReplaceAll);
                 assert!(patch.condition.is_none());
             }
             _ => panic!("Expected Merge patch"),
@@ -943,6 +1438,7 @@ dependencies = [
         match delete_patch {
             super::Patch::Delete(patch) => {
                 assert_eq!(patch.target, "animals/oldanimal.ai");
+                assert!(patch.deletes_file());
                 assert!(patch.condition.is_none());
             }
             _ => panic!("Expected Delete patch"),
@@ -1045,6 +1541,20 @@ dependencies = [
             _ => panic!("Expected SetPalette patch"),
         }
 
+        // Test edit_list patch (separator defaults to a comma)
+        let edit_list_patch = patches.get("add_languages").expect("add_languages patch not found");
+        match edit_list_patch {
+            super::Patch::EditList(patch) => {
+                assert_eq!(patch.target, "config/settings.ini");
+                assert_eq!(patch.section, "Language");
+                assert_eq!(patch.key, "Available");
+                assert_eq!(patch.action, super::ListAction::Append);
+                assert_eq!(patch.values, vec!["klingon", "elvish"]);
+                assert_eq!(patch.separator, ",");
+            }
+            _ => panic!("Expected EditList patch"),
+        }
+
         // Test that patches are ordered correctly (IndexMap preserves insertion order)
         let patch_names: Vec<_> = patches.keys().collect();
         assert_eq!(patch_names[0], "merge_blackbuck_ai");
@@ -1057,6 +1567,7 @@ dependencies = [
         assert_eq!(patch_names[7], "add_section_with_on_exists");
         assert_eq!(patch_names[8], "set_elephant_palette");
         assert_eq!(patch_names[9], "conditional_palette_swap");
+        assert_eq!(patch_names[10], "add_languages");
     }
 
     #[test]
@@ -1113,4 +1624,26 @@ dependencies = [
         assert!(all_extensions.contains_key("fences.wood"));
         assert!(all_extensions.contains_key("buildings.restaurant"));
     }
+
+    #[test]
+    fn test_parse_expansions() {
+        let mod_def: super::ModDefinition = toml::from_str(
+            r#"
+[expansions.lunar]
+name = "Lunar Pack"
+
+[expansions.moon_trees]
+name = "Moon Trees"
+members = ["moon_tree", "moon_shrub", "extra_trees.ztd"]
+"#,
+        )
+        .unwrap();
+        let expansions = mod_def.expansions.as_ref().expect("expansions should be present");
+        assert_eq!(mod_def.len(), 2);
+
+        let lunar = expansions.get("lunar").unwrap();
+        assert_eq!(lunar.name(), "Lunar Pack");
+        assert!(lunar.members().is_empty());
+        assert_eq!(expansions.get("moon_trees").unwrap().members().len(), 3);
+    }
 }
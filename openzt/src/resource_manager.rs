@@ -1,13 +1,26 @@
 pub(crate) mod bfresourcemgr;
+mod checksums;
 mod commands;
+mod file_overrides;
 mod handlers;
 mod hooks;
+pub mod launcher;
 pub(crate) mod lazyresourcemap;
 #[cfg(feature = "integration-tests")]
 pub mod legacy_loading;
 #[cfg(not(feature = "integration-tests"))]
 mod legacy_loading;
+mod load_order_preset;
+mod load_profile;
+mod load_report;
+mod mod_list;
+mod mod_toggle;
+#[cfg(feature = "experimental")]
+mod mod_updates;
 pub(crate) mod openzt_mods;
+mod parse_cache;
+pub(crate) mod save_mods;
+mod signatures;
 mod ztd;
 pub(crate) mod ztfile;
 
@@ -41,4 +54,10 @@ pub use ztfile::{modify_ztfile_as_animation, modify_ztfile_as_ini};
 pub fn init() {
     init_hooks();
     init_commands();
+    load_report::init();
+    mod_list::init();
+    if cfg!(feature = "experimental") {
+        openzt_mods::scripts::init();
+        save_mods::init();
+    }
 }
@@ -0,0 +1,439 @@
+//! SHA-256 checksums of the archives in /mods/
+//!
+//! When `[mod_loading.checksums]` in openzt.toml lists an archive, it is hashed as it
+//! is read and the hash must match before the archive is loaded; `checksum_policy`
+//! decides whether a mismatch is only logged or the archive is skipped. Other archives
+//! are hashed on a background thread once loading has finished, for the mod lists
+//! written next to save games. The `mod_checksums()` console command prints the hashes
+//! of the loaded archives ready to paste into openzt.toml, with a fingerprint of the
+//! whole mod set that multiplayer participants can compare.
+//!
+//! An unpacked mod directory is listed under its directory name, and its checksum covers
+//! the path and contents of every file in it. Checksums are only checked with the
+//! `experimental` feature.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use indexmap::IndexMap;
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+
+use crate::resource_manager::{
+    mod_config::{get_openzt_config, ChecksumPolicy},
+    openzt_mods::hot_reload::read_mod_dir,
+};
+
+/// Outcome of checking an archive against `[mod_loading.checksums]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    Verified,
+    /// No checksum is configured for the archive
+    Unlisted,
+    Mismatch {
+        expected: String,
+    },
+    /// The archive could not be hashed
+    Unreadable(String),
+}
+
+/// An archive checked during loading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveChecksum {
+    /// Archive file name, lowercased
+    pub archive: String,
+    pub path: PathBuf,
+    /// `None` until the archive is hashed, unlisted archives aren't hashed while loading
+    pub sha256: Option<String>,
+    pub status: ChecksumStatus,
+    pub loaded: bool,
+}
+
+static CHECKED: LazyLock<Mutex<Vec<ArchiveChecksum>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Hex-encoded SHA-256 of everything left in a reader
+pub fn sha256_reader(reader: &mut impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hex-encoded SHA-256 of a file
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    Ok(sha256_reader(&mut BufReader::new(File::open(path)?))?)
+}
+
+/// Hex-encoded SHA-256 of an unpacked mod directory's files, keyed by their path in the mod
+///
+/// Files are hashed in path order, each as its path, length and contents.
+pub fn sha256_files(files: &HashMap<String, Arc<[u8]>>) -> String {
+    let mut names: Vec<&String> = files.keys().collect();
+    names.sort();
+
+    let mut hasher = Sha256::new();
+    for name in names {
+        let data = &files[name];
+        hasher.update(format!("{}\n{}\n", name, data.len()));
+        hasher.update(data);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Hex-encoded SHA-256 of an archive, or of the files in an unpacked mod directory
+pub fn sha256_path(path: &Path) -> anyhow::Result<String> {
+    if path.is_dir() {
+        Ok(sha256_files(&read_mod_dir(path)?))
+    } else {
+        sha256_file(path)
+    }
+}
+
+/// Normalise a configured checksum: lowercase, without an optional "sha256:" prefix
+fn normalize(checksum: &str) -> String {
+    let checksum = checksum.trim().to_lowercase();
+    checksum.strip_prefix("sha256:").unwrap_or(&checksum).to_string()
+}
+
+/// The configured checksum for an archive, matching the file name case-insensitively
+fn expected_checksum(checksums: &IndexMap<String, String>, archive: &str) -> Option<String> {
+    checksums
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(archive))
+        .map(|(_, checksum)| normalize(checksum))
+}
+
+/// Whether an archive in /mods/ has a configured checksum, and so is hashed while it is read
+pub fn is_listed(resource: &Path) -> bool {
+    let archive = resource.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    expected_checksum(&get_openzt_config().mod_loading.checksums, archive).is_some()
+}
+
+/// `strict` only refuses unlisted archives once at least one checksum is configured
+fn allows_unlisted(checksums: &IndexMap<String, String>, policy: ChecksumPolicy) -> bool {
+    policy != ChecksumPolicy::Strict || checksums.is_empty()
+}
+
+/// Check an archive's hash against the configured checksums
+///
+/// Returns the archive's status and whether it should be loaded under `policy`.
+pub fn evaluate(checksums: &IndexMap<String, String>, policy: ChecksumPolicy, archive: &str, sha256: &Result<String, String>) -> (ChecksumStatus, bool) {
+    let expected = expected_checksum(checksums, archive);
+    let allow_unlisted = allows_unlisted(checksums, policy);
+
+    match (sha256, expected) {
+        (Ok(sha256), Some(expected)) if *sha256 == expected => (ChecksumStatus::Verified, true),
+        (Ok(_), Some(expected)) => (ChecksumStatus::Mismatch { expected }, policy == ChecksumPolicy::Warn),
+        (Ok(_), None) => (ChecksumStatus::Unlisted, allow_unlisted),
+        (Err(e), Some(_)) => (ChecksumStatus::Unreadable(e.clone()), policy == ChecksumPolicy::Warn),
+        (Err(e), None) => (ChecksumStatus::Unreadable(e.clone()), allow_unlisted),
+    }
+}
+
+/// Check an archive from /mods/ using the configured checksums and policy
+///
+/// `sha256` is `None` for archives that weren't hashed because they aren't listed.
+/// The result is recorded for [`report`]. Returns whether the archive should be loaded.
+pub fn verify(resource: &Path, sha256: Option<anyhow::Result<String>>) -> bool {
+    let config = get_openzt_config().mod_loading;
+    let archive = resource.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_lowercase();
+    let Some(sha256) = sha256 else {
+        let loaded = allows_unlisted(&config.checksums, config.checksum_policy);
+        if !loaded {
+            error!("Skipping '{}': no checksum in openzt.toml (checksum_policy = \"strict\")", archive);
+        }
+        CHECKED.lock().unwrap().push(ArchiveChecksum {
+            archive,
+            path: resource.to_path_buf(),
+            sha256: None,
+            status: ChecksumStatus::Unlisted,
+            loaded,
+        });
+        return loaded;
+    };
+    let sha256 = sha256.map_err(|e| format!("{:#}", e));
+    let (status, loaded) = evaluate(&config.checksums, config.checksum_policy, &archive, &sha256);
+
+    match &status {
+        ChecksumStatus::Verified => info!("Checksum verified for '{}'", archive),
+        ChecksumStatus::Unlisted if !loaded => error!("Skipping '{}': no checksum in openzt.toml (checksum_policy = \"strict\")", archive),
+        ChecksumStatus::Unlisted => {}
+        ChecksumStatus::Mismatch { expected } => {
+            let actual = sha256.as_deref().unwrap_or_default();
+            if loaded {
+                warn!("Checksum mismatch for '{}': expected {}, found {}", archive, expected, actual);
+            } else {
+                error!("Skipping '{}': checksum mismatch, expected {}, found {}", archive, expected, actual);
+            }
+        }
+        ChecksumStatus::Unreadable(e) if !loaded => error!("Skipping '{}': could not compute its checksum: {}", archive, e),
+        ChecksumStatus::Unreadable(e) => warn!("Could not compute the checksum of '{}': {}", archive, e),
+    }
+
+    CHECKED.lock().unwrap().push(ArchiveChecksum {
+        archive,
+        path: resource.to_path_buf(),
+        sha256: sha256.ok(),
+        status,
+        loaded,
+    });
+    loaded
+}
+
+/// Fingerprint of a mod set: the SHA-256 of its sorted "archive:sha256" lines
+///
+/// Independent of load order, so two players with the same archives get the same fingerprint.
+pub fn fingerprint(checked: &[ArchiveChecksum]) -> String {
+    let mut lines: Vec<String> = checked
+        .iter()
+        .filter(|checked| checked.loaded)
+        .filter_map(|checked| checked.sha256.as_ref().map(|sha256| format!("{}:{}\n", checked.archive, sha256)))
+        .collect();
+    lines.sort();
+
+    let mut hasher = Sha256::new();
+    for line in lines {
+        hasher.update(line.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Format the checksum report, with the checksums as a `[mod_loading.checksums]` table
+pub fn format_report(checked: &[ArchiveChecksum]) -> String {
+    let loaded = checked.iter().filter(|checked| checked.loaded).count();
+
+    let mut report = String::new();
+    let _ = writeln!(report, "Mod set fingerprint: {} ({} archives)", fingerprint(checked), loaded);
+    let _ = writeln!(report, "\n[mod_loading.checksums]");
+    for checked in checked {
+        let note = match &checked.status {
+            ChecksumStatus::Verified => "verified".to_string(),
+            ChecksumStatus::Unlisted => "not in openzt.toml".to_string(),
+            ChecksumStatus::Mismatch { expected } => format!("MISMATCH, openzt.toml has {}", expected),
+            ChecksumStatus::Unreadable(e) => format!("unreadable: {}", e),
+        };
+        let skipped = if checked.loaded { "" } else { ", skipped" };
+        match &checked.sha256 {
+            Some(sha256) => {
+                let _ = writeln!(report, "\"{}\" = \"{}\"  # {}{}", checked.archive, sha256, note, skipped);
+            }
+            None => {
+                let _ = writeln!(report, "# \"{}\"  # {}{}", checked.archive, note, skipped);
+            }
+        }
+    }
+
+    report
+}
+
+/// Whether an archive wasn't hashed while loading and hasn't been hashed since
+fn needs_hash(checked: &ArchiveChecksum) -> bool {
+    checked.sha256.is_none() && checked.status == ChecksumStatus::Unlisted
+}
+
+fn record_hash(checked: &mut ArchiveChecksum, sha256: anyhow::Result<String>) {
+    match sha256 {
+        Ok(sha256) => checked.sha256 = Some(sha256),
+        Err(e) => checked.status = ChecksumStatus::Unreadable(format!("{:#}", e)),
+    }
+}
+
+/// Hash the checked archives that weren't hashed while loading
+fn hash_unlisted(checked: &mut [ArchiveChecksum]) {
+    for checked in checked.iter_mut().filter(|checked| needs_hash(checked)) {
+        let sha256 = sha256_path(&checked.path);
+        record_hash(checked, sha256);
+    }
+}
+
+/// Hash the archives that weren't hashed while loading on a background thread, then log the fingerprint
+///
+/// Archives are hashed without holding the lock, so saving a zoo never waits on them.
+fn hash_unlisted_in_background(paths: Vec<PathBuf>) {
+    std::thread::spawn(move || {
+        for path in paths {
+            let sha256 = sha256_path(&path);
+            let mut checked = CHECKED.lock().unwrap();
+            if let Some(checked) = checked.iter_mut().find(|checked| checked.path == path && needs_hash(checked)) {
+                record_hash(checked, sha256);
+            }
+        }
+        log_fingerprint(&CHECKED.lock().unwrap());
+    });
+}
+
+/// Checksum report for the archives loaded so far
+pub fn report() -> String {
+    let mut checked = CHECKED.lock().unwrap();
+    hash_unlisted(&mut checked);
+    format_report(&checked)
+}
+
+/// The (archive, sha256) of the loaded archives from /mods/ hashed so far, without hashing any
+///
+/// Archives without a configured checksum are hashed in the background once loading has finished.
+pub fn loaded_archives() -> Vec<(String, String)> {
+    hashed_archives(CHECKED.lock().unwrap().iter())
+}
+
+/// The (archive, sha256) of the loaded archives with a configured checksum, which are hashed while loading
+pub fn verified_archives() -> Vec<(String, String)> {
+    hashed_archives(CHECKED.lock().unwrap().iter().filter(|checked| checked.status != ChecksumStatus::Unlisted))
+}
+
+fn hashed_archives<'a>(checked: impl Iterator<Item = &'a ArchiveChecksum>) -> Vec<(String, String)> {
+    checked
+        .filter(|checked| checked.loaded)
+        .filter_map(|checked| checked.sha256.as_ref().map(|sha256| (checked.archive.clone(), sha256.clone())))
+        .collect()
+}
+
+/// Log the mod set fingerprint if every archive was hashed
+fn log_fingerprint(checked: &[ArchiveChecksum]) {
+    if checked.iter().all(|checked| checked.sha256.is_some()) {
+        info!("Mod set fingerprint: {} (run mod_checksums() in the console for details)", fingerprint(checked));
+    }
+}
+
+/// Log the archives skipped because of their checksums, and start hashing the archives that weren't hashed while loading
+///
+/// Called once mod loading has finished. The mod set fingerprint is logged once every archive is hashed.
+pub fn finish() {
+    let checked = CHECKED.lock().unwrap();
+    if checked.is_empty() {
+        return;
+    }
+
+    let skipped = checked.iter().filter(|checked| !checked.loaded).count();
+    if skipped > 0 {
+        warn!("{} archives in /mods/ were skipped because of their checksums", skipped);
+    }
+    let unhashed: Vec<PathBuf> = checked.iter().filter(|checked| needs_hash(checked)).map(|checked| checked.path.clone()).collect();
+    if unhashed.is_empty() {
+        log_fingerprint(&checked);
+    } else {
+        hash_unlisted_in_background(unhashed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_manager::mod_config::ChecksumPolicy::{Block, Strict, Warn};
+
+    const HASH: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn checksums(entries: &[(&str, &str)]) -> IndexMap<String, String> {
+        entries.iter().map(|(name, checksum)| (name.to_string(), checksum.to_string())).collect()
+    }
+
+    fn checked(archive: &str, sha256: &str) -> ArchiveChecksum {
+        ArchiveChecksum {
+            archive: archive.to_string(),
+            path: PathBuf::from(archive),
+            sha256: Some(sha256.to_string()),
+            status: ChecksumStatus::Unlisted,
+            loaded: true,
+        }
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let path = std::env::temp_dir().join(format!("openzt-checksum-test-{}", std::process::id()));
+        std::fs::write(&path, "test").unwrap();
+        assert_eq!(sha256_file(&path).unwrap(), HASH);
+        let _ = std::fs::remove_file(&path);
+        assert!(sha256_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_sha256_mod_dir() {
+        let dir = std::env::temp_dir().join(format!("openzt-checksum-dir-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("defs")).unwrap();
+        std::fs::write(dir.join("meta.toml"), "name = \"Moon\"\n").unwrap();
+        std::fs::write(dir.join("defs").join("moon.toml"), "[habitats]\n").unwrap();
+
+        let sha256 = sha256_path(&dir).unwrap();
+        assert_eq!(sha256, sha256_files(&read_mod_dir(&dir).unwrap()));
+
+        std::fs::write(dir.join("defs").join("moon.toml"), "[habitats.moon]\n").unwrap();
+        assert_ne!(sha256, sha256_path(&dir).unwrap());
+        std::fs::rename(dir.join("defs").join("moon.toml"), dir.join("defs").join("sun.toml")).unwrap();
+        assert_ne!(sha256, sha256_path(&dir).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_hash_unlisted() {
+        let path = std::env::temp_dir().join(format!("openzt-checksum-unlisted-test-{}", std::process::id()));
+        std::fs::write(&path, "test").unwrap();
+        let unhashed = |path: &Path| ArchiveChecksum {
+            path: path.to_path_buf(),
+            sha256: None,
+            ..checked("a.ztd", HASH)
+        };
+
+        let mut checked = [unhashed(&path), unhashed(&path.with_extension("missing"))];
+        hash_unlisted(&mut checked);
+        assert_eq!(checked[0].sha256.as_deref(), Some(HASH));
+        assert_eq!(checked[0].status, ChecksumStatus::Unlisted);
+        assert_eq!(checked[1].sha256, None);
+        assert!(matches!(checked[1].status, ChecksumStatus::Unreadable(_)));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_evaluate_policies() {
+        let configured = checksums(&[("MyMod.ztd", &format!("sha256:{}", HASH.to_uppercase()))]);
+        let good = Ok(HASH.to_string());
+        let bad = Ok("0".repeat(64));
+
+        assert_eq!(evaluate(&configured, Block, "mymod.ztd", &good), (ChecksumStatus::Verified, true));
+
+        let mismatch = ChecksumStatus::Mismatch { expected: HASH.to_string() };
+        assert_eq!(evaluate(&configured, Warn, "mymod.ztd", &bad), (mismatch.clone(), true));
+        assert_eq!(evaluate(&configured, Block, "mymod.ztd", &bad), (mismatch, false));
+
+        // Unlisted archives are only refused by strict, and only once checksums are configured
+        assert_eq!(evaluate(&configured, Block, "other.ztd", &bad), (ChecksumStatus::Unlisted, true));
+        assert_eq!(evaluate(&configured, Strict, "other.ztd", &bad), (ChecksumStatus::Unlisted, false));
+        assert_eq!(evaluate(&IndexMap::new(), Strict, "other.ztd", &bad), (ChecksumStatus::Unlisted, true));
+
+        let unreadable = Err("missing".to_string());
+        assert!(!evaluate(&configured, Block, "mymod.ztd", &unreadable).1);
+        assert!(evaluate(&configured, Block, "other.ztd", &unreadable).1);
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_order_and_skipped_archives() {
+        let a = checked("a.ztd", HASH);
+        let b = checked("b.ztd", &"0".repeat(64));
+        let skipped = ArchiveChecksum {
+            loaded: false,
+            ..checked("c.ztd", HASH)
+        };
+
+        assert_eq!(fingerprint(&[a.clone(), b.clone()]), fingerprint(&[b.clone(), skipped, a.clone()]));
+        assert_ne!(fingerprint(&[a.clone(), b]), fingerprint(&[a]));
+    }
+
+    #[test]
+    fn test_report_is_pasteable_toml() {
+        let mismatch = ArchiveChecksum {
+            status: ChecksumStatus::Mismatch { expected: "0".repeat(64) },
+            loaded: false,
+            ..checked("b.ztd", HASH)
+        };
+        let report = format_report(&[checked("a.ztd", HASH), mismatch]);
+        assert!(report.starts_with("Mod set fingerprint: "));
+        assert!(report.contains(&format!("\"a.ztd\" = \"{}\"  # not in openzt.toml\n", HASH)));
+        assert!(report.contains(", skipped\n"));
+
+        let table = report.split_once('\n').unwrap().1;
+        let parsed: toml::Table = toml::from_str(table).unwrap();
+        assert_eq!(parsed["mod_loading"]["checksums"]["a.ztd"].as_str(), Some(HASH));
+    }
+}
@@ -1,11 +1,26 @@
+use std::path::Path;
+
+#[cfg(feature = "experimental")]
+use crate::resource_manager::mod_updates;
 use crate::{
     command_console::CommandError,
+    encoding_utils::decode_game_text,
     globals::globals,
     lua_fn,
     resource_manager::{
         bfresourcemgr::read_bf_resource_dir_contents_from_memory,
-        lazyresourcemap::{decrement_ref, get_cache_stats, get_file_names, get_ref_count, increment_ref, unload_all_resources, UnloadResult},
-        openzt_mods::{get_location_habitat_ids, get_mod_ids},
+        checksums, file_overrides,
+        lazyresourcemap::{decrement_ref, get_cache_stats, get_file, get_file_names, get_ref_count, increment_ref, unload_all_resources, UnloadResult},
+        load_order_preset, load_profile, load_report, mod_list, mod_toggle,
+        openzt_mods::{
+            get_location_habitat_ids, hot_reload,
+            loading::{get_mod_meta, get_mod_metas},
+            mod_settings, patch_conflicts, patch_dry_run,
+            patches::glob_matches,
+            scripts,
+        },
+        save_mods,
+        ztfile::ZTFileType,
     },
     string_registry::get_string_from_registry,
     util::ZTString,
@@ -60,13 +75,31 @@ pub fn init_commands() {
         }
     );
 
-    // list_openzt_mods() - no args
-    lua_fn!("list_openzt_mods", "Lists all OpenZT mod IDs", "list_openzt_mods()", || {
-        match command_list_openzt_mod_ids(vec![]) {
-            Ok(result) => Ok((Some(result), None::<String>)),
-            Err(e) => Ok((None::<String>, Some(e.to_string()))),
+    // list_openzt_mods([tag]) - optional string arg
+    lua_fn!(
+        "list_openzt_mods",
+        "Lists the loaded OpenZT mods, or only those with a tag, e.g. \"animals\"",
+        "list_openzt_mods([tag])",
+        |tag: Option<String>| {
+            match command_list_openzt_mods(tag.as_deref()) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(e.to_string()))),
+            }
         }
-    });
+    );
+
+    // mod_info(mod_id) - required string arg
+    lua_fn!(
+        "mod_info",
+        "Shows the meta.toml details of a loaded OpenZT mod",
+        "mod_info(mod_id)",
+        |mod_id: String| {
+            match get_mod_meta(&mod_id) {
+                Some(meta) => Ok((Some(meta.format_info()), None::<String>)),
+                None => Ok((None::<String>, Some(format!("No OpenZT mod with ID '{}' is loaded", mod_id)))),
+            }
+        }
+    );
 
     // list_openzt_locations_habitats() - no args
     lua_fn!(
@@ -98,10 +131,25 @@ pub fn init_commands() {
     // cache_stats() - no args
     lua_fn!("cache_stats", "Show resource cache statistics", "cache_stats()", || {
         let stats = get_cache_stats();
+        let budget = match stats.max_memory_mb {
+            0 => "no limit".to_string(),
+            max_memory_mb => format!("{} MB", max_memory_mb),
+        };
         Ok((
             Some(format!(
-                "Loaded: {} resources\nMemory: {} MB ({} bytes)",
-                stats.loaded_resources, stats.total_memory_mb, stats.total_memory_bytes
+                "Loaded: {} resources ({} not loaded)\nMemory: {} MB ({} bytes, {} bytes pinned)\nBudget: {}\nHits: {}, misses: {}, re-decoded after eviction: {}\nEvicted: {} resources ({} MB)\nInterned paths: {}",
+                stats.loaded_resources,
+                stats.lazy_resources,
+                stats.total_memory_mb,
+                stats.total_memory_bytes,
+                stats.pinned_memory_bytes,
+                budget,
+                stats.hits,
+                stats.misses,
+                stats.redecodes,
+                stats.evictions,
+                stats.evicted_bytes / (1024 * 1024),
+                stats.interned_paths
             )),
             None::<String>,
         ))
@@ -146,6 +194,255 @@ pub fn init_commands() {
             }
         }
     );
+
+    // patch_dry_run_report() - no args
+    lua_fn!(
+        "patch_dry_run_report",
+        "Shows the changes patches would have made (requires [dev] patch_dry_run)",
+        "patch_dry_run_report()",
+        || {
+            match command_patch_dry_run_report(vec![]) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(e.to_string()))),
+            }
+        }
+    );
+
+    // patch_conflict_report() - no args
+    lua_fn!("patch_conflict_report", "Shows INI keys written by patches from more than one mod", "patch_conflict_report()", || {
+        Ok((Some(patch_conflicts::report()), None::<String>))
+    });
+
+    // mod_checksums() - no args
+    if cfg!(feature = "experimental") {
+        lua_fn!("mod_checksums", "Shows the SHA-256 of each /mods/ archive and the mod set fingerprint", "mod_checksums()", || {
+            Ok((Some(checksums::report()), None::<String>))
+        });
+    }
+
+    // file_overrides([file_name]) - optional string arg
+    lua_fn!(
+        "file_overrides",
+        "Shows files provided by more than one archive, or which archives and patches provided one file",
+        "file_overrides([file_name])",
+        |file_name: Option<String>| {
+            match file_name {
+                Some(file_name) => match file_overrides::file_report(&file_name) {
+                    Some(report) => Ok((Some(report), None::<String>)),
+                    None => Ok((None::<String>, Some(format!("No archive or patch provided: {}", file_name)))),
+                },
+                None => Ok((Some(file_overrides::report()), None::<String>)),
+            }
+        }
+    );
+
+    // list_archives() - no args
+    lua_fn!("list_archives", "Lists the loaded archives in load order, later archives override earlier ones", "list_archives()", || {
+        Ok((Some(file_overrides::archive_report()), None::<String>))
+    });
+
+    // find_resources(pattern) - required string arg
+    lua_fn!(
+        "find_resources",
+        "Lists resource paths matching a glob pattern, e.g. \"animals/*.ai\"",
+        "find_resources(pattern)",
+        |pattern: String| {
+            match command_find_resources(vec![&pattern]) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(e.to_string()))),
+            }
+        }
+    );
+
+    // dump_resource(file_name) - required string arg
+    lua_fn!(
+        "dump_resource",
+        "Shows the current contents of a resource, as text or a hex dump",
+        "dump_resource(file_name)",
+        |file_name: String| {
+            match command_dump_resource(vec![&file_name]) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(e.to_string()))),
+            }
+        }
+    );
+
+    // resource_source(file_name) - required string arg
+    lua_fn!(
+        "resource_source",
+        "Shows which archive a resource came from and which mod last modified it",
+        "resource_source(file_name)",
+        |file_name: String| {
+            match file_overrides::source(&file_name) {
+                Some(source) => Ok((Some(source), None::<String>)),
+                None => Ok((None::<String>, Some(format!("No archive or patch provided: {}", file_name)))),
+            }
+        }
+    );
+
+    // saved_mods() - no args
+    lua_fn!(
+        "saved_mods",
+        "Shows the mods the current zoo was saved with that were removed or changed since",
+        "saved_mods()",
+        || {
+            match save_mods::check_zoo() {
+                Some(Ok(changes)) => Ok((Some(save_mods::format_changes(&changes)), None::<String>)),
+                Some(Err(e)) => Ok((None::<String>, Some(format!("Failed to read the mods this zoo was saved with: {:#}", e)))),
+                None => Ok((None::<String>, Some("The current zoo has no mod set file".to_string()))),
+            }
+        }
+    );
+
+    // export_load_order(file) - required string arg
+    lua_fn!(
+        "export_load_order",
+        "Writes the load order from openzt.toml, with mod versions, to a preset file",
+        "export_load_order(file)",
+        |file: String| {
+            match load_order_preset::export(&file) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(format!("{:#}", e)))),
+            }
+        }
+    );
+
+    // import_load_order(file) - required string arg
+    lua_fn!(
+        "import_load_order",
+        "Replaces the load order in openzt.toml with a preset file's, used on the next start",
+        "import_load_order(file)",
+        |file: String| {
+            match load_order_preset::import(&file) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(format!("{:#}", e)))),
+            }
+        }
+    );
+
+    // enable_mod(id) - required string arg
+    lua_fn!(
+        "enable_mod",
+        "Removes a mod ID or .ztd file from the disabled list in openzt.toml, used on the next start",
+        "enable_mod(id)",
+        |id: String| {
+            match mod_toggle::toggle(&id, true) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(format!("{:#}", e)))),
+            }
+        }
+    );
+
+    // disable_mod(id) - required string arg
+    lua_fn!(
+        "disable_mod",
+        "Adds a mod ID or .ztd file to the disabled list in openzt.toml, used on the next start",
+        "disable_mod(id)",
+        |id: String| {
+            match mod_toggle::toggle(&id, false) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(format!("{:#}", e)))),
+            }
+        }
+    );
+
+    // mod_load_report() - no args
+    lua_fn!(
+        "mod_load_report",
+        "Lists the errors and warnings found while loading mods, grouped by mod",
+        "mod_load_report()",
+        || { Ok((Some(load_report::report()), None::<String>)) }
+    );
+
+    // mod_list() - no args
+    lua_fn!(
+        "mod_list",
+        "Lists the mods in load order with their versions and whether each loaded, was disabled or failed",
+        "mod_list()",
+        || { Ok((Some(mod_list::list()), None::<String>)) }
+    );
+
+    // Mod settings are experimental
+    if cfg!(feature = "experimental") {
+        // mod_settings([mod_id]) - optional string arg
+        lua_fn!(
+            "mod_settings",
+            "Lists the settings of loaded mods with their values, defaults and descriptions",
+            "mod_settings([mod_id])",
+            |mod_id: Option<String>| {
+                match mod_settings::list(mod_id.as_deref()) {
+                    Ok(result) => Ok((Some(result), None::<String>)),
+                    Err(e) => Ok((None::<String>, Some(e.to_string()))),
+                }
+            }
+        );
+
+        // set_mod_setting(mod_id, key, value) - required string args
+        lua_fn!(
+            "set_mod_setting",
+            "Sets a mod setting in openzt.toml, patches use the new value after a restart",
+            "set_mod_setting(mod_id, key, value)",
+            |mod_id: String, key: String, value: String| {
+                match mod_settings::set(&mod_id, &key, &value) {
+                    Ok(result) => Ok((Some(result), None::<String>)),
+                    Err(e) => Ok((None::<String>, Some(format!("{:#}", e)))),
+                }
+            }
+        );
+
+        // reset_mod_setting(mod_id, key) - required string args
+        lua_fn!(
+            "reset_mod_setting",
+            "Removes a mod setting from openzt.toml so the mod's default is used",
+            "reset_mod_setting(mod_id, key)",
+            |mod_id: String, key: String| {
+                match mod_settings::reset(&mod_id, &key) {
+                    Ok(result) => Ok((Some(result), None::<String>)),
+                    Err(e) => Ok((None::<String>, Some(format!("{:#}", e)))),
+                }
+            }
+        );
+    }
+
+    // load_timings() - no args
+    lua_fn!(
+        "load_timings",
+        "Shows how long each phase of loading mods took, and the slowest archives",
+        "load_timings()",
+        || { Ok((Some(load_profile::summary()), None::<String>)) }
+    );
+
+    // mod_scripts() - no args
+    lua_fn!(
+        "mod_scripts",
+        "Lists the Lua scripts run from OpenZT mods and the game events they handle",
+        "mod_scripts()",
+        || { Ok((Some(scripts::summary()), None::<String>)) }
+    );
+
+    // check_mod_updates() - no args
+    #[cfg(feature = "experimental")]
+    lua_fn!(
+        "check_mod_updates",
+        "Lists installed OpenZT mods with a newer version in the [updates] index_url, without installing anything",
+        "check_mod_updates()",
+        || {
+            match mod_updates::check_command() {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(e))),
+            }
+        }
+    );
+
+    // reload_dev_mod() - no args
+    if cfg!(feature = "experimental") {
+        lua_fn!("reload_dev_mod", "Reloads the mod in [dev] hot_reload_dir from disk", "reload_dev_mod()", || {
+            match command_reload_dev_mod(vec![]) {
+                Ok(result) => Ok((Some(result), None::<String>)),
+                Err(e) => Ok((None::<String>, Some(e.to_string()))),
+            }
+        });
+    }
 }
 
 fn command_list_resource_strings(args: Vec<&str>) -> Result<String, CommandError> {
@@ -172,6 +469,72 @@ fn command_list_openzt_resource_strings(_args: Vec<&str>) -> Result<String, Comm
     Ok(result_string)
 }
 
+fn command_patch_dry_run_report(_args: Vec<&str>) -> Result<String, CommandError> {
+    if !patch_dry_run::is_enabled() {
+        return Err(CommandError::new(
+            "Patch dry run is not enabled, set patch_dry_run = true in the [dev] section of openzt.toml".to_string(),
+        ));
+    }
+    Ok(patch_dry_run::report())
+}
+
+fn command_find_resources(args: Vec<&str>) -> Result<String, CommandError> {
+    let [pattern] = args[..] else {
+        return Err(CommandError::new("Expected a glob pattern".to_string()));
+    };
+    let mut matches: Vec<String> = get_file_names().into_iter().filter(|file_name| glob_matches(pattern, file_name)).collect();
+    matches.sort();
+
+    let mut result_string = format!("{} resources match {}\n", matches.len(), pattern);
+    for file_name in matches {
+        result_string.push_str(&format!("{}\n", file_name));
+    }
+    Ok(result_string)
+}
+
+/// Bytes shown by dump_resource() for files that aren't text
+const DUMP_BYTES: usize = 512;
+
+/// A byte as shown in the text column of a hex dump
+fn dump_char(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }
+}
+
+fn command_dump_resource(args: Vec<&str>) -> Result<String, CommandError> {
+    let [file_name] = args[..] else {
+        return Err(CommandError::new("Expected a file name".to_string()));
+    };
+    let Some((_, data)) = get_file(file_name) else {
+        return Err(CommandError::new(format!("Resource not found: {}", file_name)));
+    };
+    // get_file counts as a reference, release it so the resource can still be unloaded
+    decrement_ref(file_name);
+
+    let file_type = ZTFileType::try_from(Path::new(file_name)).map_err(|e| CommandError::new(e.to_string()))?;
+    match file_type {
+        file_type if file_type.is_text() => Ok(decode_game_text(&data).trim_end_matches('\0').to_string()),
+        _ => {
+            let mut result_string = format!("{} ({} bytes)\n", file_name, data.len());
+            for (row, chunk) in data.chunks(16).take(DUMP_BYTES / 16).enumerate() {
+                let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+                let ascii: String = chunk.iter().map(|&byte| dump_char(byte)).collect();
+                result_string.push_str(&format!("{:08x}  {:<47}  {}\n", row * 16, hex.join(" "), ascii));
+            }
+            if data.len() > DUMP_BYTES {
+                result_string.push_str(&format!("... {} more bytes\n", data.len() - DUMP_BYTES));
+            }
+            Ok(result_string)
+        }
+    }
+}
+
+fn command_reload_dev_mod(_args: Vec<&str>) -> Result<String, CommandError> {
+    match hot_reload::reload() {
+        Ok(mod_id) => Ok(format!("Reloaded dev mod {}", mod_id)),
+        Err(e) => Err(CommandError::new(format!("{:#}", e))),
+    }
+}
+
 fn command_list_resources(_args: Vec<&str>) -> Result<String, CommandError> {
     let mut result_string = String::new();
     let bf_resource_dir_contents = read_bf_resource_dir_contents_from_memory();
@@ -191,10 +554,16 @@ fn command_get_bf_resource_mgr(_args: Vec<&str>) -> Result<String, CommandError>
     Ok(format!("{}", bf_resource_mgr))
 }
 
-fn command_list_openzt_mod_ids(_args: Vec<&str>) -> Result<String, CommandError> {
+fn command_list_openzt_mods(tag: Option<&str>) -> Result<String, CommandError> {
+    let metas = get_mod_metas(tag);
+    if let Some(tag) = tag
+        && metas.is_empty()
+    {
+        return Err(CommandError::new(format!("No loaded OpenZT mod has the tag '{}'", tag)));
+    }
     let mut result_string = String::new();
-    for mod_id in get_mod_ids() {
-        result_string.push_str(&format!("{}\n", mod_id));
+    for meta in metas {
+        result_string.push_str(&format!("{}\n", meta.format_summary()));
     }
     Ok(result_string)
 }
@@ -1,18 +1,23 @@
 use crate::dll_dependencies;
 use crate::mods::{DependencyIdentifier, Meta, Ordering, ZtdType};
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
 use tracing::{debug, error, info, warn};
 
 /// Result of dependency resolution
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResolutionResult {
     pub order: Vec<String>,
     pub warnings: Vec<ResolutionWarning>,
 }
 
 /// Warnings generated during dependency resolution
-#[derive(Debug, Clone)]
+///
+/// `cycle` is the path of a dependency cycle, starting from its alphabetically
+/// first mod: each mod loads after the next, and the last after the first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum ResolutionWarning {
     CircularDependency { cycle: Vec<String> },
     TrulyCyclicDependency { cycle: Vec<String> },
@@ -49,6 +54,8 @@ pub struct DependencyResolver {
     mods: HashMap<String, Meta>,
     // Mapping from ztd_name to mod_id for identifier resolution
     ztd_to_mod_id: HashMap<String, String>,
+    // Mapping from mod_id to ztd_name for reporting
+    mod_id_to_ztd: HashMap<String, String>,
 }
 
 impl DependencyResolver {
@@ -59,8 +66,29 @@ impl DependencyResolver {
     /// * `discovered` - HashMap of mod_id to (ztd_name, Meta) for resolving ztd_name dependencies
     pub fn new(mods: HashMap<String, Meta>, discovered: &HashMap<String, (String, Meta)>) -> Self {
         let ztd_to_mod_id = discovered.iter().map(|(mod_id, (ztd_name, _))| (ztd_name.clone(), mod_id.clone())).collect();
+        let mod_id_to_ztd = discovered.iter().map(|(mod_id, (ztd_name, _))| (mod_id.clone(), ztd_name.clone())).collect();
 
-        Self { mods, ztd_to_mod_id }
+        Self {
+            mods,
+            ztd_to_mod_id,
+            mod_id_to_ztd,
+        }
+    }
+
+    /// Describe a cycle path for modders, e.g.
+    /// `a (a.ztd/meta.toml) → b (b.ztd/meta.toml) → a`
+    pub fn describe_cycle(&self, cycle: &[String]) -> String {
+        let mut steps: Vec<String> = cycle
+            .iter()
+            .map(|mod_id| match self.mod_id_to_ztd.get(mod_id) {
+                Some(ztd_name) => format!("{} ({}/meta.toml)", mod_id, ztd_name),
+                None => mod_id.clone(),
+            })
+            .collect();
+        if let Some(first) = cycle.first() {
+            steps.push(first.clone());
+        }
+        steps.join(" → ")
     }
 
     /// Resolve mod load order based on dependencies and existing configuration
@@ -99,13 +127,23 @@ impl DependencyResolver {
         // Sort new mods alphabetically for deterministic processing
         new_mods.sort();
 
+        // Mods another enabled mod must load before, these can't simply go first
+        let loaded_after_others: HashSet<String> = self
+            .mods
+            .iter()
+            .filter(|(id, _)| !disabled_set.contains(*id))
+            .flat_map(|(_, meta)| meta.dependencies())
+            .filter(|dep| *dep.ordering() == Ordering::Before)
+            .filter_map(|dep| self.ordering_target(dep.identifier()))
+            .collect();
+
         // Categorize new OpenZT mods
         let mut legacy_type_no_deps: Vec<String> = Vec::new();
         let mut mods_with_deps: Vec<String> = Vec::new();
 
         for mod_id in &new_mods {
             if let Some(meta) = self.mods.get(mod_id) {
-                if *meta.ztd_type() == ZtdType::Legacy && meta.dependencies().is_empty() {
+                if *meta.ztd_type() == ZtdType::Legacy && meta.dependencies().is_empty() && !loaded_after_others.contains(mod_id) {
                     legacy_type_no_deps.push(mod_id.clone());
                 } else {
                     mods_with_deps.push(mod_id.clone());
@@ -117,16 +155,17 @@ impl DependencyResolver {
         debug!("Discovered {} new ztd_type='legacy' mod(s) with no deps", legacy_type_no_deps.len());
         debug!("Discovered {} new mod(s) with deps or other ztd_type", mods_with_deps.len());
 
-        // Build dependency graph only for enabled mods with dependencies
-        let enabled_mods_with_deps: HashMap<_, _> = self
+        // Build dependency graph from all enabled mods, so constraints declared by mods
+        // already in the order also apply to the new mods they name
+        let enabled_mods: HashMap<_, _> = self
             .mods
             .iter()
-            .filter(|(id, _)| !disabled_set.contains(*id) && mods_with_deps.contains(*id))
+            .filter(|(id, _)| !disabled_set.contains(*id))
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
 
-        let graph = if !enabled_mods_with_deps.is_empty() {
-            self.build_dependency_graph(&enabled_mods_with_deps, DependencyInclusionMode::All)
+        let graph = if !enabled_mods.is_empty() {
+            self.build_dependency_graph(&enabled_mods, DependencyInclusionMode::All)
         } else {
             DependencyGraph {
                 before_deps: HashMap::new(),
@@ -137,20 +176,20 @@ impl DependencyResolver {
 
         // Two-stage cycle detection for mods with deps
         let (truly_cyclic, formerly_cyclic, stage1_cycles, stage2_cycles) = if !mods_with_deps.is_empty() {
-            self.detect_cycles_two_stage(&graph, &mods_with_deps, &enabled_mods_with_deps)
+            self.detect_cycles_two_stage(&graph, &mods_with_deps, &enabled_mods)
         } else {
             (Vec::new(), Vec::new(), Vec::new(), Vec::new())
         };
 
         // Generate warnings for Stage 1 cycles (warning level)
         for cycle in &stage1_cycles {
-            warn!("Circular dependency detected (with optional deps): {:?}", cycle);
+            warn!("Circular dependency detected (with optional deps): {}", self.describe_cycle(cycle));
             warnings.push(ResolutionWarning::CircularDependency { cycle: cycle.clone() });
         }
 
         // Generate errors for Stage 2 cycles (error level)
         for cycle in &stage2_cycles {
-            error!("Truly cyclic dependency (required deps only): {:?}", cycle);
+            error!("Truly cyclic dependency (required deps only): {}", self.describe_cycle(cycle));
             warnings.push(ResolutionWarning::TrulyCyclicDependency { cycle: cycle.clone() });
         }
 
@@ -171,7 +210,7 @@ impl DependencyResolver {
             &legacy_type_no_deps,
             &mods_with_deps,
             &graph,
-            &enabled_mods_with_deps,
+            &enabled_mods,
             &truly_cyclic,
             &formerly_cyclic,
         );
@@ -181,6 +220,15 @@ impl DependencyResolver {
         ResolutionResult { order: final_order, warnings }
     }
 
+    /// The mod a dependency orders against, `None` for DLL dependencies
+    fn ordering_target(&self, identifier: &DependencyIdentifier) -> Option<String> {
+        match identifier {
+            DependencyIdentifier::ModId(id) => Some(id.clone()),
+            DependencyIdentifier::ZtdName(ztd_name) => Some(self.ztd_to_mod_id.get(ztd_name).unwrap_or(ztd_name).clone()),
+            DependencyIdentifier::DllName(_) => None,
+        }
+    }
+
     /// Build dependency graph from mod metadata
     fn build_dependency_graph(&self, mods: &HashMap<String, Meta>, mode: DependencyInclusionMode) -> DependencyGraph {
         let mut before_deps: HashMap<String, Vec<String>> = HashMap::new();
@@ -244,6 +292,12 @@ impl DependencyResolver {
                     }
                 };
 
+                // A mod can't load before or after itself
+                if resolved_id == *mod_id {
+                    warn!("Mod '{}' depends on itself, ignoring the dependency", mod_id);
+                    continue;
+                }
+
                 // Check if we should include this dependency based on the mode
                 let should_include = match mode {
                     DependencyInclusionMode::All => true,
@@ -310,6 +364,7 @@ impl DependencyResolver {
     /// 2. For cyclic mods, re-check with only required dependencies
     ///
     /// Returns: (truly_cyclic_mods, formerly_cyclic_mods, stage1_cycles, stage2_cycles)
+    /// The mod lists are sorted alphabetically; the cycles are paths (see `find_cycle_path`).
     #[allow(clippy::type_complexity)]
     fn detect_cycles_two_stage(
         &self,
@@ -349,7 +404,8 @@ impl DependencyResolver {
 
         let required_only_graph = self.build_dependency_graph(&cyclic_mods_only, DependencyInclusionMode::RequiredOnly);
 
-        let cyclic_mod_ids: Vec<_> = stage1_cyclic_mods.iter().cloned().collect();
+        let mut cyclic_mod_ids: Vec<_> = stage1_cyclic_mods.iter().cloned().collect();
+        cyclic_mod_ids.sort();
         let stage2_cycles = self.detect_cycles_in_subgraph(&required_only_graph, &cyclic_mod_ids);
 
         info!(
@@ -365,12 +421,62 @@ impl DependencyResolver {
         // Categorize: mods in Stage 2 cycles are truly cyclic, others are formerly cyclic
         let stage2_cyclic_mods: HashSet<String> = stage2_cycles.iter().flat_map(|cycle| cycle.iter().cloned()).collect();
 
-        let truly_cyclic: Vec<_> = stage2_cyclic_mods.iter().cloned().collect();
-        let formerly_cyclic: Vec<_> = stage1_cyclic_mods.difference(&stage2_cyclic_mods).cloned().collect();
+        let mut truly_cyclic: Vec<_> = stage2_cyclic_mods.iter().cloned().collect();
+        let mut formerly_cyclic: Vec<_> = stage1_cyclic_mods.difference(&stage2_cyclic_mods).cloned().collect();
+        truly_cyclic.sort();
+        formerly_cyclic.sort();
 
         info!("Result: {} truly cyclic, {} formerly cyclic (resolved)", truly_cyclic.len(), formerly_cyclic.len());
 
-        (truly_cyclic, formerly_cyclic, stage1_cycles, stage2_cycles)
+        // Report each strongly connected component as a concrete cycle path
+        let stage1_paths = stage1_cycles.iter().map(|scc| self.find_cycle_path(scc, all_deps_graph)).collect();
+        let stage2_paths = stage2_cycles.iter().map(|scc| self.find_cycle_path(scc, &required_only_graph)).collect();
+
+        (truly_cyclic, formerly_cyclic, stage1_paths, stage2_paths)
+    }
+
+    /// Find a cycle through a strongly connected component, for reporting
+    ///
+    /// Starts at the alphabetically first mod and returns the shortest path
+    /// back to it, following "loads after" edges (neighbours visited in
+    /// alphabetical order, so the result is deterministic). The closing edge
+    /// back to the first mod is implied.
+    fn find_cycle_path(&self, scc: &[String], graph: &DependencyGraph) -> Vec<String> {
+        let members: HashSet<&String> = scc.iter().collect();
+        let Some(start) = scc.iter().min() else {
+            return Vec::new();
+        };
+
+        // Breadth-first search from start until an edge leads back to it
+        let mut previous: HashMap<&String, &String> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(mod_id) = queue.pop_front() {
+            let mut deps: Vec<&String> = graph.before_deps.get(mod_id).into_iter().flatten().filter(|dep| members.contains(dep)).collect();
+            deps.sort();
+            deps.dedup();
+
+            for dep in deps {
+                if dep == start {
+                    let mut path = vec![mod_id.clone()];
+                    let mut current = mod_id;
+                    while let Some(&prev) = previous.get(current) {
+                        path.push(prev.clone());
+                        current = prev;
+                    }
+                    path.reverse();
+                    return path;
+                }
+                if !previous.contains_key(dep) {
+                    previous.insert(dep, mod_id);
+                    queue.push_back(dep);
+                }
+            }
+        }
+
+        // Not reachable for a real SCC; fall back to the sorted members
+        let mut sorted = scc.to_vec();
+        sorted.sort();
+        sorted
     }
 
     /// Topologically sort a list of mods using Kahn's algorithm
@@ -815,10 +921,11 @@ impl DependencyResolver {
             }
         };
 
-        // Insert never-cyclic mods (using full graph)
+        // Insert never-cyclic mods (using full graph) in dependency order, so each mod
+        // is inserted after the new mods it depends on and sees their positions
         let mut insert_offset = 0;
 
-        for mod_id in never_cyclic {
+        for mod_id in self.topological_sort(&never_cyclic, graph) {
             let (position, insert_warnings) = self.find_insert_position(&mod_id, &order, graph, all_mods);
             warnings.extend(insert_warnings);
 
@@ -1109,6 +1216,22 @@ mod tests {
 
         assert!(has_stage1_warning, "Expected CircularDependency warning from Stage 1");
         assert!(has_stage2_warning, "Expected TrulyCyclicDependency warning from Stage 2");
+
+        // Fallback order is alphabetical, and the cycle is reported as a path
+        assert_eq!(result.order, vec!["test.mod_a", "test.mod_b", "test.mod_c"]);
+        let cycle = result
+            .warnings
+            .iter()
+            .find_map(|w| match w {
+                ResolutionWarning::TrulyCyclicDependency { cycle } => Some(cycle.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(cycle, vec!["test.mod_a", "test.mod_b", "test.mod_c"]);
+        assert_eq!(
+            resolver.describe_cycle(&cycle),
+            "test.mod_a (test.mod_a.ztd/meta.toml) → test.mod_b (test.mod_b.ztd/meta.toml) → test.mod_c (test.mod_c.ztd/meta.toml) → test.mod_a"
+        );
     }
 
     #[test]
@@ -1149,6 +1272,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_present_optional_dependency_orders() {
+        let mut mods = HashMap::new();
+
+        // Compatibility patch that sorts first alphabetically but must follow the mod it patches
+        let meta_patch = create_test_meta(
+            r#"
+            name = "Compat Patch"
+            description = "Test compatibility patch"
+            authors = ["Test"]
+            mod_id = "test.a_compat_patch"
+            version = "1.0.0"
+            dependencies = [
+                { mod_id = "test.z_base", name = "Base", optional = true, ordering = "after" }
+            ]
+        "#,
+        );
+
+        let meta_base = create_test_meta(
+            r#"
+            name = "Base"
+            description = "Test base mod"
+            authors = ["Test"]
+            mod_id = "test.z_base"
+            version = "1.0.0"
+        "#,
+        );
+
+        mods.insert("test.a_compat_patch".to_string(), meta_patch.clone());
+        mods.insert("test.z_base".to_string(), meta_base.clone());
+
+        let mut discovered = HashMap::new();
+        discovered.insert("test.a_compat_patch".to_string(), ("test.a_compat_patch.ztd".to_string(), meta_patch));
+        discovered.insert("test.z_base".to_string(), ("test.z_base.ztd".to_string(), meta_base));
+
+        let resolver = DependencyResolver::new(mods, &discovered);
+        let pure_legacy: &[(String, PathBuf)] = &[];
+        let result = resolver.resolve_order(&[], &[], pure_legacy);
+
+        assert_eq!(result.order, vec!["test.z_base", "test.a_compat_patch"]);
+        assert!(result.warnings.is_empty());
+    }
+
     #[test]
     fn test_missing_required_dependency() {
         let mut mods = HashMap::new();
@@ -1656,4 +1822,285 @@ mod tests {
         assert_eq!(result.order[2], "com.dependent.mod");
         assert!(result.warnings.is_empty());
     }
+
+    #[test]
+    fn test_diamond_dependency_inserted_into_existing_order() {
+        // "a.top" loads after "b.left" and "c.right", which both load after "base";
+        // it sorts first alphabetically but has to be inserted last
+        let specs = [
+            ("base", ""),
+            (
+                "a.top",
+                r#"dependencies = [{ mod_id = "b.left", name = "Left", ordering = "after" }, { mod_id = "c.right", name = "Right", ordering = "after" }]"#,
+            ),
+            ("b.left", r#"dependencies = [{ mod_id = "base", name = "Base", ordering = "after" }]"#),
+            ("c.right", r#"dependencies = [{ mod_id = "base", name = "Base", ordering = "after" }]"#),
+        ];
+
+        let mut mods = HashMap::new();
+        let mut discovered = HashMap::new();
+        for (mod_id, deps) in specs {
+            let meta = create_test_meta(&format!(
+                "name = \"{0}\"\ndescription = \"Test mod\"\nauthors = [\"Test\"]\nmod_id = \"{0}\"\nversion = \"1.0.0\"\n{1}",
+                mod_id, deps
+            ));
+            mods.insert(mod_id.to_string(), meta.clone());
+            discovered.insert(mod_id.to_string(), (format!("{}.ztd", mod_id), meta));
+        }
+
+        let resolver = DependencyResolver::new(mods, &discovered);
+        let pure_legacy: &[(String, PathBuf)] = &[];
+        let result = resolver.resolve_order(&["base".to_string()], &[], pure_legacy);
+
+        assert_eq!(result.order.len(), 4);
+        assert_eq!(result.order[0], "base");
+        assert_eq!(result.order[3], "a.top");
+        assert!(result.warnings.is_empty());
+    }
+}
+
+/// Invariants of `resolve_order` checked against random mod graphs
+#[cfg(test)]
+mod proptests {
+    use std::fmt::Write;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::mods::Meta;
+
+    /// Mods are named m0, m1, ...; dependencies on indexes past the last mod are missing
+    const MAX_MODS: usize = 8;
+    const MAX_LEGACY_ARCHIVES: usize = 3;
+
+    #[derive(Debug, Clone)]
+    struct DepSpec {
+        target: usize,
+        ordering: &'static str,
+        optional: bool,
+    }
+
+    #[derive(Debug, Clone)]
+    struct ModSpec {
+        legacy: bool,
+        deps: Vec<DepSpec>,
+    }
+
+    /// Everything `resolve_order` is given
+    #[derive(Debug, Clone)]
+    struct Scenario {
+        mods: Vec<ModSpec>,
+        existing_order: Vec<String>,
+        disabled: Vec<String>,
+        pure_legacy: Vec<(String, PathBuf)>,
+    }
+
+    fn mod_id(index: usize) -> String {
+        format!("m{}", index)
+    }
+
+    fn legacy_archive(index: usize) -> String {
+        format!("legacy{}.ztd", index)
+    }
+
+    fn meta(index: usize, spec: &ModSpec) -> Meta {
+        let mut toml_str = format!(
+            "name = \"Mod {0}\"\ndescription = \"Test mod {0}\"\nauthors = [\"Test\"]\nmod_id = \"{1}\"\nversion = \"1.0.0\"\nztd_type = \"{2}\"\n",
+            index,
+            mod_id(index),
+            if spec.legacy { "legacy" } else { "openzt" }
+        );
+        toml_str.push_str("dependencies = [\n");
+        for dep in &spec.deps {
+            let _ = writeln!(
+                toml_str,
+                "    {{ mod_id = \"{}\", name = \"Dep\", ordering = \"{}\", optional = {} }},",
+                mod_id(dep.target),
+                dep.ordering,
+                dep.optional
+            );
+        }
+        toml_str.push_str("]\n");
+        toml::from_str(&toml_str).expect("Failed to parse generated TOML")
+    }
+
+    fn dep_spec(mod_count: usize) -> impl Strategy<Value = DepSpec> {
+        (0..mod_count + 2, prop::sample::select(vec!["after", "before", "none"]), any::<bool>()).prop_map(|(target, ordering, optional)| DepSpec {
+            target,
+            ordering,
+            optional,
+        })
+    }
+
+    /// Dependencies pointing anywhere, cycles included
+    fn any_mods() -> impl Strategy<Value = Vec<ModSpec>> {
+        (1..=MAX_MODS).prop_flat_map(|mod_count| {
+            prop::collection::vec(
+                (prop::bool::weighted(0.2), prop::collection::vec(dep_spec(mod_count), 0..4)).prop_map(|(legacy, deps)| ModSpec { legacy, deps }),
+                mod_count,
+            )
+        })
+    }
+
+    /// Dependencies only ever asking for the mod with the higher index to load later, so there is no cycle
+    fn acyclic_mods() -> impl Strategy<Value = Vec<ModSpec>> {
+        any_mods().prop_map(|mut mods| {
+            for (index, spec) in mods.iter_mut().enumerate() {
+                spec.deps.retain(|dep| dep.target != index);
+                for dep in &mut spec.deps {
+                    if dep.ordering != "none" {
+                        dep.ordering = if dep.target < index { "after" } else { "before" };
+                    }
+                }
+            }
+            mods
+        })
+    }
+
+    /// Some of the mods and archives already in openzt.toml in a random order, and some disabled
+    fn scenario(mods: impl Strategy<Value = Vec<ModSpec>>) -> impl Strategy<Value = Scenario> {
+        (mods, 0..=MAX_LEGACY_ARCHIVES).prop_flat_map(|(mods, legacy_count)| {
+            let entries: Vec<String> = (0..mods.len()).map(mod_id).chain((0..legacy_count).map(legacy_archive)).collect();
+            let entry_count = entries.len();
+            (
+                Just(mods),
+                Just(legacy_count),
+                Just(entries).prop_shuffle(),
+                prop::collection::vec(any::<bool>(), entry_count),
+                prop::collection::vec(prop::bool::weighted(0.2), entry_count),
+            )
+                .prop_map(|(mods, legacy_count, shuffled, existing, disabled)| Scenario {
+                    mods,
+                    existing_order: shuffled
+                        .iter()
+                        .zip(&existing)
+                        .filter(|(_, existing)| **existing)
+                        .map(|(entry, _)| entry.clone())
+                        .collect(),
+                    disabled: shuffled
+                        .iter()
+                        .zip(&disabled)
+                        .filter(|(_, disabled)| **disabled)
+                        .map(|(entry, _)| entry.clone())
+                        .collect(),
+                    pure_legacy: (0..legacy_count)
+                        .map(|index| (legacy_archive(index), PathBuf::from(format!("./mods/{}", legacy_archive(index)))))
+                        .collect(),
+                })
+        })
+    }
+
+    /// A fresh install: nothing in openzt.toml yet
+    fn fresh_scenario(mods: impl Strategy<Value = Vec<ModSpec>>) -> impl Strategy<Value = Scenario> {
+        scenario(mods).prop_map(|mut scenario| {
+            scenario.existing_order.clear();
+            scenario
+        })
+    }
+
+    /// Resolve with the mods inserted into the resolver's maps in the given order of indexes
+    fn resolve(scenario: &Scenario, insertion_order: impl Iterator<Item = usize>) -> ResolutionResult {
+        let mut mods = HashMap::new();
+        let mut discovered = HashMap::new();
+        for index in insertion_order {
+            let meta = meta(index, &scenario.mods[index]);
+            mods.insert(mod_id(index), meta.clone());
+            discovered.insert(mod_id(index), (format!("{}.ztd", mod_id(index)), meta));
+        }
+        DependencyResolver::new(mods, &discovered).resolve_order(&scenario.existing_order, &scenario.disabled, &scenario.pure_legacy)
+    }
+
+    /// Every entry of openzt.toml stays, every enabled new mod and archive is added, and nothing is listed twice
+    fn check_entries(scenario: &Scenario, result: &ResolutionResult) -> Result<(), TestCaseError> {
+        let mut expected: HashSet<String> = scenario.existing_order.iter().cloned().collect();
+        expected.extend((0..scenario.mods.len()).map(mod_id).filter(|id| !scenario.disabled.contains(id)));
+        expected.extend(
+            scenario
+                .pure_legacy
+                .iter()
+                .map(|(name, _)| name.clone())
+                .filter(|name| !scenario.disabled.contains(name)),
+        );
+
+        let actual: HashSet<String> = result.order.iter().cloned().collect();
+        prop_assert_eq!(actual.len(), result.order.len(), "duplicate entries in {:?}", result.order);
+        prop_assert_eq!(actual, expected);
+        Ok(())
+    }
+
+    /// Every "after" and "before" dependency between two enabled mods holds, unless both were
+    /// already in openzt.toml, where the player's order wins
+    fn check_constraints(scenario: &Scenario, result: &ResolutionResult) -> Result<(), TestCaseError> {
+        let position: HashMap<&String, usize> = result.order.iter().enumerate().map(|(i, id)| (id, i)).collect();
+        for (index, spec) in scenario.mods.iter().enumerate() {
+            let id = mod_id(index);
+            // A mod can't load after itself, such a dependency is ignored
+            for dep in spec.deps.iter().filter(|dep| dep.target != index) {
+                let target = mod_id(dep.target);
+                let (Some(&pos), Some(&target_pos)) = (position.get(&id), position.get(&target)) else {
+                    continue;
+                };
+                if scenario.disabled.contains(&id) || scenario.disabled.contains(&target) {
+                    continue;
+                }
+                if scenario.existing_order.contains(&id) && scenario.existing_order.contains(&target) {
+                    continue;
+                }
+                match dep.ordering {
+                    "after" => prop_assert!(target_pos < pos, "{} should load after {} in {:?}", id, target, result.order),
+                    "before" => prop_assert!(pos < target_pos, "{} should load before {} in {:?}", id, target, result.order),
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn has_cycle_or_conflict(result: &ResolutionResult) -> bool {
+        result.warnings.iter().any(|warning| {
+            matches!(
+                warning,
+                ResolutionWarning::CircularDependency { .. } | ResolutionWarning::TrulyCyclicDependency { .. } | ResolutionWarning::ConflictingConstraints { .. }
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn resolution_contains_every_enabled_entry_once(scenario in scenario(any_mods())) {
+            let result = resolve(&scenario, 0..scenario.mods.len());
+            check_entries(&scenario, &result)?;
+        }
+
+        #[test]
+        fn resolution_is_deterministic(scenario in scenario(any_mods())) {
+            let first = resolve(&scenario, 0..scenario.mods.len());
+            let second = resolve(&scenario, (0..scenario.mods.len()).rev());
+            prop_assert_eq!(&first.order, &second.order);
+            prop_assert_eq!(&first.warnings, &second.warnings);
+        }
+
+        #[test]
+        fn fresh_install_meets_every_constraint(scenario in fresh_scenario(acyclic_mods())) {
+            let result = resolve(&scenario, 0..scenario.mods.len());
+            prop_assert!(!has_cycle_or_conflict(&result), "unexpected warnings {:?}", result.warnings);
+            check_constraints(&scenario, &result)?;
+        }
+
+        #[test]
+        fn new_mods_meet_constraints(scenario in scenario(acyclic_mods())) {
+            let result = resolve(&scenario, 0..scenario.mods.len());
+            if !has_cycle_or_conflict(&result) {
+                check_constraints(&scenario, &result)?;
+            }
+        }
+
+        #[test]
+        fn cyclic_graphs_keep_acyclic_constraints(scenario in fresh_scenario(any_mods())) {
+            let result = resolve(&scenario, 0..scenario.mods.len());
+            if !has_cycle_or_conflict(&result) {
+                check_constraints(&scenario, &result)?;
+            }
+        }
+    }
 }
@@ -0,0 +1,285 @@
+//! Which archive provides each file, and which mods' patches changed it since
+//!
+//! Archives are added to the resource map in load order, so a file in a later
+//! archive replaces the same file from an earlier one. The files of each archive
+//! are recorded as it is added, along with every patch that changes a file
+//! afterwards. Once loading finishes every file provided by more than one archive
+//! is listed in `openzt_file_overrides.txt` next to openzt.toml, and the
+//! `file_overrides([file_name])` console command shows the same while the game runs.
+//! The same record backs the `list_archives()` and `resource_source(file_name)` commands.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+use tracing::{error, info};
+
+use crate::resource_manager::lazyresourcemap::resource_key;
+
+const REPORT_FILE_NAME: &str = "openzt_file_overrides.txt";
+
+/// A patch that changed a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchWrite {
+    pub mod_id: String,
+    pub patch_name: String,
+}
+
+/// Where a file in the resource map came from
+#[derive(Debug, Default)]
+pub struct FileHistory {
+    /// Indexes into [`FileOverrides::archives`] of every archive with the file, in load order
+    providers: Vec<usize>,
+    /// Patches applied to the file, in load order
+    patches: Vec<PatchWrite>,
+}
+
+/// Every archive added to the resource map and the history of each file
+#[derive(Default)]
+pub struct FileOverrides {
+    /// Archives in load order
+    archives: Vec<String>,
+    /// File history keyed by lowercase file name
+    files: BTreeMap<String, FileHistory>,
+}
+
+static FILE_OVERRIDES: LazyLock<Mutex<FileOverrides>> = LazyLock::new(|| Mutex::new(FileOverrides::default()));
+
+impl FileOverrides {
+    /// Record the files an archive added to the resource map
+    pub fn add_archive(&mut self, archive: &str, file_names: &[String]) {
+        let index = self.archives.len();
+        self.archives.push(archive.to_string());
+        for file_name in file_names {
+            self.files.entry(file_name.to_lowercase()).or_default().providers.push(index);
+        }
+    }
+
+    /// Record a patch applied to a file
+    pub fn add_patch(&mut self, target: &str, write: PatchWrite) {
+        self.files.entry(target.to_lowercase()).or_default().patches.push(write);
+    }
+
+    /// Forget the patches applied by a mod (before the mod is reloaded)
+    pub fn release_mod(&mut self, mod_id: &str) {
+        self.files.retain(|_, history| {
+            history.patches.retain(|write| write.mod_id != mod_id);
+            !history.providers.is_empty() || !history.patches.is_empty()
+        });
+    }
+
+    /// Files provided by more than one archive
+    pub fn overridden(&self) -> Vec<(&String, &FileHistory)> {
+        self.files.iter().filter(|(_, history)| history.providers.len() > 1).collect()
+    }
+
+    fn format_file(&self, report: &mut String, file_name: &str, history: &FileHistory) {
+        let _ = writeln!(report, "\n{}", file_name);
+        for (position, index) in history.providers.iter().enumerate() {
+            let marker = if position + 1 == history.providers.len() { " <- wins" } else { "" };
+            let _ = writeln!(report, "  {}{}", self.archives[*index], marker);
+        }
+        if history.providers.is_empty() {
+            let _ = writeln!(report, "  (not from an archive)");
+        }
+        for write in &history.patches {
+            let _ = writeln!(report, "  patched by {}: {}", write.mod_id, write.patch_name);
+        }
+    }
+
+    /// Format the override report
+    pub fn format_report(&self) -> String {
+        let overridden = self.overridden();
+
+        let mut report = String::new();
+        let _ = writeln!(report, "OpenZT file overrides: {} files provided by more than one archive", overridden.len());
+        for (file_name, history) in overridden {
+            self.format_file(&mut report, file_name, history);
+        }
+
+        report
+    }
+
+    /// Format the archives in load order, with how many of their files weren't overridden by a later archive
+    pub fn format_archives(&self) -> String {
+        let mut in_use = vec![0; self.archives.len()];
+        let mut total = vec![0; self.archives.len()];
+        for history in self.files.values() {
+            for index in &history.providers {
+                total[*index] += 1;
+            }
+            if let Some(index) = history.providers.last() {
+                in_use[*index] += 1;
+            }
+        }
+
+        let mut report = String::new();
+        let _ = writeln!(report, "{} archives in load order, later archives override earlier ones:", self.archives.len());
+        for (index, archive) in self.archives.iter().enumerate() {
+            let _ = writeln!(report, "{:>4}. {} ({} of {} files in use)", index + 1, archive, in_use[index], total[index]);
+        }
+        report
+    }
+
+    /// Describe where the current version of a file came from, `None` if no archive or patch provided it
+    pub fn format_source(&self, file_name: &str) -> Option<String> {
+        let file_name = file_name.to_lowercase();
+        let history = self.files.get(&file_name)?;
+
+        let archive = match history.providers.last() {
+            Some(index) => format!("from {}", self.archives[*index]),
+            None => "not from an archive".to_string(),
+        };
+        let patch = match history.patches.last() {
+            Some(write) => format!(", last modified by {} (patch '{}')", write.mod_id, write.patch_name),
+            None => ", not modified by any patch".to_string(),
+        };
+        Some(format!("{}: {}{}", file_name, archive, patch))
+    }
+
+    /// Format the history of a single file, `None` if no archive or patch provided it
+    pub fn format_file_report(&self, file_name: &str) -> Option<String> {
+        let file_name = file_name.to_lowercase();
+        let history = self.files.get(&file_name)?;
+
+        let mut report = String::new();
+        self.format_file(&mut report, &file_name, history);
+        Some(report.trim_start().to_string())
+    }
+}
+
+/// An archive's path relative to the Zoo Tycoon directory, with '/' as separator
+fn display_archive(archive: &str) -> String {
+    let base_path = crate::util::get_base_path();
+    let path = Path::new(archive);
+    path.strip_prefix(&base_path).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+/// Record the files an archive added to the resource map
+pub fn add_archive(archive: &str, file_names: &[String]) {
+    FILE_OVERRIDES.lock().unwrap().add_archive(&display_archive(archive), file_names);
+}
+
+/// Record a patch applied to a file
+pub fn add_patch(target: &str, mod_id: &str, patch_name: &str) {
+    let write = PatchWrite {
+        mod_id: mod_id.to_string(),
+        patch_name: patch_name.to_string(),
+    };
+    FILE_OVERRIDES.lock().unwrap().add_patch(&resource_key(target), write);
+}
+
+/// Forget the patches applied by a mod (before the mod is reloaded)
+pub fn release_mod(mod_id: &str) {
+    FILE_OVERRIDES.lock().unwrap().release_mod(mod_id);
+}
+
+/// Override report for the archives loaded so far
+pub fn report() -> String {
+    FILE_OVERRIDES.lock().unwrap().format_report()
+}
+
+/// The archives and patches that provided a single file
+pub fn file_report(file_name: &str) -> Option<String> {
+    FILE_OVERRIDES.lock().unwrap().format_file_report(file_name)
+}
+
+/// The archives loaded so far, in load order
+pub fn archive_report() -> String {
+    FILE_OVERRIDES.lock().unwrap().format_archives()
+}
+
+/// The archive and mod the current version of a file came from
+pub fn source(file_name: &str) -> Option<String> {
+    FILE_OVERRIDES.lock().unwrap().format_source(file_name)
+}
+
+/// Write the override report next to openzt.toml
+///
+/// Called once mod loading has finished.
+pub fn finish() {
+    let file_overrides = FILE_OVERRIDES.lock().unwrap();
+    let report_path = crate::util::get_base_path().join(REPORT_FILE_NAME);
+
+    info!("{} files are provided by more than one archive", file_overrides.overridden().len());
+    match std::fs::write(&report_path, file_overrides.format_report()) {
+        Ok(()) => info!("File override report written to {}", report_path.display()),
+        Err(e) => error!("Failed to write file override report to {}: {}", report_path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn write(mod_id: &str, patch_name: &str) -> PatchWrite {
+        PatchWrite {
+            mod_id: mod_id.to_string(),
+            patch_name: patch_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_later_archive_wins() {
+        let mut overrides = FileOverrides::default();
+        overrides.add_archive("zupdate/animals.ztd", &files(&["animals/elephant.ai", "animals/lion.ai"]));
+        overrides.add_archive("mods/elephant_skin.ztd", &files(&["Animals/Elephant.ai"]));
+        overrides.add_patch("animals/elephant.ai", write("finn.mod_a", "bigger_elephants"));
+
+        let overridden = overrides.overridden();
+        assert_eq!(overridden.len(), 1);
+        assert_eq!(overridden[0].0, "animals/elephant.ai");
+
+        let report = overrides.format_report();
+        assert!(report.starts_with("OpenZT file overrides: 1 files provided by more than one archive\n"));
+        assert!(report.contains("\nanimals/elephant.ai\n  zupdate/animals.ztd\n  mods/elephant_skin.ztd <- wins\n  patched by finn.mod_a: bigger_elephants\n"));
+        assert!(!report.contains("lion"));
+    }
+
+    #[test]
+    fn test_file_report() {
+        let mut overrides = FileOverrides::default();
+        overrides.add_archive("zupdate/animals.ztd", &files(&["animals/lion.ai"]));
+        overrides.add_patch("animals/new.ai", write("finn.mod_a", "add_new"));
+
+        assert_eq!(
+            overrides.format_file_report("ANIMALS/LION.AI").unwrap(),
+            "animals/lion.ai\n  zupdate/animals.ztd <- wins\n"
+        );
+        assert_eq!(
+            overrides.format_file_report("animals/new.ai").unwrap(),
+            "animals/new.ai\n  (not from an archive)\n  patched by finn.mod_a: add_new\n"
+        );
+        assert!(overrides.format_file_report("animals/tiger.ai").is_none());
+
+        overrides.release_mod("finn.mod_a");
+        assert!(overrides.format_file_report("animals/new.ai").is_none());
+    }
+
+    #[test]
+    fn test_archives_and_source() {
+        let mut overrides = FileOverrides::default();
+        overrides.add_archive("zupdate/animals.ztd", &files(&["animals/elephant.ai", "animals/lion.ai"]));
+        overrides.add_archive("mods/elephant_skin.ztd", &files(&["animals/elephant.ai"]));
+        overrides.add_patch("animals/elephant.ai", write("finn.mod_a", "bigger_elephants"));
+
+        assert_eq!(
+            overrides.format_archives(),
+            "2 archives in load order, later archives override earlier ones:\n   1. zupdate/animals.ztd (1 of 2 files in use)\n   2. mods/elephant_skin.ztd (1 of 1 files in use)\n"
+        );
+        assert_eq!(
+            overrides.format_source("Animals/Elephant.ai").unwrap(),
+            "animals/elephant.ai: from mods/elephant_skin.ztd, last modified by finn.mod_a (patch 'bigger_elephants')"
+        );
+        assert_eq!(
+            overrides.format_source("animals/lion.ai").unwrap(),
+            "animals/lion.ai: from zupdate/animals.ztd, not modified by any patch"
+        );
+        assert!(overrides.format_source("animals/tiger.ai").is_none());
+    }
+}
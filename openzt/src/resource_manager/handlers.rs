@@ -245,7 +245,7 @@ impl Handler {
                     None
                 }
             }
-            ZTFileType::Bmp | ZTFileType::Lle | ZTFileType::Tga | ZTFileType::Wav | ZTFileType::Palette => {
+            ZTFileType::Bmp | ZTFileType::Lle | ZTFileType::Tga | ZTFileType::Wav | ZTFileType::Palette | ZTFileType::Other(_) if !file_type.is_text() => {
                 if let Some(handler) = self.raw_bytes_handler {
                     info!(
                         "Raw Bytes Handler {} {} is handling file: {}",
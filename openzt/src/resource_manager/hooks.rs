@@ -25,16 +25,24 @@ mod zoo_resource_mgr {
     };
     use openzt_detour::generated::ztui_general::GET_INFO_IMAGE_NAME;
 
+    #[cfg(feature = "experimental")]
+    use crate::resource_manager::mod_updates;
     use crate::{
         mods,
         resource_manager::{
             bfresourcemgr::BFResourcePtr,
+            checksums,
             dependency_resolver::DependencyResolver,
+            file_overrides,
             lazyresourcemap::{check_file, deref_resource, get_file_ptr, is_disabled_ztd_file},
             legacy_loading::{load_resources, OPENZT_DIR0},
+            load_order_preset,
+            load_profile::{self, LoadPhase},
+            load_report::{self, LoadStage},
             mod_config::{get_openzt_config, save_openzt_config},
-            openzt_mods::{discover_mods, get_location_or_habitat_by_id},
-            validation::{log_validation_result, validate_load_order},
+            mod_list, mod_toggle,
+            openzt_mods::{discover_mods, get_location_or_habitat_by_id, hot_reload, loading::set_enabled_mod_ids, patch_conflicts, patch_dry_run},
+            validation::{apply_conflict_policy, find_conflicts, log_validation_result, validate_load_order},
         },
         util::{get_ini_path, get_string_from_memory, save_to_memory},
     };
@@ -170,12 +178,40 @@ mod zoo_resource_mgr {
             // Load OpenZT configuration
             let mut config = get_openzt_config();
 
+            load_profile::begin();
+
             // Discover all mods and pure legacy archives
             debug!("Discovering mods...");
             let discovery_result = discover_mods(&paths);
             debug!("Discovered {} OpenZT mod(s)", discovery_result.openzt_mods.len());
             debug!("Discovered {} pure legacy archive(s) in /mods/", discovery_result.pure_legacy_in_mods.len());
 
+            // Remember what's installed, for load order presets, enabling mods and update checks from the console
+            let available = discovery_result
+                .openzt_mods
+                .iter()
+                .map(|(id, (_, meta))| (id.clone(), Some(meta.version().to_string())))
+                .chain(discovery_result.pure_legacy_in_mods.iter().map(|(filename, _)| (filename.clone(), None)))
+                .collect();
+            load_order_preset::set_available(available);
+            let installed = discovery_result
+                .openzt_mods
+                .iter()
+                .map(|(id, (_, meta))| (id.clone(), mod_toggle::required_dependencies(meta)))
+                .chain(discovery_result.pure_legacy_in_mods.iter().map(|(filename, _)| (filename.clone(), Vec::new())))
+                .collect();
+            mod_toggle::set_installed(installed);
+            #[cfg(feature = "experimental")]
+            {
+                let versions = discovery_result
+                    .openzt_mods
+                    .iter()
+                    .map(|(id, (_, meta))| (id.clone(), meta.version().clone()))
+                    .collect();
+                mod_updates::set_installed(versions);
+                mod_updates::check_on_startup();
+            }
+
             // Parse disabled entries into mod IDs and ZTD filenames
             let (disabled_mods, disabled_ztds) = parse_disabled_entries(&config.mod_loading.disabled);
 
@@ -187,18 +223,25 @@ mod zoo_resource_mgr {
             // Extract just the Meta structs for the resolver (convert from tuple)
             let resolver_mods: HashMap<String, mods::Meta> = discovery_result.openzt_mods.iter().map(|(id, (_, meta))| (id.clone(), meta.clone())).collect();
             let resolver = DependencyResolver::new(resolver_mods.clone(), &discovery_result.openzt_mods);
-            let resolution_result = resolver.resolve_order(&config.mod_loading.order, &disabled_mods, &discovery_result.pure_legacy_in_mods);
+            let resolution_result = load_profile::time(LoadPhase::Resolution, "load order", || {
+                resolver.resolve_order(&config.mod_loading.order, &disabled_mods, &discovery_result.pure_legacy_in_mods)
+            });
 
             // Log any dependency resolution warnings
             for warning in &resolution_result.warnings {
                 use crate::resource_manager::dependency_resolver::ResolutionWarning;
                 match warning {
                     ResolutionWarning::CircularDependency { cycle } => {
-                        warn!("Circular dependency detected (with optional deps): {:?}", cycle);
+                        warn!("Circular dependency detected (with optional deps): {}", resolver.describe_cycle(cycle));
                     }
                     ResolutionWarning::TrulyCyclicDependency { cycle } => {
-                        error!("Truly cyclic dependency detected (required deps only): {:?}", cycle);
-                        error!("  These mods will be loaded at the end of the order");
+                        let description = resolver.describe_cycle(cycle);
+                        error!("Truly cyclic dependency detected (required deps only): {}", description);
+                        error!("  These mods will be loaded at the end of the order, alphabetically");
+                        error!("  Fix the dependencies in the meta.toml files listed above to break the cycle");
+                        for mod_id in cycle {
+                            load_report::record_error(mod_id, LoadStage::Dependency, format!("Cyclic dependency: {}", description));
+                        }
                     }
                     ResolutionWarning::FormerlyCyclicDependency { mod_id, reason } => {
                         info!("Mod '{}' had cycle resolved: {}", mod_id, reason);
@@ -208,9 +251,11 @@ mod zoo_resource_mgr {
                     }
                     ResolutionWarning::MissingRequiredDependency { mod_id, missing } => {
                         warn!("Mod '{}' requires '{}' which is not present", mod_id, missing);
+                        load_report::record_warning(mod_id, LoadStage::Dependency, format!("Requires '{}' which is not present", missing));
                     }
                     ResolutionWarning::ConflictingConstraints { mod_id, details } => {
                         warn!("Mod '{}' has conflicting constraints: {}", mod_id, details);
+                        load_report::record_warning(mod_id, LoadStage::Dependency, format!("Conflicting constraints: {}", details));
                     }
                 }
             }
@@ -236,10 +281,29 @@ mod zoo_resource_mgr {
             let disabled_set: std::collections::HashSet<_> = disabled_mods.iter().collect();
             let enabled_order: Vec<String> = resolution_result.order.iter().filter(|mod_id| !disabled_set.contains(mod_id)).cloned().collect();
 
+            // Report conflicts declared between enabled mods, skipping the declaring mods if blocked
+            let conflicts = find_conflicts(&enabled_order, &resolver_mods);
+            let enabled_order = apply_conflict_policy(enabled_order, &conflicts, &resolver_mods, config.mod_loading.conflict_policy);
+            set_enabled_mod_ids(&enabled_order);
+
             if !disabled_mods.is_empty() {
                 info!("Disabled OpenZT mods (not loading): {:?}", disabled_mods);
             }
 
+            // Remember what happens to each mod in the order, for mod_list() and Ctrl+Shift+M
+            let mod_versions: HashMap<String, String> = discovery_result
+                .openzt_mods
+                .iter()
+                .map(|(id, (_, meta))| (id.clone(), meta.version().to_string()))
+                .collect();
+            mod_list::set_load_order(mod_list::build_entries(
+                &resolution_result.order,
+                &enabled_order,
+                &disabled_mods,
+                &disabled_ztds,
+                &mod_versions,
+            ));
+
             // Load resources in resolved order (excluding disabled mods, with disabled ZTD info)
             load_resources(
                 paths,
@@ -250,6 +314,19 @@ mod zoo_resource_mgr {
                 &discovery_result.pure_legacy_in_mods,
             );
             info!("Resources loaded");
+
+            if cfg!(feature = "experimental") {
+                hot_reload::init();
+            }
+
+            if patch_dry_run::is_enabled() {
+                patch_dry_run::finish();
+            }
+            patch_conflicts::finish();
+            checksums::finish();
+            file_overrides::finish();
+            load_report::finish();
+            load_profile::finish();
         }
         return_value
     }
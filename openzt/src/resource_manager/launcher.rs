@@ -0,0 +1,213 @@
+//! Load order resolution for launchers and mod managers
+//!
+//! [`resolve_directory`] runs the discovery and dependency resolution OpenZT runs when
+//! Zoo Tycoon starts, on a game directory and without starting the game or loading any
+//! mod, so a mod manager shows exactly the load order the game will use. The result is
+//! plain data that serializes with serde, e.g. to JSON for a GUI in another language.
+//! [`discover_mods_in`] and [`DependencyResolver`] are exported for tools that need the
+//! individual steps.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use openzt_configparser::ini::Ini;
+use serde::{Deserialize, Serialize};
+
+pub use crate::mods::Meta;
+pub use crate::resource_manager::dependency_resolver::{DependencyResolver, ResolutionResult, ResolutionWarning};
+pub use crate::resource_manager::openzt_mods::loading::{discover_mods_in, DiscoveryResult};
+pub use crate::resource_manager::validation::ModConflict;
+use crate::resource_manager::{
+    mod_config::{ModLoadingConfig, OpenZTConfig},
+    validation::{apply_conflict_policy, find_conflicts},
+};
+
+/// An OpenZT mod found in the game directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscoveredMod {
+    pub mod_id: String,
+    pub name: String,
+    pub version: String,
+    /// Archive or unpacked mod directory the mod's meta.toml was read from
+    pub archive: String,
+}
+
+/// A dependency no discovered mod provides
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnresolvedDependency {
+    /// Mod that declared the dependency
+    pub mod_id: String,
+    pub missing: String,
+    pub optional: bool,
+}
+
+/// The load order a game directory resolves to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadOrderResolution {
+    /// Every mod ID and /mods/ archive in load order, including disabled ones, as saved to openzt.toml
+    pub order: Vec<String>,
+    /// The entries of `order` the game loads, without disabled mods or mods blocked by a conflict
+    pub enabled: Vec<String>,
+    /// OpenZT mods found, sorted by mod ID
+    pub mods: Vec<DiscoveredMod>,
+    /// Archives in /mods/ without a meta.toml
+    pub legacy_archives: Vec<String>,
+    pub unresolved: Vec<UnresolvedDependency>,
+    /// Conflicts declared in meta.toml between enabled mods
+    pub conflicts: Vec<ModConflict>,
+    /// Dependency cycles and ordering constraints that can't all be met
+    pub warnings: Vec<ResolutionWarning>,
+}
+
+/// Resolve the load order of the Zoo Tycoon install in `game_dir`, using its openzt.toml and zoo.ini
+///
+/// openzt.toml isn't created or updated, a missing one resolves with the default settings.
+pub fn resolve_directory(game_dir: impl AsRef<Path>) -> anyhow::Result<LoadOrderResolution> {
+    let game_dir = game_dir.as_ref();
+    let config = read_config(game_dir)?;
+    let paths = resource_paths(game_dir)?;
+    let discovery = discover_mods_in(&paths, &game_dir.join("mods"));
+    Ok(resolve(&discovery, &config.mod_loading))
+}
+
+/// openzt.toml in `game_dir`, or the defaults if there isn't one
+fn read_config(game_dir: &Path) -> anyhow::Result<OpenZTConfig> {
+    let path = game_dir.join("openzt.toml");
+    if !path.exists() {
+        return Ok(OpenZTConfig::default());
+    }
+    let content = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// The resource paths from zoo.ini with ./mods first, as the game adds it, joined to `game_dir`
+fn resource_paths(game_dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut paths: Vec<String> = Vec::new();
+    let ini_path = game_dir.join("zoo.ini");
+    if ini_path.exists() {
+        let mut zoo_ini = Ini::new();
+        zoo_ini.set_comment_symbols(&['#']);
+        zoo_ini.load(&ini_path).map_err(|e| anyhow!("Failed to load {}: {}", ini_path.display(), e))?;
+        if let Some(resource_paths) = zoo_ini.get("resource", "path") {
+            paths = resource_paths.split(';').map(|s| s.to_owned()).collect();
+        }
+    }
+    if !paths.iter().any(|s| s.trim() == "./mods") {
+        paths.insert(0, "./mods".to_owned());
+    }
+    Ok(paths.iter().map(|path| game_dir.join(path).to_string_lossy().into_owned()).collect())
+}
+
+/// Resolve the order of the discovered mods with the `[mod_loading]` settings, as the game does at startup
+fn resolve(discovery: &DiscoveryResult, mod_loading: &ModLoadingConfig) -> LoadOrderResolution {
+    let (disabled_ztds, disabled_mods): (Vec<String>, Vec<String>) = mod_loading.disabled.iter().cloned().partition(|entry| entry.to_lowercase().ends_with(".ztd"));
+
+    let mods: HashMap<String, Meta> = discovery.openzt_mods.iter().map(|(id, (_, meta))| (id.clone(), meta.clone())).collect();
+    let resolver = DependencyResolver::new(mods.clone(), &discovery.openzt_mods);
+    let resolution = resolver.resolve_order(&mod_loading.order, &disabled_mods, &discovery.pure_legacy_in_mods);
+
+    let enabled: Vec<String> = resolution.order.iter().filter(|mod_id| !disabled_mods.contains(mod_id)).cloned().collect();
+    let conflicts = find_conflicts(&enabled, &mods);
+    let enabled = apply_conflict_policy(enabled, &conflicts, &mods, mod_loading.conflict_policy)
+        .into_iter()
+        .filter(|id| !disabled_ztds.iter().any(|ztd| ztd.eq_ignore_ascii_case(id)))
+        .collect();
+
+    let mut unresolved = Vec::new();
+    let mut warnings = Vec::new();
+    for warning in resolution.warnings {
+        match warning {
+            ResolutionWarning::MissingOptionalDependency { mod_id, missing } => unresolved.push(UnresolvedDependency { mod_id, missing, optional: true }),
+            ResolutionWarning::MissingRequiredDependency { mod_id, missing } => unresolved.push(UnresolvedDependency {
+                mod_id,
+                missing,
+                optional: false,
+            }),
+            other => warnings.push(other),
+        }
+    }
+
+    let mut discovered: Vec<DiscoveredMod> = discovery
+        .openzt_mods
+        .iter()
+        .map(|(mod_id, (archive, meta))| DiscoveredMod {
+            mod_id: mod_id.clone(),
+            name: meta.name().clone(),
+            version: meta.version().to_string(),
+            archive: archive.clone(),
+        })
+        .collect();
+    discovered.sort_by(|a, b| a.mod_id.cmp(&b.mod_id));
+
+    LoadOrderResolution {
+        order: resolution.order,
+        enabled,
+        mods: discovered,
+        legacy_archives: discovery.pure_legacy_in_mods.iter().map(|(name, _)| name.clone()).collect(),
+        unresolved,
+        conflicts,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn game_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openzt-launcher-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mods = [
+            ("finn.sun", "version = \"1.0.0\"\n"),
+            (
+                "finn.moon",
+                "version = \"2.1.0\"\ndependencies = [\n    { mod_id = \"finn.sun\", name = \"Sun\", ordering = \"after\" },\n    { mod_id = \"finn.stars\", name = \"Stars\", optional = true, ordering = \"after\" },\n]\n",
+            ),
+            ("finn.comet", "version = \"1.0.0\"\ndependencies = [{ mod_id = \"finn.dust\", name = \"Dust\" }]\n"),
+            (
+                "finn.eclipse",
+                "version = \"0.3.0\"\nconflicts = [{ mod_id = \"finn.sun\", reason = \"Both change the sky\" }]\n",
+            ),
+        ];
+        for (mod_id, rest) in mods {
+            let mod_dir = dir.join("mods").join(mod_id);
+            std::fs::create_dir_all(&mod_dir).unwrap();
+            let meta = format!(
+                "name = \"{}\"\ndescription = \"A mod\"\nauthors = [\"Finn\"]\nmod_id = \"{}\"\n{}",
+                mod_id, mod_id, rest
+            );
+            std::fs::write(mod_dir.join("meta.toml"), meta).unwrap();
+        }
+        std::fs::write(dir.join("openzt.toml"), "[mod_loading]\ndisabled = [\"finn.comet\"]\nconflict_policy = \"block\"\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolve_directory() {
+        let dir = game_dir();
+        let resolution = resolve_directory(&dir).unwrap();
+
+        assert_eq!(resolution.order, vec!["finn.eclipse", "finn.sun", "finn.moon"]);
+        assert_eq!(resolution.enabled, vec!["finn.sun", "finn.moon"]);
+
+        let mod_ids: Vec<&str> = resolution.mods.iter().map(|discovered| discovered.mod_id.as_str()).collect();
+        assert_eq!(mod_ids, vec!["finn.comet", "finn.eclipse", "finn.moon", "finn.sun"]);
+        assert_eq!(resolution.mods[2].version, "2.1.0");
+        assert_eq!(resolution.mods[2].archive, "finn.moon");
+        assert_eq!(
+            resolution.unresolved,
+            vec![UnresolvedDependency {
+                mod_id: "finn.moon".to_string(),
+                missing: "finn.stars".to_string(),
+                optional: true,
+            }]
+        );
+        assert_eq!(resolution.conflicts.len(), 1);
+        assert_eq!(resolution.conflicts[0].mod_id, "finn.eclipse");
+        assert!(serde_json::to_string(&resolution).unwrap().contains("\"enabled\":"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -21,12 +21,67 @@ use crate::{
     util::{ref_from_memory, ZTString},
 };
 
-static LAZY_RESOURCE_MAP: LazyLock<Mutex<HashMap<String, LazyResource>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static LAZY_RESOURCE_MAP: LazyLock<Mutex<HashMap<Arc<str>, LazyResource>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
 static TOTAL_LOADED_BYTES: AtomicU64 = AtomicU64::new(0);
 
+// Cache counters since startup, shown by the cache_stats() console command
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static CACHE_REDECODES: AtomicU64 = AtomicU64::new(0);
+static CACHE_EVICTIONS: AtomicU64 = AtomicU64::new(0);
+static CACHE_EVICTED_BYTES: AtomicU64 = AtomicU64::new(0);
+
 // Track files that originated from disabled ZTDs
 // Used to log errors only when vanilla actually tries to load them
-static DISABLED_ZTD_FILES: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+static DISABLED_ZTD_FILES: LazyLock<Mutex<HashSet<Arc<str>>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+// Interned resource paths, shared by the resource map's keys and file names and the disabled ZTD files
+// Large mod sets add the same few thousand paths over and over, so each is only stored once
+static INTERNED_PATHS: LazyLock<Mutex<HashSet<Arc<str>>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// `case_insensitive_paths` in the `[mod_loading]` section of openzt.toml
+static CASE_INSENSITIVE_PATHS: LazyLock<bool> = LazyLock::new(|| crate::resource_manager::mod_config::get_openzt_config().mod_loading.case_insensitive_paths);
+
+/// Lowercase a path, with '/' as separator and no leading "./" or "/"
+pub fn normalize_path(path: &str) -> String {
+    let normalized = path.replace('\\', "/").to_ascii_lowercase();
+    let normalized = normalized.strip_prefix("./").unwrap_or(&normalized);
+    normalized.trim_start_matches('/').to_string()
+}
+
+/// Key of a file in the resource map, lowercase and normalized with `case_insensitive_paths`
+pub fn resource_key(file_name: &str) -> String {
+    if *CASE_INSENSITIVE_PATHS {
+        normalize_path(file_name)
+    } else {
+        file_name.to_ascii_lowercase()
+    }
+}
+
+/// The shared copy of `path`, stored once however many resources refer to it
+pub fn intern(path: &str) -> Arc<str> {
+    let mut paths = INTERNED_PATHS.lock().unwrap();
+    if let Some(interned) = paths.get(path) {
+        return interned.clone();
+    }
+    let interned: Arc<str> = Arc::from(path);
+    paths.insert(interned.clone());
+    interned
+}
+
+/// Forget an interned path once nothing but the interner refers to it
+fn release(path: Arc<str>) {
+    let mut paths = INTERNED_PATHS.lock().unwrap();
+    // One reference held by the interner and one by `path`
+    if Arc::strong_count(&path) == 2 {
+        paths.remove(&path);
+    }
+}
+
+/// Key to compare paths inside OpenZT mods and patch targets by, exact unless `case_insensitive_paths` is on
+pub fn path_key(path: &str) -> String {
+    if *CASE_INSENSITIVE_PATHS { normalize_path(path) } else { path.to_string() }
+}
 
 struct LazyResourceMap {}
 
@@ -39,23 +94,26 @@ enum ResourceBacking {
 
 struct ConcreteResource {
     archive_name: Option<String>,
-    filename: String,
+    filename: Arc<str>,
     type_: ZTFileType,
     data: u32,
 }
 
 struct LazyResource {
     pub backing: ResourceBacking,
-    pub filename: String,
+    /// Interned, the same allocation as the key when the name is already lowercase
+    pub filename: Arc<str>,
     pub type_: ZTFileType,
     last_accessed: Instant,
     ref_count: Arc<AtomicU32>,
+    /// Decoded before and evicted since, so the next access decodes it again
+    evicted: bool,
 }
 
 impl LazyResourceMap {
     fn remove(file_name: String) -> Option<()> {
         let mut binding = LAZY_RESOURCE_MAP.lock().unwrap();
-        let value = binding.remove(&file_name)?;
+        let (key, value) = binding.remove_entry(file_name.as_str())?;
 
         // Subtract size if resource was loaded
         let size = match &value.backing {
@@ -66,7 +124,10 @@ impl LazyResourceMap {
             TOTAL_LOADED_BYTES.fetch_sub(size, Ordering::Relaxed);
         }
 
+        let filename = value.filename.clone();
         LazyResourceMap::drop_inner(value);
+        release(key);
+        release(filename);
         Some(())
     }
 
@@ -83,26 +144,17 @@ impl LazyResourceMap {
 
         trace!("Dropping resource: {} (type: {:?}, size: {} bytes)", resource.filename, resource.type_, size);
 
-        match resource.type_ {
-            ZTFileType::Ini
-            | ZTFileType::Ai
-            | ZTFileType::Ani
-            | ZTFileType::Cfg
-            | ZTFileType::Lyt
-            | ZTFileType::Scn
-            | ZTFileType::Uca
-            | ZTFileType::Ucs
-            | ZTFileType::Ucb
-            | ZTFileType::Toml
-            | ZTFileType::Txt => {
-                let data_string = unsafe { CString::from_raw(data as *mut i8) };
-                drop(data_string);
-            }
-            ZTFileType::Animation | ZTFileType::Bmp | ZTFileType::Lle | ZTFileType::Tga | ZTFileType::Wav | ZTFileType::Palette | ZTFileType::Zoo => {
-                let data_vec: Box<[u8]> =
-                    unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(bf_resource_ptr.data_ptr as *mut _, bf_resource_ptr.content_size as usize)) };
-                drop(data_vec);
-            }
+        if resource.type_.is_text() {
+            let data_string = unsafe { CString::from_raw(data as *mut i8) };
+            drop(data_string);
+        } else {
+            let data_vec: Box<[u8]> = unsafe {
+                Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                    bf_resource_ptr.data_ptr as *mut _,
+                    bf_resource_ptr.content_size as usize,
+                ))
+            };
+            drop(data_vec);
         }
         drop(bf_resource_ptr);
     }
@@ -111,27 +163,26 @@ impl LazyResourceMap {
         let file_type = match ZTFileType::try_from(Path::new(&file_name)) {
             Ok(file_type) => file_type,
             Err(e) => {
-                // Skip logging errors for known vanilla Zoo Tycoon files with unsupported types
-                let known_vanilla_files = ["objects/ddogstnd/fancyblg_icons.zip", "bfupdateres.h"];
-                if !known_vanilla_files.iter().any(|&f| file_name.eq_ignore_ascii_case(f)) {
-                    error!("Error inserting file: {} error: {}", file_name, e);
-                }
+                error!("Error inserting file: {} error: {}", file_name, e);
                 return;
             }
         };
 
         let mut binding = LAZY_RESOURCE_MAP.lock().unwrap();
         if let Some(existing) = binding.insert(
-            file_name.clone().to_ascii_lowercase(),
+            intern(&resource_key(&file_name)),
             LazyResource {
                 backing: ResourceBacking::LazyZipFile { archive },
-                filename: file_name.clone(),
+                filename: intern(&file_name),
                 type_: file_type,
                 last_accessed: Instant::now(),
                 ref_count: Arc::new(AtomicU32::new(0)),
+                evicted: false,
             },
         ) {
+            let old_filename = existing.filename.clone();
             LazyResourceMap::drop_inner(existing);
+            release(old_filename);
         }
     }
 
@@ -142,13 +193,14 @@ impl LazyResourceMap {
 
         let mut binding = LAZY_RESOURCE_MAP.lock().unwrap();
         if let Some(existing) = binding.insert(
-            file_name.to_ascii_lowercase(),
+            intern(&resource_key(&file_name)),
             LazyResource {
                 backing: ResourceBacking::Custom { data },
-                filename: file_name.clone(),
+                filename: intern(&file_name),
                 type_: file_type,
                 last_accessed: Instant::now(),
                 ref_count: Arc::new(AtomicU32::new(0)),
+                evicted: false,
             },
         ) {
             // Subtract size of replaced resource
@@ -159,14 +211,16 @@ impl LazyResourceMap {
             if old_size > 0 {
                 TOTAL_LOADED_BYTES.fetch_sub(old_size, Ordering::Relaxed);
             }
+            let old_filename = existing.filename.clone();
             LazyResourceMap::drop_inner(existing);
+            release(old_filename);
         }
     }
 
     fn get(key: &str) -> anyhow::Result<Option<ConcreteResource>> {
         let mut binding = LAZY_RESOURCE_MAP.lock().unwrap();
-        let lowercase_key = key.to_ascii_lowercase();
-        let Some(resource) = binding.get_mut(&lowercase_key) else {
+        let lowercase_key = resource_key(key);
+        let Some(resource) = binding.get_mut(lowercase_key.as_str()) else {
             info!("LazyResource not found: {}", lowercase_key);
             return Ok(None);
         };
@@ -179,6 +233,13 @@ impl LazyResourceMap {
 
         // Track if we're loading a new resource (for triggering auto-unload)
         let was_lazy = matches!(resource.backing, ResourceBacking::LazyZipFile { .. });
+        if !was_lazy {
+            CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+        } else if resource.evicted {
+            CACHE_REDECODES.fetch_add(1, Ordering::Relaxed);
+        } else {
+            CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+        }
 
         // Clone the fields we need before potentially dropping the binding
         let filename = resource.filename.clone();
@@ -190,17 +251,16 @@ impl LazyResourceMap {
                 let mut binding = archive.lock().unwrap();
                 let archive_name = binding.name().to_string();
                 let mut file = binding.by_name(&filename).with_context(|| format!("Error finding file in archive: {}", filename))?;
-                let mut file_buffer = vec![0u8; file.size() as usize].into_boxed_slice();
-
-                file.read_exact(&mut file_buffer).with_context(|| format!("Error reading file: {}", filename))?;
+                let file_buffer = file.read_all().with_context(|| format!("Error reading {} from {}", filename, archive_name))?;
+                let file_buffer = type_.load(&filename, file_buffer)?;
 
                 let ztfile = ZTFile::builder()
-                    .file_name(filename.clone())
+                    .file_name(filename.to_string())
                     .file_size(file_buffer.len() as u32)
                     .type_(type_)
                     .raw_data(file_buffer)
                     .build();
-                let data = ztfile_to_raw_resource(&archive_name, filename.clone(), ztfile)?;
+                let data = ztfile_to_raw_resource(&archive_name, filename.to_string(), ztfile)?;
                 resource.backing = ResourceBacking::LoadedZipFile {
                     archive: archive.clone(),
                     data: data.2,
@@ -258,16 +318,13 @@ impl LazyResourceMap {
 
     fn file_names() -> Vec<String> {
         let binding = LAZY_RESOURCE_MAP.lock().unwrap();
-        binding.keys().cloned().collect()
+        binding.keys().map(|key| key.to_string()).collect()
     }
 
     /// Unload a single resource (transition LoadedZipFile -> LazyZipFile)
     /// Returns the size of the unloaded resource
-    /// Only unloads if the resource's ref_count is 0
-    fn unload_resource(key: &str) -> Option<u64> {
-        let mut binding = LAZY_RESOURCE_MAP.lock().unwrap();
-        let resource = binding.get_mut(key)?;
-
+    /// Only unloads if the resource's ref_count is 0, the resource is decoded from its archive again on the next access
+    fn evict(resource: &mut LazyResource) -> Option<u64> {
         // Only unload LoadedZipFile (NOT Custom or LazyZipFile)
         let (data, archive) = match &resource.backing {
             ResourceBacking::LoadedZipFile { data, archive } => (*data, archive.clone()),
@@ -289,6 +346,7 @@ impl LazyResourceMap {
         // Transition back to lazy
         resource.backing = ResourceBacking::LazyZipFile { archive: archive.clone() };
         resource.last_accessed = Instant::now();
+        resource.evicted = true;
 
         // Drop the loaded data (preserve ref_count)
         let temp_resource = LazyResource {
@@ -297,41 +355,29 @@ impl LazyResourceMap {
             type_: resource.type_,
             last_accessed: Instant::now(),
             ref_count: resource.ref_count.clone(),
+            evicted: true,
         };
         Self::drop_inner(temp_resource);
 
-        // Update global counter
+        // Update global counters
         TOTAL_LOADED_BYTES.fetch_sub(size, Ordering::Relaxed);
+        CACHE_EVICTIONS.fetch_add(1, Ordering::Relaxed);
+        CACHE_EVICTED_BYTES.fetch_add(size, Ordering::Relaxed);
 
         Some(size)
     }
 
     /// Unload all loaded resources
-    /// Only unloads resources with ref_count == 0
+    /// Only unloads resources with ref_count == 0, which stay in the map to be decoded again on demand
     fn unload_all_loaded() -> UnloadResult {
         let mut binding = LAZY_RESOURCE_MAP.lock().unwrap();
-        let keys_to_unload: Vec<String> = binding
-            .iter()
-            .filter(|(_, r)| {
-                // Only unload LoadedZipFile with ref_count == 0
-                matches!(r.backing, ResourceBacking::LoadedZipFile { .. }) && r.ref_count.load(Ordering::Relaxed) == 0
-            })
-            .map(|(k, _)| k.clone())
-            .collect();
 
-        let count = keys_to_unload.len();
+        let mut count = 0;
         let mut total_size = 0u64;
-
-        for key in keys_to_unload {
-            if let Some(resource) = binding.remove(&key) {
-                // Get size before dropping
-                let size = match &resource.backing {
-                    ResourceBacking::LoadedZipFile { data, .. } => unsafe { &*(*data as *const BFResourcePtr) }.content_size as u64,
-                    _ => 0,
-                };
+        for resource in binding.values_mut() {
+            if let Some(size) = Self::evict(resource) {
+                count += 1;
                 total_size += size;
-                Self::drop_inner(resource);
-                TOTAL_LOADED_BYTES.fetch_sub(size, Ordering::Relaxed);
             }
         }
 
@@ -353,8 +399,8 @@ impl LazyResourceMap {
         // Use running total instead of calculating
         let current_size = TOTAL_LOADED_BYTES.load(Ordering::Relaxed);
 
-        // Only unload if over max threshold
-        if current_size <= max_bytes {
+        // Only unload if over max threshold (0 = no limit)
+        if max_bytes == 0 || current_size <= max_bytes {
             return;
         }
 
@@ -363,7 +409,7 @@ impl LazyResourceMap {
         // Collect candidates: (key, last_accessed, size, is_custom)
         // Custom resources are counted in size but NEVER unloaded
         // Only consider resources with ref_count == 0
-        let mut candidates: Vec<(String, Instant, u64, bool)> = binding
+        let mut candidates: Vec<(Arc<str>, Instant, u64, bool)> = binding
             .iter()
             .filter_map(|(k, r)| {
                 // Skip resources with active refs
@@ -407,13 +453,9 @@ impl LazyResourceMap {
             let is_stale = now.duration_since(accessed) > stale_duration;
             let still_over_target = unloadable_total > target_bytes;
 
-            if is_stale || still_over_target {
-                drop(binding); // Release lock before calling unload_resource
-                if Self::unload_resource(&key).is_some() {
-                    unloaded_size += size;
-                    unloaded_count += 1;
-                }
-                binding = LAZY_RESOURCE_MAP.lock().unwrap(); // Re-acquire for next iteration
+            if (is_stale || still_over_target) && binding.get_mut(&key).and_then(Self::evict).is_some() {
+                unloaded_size += size;
+                unloaded_count += 1;
             }
         }
 
@@ -429,11 +471,11 @@ impl LazyResourceMap {
 }
 
 pub fn check_file(file_name: &str) -> bool {
-    LazyResourceMap::contains_key(&file_name.to_lowercase())
+    LazyResourceMap::contains_key(&resource_key(file_name))
 }
 
 pub fn get_file_ptr(file_name: &str) -> Option<u32> {
-    if let Ok(Some(resource)) = LazyResourceMap::get(&file_name.to_lowercase()) {
+    if let Ok(Some(resource)) = LazyResourceMap::get(file_name) {
         Some(resource.data)
     } else {
         None
@@ -492,7 +534,7 @@ pub fn add_lazy(file_name: String, archive: Arc<Mutex<ZtdArchive>>) {
 }
 
 pub fn remove_resource(file_name: &str) -> bool {
-    LazyResourceMap::remove(file_name.to_lowercase()).is_some()
+    LazyResourceMap::remove(resource_key(file_name)).is_some()
 }
 
 /// Update or insert a resource in the resource map
@@ -511,8 +553,24 @@ pub fn update_resource(file_name: String, file_type: ZTFileType, data: u32) {
 /// Cache statistics for resource management
 pub struct CacheStats {
     pub loaded_resources: usize,
+    /// Resources not decoded yet, or evicted since
+    pub lazy_resources: usize,
     pub total_memory_bytes: u64,
     pub total_memory_mb: u64,
+    /// Decoded resources that can't be evicted (created by OpenZT or mods rather than read from an archive)
+    pub pinned_memory_bytes: u64,
+    /// `max_memory_mb` from openzt.toml, 0 for no limit
+    pub max_memory_mb: u32,
+    /// Accesses to a resource that was already decoded
+    pub hits: u64,
+    /// Accesses that decoded a resource for the first time
+    pub misses: u64,
+    /// Accesses that decoded an evicted resource again
+    pub redecodes: u64,
+    pub evictions: u64,
+    pub evicted_bytes: u64,
+    /// Distinct paths stored for the resource map, each shared by every resource with that path
+    pub interned_paths: usize,
 }
 
 /// Result of unloading resources
@@ -531,6 +589,7 @@ pub fn unload_all_resources() -> UnloadResult {
 
 /// Get current cache statistics
 pub fn get_cache_stats() -> CacheStats {
+    let max_memory_mb = crate::resource_manager::mod_config::get_openzt_config().resource_cache.max_memory_mb;
     let binding = LAZY_RESOURCE_MAP.lock().unwrap();
 
     // Count loaded resources (both LoadedZipFile and Custom)
@@ -539,13 +598,30 @@ pub fn get_cache_stats() -> CacheStats {
         .filter(|r| matches!(r.backing, ResourceBacking::LoadedZipFile { .. } | ResourceBacking::Custom { .. }))
         .count();
 
+    let pinned_size = binding
+        .values()
+        .filter_map(|r| match &r.backing {
+            ResourceBacking::Custom { data } => Some(unsafe { &*(*data as *const BFResourcePtr) }.content_size as u64),
+            _ => None,
+        })
+        .sum();
+
     // Use global counter for total size (includes custom resources)
     let total_size = TOTAL_LOADED_BYTES.load(Ordering::Relaxed);
 
     CacheStats {
         loaded_resources: loaded_count,
+        lazy_resources: binding.len() - loaded_count,
         total_memory_bytes: total_size,
         total_memory_mb: total_size / (1024 * 1024),
+        pinned_memory_bytes: pinned_size,
+        max_memory_mb,
+        hits: CACHE_HITS.load(Ordering::Relaxed),
+        misses: CACHE_MISSES.load(Ordering::Relaxed),
+        redecodes: CACHE_REDECODES.load(Ordering::Relaxed),
+        evictions: CACHE_EVICTIONS.load(Ordering::Relaxed),
+        evicted_bytes: CACHE_EVICTED_BYTES.load(Ordering::Relaxed),
+        interned_paths: INTERNED_PATHS.lock().unwrap().len(),
     }
 }
 
@@ -554,9 +630,9 @@ pub fn get_cache_stats() -> CacheStats {
 /// This should be called when a resource is acquired for use.
 /// Resources with ref_count > 0 will not be unloaded.
 pub fn increment_ref(file_name: &str) -> bool {
-    let lowercase_key = file_name.to_lowercase();
+    let lowercase_key = resource_key(file_name);
     let binding = LAZY_RESOURCE_MAP.lock().unwrap();
-    if let Some(resource) = binding.get(&lowercase_key) {
+    if let Some(resource) = binding.get(lowercase_key.as_str()) {
         resource.ref_count.fetch_add(1, Ordering::Relaxed);
         true
     } else {
@@ -569,9 +645,9 @@ pub fn increment_ref(file_name: &str) -> bool {
 /// This should be called when a resource is released.
 /// Returns the new ref count (0 means the resource can now be unloaded).
 pub fn decrement_ref(file_name: &str) -> Option<u32> {
-    let lowercase_key = file_name.to_lowercase();
+    let lowercase_key = resource_key(file_name);
     let binding = LAZY_RESOURCE_MAP.lock().unwrap();
-    if let Some(resource) = binding.get(&lowercase_key) {
+    if let Some(resource) = binding.get(lowercase_key.as_str()) {
         // We use fetch_sub with a check to prevent going below 0
         let mut old_count = resource.ref_count.load(Ordering::Relaxed);
         while old_count > 0 {
@@ -593,9 +669,9 @@ pub fn decrement_ref(file_name: &str) -> Option<u32> {
 ///
 /// Returns None if the resource doesn't exist.
 pub fn get_ref_count(file_name: &str) -> Option<u32> {
-    let lowercase_key = file_name.to_lowercase();
+    let lowercase_key = resource_key(file_name);
     let binding = LAZY_RESOURCE_MAP.lock().unwrap();
-    binding.get(&lowercase_key).map(|r| r.ref_count.load(Ordering::Relaxed))
+    binding.get(lowercase_key.as_str()).map(|r| r.ref_count.load(Ordering::Relaxed))
 }
 
 /// Dereference a resource by file name
@@ -670,7 +746,7 @@ pub fn create_empty_resource(filename: String, file_type: ZTFileType) -> anyhow:
 /// * `true` if the file exists in the resource map
 /// * `false` if the file is not in the resource map
 pub fn check_file_loaded(file_name: &str) -> bool {
-    LAZY_RESOURCE_MAP.lock().unwrap().contains_key(&file_name.to_lowercase())
+    LAZY_RESOURCE_MAP.lock().unwrap().contains_key(resource_key(file_name).as_str())
 }
 
 /// Mark a file as originating from a disabled ZTD
@@ -681,7 +757,7 @@ pub fn check_file_loaded(file_name: &str) -> bool {
 /// # Arguments
 /// * `file_name` - The file name to mark (case-insensitive)
 pub fn mark_disabled_ztd_file(file_name: &str) {
-    DISABLED_ZTD_FILES.lock().unwrap().insert(file_name.to_lowercase());
+    DISABLED_ZTD_FILES.lock().unwrap().insert(intern(&resource_key(file_name)));
 }
 
 /// Check if a file originated from a disabled ZTD
@@ -695,5 +771,146 @@ pub fn mark_disabled_ztd_file(file_name: &str) {
 /// * `true` if the file was marked as coming from a disabled ZTD
 /// * `false` otherwise
 pub fn is_disabled_ztd_file(file_name: &str) -> bool {
-    DISABLED_ZTD_FILES.lock().unwrap().contains(&file_name.to_lowercase())
+    DISABLED_ZTD_FILES.lock().unwrap().contains(resource_key(file_name).as_str())
+}
+
+/// Contents of the resource map at one point, to put back with `restore_snapshot_for_tests`
+#[cfg(feature = "integration-tests")]
+pub struct ResourceMapSnapshot {
+    resources: HashMap<String, SnapshotResource>,
+}
+
+#[cfg(feature = "integration-tests")]
+enum SnapshotResource {
+    /// Read from an archive, added back undecoded
+    Archive { filename: String, archive: Arc<Mutex<ZtdArchive>> },
+    /// Created by OpenZT or a mod, with a copy of the content to create it again
+    Custom {
+        filename: String,
+        type_: ZTFileType,
+        data: u32,
+        zip_name: String,
+        content: Box<[u8]>,
+    },
+}
+
+#[cfg(feature = "integration-tests")]
+impl SnapshotResource {
+    fn new(resource: &LazyResource) -> Self {
+        match &resource.backing {
+            ResourceBacking::LazyZipFile { archive } | ResourceBacking::LoadedZipFile { archive, .. } => SnapshotResource::Archive {
+                filename: resource.filename.to_string(),
+                archive: archive.clone(),
+            },
+            ResourceBacking::Custom { data } => SnapshotResource::Custom {
+                filename: resource.filename.to_string(),
+                type_: resource.type_,
+                data: *data,
+                zip_name: unsafe { ref_from_memory::<BFResourcePtr>(*data) }.bf_zip_name.copy_to_string(),
+                content: custom_content(*data),
+            },
+        }
+    }
+
+    /// Whether `resource` is still the one this was taken from, with the same content
+    fn matches(&self, resource: &LazyResource) -> bool {
+        match (self, &resource.backing) {
+            (
+                SnapshotResource::Archive { archive, .. },
+                ResourceBacking::LazyZipFile { archive: current } | ResourceBacking::LoadedZipFile { archive: current, .. },
+            ) => Arc::ptr_eq(archive, current),
+            (SnapshotResource::Custom { data, content, .. }, ResourceBacking::Custom { data: current }) => data == current && *content == custom_content(*current),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "integration-tests")]
+fn custom_content(data: u32) -> Box<[u8]> {
+    let resource_ptr = unsafe { ref_from_memory::<BFResourcePtr>(data) };
+    unsafe { slice::from_raw_parts(resource_ptr.data_ptr as *const u8, resource_ptr.content_size as usize) }.into()
+}
+
+/// Take a snapshot of the resource map (for integration tests)
+#[cfg(feature = "integration-tests")]
+pub fn snapshot_for_tests() -> ResourceMapSnapshot {
+    let binding = LAZY_RESOURCE_MAP.lock().unwrap();
+    ResourceMapSnapshot {
+        resources: binding.iter().map(|(key, resource)| (key.to_string(), SnapshotResource::new(resource))).collect(),
+    }
+}
+
+/// Put the resource map back as it was when `snapshot` was taken (for integration tests)
+///
+/// Resources added since are removed, and removed or changed ones are created again.
+/// Returns how many resources were removed or created.
+#[cfg(feature = "integration-tests")]
+pub fn restore_snapshot_for_tests(snapshot: &ResourceMapSnapshot) -> anyhow::Result<usize> {
+    let (added, changed): (Vec<String>, Vec<&SnapshotResource>) = {
+        let binding = LAZY_RESOURCE_MAP.lock().unwrap();
+        (
+            binding
+                .keys()
+                .filter(|key| !snapshot.resources.contains_key(key.as_ref()))
+                .map(|key| key.to_string())
+                .collect(),
+            snapshot
+                .resources
+                .iter()
+                .filter(|(key, resource)| !binding.get(key.as_str()).is_some_and(|current| resource.matches(current)))
+                .map(|(_, resource)| resource)
+                .collect(),
+        )
+    };
+
+    for key in &added {
+        LazyResourceMap::remove(key.clone());
+    }
+    for resource in &changed {
+        match resource {
+            SnapshotResource::Archive { filename, archive } => LazyResourceMap::insert_lazy(filename.clone(), archive.clone()),
+            SnapshotResource::Custom {
+                filename,
+                type_,
+                zip_name,
+                content,
+                ..
+            } => {
+                let length = content.len() as u32;
+                let ztfile = if type_.is_text() {
+                    ZTFile::Text(CString::new(content.to_vec())?, *type_, length)
+                } else {
+                    ZTFile::RawBytes(content.clone(), *type_, length)
+                };
+                let (file_name, type_, data) = crate::resource_manager::ztfile::raw_resource_in_zip(zip_name.clone(), filename.clone(), ztfile)?;
+                LazyResourceMap::insert_custom(file_name, type_, data);
+            }
+        }
+    }
+    Ok(added.len() + changed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let path = intern("animals/test-intern/elephant.ai");
+        assert!(Arc::ptr_eq(&path, &intern(&String::from("animals/test-intern/elephant.ai"))));
+        assert!(!Arc::ptr_eq(&path, &intern("animals/test-intern/Elephant.ai")));
+
+        release(intern("animals/test-intern/Elephant.ai"));
+        assert!(!INTERNED_PATHS.lock().unwrap().contains("animals/test-intern/Elephant.ai"));
+        release(path.clone());
+        assert!(INTERNED_PATHS.lock().unwrap().contains("animals/test-intern/elephant.ai"));
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("Animals\\Elephant.AI"), "animals/elephant.ai");
+        assert_eq!(normalize_path("./ui/Sharedui/listbk/bk.ani"), "ui/sharedui/listbk/bk.ani");
+        assert_eq!(normalize_path("/objects/fence/N"), "objects/fence/n");
+        assert_eq!(normalize_path("animals/elephant.ai"), "animals/elephant.ai");
+    }
 }
@@ -1,65 +1,122 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    panic::{self, AssertUnwindSafe},
     path::{Path, PathBuf},
     str,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
+use anyhow::anyhow;
+use indexmap::IndexMap;
 use openzt_configparser::ini::Ini;
 use regex::Regex;
 use std::sync::LazyLock;
 use tracing::{debug, error, info, trace, warn};
 use walkdir::WalkDir;
 
+/// Built-in map of regex patterns to permitted ZTD archive names
+/// Extended or replaced by `permitted_archives` in the `[mod_loading]` section of openzt.toml
+const DEFAULT_PERMITTED_ARCHIVE_PATTERNS: &[(&str, &[&str])] = &[
+    // xpac*.cfg files are only permitted from these specific archives
+    // Regex: ^xpac.*\.cfg$ matches "xpac03.cfg", "xpac99.cfg", "xpacwhatever.cfg"
+    (r"^xpac.*\.cfg$", &[
+        "zupdate/config2.ztd", // Base game config
+        "xpack1/config2.ztd",  // Dino Danger pack
+        "xpack2/config3.ztd",  // Marine Mania pack
+    ]),
+    (r"^ui/xpac.lyt$", &["zupdate/ui2.ztd", "zupdate1/ui4.ztd"]),
+    (r"^ui/sharedui/listbk/.*", &["zupdate/ui2.ztd", "zupdate1/ui4.ztd"]),
+    // Future patterns can be added here, e.g.:
+    // (r"^ui/override.*\.lyt$", &["ui5.ztd"]),
+];
+
 /// Map of regex patterns to permitted ZTD archive names
 /// Files matching a pattern are only loaded from archives in the permitted list
 /// Patterns are regex strings matched case-insensitively against filenames
 /// Archive names are case-insensitive and can include subdirectories (e.g., "xpack1/ztpack01.ztd")
-pub static PERMITTED_ARCHIVE_PATTERNS: LazyLock<Vec<(Regex, Vec<&'static str>)>> = LazyLock::new(|| {
-    vec![
-        // xpac*.cfg files are only permitted from these specific archives
-        // Regex: ^xpac.*\.cfg$ matches "xpac03.cfg", "xpac99.cfg", "xpacwhatever.cfg"
-        (Regex::new(r"(?i)^xpac.*\.cfg$").unwrap(), vec![
-            "zupdate/config2.ztd",           // Base game config
-            "xpack1/config2.ztd",  // Dino Danger pack
-            "xpack2/config3.ztd",  // Marine Mania pack
-        ]),
-        (Regex::new(r"(?i)^ui/xpac.lyt$").unwrap(), vec![
-            "zupdate/ui2.ztd",
-            "zupdate1/ui4.ztd",
-        ]),
-        (Regex::new(r"(?i)^ui/sharedui/listbk/.*").unwrap(), vec![
-            "zupdate/ui2.ztd",
-            "zupdate1/ui4.ztd",
-        ]),
-        // Future patterns can be added here, e.g.:
-        // (Regex::new(r"(?i)^ui/override.*\.lyt$").unwrap(), vec!["ui5.ztd"]),
-    ]
+pub static PERMITTED_ARCHIVE_PATTERNS: LazyLock<Vec<(Regex, Vec<String>)>> = LazyLock::new(|| {
+    let config = get_openzt_config().mod_loading;
+    let (patterns, errors) = build_permitted_archive_patterns(&config.permitted_archives, config.permitted_archives_mode);
+    for error in errors {
+        error!("Ignoring invalid permitted_archives entry in openzt.toml: {}", error);
+    }
+    if config.permitted_archives_mode == PermittedArchivesMode::Override && patterns.is_empty() {
+        warn!("permitted_archives_mode is \"override\" but no patterns are configured, files can be loaded from any archive");
+    }
+    patterns
 });
 
+/// Lowercase an archive name, with '/' as separator and no leading "./"
+fn normalize_archive_name(archive_name: &str) -> String {
+    let normalized = archive_name.trim().replace('\\', "/").to_lowercase();
+    normalized.strip_prefix("./").unwrap_or(&normalized).to_string()
+}
+
+/// A pattern's regex without the "(?i)" flag, which every pattern gets anyway
+fn pattern_source(pattern: &str) -> &str {
+    let pattern = pattern.trim();
+    pattern.strip_prefix("(?i)").unwrap_or(pattern)
+}
+
+/// Combine the built-in permitted archive patterns with the configured ones
+///
+/// Invalid configured entries (a regex that doesn't compile, no archives, or an
+/// archive that isn't a .ztd) are left out and described in the returned errors.
+pub fn build_permitted_archive_patterns(configured: &IndexMap<String, Vec<String>>, mode: PermittedArchivesMode) -> (Vec<(Regex, Vec<String>)>, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut patterns: Vec<(String, Vec<String>)> = Vec::new();
+
+    for (pattern, archives) in configured {
+        let source = pattern_source(pattern);
+        if let Err(e) = Regex::new(source) {
+            errors.push(format!("'{}' is not a valid regex: {}", pattern, e));
+            continue;
+        }
+        if archives.is_empty() {
+            errors.push(format!("'{}' lists no archives, so matching files could never be loaded", pattern));
+            continue;
+        }
+        if let Some(archive) = archives.iter().find(|archive| !archive.trim().to_lowercase().ends_with(".ztd")) {
+            errors.push(format!("'{}' lists '{}', which is not a .ztd archive", pattern, archive));
+            continue;
+        }
+        let archives = archives.iter().map(|archive| normalize_archive_name(archive));
+        match patterns.iter_mut().find(|(existing, _)| existing == source) {
+            Some((_, existing)) => existing.extend(archives),
+            None => patterns.push((source.to_string(), archives.collect())),
+        }
+    }
+
+    if mode == PermittedArchivesMode::Extend {
+        for (source, archives) in DEFAULT_PERMITTED_ARCHIVE_PATTERNS {
+            let archives = archives.iter().map(|archive| archive.to_string());
+            match patterns.iter_mut().find(|(existing, _)| existing == source) {
+                Some((_, existing)) => existing.extend(archives),
+                None => patterns.push((source.to_string(), archives.collect())),
+            }
+        }
+    }
+
+    let patterns = patterns
+        .into_iter()
+        .map(|(source, archives)| (Regex::new(&format!("(?i){}", source)).unwrap(), archives))
+        .collect();
+    (patterns, errors)
+}
+
 /// Check if an archive is permitted for a given file based on pattern matching
 /// Returns true if archive is permitted OR no pattern matches (file is unrestricted)
 pub fn is_archive_permitted_for_file(archive_name: &str, filename: &str) -> bool {
     for (pattern, permitted) in PERMITTED_ARCHIVE_PATTERNS.iter() {
         if pattern.is_match(filename) {
             // File matches a pattern - check if archive is permitted
-            // Normalize archive name:
-            // 1. Strip leading './' or '.\'
-            // 2. Convert backslashes to forward slashes
-            // 3. Convert to lowercase
-            let mut normalized = archive_name;
-            // Strip leading './' (Unix-style)
-            if let Some(stripped) = normalized.strip_prefix("./") {
-                normalized = stripped;
-            }
-            // Strip leading '.\' (Windows-style)
-            if let Some(stripped) = normalized.strip_prefix(".\\") {
-                normalized = stripped;
-            }
-            let normalized = normalized
-                .replace('\\', "/")
-                .to_lowercase();
-            return permitted.iter().any(|p| normalized == p.to_lowercase());
+            // Normalize archive name: strip leading './' or '.\', use '/' as separator, lowercase
+            let normalized = normalize_archive_name(archive_name);
+            return permitted.contains(&normalized);
         }
     }
     // No pattern match = file is unrestricted
@@ -71,15 +128,24 @@ use crate::{
     encoding_utils::decode_game_text,
     mods,
     resource_manager::{
+        checksums, file_overrides,
         handlers::{get_handlers, RunStage},
-        lazyresourcemap::{add_lazy, check_file_loaded, create_empty_resource, get_file, get_file_names, get_num_resources},
+        lazyresourcemap::{add_lazy, add_ztfile, check_file_loaded, create_empty_resource, get_file, get_file_names, get_num_resources},
+        load_profile::{self, LoadPhase},
+        load_report::{self, LoadStage},
+        mod_config::{get_openzt_config, PermittedArchivesMode},
         openzt_mods::{
             get_num_mod_ids,
-            legacy_attributes::{add_legacy_entity, LegacyEntityAttributes, LegacyEntityType, SubtypeAttributes},
-            load_open_zt_mod,
+            hot_reload::read_mod_dir,
+            legacy_attributes::{LegacyEntityAttributes, LegacyEntityType, SubtypeAttributes},
+            load_open_zt_mod_files,
+            loading::load_open_zt_mod_from_dir,
+            read_open_zt_mod_files,
             ztd_registry::ZtdLoadStatus,
         },
-        ztfile::ZTFileType,
+        parse_cache::{self, ParsedConfigs},
+        signatures,
+        ztfile::{ZTFile, ZTFileType},
     },
 };
 
@@ -165,6 +231,14 @@ fn get_ztd_resources(dir: &Path, recursive: bool) -> Vec<PathBuf> {
     resources
 }
 
+/// Subdirectories of `dir` with a meta.toml, which are loaded like OpenZT mod archives
+fn get_mod_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries.flatten().map(|entry| entry.path()).filter(|path| path.join("meta.toml").is_file()).collect()
+}
+
 pub fn load_resources(
     paths: Vec<String>,
     mod_order: &[String],
@@ -173,9 +247,6 @@ pub fn load_resources(
     disabled_ztds: &[String],
     pure_legacy_in_mods: &[(String, PathBuf)],
 ) {
-    use std::collections::HashMap;
-    use std::time::Instant;
-
     let now = Instant::now();
     let mut resource_count = 0;
 
@@ -206,6 +277,15 @@ pub fn load_resources(
                 debug!("Archive not in discovered_mods or pure_legacy_in_mods: {}", file_name);
             }
         });
+
+        // Unpacked mod directories, discovery only records the ones in /mods/
+        get_mod_dirs(Path::new(path)).into_iter().for_each(|dir| {
+            let dir_name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            if let Some((mod_id, _)) = discovered_mods.iter().find(|(_, (archive_name, _))| archive_name == dir_name) {
+                debug!("Found unpacked OpenZT mod: {} -> {}", dir_name, mod_id);
+                mod_to_path.entry(mod_id.clone()).or_insert(dir);
+            }
+        });
     });
 
     // Also add any pure legacy archives that weren't found in the path scan
@@ -227,7 +307,7 @@ pub fn load_resources(
 
     // Step 1: Load vanilla (non-/mods/) archives FIRST
     // This ensures all vanilla files are in the resource system before patches are applied
-    info!("Loading vanilla archives (outside /mods/)...");
+    let mut loads = Vec::new();
     paths.iter().rev().for_each(|path| {
         let resources = get_ztd_resources(Path::new(path), false);
         resources.iter().for_each(|resource| {
@@ -247,20 +327,18 @@ pub fn load_resources(
             }
 
             // This is a vanilla legacy archive (outside /mods/)
-            trace!("Loading vanilla legacy resource: {}", resource.display());
-            match handle_ztd(resource, disabled_ztds) {
-                Ok(count) => resource_count += count,
-                Err(err) => {
-                    error!("Error loading vanilla legacy ZTD '{}': {:#}", file_name_lower, err);
-                }
-            }
+            trace!("Queueing vanilla legacy resource: {}", resource.display());
+            loads.push(ArchiveLoad {
+                path: resource.clone(),
+                name: file_name_lower,
+                kind: ArchiveKind::Vanilla,
+            });
         });
     });
+    let vanilla_count = loads.len();
 
     // Step 2: Load /mods/ archives in unified order
     // This includes OpenZT mods (with patches) and pure legacy archives from /mods/
-    info!("Loading /mods/ archives in unified order ({} entries)...", mod_order.len());
-
     for entry in mod_order {
         let entry_lower = entry.to_lowercase();
 
@@ -270,12 +348,11 @@ pub fn load_resources(
                 // Check if this ZTD is disabled (only check for /mods/ archives)
                 let is_disabled = disabled_ztds.iter().any(|d| d.to_lowercase() == entry_lower);
 
-                match handle_ztd_with_status(path, is_disabled) {
-                    Ok(count) => resource_count += count,
-                    Err(err) => {
-                        error!("Error loading pure legacy ZTD '{}': {:#}", entry, err);
-                    }
-                }
+                loads.push(ArchiveLoad {
+                    path: path.clone(),
+                    name: entry.clone(),
+                    kind: ArchiveKind::PureLegacy { is_disabled },
+                });
             } else {
                 warn!("Pure legacy archive '{}' in order but not found on disk", entry);
             }
@@ -288,18 +365,25 @@ pub fn load_resources(
                     continue;
                 }
 
-                match handle_ztd(path, disabled_ztds) {
-                    Ok(count) => resource_count += count,
-                    Err(err) => {
-                        error!("Error loading OpenZT mod '{}': {:#}", entry, err);
-                    }
-                }
+                loads.push(ArchiveLoad {
+                    path: path.clone(),
+                    name: entry.clone(),
+                    kind: if path.is_dir() { ArchiveKind::ModDir } else { ArchiveKind::OpenZtMod },
+                });
             } else {
                 warn!("OpenZT mod '{}' in order but not found on disk", entry);
             }
         }
     }
 
+    info!(
+        "Loading {} vanilla archives (outside /mods/), then {} /mods/ archives in unified order ({} entries)...",
+        vanilla_count,
+        loads.len() - vanilla_count,
+        mod_order.len()
+    );
+    resource_count += load_archives(&loads, disabled_ztds);
+
     let elapsed = now.elapsed();
     info!(
         "Loaded {} mods and {} ({}) resources in: {:.2?}",
@@ -332,19 +416,26 @@ pub fn load_resources(
         }
     }
 
-    let mut filtered_files = Vec::new();
-
-    get_file_names().into_iter().for_each(|file| {
-        let extension = Path::new(&file).extension().unwrap_or_default().to_ascii_lowercase();
-        match extension.to_str().unwrap_or_default() {
-            "uca" | "ucs" | "ucb" => filtered_files.push(file),
-            "cfg" => {
-                let inner_filtered = parse_cfg(&file);
-                filtered_files.extend(inner_filtered);
+    let archives: Vec<(String, PathBuf)> = loads.iter().map(|load| (load.cache_label(disabled_ztds), load.path.clone())).collect();
+    let filtering_start = Instant::now();
+    let parsed = parse_cache::load_or_parse(&archives, || {
+        let mut parsed = ParsedConfigs::default();
+        get_file_names().into_iter().for_each(|file| {
+            let extension = Path::new(&file).extension().unwrap_or_default().to_ascii_lowercase();
+            match extension.to_str().unwrap_or_default() {
+                "uca" | "ucs" | "ucb" => parsed.files.push(file),
+                "cfg" => {
+                    let inner_filtered = parse_cfg(&file, &mut parsed);
+                    parsed.files.extend(inner_filtered);
+                }
+                _ => {}
             }
-            _ => {}
-        }
+        });
+        parsed
     });
+    load_profile::record(LoadPhase::Filtering, "cfg files", filtering_start, filtering_start.elapsed());
+    parsed.register_entities();
+    let filtered_files = parsed.files;
 
     info!("Loaded {} filtered files", filtered_files.len());
 
@@ -361,8 +452,239 @@ pub fn load_resources(
     info!("Extra handling took an extra: {:.2?}", elapsed);
 }
 
-fn handle_ztd(resource: &Path, disabled_ztds: &[String]) -> anyhow::Result<i32> {
-    let mut zip = ZtdArchive::new(resource)?;
+/// Where an archive in the load order comes from, which decides how it is added to the resource map
+enum ArchiveKind {
+    /// Legacy archive outside /mods/
+    Vanilla,
+    /// Legacy archive in /mods/, which can be disabled by name
+    PureLegacy {
+        is_disabled: bool,
+    },
+    OpenZtMod,
+    /// Unpacked OpenZT mod directory in /mods/
+    ModDir,
+}
+
+/// An archive to load, in load order
+struct ArchiveLoad {
+    path: PathBuf,
+    /// Archive file name or mod ID, for logging
+    name: String,
+    kind: ArchiveKind,
+}
+
+impl ArchiveLoad {
+    /// Name and whether the archive is disabled, for the parse cache key
+    fn cache_label(&self, disabled_ztds: &[String]) -> String {
+        let is_disabled = match self.kind {
+            ArchiveKind::PureLegacy { is_disabled } => is_disabled,
+            _ => disabled_ztds.iter().any(|d| d.eq_ignore_ascii_case(&self.name)),
+        };
+        if is_disabled {
+            format!("{} (disabled)", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// An archive opened and read on a loader thread, ready to be added to the resource map
+struct ReadArchive {
+    /// `None` for unpacked mod directories
+    archive: Option<anyhow::Result<ZtdArchive>>,
+    /// Every file of an OpenZT mod, `None` for legacy archives
+    mod_files: anyhow::Result<Option<HashMap<String, Arc<[u8]>>>>,
+    /// SHA-256 of the archive, `None` unless it has a configured checksum
+    sha256: Option<anyhow::Result<String>>,
+    read_time: Duration,
+}
+
+/// Number of loader threads, from `loader_threads` in openzt.toml (0 = one per CPU core)
+fn loader_threads() -> usize {
+    match get_openzt_config().mod_loading.loader_threads {
+        0 => std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1),
+        threads => threads,
+    }
+}
+
+/// Open an archive and, if it is an OpenZT mod, decompress all of its files
+///
+/// Archives in /mods/ listed in `[mod_loading.checksums]` are also hashed, from the same
+/// read, so they can be checked. Unpacked mod directories are read from disk and hashed
+/// over the files read.
+fn read_archive(load: &ArchiveLoad) -> ReadArchive {
+    let now = Instant::now();
+    let hashed = cfg!(feature = "experimental")
+        && matches!(load.kind, ArchiveKind::PureLegacy { .. } | ArchiveKind::OpenZtMod | ArchiveKind::ModDir)
+        && checksums::is_listed(&load.path);
+    let (archive, mut sha256) = match load.kind {
+        ArchiveKind::ModDir => (None, None),
+        _ if hashed => match ZtdArchive::new_hashed(&load.path) {
+            Ok((archive, sha256)) => (Some(Ok(archive)), Some(Ok(sha256))),
+            Err(e) => {
+                let message = format!("{:#}", e);
+                (Some(Err(e)), Some(Err(anyhow!(message))))
+            }
+        },
+        _ => (Some(ZtdArchive::new(&load.path)), None),
+    };
+    let (archive, mod_files) = match archive {
+        None => {
+            let mod_files = read_mod_dir(&load.path);
+            if hashed {
+                sha256 = Some(mod_files.as_ref().map(checksums::sha256_files).map_err(|e| anyhow!("{:#}", e)));
+            }
+            (None, mod_files.map(Some))
+        }
+        Some(Ok(mut archive)) => {
+            let mod_files = read_open_zt_mod_files(&mut archive);
+            (Some(Ok(archive)), mod_files)
+        }
+        Some(Err(e)) => (Some(Err(e)), Ok(None)),
+    };
+    let read_time = now.elapsed();
+    load_profile::record(LoadPhase::Read, &load.name, now, read_time);
+    ReadArchive {
+        archive,
+        mod_files,
+        sha256,
+        read_time,
+    }
+}
+
+/// Run `read`, returning the panic message if it panics
+///
+/// A panic on a loader thread would otherwise leave the archive unread, and the load waiting for it.
+fn catch_read_panic<T>(read: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(read)).map_err(|panic| {
+        panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|message| message.to_string()))
+            .unwrap_or_else(|| "unknown panic".to_string())
+    })
+}
+
+/// Read archives on a pool of loader threads and add them to the resource map in load order
+///
+/// Archives are opened and decompressed in parallel, but each one is added to the
+/// resource map (and its patches applied) on the calling thread only once every
+/// archive before it has been added, so the result is the same as a sequential load.
+/// Loader threads read at most `threads` archives past the next one to be added, so a
+/// slow archive doesn't leave the rest of the load order decompressed in memory.
+/// An archive whose read panics is logged and skipped. Returns the number of resources added.
+fn load_archives(loads: &[ArchiveLoad], disabled_ztds: &[String]) -> i32 {
+    let threads = loader_threads().clamp(1, loads.len().max(1));
+    info!("Reading {} archives on {} loader threads", loads.len(), threads);
+
+    let next = AtomicUsize::new(0);
+    // Index of the next archive to be added, loader threads wait on `added` to read further ahead
+    let adding = Mutex::new(0);
+    let added = Condvar::new();
+    let (sender, receiver) = mpsc::channel();
+    let mut resource_count = 0;
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let next = &next;
+            let adding = &adding;
+            let added = &added;
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(load) = loads.get(index) else {
+                        break;
+                    };
+                    drop(added.wait_while(adding.lock().unwrap(), |adding| index > *adding + threads).unwrap());
+                    if sender.send((index, catch_read_panic(|| read_archive(load)))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Archives can finish reading out of order, hold them until it's their turn
+        let mut waiting = BTreeMap::new();
+        for (index, load) in loads.iter().enumerate() {
+            let read = loop {
+                if let Some(read) = waiting.remove(&index) {
+                    break read;
+                }
+                match receiver.recv() {
+                    Ok((read_index, read)) => {
+                        waiting.insert(read_index, read);
+                    }
+                    Err(_) => {
+                        error!("Loader threads stopped before reading '{}', skipping the remaining archives", load.name);
+                        return;
+                    }
+                }
+            };
+            *adding.lock().unwrap() = index + 1;
+            added.notify_all();
+
+            let mut read = match read {
+                Ok(read) => read,
+                Err(message) => {
+                    error!("Reading '{}' panicked, skipping it: {}", load.name, message);
+                    load_report::record_error(&load.name, LoadStage::Load, format!("Reading the archive panicked: {}", message));
+                    continue;
+                }
+            };
+
+            let read_time = read.read_time;
+            let checked = match load.kind {
+                // Checksums are experimental
+                ArchiveKind::PureLegacy { .. } | ArchiveKind::OpenZtMod | ArchiveKind::ModDir if cfg!(feature = "experimental") => {
+                    checksums::verify(&load.path, read.sha256.take())
+                }
+                _ => true,
+            };
+            if !checked {
+                continue;
+            }
+            let mod_files = read.mod_files.as_ref().ok().and_then(Option::as_ref);
+            let signed = match load.kind {
+                ArchiveKind::Vanilla | ArchiveKind::PureLegacy { is_disabled: true } => true,
+                ArchiveKind::PureLegacy { is_disabled: false } | ArchiveKind::OpenZtMod | ArchiveKind::ModDir => signatures::check(&load.name, mod_files),
+            };
+            if !signed {
+                continue;
+            }
+
+            let now = Instant::now();
+            let result = match load.kind {
+                ArchiveKind::Vanilla | ArchiveKind::OpenZtMod => handle_ztd(read, &load.path, disabled_ztds),
+                ArchiveKind::PureLegacy { is_disabled } => handle_ztd_with_status(read, &load.path, is_disabled),
+                ArchiveKind::ModDir => handle_mod_dir(read, &load.path),
+            };
+            let add_time = now.elapsed();
+            load_profile::record(LoadPhase::Add, &load.name, now, add_time);
+
+            match result {
+                Ok(count) => resource_count += count,
+                Err(err) => {
+                    match load.kind {
+                        ArchiveKind::Vanilla => error!("Error loading vanilla legacy ZTD '{}': {:#}", load.name, err),
+                        ArchiveKind::PureLegacy { .. } => error!("Error loading pure legacy ZTD '{}': {:#}", load.name, err),
+                        ArchiveKind::OpenZtMod => error!("Error loading OpenZT mod '{}': {:#}", load.name, err),
+                        ArchiveKind::ModDir => error!("Error loading unpacked OpenZT mod '{}': {:#}", load.name, err),
+                    }
+                    load_report::record_error(&load.name, LoadStage::Load, format!("{:#}", err));
+                }
+            }
+
+            info!("Loaded archive '{}': read in {:.2?}, added in {:.2?}", load.name, read_time, add_time);
+        }
+    });
+
+    resource_count
+}
+
+fn handle_ztd(read: ReadArchive, resource: &Path, disabled_ztds: &[String]) -> anyhow::Result<i32> {
+    let zip = read.archive.ok_or_else(|| anyhow!("{} is not a .ztd archive", resource.display()))??;
     let ztd_filename = resource.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_lowercase();
 
     // Check if this ZTD is disabled
@@ -372,7 +694,10 @@ fn handle_ztd(resource: &Path, disabled_ztds: &[String]) -> anyhow::Result<i32>
     let status = if is_disabled { ZtdLoadStatus::Disabled } else { ZtdLoadStatus::Enabled };
     crate::resource_manager::openzt_mods::ztd_registry::register_ztd(&ztd_filename, status);
 
-    let ztd_type = load_open_zt_mod(&mut zip, resource)?;
+    let ztd_type = match read.mod_files? {
+        Some(file_map) => load_open_zt_mod_files(file_map, zip.name(), resource)?,
+        None => mods::ZtdType::Legacy,
+    };
 
     if ztd_type == mods::ZtdType::Openzt {
         return Ok(0);
@@ -401,6 +726,7 @@ fn handle_ztd(resource: &Path, disabled_ztds: &[String]) -> anyhow::Result<i32>
             .filter(|s| !s.ends_with("/"))
             .map(|s| s.to_string())
             .collect::<Vec<_>>();
+        let mut added = Vec::with_capacity(file_names.len());
 
         for file_name in file_names {
             // Check if file matches any pattern with archive restrictions
@@ -424,16 +750,29 @@ fn handle_ztd(resource: &Path, disabled_ztds: &[String]) -> anyhow::Result<i32>
                 continue;
             }
 
+            added.push(file_name.clone());
             add_lazy(file_name, archive.clone());
             load_count += 1;
         }
+        file_overrides::add_archive(&archive_name, &added);
         Ok(load_count)
     }
 }
 
+/// Load one .ztd archive in /mods/ as the game does at startup, without checking checksums or signatures (for integration tests)
+#[cfg(feature = "integration-tests")]
+pub fn load_ztd_for_tests(path: &Path) -> anyhow::Result<i32> {
+    let load = ArchiveLoad {
+        path: path.to_path_buf(),
+        name: path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string(),
+        kind: ArchiveKind::OpenZtMod,
+    };
+    handle_ztd(read_archive(&load), &load.path, &[])
+}
+
 /// Handle a ZTD file with explicit disabled status (for /mods/ archives)
 /// Similar to handle_ztd but with explicit is_disabled parameter instead of checking disabled_ztds list
-fn handle_ztd_with_status(resource: &Path, is_disabled: bool) -> anyhow::Result<i32> {
+fn handle_ztd_with_status(read: ReadArchive, resource: &Path, is_disabled: bool) -> anyhow::Result<i32> {
     let ztd_filename = resource.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_lowercase();
 
     // Register this ZTD in the load order BEFORE loading
@@ -445,7 +784,7 @@ fn handle_ztd_with_status(resource: &Path, is_disabled: bool) -> anyhow::Result<
 
     if is_disabled {
         info!("Processing DISABLED pure legacy ZTD '{}'", ztd_filename);
-        let zip = ZtdArchive::new(resource)?;
+        let zip = read.archive.ok_or_else(|| anyhow!("{} is not a .ztd archive", resource.display()))??;
         let archive = Arc::new(Mutex::new(zip));
 
         let (added_count, skipped_count) = process_disabled_archive_files(
@@ -456,11 +795,66 @@ fn handle_ztd_with_status(resource: &Path, is_disabled: bool) -> anyhow::Result<
         Ok(added_count as i32)
     } else {
         // Normal loading for enabled ZTD
-        handle_ztd(resource, &[])
+        handle_ztd(read, resource, &[])
+    }
+}
+
+/// Add an unpacked mod directory from /mods/ to the resource map
+///
+/// Loaded like an OpenZT mod archive, except that the legacy files of a combined
+/// mod are added from the files already read rather than lazily from an archive.
+fn handle_mod_dir(read: ReadArchive, dir: &Path) -> anyhow::Result<i32> {
+    let dir_name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_lowercase();
+
+    // Register this directory in the load order BEFORE loading, like a ZTD
+    crate::resource_manager::openzt_mods::ztd_registry::register_ztd(&dir_name, ZtdLoadStatus::Enabled);
+
+    let file_map = read.mod_files?.ok_or_else(|| anyhow!("meta.toml not found in {}", dir.display()))?;
+    if load_open_zt_mod_from_dir(file_map.clone(), dir)? == mods::ZtdType::Openzt {
+        return Ok(0);
+    }
+
+    let span = tracing::error_span!("handle_mod_dir", dir_name = %dir_name);
+    let _guard = span.enter();
+
+    let mut load_count = 0;
+    let mut added = Vec::with_capacity(file_map.len());
+    for (file_name, data) in file_map {
+        if !is_archive_permitted_for_file(&dir_name, &file_name) {
+            debug!("File '{}' in '{}' is filtered by pattern restrictions - skipping", file_name, dir_name);
+            continue;
+        }
+        let Ok(file_type) = ZTFileType::try_from(Path::new(&file_name)) else {
+            debug!("File '{}' in '{}' has unsupported type - skipping", file_name, dir_name);
+            continue;
+        };
+        let data = match file_type.load(&file_name, Box::from(&*data)) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Failed to load '{}' from '{}': {:#}", file_name, dir_name, e);
+                continue;
+            }
+        };
+
+        let ztfile = ZTFile::builder()
+            .file_name(file_name.clone())
+            .file_size(data.len() as u32)
+            .type_(file_type)
+            .raw_data(data)
+            .build();
+        match add_ztfile(dir, file_name.clone(), ztfile) {
+            Ok(()) => {
+                added.push(file_name);
+                load_count += 1;
+            }
+            Err(e) => error!("Failed to add '{}' from '{}': {:#}", file_name, dir_name, e),
+        }
     }
+    file_overrides::add_archive(&dir.to_string_lossy(), &added);
+    Ok(load_count)
 }
 
-fn parse_cfg(file_name: &String) -> Vec<String> {
+fn parse_cfg(file_name: &String, parsed: &mut ParsedConfigs) -> Vec<String> {
     if let Some(legacy_cfg) = get_legacy_cfg_type(file_name) {
         trace!("Legacy cfg: {} {:?}", file_name, legacy_cfg.cfg_type);
 
@@ -478,7 +872,7 @@ fn parse_cfg(file_name: &String) -> Vec<String> {
 
         // Extract entity attributes from .ai files for supported types
         if let Some(entity_type) = LegacyEntityType::from_legacy_cfg_type(&legacy_cfg.cfg_type) {
-            extract_legacy_entities(&ini, entity_type);
+            extract_legacy_entities(&ini, entity_type, parsed);
         }
 
         match legacy_cfg.cfg_type {
@@ -558,7 +952,7 @@ fn parse_filters_cfg(file: &Ini) -> Vec<String> {
 
 /// Extract entity attributes by loading each .ai file listed in the .cfg
 /// Also extracts subtype information from the .cfg file itself
-fn extract_legacy_entities(cfg: &Ini, entity_type: LegacyEntityType) {
+fn extract_legacy_entities(cfg: &Ini, entity_type: LegacyEntityType, parsed: &mut ParsedConfigs) {
     let section_name = entity_type.section_name();
 
     // Get the INI map to avoid temporary value issues
@@ -630,9 +1024,7 @@ fn extract_legacy_entities(cfg: &Ini, entity_type: LegacyEntityType) {
                                     }
                                 }
 
-                                if let Err(e) = add_legacy_entity(entity_type, entity_name.clone(), attrs) {
-                                    warn!("Failed to register legacy entity '{}': {}", entity_name, e);
-                                }
+                                parsed.entities.push((entity_type, entity_name.clone(), attrs));
                             }
                             Err(e) => {
                                 warn!("Failed to parse attributes from '{}': {}", ai_path, e);
@@ -826,6 +1218,7 @@ pub fn load_legacy_entities_for_tests() -> anyhow::Result<usize> {
     ];
 
     let mut loaded_count = 0;
+    let mut parsed = ParsedConfigs::default();
 
     for cfg_file in cfg_files {
         let cfg_path = std::path::Path::new(&zt_dir).join(cfg_file);
@@ -851,12 +1244,14 @@ pub fn load_legacy_entities_for_tests() -> anyhow::Result<usize> {
         if let Some(legacy_cfg) = get_legacy_cfg_type(cfg_file) {
             if let Some(entity_type) = LegacyEntityType::from_legacy_cfg_type(&legacy_cfg.cfg_type) {
                 // Extract legacy entities from this .cfg file
-                extract_legacy_entities(&ini, entity_type);
+                extract_legacy_entities(&ini, entity_type, &mut parsed);
                 loaded_count += 1;
             }
         }
     }
 
+    parsed.register_entities();
+
     // Return error if no .cfg files were found (so test attributes will be added)
     if loaded_count == 0 {
         return Err(anyhow::anyhow!("No legacy .cfg files found in {}", zt_dir));
@@ -878,6 +1273,7 @@ pub fn load_legacy_entities_from_test_files() -> anyhow::Result<()> {
     let cfg_files = vec!["animal.cfg", "bldg.cfg", "fences.cfg", "guests.cfg", "items.cfg", "staff.cfg", "twall.cfg"];
 
     let mut loaded_count = 0;
+    let mut parsed = ParsedConfigs::default();
 
     for cfg_file in cfg_files {
         // Check if file exists in resource system
@@ -896,13 +1292,70 @@ pub fn load_legacy_entities_from_test_files() -> anyhow::Result<()> {
             if let Some(legacy_cfg) = get_legacy_cfg_type(cfg_file) {
                 if let Some(entity_type) = LegacyEntityType::from_legacy_cfg_type(&legacy_cfg.cfg_type) {
                     // Extract legacy entities from this .cfg file
-                    extract_legacy_entities(&ini, entity_type);
+                    extract_legacy_entities(&ini, entity_type, &mut parsed);
                     loaded_count += 1;
                 }
             }
         }
     }
 
+    parsed.register_entities();
     info!("Loaded legacy entities from {} test .cfg files", loaded_count);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured(entries: &[(&str, &[&str])]) -> IndexMap<String, Vec<String>> {
+        entries
+            .iter()
+            .map(|(pattern, archives)| (pattern.to_string(), archives.iter().map(|archive| archive.to_string()).collect()))
+            .collect()
+    }
+
+    fn permitted<'p>(patterns: &'p [(Regex, Vec<String>)], file_name: &str) -> Option<&'p [String]> {
+        patterns.iter().find(|(pattern, _)| pattern.is_match(file_name)).map(|(_, archives)| archives.as_slice())
+    }
+
+    #[test]
+    fn test_catch_read_panic() {
+        assert_eq!(catch_read_panic(|| 1), Ok(1));
+        assert_eq!(catch_read_panic(|| -> i32 { panic!("bad archive {}", 3) }), Err("bad archive 3".to_string()));
+        assert_eq!(catch_read_panic(|| -> i32 { panic!("bad archive") }), Err("bad archive".to_string()));
+    }
+
+    #[test]
+    fn test_extend_permitted_archive_patterns() {
+        let config = configured(&[(r"(?i)^xpac.*\.cfg$", &[r"DE\ZUpdate\Config2.ztd"]), (r"^ui/extra\.lyt$", &["ui9.ztd"])]);
+        let (patterns, errors) = build_permitted_archive_patterns(&config, PermittedArchivesMode::Extend);
+        assert!(errors.is_empty());
+        assert_eq!(patterns.len(), DEFAULT_PERMITTED_ARCHIVE_PATTERNS.len() + 1);
+
+        let xpac = permitted(&patterns, "XPAC03.CFG").unwrap();
+        assert_eq!(xpac[0], "de/zupdate/config2.ztd");
+        assert!(xpac.contains(&"xpack1/config2.ztd".to_string()));
+        assert_eq!(permitted(&patterns, "ui/extra.lyt").unwrap(), ["ui9.ztd"]);
+    }
+
+    #[test]
+    fn test_override_permitted_archive_patterns() {
+        let config = configured(&[(r"^ui/xpac.lyt$", &["./ui/ui9.ztd"])]);
+        let (patterns, _) = build_permitted_archive_patterns(&config, PermittedArchivesMode::Override);
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(permitted(&patterns, "ui/xpac.lyt").unwrap(), ["ui/ui9.ztd"]);
+        assert!(permitted(&patterns, "xpac03.cfg").is_none());
+    }
+
+    #[test]
+    fn test_invalid_permitted_archive_patterns() {
+        let config = configured(&[("^xpac(.cfg", &["config2.ztd"]), ("^a.cfg$", &[]), ("^b.cfg$", &["b.zip"])]);
+        let (patterns, errors) = build_permitted_archive_patterns(&config, PermittedArchivesMode::Override);
+        assert!(patterns.is_empty());
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("'^xpac(.cfg' is not a valid regex"));
+        assert_eq!(errors[1], "'^a.cfg$' lists no archives, so matching files could never be loaded");
+        assert_eq!(errors[2], "'^b.cfg$' lists 'b.zip', which is not a .ztd archive");
+    }
+}
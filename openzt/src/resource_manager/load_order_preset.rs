@@ -0,0 +1,208 @@
+//! Shareable load order presets
+//!
+//! `export_load_order(file)` writes the resolved load order from openzt.toml,
+//! with the version of each OpenZT mod, to a preset file that can be shared with
+//! other players. `import_load_order(file)` replaces the `order` and `disabled`
+//! entries in openzt.toml with the preset's, warning about mods in the preset that
+//! aren't installed or have a different version. An imported order takes effect
+//! the next time Zoo Tycoon starts.
+//!
+//! ```toml
+//! disabled = ["oldfences.ztd"]
+//!
+//! [[mods]]
+//! id = "finn.framework"
+//! version = "1.2.0"
+//!
+//! [[mods]]
+//! id = "newfences.ztd"
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::resource_manager::mod_config::{get_openzt_config, save_openzt_config, ModLoadingConfig};
+
+/// A mod or legacy archive in a preset's load order
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PresetMod {
+    /// OpenZT mod ID, or archive file name for legacy archives in /mods/
+    pub id: String,
+    /// Version of the mod when the preset was exported, `None` for legacy archives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// A load order that can be shared and imported into openzt.toml
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LoadOrderPreset {
+    /// Same as `disabled` in openzt.toml
+    #[serde(default)]
+    pub disabled: Vec<String>,
+    /// Load order, earliest first
+    #[serde(default)]
+    pub mods: Vec<PresetMod>,
+}
+
+/// Mods and legacy archives found in /mods/ during discovery, with the version of each OpenZT mod
+static AVAILABLE: LazyLock<Mutex<HashMap<String, Option<String>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Record the mods found during discovery
+pub fn set_available(available: HashMap<String, Option<String>>) {
+    *AVAILABLE.lock().unwrap() = available;
+}
+
+/// Build a preset from openzt.toml's load order
+pub fn build_preset(mod_loading: &ModLoadingConfig, available: &HashMap<String, Option<String>>) -> LoadOrderPreset {
+    LoadOrderPreset {
+        disabled: mod_loading.disabled.clone(),
+        mods: mod_loading
+            .order
+            .iter()
+            .map(|id| PresetMod {
+                id: id.clone(),
+                version: available.get(id).cloned().flatten(),
+            })
+            .collect(),
+    }
+}
+
+/// Replace the load order and disabled list with a preset's
+///
+/// Installed mods the preset doesn't mention keep their place after the preset's mods.
+/// Returns a warning for each mod in the preset that isn't installed or has another version.
+pub fn apply_preset(preset: &LoadOrderPreset, mod_loading: &mut ModLoadingConfig, available: &HashMap<String, Option<String>>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for preset_mod in &preset.mods {
+        match (available.get(&preset_mod.id), &preset_mod.version) {
+            (None, _) => warnings.push(format!("{} is not installed", preset_mod.id)),
+            (Some(Some(installed)), Some(version)) if installed != version => {
+                warnings.push(format!("{} is version {}, the preset was made with {}", preset_mod.id, installed, version))
+            }
+            _ => {}
+        }
+    }
+
+    let in_preset: HashSet<&String> = preset.mods.iter().map(|preset_mod| &preset_mod.id).collect();
+    let mut order: Vec<String> = preset.mods.iter().map(|preset_mod| preset_mod.id.clone()).collect();
+    order.extend(mod_loading.order.iter().filter(|id| !in_preset.contains(id)).cloned());
+
+    mod_loading.order = order;
+    mod_loading.disabled = preset.disabled.clone();
+    warnings
+}
+
+/// Resolve a preset file name relative to the Zoo Tycoon directory
+fn preset_path(file: &str) -> PathBuf {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        crate::util::get_base_path().join(path)
+    }
+}
+
+/// Write the current load order to a preset file
+pub fn export(file: &str) -> anyhow::Result<String> {
+    let config = get_openzt_config();
+    let preset = build_preset(&config.mod_loading, &AVAILABLE.lock().unwrap());
+    let path = preset_path(file);
+
+    let contents = format!(
+        "# OpenZT load order preset, import with import_load_order(\"{}\")\n{}",
+        path.file_name().and_then(|name| name.to_str()).unwrap_or(file),
+        toml::to_string(&preset).context("Failed to serialize the load order")?
+    );
+    std::fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    info!("Exported load order of {} mods to {}", preset.mods.len(), path.display());
+    Ok(format!("Exported load order of {} mods to {}", preset.mods.len(), path.display()))
+}
+
+/// Replace the load order in openzt.toml with a preset file's
+pub fn import(file: &str) -> anyhow::Result<String> {
+    let path = preset_path(file);
+    let contents = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let preset = toml::from_str::<LoadOrderPreset>(&contents).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let mut config = get_openzt_config();
+    let warnings = apply_preset(&preset, &mut config.mod_loading, &AVAILABLE.lock().unwrap());
+    save_openzt_config(&config, false)?;
+
+    info!("Imported load order of {} mods from {}", preset.mods.len(), path.display());
+    let mut result = format!(
+        "Imported load order of {} mods from {}, restart Zoo Tycoon to load it",
+        preset.mods.len(),
+        path.display()
+    );
+    for warning in warnings {
+        result.push_str(&format!("\n  warning: {}", warning));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn available() -> HashMap<String, Option<String>> {
+        HashMap::from([
+            ("finn.framework".to_string(), Some("1.2.0".to_string())),
+            ("finn.moon".to_string(), Some("2.0.0".to_string())),
+            ("fences.ztd".to_string(), None),
+        ])
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mod_loading = ModLoadingConfig {
+            order: strings(&["finn.framework", "fences.ztd", "finn.moon"]),
+            disabled: strings(&["finn.moon"]),
+            ..Default::default()
+        };
+
+        let preset = build_preset(&mod_loading, &available());
+        let toml = toml::to_string(&preset).unwrap();
+        assert!(toml.contains("[[mods]]\nid = \"finn.framework\"\nversion = \"1.2.0\"\n"));
+        assert!(toml.contains("[[mods]]\nid = \"fences.ztd\"\n\n"));
+        assert_eq!(toml::from_str::<LoadOrderPreset>(&toml).unwrap(), preset);
+
+        let mut imported = ModLoadingConfig::default();
+        assert!(apply_preset(&preset, &mut imported, &available()).is_empty());
+        assert_eq!(imported.order, mod_loading.order);
+        assert_eq!(imported.disabled, mod_loading.disabled);
+    }
+
+    #[test]
+    fn test_import_warnings_and_local_mods() {
+        let preset: LoadOrderPreset = toml::from_str(
+            "[[mods]]\nid = \"finn.moon\"\nversion = \"1.0.0\"\n\n[[mods]]\nid = \"finn.sun\"\nversion = \"1.0.0\"\n\n[[mods]]\nid = \"finn.framework\"\n",
+        )
+        .unwrap();
+
+        let mut mod_loading = ModLoadingConfig {
+            order: strings(&["fences.ztd", "finn.framework", "finn.moon"]),
+            disabled: strings(&["fences.ztd"]),
+            ..Default::default()
+        };
+
+        let warnings = apply_preset(&preset, &mut mod_loading, &available());
+        assert_eq!(
+            warnings,
+            strings(&["finn.moon is version 2.0.0, the preset was made with 1.0.0", "finn.sun is not installed"])
+        );
+        assert_eq!(mod_loading.order, strings(&["finn.moon", "finn.sun", "finn.framework", "fences.ztd"]));
+        assert!(mod_loading.disabled.is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+//! Timings of each phase of loading mods
+//!
+//! Discovering mods, resolving the load order, reading each archive (on the loader
+//! threads), adding it to the resource map and applying its patches are timed per mod
+//! or archive. Once loading is done a summary is logged, with the total and slowest
+//! entries of each phase and the archives that took longest overall, and kept for the
+//! `load_timings()` console command.
+//!
+//! With `load_trace = true` in `[dev]`, every timing is also written to
+//! `openzt_load_trace.json` in the Chrome trace event format, which can be opened in
+//! chrome://tracing or https://ui.perfetto.dev to see what each thread was doing.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::resource_manager::mod_config::get_openzt_config;
+
+const TRACE_FILE_NAME: &str = "openzt_load_trace.json";
+
+/// Entries listed per phase in the summary
+const SLOWEST_COUNT: usize = 5;
+
+/// A timed part of loading mods
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadPhase {
+    /// Reading meta.toml from an archive in the resource paths
+    Discovery,
+    /// Resolving dependencies into the load order
+    Resolution,
+    /// Opening, decompressing and hashing an archive on a loader thread
+    Read,
+    /// Adding an archive's files to the resource map, including its patches
+    Add,
+    /// Applying an OpenZT mod's patches, part of adding it
+    Patches,
+    /// Parsing the .cfg files once every archive is added
+    Filtering,
+}
+
+impl LoadPhase {
+    const ALL: [LoadPhase; 6] = [
+        LoadPhase::Discovery,
+        LoadPhase::Resolution,
+        LoadPhase::Read,
+        LoadPhase::Add,
+        LoadPhase::Patches,
+        LoadPhase::Filtering,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            LoadPhase::Discovery => "discovery",
+            LoadPhase::Resolution => "resolution",
+            LoadPhase::Read => "read",
+            LoadPhase::Add => "add",
+            LoadPhase::Patches => "patches",
+            LoadPhase::Filtering => "filtering",
+        }
+    }
+
+    /// Whether the phase is timed within another phase for the same archive
+    fn is_nested(&self) -> bool {
+        *self == LoadPhase::Patches
+    }
+}
+
+/// How long one phase took for one mod or archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    pub phase: LoadPhase,
+    /// Mod ID or archive name
+    pub name: String,
+    /// Since loading started
+    pub start: Duration,
+    pub duration: Duration,
+    /// Small index of the thread it ran on, 0 for the first thread that recorded a timing
+    pub thread: usize,
+}
+
+struct Profile {
+    start: Instant,
+    timings: Vec<Timing>,
+}
+
+static PROFILE: LazyLock<Mutex<Profile>> = LazyLock::new(|| {
+    Mutex::new(Profile {
+        start: Instant::now(),
+        timings: Vec::new(),
+    })
+});
+
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+fn thread_index() -> usize {
+    THREAD.with(|thread| match thread.get() {
+        Some(index) => index,
+        None => {
+            let index = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+            thread.set(Some(index));
+            index
+        }
+    })
+}
+
+/// Start timing a new load, timings are relative to this
+pub fn begin() {
+    let mut profile = PROFILE.lock().unwrap();
+    profile.start = Instant::now();
+    profile.timings.clear();
+}
+
+/// Record that `phase` took `duration` for `name`, starting at `start`
+pub fn record(phase: LoadPhase, name: &str, start: Instant, duration: Duration) {
+    let thread = thread_index();
+    let mut profile = PROFILE.lock().unwrap();
+    let start = start.saturating_duration_since(profile.start);
+    profile.timings.push(Timing {
+        phase,
+        name: name.to_string(),
+        start,
+        duration,
+        thread,
+    });
+}
+
+/// Time `f` as `phase` for `name`
+pub fn time<T>(phase: LoadPhase, name: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    record(phase, name, start, start.elapsed());
+    result
+}
+
+fn slowest(entries: &[(&str, Duration)]) -> String {
+    let mut entries = entries.to_vec();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    entries
+        .iter()
+        .take(SLOWEST_COUNT)
+        .map(|(name, duration)| format!("{} ({:.2?})", name, duration))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format the total and slowest entries of each phase, then the slowest archives overall
+pub fn format_summary(timings: &[Timing]) -> String {
+    let mut summary = String::new();
+    for phase in LoadPhase::ALL {
+        let entries: Vec<(&str, Duration)> = timings
+            .iter()
+            .filter(|timing| timing.phase == phase)
+            .map(|timing| (timing.name.as_str(), timing.duration))
+            .collect();
+        if entries.is_empty() {
+            continue;
+        }
+        let total: Duration = entries.iter().map(|(_, duration)| *duration).sum();
+        let _ = writeln!(
+            summary,
+            "{}: {:.2?} for {} entries, slowest: {}",
+            phase.name(),
+            total,
+            entries.len(),
+            slowest(&entries)
+        );
+    }
+
+    let mut totals: HashMap<&str, Duration> = HashMap::new();
+    for timing in timings.iter().filter(|timing| !timing.phase.is_nested() && timing.phase != LoadPhase::Resolution) {
+        *totals.entry(&timing.name).or_default() += timing.duration;
+    }
+    if !totals.is_empty() {
+        let totals: Vec<(&str, Duration)> = totals.into_iter().collect();
+        let _ = writeln!(summary, "slowest overall: {}", slowest(&totals));
+    }
+    summary
+}
+
+/// The timings as Chrome trace events
+pub fn chrome_trace(timings: &[Timing]) -> serde_json::Value {
+    let events: Vec<serde_json::Value> = timings
+        .iter()
+        .map(|timing| {
+            json!({
+                "name": timing.name,
+                "cat": timing.phase.name(),
+                "ph": "X",
+                "ts": timing.start.as_micros() as u64,
+                "dur": timing.duration.as_micros() as u64,
+                "pid": 1,
+                "tid": timing.thread,
+            })
+        })
+        .collect();
+    json!({ "traceEvents": events })
+}
+
+/// The summary of the last load
+pub fn summary() -> String {
+    let summary = format_summary(&PROFILE.lock().unwrap().timings);
+    if summary.is_empty() {
+        return "No load timings recorded\n".to_string();
+    }
+    summary
+}
+
+/// Log the summary once all mods are loaded, and write the trace if `load_trace` is set
+pub fn finish() {
+    let profile = PROFILE.lock().unwrap();
+    info!("Load timings:\n{}", format_summary(&profile.timings).trim_end());
+
+    if !get_openzt_config().dev.load_trace {
+        return;
+    }
+    let trace_path = crate::util::get_base_path().join(TRACE_FILE_NAME);
+    match std::fs::write(&trace_path, chrome_trace(&profile.timings).to_string()) {
+        Ok(()) => info!("Load trace written to {}", trace_path.display()),
+        Err(e) => error!("Failed to write load trace to {}: {}", trace_path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(phase: LoadPhase, name: &str, start_ms: u64, duration_ms: u64, thread: usize) -> Timing {
+        Timing {
+            phase,
+            name: name.to_string(),
+            start: Duration::from_millis(start_ms),
+            duration: Duration::from_millis(duration_ms),
+            thread,
+        }
+    }
+
+    fn timings() -> Vec<Timing> {
+        vec![
+            timing(LoadPhase::Resolution, "load order", 5, 1, 0),
+            timing(LoadPhase::Read, "moon.ztd", 10, 200, 1),
+            timing(LoadPhase::Read, "sun.ztd", 10, 50, 2),
+            timing(LoadPhase::Add, "moon.ztd", 210, 30, 0),
+            timing(LoadPhase::Patches, "moon.ztd", 220, 20, 0),
+            timing(LoadPhase::Add, "sun.ztd", 240, 100, 0),
+        ]
+    }
+
+    #[test]
+    fn test_format_summary() {
+        assert_eq!(
+            format_summary(&timings()),
+            "resolution: 1.00ms for 1 entries, slowest: load order (1.00ms)\n\
+             read: 250.00ms for 2 entries, slowest: moon.ztd (200.00ms), sun.ztd (50.00ms)\n\
+             add: 130.00ms for 2 entries, slowest: sun.ztd (100.00ms), moon.ztd (30.00ms)\n\
+             patches: 20.00ms for 1 entries, slowest: moon.ztd (20.00ms)\n\
+             slowest overall: moon.ztd (230.00ms), sun.ztd (150.00ms)\n"
+        );
+        assert_eq!(format_summary(&[]), "");
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let trace = chrome_trace(&timings()[1..2]);
+        assert_eq!(
+            trace,
+            json!({
+                "traceEvents": [
+                    { "name": "moon.ztd", "cat": "read", "ph": "X", "ts": 10000, "dur": 200000, "pid": 1, "tid": 1 }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let start = Instant::now();
+        record(LoadPhase::Discovery, "test-record.ztd", start, Duration::from_millis(3));
+        let profile = PROFILE.lock().unwrap();
+        let timing = profile.timings.iter().find(|timing| timing.name == "test-record.ztd").unwrap();
+        assert_eq!(timing.phase, LoadPhase::Discovery);
+        assert_eq!(timing.duration, Duration::from_millis(3));
+        assert_eq!(timing.thread, thread_index());
+    }
+}
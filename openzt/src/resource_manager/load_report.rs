@@ -0,0 +1,282 @@
+//! Report of the problems found while loading mods
+//!
+//! Errors and warnings from discovering, ordering and loading mods (an unreadable
+//! meta.toml, a missing dependency, a failed patch, a mod that failed to load) are
+//! logged where they happen and also recorded here, grouped by the mod they belong to.
+//! Once loading is done the report is summarized in the log, and when a game starts a
+//! summary is shown in the game's message bar, one message per mod with an error.
+//! The `mod_load_report()` console command shows the full report.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use openzt_detour_macro::detour_mod;
+use openzt_mod::schema::Severity;
+use tracing::{error, info, warn};
+
+/// Most mods listed in game, the rest are only counted
+const MAX_IN_GAME_MODS: usize = 5;
+
+/// When in the loading process a problem was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadStage {
+    /// Reading meta.toml
+    Meta,
+    /// Checking a definition file against the schema
+    Definition,
+    /// Resolving dependencies and the load order
+    Dependency,
+    /// Applying a patch
+    Patch,
+    /// Loading the mod or archive
+    Load,
+    /// Running a mod script or one of its event handlers
+    Script,
+}
+
+impl LoadStage {
+    fn name(&self) -> &'static str {
+        match self {
+            LoadStage::Meta => "meta",
+            LoadStage::Definition => "definition",
+            LoadStage::Dependency => "dependency",
+            LoadStage::Patch => "patch",
+            LoadStage::Load => "load",
+            LoadStage::Script => "script",
+        }
+    }
+}
+
+/// A problem with one mod or archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadIssue {
+    /// Mod ID, or the archive name when the mod ID isn't known
+    pub source: String,
+    pub stage: LoadStage,
+    pub severity: Severity,
+    pub message: String,
+}
+
+static ISSUES: LazyLock<Mutex<Vec<LoadIssue>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Whether the report has been shown in game this session
+static SHOWN_IN_GAME: AtomicBool = AtomicBool::new(false);
+
+/// Record a problem found while loading `source`
+pub fn record(source: &str, stage: LoadStage, severity: Severity, message: impl Into<String>) {
+    ISSUES.lock().unwrap().push(LoadIssue {
+        source: source.to_string(),
+        stage,
+        severity,
+        message: message.into(),
+    });
+}
+
+/// Record an error found while loading `source`
+pub fn record_error(source: &str, stage: LoadStage, message: impl Into<String>) {
+    record(source, stage, Severity::Error, message);
+}
+
+/// Record a warning found while loading `source`
+pub fn record_warning(source: &str, stage: LoadStage, message: impl Into<String>) {
+    record(source, stage, Severity::Warning, message);
+}
+
+/// Issues grouped by source, in the order each source first had an issue
+fn group_by_source(issues: &[LoadIssue]) -> Vec<(&str, Vec<&LoadIssue>)> {
+    let mut groups: Vec<(&str, Vec<&LoadIssue>)> = Vec::new();
+    for issue in issues {
+        match groups.iter_mut().find(|(source, _)| *source == issue.source) {
+            Some((_, group)) => group.push(issue),
+            None => groups.push((&issue.source, vec![issue])),
+        }
+    }
+    groups
+}
+
+fn count(issues: &[&LoadIssue], severity: Severity) -> usize {
+    issues.iter().filter(|issue| issue.severity == severity).count()
+}
+
+/// Format the full report, one section per mod with its errors first
+pub fn format_report(issues: &[LoadIssue]) -> String {
+    if issues.is_empty() {
+        return "All mods loaded without errors or warnings\n".to_string();
+    }
+
+    let mut report = String::new();
+    for (source, mut group) in group_by_source(issues) {
+        group.sort_by_key(|issue| issue.severity != Severity::Error);
+        let _ = writeln!(
+            report,
+            "{}: {} errors, {} warnings",
+            source,
+            count(&group, Severity::Error),
+            count(&group, Severity::Warning)
+        );
+        for issue in group {
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            let _ = writeln!(report, "  {} ({}): {}", severity, issue.stage.name(), issue.message.replace('\n', "\n    "));
+        }
+    }
+    report
+}
+
+/// Messages shown in game: a summary, then the first error of each mod that had one
+///
+/// Empty if no mod had an error, warnings alone aren't worth interrupting the game for.
+pub fn in_game_messages(issues: &[LoadIssue]) -> Vec<String> {
+    let failed: Vec<(&str, &LoadIssue)> = group_by_source(issues)
+        .into_iter()
+        .filter_map(|(source, group)| group.into_iter().find(|issue| issue.severity == Severity::Error).map(|issue| (source, issue)))
+        .collect();
+    if failed.is_empty() {
+        return Vec::new();
+    }
+
+    let mut messages = vec![format!(
+        "OpenZT: {} mods had errors while loading, run mod_load_report() in the console for details",
+        failed.len()
+    )];
+    for (source, issue) in failed.iter().take(MAX_IN_GAME_MODS) {
+        messages.push(format!("{}: {}", source, first_line(&issue.message)));
+    }
+    if failed.len() > MAX_IN_GAME_MODS {
+        messages.push(format!("...and {} more", failed.len() - MAX_IN_GAME_MODS));
+    }
+    messages
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// The full report of the problems found so far
+pub fn report() -> String {
+    format_report(&ISSUES.lock().unwrap())
+}
+
+/// Mods and archives with at least one error
+pub fn sources_with_errors() -> HashSet<String> {
+    ISSUES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .map(|issue| issue.source.clone())
+        .collect()
+}
+
+/// Log a summary once all mods are loaded
+pub fn finish() {
+    let issues = ISSUES.lock().unwrap();
+    let groups = group_by_source(&issues);
+    let failed = groups.iter().filter(|(_, group)| count(group, Severity::Error) > 0).count();
+    if failed > 0 {
+        error!("{} mods had errors while loading:\n{}", failed, format_report(&issues).trim_end());
+    } else if !groups.is_empty() {
+        warn!("{} mods had warnings while loading, run mod_load_report() for details", groups.len());
+    } else {
+        info!("All mods loaded without errors or warnings");
+    }
+}
+
+/// Show the report in the game's message bar, once per session
+fn show_in_game() {
+    if SHOWN_IN_GAME.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    for message in in_game_messages(&ISSUES.lock().unwrap()) {
+        crate::ztui::display_message(&message);
+    }
+}
+
+pub fn init() {
+    if unsafe { load_report_hooks::init_detours() }.is_err() {
+        error!("Error initialising mod load report detours");
+    }
+}
+
+#[detour_mod]
+mod load_report_hooks {
+    use openzt_detour::generated::ztgamemgr::START;
+
+    #[detour(START)]
+    unsafe extern "fastcall" fn zoo_zt_game_mgr_start(this_ptr: i32) {
+        unsafe { START_DETOUR.call(this_ptr) };
+        super::show_in_game();
+        // A function can only be detoured once, so mod scripts' zoo_loaded event is fired here too
+        crate::resource_manager::openzt_mods::scripts::zoo_loaded();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues() -> Vec<LoadIssue> {
+        vec![
+            LoadIssue {
+                source: "finn.moon".to_string(),
+                stage: LoadStage::Dependency,
+                severity: Severity::Warning,
+                message: "requires 'finn.sun' which is not present".to_string(),
+            },
+            LoadIssue {
+                source: "broken.ztd".to_string(),
+                stage: LoadStage::Meta,
+                severity: Severity::Error,
+                message: "Failed to parse meta.toml\nmissing field `name`".to_string(),
+            },
+            LoadIssue {
+                source: "finn.moon".to_string(),
+                stage: LoadStage::Patch,
+                severity: Severity::Error,
+                message: "Patch 'moon_rock' failed: file 'objects/rock.ai' not found".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_format_report() {
+        assert_eq!(
+            format_report(&issues()),
+            "finn.moon: 1 errors, 1 warnings\n  \
+             error (patch): Patch 'moon_rock' failed: file 'objects/rock.ai' not found\n  \
+             warning (dependency): requires 'finn.sun' which is not present\n\
+             broken.ztd: 1 errors, 0 warnings\n  \
+             error (meta): Failed to parse meta.toml\n    missing field `name`\n"
+        );
+        assert_eq!(format_report(&[]), "All mods loaded without errors or warnings\n");
+    }
+
+    #[test]
+    fn test_in_game_messages() {
+        assert_eq!(
+            in_game_messages(&issues()),
+            vec![
+                "OpenZT: 2 mods had errors while loading, run mod_load_report() in the console for details",
+                "finn.moon: Patch 'moon_rock' failed: file 'objects/rock.ai' not found",
+                "broken.ztd: Failed to parse meta.toml",
+            ]
+        );
+        assert!(in_game_messages(&issues()[..1]).is_empty());
+
+        let many: Vec<LoadIssue> = (0..7)
+            .map(|i| LoadIssue {
+                source: format!("mod{}", i),
+                stage: LoadStage::Load,
+                severity: Severity::Error,
+                message: "failed".to_string(),
+            })
+            .collect();
+        let messages = in_game_messages(&many);
+        assert_eq!(messages.len(), MAX_IN_GAME_MODS + 2);
+        assert_eq!(messages.last().unwrap(), "...and 2 more");
+    }
+}
@@ -30,6 +30,15 @@ pub struct OpenZTConfig {
     #[serde(default)]
     pub dev: DevConfig,
 
+    #[serde(default)]
+    pub updates: UpdatesConfig,
+
+    /// Values chosen for OpenZT mods' settings, by mod ID then setting key, e.g.
+    /// `[mod_settings."finn.moon"]` `litter_size = 3`. Settings not listed use the
+    /// default from the mod's meta.toml.
+    #[serde(default)]
+    pub mod_settings: IndexMap<String, IndexMap<String, toml::Value>>,
+
     #[cfg(feature = "tui")]
     #[serde(default)]
     pub tui: TuiConfig,
@@ -62,13 +71,131 @@ pub struct ModLoadingConfig {
     /// Warn on conflicts (default: true)
     #[serde(default = "default_true")]
     pub warn_on_conflicts: bool,
+
+    /// What to do when two enabled mods declare a conflict in meta.toml (default: warn)
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+
+    /// What to do when patches from two mods with the same priority write the same INI key (default: warn)
+    #[serde(default)]
+    pub patch_conflict_policy: PatchConflictPolicy,
+
+    /// Threads used to open and read archives during loading, 0 for one per CPU core (default: 1)
+    /// Archives are still added to the resource map in load order.
+    #[serde(default = "default_loader_threads")]
+    pub loader_threads: usize,
+
+    /// Locale used to pick mods' string overrides, e.g. "de-DE" (default: "", the Windows user locale)
+    #[serde(default)]
+    pub locale: String,
+
+    /// What to do when an archive in /mods/ doesn't match its entry in `checksums` (default: warn)
+    #[serde(default)]
+    pub checksum_policy: ChecksumPolicy,
+
+    /// What to do with archives in /mods/ that aren't signed by a key in `trusted_keys` (default: off)
+    #[serde(default)]
+    pub signature_policy: SignaturePolicy,
+
+    /// Whether `permitted_archives` adds to the built-in patterns or replaces them (default: extend)
+    #[serde(default)]
+    pub permitted_archives_mode: PermittedArchivesMode,
+
+    /// Match resource paths, patch targets and files inside OpenZT mods ignoring case, with
+    /// '\\' and '/' as the same separator, like the game does (default: false)
+    #[serde(default)]
+    pub case_insensitive_paths: bool,
+
+    /// Run the Lua scripts in OpenZT mods' scripts/ directories (default: true)
+    #[serde(default = "default_true")]
+    pub run_scripts: bool,
+
+    /// Expected SHA-256 of archives in /mods/, keyed by archive file name, or directory
+    /// name for unpacked mods (e.g. "mymod.ztd" = "9f86d0..."). The `mod_checksums()`
+    /// console command lists the checksums of the loaded archives.
+    #[serde(default)]
+    pub checksums: IndexMap<String, String>,
+
+    /// Ed25519 public keys whose mod signatures are trusted, keyed by a name for
+    /// the signer (e.g. "finn" = "4a6adf..."). Authors create keys with `openzt-mod keygen`.
+    #[serde(default)]
+    pub trusted_keys: IndexMap<String, String>,
+
+    /// Files that may only be loaded from specific archives, as a regex matched
+    /// case-insensitively against the file name and the archives (relative to the
+    /// Zoo Tycoon directory) it may come from, e.g. "^xpac.*\\.cfg$" = ["zupdate/config2.ztd"].
+    /// Needed for re-releases or localized installs that keep expansion files elsewhere.
+    #[serde(default)]
+    pub permitted_archives: IndexMap<String, Vec<String>>,
+}
+
+/// Handling of conflicts declared between enabled mods
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Log the conflicts and load every mod anyway
+    #[default]
+    Warn,
+    /// Log the conflicts and skip each mod that declared one
+    Block,
+}
+
+/// Handling of INI keys written by patches from more than one mod
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchConflictPolicy {
+    /// Let the later mod's patch win and log a warning
+    #[default]
+    Warn,
+    /// Fail the later mod's patch, which is then handled according to its on_error
+    Error,
+    /// Let the later mod's patch win without a warning
+    LastWins,
+}
+
+/// Handling of archives whose checksum doesn't match `checksums`
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumPolicy {
+    /// Log mismatches and load the archive anyway
+    #[default]
+    Warn,
+    /// Skip archives that don't match their checksum
+    Block,
+    /// Also skip archives without a checksum, once any checksum is configured
+    Strict,
+}
+
+/// Handling of mods that aren't signed by a trusted key
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SignaturePolicy {
+    /// Don't check signatures
+    #[default]
+    Off,
+    /// Log mods that are unsigned, signed by an untrusted key or whose signature doesn't match, and load them anyway
+    Warn,
+    /// Skip those mods, and legacy archives in /mods/ which can't be signed
+    Refuse,
+}
+
+/// How `permitted_archives` combines with the built-in permitted archive patterns
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PermittedArchivesMode {
+    /// Add archives to built-in patterns with the same regex, and check new patterns before the built-in ones
+    #[default]
+    Extend,
+    /// Use only the configured patterns
+    Override,
 }
 
 /// Resource cache configuration section
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct ResourceCacheConfig {
-    /// Maximum memory usage before unloading begins (in MB)
+    /// Maximum memory usage before unloading begins (in MB), 0 for no limit
+    /// Least recently used resources read from archives are unloaded first and decoded again when next needed
     #[serde(default = "default_max_memory_mb")]
     pub max_memory_mb: u32,
 
@@ -79,6 +206,10 @@ pub struct ResourceCacheConfig {
     /// Unload resources not accessed within this time (in seconds)
     #[serde(default = "default_stale_timeout_seconds")]
     pub stale_timeout_seconds: u64,
+
+    /// Reuse the parsed .cfg files from the last run while the loaded archives are unchanged
+    #[serde(default = "default_true")]
+    pub cache_parsed_configs: bool,
 }
 
 /// Custom expansions configuration section
@@ -108,12 +239,47 @@ pub struct DevConfig {
     /// Console listening address (default: "127.0.0.1:8080")
     #[serde(default = "default_console_listen")]
     pub console_listen: String,
+
+    /// Apply patches to a shadow copy instead of the live resources, and write a
+    /// report of every change to openzt_patch_report.txt (default: false)
+    #[serde(default)]
+    pub patch_dry_run: bool,
+
+    /// Unpacked mod directory loaded after all other mods and reloaded whenever its
+    /// files change, relative to the Zoo Tycoon directory (default: "", disabled).
+    /// Only used with the `experimental` feature.
+    #[serde(default)]
+    pub hot_reload_dir: String,
+
+    /// Write the time each phase of loading took per mod to openzt_load_trace.json, in the
+    /// Chrome trace event format (default: false)
+    #[serde(default)]
+    pub load_trace: bool,
+}
+
+/// Mod update checking configuration section
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct UpdatesConfig {
+    /// URL of a JSON index of published mod versions, checked by `check_mod_updates()`
+    /// (default: "", update checks disabled)
+    #[serde(default)]
+    pub index_url: String,
+
+    /// Check for updates in the background each time Zoo Tycoon starts and log the
+    /// result, only when `index_url` is set (default: false)
+    #[serde(default)]
+    pub check_on_startup: bool,
 }
 
 fn default_true() -> bool {
     true
 }
 
+fn default_loader_threads() -> usize {
+    1
+}
+
 fn default_console_listen() -> String {
     "127.0.0.1:8080".to_string()
 }
@@ -138,11 +304,25 @@ impl Default for OpenZTConfig {
                 disabled: Vec::new(),
                 auto_resolve_new_mods: true,
                 warn_on_conflicts: true,
+                conflict_policy: ConflictPolicy::default(),
+                patch_conflict_policy: PatchConflictPolicy::default(),
+                loader_threads: 1,
+                locale: String::new(),
+                checksum_policy: ChecksumPolicy::default(),
+                signature_policy: SignaturePolicy::default(),
+                permitted_archives_mode: PermittedArchivesMode::default(),
+                case_insensitive_paths: false,
+                run_scripts: true,
+                checksums: IndexMap::new(),
+                trusted_keys: IndexMap::new(),
+                permitted_archives: IndexMap::new(),
             },
             logging: LoggingConfig::default(),
             resource_cache: ResourceCacheConfig::default(),
             expansions: ExpansionConfig::default(),
             dev: DevConfig::default(),
+            updates: UpdatesConfig::default(),
+            mod_settings: IndexMap::new(),
             #[cfg(feature = "tui")]
             tui: TuiConfig::default(),
         }
@@ -156,6 +336,18 @@ impl Default for ModLoadingConfig {
             disabled: Vec::new(),
             auto_resolve_new_mods: true,
             warn_on_conflicts: true,
+            conflict_policy: ConflictPolicy::default(),
+            patch_conflict_policy: PatchConflictPolicy::default(),
+            loader_threads: 1,
+            locale: String::new(),
+            checksum_policy: ChecksumPolicy::default(),
+            signature_policy: SignaturePolicy::default(),
+            permitted_archives_mode: PermittedArchivesMode::default(),
+            case_insensitive_paths: false,
+            run_scripts: true,
+            checksums: IndexMap::new(),
+            trusted_keys: IndexMap::new(),
+            permitted_archives: IndexMap::new(),
         }
     }
 }
@@ -166,6 +358,7 @@ impl Default for ResourceCacheConfig {
             max_memory_mb: 2048,
             target_memory_mb: 1536,
             stale_timeout_seconds: 300,
+            cache_parsed_configs: true,
         }
     }
 }
@@ -180,6 +373,9 @@ impl Default for DevConfig {
     fn default() -> Self {
         DevConfig {
             console_listen: "127.0.0.1:8080".to_string(),
+            patch_dry_run: false,
+            hot_reload_dir: String::new(),
+            load_trace: false,
         }
     }
 }
@@ -220,6 +416,8 @@ fn load_openzt_config_from_disk() -> OpenZTConfig {
                     let has_resource_cache = toml_value.get("resource_cache").is_some();
                     let has_expansions = toml_value.get("expansions").is_some();
                     let has_dev = toml_value.get("dev").is_some();
+                    let has_updates = toml_value.get("updates").is_some();
+                    let has_mod_settings = toml_value.get("mod_settings").is_some();
                     #[cfg(feature = "tui")]
                     let has_tui = toml_value.get("tui").is_some();
                     #[cfg(not(feature = "tui"))]
@@ -231,12 +429,27 @@ fn load_openzt_config_from_disk() -> OpenZTConfig {
                             && mod_loading.get("disabled").is_some()
                             && mod_loading.get("auto_resolve_new_mods").is_some()
                             && mod_loading.get("warn_on_conflicts").is_some()
+                            && mod_loading.get("conflict_policy").is_some()
+                            && mod_loading.get("patch_conflict_policy").is_some()
+                            && mod_loading.get("loader_threads").is_some()
+                            && mod_loading.get("locale").is_some()
+                            && mod_loading.get("checksum_policy").is_some()
+                            && mod_loading.get("signature_policy").is_some()
+                            && mod_loading.get("permitted_archives_mode").is_some()
+                            && mod_loading.get("case_insensitive_paths").is_some()
+                            && mod_loading.get("run_scripts").is_some()
+                            && mod_loading.get("checksums").is_some()
+                            && mod_loading.get("trusted_keys").is_some()
+                            && mod_loading.get("permitted_archives").is_some()
                     } else {
                         false
                     };
 
                     let logging_complete = if let Some(logging) = toml_value.get("logging") {
-                        logging.get("log_to_file").is_some() && logging.get("level").is_some()
+                        logging.get("log_to_file").is_some()
+                            && logging.get("level").is_some()
+                            && logging.get("max_log_files").is_some()
+                            && logging.get("modules").is_some()
                     } else {
                         false
                     };
@@ -245,12 +458,22 @@ fn load_openzt_config_from_disk() -> OpenZTConfig {
                         resource_cache.get("max_memory_mb").is_some()
                             && resource_cache.get("target_memory_mb").is_some()
                             && resource_cache.get("stale_timeout_seconds").is_some()
+                            && resource_cache.get("cache_parsed_configs").is_some()
                     } else {
                         false
                     };
 
                     let dev_complete = if let Some(dev) = toml_value.get("dev") {
                         dev.get("console_listen").is_some()
+                            && dev.get("patch_dry_run").is_some()
+                            && dev.get("hot_reload_dir").is_some()
+                            && dev.get("load_trace").is_some()
+                    } else {
+                        false
+                    };
+
+                    let updates_complete = if let Some(updates) = toml_value.get("updates") {
+                        updates.get("index_url").is_some() && updates.get("check_on_startup").is_some()
                     } else {
                         false
                     };
@@ -275,10 +498,13 @@ fn load_openzt_config_from_disk() -> OpenZTConfig {
                         || !has_resource_cache
                         || !has_expansions
                         || !has_dev
+                        || !has_updates
+                        || !has_mod_settings
                         || !mod_loading_complete
                         || !logging_complete
                         || !resource_cache_complete
                         || !dev_complete
+                        || !updates_complete
                         || !has_tui
                         || !tui_complete
                 }
@@ -448,6 +674,7 @@ mod tests {
         let mut config = LoggingConfig {
             log_to_file: true,
             level: LogLevel::Trace,
+            ..LoggingConfig::default()
         };
 
         assert!(toml::to_string(&config).unwrap().contains("level = \"trace\""));
@@ -560,6 +787,23 @@ level = "error"
         assert!(parsed.mod_loading.warn_on_conflicts);
         assert!(parsed.logging.log_to_file);
         assert_eq!(parsed.logging.level, LogLevel::Warn);
+        assert_eq!(parsed.logging.max_log_files, 3);
+        assert!(parsed.logging.modules.is_empty());
+        assert!(parsed.updates.index_url.is_empty());
+        assert!(!parsed.updates.check_on_startup);
+    }
+
+    #[test]
+    fn test_logging_modules() {
+        let config_str = "[logging]\nlevel = \"warn\"\n\n[logging.modules]\nresource_manager = \"debug\"\npatches = \"info\"";
+        let parsed: OpenZTConfig = toml::from_str(config_str).unwrap();
+        assert_eq!(
+            parsed.logging.modules.into_iter().collect::<Vec<_>>(),
+            vec![("resource_manager".to_string(), LogLevel::Debug), ("patches".to_string(), LogLevel::Info)]
+        );
+
+        let serialized = toml::to_string(&OpenZTConfig::default()).unwrap();
+        assert!(toml::from_str::<OpenZTConfig>(&serialized).is_ok());
     }
 
     #[test]
@@ -622,4 +866,63 @@ level = "debug"
         assert_eq!(parsed.logging.level, LogLevel::Debug);
         assert!(parsed.logging.log_to_file); // default
     }
+
+    #[test]
+    fn test_checksums_section() {
+        let config_str = r#"
+[mod_loading]
+checksum_policy = "strict"
+
+[mod_loading.checksums]
+"mymod.ztd" = "sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+"#;
+
+        let parsed: OpenZTConfig = toml::from_str(config_str).unwrap();
+        assert_eq!(parsed.mod_loading.checksum_policy, ChecksumPolicy::Strict);
+        assert_eq!(parsed.mod_loading.checksums.len(), 1);
+
+        // Saving keeps the checksums table after the plain mod_loading keys
+        let toml_str = toml::to_string_pretty(&parsed).unwrap();
+        let reparsed: OpenZTConfig = toml::from_str(&toml_str).unwrap();
+        assert_eq!(reparsed.mod_loading.checksums, parsed.mod_loading.checksums);
+        assert_eq!(OpenZTConfig::default().mod_loading.checksum_policy, ChecksumPolicy::Warn);
+    }
+
+    #[test]
+    fn test_mod_settings_section() {
+        let config_str = r#"
+[mod_settings."finn.moon"]
+litter_size = 3
+hard_mode = true
+"#;
+
+        let parsed: OpenZTConfig = toml::from_str(config_str).unwrap();
+        assert_eq!(parsed.mod_settings["finn.moon"]["litter_size"], toml::Value::Integer(3));
+
+        let toml_str = toml::to_string_pretty(&parsed).unwrap();
+        assert!(toml_str.contains("[mod_settings.\"finn.moon\"]\nlitter_size = 3\nhard_mode = true\n"), "{}", toml_str);
+        let reparsed: OpenZTConfig = toml::from_str(&toml_str).unwrap();
+        assert_eq!(reparsed.mod_settings, parsed.mod_settings);
+        assert!(OpenZTConfig::default().mod_settings.is_empty());
+    }
+
+    #[test]
+    fn test_permitted_archives_section() {
+        let config_str = r#"
+[mod_loading]
+permitted_archives_mode = "override"
+
+[mod_loading.permitted_archives]
+"^xpac.*\\.cfg$" = ["zupdate/config2.ztd", "de/zupdate/config2.ztd"]
+"#;
+
+        let parsed: OpenZTConfig = toml::from_str(config_str).unwrap();
+        assert_eq!(parsed.mod_loading.permitted_archives_mode, PermittedArchivesMode::Override);
+        assert_eq!(parsed.mod_loading.permitted_archives[r"^xpac.*\.cfg$"].len(), 2);
+
+        let toml_str = toml::to_string_pretty(&parsed).unwrap();
+        let reparsed: OpenZTConfig = toml::from_str(&toml_str).unwrap();
+        assert_eq!(reparsed.mod_loading.permitted_archives, parsed.mod_loading.permitted_archives);
+        assert_eq!(OpenZTConfig::default().mod_loading.permitted_archives_mode, PermittedArchivesMode::Extend);
+    }
 }
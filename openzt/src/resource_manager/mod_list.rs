@@ -0,0 +1,252 @@
+//! List of the mods in the resolved load order and what happened to each
+//!
+//! Recorded once the load order is resolved: each OpenZT mod and /mods/ archive in
+//! the order they were loaded, with its version and whether it was loaded, disabled
+//! in openzt.toml or blocked by a conflict. A loaded mod that recorded an error in the
+//! load report is listed as failed. Ctrl+Shift+M shows the list in the game's message
+//! bar, so it ends up in screenshots, and `mod_list()` returns it in the console.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+
+use crate::resource_manager::load_report;
+use crate::shortcuts::{Ctrl, M, Shift};
+
+/// Most mods listed in game, the rest are only counted
+const MAX_IN_GAME_MODS: usize = 20;
+
+/// What happened to a mod in the load order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModStatus {
+    Loaded,
+    /// In the `disabled` list in openzt.toml
+    Disabled,
+    /// Not loaded because it conflicts with another mod and conflict_policy is "block"
+    Blocked,
+    /// Loaded, but with errors in the load report
+    Failed,
+}
+
+impl ModStatus {
+    fn name(&self) -> &'static str {
+        match self {
+            ModStatus::Loaded => "loaded",
+            ModStatus::Disabled => "disabled",
+            ModStatus::Blocked => "blocked",
+            ModStatus::Failed => "failed",
+        }
+    }
+}
+
+/// A mod ID or .ztd file name in the load order
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModEntry {
+    pub id: String,
+    /// Version from meta.toml, `None` for legacy archives
+    pub version: Option<String>,
+    pub status: ModStatus,
+}
+
+static LOAD_ORDER: LazyLock<Mutex<Vec<ModEntry>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// The entries of the resolved `order`, given the mods actually `loading` after conflicts were applied
+pub fn build_entries(order: &[String], loading: &[String], disabled_mods: &[String], disabled_ztds: &[String], versions: &HashMap<String, String>) -> Vec<ModEntry> {
+    order
+        .iter()
+        .map(|id| {
+            let status = if disabled_mods.contains(id) || disabled_ztds.iter().any(|ztd| ztd.eq_ignore_ascii_case(id)) {
+                ModStatus::Disabled
+            } else if !loading.contains(id) {
+                ModStatus::Blocked
+            } else {
+                ModStatus::Loaded
+            };
+            ModEntry {
+                id: id.clone(),
+                version: versions.get(id).cloned(),
+                status,
+            }
+        })
+        .collect()
+}
+
+/// Record the resolved load order
+pub fn set_load_order(entries: Vec<ModEntry>) {
+    *LOAD_ORDER.lock().unwrap() = entries;
+}
+
+/// `entries` with the loaded ones that had errors marked as failed
+fn with_failures(entries: &[ModEntry], failed: &HashSet<String>) -> Vec<ModEntry> {
+    entries
+        .iter()
+        .map(|entry| {
+            let mut entry = entry.clone();
+            if entry.status == ModStatus::Loaded && failed.contains(&entry.id) {
+                entry.status = ModStatus::Failed;
+            }
+            entry
+        })
+        .collect()
+}
+
+fn count(entries: &[ModEntry], status: ModStatus) -> usize {
+    entries.iter().filter(|entry| entry.status == status).count()
+}
+
+fn summary(entries: &[ModEntry]) -> String {
+    let mut summary = format!(
+        "OpenZT {}: {} mods, {} loaded, {} disabled, {} failed",
+        env!("CARGO_PKG_VERSION"),
+        entries.len(),
+        count(entries, ModStatus::Loaded),
+        count(entries, ModStatus::Disabled),
+        count(entries, ModStatus::Failed)
+    );
+    let blocked = count(entries, ModStatus::Blocked);
+    if blocked > 0 {
+        let _ = write!(summary, ", {} blocked", blocked);
+    }
+    summary
+}
+
+fn entry_line(position: usize, entry: &ModEntry) -> String {
+    format!(
+        "{}. {} {} ({})",
+        position + 1,
+        entry.id,
+        entry.version.as_deref().unwrap_or("legacy"),
+        entry.status.name()
+    )
+}
+
+/// The full list, a summary then one line per mod in load order
+pub fn format_list(entries: &[ModEntry]) -> String {
+    if entries.is_empty() {
+        return "No mods in the load order\n".to_string();
+    }
+
+    let mut list = format!("{}\n", summary(entries));
+    for (position, entry) in entries.iter().enumerate() {
+        let _ = writeln!(list, "  {}", entry_line(position, entry));
+    }
+    list
+}
+
+/// Messages shown in game: the summary, then one per mod
+pub fn in_game_messages(entries: &[ModEntry]) -> Vec<String> {
+    if entries.is_empty() {
+        return vec!["OpenZT: No mods in the load order".to_string()];
+    }
+
+    let mut messages = vec![summary(entries)];
+    for (position, entry) in entries.iter().enumerate().take(MAX_IN_GAME_MODS) {
+        messages.push(entry_line(position, entry));
+    }
+    if entries.len() > MAX_IN_GAME_MODS {
+        messages.push(format!("...and {} more, run mod_list() in the console for all", entries.len() - MAX_IN_GAME_MODS));
+    }
+    messages
+}
+
+fn current() -> Vec<ModEntry> {
+    with_failures(&LOAD_ORDER.lock().unwrap(), &load_report::sources_with_errors())
+}
+
+/// The full list of the mods in the load order
+pub fn list() -> String {
+    format_list(&current())
+}
+
+/// Show the list in the game's message bar
+fn show_in_game() {
+    for message in in_game_messages(&current()) {
+        crate::ztui::display_message(&message);
+    }
+}
+
+pub fn init() {
+    crate::shortcut!(
+        "mod_list",
+        "Show the loaded mods and their versions in the message bar",
+        M + Ctrl + Shift,
+        false, // override
+        || {
+            show_in_game();
+        }
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn entries() -> Vec<ModEntry> {
+        let order = strings(&["finn.framework", "Fences.ztd", "finn.moon", "finn.sun", "finn.comet"]);
+        let loading = strings(&["finn.framework", "Fences.ztd", "finn.moon"]);
+        let versions = HashMap::from([
+            ("finn.framework".to_string(), "1.0.0".to_string()),
+            ("finn.moon".to_string(), "2.1.0".to_string()),
+            ("finn.sun".to_string(), "0.3.0".to_string()),
+            ("finn.comet".to_string(), "1.0.0".to_string()),
+        ]);
+        build_entries(&order, &loading, &strings(&["finn.sun"]), &strings(&["fences.ztd"]), &versions)
+    }
+
+    #[test]
+    fn test_build_entries() {
+        let entries = entries();
+        let statuses: Vec<(&str, ModStatus)> = entries.iter().map(|entry| (entry.id.as_str(), entry.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("finn.framework", ModStatus::Loaded),
+                ("Fences.ztd", ModStatus::Disabled),
+                ("finn.moon", ModStatus::Loaded),
+                ("finn.sun", ModStatus::Disabled),
+                ("finn.comet", ModStatus::Blocked),
+            ]
+        );
+        assert_eq!(entries[1].version, None);
+    }
+
+    #[test]
+    fn test_format_list() {
+        let entries = with_failures(&entries(), &HashSet::from(["finn.moon".to_string(), "finn.sun".to_string()]));
+        assert_eq!(
+            format_list(&entries),
+            format!(
+                "OpenZT {}: 5 mods, 1 loaded, 2 disabled, 1 failed, 1 blocked\n  \
+                 1. finn.framework 1.0.0 (loaded)\n  \
+                 2. Fences.ztd legacy (disabled)\n  \
+                 3. finn.moon 2.1.0 (failed)\n  \
+                 4. finn.sun 0.3.0 (disabled)\n  \
+                 5. finn.comet 1.0.0 (blocked)\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+        assert_eq!(format_list(&[]), "No mods in the load order\n");
+    }
+
+    #[test]
+    fn test_in_game_messages() {
+        let messages = in_game_messages(&entries());
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[1], "1. finn.framework 1.0.0 (loaded)");
+
+        let many: Vec<ModEntry> = (0..MAX_IN_GAME_MODS + 3)
+            .map(|i| ModEntry {
+                id: format!("mod{}", i),
+                version: None,
+                status: ModStatus::Loaded,
+            })
+            .collect();
+        let messages = in_game_messages(&many);
+        assert_eq!(messages.len(), MAX_IN_GAME_MODS + 2);
+        assert_eq!(messages.last().unwrap(), "...and 3 more, run mod_list() in the console for all");
+    }
+}
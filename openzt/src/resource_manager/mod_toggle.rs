@@ -0,0 +1,162 @@
+//! Enabling and disabling mods from the console
+//!
+//! `enable_mod(id)` and `disable_mod(id)` edit the `disabled` list in the
+//! `[mod_loading]` section of openzt.toml, which the resolver honors the next
+//! time Zoo Tycoon starts. `id` is an OpenZT mod ID or a .ztd file name. The
+//! change is checked against the dependencies declared in the installed mods'
+//! meta.toml: disabling a mod that enabled mods require, or enabling a mod whose
+//! required dependencies are disabled or missing, is written anyway with a warning.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::anyhow;
+use tracing::info;
+
+use crate::{
+    mods::{DependencyIdentifier, Meta},
+    resource_manager::mod_config::{get_openzt_config, save_openzt_config, ModLoadingConfig},
+};
+
+/// Installed mods and legacy archives in /mods/, with the mod IDs and .ztd names each requires
+static INSTALLED: LazyLock<Mutex<HashMap<String, Vec<String>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The mod IDs and .ztd file names a mod can't load without
+pub fn required_dependencies(meta: &Meta) -> Vec<String> {
+    meta.dependencies()
+        .iter()
+        .filter(|dependency| !*dependency.optional())
+        .filter_map(|dependency| match dependency.identifier() {
+            DependencyIdentifier::ModId(id) => Some(id.clone()),
+            DependencyIdentifier::ZtdName(name) => Some(name.clone()),
+            DependencyIdentifier::DllName(_) => None,
+        })
+        .collect()
+}
+
+/// Record the mods found during discovery
+pub fn set_installed(installed: HashMap<String, Vec<String>>) {
+    *INSTALLED.lock().unwrap() = installed;
+}
+
+fn is_ztd(id: &str) -> bool {
+    id.to_lowercase().ends_with(".ztd")
+}
+
+/// Whether two entries name the same mod; .ztd names are compared case-insensitively
+fn same_entry(a: &str, b: &str) -> bool {
+    if is_ztd(a) { a.eq_ignore_ascii_case(b) } else { a == b }
+}
+
+fn is_disabled(mod_loading: &ModLoadingConfig, id: &str) -> bool {
+    mod_loading.disabled.iter().any(|disabled| same_entry(disabled, id))
+}
+
+/// Enable or disable a mod in `mod_loading`
+///
+/// Returns a warning for each dependency the change breaks. Fails without changing anything
+/// if `id` isn't installed (any .ztd may be disabled) or is already in the requested state.
+pub fn set_enabled(mod_loading: &mut ModLoadingConfig, id: &str, enabled: bool, installed: &HashMap<String, Vec<String>>) -> anyhow::Result<Vec<String>> {
+    if !is_ztd(id) && !installed.contains_key(id) {
+        return Err(anyhow!("No mod with ID '{}' is installed", id));
+    }
+    if is_disabled(mod_loading, id) != enabled {
+        return Err(anyhow!("'{}' is already {}", id, if enabled { "enabled" } else { "disabled" }));
+    }
+
+    let mut warnings = Vec::new();
+    if enabled {
+        mod_loading.disabled.retain(|disabled| !same_entry(disabled, id));
+        for dependency in installed.get(id).into_iter().flatten() {
+            if is_disabled(mod_loading, dependency) {
+                warnings.push(format!("{} requires {}, which is disabled", id, dependency));
+            } else if !is_ztd(dependency) && !installed.contains_key(dependency) {
+                warnings.push(format!("{} requires {}, which is not installed", id, dependency));
+            }
+        }
+    } else {
+        mod_loading.disabled.push(id.to_string());
+        let mut dependents: Vec<&String> = installed
+            .iter()
+            .filter(|(dependent, dependencies)| !is_disabled(mod_loading, dependent) && dependencies.iter().any(|dependency| same_entry(dependency, id)))
+            .map(|(dependent, _)| dependent)
+            .collect();
+        dependents.sort();
+        for dependent in dependents {
+            warnings.push(format!("{} requires {} and may fail to load without it", dependent, id));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Enable or disable a mod in openzt.toml, taking effect the next time Zoo Tycoon starts
+pub fn toggle(id: &str, enabled: bool) -> anyhow::Result<String> {
+    let mut config = get_openzt_config();
+    let warnings = set_enabled(&mut config.mod_loading, id, enabled, &INSTALLED.lock().unwrap())?;
+    save_openzt_config(&config, false)?;
+
+    let action = if enabled { "Enabled" } else { "Disabled" };
+    info!("{} '{}' in openzt.toml", action, id);
+    let mut result = format!("{} '{}', restart Zoo Tycoon to apply", action, id);
+    for warning in warnings {
+        result.push_str(&format!("\n  warning: {}", warning));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed() -> HashMap<String, Vec<String>> {
+        HashMap::from([
+            ("finn.framework".to_string(), Vec::new()),
+            ("finn.moon".to_string(), vec!["finn.framework".to_string(), "Fences.ztd".to_string()]),
+            ("finn.sun".to_string(), vec!["finn.framework".to_string(), "finn.missing".to_string()]),
+            ("fences.ztd".to_string(), Vec::new()),
+        ])
+    }
+
+    #[test]
+    fn test_disable_warns_about_dependents() {
+        let mut mod_loading = ModLoadingConfig {
+            disabled: vec!["finn.sun".to_string()],
+            ..Default::default()
+        };
+
+        let warnings = set_enabled(&mut mod_loading, "finn.framework", false, &installed()).unwrap();
+        assert_eq!(warnings, vec!["finn.moon requires finn.framework and may fail to load without it"]);
+        assert_eq!(mod_loading.disabled, vec!["finn.sun", "finn.framework"]);
+
+        let warnings = set_enabled(&mut mod_loading, "FENCES.ZTD", false, &installed()).unwrap();
+        assert_eq!(warnings, vec!["finn.moon requires FENCES.ZTD and may fail to load without it"]);
+
+        assert_eq!(
+            set_enabled(&mut mod_loading, "fences.ztd", false, &installed()).unwrap_err().to_string(),
+            "'fences.ztd' is already disabled"
+        );
+        assert_eq!(
+            set_enabled(&mut mod_loading, "finn.comet", false, &installed()).unwrap_err().to_string(),
+            "No mod with ID 'finn.comet' is installed"
+        );
+    }
+
+    #[test]
+    fn test_enable_warns_about_dependencies() {
+        let mut mod_loading = ModLoadingConfig {
+            disabled: vec!["finn.sun".to_string(), "finn.framework".to_string()],
+            ..Default::default()
+        };
+
+        let warnings = set_enabled(&mut mod_loading, "finn.sun", true, &installed()).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                "finn.sun requires finn.framework, which is disabled",
+                "finn.sun requires finn.missing, which is not installed"
+            ]
+        );
+        assert_eq!(mod_loading.disabled, vec!["finn.framework"]);
+        assert!(set_enabled(&mut mod_loading, "finn.moon", true, &installed()).is_err());
+    }
+}
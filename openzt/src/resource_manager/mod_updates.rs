@@ -0,0 +1,254 @@
+//! Checking installed mods for updates against a remote index
+//!
+//! Update checks are opt-in: `index_url` in the `[updates]` section of openzt.toml
+//! points to a JSON index of published mod versions. The index is fetched on a
+//! background thread, either once mods are discovered when `check_on_startup` is set or
+//! when `check_mod_updates()` is first run, and the console command reports the last
+//! result: the installed OpenZT mods that have a newer version. Nothing is downloaded
+//! or installed, the index only says where each update is. Update checks, and the HTTP
+//! client they use, are only built with the `experimental` feature.
+//!
+//! ```json
+//! {
+//!   "mods": {
+//!     "finn.framework": { "version": "1.3.0", "url": "https://example.com/framework" },
+//!     "finn.moon": { "version": "2.0.1" }
+//!   }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{mods::Version, resource_manager::mod_config::get_openzt_config};
+
+/// How long fetching the index may take before the check gives up
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A mod's latest published version in the index
+#[derive(Deserialize, Debug, Clone)]
+pub struct PublishedMod {
+    pub version: String,
+    /// Where the update can be downloaded
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// Published versions keyed by mod ID
+#[derive(Deserialize, Debug, Default)]
+pub struct UpdateIndex {
+    #[serde(default)]
+    pub mods: HashMap<String, PublishedMod>,
+}
+
+/// An installed mod with a newer published version
+#[derive(Debug, PartialEq)]
+pub struct Update {
+    pub mod_id: String,
+    pub installed: Version,
+    pub published: Version,
+    pub url: Option<String>,
+}
+
+/// OpenZT mods found in /mods/ during discovery, with their versions
+static INSTALLED: LazyLock<Mutex<HashMap<String, Version>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Progress of the background update check
+enum CheckState {
+    NotStarted,
+    Running,
+    Finished(Result<String, String>),
+}
+
+/// The last update check, shared between the check thread and the console command
+static CHECK_STATE: LazyLock<Mutex<CheckState>> = LazyLock::new(|| Mutex::new(CheckState::NotStarted));
+
+/// Record the mods found during discovery
+pub fn set_installed(installed: HashMap<String, Version>) {
+    *INSTALLED.lock().unwrap() = installed;
+}
+
+/// Compare installed versions against an index, sorted by mod ID
+///
+/// Returns the available updates and a warning for each index entry with an invalid version.
+/// Mods the index doesn't list are skipped.
+pub fn find_updates(index: &UpdateIndex, installed: &HashMap<String, Version>) -> (Vec<Update>, Vec<String>) {
+    let mut updates = Vec::new();
+    let mut warnings = Vec::new();
+    for (mod_id, installed_version) in installed {
+        let Some(published) = index.mods.get(mod_id) else {
+            continue;
+        };
+        match published.version.parse::<Version>() {
+            Ok(published_version) if published_version > *installed_version => updates.push(Update {
+                mod_id: mod_id.clone(),
+                installed: installed_version.clone(),
+                published: published_version,
+                url: published.url.clone(),
+            }),
+            Ok(_) => {}
+            Err(e) => warnings.push(format!("{} has an invalid version '{}' in the index: {}", mod_id, published.version, e)),
+        }
+    }
+    updates.sort_by(|a, b| a.mod_id.cmp(&b.mod_id));
+    warnings.sort();
+    (updates, warnings)
+}
+
+/// Format the result of an update check
+pub fn format_updates(updates: &[Update], warnings: &[String], checked: usize) -> String {
+    let mut report = String::new();
+    if updates.is_empty() {
+        let _ = writeln!(report, "All {} installed OpenZT mods are up to date", checked);
+    } else {
+        let _ = writeln!(report, "{} of {} installed OpenZT mods have updates:", updates.len(), checked);
+    }
+    for update in updates {
+        let _ = write!(report, "  {} {} -> {}", update.mod_id, update.installed, update.published);
+        match &update.url {
+            Some(url) => {
+                let _ = writeln!(report, " ({})", url);
+            }
+            None => report.push('\n'),
+        }
+    }
+    for warning in warnings {
+        let _ = writeln!(report, "  warning: {}", warning);
+    }
+    report
+}
+
+/// Download and parse the index
+fn fetch_index(url: &str) -> anyhow::Result<UpdateIndex> {
+    let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(FETCH_TIMEOUT)).build().into();
+    let body = agent
+        .get(url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .with_context(|| format!("Failed to fetch the update index from {}", url))?;
+    serde_json::from_str(&body).with_context(|| format!("Failed to parse the update index from {}", url))
+}
+
+/// Check the installed mods against the index in openzt.toml
+pub fn check() -> anyhow::Result<String> {
+    let url = get_openzt_config().updates.index_url;
+    if url.is_empty() {
+        return Err(anyhow!("No update index configured, set index_url in the [updates] section of openzt.toml"));
+    }
+
+    let index = fetch_index(&url)?;
+    let installed = INSTALLED.lock().unwrap().clone();
+    let (updates, warnings) = find_updates(&index, &installed);
+    info!("{} of {} installed OpenZT mods have updates in {}", updates.len(), installed.len(), url);
+    Ok(format_updates(&updates, &warnings, installed.len()))
+}
+
+/// Run `check` on a background thread, logging the result and keeping it for `check_mod_updates()`
+///
+/// Does nothing if a check is already running.
+fn start_check() {
+    {
+        let mut state = CHECK_STATE.lock().unwrap();
+        if matches!(*state, CheckState::Running) {
+            return;
+        }
+        *state = CheckState::Running;
+    }
+    std::thread::spawn(|| {
+        let result = check().map_err(|e| format!("{:#}", e));
+        match &result {
+            Ok(report) => info!("{}", report.trim_end()),
+            Err(e) => warn!("Update check failed: {}", e),
+        }
+        *CHECK_STATE.lock().unwrap() = CheckState::Finished(result);
+    });
+}
+
+/// Check for updates on a background thread if `check_on_startup` is set
+pub fn check_on_startup() {
+    let updates = get_openzt_config().updates;
+    if !updates.check_on_startup || updates.index_url.is_empty() {
+        return;
+    }
+    start_check();
+}
+
+/// Report the last update check, starting one if none has run yet
+///
+/// The index is never fetched on the calling thread, so the console command doesn't hold up the game.
+pub fn check_command() -> Result<String, String> {
+    if get_openzt_config().updates.index_url.is_empty() {
+        return Err("No update index configured, set index_url in the [updates] section of openzt.toml".to_string());
+    }
+    match &*CHECK_STATE.lock().unwrap() {
+        CheckState::Finished(result) => return result.clone(),
+        CheckState::Running => return Ok("Update check in progress, run check_mod_updates() again for the result".to_string()),
+        CheckState::NotStarted => {}
+    }
+    start_check();
+    Ok("Update check started, run check_mod_updates() again for the result".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> Version {
+        version.parse().unwrap()
+    }
+
+    fn installed() -> HashMap<String, Version> {
+        HashMap::from([
+            ("finn.framework".to_string(), version("1.2.0")),
+            ("finn.moon".to_string(), version("2.0.1")),
+            ("finn.sun".to_string(), version("1.0.0")),
+            ("finn.local".to_string(), version("0.1.0")),
+        ])
+    }
+
+    #[test]
+    fn test_find_updates() {
+        let index: UpdateIndex = serde_json::from_str(
+            r#"{
+                "mods": {
+                    "finn.framework": { "version": "1.10.0", "url": "https://example.com/framework" },
+                    "finn.moon": { "version": "2.0.1" },
+                    "finn.sun": { "version": "1.0" },
+                    "finn.uninstalled": { "version": "3.0.0" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let (updates, warnings) = find_updates(&index, &installed());
+        assert_eq!(
+            updates,
+            vec![Update {
+                mod_id: "finn.framework".to_string(),
+                installed: version("1.2.0"),
+                published: version("1.10.0"),
+                url: Some("https://example.com/framework".to_string()),
+            }]
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("finn.sun has an invalid version '1.0' in the index"), "{}", warnings[0]);
+
+        assert_eq!(
+            format_updates(&updates, &[], 4),
+            "1 of 4 installed OpenZT mods have updates:\n  finn.framework 1.2.0 -> 1.10.0 (https://example.com/framework)\n"
+        );
+    }
+
+    #[test]
+    fn test_up_to_date() {
+        let (updates, warnings) = find_updates(&UpdateIndex::default(), &installed());
+        assert!(updates.is_empty() && warnings.is_empty());
+        assert_eq!(format_updates(&updates, &warnings, 4), "All 4 installed OpenZT mods are up to date\n");
+    }
+}
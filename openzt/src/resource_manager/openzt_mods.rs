@@ -1,14 +1,23 @@
 pub(crate) mod entity_lookup;
+pub(crate) mod entity_types;
 pub(crate) mod extensions;
 pub(crate) mod habitats_locations;
+pub(crate) mod hot_reload;
 pub(crate) mod legacy_attributes;
 pub(crate) mod loading;
+pub(crate) mod mod_settings;
+pub(crate) mod patch_conflicts;
+pub(crate) mod patch_dry_run;
 pub mod patches;
+pub(crate) mod scripts;
+pub(crate) mod shared_sources;
+pub(crate) mod sprites;
+pub(crate) mod strings;
 pub(crate) mod ztd_registry;
 
 pub use crate::resource_manager::openzt_mods::{
     habitats_locations::{get_location_habitat_ids, get_location_or_habitat_by_id},
-    loading::{discover_mods, get_mod_ids, get_num_mod_ids, load_open_zt_mod},
+    loading::{discover_mods, get_num_mod_ids, load_open_zt_mod_files, read_open_zt_mod_files},
 };
 
 // Re-export items needed for integration tests
@@ -0,0 +1,336 @@
+//! New entity types defined in OpenZT mods
+//!
+//! A def file's `[new_scenery]` and `[new_animals]` tables define entities from a
+//! handful of fields, instead of a hand-written .ai file, .cfg entries and expansion
+//! members for each one. The table key is the entity's name:
+//!
+//! ```toml
+//! [new_scenery.moonrock]
+//! name = "Moon Rock"
+//! help = "A rock brought back from the moon"
+//! cost = 150
+//! footprint_x = 2
+//! icon = "objects/moonrock/icon/moonrock/moonrock"
+//! animations = { idle = "objects/moonrock/idle" }
+//! expansion = "lunar"
+//! ```
+//!
+//! When the mod is loaded each entity is expanded into:
+//! - `objects/<name>/<name>.ai` (`animals/<name>/<name>.ai` for animals), with the
+//!   name and help registered as new strings, the cost, footprint, icon, animations
+//!   and any extra `characteristics`. Every animal subtype (m, f and j unless
+//!   `subtypes` is set) gets its own name and animations, with "{subtype}" in the
+//!   animation paths replaced by the subtype. An animal's `habitat` and `location`
+//!   name habitats and locations of the same mod.
+//! - An entry in the `[objects]` section of scener.cfg (`[animals]` of animal.cfg,
+//!   with its subtypes), so the entity is parsed like any other once every mod is
+//!   loaded and other mods can extend or patch it.
+//! - With `expansion`, a member of that expansion from the same def file.
+//!
+//! The icon and animations aren't generated, they are the mod's own files or
+//! come from `[sprites]`.
+
+use std::collections::HashSet;
+use std::ffi::CString;
+
+use anyhow::{anyhow, Context};
+use openzt_configparser::ini::{Ini, WriteOptions};
+use tracing::info;
+
+use crate::{
+    encoding_utils::decode_game_text,
+    mods::{EntityTypeDefinition, ModDefinition},
+    resource_manager::{
+        file_overrides,
+        lazyresourcemap::{add_ztfile_from_memory, check_file, get_file},
+        openzt_mods::{
+            habitats_locations::{get_habitat_id, get_location_id},
+            hot_reload,
+        },
+        ztfile::{ZTFile, ZTFileType},
+    },
+    string_registry::add_string_to_registry,
+};
+
+/// Subtypes of an animal that doesn't list its own
+const DEFAULT_SUBTYPES: [&str; 3] = ["m", "f", "j"];
+
+const INTEGERS_SECTION: &str = "Characteristics/Integers";
+
+/// The kind of entity a definition adds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Scenery,
+    Animal,
+}
+
+impl EntityKind {
+    /// The def file table the definitions are in
+    fn def_section(&self) -> &'static str {
+        match self {
+            EntityKind::Scenery => "new_scenery",
+            EntityKind::Animal => "new_animals",
+        }
+    }
+
+    /// The .cfg file the game lists entities of this kind in
+    fn cfg_file(&self) -> &'static str {
+        match self {
+            EntityKind::Scenery => "scener.cfg",
+            EntityKind::Animal => "animal.cfg",
+        }
+    }
+
+    fn cfg_section(&self) -> &'static str {
+        match self {
+            EntityKind::Scenery => "objects",
+            EntityKind::Animal => "animals",
+        }
+    }
+
+    fn directory(&self) -> &'static str {
+        match self {
+            EntityKind::Scenery => "objects",
+            EntityKind::Animal => "animals",
+        }
+    }
+}
+
+/// IDs of the strings, habitat and location an entity refers to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntityIds {
+    pub name: u32,
+    pub help: Option<u32>,
+    pub habitat: Option<u32>,
+    pub location: Option<u32>,
+}
+
+/// Path of the generated .ai file
+pub fn ai_path(kind: EntityKind, name: &str) -> String {
+    format!("{}/{}/{}.ai", kind.directory(), name, name)
+}
+
+/// Subtypes of an entity, none for scenery
+pub fn subtypes(kind: EntityKind, definition: &EntityTypeDefinition) -> Vec<String> {
+    match kind {
+        EntityKind::Scenery => Vec::new(),
+        EntityKind::Animal => definition
+            .subtypes()
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SUBTYPES.iter().map(|subtype| subtype.to_string()).collect()),
+    }
+}
+
+/// The new entity types of a def file, scenery first, then in name order
+pub fn definitions(mod_def: &ModDefinition) -> Vec<(EntityKind, &String, &EntityTypeDefinition)> {
+    let mut definitions = Vec::new();
+    for (kind, entities) in [(EntityKind::Scenery, mod_def.new_scenery()), (EntityKind::Animal, mod_def.new_animals())] {
+        let Some(entities) = entities else {
+            continue;
+        };
+        let mut entities: Vec<_> = entities.iter().collect();
+        entities.sort_by_key(|(name, _)| name.as_str());
+        definitions.extend(entities.into_iter().map(|(name, definition)| (kind, name, definition)));
+    }
+    definitions
+}
+
+/// Names of the new entity types in a def file that join the expansion `key`
+pub fn expansion_members(mod_def: &ModDefinition, key: &str) -> Vec<String> {
+    definitions(mod_def)
+        .into_iter()
+        .filter(|(_, _, definition)| definition.expansion().as_deref() == Some(key))
+        .map(|(_, name, _)| name.clone())
+        .collect()
+}
+
+/// Generate the .ai file of an entity
+pub fn generate_ai(kind: EntityKind, definition: &EntityTypeDefinition, ids: &EntityIds) -> Ini {
+    let mut ai = Ini::new_cs();
+    let subtypes = subtypes(kind, definition);
+    if subtypes.is_empty() {
+        ai.set(INTEGERS_SECTION, "cNameID", Some(ids.name.to_string()));
+    }
+    if let Some(help) = ids.help {
+        ai.set(INTEGERS_SECTION, "cHelpID", Some(help.to_string()));
+    }
+    ai.set(INTEGERS_SECTION, "cPurchaseCost", Some(definition.cost().to_string()));
+    ai.set(INTEGERS_SECTION, "cFootprintX", Some(definition.footprint_x().to_string()));
+    ai.set(INTEGERS_SECTION, "cFootprintY", Some(definition.footprint_y().to_string()));
+    if let Some(habitat) = ids.habitat {
+        ai.set(INTEGERS_SECTION, "cHabitat", Some(habitat.to_string()));
+    }
+    if let Some(location) = ids.location {
+        ai.set(INTEGERS_SECTION, "cLocation", Some(location.to_string()));
+    }
+    for (key, value) in definition.characteristics() {
+        ai.set(INTEGERS_SECTION, key, Some(value.clone()));
+    }
+
+    ai.set("Icon", "Icon", Some(definition.icon().clone()));
+    if subtypes.is_empty() {
+        for (animation, path) in definition.animations() {
+            ai.set("Animations", animation, Some(path.clone()));
+        }
+    }
+    for subtype in &subtypes {
+        ai.set(&format!("{}/{}", subtype, INTEGERS_SECTION), "cNameID", Some(ids.name.to_string()));
+        for (animation, path) in definition.animations() {
+            ai.set(&format!("{}/Animations", subtype), animation, Some(path.replace("{subtype}", subtype)));
+        }
+    }
+    ai
+}
+
+/// Add an entity to the .cfg listing its kind, with its subtypes
+pub fn add_to_cfg(cfg: &mut Ini, kind: EntityKind, name: &str, subtypes: &[String]) {
+    cfg.set(kind.cfg_section(), name, Some(ai_path(kind, name)));
+    for subtype in subtypes {
+        cfg.set(&format!("{}/subtypes", name), subtype, None);
+    }
+}
+
+fn add_ini(mod_id: &str, path: &str, ini: &Ini, file_type: ZTFileType) -> anyhow::Result<()> {
+    let mut write_options = WriteOptions::default();
+    write_options.space_around_delimiters = true;
+    write_options.blank_lines_between_sections = 1;
+    let content = ini.pretty_writes(&write_options);
+    let file_size = content.len() as u32;
+    let c_string = CString::new(content).with_context(|| format!("Generated {} contains a null byte", path))?;
+    add_ztfile_from_memory(mod_id, path.to_string(), ZTFile::Text(c_string, file_type, file_size))
+}
+
+/// The .cfg listing entities of `kind`, empty if no archive has one
+fn read_cfg(kind: EntityKind) -> anyhow::Result<Ini> {
+    let mut cfg = Ini::new_cs();
+    cfg.set_comment_symbols(&[';', '#', ':']);
+    if let Some((_, data)) = get_file(kind.cfg_file()) {
+        cfg.read(decode_game_text(&data)).map_err(|e| anyhow!("Failed to parse {}: {}", kind.cfg_file(), e))?;
+    }
+    Ok(cfg)
+}
+
+/// Generate an entity's .ai file and add the entity to its kind's .cfg
+pub fn load_entity_type(mod_id: &str, kind: EntityKind, name: &str, definition: &EntityTypeDefinition) -> anyhow::Result<()> {
+    if definition.animations().is_empty() {
+        return Err(anyhow!("Entity type {} has no animations", name));
+    }
+    let ai_path = ai_path(kind, name);
+    if check_file(&ai_path) {
+        return Err(anyhow!("Entity type {} would replace {}, which another archive already has", name, ai_path));
+    }
+
+    let habitat = match definition.habitat() {
+        Some(habitat) => Some(get_habitat_id(mod_id, habitat).with_context(|| format!("Mod {} has no habitat {}", mod_id, habitat))?),
+        None => None,
+    };
+    let location = match definition.location() {
+        Some(location) => Some(get_location_id(mod_id, location).with_context(|| format!("Mod {} has no location {}", mod_id, location))?),
+        None => None,
+    };
+    let ids = EntityIds {
+        name: add_string_to_registry(definition.name().clone()),
+        help: definition.help().clone().map(add_string_to_registry),
+        habitat,
+        location,
+    };
+    let ai = generate_ai(kind, definition, &ids);
+
+    let mut cfg = read_cfg(kind)?;
+    add_to_cfg(&mut cfg, kind, name, &subtypes(kind, definition));
+
+    // Files replaced by the dev mod are restored when it is reloaded
+    let cfg_file = kind.cfg_file();
+    hot_reload::snapshot_before_patches(mod_id, &HashSet::from([ai_path.clone(), cfg_file.to_string()]))?;
+    add_ini(mod_id, &ai_path, &ai, ZTFileType::Ai)?;
+    add_ini(mod_id, cfg_file, &cfg, ZTFileType::Cfg)?;
+
+    let source = format!("{}.{}", kind.def_section(), name);
+    file_overrides::add_patch(&ai_path, mod_id, &source);
+    file_overrides::add_patch(cfg_file, mod_id, &source);
+
+    info!("Added entity type {} as {} (name string {})", name, ai_path, ids.name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(toml: &str) -> EntityTypeDefinition {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn write(ini: &Ini) -> String {
+        let mut write_options = WriteOptions::default();
+        write_options.space_around_delimiters = true;
+        write_options.blank_lines_between_sections = 1;
+        ini.pretty_writes(&write_options)
+    }
+
+    #[test]
+    fn test_generate_scenery() {
+        let moonrock = definition(
+            "name = \"Moon Rock\"\ncost = 150\nfootprint_x = 2\nicon = \"objects/moonrock/icon/moonrock/moonrock\"\n\
+             animations = { idle = \"objects/moonrock/idle\" }\ncharacteristics = { cHeight = \"2\" }\n",
+        );
+        let ids = EntityIds {
+            name: 100_001,
+            help: Some(100_002),
+            ..Default::default()
+        };
+        assert_eq!(ai_path(EntityKind::Scenery, "moonrock"), "objects/moonrock/moonrock.ai");
+        assert_eq!(
+            write(&generate_ai(EntityKind::Scenery, &moonrock, &ids)),
+            "[Characteristics/Integers]\ncNameID = 100001\ncHelpID = 100002\ncPurchaseCost = 150\ncFootprintX = 2\ncFootprintY = 1\ncHeight = 2\n\n\
+             [Icon]\nIcon = objects/moonrock/icon/moonrock/moonrock\n\n\
+             [Animations]\nidle = objects/moonrock/idle\n"
+        );
+
+        let mut cfg = Ini::new_cs();
+        cfg.read("[objects]\nrock = objects/rock/rock.ai\n".to_string()).unwrap();
+        add_to_cfg(&mut cfg, EntityKind::Scenery, "moonrock", &subtypes(EntityKind::Scenery, &moonrock));
+        assert_eq!(write(&cfg), "[objects]\nrock = objects/rock/rock.ai\nmoonrock = objects/moonrock/moonrock.ai\n");
+    }
+
+    #[test]
+    fn test_generate_animal() {
+        let mooncow = definition(
+            "name = \"Moon Cow\"\ncost = 900\nicon = \"animals/mooncow/icon/mooncow/mooncow\"\nsubtypes = [\"m\", \"f\"]\nhabitat = \"crater\"\n\
+             animations = { idle = \"animals/mooncow/{subtype}/idle\" }\n",
+        );
+        let ids = EntityIds {
+            name: 100_003,
+            habitat: Some(100_004),
+            ..Default::default()
+        };
+        assert_eq!(
+            write(&generate_ai(EntityKind::Animal, &mooncow, &ids)),
+            "[Characteristics/Integers]\ncPurchaseCost = 900\ncFootprintX = 1\ncFootprintY = 1\ncHabitat = 100004\n\n\
+             [Icon]\nIcon = animals/mooncow/icon/mooncow/mooncow\n\n\
+             [m/Characteristics/Integers]\ncNameID = 100003\n\n[m/Animations]\nidle = animals/mooncow/m/idle\n\n\
+             [f/Characteristics/Integers]\ncNameID = 100003\n\n[f/Animations]\nidle = animals/mooncow/f/idle\n"
+        );
+
+        let mut cfg = Ini::new_cs();
+        add_to_cfg(&mut cfg, EntityKind::Animal, "mooncow", &subtypes(EntityKind::Animal, &mooncow));
+        assert_eq!(write(&cfg), "[animals]\nmooncow = animals/mooncow/mooncow.ai\n\n[mooncow/subtypes]\nm\nf\n");
+
+        let default_subtypes = definition("name = \"Moon Cow\"\ncost = 900\nicon = \"icon\"\nanimations = {}\n");
+        assert_eq!(subtypes(EntityKind::Animal, &default_subtypes), vec!["m", "f", "j"]);
+    }
+
+    #[test]
+    fn test_expansion_members() {
+        let mod_def: ModDefinition = toml::from_str(
+            "[new_scenery.moonrock]\nname = \"Moon Rock\"\ncost = 150\nicon = \"icon\"\nanimations = { idle = \"idle\" }\nexpansion = \"lunar\"\n\n\
+             [new_scenery.crater]\nname = \"Crater\"\ncost = 50\nicon = \"icon\"\nanimations = { idle = \"idle\" }\n\n\
+             [new_animals.mooncow]\nname = \"Moon Cow\"\ncost = 900\nicon = \"icon\"\nanimations = { idle = \"idle\" }\nexpansion = \"lunar\"\n",
+        )
+        .unwrap();
+        let names: Vec<&str> = definitions(&mod_def).iter().map(|(_, name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["crater", "moonrock", "mooncow"]);
+        assert_eq!(expansion_members(&mod_def, "lunar"), vec!["moonrock", "mooncow"]);
+        assert!(expansion_members(&mod_def, "solar").is_empty());
+    }
+}
@@ -252,6 +252,19 @@ pub fn list_extensions_with_tag(tag: &str) -> Vec<String> {
         .collect()
 }
 
+/// Remove every extension registered by a mod (before the mod is reloaded)
+pub fn remove_mod_extensions(mod_id: &str) {
+    let mut storage = EXTENSION_STORAGE.lock().unwrap();
+    let mut by_base = EXTENSION_BY_BASE.lock().unwrap();
+    storage.retain(|_, record| {
+        if record.mod_id != mod_id {
+            return true;
+        }
+        by_base.remove(&record.base);
+        false
+    });
+}
+
 #[cfg(feature = "integration-tests")]
 pub fn clear_extensions() {
     EXTENSION_STORAGE.lock().unwrap().clear();
@@ -25,7 +25,14 @@ pub fn add_location_or_habitat(mod_id: &str, name: &String, icon_resource_id: &S
 
     let mut id_binding = LOCATIONS_HABITATS_ID_MAP.lock().unwrap();
 
-    let string_id = add_string_to_registry(name.clone());
+    let mod_map = if is_habitat { &MOD_HABITATS_MAP } else { &MOD_LOCATIONS_MAP };
+
+    // A reloaded mod keeps the string id it registered the name with
+    let existing_id = mod_map.lock().unwrap().get(mod_id).and_then(|names| names.get(name)).copied();
+    let string_id = match existing_id {
+        Some(string_id) => string_id,
+        None => add_string_to_registry(name.clone()),
+    };
 
     info!(
         "Adding location/habitat: {} {} -> {} (mod: {}, is_habitat: {})",
@@ -38,8 +45,6 @@ pub fn add_location_or_habitat(mod_id: &str, name: &String, icon_resource_id: &S
     id_binding.insert(name.clone(), string_id);
 
     // Track per-mod registration for variable substitution
-    let mod_map = if is_habitat { &MOD_HABITATS_MAP } else { &MOD_LOCATIONS_MAP };
-
     let mut mod_map_binding = mod_map.lock().unwrap();
     mod_map_binding.entry(mod_id.to_string()).or_insert_with(HashMap::new).insert(name.clone(), string_id);
 
@@ -0,0 +1,261 @@
+//! Hot reload of an in-development mod (`hot_reload_dir` in the `[dev]` section of openzt.toml)
+//!
+//! The mod is an unpacked directory (meta.toml, defs/, resources/...) that is
+//! loaded after every other mod. When a file in it changes, or on the
+//! `reload_dev_mod()` console command, the mod is unloaded - resources it added
+//! are removed and files its patches changed are restored - and then loaded
+//! again from the directory, without restarting Zoo Tycoon. Hot reload is only
+//! available with the `experimental` feature.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context};
+use tracing::{error, info};
+
+use crate::{
+    expansions, mods,
+    resource_manager::{
+        file_overrides,
+        lazyresourcemap::{add_ztfile, get_file_names, path_key, remove_resource},
+        mod_config::get_openzt_config,
+        openzt_mods::{
+            extensions, loading, patch_conflicts,
+            patches::{ShadowResources, ShadowScope},
+            scripts, shared_sources, strings,
+        },
+        ztfile::ZTFile,
+    },
+};
+
+/// How often the dev mod directory is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the dev mod changed in the resource map, so it can be undone
+#[derive(Default)]
+struct DevModState {
+    mod_id: Option<String>,
+    /// Resources that did not exist before the mod was loaded
+    added: Vec<String>,
+    /// Files as they were before the mod's patches first changed them (`None` = file absent)
+    originals: HashMap<String, Option<ZTFile>>,
+}
+
+static DEV_MOD: LazyLock<Mutex<DevModState>> = LazyLock::new(|| Mutex::new(DevModState::default()));
+
+/// Held for a whole reload so the watcher and console can't reload at the same time
+static RELOAD_LOCK: Mutex<()> = Mutex::new(());
+
+/// The configured dev mod directory, or `None` if hot reload is disabled
+fn dev_mod_dir() -> Option<PathBuf> {
+    let dir = get_openzt_config().dev.hot_reload_dir;
+    if dir.is_empty() {
+        return None;
+    }
+    Some(crate::util::get_base_path().join(dir))
+}
+
+/// Load the dev mod and start watching its directory
+///
+/// Called once every other mod has been loaded. Does nothing unless `hot_reload_dir` is set.
+pub fn init() {
+    let Some(dir) = dev_mod_dir() else {
+        return;
+    };
+
+    match load(&dir) {
+        Ok(mod_id) => info!("Loaded dev mod {} from {}", mod_id, dir.display()),
+        Err(e) => error!("Failed to load dev mod from {}: {:#}", dir.display(), e),
+    }
+
+    std::thread::spawn(move || watch(dir));
+}
+
+/// Unload the dev mod and load it again from its directory, returning its mod ID
+pub fn reload() -> anyhow::Result<String> {
+    let dir = dev_mod_dir().ok_or_else(|| anyhow!("Hot reload is not enabled, set hot_reload_dir in the [dev] section of openzt.toml"))?;
+    load(&dir)
+}
+
+/// Keep the current contents of files the dev mod's patches are about to change
+///
+/// Called before every patch file is applied; does nothing for other mods. Only
+/// the first snapshot of a file is kept, so unloading restores it as it was
+/// before the dev mod was loaded.
+pub fn snapshot_before_patches(mod_id: &str, affected_files: &HashSet<String>) -> anyhow::Result<()> {
+    let mut state = DEV_MOD.lock().unwrap();
+    if state.mod_id.as_deref() != Some(mod_id) {
+        return Ok(());
+    }
+
+    let unseen: HashSet<String> = affected_files.iter().filter(|path| !state.originals.contains_key(&path_key(path))).cloned().collect();
+    if unseen.is_empty() {
+        return Ok(());
+    }
+
+    let snapshot = ShadowResources::new(&unseen, ShadowScope::HotReload)?;
+    state.originals.extend(snapshot.files.into_iter().map(|(path, file)| (path, Some(file))));
+    state.originals.extend(snapshot.new_files.into_iter().map(|path| (path, None)));
+    Ok(())
+}
+
+/// Unload the dev mod if it is loaded, then load it from `dir`
+fn load(dir: &Path) -> anyhow::Result<String> {
+    let _reloading = RELOAD_LOCK.lock().unwrap();
+
+    let file_map = read_mod_dir(dir)?;
+    let meta_file = file_map.get("meta.toml").ok_or_else(|| anyhow!("meta.toml not found in {}", dir.display()))?;
+    let meta = toml::from_str::<mods::Meta>(&String::from_utf8_lossy(meta_file)).with_context(|| format!("Failed to parse meta.toml in {}", dir.display()))?;
+    let mod_id = meta.mod_id().to_string();
+
+    unload();
+    DEV_MOD.lock().unwrap().mod_id = Some(mod_id.clone());
+
+    let before: HashSet<String> = get_file_names().into_iter().collect();
+    let result = loading::load_open_zt_mod_from_dir(file_map, dir);
+    DEV_MOD.lock().unwrap().added = get_file_names().into_iter().filter(|name| !before.contains(name)).collect();
+
+    result.map(|_| mod_id)
+}
+
+/// Undo everything the dev mod changed, so it can be loaded again
+fn unload() {
+    let state = std::mem::take(&mut *DEV_MOD.lock().unwrap());
+    let Some(mod_id) = state.mod_id else {
+        return;
+    };
+
+    for name in &state.added {
+        remove_resource(name);
+    }
+
+    let restored = state.originals.len();
+    for (path, original) in state.originals {
+        match original {
+            Some(file) => {
+                if let Err(e) = add_ztfile(Path::new(""), path.clone(), file) {
+                    error!("Failed to restore {} while unloading dev mod {}: {}", path, mod_id, e);
+                }
+            }
+            None => {
+                remove_resource(&path);
+            }
+        }
+    }
+
+    loading::remove_mod_id(&mod_id);
+    extensions::remove_mod_extensions(&mod_id);
+    patch_conflicts::release_mod(&mod_id);
+    file_overrides::release_mod(&mod_id);
+    shared_sources::release_mod(&mod_id);
+    strings::remove_mod_strings(&mod_id);
+    scripts::remove_mod_scripts(&mod_id);
+    expansions::remove_mod_expansions(&mod_id);
+
+    info!("Unloaded dev mod {}: {} resources removed, {} files restored", mod_id, state.added.len(), restored);
+}
+
+/// Poll the dev mod directory, reloading the mod once a change has settled
+fn watch(dir: PathBuf) {
+    info!("Watching {} for changes", dir.display());
+
+    let mut last_change = latest_change(&dir);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let mut change = latest_change(&dir);
+        if change == last_change {
+            continue;
+        }
+
+        // Wait for editors and build scripts to finish writing
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let settled = latest_change(&dir);
+            if settled == change {
+                break;
+            }
+            change = settled;
+        }
+        last_change = change;
+
+        info!("Change detected in {}, reloading dev mod", dir.display());
+        match load(&dir) {
+            Ok(mod_id) => info!("Reloaded dev mod {}", mod_id),
+            Err(e) => error!("Failed to reload dev mod from {}: {:#}", dir.display(), e),
+        }
+    }
+}
+
+/// Latest modification time of any file in the directory, with the number of files
+///
+/// The file count catches deletions, which leave no newer modification time behind.
+fn latest_change(dir: &Path) -> (Option<SystemTime>, usize) {
+    let mut files = Vec::new();
+    if collect_files(dir, &mut files).is_err() {
+        return (None, 0);
+    }
+
+    let latest = files.iter().filter_map(|path| path.metadata().and_then(|metadata| metadata.modified()).ok()).max();
+    (latest, files.len())
+}
+
+/// Read every file in an unpacked mod directory, keyed by its '/'-separated path within the directory
+pub fn read_mod_dir(dir: &Path) -> anyhow::Result<HashMap<String, Arc<[u8]>>> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files).with_context(|| format!("Failed to read mod directory {}", dir.display()))?;
+
+    files
+        .into_iter()
+        .map(|path| {
+            let relative = path.strip_prefix(dir)?;
+            let name = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let data = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            Ok((name, Arc::from(data)))
+        })
+        .collect()
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_mod_dir_and_detect_changes() {
+        let dir = std::env::temp_dir().join(format!("openzt-hot-reload-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("defs")).unwrap();
+        std::fs::write(dir.join("meta.toml"), "name = \"Dev Mod\"\n").unwrap();
+        std::fs::write(dir.join("defs").join("patches.toml"), "[patches]\n").unwrap();
+
+        let file_map = read_mod_dir(&dir).unwrap();
+        let mut names: Vec<&String> = file_map.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["defs/patches.toml", "meta.toml"]);
+        assert_eq!(file_map["meta.toml"].as_ref(), b"name = \"Dev Mod\"\n");
+
+        // Deleting a file is a change even though no file got newer
+        let before = latest_change(&dir);
+        std::fs::remove_file(dir.join("defs").join("patches.toml")).unwrap();
+        assert_ne!(latest_change(&dir), before);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(latest_change(&dir), (None, 0));
+    }
+}
@@ -6,6 +6,7 @@
 use std::{collections::HashMap, str::FromStr, sync::Mutex};
 
 use openzt_configparser::ini::Ini;
+use serde::{Deserialize, Serialize};
 use std::sync::LazyLock;
 use tracing::trace;
 
@@ -13,7 +14,7 @@ use tracing::trace;
 use crate::resource_manager::legacy_loading::LegacyCfgType;
 
 /// Entity types that correspond to Zoo Tycoon .cfg file patterns
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum LegacyEntityType {
     Animal,
     Building,
@@ -126,7 +127,7 @@ impl FromStr for LegacyEntityType {
 }
 
 /// Attributes for a specific subtype
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubtypeAttributes {
     /// The subtype identifier (e.g., "m", "f", "man", etc.)
     pub subtype: String,
@@ -138,7 +139,7 @@ pub struct SubtypeAttributes {
 ///
 /// For entities with subtypes, stores attributes per subtype.
 /// For entities without subtypes, uses a single default entry.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LegacyEntityAttributes {
     /// The entity name (e.g., "elephant" from "animals/elephant.ai")
     pub entity_name: String,
@@ -4,19 +4,22 @@ use std::{
     fmt,
     path::{Path, PathBuf},
     str,
-    sync::Mutex,
+    sync::{Arc, Mutex},
 };
 
 use anyhow::{anyhow, Context};
 use openzt_configparser::ini::{Ini, WriteOptions};
+use openzt_mod::schema::{self, Diagnostic, Severity};
 use std::sync::LazyLock;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
     animation::Animation,
     mods,
     resource_manager::{
-        lazyresourcemap::add_ztfile,
+        lazyresourcemap::{add_ztfile, path_key},
+        load_profile::{self, LoadPhase},
+        load_report::{self, LoadStage},
         openzt_mods::habitats_locations::add_location_or_habitat,
         ztd::ZtdArchive,
         ztfile::{ZTFile, ZTFileType},
@@ -32,6 +35,13 @@ pub fn add_new_mod_id(mod_id: &str) -> bool {
     binding.insert(mod_id.to_string())
 }
 
+/// Removes a mod id from the set so the mod can be loaded again, returns false if it wasn't loaded
+pub fn remove_mod_id(mod_id: &str) -> bool {
+    MOD_METAS.lock().unwrap().remove(mod_id);
+    let mut binding = MOD_ID_SET.lock().unwrap();
+    binding.remove(mod_id)
+}
+
 pub fn get_num_mod_ids() -> usize {
     let binding = MOD_ID_SET.lock().unwrap();
     binding.len()
@@ -42,19 +52,85 @@ pub fn get_mod_ids() -> Vec<String> {
     binding.iter().cloned().collect()
 }
 
+/// Mods enabled for the current load, including those that haven't been loaded yet
+static ENABLED_MOD_IDS: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Record the mods enabled for this load, once the load order has been resolved
+pub fn set_enabled_mod_ids(enabled_order: &[String]) {
+    *ENABLED_MOD_IDS.lock().unwrap() = enabled_order.iter().cloned().collect();
+}
+
+/// True if the mod is loaded or will be loaded later in the current load order
+pub fn is_mod_loaded(mod_id: &str) -> bool {
+    ENABLED_MOD_IDS.lock().unwrap().contains(mod_id) || MOD_ID_SET.lock().unwrap().contains(mod_id)
+}
+
+/// meta.toml of each loaded OpenZT mod, for the mod queries in the console
+static MOD_METAS: LazyLock<Mutex<HashMap<String, mods::Meta>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Record the meta.toml of a loaded mod
+fn add_mod_meta(meta: &mods::Meta) {
+    MOD_METAS.lock().unwrap().insert(meta.mod_id().clone(), meta.clone());
+}
+
+/// meta.toml of a loaded mod
+pub fn get_mod_meta(mod_id: &str) -> Option<mods::Meta> {
+    MOD_METAS.lock().unwrap().get(mod_id).cloned()
+}
+
+/// meta.toml of the loaded mods with a tag (ignoring case), or of all loaded mods, sorted by mod ID
+pub fn get_mod_metas(tag: Option<&str>) -> Vec<mods::Meta> {
+    let mut metas: Vec<mods::Meta> = MOD_METAS
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|meta| tag.is_none_or(|tag| meta.has_tag(tag)))
+        .cloned()
+        .collect();
+    metas.sort_by(|a, b| a.mod_id().cmp(b.mod_id()));
+    metas
+}
+
 /// Clear the MOD_ID_SET (for integration tests)
 #[cfg(feature = "integration-tests")]
 pub fn clear_mod_ids_for_tests() {
     MOD_ID_SET.lock().unwrap().clear();
+    ENABLED_MOD_IDS.lock().unwrap().clear();
+    MOD_METAS.lock().unwrap().clear();
+}
+
+/// The loaded mod IDs and their meta.toml at one point, to put back with `restore_mod_ids_for_tests`
+#[cfg(feature = "integration-tests")]
+#[derive(Debug, Clone)]
+pub struct ModIdSnapshot {
+    mod_ids: HashSet<String>,
+    enabled_mod_ids: HashSet<String>,
+    metas: HashMap<String, mods::Meta>,
+}
+
+#[cfg(feature = "integration-tests")]
+pub fn snapshot_mod_ids_for_tests() -> ModIdSnapshot {
+    ModIdSnapshot {
+        mod_ids: MOD_ID_SET.lock().unwrap().clone(),
+        enabled_mod_ids: ENABLED_MOD_IDS.lock().unwrap().clone(),
+        metas: MOD_METAS.lock().unwrap().clone(),
+    }
+}
+
+#[cfg(feature = "integration-tests")]
+pub fn restore_mod_ids_for_tests(snapshot: &ModIdSnapshot) {
+    *MOD_ID_SET.lock().unwrap() = snapshot.mod_ids.clone();
+    *ENABLED_MOD_IDS.lock().unwrap() = snapshot.enabled_mod_ids.clone();
+    *MOD_METAS.lock().unwrap() = snapshot.metas.clone();
 }
 
 /// Result of discovering mods and legacy archives
 ///
 /// Contains both OpenZT mods (with meta.toml) and pure legacy archives (without meta.toml)
 /// Only pure legacy archives found in /mods/ directory are included
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DiscoveryResult {
-    /// OpenZT mods: mod_id -> (archive_name, Meta)
+    /// OpenZT mods: mod_id -> (archive or mod directory name, Meta)
     pub openzt_mods: HashMap<String, (String, mods::Meta)>,
     /// Pure legacy archives in /mods/: (archive_name, path)
     pub pure_legacy_in_mods: Vec<(String, PathBuf)>,
@@ -69,7 +145,8 @@ impl DiscoveryResult {
     }
 }
 
-/// Discover all OpenZT mods and pure legacy archives from .ztd archives without loading them
+/// Discover all OpenZT mods and pure legacy archives from .ztd archives, and unpacked
+/// mod directories in /mods/, without loading them
 ///
 /// Returns a DiscoveryResult containing:
 /// - OpenZT mods (with meta.toml) from all resource paths
@@ -77,8 +154,14 @@ impl DiscoveryResult {
 ///
 /// This is used for dependency resolution and load order generation before actual mod loading
 pub fn discover_mods(paths: &[String]) -> DiscoveryResult {
+    discover_mods_in(paths, Path::new("./mods"))
+}
+
+/// Discover mods in `paths` like [`discover_mods`], with `mods_path` as the /mods/ directory
+///
+/// Used by launchers, whose working directory isn't the game's.
+pub fn discover_mods_in(paths: &[String], mods_path: &Path) -> DiscoveryResult {
     let mut result = DiscoveryResult::new();
-    let mods_path = PathBuf::from("./mods");
 
     // Iterate through resource paths to find .ztd files
     for path_str in paths.iter().rev() {
@@ -93,7 +176,7 @@ pub fn discover_mods(paths: &[String]) -> DiscoveryResult {
             || path
                 .canonicalize()
                 .ok()
-                .map(|p| p == mods_path.canonicalize().unwrap_or(mods_path.clone()))
+                .map(|p| p == mods_path.canonicalize().unwrap_or(mods_path.to_path_buf()))
                 .unwrap_or(false);
 
         // Read directory entries
@@ -104,15 +187,25 @@ pub fn discover_mods(paths: &[String]) -> DiscoveryResult {
         for entry in entries.flatten() {
             let file_path = entry.path();
 
-            // Only process .ztd files (case-insensitive)
-            if !file_path.extension().is_some_and(|s| s.eq_ignore_ascii_case("ztd")) {
+            // Unpacked mod directories are only loaded from /mods/
+            let is_mod_dir = is_mods_dir && file_path.is_dir();
+
+            // Otherwise only process .ztd files (case-insensitive)
+            if !is_mod_dir && !file_path.extension().is_some_and(|s| s.eq_ignore_ascii_case("ztd")) {
                 continue;
             }
 
             let archive_name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
 
-            // Try to read meta.toml from the archive
-            match read_meta_from_archive(&file_path) {
+            // Try to read meta.toml from the archive or directory
+            let meta = load_profile::time(LoadPhase::Discovery, &archive_name, || {
+                if is_mod_dir {
+                    read_meta_from_dir(&file_path)
+                } else {
+                    read_meta_from_archive(&file_path)
+                }
+            });
+            match meta {
                 Ok(Some(meta)) => {
                     let mod_id = meta.mod_id().to_string();
 
@@ -130,6 +223,9 @@ pub fn discover_mods(paths: &[String]) -> DiscoveryResult {
                         (archive_name, meta)
                     });
                 }
+                Ok(None) if is_mod_dir => {
+                    // Directory without meta.toml, not a mod
+                }
                 Ok(None) => {
                     // Legacy mod (no meta.toml)
                     // Only add to pure_legacy_in_mods if in /mods/ directory
@@ -145,6 +241,7 @@ pub fn discover_mods(paths: &[String]) -> DiscoveryResult {
                 }
                 Err(e) => {
                     error!("Failed to read meta from  {:?}: Failed to parse meta.toml\n{:#}", file_path, e);
+                    load_report::record_error(&archive_name, LoadStage::Meta, format!("Failed to parse meta.toml\n{:#}", e));
                 }
             }
         }
@@ -183,6 +280,20 @@ fn read_meta_from_archive(archive_path: &Path) -> anyhow::Result<Option<mods::Me
     Ok(Some(meta))
 }
 
+/// Read and parse meta.toml from an unpacked mod directory
+///
+/// Returns None if the directory has no meta.toml
+fn read_meta_from_dir(dir: &Path) -> anyhow::Result<Option<mods::Meta>> {
+    let meta_path = dir.join("meta.toml");
+    if !meta_path.is_file() {
+        return Ok(None);
+    }
+
+    let meta_str = std::fs::read_to_string(&meta_path).with_context(|| format!("Failed to read {:?}", meta_path))?;
+    let meta = toml::from_str::<mods::Meta>(&meta_str).with_context(|| format!("Failed to parse meta.toml from {:?}", dir))?;
+    Ok(Some(meta))
+}
+
 // === Load Order Tracking (for integration tests) ===
 #[cfg(feature = "integration-tests")]
 #[derive(Debug, Clone)]
@@ -217,7 +328,11 @@ pub enum DefFileCategory {
 /// Classify a definition file based on its contents
 fn classify_def_file(mod_def: &mods::ModDefinition) -> DefFileCategory {
     let has_patches = mod_def.patches().as_ref().map(|p| !p.is_empty()).unwrap_or(false);
-    let has_other = mod_def.habitats().is_some() || mod_def.locations().is_some();
+    let has_other = mod_def.habitats().is_some()
+        || mod_def.locations().is_some()
+        || mod_def.sprites().is_some()
+        || mod_def.new_scenery().is_some()
+        || mod_def.new_animals().is_some();
 
     match (has_patches, has_other) {
         (false, _) => DefFileCategory::NoPatch,
@@ -237,13 +352,34 @@ pub fn openzt_full_resource_id_path(base_resource_id: &str, file_type: ZTFileTyp
     format!("{}.{}", base_resource_id, file_type)
 }
 
+/// Refuse a mod that needs a newer OpenZT than `openzt_version`
+///
+/// Checked before the mod's defs are parsed, as newer patch operations would otherwise fail with parse errors.
+fn check_openzt_version(meta: &mods::Meta, openzt_version: &mods::Version) -> anyhow::Result<()> {
+    match meta.min_openzt_version() {
+        Some(min_version) if openzt_version < min_version => Err(anyhow!(
+            "Mod {} ({}) needs OpenZT {}+, you have {}",
+            meta.name(),
+            meta.mod_id(),
+            min_version,
+            openzt_version
+        )),
+        _ => Ok(()),
+    }
+}
+
 /// Load an OpenZT mod from a file map (shared implementation)
-fn load_open_zt_mod_internal(file_map: HashMap<String, Box<[u8]>>, archive_name: &str, _resource: &Path) -> anyhow::Result<mods::ZtdType> {
+fn load_open_zt_mod_internal(file_map: HashMap<String, Arc<[u8]>>, archive_name: &str, resource: &Path) -> anyhow::Result<mods::ZtdType> {
     let meta_file = file_map.get("meta.toml").ok_or_else(|| anyhow!("meta.toml not found in {}", archive_name))?;
 
     let meta_str = String::from_utf8_lossy(meta_file.as_ref());
+    log_diagnostics(archive_name, &schema::check_meta("meta.toml", &meta_str));
     let meta = toml::from_str::<mods::Meta>(&meta_str).with_context(|| format!("Failed to parse meta.toml in {}", archive_name))?;
 
+    if let Ok(openzt_version) = env!("CARGO_PKG_VERSION").parse::<mods::Version>() {
+        check_openzt_version(&meta, &openzt_version)?;
+    }
+
     if meta.ztd_type() == &mods::ZtdType::Legacy {
         return Ok(mods::ZtdType::Legacy);
     }
@@ -253,9 +389,16 @@ fn load_open_zt_mod_internal(file_map: HashMap<String, Box<[u8]>>, archive_name:
     if !add_new_mod_id(&mod_id) {
         return Err(anyhow!("Mod already loaded: {}", mod_id));
     }
+    add_mod_meta(&meta);
 
     // Register the mod_id to ZTD mapping for ztd_loaded condition
     crate::resource_manager::openzt_mods::ztd_registry::register_mod_ztd(&mod_id, archive_name);
+    crate::resource_manager::save_mods::register_mod(&mod_id, &meta.version().to_string(), archive_name);
+    crate::resource_manager::openzt_mods::shared_sources::register_mod(&meta, resource);
+    // Registered before the defs and patches below, which read the values. Mod settings are experimental
+    if cfg!(feature = "experimental") {
+        crate::resource_manager::openzt_mods::mod_settings::register_mod(&meta);
+    }
 
     // Create span for the entire loading process
     let mod_name = meta.name().to_string();
@@ -270,7 +413,7 @@ fn load_open_zt_mod_internal(file_map: HashMap<String, Box<[u8]>>, archive_name:
     info!("Loading OpenZT mod: {} {}", meta.name(), meta.mod_id());
 
     // Collect all defs/ files and sort alphabetically (case-insensitive)
-    let mut def_files: Vec<String> = file_map.keys().filter(|name| name.starts_with("defs/")).cloned().collect();
+    let mut def_files: Vec<String> = file_map.keys().filter(|name| path_key(name).starts_with("defs/")).cloned().collect();
 
     // Sort case-insensitively, then by original case for stability
     def_files.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b)));
@@ -331,37 +474,57 @@ fn load_open_zt_mod_internal(file_map: HashMap<String, Box<[u8]>>, archive_name:
         // Load extensions
         load_extensions(&mod_id, &file_info.mod_def)?;
 
+        // Load string overrides
+        load_strings(&mod_id, &file_info.mod_def)?;
+
+        // Register expansion packs
+        load_expansions(&mod_id, &file_info.mod_def)?;
+
+        // Generate new entity types, after the habitats and locations they may live in
+        load_entity_types(&mod_id, &file_info.mod_def)?;
+
+        // Convert sprites before patches, which may change the animations they add
+        load_sprites(&mod_id, &file_info.mod_def, &file_map)?;
+
         // Then apply patches if present
         if let Some(patches) = file_info.mod_def.patches() {
             let patch_meta = file_info.mod_def.patch_meta().as_ref().cloned().unwrap_or_default();
             info!("Found {} patches in {}", patches.len(), file_info.filename);
-            if let Err(e) = super::patches::apply_patches(&patch_meta, patches, &file_map, &mod_id) {
+            let result = load_profile::time(LoadPhase::Patches, &mod_id, || {
+                super::patches::apply_patches(&patch_meta, patches, &file_map, &mod_id)
+            });
+            if let Err(e) = result {
                 error!("Failed to apply patches from {}: {}", file_info.filename, e);
                 return Err(e);
             }
         }
     }
 
+    // Scripts run last, so they see everything the mod's definitions added
+    super::scripts::load_mod_scripts(&mod_id, &file_map);
+
     Ok(meta.ztd_type().clone())
 }
 
-pub fn load_open_zt_mod(archive: &mut ZtdArchive, resource: &Path) -> anyhow::Result<mods::ZtdType> {
+/// Read every file of an archive that has a meta.toml, or `None` for legacy archives without one
+///
+/// Only reads the archive, so it can run on a loader thread ahead of [`load_open_zt_mod_files`].
+pub fn read_open_zt_mod_files(archive: &mut ZtdArchive) -> anyhow::Result<Option<HashMap<String, Arc<[u8]>>>> {
     let archive_name = archive.name().to_string();
 
     // Early exit: check if meta.toml exists in the archive
     let Ok(meta_file) = archive.by_name("meta.toml") else {
-        return Ok(mods::ZtdType::Legacy);
+        return Ok(None);
     };
 
     // Build file map from archive
-    let mut file_map: HashMap<String, Box<[u8]>> = HashMap::new();
+    let mut file_map: HashMap<String, Arc<[u8]>> = HashMap::new();
 
     // Add meta.toml to file map (we already read it for the early check)
     let meta_bytes = String::try_from(meta_file)
         .with_context(|| format!("error reading meta.toml from {}", archive_name))?
-        .into_bytes()
-        .into_boxed_slice();
-    file_map.insert("meta.toml".to_string(), meta_bytes);
+        .into_bytes();
+    file_map.insert("meta.toml".to_string(), meta_bytes.into());
 
     // Read remaining files from archive
     for i in 0..archive.len() {
@@ -380,22 +543,43 @@ pub fn load_open_zt_mod(archive: &mut ZtdArchive, resource: &Path) -> anyhow::Re
             continue;
         }
 
-        let mut file_buffer = vec![0; file.size() as usize].into_boxed_slice();
-        file.read_exact(&mut file_buffer).with_context(|| format!("Error reading file: {}", file_name))?;
+        let file_buffer = file.read_all().with_context(|| format!("Error reading {} from {}", file_name, archive_name))?;
 
-        file_map.insert(file_name, file_buffer);
+        file_map.insert(file_name, file_buffer.into());
     }
 
-    // Call shared implementation
-    load_open_zt_mod_internal(file_map, &archive_name, resource)
+    Ok(Some(file_map))
+}
+
+/// Load an OpenZT mod from the files read by [`read_open_zt_mod_files`]
+pub fn load_open_zt_mod_files(file_map: HashMap<String, Arc<[u8]>>, archive_name: &str, resource: &Path) -> anyhow::Result<mods::ZtdType> {
+    load_open_zt_mod_internal(file_map, archive_name, resource)
+}
+
+/// Load an OpenZT mod from the file map of an unpacked mod directory
+pub fn load_open_zt_mod_from_dir(file_map: HashMap<String, Arc<[u8]>>, dir: &Path) -> anyhow::Result<mods::ZtdType> {
+    let dir_name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| dir.display().to_string());
+    load_open_zt_mod_internal(file_map, &dir_name, dir)
 }
 
 /// Load an OpenZT mod from an in-memory file map (for testing)
 #[cfg(feature = "integration-tests")]
-pub fn load_open_zt_mod_from_memory(file_map: HashMap<String, Box<[u8]>>, mod_name: &str, resource: &Path) -> anyhow::Result<mods::ZtdType> {
+pub fn load_open_zt_mod_from_memory(file_map: HashMap<String, Arc<[u8]>>, mod_name: &str, resource: &Path) -> anyhow::Result<mods::ZtdType> {
     load_open_zt_mod_internal(file_map, mod_name, resource)
 }
 
+/// Look up a file in a mod's file map, ignoring case and separators with `case_insensitive_paths`
+pub fn get_mod_file<'a>(file_map: &'a HashMap<String, Arc<[u8]>>, path: &str) -> Option<&'a [u8]> {
+    if let Some(data) = file_map.get(path) {
+        return Some(data);
+    }
+    let key = path_key(path);
+    file_map.iter().find(|(name, _)| path_key(name) == key).map(|(_, data)| &**data)
+}
+
 pub enum ResourceType {
     Location,
     Habitat,
@@ -411,7 +595,7 @@ impl fmt::Display for ResourceType {
 }
 
 /// Parse a definition file from TOML without side effects
-pub fn parse_def(mod_id: &str, file_name: &str, file_map: &HashMap<String, Box<[u8]>>) -> anyhow::Result<mods::ModDefinition> {
+pub fn parse_def(mod_id: &str, file_name: &str, file_map: &HashMap<String, Arc<[u8]>>) -> anyhow::Result<mods::ModDefinition> {
     let span = tracing::error_span!("parse_def", mod_id = %mod_id, file_name = %file_name);
     let _guard = span.enter();
 
@@ -422,6 +606,7 @@ pub fn parse_def(mod_id: &str, file_name: &str, file_map: &HashMap<String, Box<[
         .with_context(|| format!("Error finding file {} in resource map for mod {}", file_name, mod_id))?;
 
     let intermediate_string = crate::encoding_utils::decode_game_text(file);
+    log_diagnostics(mod_id, &schema::check_def(file_name, &intermediate_string));
 
     let defs = toml::from_str::<mods::ModDefinition>(&intermediate_string).with_context(|| format!("Error parsing defs from OpenZT mod: {}", file_name))?;
 
@@ -430,8 +615,30 @@ pub fn parse_def(mod_id: &str, file_name: &str, file_map: &HashMap<String, Box<[
     Ok(defs)
 }
 
+/// Log the problems the schema check found in a mod file, and add them to the load report
+///
+/// Runs before the file is deserialized, which only reports the first problem
+/// and without a line or a hint at what was meant.
+fn log_diagnostics(mod_name: &str, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        match diagnostic.severity {
+            Severity::Error => error!("{}: {}", mod_name, diagnostic),
+            Severity::Warning => warn!("{}: {}", mod_name, diagnostic),
+        }
+        let stage = if diagnostic.file == "meta.toml" { LoadStage::Meta } else { LoadStage::Definition };
+        let mut message = match diagnostic.line {
+            Some(line) => format!("{}:{}: {}", diagnostic.file, line, diagnostic.message),
+            None => format!("{}: {}", diagnostic.file, diagnostic.message),
+        };
+        if let Some(suggestion) = &diagnostic.suggestion {
+            message = format!("{} ({})", message, suggestion);
+        }
+        load_report::record(mod_name, stage, diagnostic.severity, message);
+    }
+}
+
 /// Load habitats and locations from a ModDefinition into the resource system
-pub fn load_habitats_locations(mod_id: &str, mod_def: &mods::ModDefinition, file_map: &HashMap<String, Box<[u8]>>) -> anyhow::Result<()> {
+pub fn load_habitats_locations(mod_id: &str, mod_def: &mods::ModDefinition, file_map: &HashMap<String, Arc<[u8]>>) -> anyhow::Result<()> {
     // Habitats
     if let Some(habitats) = mod_def.habitats() {
         for (habitat_name, habitat_def) in habitats.iter() {
@@ -483,8 +690,91 @@ pub fn load_extensions(mod_id: &str, mod_def: &mods::ModDefinition) -> anyhow::R
     Ok(())
 }
 
+/// Load game string overrides from a ModDefinition
+pub fn load_strings(mod_id: &str, mod_def: &mods::ModDefinition) -> anyhow::Result<()> {
+    use crate::resource_manager::openzt_mods::strings;
+
+    match mod_def.strings() {
+        Some(mod_strings) if !mod_strings.is_empty() => strings::add_mod_strings(mod_id, mod_strings),
+        _ => Ok(()),
+    }
+}
+
+/// Register the expansion packs declared in a ModDefinition
+pub fn load_expansions(mod_id: &str, mod_def: &mods::ModDefinition) -> anyhow::Result<()> {
+    let Some(expansions) = mod_def.expansions() else {
+        return Ok(());
+    };
+
+    let mut expansions: Vec<_> = expansions.iter().collect();
+    expansions.sort_by_key(|(key, _)| key.as_str());
+    for (key, expansion) in expansions {
+        let mut members = expansion.members().clone();
+        let new_entities = if cfg!(feature = "experimental") {
+            super::entity_types::expansion_members(mod_def, key)
+        } else {
+            Vec::new()
+        };
+        if !new_entities.is_empty() {
+            // No members means the mod's archive, which doesn't hold the generated entities
+            if members.is_empty()
+                && let Some(archive) = super::ztd_registry::get_mod_ztd(mod_id)
+            {
+                members.push(archive);
+            }
+            members.extend(new_entities);
+        }
+        crate::expansions::add_mod_expansion(mod_id, expansion.name(), &members).with_context(|| format!("Failed to register expansion '{}'", key))?;
+    }
+
+    Ok(())
+}
+
+/// Generate the files of the new entity types in a ModDefinition, scenery first, in name order
+///
+/// New entity types are experimental, without the feature they're skipped with a warning.
+pub fn load_entity_types(mod_id: &str, mod_def: &mods::ModDefinition) -> anyhow::Result<()> {
+    let definitions = super::entity_types::definitions(mod_def);
+    if !cfg!(feature = "experimental") {
+        if !definitions.is_empty() {
+            warn!("{}: skipping {} new entity types, they need the experimental feature", mod_id, definitions.len());
+        }
+        return Ok(());
+    }
+
+    for (kind, name, definition) in definitions {
+        if let Some(expansion) = definition.expansion()
+            && !mod_def.expansions().as_ref().is_some_and(|expansions| expansions.contains_key(expansion))
+        {
+            return Err(anyhow!(
+                "Entity type '{}' joins expansion '{}', which isn't declared in the same file",
+                name,
+                expansion
+            ));
+        }
+        super::entity_types::load_entity_type(mod_id, kind, name, definition).with_context(|| format!("Failed to add entity type '{}'", name))?;
+    }
+
+    Ok(())
+}
+
+/// Convert the sprites in a ModDefinition to animations, in name order
+pub fn load_sprites(mod_id: &str, mod_def: &mods::ModDefinition, file_map: &HashMap<String, Arc<[u8]>>) -> anyhow::Result<()> {
+    let Some(sprites) = mod_def.sprites() else {
+        return Ok(());
+    };
+
+    let mut sprites: Vec<_> = sprites.iter().collect();
+    sprites.sort_by_key(|(name, _)| name.as_str());
+    for (name, sprite) in sprites {
+        super::sprites::load_sprite(mod_id, name, sprite, file_map).with_context(|| format!("Failed to load sprite '{}'", name))?;
+    }
+
+    Ok(())
+}
+
 /// Legacy function that combines parsing and loading - kept for backwards compatibility
-pub fn load_def(mod_id: &str, file_name: &str, file_map: &HashMap<String, Box<[u8]>>) -> anyhow::Result<mods::ModDefinition> {
+pub fn load_def(mod_id: &str, file_name: &str, file_map: &HashMap<String, Arc<[u8]>>) -> anyhow::Result<mods::ModDefinition> {
     let defs = parse_def(mod_id, file_name, file_map)?;
     load_habitats_locations(mod_id, &defs, file_map)?;
     Ok(defs)
@@ -493,11 +783,11 @@ pub fn load_def(mod_id: &str, file_name: &str, file_map: &HashMap<String, Box<[u
 fn load_icon_definition(
     base_resource_id: &str,
     icon_definition: &mods::IconDefinition,
-    file_map: &HashMap<String, Box<[u8]>>,
+    file_map: &HashMap<String, Arc<[u8]>>,
     mod_id: &str,
     base_config: String,
 ) -> anyhow::Result<()> {
-    let icon_file = file_map.get(icon_definition.icon_path()).with_context(|| {
+    let icon_file = get_mod_file(file_map, icon_definition.icon_path()).with_context(|| {
         format!(
             "Error loading openzt mod {}, cannot find file {} for icon_def {}",
             mod_id,
@@ -506,7 +796,7 @@ fn load_icon_definition(
         )
     })?;
 
-    let icon_file_palette = file_map.get(icon_definition.icon_palette_path()).with_context(|| {
+    let icon_file_palette = get_mod_file(file_map, icon_definition.icon_palette_path()).with_context(|| {
         format!(
             "Error loading openzt mod {}, cannot find file {} for icon_def {}",
             mod_id,
@@ -520,7 +810,7 @@ fn load_icon_definition(
         .file_name(palette_file_name.clone())
         .file_size(icon_file_palette.len() as u32)
         .type_(ZTFileType::Palette)
-        .raw_data(icon_file_palette.clone())
+        .raw_data(Box::from(icon_file_palette))
         .build();
     add_ztfile(Path::new("zip::./openzt.ztd"), palette_file_name.clone(), palette_ztfile)?;
 
@@ -581,7 +871,7 @@ fn load_icon_definition(
         .file_name(palette_file_name.clone())
         .file_size(icon_file_palette.len() as u32)
         .type_(ZTFileType::Palette)
-        .raw_data(icon_file_palette.clone())
+        .raw_data(Box::from(icon_file_palette))
         .build();
     add_ztfile(Path::new("zip::./openzt.ztd"), palette_file_name, palette_ztfile)?;
 
@@ -602,10 +892,43 @@ mod tests {
     fn create_test_patch() -> mods::Patch {
         mods::Patch::Delete(mods::DeletePatch {
             target: "test.ai".to_string(),
+            section: None,
+            keys: Vec::new(),
+            sections: Vec::new(),
             condition: None,
         })
     }
 
+    #[test]
+    fn test_check_openzt_version() {
+        let meta_toml = "name = \"Moon\"\ndescription = \"\"\nauthors = []\nmod_id = \"finn.moon\"\nversion = \"1.0.0\"\nmin_openzt_version = \"0.6.0\"\n";
+        let meta: mods::Meta = toml::from_str(meta_toml).unwrap();
+        let version = |text: &str| text.parse::<mods::Version>().unwrap();
+
+        assert!(check_openzt_version(&meta, &version("0.6.0")).is_ok());
+        assert!(check_openzt_version(&meta, &version("1.0.0")).is_ok());
+        assert_eq!(
+            check_openzt_version(&meta, &version("0.5.3")).unwrap_err().to_string(),
+            "Mod Moon (finn.moon) needs OpenZT 0.6.0+, you have 0.5.3"
+        );
+    }
+
+    #[test]
+    fn test_read_meta_from_dir() {
+        let dir = std::env::temp_dir().join(format!("openzt-mod-dir-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("defs")).unwrap();
+        assert!(read_meta_from_dir(&dir).unwrap().is_none());
+
+        let meta_toml = "name = \"Moon\"\ndescription = \"\"\nauthors = []\nmod_id = \"finn.moon\"\nversion = \"1.0.0\"\n";
+        std::fs::write(dir.join("meta.toml"), meta_toml).unwrap();
+        assert_eq!(read_meta_from_dir(&dir).unwrap().unwrap().mod_id(), "finn.moon");
+
+        std::fs::write(dir.join("meta.toml"), "name = ").unwrap();
+        assert!(read_meta_from_dir(&dir).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_classify_nopatch_file() {
         let mut habitats = HashMap::new();
@@ -0,0 +1,283 @@
+//! Settings declared by OpenZT mods
+//!
+//! A mod declares settings in meta.toml under `[settings.<key>]`, each with a `type`
+//! (integer, float, boolean or string), a `default` and a `description`. Players choose
+//! their own values in the `[mod_settings."<mod_id>"]` section of openzt.toml or with
+//! `set_mod_setting(mod_id, key, value)` in the console, so a mod no longer needs "easy"
+//! and "hard" variants to change one number.
+//!
+//! Patches read a setting with `{settings.<key>}`, or another mod's with
+//! `{<mod_id>.settings.<key>}`; booleans are written as 1 or 0 like the game's own flags.
+//! Scripts read it with `openzt.setting(key)`. Values are read from openzt.toml when the
+//! mod loads, so patches only see a changed value the next time Zoo Tycoon starts, while
+//! scripts see it straight away. Mod settings are only available with the `experimental`
+//! feature.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::anyhow;
+use indexmap::IndexMap;
+use toml::Value;
+use tracing::{info, warn};
+
+use crate::{
+    mods::{Meta, ModSetting},
+    resource_manager::{
+        load_report::{self, LoadStage},
+        mod_config::{get_openzt_config, save_openzt_config},
+    },
+};
+
+/// A declared setting and the value in use
+#[derive(Debug, Clone)]
+struct Setting {
+    definition: ModSetting,
+    value: Value,
+}
+
+impl Setting {
+    /// The declared default, `None` if it isn't a value of the setting's type
+    fn default_value(&self) -> Option<Value> {
+        self.definition.setting_type().coerce(self.definition.default())
+    }
+}
+
+/// Settings of the loaded mods that declare any, by mod ID then key
+static SETTINGS: LazyLock<Mutex<HashMap<String, IndexMap<String, Setting>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The settings `definitions` declares with the values in `chosen`, and a warning for each value that can't be used
+fn resolve(definitions: &IndexMap<String, ModSetting>, chosen: Option<&IndexMap<String, Value>>) -> (IndexMap<String, Setting>, Vec<String>) {
+    let mut settings = IndexMap::new();
+    let mut warnings = Vec::new();
+    for (key, definition) in definitions {
+        let setting_type = definition.setting_type();
+        let Some(default) = setting_type.coerce(definition.default()) else {
+            warnings.push(format!(
+                "Setting '{}' is skipped, its default {} isn't a valid {}",
+                key,
+                definition.default(),
+                setting_type.name()
+            ));
+            continue;
+        };
+        let value = match chosen.and_then(|chosen| chosen.get(key)) {
+            Some(value) => setting_type.coerce(value).unwrap_or_else(|| {
+                warnings.push(format!(
+                    "openzt.toml sets '{}' to {}, which isn't a valid {}, using the default {}",
+                    key,
+                    value,
+                    setting_type.name(),
+                    default
+                ));
+                default
+            }),
+            None => default,
+        };
+        settings.insert(
+            key.clone(),
+            Setting {
+                definition: definition.clone(),
+                value,
+            },
+        );
+    }
+    for key in chosen.into_iter().flat_map(IndexMap::keys).filter(|key| !definitions.contains_key(*key)) {
+        warnings.push(format!("openzt.toml sets '{}', which isn't a setting of this mod", key));
+    }
+    (settings, warnings)
+}
+
+/// Record the settings of a mod that is being loaded, with the values chosen in openzt.toml
+pub fn register_mod(meta: &Meta) {
+    let mod_id = meta.mod_id();
+    let config = get_openzt_config();
+    let (settings, warnings) = resolve(meta.settings(), config.mod_settings.get(mod_id));
+    for warning in warnings {
+        warn!("{}: {}", mod_id, warning);
+        load_report::record_warning(mod_id, LoadStage::Meta, warning);
+    }
+
+    let mut all = SETTINGS.lock().unwrap();
+    if settings.is_empty() {
+        all.remove(mod_id);
+    } else {
+        all.insert(mod_id.clone(), settings);
+    }
+}
+
+/// The value of a loaded mod's setting
+pub fn get(mod_id: &str, key: &str) -> Option<Value> {
+    SETTINGS.lock().unwrap().get(mod_id)?.get(key).map(|setting| setting.value.clone())
+}
+
+/// A setting's value as it is written into a patched file
+pub fn patch_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Boolean(flag) => if *flag { "1" } else { "0" }.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn find<'s>(all: &'s mut HashMap<String, IndexMap<String, Setting>>, mod_id: &str, key: &str) -> anyhow::Result<&'s mut Setting> {
+    let settings = all.get_mut(mod_id).ok_or_else(|| anyhow!("No loaded mod with ID '{}' has settings", mod_id))?;
+    if !settings.contains_key(key) {
+        let keys: Vec<&str> = settings.keys().map(String::as_str).collect();
+        return Err(anyhow!("'{}' has no setting '{}', expected one of {}", mod_id, key, keys.join(", ")));
+    }
+    Ok(&mut settings[key])
+}
+
+/// Choose a value for a setting in openzt.toml, parsed from `text` according to the setting's type
+pub fn set(mod_id: &str, key: &str, text: &str) -> anyhow::Result<String> {
+    let value = {
+        let mut all = SETTINGS.lock().unwrap();
+        find(&mut all, mod_id, key)?.definition.setting_type().parse(text)?
+    };
+
+    let mut config = get_openzt_config();
+    config.mod_settings.entry(mod_id.to_string()).or_default().insert(key.to_string(), value.clone());
+    save_openzt_config(&config, false)?;
+
+    info!("Set {} setting '{}' to {} in openzt.toml", mod_id, key, value);
+    let mut all = SETTINGS.lock().unwrap();
+    find(&mut all, mod_id, key)?.value = value.clone();
+    Ok(format!("Set {} '{}' to {}, patches use it after restarting Zoo Tycoon", mod_id, key, value))
+}
+
+/// Remove the value chosen for a setting from openzt.toml, going back to the mod's default
+pub fn reset(mod_id: &str, key: &str) -> anyhow::Result<String> {
+    let default = {
+        let mut all = SETTINGS.lock().unwrap();
+        let setting = find(&mut all, mod_id, key)?;
+        setting.default_value().ok_or_else(|| anyhow!("'{}' has no valid default", key))?
+    };
+
+    let mut config = get_openzt_config();
+    if let Some(chosen) = config.mod_settings.get_mut(mod_id) {
+        chosen.shift_remove(key);
+        if chosen.is_empty() {
+            config.mod_settings.shift_remove(mod_id);
+        }
+    }
+    save_openzt_config(&config, false)?;
+
+    info!("Reset {} setting '{}' in openzt.toml", mod_id, key);
+    let mut all = SETTINGS.lock().unwrap();
+    find(&mut all, mod_id, key)?.value = default.clone();
+    Ok(format!(
+        "Reset {} '{}' to its default {}, patches use it after restarting Zoo Tycoon",
+        mod_id, key, default
+    ))
+}
+
+/// One line per setting with its value, type, default and description
+fn format_settings(mod_id: &str, settings: &IndexMap<String, Setting>) -> String {
+    let mut list = format!("{}:\n", mod_id);
+    for (key, setting) in settings {
+        let changed = if setting.default_value().as_ref() == Some(&setting.value) {
+            ""
+        } else {
+            ", changed"
+        };
+        let _ = writeln!(
+            list,
+            "  {} = {} ({}, default {}{}): {}",
+            key,
+            setting.value,
+            setting.definition.setting_type().name(),
+            setting.definition.default(),
+            changed,
+            setting.definition.description()
+        );
+    }
+    list
+}
+
+/// The settings of one loaded mod, or of every loaded mod that has any
+pub fn list(mod_id: Option<&str>) -> anyhow::Result<String> {
+    let all = SETTINGS.lock().unwrap();
+    if let Some(mod_id) = mod_id {
+        let settings = all.get(mod_id).ok_or_else(|| anyhow!("No loaded mod with ID '{}' has settings", mod_id))?;
+        return Ok(format_settings(mod_id, settings));
+    }
+
+    if all.is_empty() {
+        return Ok("No loaded mod has settings\n".to_string());
+    }
+    let mut mod_ids: Vec<&String> = all.keys().collect();
+    mod_ids.sort();
+    Ok(mod_ids.into_iter().map(|mod_id| format_settings(mod_id, &all[mod_id])).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta() -> Meta {
+        toml::from_str(
+            r#"
+            name = "Moon"
+            description = "A habitat"
+            authors = ["Finn"]
+            mod_id = "finn.moon"
+            version = "1.0.0"
+
+            [settings.litter_size]
+            type = "integer"
+            default = 2
+            description = "Babies born per litter"
+
+            [settings.speed]
+            type = "float"
+            default = 1
+            description = "Walking speed"
+
+            [settings.hard_mode]
+            type = "boolean"
+            default = "no"
+            description = "Harder"
+        "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let chosen: IndexMap<String, Value> = toml::from_str("litter_size = 3\nspeed = \"fast\"\ncolour = \"red\"").unwrap();
+        let (settings, warnings) = resolve(meta().settings(), Some(&chosen));
+
+        assert_eq!(settings.keys().collect::<Vec<_>>(), vec!["litter_size", "speed"]);
+        assert_eq!(settings["litter_size"].value, Value::Integer(3));
+        assert_eq!(settings["speed"].value, Value::Float(1.0));
+        assert_eq!(
+            warnings,
+            vec![
+                "openzt.toml sets 'speed' to \"fast\", which isn't a valid float, using the default 1.0",
+                "Setting 'hard_mode' is skipped, its default \"no\" isn't a valid boolean",
+                "openzt.toml sets 'colour', which isn't a setting of this mod",
+            ]
+        );
+
+        let (settings, warnings) = resolve(meta().settings(), None);
+        assert_eq!(settings["litter_size"].value, Value::Integer(2));
+        assert_eq!(warnings.len(), 1);
+
+        assert_eq!(
+            format_settings("finn.moon", &resolve(meta().settings(), Some(&chosen)).0),
+            "finn.moon:\n  litter_size = 3 (integer, default 2, changed): Babies born per litter\n  \
+             speed = 1.0 (float, default 1): Walking speed\n"
+        );
+    }
+
+    #[test]
+    fn test_patch_text() {
+        assert_eq!(patch_text(&Value::Integer(3)), "3");
+        assert_eq!(patch_text(&Value::Float(1.5)), "1.5");
+        assert_eq!(patch_text(&Value::Boolean(true)), "1");
+        assert_eq!(patch_text(&Value::Boolean(false)), "0");
+        assert_eq!(patch_text(&Value::String("Moon".to_string())), "Moon");
+    }
+}
@@ -0,0 +1,266 @@
+//! Cross-mod patch conflicts
+//!
+//! Patches that set or remove INI keys, or merge or replace INI files, claim those
+//! keys for their mod. When a patch from another mod writes a claimed key, the patch
+//! with the higher `priority` wins regardless of load order. A patch's priority is
+//! its own `priority` field, or else `priority` in `[patch_meta]`. On equal priority
+//! the later mod wins, and `patch_conflict_policy` in the `[mod_loading]` section
+//! of openzt.toml decides whether that happens with a warning, silently, or fails
+//! the later patch. Once loading finishes every key written by more than one mod
+//! is listed in `openzt_patch_conflicts.txt` next to openzt.toml.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+
+use tracing::{error, info, warn};
+
+use crate::resource_manager::{
+    lazyresourcemap::resource_key,
+    mod_config::{get_openzt_config, PatchConflictPolicy},
+    openzt_mods::patch_dry_run::IniKey,
+};
+
+const REPORT_FILE_NAME: &str = "openzt_patch_conflicts.txt";
+
+/// An INI key in a file as (target, section, key), with the target lowercased
+type KeyId = (String, String, String);
+
+/// A patch that wrote an INI key
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyWrite {
+    pub mod_id: String,
+    pub patch_name: String,
+    pub priority: i32,
+}
+
+/// Every patch write to each tracked INI key, in load order
+#[derive(Default)]
+pub struct KeyWrites {
+    writes: BTreeMap<KeyId, Vec<KeyWrite>>,
+}
+
+static KEY_WRITES: LazyLock<Mutex<KeyWrites>> = LazyLock::new(|| Mutex::new(KeyWrites::default()));
+
+/// The write that ends up in the file: highest priority, latest on equal priority
+fn winner(writes: &[KeyWrite]) -> Option<&KeyWrite> {
+    writes.iter().max_by_key(|write| write.priority)
+}
+
+impl KeyWrites {
+    /// Claim `keys` in `target` for a patch
+    ///
+    /// # Returns
+    /// * `Ok(blocked)` - the keys owned by a higher-priority mod, which the patch must not write
+    /// * `Err(_)` - the patch overrides another mod's key at equal priority and the policy is `error`
+    pub fn claim(&mut self, target: &str, keys: &[IniKey], write: KeyWrite, policy: PatchConflictPolicy) -> anyhow::Result<Vec<IniKey>> {
+        let target = target.to_lowercase();
+        let mut blocked = Vec::new();
+        let mut overridden = Vec::new();
+
+        for (section, key) in keys {
+            let id = (target.clone(), section.clone(), key.clone());
+            let Some(owner) = self.writes.get(&id).and_then(|writes| winner(writes)) else {
+                continue;
+            };
+            if owner.mod_id == write.mod_id {
+                continue;
+            }
+
+            if owner.priority > write.priority {
+                info!(
+                    "Patch '{}': keeping [{}] {} in {} from mod '{}' (priority {} > {})",
+                    write.patch_name, section, key, target, owner.mod_id, owner.priority, write.priority
+                );
+                blocked.push((section.clone(), key.clone()));
+            } else if owner.priority < write.priority {
+                info!(
+                    "Patch '{}': overriding [{}] {} in {} from mod '{}' (priority {} > {})",
+                    write.patch_name, section, key, target, owner.mod_id, write.priority, owner.priority
+                );
+            } else {
+                overridden.push((section, key, owner.mod_id.clone()));
+            }
+        }
+
+        for (section, key, mod_id) in &overridden {
+            match policy {
+                PatchConflictPolicy::Error => {
+                    return Err(anyhow::anyhow!(
+                        "[{}] {} in {} was already written by mod '{}' with the same priority (patch_conflict_policy = \"error\")",
+                        section,
+                        key,
+                        target,
+                        mod_id
+                    ));
+                }
+                PatchConflictPolicy::Warn => warn!("Patch '{}': overriding [{}] {} in {} from mod '{}'", write.patch_name, section, key, target, mod_id),
+                PatchConflictPolicy::LastWins => {}
+            }
+        }
+
+        for (section, key) in keys {
+            self.writes.entry((target.clone(), section.clone(), key.clone())).or_default().push(write.clone());
+        }
+
+        Ok(blocked)
+    }
+
+    /// Forget the writes of patches that were rolled back
+    pub fn release(&mut self, mod_id: &str, patch_names: &[&str]) {
+        self.release_where(|write| write.mod_id == mod_id && patch_names.contains(&write.patch_name.as_str()));
+    }
+
+    /// Forget every write made by a mod (before the mod is reloaded)
+    pub fn release_mod(&mut self, mod_id: &str) {
+        self.release_where(|write| write.mod_id == mod_id);
+    }
+
+    fn release_where(&mut self, released: impl Fn(&KeyWrite) -> bool) {
+        for writes in self.writes.values_mut() {
+            writes.retain(|write| !released(write));
+        }
+        self.writes.retain(|_, writes| !writes.is_empty());
+    }
+
+    /// Keys written by more than one mod, with every write in load order
+    pub fn conflicts(&self) -> Vec<(&KeyId, &[KeyWrite])> {
+        self.writes
+            .iter()
+            .filter(|(_, writes)| writes.iter().any(|write| write.mod_id != writes[0].mod_id))
+            .map(|(id, writes)| (id, writes.as_slice()))
+            .collect()
+    }
+
+    /// Format the conflict report
+    pub fn format_report(&self) -> String {
+        let conflicts = self.conflicts();
+
+        let mut report = String::new();
+        let _ = writeln!(report, "OpenZT patch conflicts: {} keys written by more than one mod", conflicts.len());
+
+        for ((target, section, key), writes) in conflicts {
+            let _ = writeln!(report, "\n{} [{}] {}", target, section, key);
+            let winner = winner(writes);
+            for write in writes {
+                let marker = if Some(write) == winner { " <- wins" } else { "" };
+                let _ = writeln!(report, "  {}: {} (priority {}){}", write.mod_id, write.patch_name, write.priority, marker);
+            }
+        }
+
+        report
+    }
+}
+
+/// Claim the keys a patch writes, using the configured conflict policy
+///
+/// See [`KeyWrites::claim`].
+pub fn claim(target: &str, keys: &[IniKey], write: KeyWrite) -> anyhow::Result<Vec<IniKey>> {
+    let policy = get_openzt_config().mod_loading.patch_conflict_policy;
+    KEY_WRITES.lock().unwrap().claim(&resource_key(target), keys, write, policy)
+}
+
+/// Forget the writes of patches that were rolled back
+pub fn release(mod_id: &str, patch_names: &[&str]) {
+    KEY_WRITES.lock().unwrap().release(mod_id, patch_names);
+}
+
+/// Forget every write made by a mod (before the mod is reloaded)
+pub fn release_mod(mod_id: &str) {
+    KEY_WRITES.lock().unwrap().release_mod(mod_id);
+}
+
+/// Conflict report for the patches applied so far
+pub fn report() -> String {
+    KEY_WRITES.lock().unwrap().format_report()
+}
+
+/// Write the conflict report next to openzt.toml
+///
+/// Called once mod loading has finished. A report left over from an earlier
+/// run is removed when there are no conflicts.
+pub fn finish() {
+    let key_writes = KEY_WRITES.lock().unwrap();
+    let report_path = crate::util::get_base_path().join(REPORT_FILE_NAME);
+
+    let conflicts = key_writes.conflicts().len();
+    if conflicts == 0 {
+        if report_path.exists() && let Err(e) = std::fs::remove_file(&report_path) {
+            error!("Failed to remove old patch conflict report {}: {}", report_path.display(), e);
+        }
+        return;
+    }
+
+    warn!("{} INI keys were written by patches from more than one mod", conflicts);
+    match std::fs::write(&report_path, key_writes.format_report()) {
+        Ok(()) => info!("Patch conflict report written to {}", report_path.display()),
+        Err(e) => error!("Failed to write patch conflict report to {}: {}", report_path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_manager::mod_config::PatchConflictPolicy::{Error, LastWins, Warn};
+
+    const TARGET: &str = "animals/elephant.ai";
+
+    fn key(section: &str, key: &str) -> IniKey {
+        (section.to_string(), key.to_string())
+    }
+
+    fn write(mod_id: &str, patch_name: &str, priority: i32) -> KeyWrite {
+        KeyWrite {
+            mod_id: mod_id.to_string(),
+            patch_name: patch_name.to_string(),
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_equal_priority_policies() {
+        let mut writes = KeyWrites::default();
+        let keys = [key("Section", "Key")];
+
+        assert_eq!(writes.claim(TARGET, &keys, write("finn.mod_a", "a", 0), Warn).unwrap(), vec![]);
+        // The same mod writing its own key again is not a conflict
+        assert!(writes.claim(TARGET, &keys, write("finn.mod_a", "a2", 0), Error).is_ok());
+        assert!(writes.claim("Animals/Elephant.ai", &keys, write("finn.mod_b", "b", 0), Error).is_err());
+        assert_eq!(writes.claim(TARGET, &keys, write("finn.mod_b", "b", 0), LastWins).unwrap(), vec![]);
+
+        let conflicts = writes.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(winner(conflicts[0].1), Some(&write("finn.mod_b", "b", 0)));
+    }
+
+    #[test]
+    fn test_higher_priority_wins() {
+        let mut writes = KeyWrites::default();
+        let keys = [key("Section", "Key"), key("Section", "Other")];
+
+        writes.claim(TARGET, &keys[..1], write("finn.mod_a", "a", 5), Error).unwrap();
+        let blocked = writes.claim(TARGET, &keys, write("finn.mod_b", "b", 0), Error).unwrap();
+        assert_eq!(blocked, vec![key("Section", "Key")]);
+
+        // A later, higher-priority mod overrides without tripping the policy
+        assert_eq!(writes.claim(TARGET, &keys[..1], write("finn.mod_c", "c", 9), Error).unwrap(), vec![]);
+
+        let report = writes.format_report();
+        assert!(report.starts_with("OpenZT patch conflicts: 1 keys written by more than one mod"));
+        assert!(report.contains("  finn.mod_a: a (priority 5)\n"));
+        assert!(report.contains("  finn.mod_c: c (priority 9) <- wins\n"));
+    }
+
+    #[test]
+    fn test_release_restores_previous_owner() {
+        let mut writes = KeyWrites::default();
+        let keys = [key("Section", "Key")];
+
+        writes.claim(TARGET, &keys, write("finn.mod_a", "a", 0), Warn).unwrap();
+        writes.claim(TARGET, &keys, write("finn.mod_b", "b", 3), Warn).unwrap();
+        writes.release("finn.mod_b", &["b"]);
+
+        assert!(writes.conflicts().is_empty());
+        assert!(writes.claim(TARGET, &keys, write("finn.mod_c", "c", 0), Error).is_err());
+    }
+}
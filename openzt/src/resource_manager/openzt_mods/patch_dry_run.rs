@@ -0,0 +1,289 @@
+//! Patch dry-run mode (`patch_dry_run = true` in the `[dev]` section of openzt.toml)
+//!
+//! Patches from every mod are applied to one shared shadow copy instead of the
+//! live resource map, so later mods see earlier mods' changes exactly as they
+//! would in a real load. Each patch is recorded with the keys it changed, and
+//! once loading finishes a report is written to `openzt_patch_report.txt` next
+//! to openzt.toml. Keys written by more than one mod are listed as conflicts.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+
+use openzt_configparser::ini::Ini;
+use tracing::{error, info};
+
+use crate::resource_manager::{
+    mod_config::get_openzt_config,
+    openzt_mods::patches::{ShadowResources, ShadowScope},
+    ztfile::ZTFile,
+};
+
+const REPORT_FILE_NAME: &str = "openzt_patch_report.txt";
+
+/// An INI key as (section, key)
+pub type IniKey = (String, String);
+
+type IniValues = BTreeMap<IniKey, Option<Vec<String>>>;
+
+/// How a patch changed its target file
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+    /// Applied without changing the file
+    Unchanged,
+    /// Condition not met
+    Skipped,
+    Failed(String),
+}
+
+/// One patch recorded during a dry run
+#[derive(Debug, Clone)]
+pub struct PatchChange {
+    pub mod_id: String,
+    pub patch_name: String,
+    pub target: String,
+    pub kind: ChangeKind,
+    /// INI keys whose values changed
+    pub keys: Vec<IniKey>,
+}
+
+#[derive(Default)]
+struct DryRunState {
+    shadow: Option<ShadowResources>,
+    changes: Vec<PatchChange>,
+}
+
+static DRY_RUN: LazyLock<Mutex<DryRunState>> = LazyLock::new(|| Mutex::new(DryRunState::default()));
+
+/// Whether patches should be dry-run rather than applied
+pub fn is_enabled() -> bool {
+    get_openzt_config().dev.patch_dry_run
+}
+
+/// Run `f` against the shared dry-run shadow, creating it on first use
+pub fn with_shadow<T>(f: impl FnOnce(&mut ShadowResources) -> T) -> anyhow::Result<T> {
+    let mut state = DRY_RUN.lock().unwrap();
+    if state.shadow.is_none() {
+        state.shadow = Some(ShadowResources::new(&HashSet::new(), ShadowScope::DryRun)?);
+    }
+    Ok(f(state.shadow.as_mut().expect("dry-run shadow was just created")))
+}
+
+/// Record the patches applied from one patch file
+///
+/// Must not be called from within [`with_shadow`], which holds the same lock.
+pub fn record(changes: Vec<PatchChange>) {
+    DRY_RUN.lock().unwrap().changes.extend(changes);
+}
+
+/// Classify a change from a target's contents before and after a patch (`None` = file absent)
+pub fn compare(before: Option<&ZTFile>, after: Option<&ZTFile>) -> (ChangeKind, Vec<IniKey>) {
+    match (before, after) {
+        (None, None) => (ChangeKind::Unchanged, Vec::new()),
+        (None, Some(after)) => (ChangeKind::Created, changed_keys(None, after)),
+        (Some(_), None) => (ChangeKind::Deleted, Vec::new()),
+        (Some(before), Some(after)) if file_bytes(before) == file_bytes(after) => (ChangeKind::Unchanged, Vec::new()),
+        (Some(before), Some(after)) => (ChangeKind::Modified, changed_keys(Some(before), after)),
+    }
+}
+
+fn file_bytes(file: &ZTFile) -> &[u8] {
+    match file {
+        ZTFile::Text(content, _, _) => content.as_bytes(),
+        ZTFile::RawBytes(data, _, _) => data,
+    }
+}
+
+/// Keys whose values differ between two versions of an INI file; empty for non-text files
+fn changed_keys(before: Option<&ZTFile>, after: &ZTFile) -> Vec<IniKey> {
+    let before = match before {
+        Some(file) => match ini_values(file) {
+            Some(values) => values,
+            None => return Vec::new(),
+        },
+        None => BTreeMap::new(),
+    };
+    let Some(after) = ini_values(after) else {
+        return Vec::new();
+    };
+
+    let mut keys: Vec<IniKey> = before.keys().chain(after.keys()).filter(|key| before.get(*key) != after.get(*key)).cloned().collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+fn ini_values(file: &ZTFile) -> Option<IniValues> {
+    let ZTFile::Text(content, _, _) = file else {
+        return None;
+    };
+
+    let mut ini = Ini::new_cs();
+    ini.set_comment_symbols(&[';', '#', ':']);
+    ini.read(content.to_string_lossy().into_owned()).ok()?;
+
+    Some(
+        ini.get_map_ref()
+            .iter()
+            .flat_map(|(section, keys)| keys.iter().map(move |(key, value)| ((section.clone(), key.clone()), value.clone())))
+            .collect(),
+    )
+}
+
+/// INI keys written by more than one mod, with the mods in load order (the last one wins)
+pub fn find_conflicts(changes: &[PatchChange]) -> BTreeMap<(String, String, String), Vec<String>> {
+    let mut writers: BTreeMap<(String, String, String), Vec<String>> = BTreeMap::new();
+    for change in changes {
+        for (section, key) in &change.keys {
+            let mods = writers.entry((change.target.clone(), section.clone(), key.clone())).or_default();
+            if !mods.contains(&change.mod_id) {
+                mods.push(change.mod_id.clone());
+            }
+        }
+    }
+    writers.retain(|_, mods| mods.len() > 1);
+    writers
+}
+
+/// Format the dry-run report for a set of recorded changes
+pub fn format_report(changes: &[PatchChange]) -> String {
+    let count = |f: fn(&ChangeKind) -> bool| changes.iter().filter(|change| f(&change.kind)).count();
+    let changed = count(|kind| matches!(kind, ChangeKind::Created | ChangeKind::Modified | ChangeKind::Deleted));
+    let skipped = count(|kind| *kind == ChangeKind::Skipped);
+    let failed = count(|kind| matches!(kind, ChangeKind::Failed(_)));
+
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "OpenZT patch dry run: {} patches, {} changed a file, {} skipped, {} failed",
+        changes.len(),
+        changed,
+        skipped,
+        failed
+    );
+
+    let mut current_mod = None;
+    for change in changes {
+        if current_mod != Some(&change.mod_id) {
+            let _ = writeln!(report, "\n{}", change.mod_id);
+            current_mod = Some(&change.mod_id);
+        }
+
+        let outcome = match &change.kind {
+            ChangeKind::Created => "creates",
+            ChangeKind::Modified => "modifies",
+            ChangeKind::Deleted => "deletes",
+            ChangeKind::Unchanged => "leaves unchanged",
+            ChangeKind::Skipped => "skipped for",
+            ChangeKind::Failed(_) => "FAILED on",
+        };
+        let _ = write!(report, "  {}: {} {}", change.patch_name, outcome, change.target);
+        if let ChangeKind::Failed(reason) = &change.kind {
+            let _ = write!(report, " - {}", reason);
+        }
+        let _ = writeln!(report);
+
+        for (section, key) in &change.keys {
+            let _ = writeln!(report, "    [{}] {}", section, key);
+        }
+    }
+
+    let conflicts = find_conflicts(changes);
+    if !conflicts.is_empty() {
+        let _ = writeln!(report, "\nConflicts (keys written by more than one mod, last one wins):");
+        for ((target, section, key), mods) in &conflicts {
+            let _ = writeln!(report, "  {} [{}] {}: {}", target, section, key, mods.join(" -> "));
+        }
+    }
+
+    report
+}
+
+/// Report for the changes recorded so far
+pub fn report() -> String {
+    format_report(&DRY_RUN.lock().unwrap().changes)
+}
+
+/// Discard the dry-run shadow and write the report next to openzt.toml
+///
+/// Called once mod loading has finished. Recorded changes are kept, so the
+/// report can still be shown from the console.
+pub fn finish() {
+    let mut state = DRY_RUN.lock().unwrap();
+    if let Some(shadow) = state.shadow.take() {
+        shadow.discard();
+    }
+
+    let report = format_report(&state.changes);
+    info!("{}", report.lines().next().unwrap_or_default());
+
+    let report_path = crate::util::get_base_path().join(REPORT_FILE_NAME);
+    match std::fs::write(&report_path, report) {
+        Ok(()) => info!("Patch dry-run report written to {}", report_path.display()),
+        Err(e) => error!("Failed to write patch dry-run report to {}: {}", report_path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_manager::ztfile::ZTFileType;
+
+    fn text_file(content: &str) -> ZTFile {
+        ZTFile::Text(std::ffi::CString::new(content).unwrap(), ZTFileType::Ai, content.len() as u32)
+    }
+
+    fn change(mod_id: &str, target: &str, keys: &[(&str, &str)]) -> PatchChange {
+        PatchChange {
+            mod_id: mod_id.to_string(),
+            patch_name: "patch".to_string(),
+            target: target.to_string(),
+            kind: ChangeKind::Modified,
+            keys: keys.iter().map(|(section, key)| (section.to_string(), key.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_compare_lists_changed_keys() {
+        let before = text_file("[Section]\nKept = 1\nChanged = 1\nRemoved = 1\n");
+        let after = text_file("[Section]\nKept = 1\nChanged = 2\n[New]\nAdded = 1\n");
+
+        let (kind, keys) = compare(Some(&before), Some(&after));
+        assert_eq!(kind, ChangeKind::Modified);
+        assert_eq!(
+            keys,
+            vec![
+                ("New".to_string(), "Added".to_string()),
+                ("Section".to_string(), "Changed".to_string()),
+                ("Section".to_string(), "Removed".to_string()),
+            ]
+        );
+
+        assert_eq!(compare(Some(&before), Some(&before)).0, ChangeKind::Unchanged);
+        assert_eq!(compare(Some(&before), None).0, ChangeKind::Deleted);
+        assert_eq!(compare(None, Some(&after)).0, ChangeKind::Created);
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let changes = vec![
+            change("finn.mod_a", "animals/elephant.ai", &[("Section", "Key"), ("Section", "Other")]),
+            change("finn.mod_b", "animals/elephant.ai", &[("Section", "Key")]),
+            change("finn.mod_b", "animals/tiger.ai", &[("Section", "Key")]),
+        ];
+
+        let conflicts = find_conflicts(&changes);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts.get(&("animals/elephant.ai".to_string(), "Section".to_string(), "Key".to_string())),
+            Some(&vec!["finn.mod_a".to_string(), "finn.mod_b".to_string()])
+        );
+
+        let report = format_report(&changes);
+        assert!(report.starts_with("OpenZT patch dry run: 3 patches, 3 changed a file, 0 skipped, 0 failed"));
+        assert!(report.contains("animals/elephant.ai [Section] Key: finn.mod_a -> finn.mod_b"));
+    }
+}
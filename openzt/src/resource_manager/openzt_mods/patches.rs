@@ -1,23 +1,33 @@
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::str;
+use std::sync::Arc;
 
 use anyhow::{self, Context};
-use openzt_configparser::ini::{Ini, MergeMode as IniMergeMode};
+use openzt_configparser::ini::{DuplicateKeyMode as IniDuplicateKeyMode, Ini, MergeMode as IniMergeMode};
 use tracing::{error, info, warn};
 
 use crate::{
     animation::Animation,
     mods::{
-        AddSectionPatch, AppendValuePatch, AppendValuesPatch, ClearSectionPatch, DeletePatch, ErrorHandling, MergeMode, MergePatch, OnExists, Patch, PatchCondition,
-        PatchMeta, RemoveKeyPatch, RemoveKeysPatch, RemoveSectionPatch, ReplacePatch, SetKeyPatch, SetKeysPatch, SetPalettePatch,
+        AddSectionPatch, AppendValuePatch, AppendValuesPatch, ClearSectionPatch, DeletePatch, DuplicateKeyMode, EditListPatch, ErrorHandling, ListAction, MergeMode,
+        MergePatch, OnExists, Patch, PatchCondition, PatchMeta, RemoveKeyPatch, RemoveKeysPatch, RemoveSectionPatch, ReplacePatch, SetKeyPatch, SetKeysPatch,
+        SetPalettePatch,
     },
     resource_manager::{
-        lazyresourcemap::{add_ztfile, add_ztfile_from_memory, check_file, get_file, remove_resource},
+        file_overrides,
+        lazyresourcemap::{add_ztfile, add_ztfile_from_memory, check_file, get_file, get_file_names, path_key, remove_resource},
+        load_report::{self, LoadStage},
         openzt_mods::{
-            get_mod_ids,
             habitats_locations::{get_habitat_id, get_location_id},
+            hot_reload,
             legacy_attributes::{get_legacy_attribute_with_subtype, LegacyEntityType},
+            loading::{get_mod_file, is_mod_loaded},
+            mod_settings,
+            patch_conflicts::{self, KeyWrite},
+            patch_dry_run::{self, ChangeKind, IniKey, PatchChange},
+            shared_sources,
         },
         ztfile::{modify_ztfile_as_animation, ZTFile, ZTFileType},
     },
@@ -34,6 +44,7 @@ enum VariableType {
     Habitats,
     Locations,
     Strings,
+    Settings,
     Legacy,
 }
 
@@ -73,6 +84,7 @@ pub struct SubstitutionContext {
 /// * "habitats.swamp" → ParsedVariable { var_type: Habitat, mod_id: None, identifier: "swamp" }
 /// * "lunar.locations.moon" → ParsedVariable { var_type: Location, mod_id: Some("lunar"), identifier: "moon" }
 /// * "string.9500" → ParsedVariable { var_type: String, mod_id: None, identifier: "9500" }
+/// * "settings.litter_size" → ParsedVariable { var_type: Settings, mod_id: None, identifier: "litter_size" }
 fn parse_variable(var_str: &str) -> anyhow::Result<ParsedVariable> {
     let parts: Vec<&str> = var_str.split('.').collect();
 
@@ -83,7 +95,8 @@ fn parse_variable(var_str: &str) -> anyhow::Result<ParsedVariable> {
                 "habitats" => VariableType::Habitats,
                 "locations" => VariableType::Locations,
                 "strings" => VariableType::Strings,
-                _ => anyhow::bail!("Invalid variable type '{}': expected 'habitat', 'location', or 'string'", parts[0]),
+                "settings" => VariableType::Settings,
+                _ => anyhow::bail!("Invalid variable type '{}': expected 'habitats', 'locations', 'strings', or 'settings'", parts[0]),
             };
 
             Ok(ParsedVariable {
@@ -114,7 +127,8 @@ fn parse_variable(var_str: &str) -> anyhow::Result<ParsedVariable> {
                     "habitats" => VariableType::Habitats,
                     "locations" => VariableType::Locations,
                     "strings" => VariableType::Strings,
-                    _ => anyhow::bail!("Invalid variable type '{}': expected 'habitats', 'locations', or 'string'", parts[1]),
+                    "settings" => VariableType::Settings,
+                    _ => anyhow::bail!("Invalid variable type '{}': expected 'habitats', 'locations', 'strings', or 'settings'", parts[1]),
                 };
 
                 Ok(ParsedVariable {
@@ -238,6 +252,24 @@ fn resolve_variable(var: &ParsedVariable, context: &SubstitutionContext) -> anyh
 
             get_string_from_registry(string_id).map_err(|_| anyhow::anyhow!("String ID {} not found in registry", string_id))
         }
+        VariableType::Settings => {
+            let mod_id = var.mod_id.as_deref().unwrap_or(&context.current_mod_id);
+
+            match mod_settings::get(mod_id, &var.identifier) {
+                Some(value) => Ok(mod_settings::patch_text(&value)),
+                None => {
+                    if var.mod_id.is_some() {
+                        anyhow::bail!(
+                            "Setting '{}' not found in mod '{}' (ensure mod is loaded and declares the setting in meta.toml)",
+                            var.identifier,
+                            mod_id
+                        )
+                    } else {
+                        anyhow::bail!("Setting '{}' not found in current mod's meta.toml", var.identifier)
+                    }
+                }
+            }
+        }
         VariableType::Legacy => {
             // NEW: Resolve legacy entity attribute
             let parts = var.legacy_parts.as_ref().ok_or_else(|| anyhow::anyhow!("Legacy variable missing parts"))?;
@@ -329,6 +361,8 @@ fn substitute_variables(input: &str, context: &SubstitutionContext) -> anyhow::R
 /// This struct holds shadow copies of files that patches will modify.
 /// Patches are applied to the shadow copies, then committed to the main
 /// resource system on success, or discarded on failure (automatic rollback).
+/// Files are keyed by [`path_key`], so with `case_insensitive_paths` targets
+/// that only differ in case or separators share a shadow copy.
 pub struct ShadowResources {
     /// Shadow copies of files being modified (path -> shadow ZTFile)
     pub files: HashMap<String, ZTFile>,
@@ -350,6 +384,12 @@ pub enum ShadowScope {
 
     /// Shadow for entire mod
     Mod,
+
+    /// Shadow shared by every mod during a patch dry run (never committed)
+    DryRun,
+
+    /// Files as they were before the hot-reloaded dev mod changed them (never committed)
+    HotReload,
 }
 
 impl ShadowResources {
@@ -372,17 +412,7 @@ impl ShadowResources {
                 let file_type = ZTFileType::try_from(Path::new(path)).map_err(|e| anyhow::anyhow!("Invalid file type for '{}': {}", path, e))?;
 
                 let ztfile = match file_type {
-                    ZTFileType::Ini
-                    | ZTFileType::Ai
-                    | ZTFileType::Ani
-                    | ZTFileType::Cfg
-                    | ZTFileType::Lyt
-                    | ZTFileType::Scn
-                    | ZTFileType::Uca
-                    | ZTFileType::Ucs
-                    | ZTFileType::Ucb
-                    | ZTFileType::Txt
-                    | ZTFileType::Toml => {
+                    file_type if file_type.is_text() => {
                         let content = crate::encoding_utils::decode_game_text(&raw_data);
                         let content_len = content.len() as u32;
                         let c_string = std::ffi::CString::new(content)?;
@@ -391,10 +421,10 @@ impl ShadowResources {
                     _ => ZTFile::RawBytes(raw_data, file_type, 0),
                 };
 
-                files.insert(path.clone(), ztfile);
+                files.insert(path_key(path), ztfile);
             } else {
                 // File doesn't exist yet - mark as new
-                new_files.insert(path.clone());
+                new_files.insert(path_key(path));
             }
         }
 
@@ -418,7 +448,7 @@ impl ShadowResources {
     /// * `None` - File not found anywhere
     pub fn get_file(&self, path: &str) -> Option<ZTFile> {
         // Check shadow first
-        if let Some(file) = self.files.get(path) {
+        if let Some(file) = self.files.get(&path_key(path)) {
             return Some(file.clone());
         }
 
@@ -428,17 +458,7 @@ impl ShadowResources {
             let file_type = ZTFileType::try_from(Path::new(path)).ok()?;
 
             let ztfile = match file_type {
-                ZTFileType::Ini
-                | ZTFileType::Ai
-                | ZTFileType::Ani
-                | ZTFileType::Cfg
-                | ZTFileType::Lyt
-                | ZTFileType::Scn
-                | ZTFileType::Uca
-                | ZTFileType::Ucs
-                | ZTFileType::Ucb
-                | ZTFileType::Txt
-                | ZTFileType::Toml => {
+                file_type if file_type.is_text() => {
                     let content = crate::encoding_utils::decode_game_text(&raw_data);
                     let content_len = content.len() as u32;
                     let c_string = std::ffi::CString::new(content).ok()?;
@@ -459,10 +479,11 @@ impl ShadowResources {
     /// * `path` - File path to update
     /// * `file` - New file content
     pub fn update_file(&mut self, path: &str, file: ZTFile) {
-        self.files.insert(path.to_string(), file);
+        let key = path_key(path);
+        self.files.insert(key.clone(), file);
 
         // If this was marked as new, it's now created
-        self.new_files.remove(path);
+        self.new_files.remove(&key);
     }
 
     /// Delete a file from the shadow
@@ -470,10 +491,11 @@ impl ShadowResources {
     /// # Arguments
     /// * `path` - File path to delete
     pub fn delete_file(&mut self, path: &str) {
-        self.files.remove(path);
-        self.new_files.remove(path);
+        let key = path_key(path);
+        self.files.remove(&key);
+        self.new_files.remove(&key);
         // Mark file for deletion from main resources on commit
-        self.deleted_files.insert(path.to_string());
+        self.deleted_files.insert(key);
     }
 
     /// Check if a file exists in shadow or main resources
@@ -486,12 +508,13 @@ impl ShadowResources {
     /// * `false` - File not found or marked for deletion
     pub fn file_exists(&self, path: &str) -> bool {
         // If file is marked for deletion, it doesn't exist
-        if self.deleted_files.contains(path) {
+        let key = path_key(path);
+        if self.deleted_files.contains(&key) {
             return false;
         }
 
         // Check shadow or main resources
-        self.files.contains_key(path) || check_file(path)
+        self.files.contains_key(&key) || check_file(path)
     }
 
     /// Commit shadow to main resource system (success case)
@@ -642,6 +665,9 @@ fn collect_affected_files(patches: &indexmap::IndexMap<String, Patch>) -> HashSe
             Patch::AppendValues(p) => {
                 files.insert(p.target.clone());
             }
+            Patch::EditList(p) => {
+                files.insert(p.target.clone());
+            }
             Patch::RemoveKey(p) => {
                 files.insert(p.target.clone());
             }
@@ -670,7 +696,7 @@ fn collect_affected_files(patches: &indexmap::IndexMap<String, Patch>) -> HashSe
 /// Apply set_key patch to shadow
 fn apply_set_key_patch_shadow(
     patch: &SetKeyPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     context: &SubstitutionContext,
     shadow: &mut ShadowResources,
@@ -695,7 +721,7 @@ fn apply_set_key_patch_shadow(
 /// Apply set_keys patch to shadow
 fn apply_set_keys_patch_shadow(
     patch: &SetKeysPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     context: &SubstitutionContext,
     shadow: &mut ShadowResources,
@@ -725,7 +751,7 @@ fn apply_set_keys_patch_shadow(
 /// Apply append_value patch to shadow
 fn apply_append_value_patch_shadow(
     patch: &AppendValuePatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     context: &SubstitutionContext,
     shadow: &mut ShadowResources,
@@ -750,7 +776,7 @@ fn apply_append_value_patch_shadow(
 /// Apply append_values patch to shadow
 fn apply_append_values_patch_shadow(
     patch: &AppendValuesPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     context: &SubstitutionContext,
     shadow: &mut ShadowResources,
@@ -778,10 +804,42 @@ fn apply_append_values_patch_shadow(
     Ok(())
 }
 
+/// Apply edit_list patch to shadow
+fn apply_edit_list_patch_shadow(
+    patch: &EditListPatch,
+    _file_map: &HashMap<String, Arc<[u8]>>,
+    patch_name: &str,
+    context: &SubstitutionContext,
+    shadow: &mut ShadowResources,
+) -> anyhow::Result<()> {
+    info!(
+        "Applying edit_list patch '{}' to shadow: {} [{}] {} {:?} {} values",
+        patch_name,
+        patch.target,
+        patch.section,
+        patch.key,
+        patch.action,
+        patch.values.len()
+    );
+
+    validate_edit_list(patch)?;
+    let mut ini = load_ini_from_shadow(&patch.target, shadow)?;
+
+    let values = patch.values.iter().map(|v| substitute_variables(v, context)).collect::<anyhow::Result<Vec<_>>>()?;
+    if !edit_list_in_ini(patch, &values, &mut ini, patch_name) {
+        return Ok(());
+    }
+
+    save_ini_to_shadow(&patch.target, &ini, shadow)?;
+
+    info!("Successfully applied edit_list patch '{}' to shadow", patch_name);
+    Ok(())
+}
+
 /// Apply remove_key patch to shadow
 fn apply_remove_key_patch_shadow(
     patch: &RemoveKeyPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     shadow: &mut ShadowResources,
 ) -> anyhow::Result<()> {
@@ -821,7 +879,7 @@ fn apply_remove_key_patch_shadow(
 /// Apply remove_keys patch to shadow
 fn apply_remove_keys_patch_shadow(
     patch: &RemoveKeysPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     shadow: &mut ShadowResources,
 ) -> anyhow::Result<()> {
@@ -860,7 +918,7 @@ fn apply_remove_keys_patch_shadow(
 /// Apply add_section patch to shadow
 fn apply_add_section_patch_shadow(
     patch: &AddSectionPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     context: &SubstitutionContext,
     shadow: &mut ShadowResources,
@@ -907,7 +965,7 @@ fn apply_add_section_patch_shadow(
 /// Apply clear_section patch to shadow
 fn apply_clear_section_patch_shadow(
     patch: &ClearSectionPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     shadow: &mut ShadowResources,
 ) -> anyhow::Result<()> {
@@ -937,7 +995,7 @@ fn apply_clear_section_patch_shadow(
 /// Apply remove_section patch to shadow
 fn apply_remove_section_patch_shadow(
     patch: &RemoveSectionPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     shadow: &mut ShadowResources,
 ) -> anyhow::Result<()> {
@@ -967,8 +1025,9 @@ fn apply_remove_section_patch_shadow(
 /// Apply replace patch to shadow
 fn apply_replace_patch_shadow(
     patch: &ReplacePatch,
-    file_map: &HashMap<String, Box<[u8]>>,
+    file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
+    current_mod_id: &str,
     shadow: &mut ShadowResources,
 ) -> anyhow::Result<()> {
     info!("Applying replace patch '{}' to shadow: {} -> {}", patch_name, patch.source, patch.target);
@@ -979,27 +1038,20 @@ fn apply_replace_patch_shadow(
     }
 
     // Load source file from archive
-    let source_data = resolve_source_file(&patch.source, file_map)?;
+    let source_data = resolve_source_file(&patch.source, file_map, current_mod_id)?;
     let file_type = ZTFileType::try_from(Path::new(&patch.target)).map_err(|e| anyhow::anyhow!("Invalid target file type: {}", e))?;
 
     let ztfile = match file_type {
-        ZTFileType::Ini
-        | ZTFileType::Ai
-        | ZTFileType::Ani
-        | ZTFileType::Cfg
-        | ZTFileType::Lyt
-        | ZTFileType::Scn
-        | ZTFileType::Uca
-        | ZTFileType::Ucs
-        | ZTFileType::Ucb
-        | ZTFileType::Txt
-        | ZTFileType::Toml => {
-            let content = crate::encoding_utils::decode_game_text(&source_data);
+        file_type if file_type.is_text() => {
+            let mut content = crate::encoding_utils::decode_game_text(&source_data);
+            if !patch.kept_keys.is_empty() {
+                content = with_kept_keys(content, &load_ini_from_shadow(&patch.target, shadow)?, &patch.kept_keys)?;
+            }
             let content_len = content.len() as u32;
             let c_string = std::ffi::CString::new(content)?;
             ZTFile::Text(c_string, file_type, content_len)
         }
-        _ => ZTFile::RawBytes(source_data.into_boxed_slice(), file_type, 0),
+        _ => ZTFile::RawBytes(source_data.into_owned().into_boxed_slice(), file_type, 0),
     };
 
     shadow.update_file(&patch.target, ztfile);
@@ -1011,8 +1063,9 @@ fn apply_replace_patch_shadow(
 /// Apply merge patch to shadow
 fn apply_merge_patch_shadow(
     patch: &MergePatch,
-    file_map: &HashMap<String, Box<[u8]>>,
+    file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
+    current_mod_id: &str,
     shadow: &mut ShadowResources,
 ) -> anyhow::Result<()> {
     info!(
@@ -1037,13 +1090,16 @@ fn apply_merge_patch_shadow(
     let mut target_ini = load_ini_from_shadow(&patch.target, shadow)?;
 
     // Load source INI from archive
-    let source_data = resolve_source_file(&patch.source, file_map)?;
+    let source_data = resolve_source_file(&patch.source, file_map, current_mod_id)?;
     let source_str = crate::encoding_utils::decode_game_text(&source_data);
     let mut source_ini = Ini::new_cs();
     source_ini.set_comment_symbols(&[';', '#', ':']);
     source_ini
         .read(source_str)
         .map_err(|e| anyhow::anyhow!("Failed to parse source INI '{}': {}", patch.source, e))?;
+    for (section, key) in &patch.kept_keys {
+        source_ini.remove_key(section, key);
+    }
 
     // Merge based on mode
     let mode = match patch.merge_mode {
@@ -1051,7 +1107,7 @@ fn apply_merge_patch_shadow(
         MergeMode::BasePriority => IniMergeMode::BasePriority,
     };
 
-    target_ini.merge_with_priority(&source_ini, mode);
+    target_ini.merge_with_options(&source_ini, mode, ini_duplicate_key_mode(patch.duplicate_keys));
 
     // Save merged result to shadow
     save_ini_to_shadow(&patch.target, &target_ini, shadow)?;
@@ -1064,11 +1120,27 @@ fn apply_merge_patch_shadow(
 fn apply_delete_patch_shadow(patch: &DeletePatch, patch_name: &str, shadow: &mut ShadowResources) -> anyhow::Result<()> {
     info!("Applying delete patch '{}' to shadow: -{}", patch_name, patch.target);
 
+    if !patch.deletes_file() {
+        validate_delete_entries(patch)?;
+    }
+
     if !check_file_in_shadow(&patch.target, shadow) {
         warn!("Delete patch '{}': file '{}' not found, skipping", patch_name, patch.target);
         return Ok(());
     }
 
+    if !patch.deletes_file() {
+        let mut ini = load_ini_from_shadow(&patch.target, shadow)?;
+        if delete_ini_entries(patch, &mut ini, patch_name) == 0 {
+            warn!("Delete patch '{}': nothing to remove from '{}', skipping", patch_name, patch.target);
+            return Ok(());
+        }
+        save_ini_to_shadow(&patch.target, &ini, shadow)?;
+
+        info!("Successfully applied delete patch '{}' to shadow", patch_name);
+        return Ok(());
+    }
+
     shadow.delete_file(&patch.target);
 
     info!("Successfully applied delete patch '{}' to shadow", patch_name);
@@ -1141,7 +1213,7 @@ fn apply_set_palette_patch_shadow(patch: &SetPalettePatch, patch_name: &str, sha
 /// # Returns
 /// * `Ok(())` if the patch was applied successfully
 /// * `Err(_)` if the target file doesn't exist, source file doesn't exist, or other errors occur
-fn apply_replace_patch_direct(patch: &ReplacePatch, file_map: &HashMap<String, Box<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
+fn apply_replace_patch_direct(patch: &ReplacePatch, file_map: &HashMap<String, Arc<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
     info!("Applying replace patch '{}': {} -> {}", patch_name, patch.source, patch.target);
 
     // Check if target file exists in resource system
@@ -1150,28 +1222,21 @@ fn apply_replace_patch_direct(patch: &ReplacePatch, file_map: &HashMap<String, B
     }
 
     // Load source file from archive
-    let source_data = resolve_source_file(&patch.source, file_map)?;
+    let source_data = resolve_source_file(&patch.source, file_map, current_mod_id)?;
     let file_type = ZTFileType::try_from(Path::new(&patch.target)).map_err(|e| anyhow::anyhow!("Invalid target file type: {}", e))?;
 
     // Create ZTFile based on file type
     let ztfile = match file_type {
-        ZTFileType::Ini
-        | ZTFileType::Ai
-        | ZTFileType::Ani
-        | ZTFileType::Cfg
-        | ZTFileType::Lyt
-        | ZTFileType::Scn
-        | ZTFileType::Uca
-        | ZTFileType::Ucs
-        | ZTFileType::Ucb
-        | ZTFileType::Txt
-        | ZTFileType::Toml => {
-            let content = crate::encoding_utils::decode_game_text(&source_data);
+        file_type if file_type.is_text() => {
+            let mut content = crate::encoding_utils::decode_game_text(&source_data);
+            if !patch.kept_keys.is_empty() {
+                content = with_kept_keys(content, &load_ini_from_resources(&patch.target)?, &patch.kept_keys)?;
+            }
             let content_len = content.len() as u32;
             let c_string = std::ffi::CString::new(content)?;
             ZTFile::Text(c_string, file_type, content_len)
         }
-        _ => ZTFile::RawBytes(source_data.into_boxed_slice(), file_type, 0),
+        _ => ZTFile::RawBytes(source_data.into_owned().into_boxed_slice(), file_type, 0),
     };
 
     // Update resource (add_ztfile_from_memory automatically replaces if exists)
@@ -1195,7 +1260,7 @@ fn apply_replace_patch_direct(patch: &ReplacePatch, file_map: &HashMap<String, B
 /// # Returns
 /// * `Ok(())` if the patch was applied successfully
 /// * `Err(_)` if files don't exist, aren't INI files, or other errors occur
-fn apply_merge_patch_direct(patch: &MergePatch, file_map: &HashMap<String, Box<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
+fn apply_merge_patch_direct(patch: &MergePatch, file_map: &HashMap<String, Arc<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
     info!(
         "Applying merge patch '{}': {} + {} (mode: {:?})",
         patch_name, patch.target, patch.source, patch.merge_mode
@@ -1219,13 +1284,16 @@ fn apply_merge_patch_direct(patch: &MergePatch, file_map: &HashMap<String, Box<[
         .map_err(|e| anyhow::anyhow!("Failed to parse target INI file '{}': {}", patch.target, e))?;
 
     // Load source INI file from archive
-    let source_data = resolve_source_file(&patch.source, file_map)?;
+    let source_data = resolve_source_file(&patch.source, file_map, current_mod_id)?;
     let source_str = crate::encoding_utils::decode_game_text(&source_data);
     let mut source_ini = Ini::new_cs();
     source_ini.set_comment_symbols(&[';', '#', ':']);
     source_ini
         .read(source_str)
         .map_err(|e| anyhow::anyhow!("Failed to parse source INI file '{}': {}", patch.source, e))?;
+    for (section, key) in &patch.kept_keys {
+        source_ini.remove_key(section, key);
+    }
 
     // Convert MergeMode enum from mods to IniMergeMode from configparser
     let ini_merge_mode = match patch.merge_mode {
@@ -1234,7 +1302,7 @@ fn apply_merge_patch_direct(patch: &MergePatch, file_map: &HashMap<String, Box<[
     };
 
     // Merge source into target
-    target_ini.merge_with_priority(&source_ini, ini_merge_mode);
+    target_ini.merge_with_options(&source_ini, ini_merge_mode, ini_duplicate_key_mode(patch.duplicate_keys));
 
     // Write merged INI to string
     let merged_content = target_ini.writes();
@@ -1251,17 +1319,24 @@ fn apply_merge_patch_direct(patch: &MergePatch, file_map: &HashMap<String, Box<[
     Ok(())
 }
 
-/// Apply a delete patch directly to resources: removes a file from the resource system
+/// Apply a delete patch directly to resources: removes a file from the resource system,
+/// or only the listed keys and sections when any are given
 ///
 /// # Arguments
 /// * `patch` - The delete patch configuration
 /// * `patch_name` - Name of the patch (for logging)
+/// * `current_mod_id` - ID of the mod applying the patch (owner of a rewritten INI file)
 ///
 /// # Returns
-/// * `Ok(())` always (warnings logged if file doesn't exist)
-fn apply_delete_patch_direct(patch: &DeletePatch, patch_name: &str) -> anyhow::Result<()> {
+/// * `Ok(())` if the patch was applied (warnings logged if file, keys or sections don't exist)
+/// * `Err(_)` if a partial delete targets a non-INI file or `keys` is set without `section`
+fn apply_delete_patch_direct(patch: &DeletePatch, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
     info!("Applying delete patch '{}': {}", patch_name, patch.target);
 
+    if !patch.deletes_file() {
+        validate_delete_entries(patch)?;
+    }
+
     if !check_file(&patch.target) {
         warn!(
             "Delete patch '{}': target file '{}' not found (already deleted or never existed)",
@@ -1270,6 +1345,22 @@ fn apply_delete_patch_direct(patch: &DeletePatch, patch_name: &str) -> anyhow::R
         return Ok(());
     }
 
+    if !patch.deletes_file() {
+        let mut ini = load_ini_from_resources(&patch.target)?;
+        let removed = delete_ini_entries(patch, &mut ini, patch_name);
+        if removed == 0 {
+            warn!("Delete patch '{}': nothing to remove from '{}'", patch_name, patch.target);
+            return Ok(());
+        }
+        save_ini_to_resources(&patch.target, &ini, current_mod_id)?;
+
+        info!(
+            "Successfully applied delete patch '{}' - removed {} entries from '{}'",
+            patch_name, removed, patch.target
+        );
+        return Ok(());
+    }
+
     let removed = remove_resource(&patch.target);
     if removed {
         info!("Successfully applied delete patch '{}' - removed '{}'", patch_name, patch.target);
@@ -1331,6 +1422,16 @@ fn apply_set_palette_patch_direct(patch: &SetPalettePatch, patch_name: &str) ->
 // Phase 4: Direct Element-Level Patch Operations (INI Files - for continue mode)
 // ============================================================================
 
+/// Convert DuplicateKeyMode from mods to the configparser equivalent
+fn ini_duplicate_key_mode(mode: DuplicateKeyMode) -> IniDuplicateKeyMode {
+    match mode {
+        DuplicateKeyMode::ReplaceAll => IniDuplicateKeyMode::ReplaceAll,
+        DuplicateKeyMode::ReplaceFirst => IniDuplicateKeyMode::ReplaceFirst,
+        DuplicateKeyMode::AppendDuplicate => IniDuplicateKeyMode::AppendDuplicate,
+        DuplicateKeyMode::KeyedByValue => IniDuplicateKeyMode::KeyedByValue,
+    }
+}
+
 /// Valid INI file extensions
 const VALID_INI_EXTENSIONS: &[&str] = &["ini", "ai", "cfg", "uca", "ucs", "ucb", "scn", "lyt"];
 
@@ -1342,6 +1443,107 @@ fn is_valid_ini_extension(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
+/// Apply an edit_list patch's action to its key, returning whether the value was written
+///
+/// A missing key is created by append and prepend; remove and dedupe skip it with a warning.
+fn edit_list_in_ini(patch: &EditListPatch, values: &[String], ini: &mut Ini, patch_name: &str) -> bool {
+    let current = ini.get(&patch.section, &patch.key);
+    if current.is_none() && matches!(patch.action, ListAction::Remove | ListAction::Dedupe) {
+        warn!(
+            "Edit_list patch '{}': key '{}' not found in section '{}', skipping",
+            patch_name, patch.key, patch.section
+        );
+        return false;
+    }
+
+    let edited = edit_list_value(current.as_deref().unwrap_or(""), patch.action, values, &patch.separator);
+    ini.setstr(&patch.section, &patch.key, Some(&edited));
+    true
+}
+
+/// Check that an edit_list patch targets an INI file and has a separator to split the list on
+fn validate_edit_list(patch: &EditListPatch) -> anyhow::Result<()> {
+    validate_ini_file(&patch.target)?;
+    if patch.separator.is_empty() {
+        anyhow::bail!("Edit_list patch on '{}' has an empty separator", patch.target);
+    }
+    Ok(())
+}
+
+/// Edit a separated list value, trimming whitespace around items and dropping empty ones
+///
+/// Items are rejoined with the separator and the spacing the value had after its first
+/// separator, so `a, b` becomes `a, b, c` after appending `c`.
+fn edit_list_value(current: &str, action: ListAction, values: &[String], separator: &str) -> String {
+    let joiner = match current.split_once(separator) {
+        Some((_, rest)) => format!("{}{}", separator, &rest[..rest.len() - rest.trim_start().len()]),
+        None => separator.to_string(),
+    };
+    let mut items: Vec<&str> = current.split(separator).map(str::trim).filter(|item| !item.is_empty()).collect();
+    let values = values.iter().map(|v| v.trim()).filter(|v| !v.is_empty());
+
+    match action {
+        ListAction::Append => items.extend(values),
+        ListAction::Prepend => {
+            items.splice(0..0, values);
+        }
+        ListAction::Remove => {
+            let removed: Vec<&str> = values.collect();
+            items.retain(|item| !removed.contains(item));
+        }
+        ListAction::Dedupe => {
+            let mut seen = HashSet::new();
+            items.retain(|item| seen.insert(*item));
+        }
+    }
+
+    items.join(&joiner)
+}
+
+/// Check that a partial delete patch targets an INI file and names the section its keys belong to
+fn validate_delete_entries(patch: &DeletePatch) -> anyhow::Result<()> {
+    validate_ini_file(&patch.target)?;
+    if !patch.keys.is_empty() && patch.section.is_none() {
+        anyhow::bail!("Delete patch on '{}' lists keys but no section", patch.target);
+    }
+    Ok(())
+}
+
+/// Remove the keys and sections named by a delete patch, returning how many were removed
+///
+/// Keys are removed before sections, so listing a key's section in both is harmless.
+/// Missing keys and sections are logged and skipped.
+fn delete_ini_entries(patch: &DeletePatch, ini: &mut Ini, patch_name: &str) -> usize {
+    let mut removed = 0;
+
+    if let Some(section) = &patch.section {
+        if patch.keys.is_empty() {
+            if ini.remove_section(section).is_some() {
+                removed += 1;
+            } else {
+                warn!("Delete patch '{}': section '{}' not found", patch_name, section);
+            }
+        }
+        for key in &patch.keys {
+            if ini.remove_key(section, key).is_some() {
+                removed += 1;
+            } else {
+                warn!("Delete patch '{}': key '{}' not found in section '{}'", patch_name, key, section);
+            }
+        }
+    }
+
+    for section in &patch.sections {
+        if ini.remove_section(section).is_some() {
+            removed += 1;
+        } else {
+            warn!("Delete patch '{}': section '{}' not found", patch_name, section);
+        }
+    }
+
+    removed
+}
+
 /// Helper function to validate that a file is an INI-compatible file
 fn validate_ini_file(target: &str) -> anyhow::Result<()> {
     let path = Path::new(target);
@@ -1408,7 +1610,7 @@ fn save_ini_to_resources(target: &str, ini: &Ini, current_mod_id: &str) -> anyho
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
 fn apply_set_key_patch_direct(
     patch: &SetKeyPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     current_mod_id: &str,
     context: &SubstitutionContext,
@@ -1447,7 +1649,7 @@ fn apply_set_key_patch_direct(
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
 fn apply_set_keys_patch_direct(
     patch: &SetKeysPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     current_mod_id: &str,
     context: &SubstitutionContext,
@@ -1489,7 +1691,7 @@ fn apply_set_keys_patch_direct(
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
 fn apply_append_value_patch_direct(
     patch: &AppendValuePatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     current_mod_id: &str,
     context: &SubstitutionContext,
@@ -1528,7 +1730,7 @@ fn apply_append_value_patch_direct(
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
 fn apply_append_values_patch_direct(
     patch: &AppendValuesPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     current_mod_id: &str,
     context: &SubstitutionContext,
@@ -1558,6 +1760,52 @@ fn apply_append_values_patch_direct(
     Ok(())
 }
 
+/// Apply an edit_list patch directly to resources: edits the items of a separated list value
+///
+/// # Arguments
+/// * `patch` - The edit_list patch configuration
+/// * `patch_name` - Name of the patch (for logging)
+/// * `current_mod_id` - ID of the mod applying the patch
+/// * `context` - Substitution context for variable resolution
+///
+/// # Returns
+/// * `Ok(())` if the patch was applied successfully (warning logged if the key doesn't exist)
+/// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
+fn apply_edit_list_patch_direct(
+    patch: &EditListPatch,
+    _file_map: &HashMap<String, Arc<[u8]>>,
+    patch_name: &str,
+    current_mod_id: &str,
+    context: &SubstitutionContext,
+) -> anyhow::Result<()> {
+    info!(
+        "Applying edit_list patch '{}': {} [{}] {} {:?} {} values",
+        patch_name,
+        patch.target,
+        patch.section,
+        patch.key,
+        patch.action,
+        patch.values.len()
+    );
+
+    validate_edit_list(patch)?;
+
+    // Load INI file
+    let mut ini = load_ini_from_resources(&patch.target)?;
+
+    // Resolve variables before comparing items, so removals match substituted entries
+    let values = patch.values.iter().map(|v| substitute_variables(v, context)).collect::<anyhow::Result<Vec<_>>>()?;
+    if !edit_list_in_ini(patch, &values, &mut ini, patch_name) {
+        return Ok(());
+    }
+
+    // Save back to resources
+    save_ini_to_resources(&patch.target, &ini, current_mod_id)?;
+
+    info!("Successfully applied edit_list patch '{}'", patch_name);
+    Ok(())
+}
+
 /// Apply a remove_key patch directly to resources: removes a single key from an INI section
 ///
 /// # Arguments
@@ -1568,7 +1816,7 @@ fn apply_append_values_patch_direct(
 /// # Returns
 /// * `Ok(())` if the patch was applied successfully (warnings logged if key doesn't exist)
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
-fn apply_remove_key_patch_direct(patch: &RemoveKeyPatch, _file_map: &HashMap<String, Box<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
+fn apply_remove_key_patch_direct(patch: &RemoveKeyPatch, _file_map: &HashMap<String, Arc<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
     info!(
         "Applying remove_key patch '{}': {} [{}] remove key '{}'",
         patch_name, patch.target, patch.section, patch.key
@@ -1605,7 +1853,7 @@ fn apply_remove_key_patch_direct(patch: &RemoveKeyPatch, _file_map: &HashMap<Str
 /// # Returns
 /// * `Ok(())` if the patch was applied successfully (warnings logged for missing keys)
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
-fn apply_remove_keys_patch_direct(patch: &RemoveKeysPatch, _file_map: &HashMap<String, Box<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
+fn apply_remove_keys_patch_direct(patch: &RemoveKeysPatch, _file_map: &HashMap<String, Arc<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
     info!(
         "Applying remove_keys patch '{}': {} [{}] remove {} keys",
         patch_name,
@@ -1658,7 +1906,7 @@ fn apply_remove_keys_patch_direct(patch: &RemoveKeysPatch, _file_map: &HashMap<S
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, section collision occurs with on_exists=error, or other errors
 fn apply_add_section_patch_direct(
     patch: &AddSectionPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     current_mod_id: &str,
     context: &SubstitutionContext,
@@ -1736,7 +1984,7 @@ fn apply_add_section_patch_direct(
 /// # Returns
 /// * `Ok(())` if the patch was applied successfully (warnings logged if section doesn't exist)
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
-fn apply_clear_section_patch_direct(patch: &ClearSectionPatch, _file_map: &HashMap<String, Box<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
+fn apply_clear_section_patch_direct(patch: &ClearSectionPatch, _file_map: &HashMap<String, Arc<[u8]>>, patch_name: &str, current_mod_id: &str) -> anyhow::Result<()> {
     info!("Applying clear_section patch '{}': {} [{}]", patch_name, patch.target, patch.section);
 
     // Load INI file
@@ -1770,7 +2018,7 @@ fn apply_clear_section_patch_direct(patch: &ClearSectionPatch, _file_map: &HashM
 /// * `Err(_)` if the file doesn't exist, isn't an INI file, or other errors occur
 fn apply_remove_section_patch_direct(
     patch: &RemoveSectionPatch,
-    _file_map: &HashMap<String, Box<[u8]>>,
+    _file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     current_mod_id: &str,
 ) -> anyhow::Result<()> {
@@ -1796,21 +2044,30 @@ fn apply_remove_section_patch_direct(
 
 /// Resolve a source file for patch operations
 ///
-/// Looks up source files in the file_map (from .ztd archive) with "resources/" prefix.
-/// Errors if not found in archive.
+/// Looks up source files in the file_map (from .ztd archive) with "resources/" prefix,
+/// see [`get_mod_file`]. Errors if not found in archive. A source of the form
+/// `mod:<mod_id>:<path>` is read from `resources/<path>` in another loaded mod instead,
+/// see [`shared_sources`].
 ///
 /// # Arguments
 /// * `source` - Relative path to the source file
 /// * `file_map` - HashMap of files from the .ztd archive
+/// * `current_mod_id` - The ID of the mod applying the patch
 ///
 /// # Returns
-/// * `Ok(Vec<u8>)` - File contents
+/// * `Ok(Cow<[u8]>)` - File contents, borrowed from the file map when the source is in this mod
 /// * `Err(_)` - File not found
-fn resolve_source_file(source: &str, file_map: &HashMap<String, Box<[u8]>>) -> anyhow::Result<Vec<u8>> {
+fn resolve_source_file<'a>(source: &str, file_map: &'a HashMap<String, Arc<[u8]>>, current_mod_id: &str) -> anyhow::Result<Cow<'a, [u8]>> {
+    let source = match shared_sources::parse_source(source) {
+        Some((source_mod_id, path)) if source_mod_id != current_mod_id => {
+            return shared_sources::read_source(current_mod_id, source_mod_id, path).map(Cow::Owned);
+        }
+        Some((_, path)) => path,
+        None => source,
+    };
     let archive_path = format!("resources/{}", source);
-    file_map
-        .get(&archive_path)
-        .map(|data| data.to_vec())
+    get_mod_file(file_map, &archive_path)
+        .map(Cow::Borrowed)
         .ok_or_else(|| anyhow::anyhow!("Source file '{}' not found in archive (expected as 'resources/{}')", source, source))
 }
 
@@ -1832,7 +2089,7 @@ fn resolve_source_file(source: &str, file_map: &HashMap<String, Box<[u8]>>) -> a
 /// * `Err(_)` if the patch failed
 fn apply_single_patch_direct(
     patch: &Patch,
-    file_map: &HashMap<String, Box<[u8]>>,
+    file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     current_mod_id: &str,
     context: &SubstitutionContext,
@@ -1840,12 +2097,13 @@ fn apply_single_patch_direct(
     match patch {
         Patch::Replace(p) => apply_replace_patch_direct(p, file_map, patch_name, current_mod_id),
         Patch::Merge(p) => apply_merge_patch_direct(p, file_map, patch_name, current_mod_id),
-        Patch::Delete(p) => apply_delete_patch_direct(p, patch_name),
+        Patch::Delete(p) => apply_delete_patch_direct(p, patch_name, current_mod_id),
         Patch::SetPalette(p) => apply_set_palette_patch_direct(p, patch_name),
         Patch::SetKey(p) => apply_set_key_patch_direct(p, file_map, patch_name, current_mod_id, context),
         Patch::SetKeys(p) => apply_set_keys_patch_direct(p, file_map, patch_name, current_mod_id, context),
         Patch::AppendValue(p) => apply_append_value_patch_direct(p, file_map, patch_name, current_mod_id, context),
         Patch::AppendValues(p) => apply_append_values_patch_direct(p, file_map, patch_name, current_mod_id, context),
+        Patch::EditList(p) => apply_edit_list_patch_direct(p, file_map, patch_name, current_mod_id, context),
         Patch::RemoveKey(p) => apply_remove_key_patch_direct(p, file_map, patch_name, current_mod_id),
         Patch::RemoveKeys(p) => apply_remove_keys_patch_direct(p, file_map, patch_name, current_mod_id),
         Patch::AddSection(p) => apply_add_section_patch_direct(p, file_map, patch_name, current_mod_id, context),
@@ -1869,20 +2127,21 @@ fn apply_single_patch_direct(
 /// * `Err(_)` if the patch failed
 fn apply_single_patch_shadow(
     patch: &Patch,
-    file_map: &HashMap<String, Box<[u8]>>,
+    file_map: &HashMap<String, Arc<[u8]>>,
     patch_name: &str,
     context: &SubstitutionContext,
     shadow: &mut ShadowResources,
 ) -> anyhow::Result<()> {
     match patch {
-        Patch::Replace(p) => apply_replace_patch_shadow(p, file_map, patch_name, shadow),
-        Patch::Merge(p) => apply_merge_patch_shadow(p, file_map, patch_name, shadow),
+        Patch::Replace(p) => apply_replace_patch_shadow(p, file_map, patch_name, &context.current_mod_id, shadow),
+        Patch::Merge(p) => apply_merge_patch_shadow(p, file_map, patch_name, &context.current_mod_id, shadow),
         Patch::Delete(p) => apply_delete_patch_shadow(p, patch_name, shadow),
         Patch::SetPalette(p) => apply_set_palette_patch_shadow(p, patch_name, shadow),
         Patch::SetKey(p) => apply_set_key_patch_shadow(p, file_map, patch_name, context, shadow),
         Patch::SetKeys(p) => apply_set_keys_patch_shadow(p, file_map, patch_name, context, shadow),
         Patch::AppendValue(p) => apply_append_value_patch_shadow(p, file_map, patch_name, context, shadow),
         Patch::AppendValues(p) => apply_append_values_patch_shadow(p, file_map, patch_name, context, shadow),
+        Patch::EditList(p) => apply_edit_list_patch_shadow(p, file_map, patch_name, context, shadow),
         Patch::RemoveKey(p) => apply_remove_key_patch_shadow(p, file_map, patch_name, shadow),
         Patch::RemoveKeys(p) => apply_remove_keys_patch_shadow(p, file_map, patch_name, shadow),
         Patch::AddSection(p) => apply_add_section_patch_shadow(p, file_map, patch_name, context, shadow),
@@ -1895,18 +2154,6 @@ fn apply_single_patch_shadow(
 // Phase 6: Patch Orchestration, Conditional Evaluation, and Error Handling
 // ============================================================================
 
-/// Helper function to check if a mod is loaded
-///
-/// # Arguments
-/// * `mod_id` - The mod ID to check
-///
-/// # Returns
-/// * `true` if the mod is loaded, `false` otherwise
-fn is_mod_loaded(mod_id: &str) -> bool {
-    let loaded_mods = get_mod_ids();
-    loaded_mods.iter().any(|id| id == mod_id)
-}
-
 /// Check if a ZTD was loaded before the current mod
 ///
 /// # Arguments
@@ -1964,6 +2211,18 @@ fn evaluate_patch_condition_with_target(condition: &Option<PatchCondition>, defa
         return Ok(false);
     }
 
+    // Check mod_not_loaded condition
+    if let Some(excluded_mod) = &cond.mod_not_loaded && is_mod_loaded(excluded_mod) {
+        info!("Patch '{}': skipping - mod '{}' is loaded", patch_name, excluded_mod);
+        return Ok(false);
+    }
+
+    // Check file_exists condition
+    if let Some(required_file) = &cond.file_exists && !check_file(required_file) {
+        info!("Patch '{}': skipping - required file '{}' not found", patch_name, required_file);
+        return Ok(false);
+    }
+
     // Check ztd_loaded condition
     if let Some(required_ztd) = &cond.ztd_loaded && !is_ztd_loaded_before_current(required_ztd, current_mod_id) {
         info!("Patch '{}': skipping - required ZTD '{}' not loaded before current mod", patch_name, required_ztd);
@@ -2049,6 +2308,7 @@ fn get_patch_target(patch: &Patch) -> &str {
         Patch::SetKeys(p) => &p.target,
         Patch::AppendValue(p) => &p.target,
         Patch::AppendValues(p) => &p.target,
+        Patch::EditList(p) => &p.target,
         Patch::RemoveKey(p) => &p.target,
         Patch::RemoveKeys(p) => &p.target,
         Patch::AddSection(p) => &p.target,
@@ -2068,6 +2328,7 @@ fn get_patch_condition(patch: &Patch) -> &Option<PatchCondition> {
         Patch::SetKeys(p) => &p.condition,
         Patch::AppendValue(p) => &p.condition,
         Patch::AppendValues(p) => &p.condition,
+        Patch::EditList(p) => &p.condition,
         Patch::RemoveKey(p) => &p.condition,
         Patch::RemoveKeys(p) => &p.condition,
         Patch::AddSection(p) => &p.condition,
@@ -2076,6 +2337,257 @@ fn get_patch_condition(patch: &Patch) -> &Option<PatchCondition> {
     }
 }
 
+/// Set the target file path of a patch (for expanding glob targets)
+fn set_patch_target(patch: &mut Patch, target: String) {
+    match patch {
+        Patch::Replace(p) => p.target = target,
+        Patch::Merge(p) => p.target = target,
+        Patch::Delete(p) => p.target = target,
+        Patch::SetPalette(p) => p.target = target,
+        Patch::SetKey(p) => p.target = target,
+        Patch::SetKeys(p) => p.target = target,
+        Patch::AppendValue(p) => p.target = target,
+        Patch::AppendValues(p) => p.target = target,
+        Patch::EditList(p) => p.target = target,
+        Patch::RemoveKey(p) => p.target = target,
+        Patch::RemoveKeys(p) => p.target = target,
+        Patch::AddSection(p) => p.target = target,
+        Patch::ClearSection(p) => p.target = target,
+        Patch::RemoveSection(p) => p.target = target,
+    }
+}
+
+/// Check whether a patch target is a glob pattern
+fn is_glob_target(target: &str) -> bool {
+    target.contains(['*', '?'])
+}
+
+/// Match a resource path against a glob pattern, ignoring case
+///
+/// `*` matches any run of characters and `?` a single character, neither crossing a `/`,
+/// so `animals/*.ai` matches `animals/elephant.ai` but not `animals/elephant/n.ai`.
+pub(crate) fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let path: Vec<char> = path.to_lowercase().chars().collect();
+
+    let (mut p, mut s) = (0, 0);
+    // Position of the last `*` in the pattern and how far into the path it currently reaches
+    let mut star: Option<(usize, usize)> = None;
+
+    while s < path.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, s));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == path[s] || (pattern[p] == '?' && path[s] != '/')) {
+            p += 1;
+            s += 1;
+        } else if let Some((star_p, star_s)) = star
+            && path[star_s] != '/'
+        {
+            // Let the last `*` swallow one more character and retry
+            star = Some((star_p, star_s + 1));
+            p = star_p + 1;
+            s = star_s + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Expand patches with glob targets into one patch per matching file in the resource map
+///
+/// Expanded patches are named `name[path]`, so failures and skipped conditions are
+/// reported per file. Patches with a plain target are kept as they are.
+fn expand_glob_targets(patches: &indexmap::IndexMap<String, Patch>) -> Cow<'_, indexmap::IndexMap<String, Patch>> {
+    if !patches.values().any(|patch| is_glob_target(get_patch_target(patch))) {
+        return Cow::Borrowed(patches);
+    }
+
+    let mut file_names = get_file_names();
+    file_names.sort();
+
+    let mut expanded = indexmap::IndexMap::new();
+    for (patch_name, patch) in patches {
+        let target = get_patch_target(patch);
+        if !is_glob_target(target) {
+            expanded.insert(patch_name.clone(), patch.clone());
+            continue;
+        }
+
+        let matched: Vec<&String> = file_names.iter().filter(|file_name| glob_matches(target, file_name)).collect();
+        if matched.is_empty() {
+            warn!("Patch '{}': target '{}' matched no files, skipping", patch_name, target);
+            continue;
+        }
+        info!("Patch '{}': target '{}' matched {} files", patch_name, target, matched.len());
+
+        for file_name in matched {
+            let mut file_patch = patch.clone();
+            set_patch_target(&mut file_patch, file_name.clone());
+            expanded.insert(format!("{}[{}]", patch_name, file_name), file_patch);
+        }
+    }
+
+    Cow::Owned(expanded)
+}
+
+/// INI keys a patch sets or removes, as tracked for cross-mod conflicts
+///
+/// Merges and replacements of INI files write every key in their source, except that a
+/// `base_priority` merge leaves the keys `target_ini` already has. Appends and list edits
+/// are meant to combine with other mods, and whole-section patches are not tracked per key.
+fn written_keys(patch: &Patch, file_map: &HashMap<String, Arc<[u8]>>, current_mod_id: &str, target_ini: impl FnOnce(&str) -> Option<Ini>) -> Vec<IniKey> {
+    match patch {
+        Patch::SetKey(p) => vec![(p.section.clone(), p.key.clone())],
+        Patch::SetKeys(p) => p.keys.keys().map(|key| (p.section.clone(), key.clone())).collect(),
+        Patch::RemoveKey(p) => vec![(p.section.clone(), p.key.clone())],
+        Patch::RemoveKeys(p) => p.keys.iter().map(|key| (p.section.clone(), key.clone())).collect(),
+        Patch::AddSection(p) => p.keys.keys().map(|key| (p.section.clone(), key.clone())).collect(),
+        Patch::Merge(p) if p.merge_mode == MergeMode::BasePriority => {
+            let keys = source_keys(&p.source, file_map, current_mod_id);
+            match target_ini(&p.target) {
+                Some(target) => keys.into_iter().filter(|(section, key)| ini_value(&target, section, key).is_none()).collect(),
+                None => keys,
+            }
+        }
+        Patch::Merge(p) => source_keys(&p.source, file_map, current_mod_id),
+        Patch::Replace(p) if is_valid_ini_extension(Path::new(&p.target)) => source_keys(&p.source, file_map, current_mod_id),
+        _ => Vec::new(),
+    }
+}
+
+/// Every key in a patch's source INI file
+///
+/// Empty if the source can't be read or parsed, which the patch reports when it is applied.
+fn source_keys(source: &str, file_map: &HashMap<String, Arc<[u8]>>, current_mod_id: &str) -> Vec<IniKey> {
+    let Ok(data) = resolve_source_file(source, file_map, current_mod_id) else {
+        return Vec::new();
+    };
+    let mut ini = Ini::new_cs();
+    ini.set_comment_symbols(&[';', '#', ':']);
+    if ini.read(crate::encoding_utils::decode_game_text(&data)).is_err() {
+        return Vec::new();
+    }
+    ini.get_map_ref()
+        .iter()
+        .flat_map(|(section, keys)| keys.keys().map(move |key| (section.clone(), key.clone())))
+        .collect()
+}
+
+/// Values of a key in an INI file, `None` if the file doesn't have the key
+fn ini_value<'a>(ini: &'a Ini, section: &str, key: &str) -> Option<&'a Option<Vec<String>>> {
+    ini.get_map_ref().get(section).and_then(|keys| keys.get(key))
+}
+
+/// A replacement INI file with the `kept_keys` of its patch left at their values in `target`
+///
+/// Keys the target doesn't have are removed from the replacement.
+fn with_kept_keys(content: String, target: &Ini, kept_keys: &[IniKey]) -> anyhow::Result<String> {
+    let mut ini = Ini::new_cs();
+    ini.set_comment_symbols(&[';', '#', ':']);
+    ini.read(content).map_err(|e| anyhow::anyhow!("Failed to parse source INI: {}", e))?;
+    for (section, key) in kept_keys {
+        match ini_value(target, section, key) {
+            Some(values) => {
+                ini.get_mut_map().entry(section.clone()).or_default().insert(key.clone(), values.clone());
+            }
+            None => {
+                ini.remove_key(section, key);
+            }
+        }
+    }
+    Ok(ini.writes())
+}
+
+/// Priority of the keys a patch writes, `None` to use `[patch_meta] priority`
+fn get_patch_priority(patch: &Patch) -> Option<i32> {
+    match patch {
+        Patch::Replace(p) => p.priority,
+        Patch::Merge(p) => p.priority,
+        Patch::SetKey(p) => p.priority,
+        Patch::SetKeys(p) => p.priority,
+        Patch::RemoveKey(p) => p.priority,
+        Patch::RemoveKeys(p) => p.priority,
+        Patch::AddSection(p) => p.priority,
+        _ => None,
+    }
+}
+
+/// Copy of a patch without the blocked keys, or `None` if nothing is left to apply
+fn without_keys(patch: &Patch, blocked: &[IniKey]) -> Option<Patch> {
+    let is_blocked = |section: &str, key: &str| blocked.iter().any(|(s, k)| s == section && k == key);
+
+    let mut patch = patch.clone();
+    let remaining = match &mut patch {
+        Patch::SetKey(_) | Patch::RemoveKey(_) => false,
+        Patch::SetKeys(p) => {
+            p.keys.retain(|key, _| !is_blocked(&p.section, key));
+            !p.keys.is_empty()
+        }
+        Patch::RemoveKeys(p) => {
+            p.keys.retain(|key| !is_blocked(&p.section, key));
+            !p.keys.is_empty()
+        }
+        // The section itself is still added
+        Patch::AddSection(p) => {
+            p.keys.retain(|key, _| !is_blocked(&p.section, key));
+            true
+        }
+        // The rest of the source is still written
+        Patch::Merge(p) => {
+            p.kept_keys = blocked.to_vec();
+            true
+        }
+        Patch::Replace(p) => {
+            p.kept_keys = blocked.to_vec();
+            true
+        }
+        _ => true,
+    };
+    remaining.then_some(patch)
+}
+
+/// Claim the INI keys a patch writes against patches from other mods
+///
+/// A patch's own `priority` takes precedence over `default_priority` from `[patch_meta]`.
+/// `target_ini` reads the patch's target as it is before the patch.
+///
+/// # Returns
+/// * `Ok(Some(patch))` - the patch to apply, without keys owned by a higher-priority mod
+/// * `Ok(None)` - every key the patch writes is owned by a higher-priority mod
+/// * `Err(_)` - the patch conflicts with another mod and `patch_conflict_policy` is `error`
+fn claim_patch_keys<'a>(
+    patch: &'a Patch,
+    patch_name: &str,
+    file_map: &HashMap<String, Arc<[u8]>>,
+    current_mod_id: &str,
+    default_priority: i32,
+    target_ini: impl FnOnce(&str) -> Option<Ini>,
+) -> anyhow::Result<Option<Cow<'a, Patch>>> {
+    let keys = written_keys(patch, file_map, current_mod_id, target_ini);
+    if keys.is_empty() {
+        return Ok(Some(Cow::Borrowed(patch)));
+    }
+
+    let write = KeyWrite {
+        mod_id: current_mod_id.to_string(),
+        patch_name: patch_name.to_string(),
+        priority: get_patch_priority(patch).unwrap_or(default_priority),
+    };
+    let blocked = patch_conflicts::claim(get_patch_target(patch), &keys, write)?;
+    if blocked.is_empty() {
+        return Ok(Some(Cow::Borrowed(patch)));
+    }
+
+    let patch = without_keys(patch, &blocked);
+    if patch.is_none() {
+        info!("Patch '{}': skipping (keys owned by a higher-priority mod)", patch_name);
+    }
+    Ok(patch.map(Cow::Owned))
+}
+
 /// Result of applying a single patch
 #[derive(Debug, Clone, PartialEq)]
 enum PatchResult {
@@ -2084,6 +2596,63 @@ enum PatchResult {
     Error(String), // Error occurred
 }
 
+/// Evaluate a patch file's top-level condition
+///
+/// # Returns
+/// * `Ok(true)` if there is no condition or it passes
+/// * `Ok(false)` if the patch file should be skipped
+/// * `Err(_)` if key_exists/value_equals is used without a target
+fn evaluate_top_level_condition(patch_meta: &PatchMeta, current_mod_id: &str) -> anyhow::Result<bool> {
+    let Some(top_level_condition) = &patch_meta.condition else {
+        return Ok(true);
+    };
+
+    // Check mod_loaded at file level
+    if let Some(required_mod) = &top_level_condition.mod_loaded && !is_mod_loaded(required_mod) {
+        warn!("Patch file skipped - required mod '{}' not loaded", required_mod);
+        return Ok(false);
+    }
+
+    // Check mod_not_loaded at file level
+    if let Some(excluded_mod) = &top_level_condition.mod_not_loaded && is_mod_loaded(excluded_mod) {
+        warn!("Patch file skipped - mod '{}' is loaded", excluded_mod);
+        return Ok(false);
+    }
+
+    // Check file_exists at file level
+    if let Some(required_file) = &top_level_condition.file_exists && !check_file(required_file) {
+        warn!("Patch file skipped - required file '{}' not found", required_file);
+        return Ok(false);
+    }
+
+    // Check ztd_loaded at file level
+    if let Some(required_ztd) = &top_level_condition.ztd_loaded && !is_ztd_loaded_before_current(required_ztd, current_mod_id) {
+        warn!("Patch file skipped - required ZTD '{}' not loaded before current mod", required_ztd);
+        return Ok(false);
+    }
+
+    // Check entity_exists at file level
+    if let Some(entity_id) = &top_level_condition.entity_exists && !crate::resource_manager::openzt_mods::entity_lookup::entity_exists(entity_id) {
+        warn!("Patch file skipped - required legacy entity '{}' not loaded", entity_id);
+        return Ok(false);
+    }
+
+    // Check key_exists and value_equals with target
+    if top_level_condition.key_exists.is_some() || top_level_condition.value_equals.is_some() {
+        let Some(target) = &top_level_condition.target else {
+            return Err(anyhow::anyhow!("Top-level condition with key_exists/value_equals requires 'target' field"));
+        };
+
+        // Use existing evaluation function with target
+        if !evaluate_patch_condition_with_target(&Some(top_level_condition.clone()), target, "top-level", current_mod_id)? {
+            warn!("Patch file skipped - top-level conditions failed");
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
 /// Apply patches directly without shadow (continue mode)
 ///
 /// In this mode, patches are applied directly to the resource system.
@@ -2100,7 +2669,7 @@ enum PatchResult {
 fn apply_patches_direct(
     patch_meta: &PatchMeta,
     patches: &indexmap::IndexMap<String, Patch>,
-    file_map: &HashMap<String, Box<[u8]>>,
+    file_map: &HashMap<String, Arc<[u8]>>,
     current_mod_id: &str,
 ) -> anyhow::Result<()> {
     // Create substitution context for variable resolution
@@ -2111,37 +2680,8 @@ fn apply_patches_direct(
     info!("Applying patch file with {} patches (on_error: continue)", patches.len());
 
     // Evaluate top-level conditions
-    if let Some(top_level_condition) = &patch_meta.condition {
-        // Check mod_loaded at file level
-        if let Some(required_mod) = &top_level_condition.mod_loaded && !is_mod_loaded(required_mod) {
-            warn!("Patch file skipped - required mod '{}' not loaded", required_mod);
-            return Ok(());
-        }
-
-        // Check ztd_loaded at file level
-        if let Some(required_ztd) = &top_level_condition.ztd_loaded && !is_ztd_loaded_before_current(required_ztd, current_mod_id) {
-            warn!("Patch file skipped - required ZTD '{}' not loaded before current mod", required_ztd);
-            return Ok(());
-        }
-
-        // Check entity_exists at file level
-        if let Some(entity_id) = &top_level_condition.entity_exists && !crate::resource_manager::openzt_mods::entity_lookup::entity_exists(entity_id) {
-            warn!("Patch file skipped - required legacy entity '{}' not loaded", entity_id);
-            return Ok(());
-        }
-
-        // Check key_exists and value_equals with target
-        if top_level_condition.key_exists.is_some() || top_level_condition.value_equals.is_some() {
-            let Some(target) = &top_level_condition.target else {
-                return Err(anyhow::anyhow!("Top-level condition with key_exists/value_equals requires 'target' field"));
-            };
-
-            // Use existing evaluation function with target
-            if !evaluate_patch_condition_with_target(&Some(top_level_condition.clone()), target, "top-level", current_mod_id)? {
-                warn!("Patch file skipped - top-level conditions failed");
-                return Ok(());
-            }
-        }
+    if !evaluate_top_level_condition(patch_meta, current_mod_id)? {
+        return Ok(());
     }
 
     // Apply patches in order
@@ -2154,11 +2694,16 @@ fn apply_patches_direct(
 
         match evaluate_patch_condition_with_target(condition, target, patch_name, current_mod_id) {
             Ok(true) => {
-                // Condition passed, apply patch
-                let result = apply_single_patch_direct(patch, file_map, patch_name, current_mod_id, &context);
-
-                if let Err(e) = result {
-                    error!("Patch '{}' failed: {}. Continuing.", patch_name, e);
+                // Condition passed, apply patch without keys owned by higher-priority mods
+                let result = claim_patch_keys(patch, patch_name, file_map, current_mod_id, patch_meta.priority, |target| load_ini_from_resources(target).ok())
+                    .and_then(|claimed| claimed.map_or(Ok(()), |patch| apply_single_patch_direct(&patch, file_map, patch_name, current_mod_id, &context)));
+
+                match result {
+                    Ok(()) => file_overrides::add_patch(target, current_mod_id, patch_name),
+                    Err(e) => {
+                        error!("Patch '{}' failed: {}. Continuing.", patch_name, e);
+                        load_report::record_error(current_mod_id, LoadStage::Patch, format!("Patch '{}' failed: {}", patch_name, e));
+                    }
                 }
             }
             Ok(false) => {
@@ -2168,6 +2713,7 @@ fn apply_patches_direct(
             Err(e) => {
                 // Error evaluating condition
                 error!("Patch '{}': error evaluating condition: {}. Continuing.", patch_name, e);
+                load_report::record_error(current_mod_id, LoadStage::Patch, format!("Patch '{}': error evaluating condition: {}", patch_name, e));
             }
         }
     }
@@ -2194,7 +2740,7 @@ fn apply_patches_direct(
 fn apply_patches_with_shadow(
     patch_meta: &PatchMeta,
     patches: &indexmap::IndexMap<String, Patch>,
-    file_map: &HashMap<String, Box<[u8]>>,
+    file_map: &HashMap<String, Arc<[u8]>>,
     current_mod_id: &str,
 ) -> anyhow::Result<()> {
     // Create substitution context for variable resolution
@@ -2205,37 +2751,8 @@ fn apply_patches_with_shadow(
     info!("Applying patch file with {} patches (on_error: {:?})", patches.len(), patch_meta.on_error);
 
     // Evaluate top-level conditions
-    if let Some(top_level_condition) = &patch_meta.condition {
-        // Check mod_loaded at file level
-        if let Some(required_mod) = &top_level_condition.mod_loaded && !is_mod_loaded(required_mod) {
-            warn!("Patch file skipped - required mod '{}' not loaded", required_mod);
-            return Ok(());
-        }
-
-        // Check ztd_loaded at file level
-        if let Some(required_ztd) = &top_level_condition.ztd_loaded && !is_ztd_loaded_before_current(required_ztd, current_mod_id) {
-            warn!("Patch file skipped - required ZTD '{}' not loaded before current mod", required_ztd);
-            return Ok(());
-        }
-
-        // Check entity_exists at file level
-        if let Some(entity_id) = &top_level_condition.entity_exists && !crate::resource_manager::openzt_mods::entity_lookup::entity_exists(entity_id) {
-            warn!("Patch file skipped - required legacy entity '{}' not loaded", entity_id);
-            return Ok(());
-        }
-
-        // Check key_exists and value_equals with target
-        if top_level_condition.key_exists.is_some() || top_level_condition.value_equals.is_some() {
-            let Some(target) = &top_level_condition.target else {
-                return Err(anyhow::anyhow!("Top-level condition with key_exists/value_equals requires 'target' field"));
-            };
-
-            // Use existing evaluation function with target
-            if !evaluate_patch_condition_with_target(&Some(top_level_condition.clone()), target, "top-level", current_mod_id)? {
-                warn!("Patch file skipped - top-level conditions failed");
-                return Ok(());
-            }
-        }
+    if !evaluate_top_level_condition(patch_meta, current_mod_id)? {
+        return Ok(());
     }
 
     // Collect affected files and create shadow
@@ -2249,6 +2766,11 @@ fn apply_patches_with_shadow(
 
     let mut shadow = ShadowResources::new(&affected_files, scope)?;
 
+    // Key claims made by these patches are released if the shadow is discarded
+    let patch_names: Vec<&str> = patches.keys().map(String::as_str).collect();
+    // Patches applied to the shadow, recorded once it is committed
+    let mut applied = Vec::new();
+
     // Apply patches to shadow
     for (patch_name, patch) in patches {
         info!("Processing patch '{}'", patch_name);
@@ -2259,14 +2781,19 @@ fn apply_patches_with_shadow(
 
         match evaluate_patch_condition_with_target(condition, target, patch_name, current_mod_id) {
             Ok(true) => {
-                // Condition passed, apply patch to shadow
-                let result = apply_single_patch_shadow(patch, file_map, patch_name, &context, &mut shadow);
+                // Condition passed, apply patch to shadow without keys owned by higher-priority mods
+                let result = claim_patch_keys(patch, patch_name, file_map, current_mod_id, patch_meta.priority, |target| {
+                    load_ini_from_shadow(target, &shadow).ok()
+                })
+                .and_then(|claimed| claimed.map_or(Ok(()), |patch| apply_single_patch_shadow(&patch, file_map, patch_name, &context, &mut shadow)));
 
                 if let Err(e) = result {
                     error!("Patch '{}' failed: {}. Rolling back.", patch_name, e);
                     shadow.discard();
+                    patch_conflicts::release(current_mod_id, &patch_names);
                     return Err(e);
                 }
+                applied.push((target, patch_name));
             }
             Ok(false) => {
                 // Condition failed, skip patch
@@ -2276,6 +2803,7 @@ fn apply_patches_with_shadow(
                 // Error evaluating condition
                 error!("Patch '{}': error evaluating condition: {}. Rolling back.", patch_name, e);
                 shadow.discard();
+                patch_conflicts::release(current_mod_id, &patch_names);
                 return Err(e);
             }
         }
@@ -2283,16 +2811,89 @@ fn apply_patches_with_shadow(
 
     // All patches succeeded - commit shadow to main resources
     shadow.commit()?;
+    for (target, patch_name) in applied {
+        file_overrides::add_patch(target, current_mod_id, patch_name);
+    }
 
     info!("All patches applied successfully and committed");
     Ok(())
 }
 
+/// Apply patches to the shared dry-run shadow, recording each change instead of committing
+///
+/// Every patch is attempted regardless of on_error, so one run reports all failures.
+/// Conditions are still evaluated against the live resources.
+///
+/// # Arguments
+/// * `patch_meta` - Patch metadata containing file-level conditions
+/// * `patches` - Ordered map of patches to apply (order is preserved via IndexMap)
+/// * `file_map` - HashMap of files from the .ztd archive
+/// * `current_mod_id` - The ID of the current mod (for variable substitution)
+///
+/// # Returns
+/// * `Ok(())` unless the top-level condition is invalid or the shadow can't be created
+fn apply_patches_dry_run(
+    patch_meta: &PatchMeta,
+    patches: &indexmap::IndexMap<String, Patch>,
+    file_map: &HashMap<String, Arc<[u8]>>,
+    current_mod_id: &str,
+) -> anyhow::Result<()> {
+    let context = SubstitutionContext {
+        current_mod_id: current_mod_id.to_string(),
+    };
+
+    info!("Dry run: applying patch file with {} patches to shadow", patches.len());
+
+    // Evaluate top-level conditions
+    if !evaluate_top_level_condition(patch_meta, current_mod_id)? {
+        return Ok(());
+    }
+
+    let changes = patch_dry_run::with_shadow(|shadow| {
+        let snapshot = |shadow: &ShadowResources, target: &str| shadow.file_exists(target).then(|| shadow.get_file(target)).flatten();
+
+        patches
+            .iter()
+            .map(|(patch_name, patch)| {
+                let target = get_patch_target(patch);
+                let before = snapshot(shadow, target);
+
+                let (kind, keys) = match evaluate_patch_condition_with_target(get_patch_condition(patch), target, patch_name, current_mod_id) {
+                    Ok(true) => match claim_patch_keys(patch, patch_name, file_map, current_mod_id, patch_meta.priority, |target| {
+                        load_ini_from_shadow(target, shadow).ok()
+                    })
+                        .and_then(|claimed| claimed.map_or(Ok(()), |patch| apply_single_patch_shadow(&patch, file_map, patch_name, &context, shadow)))
+                    {
+                        Ok(()) => patch_dry_run::compare(before.as_ref(), snapshot(shadow, target).as_ref()),
+                        Err(e) => (ChangeKind::Failed(e.to_string()), Vec::new()),
+                    },
+                    Ok(false) => (ChangeKind::Skipped, Vec::new()),
+                    Err(e) => (ChangeKind::Failed(format!("error evaluating condition: {}", e)), Vec::new()),
+                };
+
+                PatchChange {
+                    mod_id: current_mod_id.to_string(),
+                    patch_name: patch_name.clone(),
+                    target: target.to_string(),
+                    kind,
+                    keys,
+                }
+            })
+            .collect()
+    })?;
+
+    patch_dry_run::record(changes);
+    Ok(())
+}
+
 /// Apply all patches with error handling and conditional evaluation
 ///
 /// This is the main entry point for patch application. It routes to either
 /// direct mode (continue) or shadow mode (abort/abort_mod) based on the
-/// error handling strategy specified in patch_meta.
+/// error handling strategy specified in patch_meta. Patches whose target is a
+/// glob pattern are first expanded to every matching file in the resource map.
+/// With `[dev] patch_dry_run` enabled, patches are only recorded in a dry-run report.
+/// Files changed by the hot-reloaded dev mod are snapshotted before its patches apply.
 ///
 /// # Arguments
 /// * `patch_meta` - Patch metadata containing error handling and file-level conditions
@@ -2306,18 +2907,28 @@ fn apply_patches_with_shadow(
 pub fn apply_patches(
     patch_meta: &PatchMeta,
     patches: &indexmap::IndexMap<String, Patch>,
-    file_map: &HashMap<String, Box<[u8]>>,
+    file_map: &HashMap<String, Arc<[u8]>>,
     current_mod_id: &str,
 ) -> anyhow::Result<()> {
+    // Glob targets (e.g. "animals/*.ai") become one patch per matching file
+    let patches = expand_glob_targets(patches);
+
+    if patch_dry_run::is_enabled() {
+        return apply_patches_dry_run(patch_meta, &patches, file_map, current_mod_id);
+    }
+
+    // Keep the dev mod's original files so it can be unloaded for a hot reload
+    hot_reload::snapshot_before_patches(current_mod_id, &collect_affected_files(&patches))?;
+
     // Route based on error handling mode
     match patch_meta.on_error {
         ErrorHandling::Continue => {
             // Direct mode - no shadow, patches applied directly
-            apply_patches_direct(patch_meta, patches, file_map, current_mod_id)
+            apply_patches_direct(patch_meta, &patches, file_map, current_mod_id)
         }
         ErrorHandling::Abort | ErrorHandling::AbortMod => {
             // Shadow mode - patches applied to shadow, committed on success
-            apply_patches_with_shadow(patch_meta, patches, file_map, current_mod_id)
+            apply_patches_with_shadow(patch_meta, &patches, file_map, current_mod_id)
         }
     }
 }
@@ -2354,6 +2965,19 @@ mod tests {
         assert_eq!(result.identifier, "9500");
     }
 
+    #[test]
+    fn test_parse_variable_settings() {
+        let result = parse_variable("settings.litter_size").unwrap();
+        assert_eq!(result.var_type, VariableType::Settings);
+        assert_eq!(result.mod_id, None);
+        assert_eq!(result.identifier, "litter_size");
+
+        let result = parse_variable("lunar.settings.hard_mode").unwrap();
+        assert_eq!(result.var_type, VariableType::Settings);
+        assert_eq!(result.mod_id, Some("lunar".to_string()));
+        assert_eq!(result.identifier, "hard_mode");
+    }
+
     #[test]
     fn test_parse_variable_cross_mod_habitat() {
         let result = parse_variable("lunar.habitats.crater").unwrap();
@@ -2482,6 +3106,7 @@ mod tests {
                 key: "Key".to_string(),
                 value: "Value".to_string(),
                 condition: None,
+                priority: None,
             }),
         );
         patches.insert(
@@ -2492,12 +3117,16 @@ mod tests {
                 key: "Key2".to_string(),
                 value: "Value2".to_string(),
                 condition: None,
+                priority: None,
             }),
         );
         patches.insert(
             "patch3".to_string(),
             Patch::Delete(DeletePatch {
                 target: "file2.ini".to_string(),
+                section: None,
+                keys: Vec::new(),
+                sections: Vec::new(),
                 condition: None,
             }),
         );
@@ -2510,6 +3139,183 @@ mod tests {
         assert!(affected.contains("file2.ini"), "Should contain file2.ini");
     }
 
+    #[test]
+    fn test_edit_list_value() {
+        let values = |items: &[&str]| items.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+
+        // The spacing after the separator is kept
+        assert_eq!(edit_list_value("a, b", ListAction::Append, &values(&["c"]), ","), "a, b, c");
+        assert_eq!(edit_list_value("a, b", ListAction::Prepend, &values(&["x", "y"]), ","), "x, y, a, b");
+        assert_eq!(edit_list_value("a;b;a;c", ListAction::Remove, &values(&["a"]), ";"), "b;c");
+        assert_eq!(edit_list_value("a,b,a,c,b", ListAction::Dedupe, &[], ","), "a,b,c");
+        // Appending to an empty value doesn't leave a leading separator
+        assert_eq!(edit_list_value("", ListAction::Append, &values(&["a"]), ","), "a");
+    }
+
+    #[test]
+    fn test_edit_list_in_ini() {
+        let mut ini = Ini::new_cs();
+        ini.read("[Language]\nAvailable = english, french\n".to_string()).unwrap();
+
+        let mut patch = EditListPatch {
+            target: "config/settings.ini".to_string(),
+            section: "Language".to_string(),
+            key: "Available".to_string(),
+            action: ListAction::Append,
+            values: Vec::new(),
+            separator: ",".to_string(),
+            condition: None,
+        };
+        assert!(edit_list_in_ini(&patch, &["klingon".to_string()], &mut ini, "test"));
+        assert_eq!(ini.get("Language", "Available"), Some("english, french, klingon".to_string()));
+
+        // Missing keys are created by append but skipped by remove
+        patch.key = "Missing".to_string();
+        patch.action = ListAction::Remove;
+        assert!(!edit_list_in_ini(&patch, &["a".to_string()], &mut ini, "test"));
+        assert_eq!(ini.get("Language", "Missing"), None);
+        patch.action = ListAction::Append;
+        assert!(edit_list_in_ini(&patch, &["a".to_string()], &mut ini, "test"));
+        assert_eq!(ini.get("Language", "Missing"), Some("a".to_string()));
+
+        assert!(validate_edit_list(&patch).is_ok());
+        patch.separator = String::new();
+        assert!(validate_edit_list(&patch).is_err());
+    }
+
+    #[test]
+    fn test_written_keys_of_merge_and_replace() {
+        let mut file_map: HashMap<String, Arc<[u8]>> = HashMap::new();
+        file_map.insert("resources/source.ini".to_string(), b"[A]\nx = 1\ny = 2\n[B]\nz = 3\n".to_vec().into());
+        let mut target = Ini::new_cs();
+        target.read("[A]\nx = 0\n".to_string()).unwrap();
+        let keys = |items: &[(&str, &str)]| items.iter().map(|(s, k)| (s.to_string(), k.to_string())).collect::<Vec<_>>();
+
+        let mut merge = MergePatch {
+            target: "animals/elephant.ai".to_string(),
+            source: "source.ini".to_string(),
+            merge_mode: MergeMode::PatchPriority,
+            duplicate_keys: DuplicateKeyMode::ReplaceAll,
+            condition: None,
+            priority: None,
+            kept_keys: Vec::new(),
+        };
+        let written = |patch: &Patch| written_keys(patch, &file_map, "test_mod", |_| Some(target.clone()));
+        assert_eq!(written(&Patch::Merge(merge.clone())), keys(&[("A", "x"), ("A", "y"), ("B", "z")]));
+
+        // A base_priority merge leaves keys the target already has
+        merge.merge_mode = MergeMode::BasePriority;
+        assert_eq!(written(&Patch::Merge(merge)), keys(&[("A", "y"), ("B", "z")]));
+
+        // Replacing a file that isn't INI writes no keys
+        let mut replace = ReplacePatch {
+            target: "config/settings.ini".to_string(),
+            source: "source.ini".to_string(),
+            condition: None,
+            priority: None,
+            kept_keys: Vec::new(),
+        };
+        assert_eq!(written(&Patch::Replace(replace.clone())), keys(&[("A", "x"), ("A", "y"), ("B", "z")]));
+        replace.target = "objects/tree/idle/n".to_string();
+        assert!(written(&Patch::Replace(replace)).is_empty());
+    }
+
+    #[test]
+    fn test_with_kept_keys() {
+        let mut target = Ini::new_cs();
+        target.read("[A]\nx = kept\n".to_string()).unwrap();
+
+        let content = with_kept_keys("[A]\nx = new\ny = new\n".to_string(), &target, &[("A".to_string(), "x".to_string()), ("A".to_string(), "y".to_string())]).unwrap();
+        let mut ini = Ini::new_cs();
+        ini.read(content).unwrap();
+        assert_eq!(ini.get("A", "x"), Some("kept".to_string()));
+        // The target doesn't have y, so the replacement doesn't either
+        assert_eq!(ini.get("A", "y"), None);
+    }
+
+    #[test]
+    fn test_delete_ini_entries() {
+        let mut ini = Ini::new_cs();
+        ini.read("[Keep]\nA = 1\nB = 2\n[Drop]\nC = 3\n[AlsoDrop]\nD = 4\n".to_string()).unwrap();
+
+        let patch = DeletePatch {
+            target: "animals/test.ai".to_string(),
+            section: Some("Keep".to_string()),
+            keys: vec!["B".to_string(), "Missing".to_string()],
+            sections: vec!["Drop".to_string(), "AlsoDrop".to_string()],
+            condition: None,
+        };
+        assert!(!patch.deletes_file());
+        assert!(validate_delete_entries(&patch).is_ok());
+
+        // Missing key is skipped rather than counted
+        assert_eq!(delete_ini_entries(&patch, &mut ini, "test"), 3);
+        assert_eq!(ini.get("Keep", "A"), Some("1".to_string()));
+        assert_eq!(ini.get("Keep", "B"), None);
+        assert!(!ini.has_section("Drop"));
+        assert!(!ini.has_section("AlsoDrop"));
+
+        // A lone section removes the whole section
+        let patch = DeletePatch {
+            sections: Vec::new(),
+            keys: Vec::new(),
+            ..patch
+        };
+        assert_eq!(delete_ini_entries(&patch, &mut ini, "test"), 1);
+        assert!(!ini.has_section("Keep"));
+    }
+
+    #[test]
+    fn test_validate_delete_entries() {
+        let keys_without_section = DeletePatch {
+            target: "animals/test.ai".to_string(),
+            section: None,
+            keys: vec!["A".to_string()],
+            sections: Vec::new(),
+            condition: None,
+        };
+        assert!(validate_delete_entries(&keys_without_section).is_err());
+
+        let non_ini_target = DeletePatch {
+            target: "animals/test/n".to_string(),
+            section: None,
+            keys: Vec::new(),
+            sections: vec!["Drop".to_string()],
+            condition: None,
+        };
+        assert!(validate_delete_entries(&non_ini_target).is_err());
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("animals/*.ai", "animals/elephant.ai"));
+        assert!(glob_matches("animals/*.ai", "Animals/Elephant.AI"));
+        assert!(glob_matches("animals/?lephant.ai", "animals/elephant.ai"));
+        assert!(glob_matches("animals/*/*.ai", "animals/elephant/adult.ai"));
+        assert!(glob_matches("*e*p*.ai", "elephant.ai"));
+        assert!(!glob_matches("animals/*.ai", "animals/elephant/adult.ai"));
+        assert!(!glob_matches("animals/*.ai", "animals/elephant.cfg"));
+        assert!(!glob_matches("animals/*.ai", "scenery/animals/elephant.ai"));
+        assert!(!glob_matches("animals?elephant.ai", "animals/elephant.ai"));
+    }
+
+    #[test]
+    fn test_set_patch_target() {
+        let mut patch = Patch::SetKey(SetKeyPatch {
+            target: "animals/*.ai".to_string(),
+            section: "Section".to_string(),
+            key: "Key".to_string(),
+            value: "Value".to_string(),
+            condition: None,
+            priority: None,
+        });
+        assert!(is_glob_target(get_patch_target(&patch)));
+
+        set_patch_target(&mut patch, "animals/elephant.ai".to_string());
+        assert_eq!(get_patch_target(&patch), "animals/elephant.ai");
+        assert!(!is_glob_target(get_patch_target(&patch)));
+    }
+
     #[test]
     fn test_shadow_resources_update_file() {
         // Create shadow
@@ -0,0 +1,506 @@
+//! Lua scripts shipped by OpenZT mods
+//!
+//! Every `.lua` file in a mod's `scripts/` directory is run once, in file name order,
+//! after the mod's definitions are loaded. Scripts register handlers for game events
+//! with `openzt.on` and read and change the zoo through the rest of the `openzt` table:
+//!
+//! ```lua
+//! openzt.on("animal_born", function(animal)
+//!     openzt.message("A baby " .. animal.species .. " was born!")
+//!     openzt.add_cash(100)
+//! end)
+//! ```
+//!
+//! Events:
+//! - `zoo_loaded()`: a saved zoo is loaded or a new game starts
+//! - `animal_born(animal)`: a newborn animal hatches, `animal` has `name`, `species` and `subtype`
+//! - `guest_spawned()`: a new guest is created
+//!
+//! The `openzt` table has `mod_id`, `on(event, handler)`, `log(message)`, `message(text)`
+//! (shown in the game's message bar), `cash()`, `add_cash(amount)`, `zoo_stats()`,
+//! `is_mod_loaded(mod_id)`, `has_tag(extension_key, tag)`, `attribute(extension_key, key)` and
+//! `setting(key)` (the value chosen for one of the mod's settings, see [`mod_settings`]).
+//!
+//! Each mod's scripts share an environment of their own, separate from other mods and
+//! from the console, with only Lua's `string`, `table` and `math` libraries. A script or
+//! handler that runs more than [`INSTRUCTION_LIMIT`] Lua instructions is stopped with an
+//! error. Errors in a script, or in a handler (which is then removed), are logged and added
+//! to the mod load report. Set `run_scripts = false` in `[mod_loading]` to not run any mod
+//! scripts. Mod scripts are only run with the `experimental` feature.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, LazyLock, Mutex,
+};
+
+use mlua::{Function, HookTriggers, Lua, LuaOptions, StdLib, Table, Value, VmState};
+use openzt_detour_macro::detour_mod;
+use tracing::{error, info};
+
+use crate::{
+    globals::globals,
+    resource_manager::{
+        lazyresourcemap::path_key,
+        load_report::{self, LoadStage},
+        mod_config::get_openzt_config,
+        openzt_mods::{extensions, loading, mod_settings},
+    },
+};
+
+/// A game event scripts can handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptEvent {
+    ZooLoaded,
+    AnimalBorn,
+    GuestSpawned,
+}
+
+impl ScriptEvent {
+    const ALL: [ScriptEvent; 3] = [ScriptEvent::ZooLoaded, ScriptEvent::AnimalBorn, ScriptEvent::GuestSpawned];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ScriptEvent::ZooLoaded => "zoo_loaded",
+            ScriptEvent::AnimalBorn => "animal_born",
+            ScriptEvent::GuestSpawned => "guest_spawned",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<ScriptEvent> {
+        ScriptEvent::ALL.into_iter().find(|event| event.name() == name)
+    }
+}
+
+/// A function a mod registered for an event
+#[derive(Clone)]
+struct Handler {
+    mod_id: String,
+    event: ScriptEvent,
+    function: Function,
+}
+
+/// Lua instructions a script or handler may run before it is stopped, so a runaway loop can't hang the game
+pub const INSTRUCTION_LIMIT: u32 = 10_000_000;
+
+/// How many instructions run between checks of the instruction limit
+const HOOK_INTERVAL: u32 = 10_000;
+
+/// Instructions run by the current script or handler, counted by the instruction hook
+static INSTRUCTIONS: AtomicU32 = AtomicU32::new(0);
+
+/// A mod's ID and the names of the scripts it ran
+type ModScripts = (String, Vec<String>);
+
+/// Lua state shared by every mod's scripts, each mod gets its own environment in it
+static SCRIPT_LUA: LazyLock<Mutex<Lua>> = LazyLock::new(|| Mutex::new(new_lua()));
+
+/// Handlers in the order they were registered
+static HANDLERS: LazyLock<Mutex<Vec<Handler>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Scripts run for each mod, in load order
+static LOADED_SCRIPTS: LazyLock<Mutex<Vec<ModScripts>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+fn new_lua() -> Lua {
+    let lua = Lua::new_with(StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::JIT, LuaOptions::default()).expect("Failed to create the mod script Lua state");
+    // LuaJIT doesn't run hooks in compiled code, so the JIT is turned off for the instruction limit to hold
+    if let Err(e) = lua.load("jit.off()").exec() {
+        error!("Failed to turn off the JIT for mod scripts: {}", e);
+    }
+    let limit = lua.set_global_hook(HookTriggers::new().every_nth_instruction(HOOK_INTERVAL), |_, _| {
+        if INSTRUCTIONS.fetch_add(HOOK_INTERVAL, Ordering::Relaxed) + HOOK_INTERVAL > INSTRUCTION_LIMIT {
+            return Err(runtime_error(format!("stopped after running {} instructions", INSTRUCTION_LIMIT)));
+        }
+        Ok(VmState::Continue)
+    });
+    if let Err(e) = limit {
+        error!("Failed to set the instruction limit for mod scripts: {}", e);
+    }
+    // The base library can read files, scripts should only see what the mod's API gives them
+    for name in ["dofile", "loadfile", "jit"] {
+        if let Err(e) = lua.globals().set(name, Value::Nil) {
+            error!("Failed to remove {} from mod scripts: {}", name, e);
+        }
+    }
+    lua
+}
+
+/// The `scripts/*.lua` files of a mod, sorted case-insensitively
+pub fn script_files(file_map: &HashMap<String, Arc<[u8]>>) -> Vec<String> {
+    let mut scripts: Vec<String> = file_map
+        .keys()
+        .filter(|name| path_key(name).starts_with("scripts/") && name.to_lowercase().ends_with(".lua"))
+        .cloned()
+        .collect();
+    scripts.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b)));
+    scripts
+}
+
+fn runtime_error(message: String) -> mlua::Error {
+    mlua::Error::RuntimeError(message)
+}
+
+/// The `openzt` table given to `mod_id`'s scripts
+fn create_api(lua: &Lua, mod_id: &str) -> mlua::Result<Table> {
+    let api = lua.create_table()?;
+    api.set("mod_id", mod_id)?;
+
+    let owner = mod_id.to_string();
+    api.set(
+        "on",
+        lua.create_function(move |_, (event, function): (String, Function)| {
+            let Some(event) = ScriptEvent::from_name(&event) else {
+                let names: Vec<&str> = ScriptEvent::ALL.iter().map(ScriptEvent::name).collect();
+                return Err(runtime_error(format!("unknown event '{}', expected one of {}", event, names.join(", "))));
+            };
+            HANDLERS.lock().unwrap().push(Handler {
+                mod_id: owner.clone(),
+                event,
+                function,
+            });
+            Ok(())
+        })?,
+    )?;
+
+    let owner = mod_id.to_string();
+    api.set(
+        "log",
+        lua.create_function(move |_, message: String| {
+            info!("[{}] {}", owner, message);
+            Ok(())
+        })?,
+    )?;
+
+    api.set(
+        "message",
+        lua.create_function(|_, text: String| {
+            crate::ztui::display_message(&text);
+            Ok(())
+        })?,
+    )?;
+
+    api.set("cash", lua.create_function(|_, ()| Ok(globals().ztgamemgr().cash()))?)?;
+
+    api.set(
+        "add_cash",
+        lua.create_function(|_, amount: f32| {
+            unsafe { (*globals().ztgamemgr_ptr()).add_cash(amount) };
+            Ok(())
+        })?,
+    )?;
+
+    api.set(
+        "zoo_stats",
+        lua.create_function(|lua, ()| {
+            let ztgamemgr = globals().ztgamemgr();
+            let stats = lua.create_table()?;
+            stats.set("cash", ztgamemgr.cash())?;
+            stats.set("animals", ztgamemgr.num_animals())?;
+            stats.set("species", ztgamemgr.num_species())?;
+            stats.set("guests", ztgamemgr.num_guests())?;
+            Ok(stats)
+        })?,
+    )?;
+
+    api.set("is_mod_loaded", lua.create_function(|_, mod_id: String| Ok(loading::is_mod_loaded(&mod_id)))?)?;
+
+    api.set(
+        "has_tag",
+        lua.create_function(|_, (extension_key, tag): (String, String)| extensions::entity_has_tag(&extension_key, &tag).map_err(|e| runtime_error(e.to_string())))?,
+    )?;
+
+    api.set(
+        "attribute",
+        lua.create_function(|_, (extension_key, key): (String, String)| {
+            extensions::get_entity_attribute(&extension_key, &key).map_err(|e| runtime_error(e.to_string()))
+        })?,
+    )?;
+
+    let owner = mod_id.to_string();
+    api.set(
+        "setting",
+        lua.create_function(move |lua, key: String| {
+            let value = mod_settings::get(&owner, &key).ok_or_else(|| runtime_error(format!("'{}' has no setting '{}'", owner, key)))?;
+            setting_to_lua(lua, value)
+        })?,
+    )?;
+
+    Ok(api)
+}
+
+/// A setting's value as a Lua value, settings are only ever integers, floats, booleans or strings
+fn setting_to_lua(lua: &Lua, value: toml::Value) -> mlua::Result<Value> {
+    Ok(match value {
+        // Lua integers are 32 bits in the game, larger settings become floats
+        toml::Value::Integer(number) => mlua::Integer::try_from(number).map_or(Value::Number(number as f64), Value::Integer),
+        toml::Value::Float(number) => Value::Number(number),
+        toml::Value::Boolean(flag) => Value::Boolean(flag),
+        toml::Value::String(text) => Value::String(lua.create_string(text)?),
+        other => return Err(runtime_error(format!("unsupported setting value {}", other))),
+    })
+}
+
+/// A copy of the globals with the mod's `openzt` table, so mods can't change each other's globals
+fn create_environment(lua: &Lua, mod_id: &str) -> mlua::Result<Table> {
+    let environment = lua.create_table()?;
+    for pair in lua.globals().pairs::<Value, Value>() {
+        let (key, value) = pair?;
+        environment.raw_set(key, value)?;
+    }
+    environment.raw_set("_G", environment.clone())?;
+    environment.raw_set("openzt", create_api(lua, mod_id)?)?;
+    Ok(environment)
+}
+
+/// Run `mod_id`'s scripts, replacing the handlers it registered before
+///
+/// Returns an error message for each script that failed, the other scripts still run.
+fn run_scripts(mod_id: &str, scripts: &[(String, String)]) -> Vec<String> {
+    remove_mod_scripts(mod_id);
+
+    let lua = SCRIPT_LUA.lock().unwrap();
+    let environment = match create_environment(&lua, mod_id) {
+        Ok(environment) => environment,
+        Err(e) => return vec![format!("Failed to create the script environment: {}", e)],
+    };
+
+    let mut errors = Vec::new();
+    for (name, source) in scripts {
+        INSTRUCTIONS.store(0, Ordering::Relaxed);
+        let result = lua
+            .load(source.as_str())
+            .set_name(format!("{}/{}", mod_id, name))
+            .set_environment(environment.clone())
+            .exec();
+        if let Err(e) = result {
+            errors.push(format!("Script {} failed: {}", name, e));
+        }
+    }
+    LOADED_SCRIPTS
+        .lock()
+        .unwrap()
+        .push((mod_id.to_string(), scripts.iter().map(|(name, _)| name.clone()).collect()));
+    errors
+}
+
+/// Run the `scripts/*.lua` files of a mod that was just loaded
+pub fn load_mod_scripts(mod_id: &str, file_map: &HashMap<String, Arc<[u8]>>) {
+    let scripts: Vec<(String, String)> = script_files(file_map)
+        .into_iter()
+        .map(|name| {
+            let source = String::from_utf8_lossy(&file_map[&name]).into_owned();
+            (name, source)
+        })
+        .collect();
+    if scripts.is_empty() {
+        return;
+    }
+    if !cfg!(feature = "experimental") {
+        info!("Skipping {} scripts of {}, mod scripts are experimental", scripts.len(), mod_id);
+        return;
+    }
+    if !get_openzt_config().mod_loading.run_scripts {
+        info!("Skipping {} scripts of {}, run_scripts is off", scripts.len(), mod_id);
+        return;
+    }
+
+    info!("Running {} scripts of {}", scripts.len(), mod_id);
+    for message in run_scripts(mod_id, &scripts) {
+        error!("{}: {}", mod_id, message);
+        load_report::record_error(mod_id, LoadStage::Script, message);
+    }
+}
+
+/// Forget a mod's scripts and handlers, e.g. before it is reloaded
+pub fn remove_mod_scripts(mod_id: &str) {
+    HANDLERS.lock().unwrap().retain(|handler| handler.mod_id != mod_id);
+    LOADED_SCRIPTS.lock().unwrap().retain(|(loaded, _)| loaded != mod_id);
+}
+
+/// Call every handler of `event` with the argument built by `argument`
+///
+/// A handler that fails is logged, recorded in the load report and removed.
+fn fire(event: ScriptEvent, argument: impl FnOnce(&Lua) -> mlua::Result<Value>) {
+    let handlers: Vec<Handler> = HANDLERS.lock().unwrap().iter().filter(|handler| handler.event == event).cloned().collect();
+    if handlers.is_empty() {
+        return;
+    }
+
+    let lua = SCRIPT_LUA.lock().unwrap();
+    let argument = match argument(&lua) {
+        Ok(argument) => argument,
+        Err(e) => {
+            error!("Failed to build the {} event for mod scripts: {}", event.name(), e);
+            return;
+        }
+    };
+
+    for handler in handlers {
+        INSTRUCTIONS.store(0, Ordering::Relaxed);
+        if let Err(e) = handler.function.call::<()>(argument.clone()) {
+            let message = format!("{} handler failed and was removed: {}", event.name(), e);
+            error!("{}: {}", handler.mod_id, message);
+            load_report::record_error(&handler.mod_id, LoadStage::Script, message);
+            HANDLERS.lock().unwrap().retain(|registered| registered.function != handler.function);
+        }
+    }
+}
+
+/// A saved zoo was loaded or a new game started
+pub fn zoo_loaded() {
+    fire(ScriptEvent::ZooLoaded, |_| Ok(Value::Nil));
+}
+
+/// A newborn animal hatched
+pub fn animal_born(name: &str, species: &str, subtype: &str) {
+    fire(ScriptEvent::AnimalBorn, |lua| {
+        let animal = lua.create_table()?;
+        animal.set("name", name)?;
+        animal.set("species", species)?;
+        animal.set("subtype", subtype)?;
+        Ok(Value::Table(animal))
+    });
+}
+
+/// A new guest was created
+pub fn guest_spawned() {
+    fire(ScriptEvent::GuestSpawned, |_| Ok(Value::Nil));
+}
+
+/// The scripts each mod ran and how many handlers it has for each event
+pub fn summary() -> String {
+    let loaded = LOADED_SCRIPTS.lock().unwrap();
+    if loaded.is_empty() {
+        return "No mod scripts loaded\n".to_string();
+    }
+
+    let handlers = HANDLERS.lock().unwrap();
+    let mut summary = String::new();
+    for (mod_id, scripts) in loaded.iter() {
+        let _ = writeln!(summary, "{}: {}", mod_id, scripts.join(", "));
+        for event in ScriptEvent::ALL {
+            let count = handlers.iter().filter(|handler| handler.mod_id == *mod_id && handler.event == event).count();
+            if count > 0 {
+                let _ = writeln!(summary, "  {}: {} handlers", event.name(), count);
+            }
+        }
+    }
+    summary
+}
+
+pub fn init() {
+    if unsafe { script_hooks::init_detours() }.is_err() {
+        error!("Error initialising mod script detours");
+    }
+}
+
+#[detour_mod]
+mod script_hooks {
+    use openzt_detour::generated::{zoostatus::F_CREATE_GUEST, ztanimal::FINISH_HATCH};
+
+    use crate::ztworldmgr::read_zt_entity_from_memory;
+
+    #[detour(FINISH_HATCH)]
+    unsafe extern "thiscall" fn zt_animal_finish_hatch(this_ptr: u32) {
+        unsafe { FINISH_HATCH_DETOUR.call(this_ptr) };
+        let animal = read_zt_entity_from_memory(this_ptr);
+        super::animal_born(animal.name(), animal.type_class().zt_type(), animal.type_class().zt_sub_type());
+    }
+
+    #[detour(F_CREATE_GUEST)]
+    unsafe extern "thiscall" fn zoo_status_create_guest(this_ptr: u32) {
+        unsafe { F_CREATE_GUEST_DETOUR.call(this_ptr) };
+        super::guest_spawned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handler_count(mod_id: &str, event: ScriptEvent) -> usize {
+        HANDLERS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|handler| handler.mod_id == mod_id && handler.event == event)
+            .count()
+    }
+
+    fn scripts(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries.iter().map(|(name, source)| (name.to_string(), source.to_string())).collect()
+    }
+
+    #[test]
+    fn test_script_files() {
+        let file_map: HashMap<String, Arc<[u8]>> = ["scripts/b.lua", "scripts/A.lua", "scripts/notes.txt", "defs/moon.toml", "meta.toml"]
+            .into_iter()
+            .map(|name| (name.to_string(), Arc::from(&b""[..])))
+            .collect();
+        assert_eq!(script_files(&file_map), vec!["scripts/A.lua", "scripts/b.lua"]);
+    }
+
+    #[test]
+    fn test_run_scripts() {
+        let errors = run_scripts(
+            "test.scripts",
+            &scripts(&[
+                ("scripts/a.lua", "openzt.on('zoo_loaded', function() loads = (loads or 0) + 1 end)"),
+                (
+                    "scripts/b.lua",
+                    "openzt.on('zoo_loaded', function() error('broken') end)\nopenzt.on('guest_spawned', function() end)",
+                ),
+                ("scripts/c.lua", "openzt.on('zoo_exploded', function() end)"),
+                ("scripts/d.lua", "this is not lua"),
+            ]),
+        );
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(
+            errors[0].starts_with("Script scripts/c.lua failed") && errors[0].contains("unknown event 'zoo_exploded'"),
+            "{}",
+            errors[0]
+        );
+        assert!(errors[1].starts_with("Script scripts/d.lua failed"), "{}", errors[1]);
+        assert_eq!(handler_count("test.scripts", ScriptEvent::ZooLoaded), 2);
+
+        // The failing handler is removed, the other one keeps running
+        zoo_loaded();
+        assert_eq!(handler_count("test.scripts", ScriptEvent::ZooLoaded), 1);
+        zoo_loaded();
+        assert_eq!(handler_count("test.scripts", ScriptEvent::ZooLoaded), 1);
+        assert!(
+            summary().contains("test.scripts: scripts/a.lua, scripts/b.lua, scripts/c.lua, scripts/d.lua\n  zoo_loaded: 1 handlers\n  guest_spawned: 1 handlers\n")
+        );
+
+        // Running the scripts again replaces the mod's handlers
+        run_scripts("test.scripts", &scripts(&[("scripts/a.lua", "openzt.on('animal_born', function(animal) end)")]));
+        assert_eq!(handler_count("test.scripts", ScriptEvent::ZooLoaded), 0);
+        assert_eq!(handler_count("test.scripts", ScriptEvent::AnimalBorn), 1);
+        remove_mod_scripts("test.scripts");
+        assert_eq!(handler_count("test.scripts", ScriptEvent::AnimalBorn), 0);
+    }
+
+    #[test]
+    fn test_runaway_scripts_are_stopped() {
+        let errors = run_scripts(
+            "test.runaway",
+            &scripts(&[
+                ("scripts/a.lua", "while true do end"),
+                ("scripts/b.lua", "openzt.on('guest_spawned', function() while true do end end)"),
+            ]),
+        );
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].starts_with("Script scripts/a.lua failed") && errors[0].contains("instructions"),
+            "{}",
+            errors[0]
+        );
+
+        // The handler is stopped and removed like any other failing handler
+        assert_eq!(handler_count("test.runaway", ScriptEvent::GuestSpawned), 1);
+        guest_spawned();
+        assert_eq!(handler_count("test.runaway", ScriptEvent::GuestSpawned), 0);
+        remove_mod_scripts("test.runaway");
+    }
+}
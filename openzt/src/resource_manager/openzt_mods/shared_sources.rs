@@ -0,0 +1,182 @@
+//! Patch sources shared between OpenZT mods
+//!
+//! A replace or merge patch normally reads its `source` from `resources/` in its own mod.
+//! `source = "mod:<mod_id>:<path>"` reads `resources/<path>` from another OpenZT mod instead,
+//! so a family of mods can share template files. The other mod has to be loaded before the
+//! patching mod, which is guaranteed by declaring it as a dependency with `ordering = "after"`;
+//! a source from a loaded mod that isn't a declared dependency still works, with a warning.
+//!
+//! Files are read from the other mod's archive or directory when a patch needs them, nothing is
+//! kept in memory after a mod has loaded. Paths that are absolute or contain `..` are refused,
+//! so a source can't be read from outside the other mod's `resources/`.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::{anyhow, bail, Context};
+use tracing::warn;
+
+use crate::{
+    mods::{DependencyIdentifier, Meta},
+    resource_manager::{lazyresourcemap::path_key, ztd::ZtdArchive},
+};
+
+const SOURCE_PREFIX: &str = "mod:";
+
+/// A loaded OpenZT mod that patch sources can be read from
+#[derive(Debug, Clone)]
+struct ModSource {
+    /// Archive or directory the mod was loaded from
+    resource: PathBuf,
+    /// Mod IDs the mod declares as dependencies
+    dependencies: Vec<String>,
+}
+
+/// Loaded OpenZT mods by mod ID, in load order as mods are only added once loaded
+#[derive(Default)]
+struct ModSources {
+    mods: HashMap<String, ModSource>,
+}
+
+static MOD_SOURCES: LazyLock<Mutex<ModSources>> = LazyLock::new(|| Mutex::new(ModSources::default()));
+
+/// Split a `mod:<mod_id>:<path>` source into the mod ID and path, `None` for sources in the patching mod
+pub fn parse_source(source: &str) -> Option<(&str, &str)> {
+    source.strip_prefix(SOURCE_PREFIX)?.split_once(':')
+}
+
+impl ModSources {
+    /// Find the mod a patch in `current_mod_id` reads a shared source from
+    fn find(&self, current_mod_id: &str, source_mod_id: &str) -> anyhow::Result<&ModSource> {
+        let source_mod = self.mods.get(source_mod_id).ok_or_else(|| {
+            anyhow!(
+                "Mod '{}' is not loaded before '{}', add it to the dependencies in meta.toml with ordering = \"after\"",
+                source_mod_id,
+                current_mod_id
+            )
+        })?;
+
+        let declared = self
+            .mods
+            .get(current_mod_id)
+            .is_some_and(|current| current.dependencies.iter().any(|dependency| dependency == source_mod_id));
+        if !declared {
+            warn!(
+                "'{}' reads patch sources from '{}' without declaring it as a dependency, the load order may change",
+                current_mod_id, source_mod_id
+            );
+        }
+        Ok(source_mod)
+    }
+}
+
+/// Record a loaded OpenZT mod so later mods can read patch sources from it
+pub fn register_mod(meta: &Meta, resource: &Path) {
+    let dependencies = meta
+        .dependencies()
+        .iter()
+        .filter_map(|dependency| match dependency.identifier() {
+            DependencyIdentifier::ModId(id) => Some(id.clone()),
+            _ => None,
+        })
+        .collect();
+    let source = ModSource {
+        resource: resource.to_path_buf(),
+        dependencies,
+    };
+    MOD_SOURCES.lock().unwrap().mods.insert(meta.mod_id().to_string(), source);
+}
+
+/// Forget a mod before it is reloaded
+pub fn release_mod(mod_id: &str) {
+    MOD_SOURCES.lock().unwrap().mods.remove(mod_id);
+}
+
+/// Read `resources/<path>` from another loaded OpenZT mod for a patch in `current_mod_id`
+pub fn read_source(current_mod_id: &str, source_mod_id: &str, path: &str) -> anyhow::Result<Vec<u8>> {
+    let source_mod = MOD_SOURCES.lock().unwrap().find(current_mod_id, source_mod_id)?.clone();
+    read_resource(&source_mod.resource, path).with_context(|| format!("Failed to read source '{}' from mod '{}'", path, source_mod_id))
+}
+
+/// Refuse paths that would leave the mod's `resources/`, with either separator
+fn check_source_path(path: &str) -> anyhow::Result<()> {
+    let outside = path.starts_with(['/', '\\'])
+        || path.split(['/', '\\']).any(|segment| segment == "..")
+        || Path::new(path)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+    if outside {
+        bail!("'{}' must be a relative path inside the mod's resources/", path);
+    }
+    Ok(())
+}
+
+/// Read `resources/<path>` from a mod's archive or directory
+fn read_resource(resource: &Path, path: &str) -> anyhow::Result<Vec<u8>> {
+    check_source_path(path)?;
+    let archive_path = format!("resources/{}", path);
+    if resource.is_dir() {
+        let file_path = resource.join(&archive_path);
+        return std::fs::read(&file_path).with_context(|| format!("{} not found", file_path.display()));
+    }
+
+    let mut archive = ZtdArchive::new(resource)?;
+    let key = path_key(&archive_path);
+    let file_name = archive
+        .file_names()
+        .find(|name| path_key(name) == key)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("{} not found in {}", archive_path, resource.display()))?;
+    Ok(archive.by_name(&file_name)?.read_all()?.into_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mod_source(resource: &Path, dependencies: &[&str]) -> ModSource {
+        ModSource {
+            resource: resource.to_path_buf(),
+            dependencies: dependencies.iter().map(|dependency| dependency.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(parse_source("mod:finn.framework:templates/base.ai"), Some(("finn.framework", "templates/base.ai")));
+        assert_eq!(parse_source("templates/base.ai"), None);
+        assert_eq!(parse_source("mod:finn.framework"), None);
+    }
+
+    #[test]
+    fn test_read_from_loaded_mod() {
+        let dir = std::env::temp_dir().join(format!("openzt-shared-sources-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("resources/templates")).unwrap();
+        std::fs::write(dir.join("resources/templates/base.ai"), b"[Global]\nType = base\n").unwrap();
+
+        let mut sources = ModSources::default();
+        sources.mods.insert("finn.framework".to_string(), mod_source(&dir, &[]));
+        sources
+            .mods
+            .insert("finn.moon".to_string(), mod_source(Path::new("finn.moon.ztd"), &["finn.framework"]));
+
+        let framework = sources.find("finn.moon", "finn.framework").unwrap();
+        assert_eq!(read_resource(&framework.resource, "templates/base.ai").unwrap(), b"[Global]\nType = base\n");
+        assert!(read_resource(&framework.resource, "templates/missing.ai").is_err());
+        assert!(read_resource(&framework.resource, "./templates/base.ai").is_ok());
+
+        std::fs::write(dir.join("secret.txt"), b"secret").unwrap();
+        for path in ["../secret.txt", "templates/../../secret.txt", "..\\secret.txt", "/etc/passwd", "\\secret.txt"] {
+            assert!(read_resource(&framework.resource, path).is_err(), "{}", path);
+        }
+        let absolute = dir.join("secret.txt");
+        assert!(read_resource(&framework.resource, &absolute.to_string_lossy()).is_err());
+
+        assert_eq!(
+            sources.find("finn.moon", "finn.sun").map(|_| ()).unwrap_err().to_string(),
+            "Mod 'finn.sun' is not loaded before 'finn.moon', add it to the dependencies in meta.toml with ordering = \"after\""
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,398 @@
+//! Animations converted from PNG images in OpenZT mods
+//!
+//! A def file's `[sprites]` table turns PNG frames shipped in the mod into a ZT
+//! animation when the mod is loaded. The animation is added at `target`,
+//! replacing the game's animation if one exists there:
+//!
+//! ```toml
+//! [sprites.moon_tree_idle]
+//! target = "objects/moontree/idle/N"
+//! frames = ["resources/moontree/idle_0.png", "resources/moontree/idle_1.png"]
+//! animation_speed = 150
+//! ```
+//!
+//! Without a `palette` the frames' colors become a new palette at `<target>.pal`,
+//! which holds at most 255 colors. With one, each color is mapped to the closest
+//! color of that palette, which must already be in the resource map (a game
+//! palette, or one generated for another sprite; sprites are converted in name
+//! order). Pixels less than half opaque are transparent.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use tracing::info;
+
+use crate::{
+    animation::{Animation, DrawInstruction, Frame, Line},
+    mods::SpriteDefinition,
+    resource_manager::{
+        file_overrides,
+        lazyresourcemap::{add_ztfile, get_file},
+        openzt_mods::{hot_reload, loading::get_mod_file},
+        ztfile::{ZTFile, ZTFileType},
+    },
+};
+
+/// Colors a generated palette can hold, index 0 is left unused like in the game's palettes
+const MAX_PALETTE_COLORS: usize = 255;
+
+/// A PNG frame decoded to RGBA
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u16,
+    pub height: u16,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// The color of a pixel, `None` if it is transparent
+    fn color(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+        let [r, g, b, a] = self.pixels[y * self.width as usize + x];
+        (a >= 128).then_some([r, g, b])
+    }
+}
+
+/// Decode a PNG file of any color type and bit depth
+pub fn decode_png(data: &[u8]) -> anyhow::Result<Image> {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size().ok_or_else(|| anyhow!("Image is too large"))?];
+    let frame = reader.next_frame(&mut buffer)?;
+
+    let (Ok(width), Ok(height)) = (u16::try_from(frame.width), u16::try_from(frame.height)) else {
+        return Err(anyhow!("Image is {}x{}, frames can be at most {}x{}", frame.width, frame.height, u16::MAX, u16::MAX));
+    };
+    let buffer = &buffer[..frame.buffer_size()];
+    let pixels = match frame.color_type {
+        png::ColorType::Rgba => buffer.chunks_exact(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+        png::ColorType::Rgb => buffer.chunks_exact(3).map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buffer.iter().map(|&p| [p, p, p, 255]).collect(),
+        png::ColorType::Indexed => return Err(anyhow!("Indexed image was not expanded")),
+    };
+
+    Ok(Image { width, height, pixels })
+}
+
+/// Read a ZT palette: the number of colors, then red, green, blue and an unused byte for each color
+pub fn parse_palette(data: &[u8]) -> anyhow::Result<Vec<[u8; 3]>> {
+    let count = data.get(..4).ok_or_else(|| anyhow!("Palette is empty"))?;
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    let colors: Vec<[u8; 3]> = data[4..].chunks_exact(4).take(count).map(|c| [c[0], c[1], c[2]]).collect();
+    if colors.len() != count || count == 0 || count > 256 {
+        return Err(anyhow!("Palette should have {} colors, found {}", count, colors.len()));
+    }
+    Ok(colors)
+}
+
+/// Write a ZT palette, with index 0 left unused
+pub fn write_palette(colors: &[[u8; 3]]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + (colors.len() + 1) * 4);
+    data.extend_from_slice(&(colors.len() as u32 + 1).to_le_bytes());
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    for [r, g, b] in colors {
+        data.extend_from_slice(&[*r, *g, *b, 255]);
+    }
+    data
+}
+
+/// Every color used by the frames, in order of first use
+///
+/// Fails if there are more than a palette can hold.
+pub fn collect_colors(images: &[Image]) -> anyhow::Result<Vec<[u8; 3]>> {
+    let mut seen = HashSet::new();
+    let mut colors = Vec::new();
+    for image in images {
+        for y in 0..image.height as usize {
+            for x in 0..image.width as usize {
+                if let Some(color) = image.color(x, y)
+                    && seen.insert(color)
+                {
+                    colors.push(color);
+                }
+            }
+        }
+    }
+
+    if colors.len() > MAX_PALETTE_COLORS {
+        return Err(anyhow!(
+            "Frames use {} colors, a palette holds at most {} (reduce the colors or set 'palette' to map them onto an existing palette)",
+            colors.len(),
+            MAX_PALETTE_COLORS
+        ));
+    }
+    Ok(colors)
+}
+
+/// The palette index closest to a color
+///
+/// Index 0 is usually left unused, so other entries win ties with it.
+fn closest_index(palette: &[[u8; 3]], color: [u8; 3], cache: &mut HashMap<[u8; 3], u8>) -> u8 {
+    *cache.entry(color).or_insert_with(|| {
+        let distance = |entry: &[u8; 3]| -> u32 { entry.iter().zip(color).map(|(a, b)| (*a as i32 - b as i32).pow(2) as u32).sum() };
+        palette
+            .iter()
+            .enumerate()
+            .min_by_key(|(index, entry)| (distance(entry), *index == 0))
+            .map(|(index, _)| index as u8)
+            .unwrap_or_default()
+    })
+}
+
+/// Encode an image as a frame, with palette indices as colors and transparent pixels skipped
+///
+/// `offset` is the point of the frame placed at the entity's position, the center when `None`.
+pub fn encode_frame(image: &Image, palette: &[[u8; 3]], offset: (Option<u16>, Option<u16>), cache: &mut HashMap<[u8; 3], u8>) -> anyhow::Result<Frame> {
+    let mut lines = Vec::with_capacity(image.height as usize);
+    for y in 0..image.height as usize {
+        let mut draw_instructions = Vec::new();
+        let mut skipped: usize = 0;
+        let mut x = 0;
+        while x < image.width as usize {
+            if image.color(x, y).is_none() {
+                skipped += 1;
+                x += 1;
+                continue;
+            }
+
+            // Offsets and runs are single bytes, longer ones are split over several instructions
+            while skipped > u8::MAX as usize {
+                draw_instructions.push(DrawInstruction {
+                    offset: u8::MAX,
+                    num_colors: 0,
+                    colors: Vec::new(),
+                });
+                skipped -= u8::MAX as usize;
+            }
+            let mut colors = Vec::new();
+            while x < image.width as usize && colors.len() < u8::MAX as usize {
+                let Some(color) = image.color(x, y) else {
+                    break;
+                };
+                colors.push(closest_index(palette, color, cache));
+                x += 1;
+            }
+            draw_instructions.push(DrawInstruction {
+                offset: skipped as u8,
+                num_colors: colors.len() as u8,
+                colors,
+            });
+            skipped = 0;
+        }
+
+        let num_draw_instructions = u8::try_from(draw_instructions.len())
+            .map_err(|_| anyhow!("Row {} has {} separate runs of pixels, at most {} are supported", y, draw_instructions.len(), u8::MAX))?;
+        lines.push(Line {
+            num_draw_instructions,
+            draw_instructions,
+        });
+    }
+
+    let mut frame = Frame {
+        num_bytes: 0,
+        pixel_height: image.height,
+        pixel_width: image.width,
+        vertical_offset_y: offset.1.unwrap_or(image.height / 2),
+        horizontal_offset_x: offset.0.unwrap_or(image.width / 2),
+        mystery_u16: 0,
+        lines,
+    };
+    frame.num_bytes = frame.calc_byte_size() as u32;
+    Ok(frame)
+}
+
+/// Build an animation from decoded frames, using the palette at `palette_filename`
+pub fn encode_animation(images: &[Image], palette: &[[u8; 3]], palette_filename: &str, sprite: &SpriteDefinition) -> anyhow::Result<Animation> {
+    let mut cache = HashMap::new();
+    let frames = images
+        .iter()
+        .enumerate()
+        .map(|(index, image)| {
+            encode_frame(image, palette, (*sprite.offset_x(), *sprite.offset_y()), &mut cache).with_context(|| format!("Failed to encode frame {}", index))
+        })
+        .collect::<anyhow::Result<Vec<Frame>>>()?;
+
+    let mut animation = Animation {
+        header: None,
+        animation_speed: *sprite.animation_speed(),
+        palette_filename_length: 0,
+        palette_filename: String::new(),
+        num_frames: frames.len() as u32,
+        frames,
+    };
+    animation.set_palette_filename(palette_filename.to_string());
+    Ok(animation)
+}
+
+/// Convert a sprite's frames and add the animation, and its palette if generated, to the resource map
+pub fn load_sprite(mod_id: &str, name: &str, sprite: &SpriteDefinition, file_map: &HashMap<String, Arc<[u8]>>) -> anyhow::Result<()> {
+    if sprite.frames().is_empty() {
+        return Err(anyhow!("Sprite {} has no frames", name));
+    }
+    let images = sprite
+        .frames()
+        .iter()
+        .map(|path| {
+            let data = get_mod_file(file_map, path).with_context(|| format!("Cannot find frame {} for sprite {}", path, name))?;
+            decode_png(data).with_context(|| format!("Failed to decode frame {} for sprite {}", path, name))
+        })
+        .collect::<anyhow::Result<Vec<Image>>>()?;
+
+    let (palette_filename, palette, new_palette) = match sprite.palette() {
+        Some(palette_filename) => {
+            let (_, data) = get_file(palette_filename).with_context(|| format!("Cannot find palette {} for sprite {}", palette_filename, name))?;
+            let palette = parse_palette(&data).with_context(|| format!("Failed to read palette {} for sprite {}", palette_filename, name))?;
+            (palette_filename.clone(), palette, None)
+        }
+        None => {
+            let colors = collect_colors(&images).with_context(|| format!("Failed to build a palette for sprite {}", name))?;
+            let mut palette = vec![[0, 0, 0]];
+            palette.extend_from_slice(&colors);
+            (format!("{}.pal", sprite.target()), palette, Some(write_palette(&colors)))
+        }
+    };
+
+    let animation = encode_animation(&images, &palette, &palette_filename, sprite).with_context(|| format!("Failed to convert sprite {}", name))?;
+    let (animation_bytes, animation_size) = animation.write()?;
+
+    // Files replaced by the dev mod are restored when it is reloaded
+    let mut targets = vec![sprite.target().clone()];
+    if new_palette.is_some() {
+        targets.push(palette_filename.clone());
+    }
+    hot_reload::snapshot_before_patches(mod_id, &targets.iter().cloned().collect())?;
+
+    if let Some(palette_data) = new_palette {
+        let palette_ztfile = ZTFile::builder()
+            .file_name(palette_filename.clone())
+            .file_size(palette_data.len() as u32)
+            .type_(ZTFileType::Palette)
+            .raw_data(palette_data.into_boxed_slice())
+            .build();
+        add_ztfile(Path::new("zip::./openzt.ztd"), palette_filename.clone(), palette_ztfile)?;
+    }
+
+    let animation_ztfile = ZTFile::builder()
+        .file_name(sprite.target().clone())
+        .file_size(animation_size as u32)
+        .type_(ZTFileType::Animation)
+        .raw_data(animation_bytes.into_boxed_slice())
+        .build();
+    add_ztfile(Path::new("zip::./openzt.ztd"), sprite.target().clone(), animation_ztfile)?;
+
+    let source = format!("sprites.{}", name);
+    for target in &targets {
+        file_overrides::add_patch(target, mod_id, &source);
+    }
+
+    info!(
+        "Converted sprite {} to {} ({} frames, palette {})",
+        name,
+        sprite.target(),
+        images.len(),
+        palette_filename
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    fn sprite(toml: &str) -> SpriteDefinition {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn encode_png(width: u32, height: u32, pixels: &[[u8; 4]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels.concat()).unwrap();
+        writer.finish().unwrap();
+        data
+    }
+
+    #[test]
+    fn test_decode_png() {
+        let pixels = [RED, CLEAR, BLUE, [0, 255, 0, 100]];
+        let image = decode_png(&encode_png(2, 2, &pixels)).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixels, pixels);
+        assert_eq!(image.color(0, 1), Some([0, 0, 255]));
+        assert_eq!(image.color(1, 1), None);
+
+        assert!(decode_png(b"not a png").is_err());
+    }
+
+    #[test]
+    fn test_palette_round_trip() {
+        let data = write_palette(&[[255, 0, 0], [0, 0, 255]]);
+        assert_eq!(data, [3, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 255, 0, 0, 255, 255]);
+        assert_eq!(parse_palette(&data).unwrap(), vec![[0, 0, 0], [255, 0, 0], [0, 0, 255]]);
+
+        let moon = include_bytes!("../../../resources/test/moon-location/resources/moon/moon.pal");
+        assert_eq!(parse_palette(moon).unwrap().len(), 10);
+        assert!(parse_palette(&data[..12]).is_err());
+    }
+
+    #[test]
+    fn test_encode_animation() {
+        // Row 0: transparent, red, red, transparent, blue; row 1: fully transparent
+        let image = Image {
+            width: 5,
+            height: 2,
+            pixels: vec![CLEAR, RED, RED, CLEAR, BLUE, CLEAR, CLEAR, CLEAR, CLEAR, CLEAR],
+        };
+        let colors = collect_colors(std::slice::from_ref(&image)).unwrap();
+        assert_eq!(colors, vec![[255, 0, 0], [0, 0, 255]]);
+
+        let sprite = sprite("target = \"objects/moon/N\"\nframes = [\"moon.png\"]\nanimation_speed = 150\noffset_y = 2\n");
+        let palette = [[0, 0, 0], [255, 0, 0], [0, 0, 255]];
+        let animation = encode_animation(&[image], &palette, "objects/moon/N.pal", &sprite).unwrap();
+        assert_eq!(animation.animation_speed, 150);
+        assert_eq!(animation.num_frames, 1);
+
+        let frame = &animation.frames[0];
+        assert_eq!((frame.pixel_width, frame.pixel_height), (5, 2));
+        assert_eq!((frame.horizontal_offset_x, frame.vertical_offset_y), (2, 2));
+        assert_eq!(frame.lines[0].num_draw_instructions, 2);
+        assert_eq!(frame.lines[0].draw_instructions[0].offset, 1);
+        assert_eq!(frame.lines[0].draw_instructions[0].colors, vec![1, 1]);
+        assert_eq!(frame.lines[0].draw_instructions[1].offset, 1);
+        assert_eq!(frame.lines[0].draw_instructions[1].colors, vec![2]);
+        assert_eq!(frame.lines[1].num_draw_instructions, 0);
+        assert_eq!(frame.num_bytes as usize, frame.calc_byte_size());
+
+        let (bytes, _) = animation.clone().write().unwrap();
+        assert_eq!(Animation::parse(&bytes).unwrap(), animation);
+    }
+
+    #[test]
+    fn test_long_runs_and_closest_colors() {
+        let mut pixels = vec![CLEAR; 300];
+        pixels.extend(vec![[250, 10, 10, 255]; 300]);
+        let image = Image { width: 600, height: 1, pixels };
+
+        let frame = encode_frame(&image, &[[0, 0, 0], [255, 0, 0], [0, 0, 255]], (None, None), &mut HashMap::new()).unwrap();
+        let offsets: Vec<(u8, u8)> = frame.lines[0].draw_instructions.iter().map(|d| (d.offset, d.num_colors)).collect();
+        assert_eq!(offsets, vec![(255, 0), (45, 255), (0, 45)]);
+        assert!(frame.lines[0].draw_instructions.iter().flat_map(|d| &d.colors).all(|color| *color == 1));
+
+        let many_colors = Image {
+            width: 256,
+            height: 1,
+            pixels: (0..=255).map(|c| [c, 0, 0, 255]).collect(),
+        };
+        assert!(collect_colors(&[many_colors]).unwrap_err().to_string().starts_with("Frames use 256 colors"));
+    }
+}
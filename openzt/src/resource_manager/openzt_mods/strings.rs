@@ -0,0 +1,171 @@
+//! Game string overrides from OpenZT mods
+//!
+//! A def file's `[strings]` table replaces or adds strings from the game's
+//! language DLL string tables, keyed by string ID and then locale:
+//!
+//! ```toml
+//! [strings.3383]
+//! default = "Marsh"
+//! de = "Sumpf"
+//! fr-FR = "Marais"
+//! ```
+//!
+//! The text for the current locale (`locale` in the `[mod_loading]` section of
+//! openzt.toml, or the Windows user locale) is picked by exact locale, then by
+//! language, then `default`; a string with none of those is left alone. When
+//! several mods override the same ID the mod loaded last wins.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::anyhow;
+use tracing::info;
+
+use crate::{
+    resource_manager::mod_config::get_openzt_config,
+    string_registry::{add_override_string_to_registry, remove_override_string_from_registry},
+};
+
+/// Locale used when Windows doesn't report one
+const DEFAULT_LOCALE: &str = "en-US";
+
+/// Translation used when none matches the locale
+const FALLBACK_KEY: &str = "default";
+
+/// The locale mod strings are picked for, e.g. "de-DE"
+static LOCALE: LazyLock<String> = LazyLock::new(|| {
+    let configured = get_openzt_config().mod_loading.locale;
+    let locale = if configured.trim().is_empty() {
+        system_locale()
+    } else {
+        configured.trim().to_string()
+    };
+    info!("Using locale {} for mod strings", locale);
+    locale
+});
+
+/// A mod's text for a string, as (mod ID, text)
+type ModString = (String, String);
+
+/// Every mod's override of each string ID, in load order
+static MOD_STRINGS: LazyLock<Mutex<HashMap<u32, Vec<ModString>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[cfg(windows)]
+fn system_locale() -> String {
+    use windows::Win32::Globalization::GetUserDefaultLocaleName;
+
+    // LOCALE_NAME_MAX_LENGTH
+    let mut buffer = [0u16; 85];
+    let length = unsafe { GetUserDefaultLocaleName(&mut buffer) };
+    if length <= 1 {
+        return DEFAULT_LOCALE.to_string();
+    }
+    String::from_utf16_lossy(&buffer[..length as usize - 1])
+}
+
+#[cfg(not(windows))]
+fn system_locale() -> String {
+    DEFAULT_LOCALE.to_string()
+}
+
+/// Lowercase a locale and use '-' as its separator, so "de_DE" matches "de-DE"
+fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_lowercase()
+}
+
+/// Pick the translation for `locale`: the exact locale, then its language, then `default`
+pub fn select<'t>(translations: &'t HashMap<String, String>, locale: &str) -> Option<&'t str> {
+    let locale = normalize_locale(locale);
+    let language = locale.split('-').next().unwrap_or_default();
+    let find = |wanted: &str| translations.iter().find(|(key, _)| normalize_locale(key) == wanted).map(|(_, text)| text.as_str());
+
+    find(&locale).or_else(|| find(language)).or_else(|| find(FALLBACK_KEY))
+}
+
+/// Resolve a def file's `[strings]` table for `locale` into (string ID, text) pairs
+pub fn resolve(strings: &HashMap<String, HashMap<String, String>>, locale: &str) -> anyhow::Result<Vec<(u32, String)>> {
+    let mut resolved = Vec::new();
+    for (string_id, translations) in strings {
+        let id = string_id
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| anyhow!("String ID '{}' in [strings] is not a number", string_id))?;
+        if let Some(text) = select(translations, locale) {
+            resolved.push((id, text.to_string()));
+        }
+    }
+    resolved.sort_by_key(|(id, _)| *id);
+    Ok(resolved)
+}
+
+/// Apply a mod's string overrides for the current locale
+pub fn add_mod_strings(mod_id: &str, strings: &HashMap<String, HashMap<String, String>>) -> anyhow::Result<()> {
+    let resolved = resolve(strings, &LOCALE)?;
+    info!(
+        "Overriding {} of {} strings from mod {} for locale {}",
+        resolved.len(),
+        strings.len(),
+        mod_id,
+        *LOCALE
+    );
+
+    let mut mod_strings = MOD_STRINGS.lock().unwrap();
+    for (id, text) in resolved {
+        let overrides = mod_strings.entry(id).or_default();
+        overrides.retain(|(owner, _)| owner != mod_id);
+        overrides.push((mod_id.to_string(), text.clone()));
+        add_override_string_to_registry(id, text);
+    }
+    Ok(())
+}
+
+/// Undo a mod's string overrides (before the mod is reloaded), restoring the previous mod's text
+pub fn remove_mod_strings(mod_id: &str) {
+    let mut mod_strings = MOD_STRINGS.lock().unwrap();
+    mod_strings.retain(|id, overrides| {
+        let Some(position) = overrides.iter().position(|(owner, _)| owner == mod_id) else {
+            return true;
+        };
+        overrides.remove(position);
+        if position == overrides.len() {
+            match overrides.last() {
+                Some((_, text)) => add_override_string_to_registry(*id, text.clone()),
+                None => remove_override_string_from_registry(*id),
+            }
+        }
+        !overrides.is_empty()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translations(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries.iter().map(|(locale, text)| (locale.to_string(), text.to_string())).collect()
+    }
+
+    #[test]
+    fn test_select_locale() {
+        let swamp = translations(&[("default", "Marsh"), ("de", "Sumpf"), ("fr_FR", "Marais"), ("fr-CA", "Marécage")]);
+
+        assert_eq!(select(&swamp, "de-AT"), Some("Sumpf"));
+        assert_eq!(select(&swamp, "fr-FR"), Some("Marais"));
+        assert_eq!(select(&swamp, "FR-ca"), Some("Marécage"));
+        assert_eq!(select(&swamp, "en-US"), Some("Marsh"));
+        assert_eq!(select(&translations(&[("de", "Sumpf")]), "en-US"), None);
+    }
+
+    #[test]
+    fn test_resolve_strings() {
+        let strings = HashMap::from([
+            ("3383".to_string(), translations(&[("default", "Marsh")])),
+            ("120".to_string(), translations(&[("en", "Zoo")])),
+            ("9000".to_string(), translations(&[("de", "Zoo")])),
+        ]);
+        assert_eq!(resolve(&strings, "en-GB").unwrap(), vec![(120, "Zoo".to_string()), (3383, "Marsh".to_string())]);
+
+        let invalid = HashMap::from([("swamp".to_string(), translations(&[("default", "Marsh")]))]);
+        assert_eq!(resolve(&invalid, "en-US").unwrap_err().to_string(), "String ID 'swamp' in [strings] is not a number");
+    }
+}
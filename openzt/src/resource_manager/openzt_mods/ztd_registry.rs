@@ -110,3 +110,28 @@ pub fn clear_registry_for_tests() {
     *CURRENT_LOAD_POSITION.lock().unwrap() = 0;
     MOD_TO_ZTD.lock().unwrap().clear();
 }
+
+/// Contents of the registry at one point, to put back with `restore_registry_for_tests`
+#[cfg(feature = "integration-tests")]
+#[derive(Debug, Clone)]
+pub struct ZtdRegistrySnapshot {
+    load_order: HashMap<String, (usize, ZtdLoadStatus)>,
+    position: usize,
+    mod_to_ztd: HashMap<String, String>,
+}
+
+#[cfg(feature = "integration-tests")]
+pub fn snapshot_registry_for_tests() -> ZtdRegistrySnapshot {
+    ZtdRegistrySnapshot {
+        load_order: ZTD_LOAD_ORDER.lock().unwrap().clone(),
+        position: *CURRENT_LOAD_POSITION.lock().unwrap(),
+        mod_to_ztd: MOD_TO_ZTD.lock().unwrap().clone(),
+    }
+}
+
+#[cfg(feature = "integration-tests")]
+pub fn restore_registry_for_tests(snapshot: &ZtdRegistrySnapshot) {
+    *ZTD_LOAD_ORDER.lock().unwrap() = snapshot.load_order.clone();
+    *CURRENT_LOAD_POSITION.lock().unwrap() = snapshot.position;
+    *MOD_TO_ZTD.lock().unwrap() = snapshot.mod_to_ztd.clone();
+}
@@ -0,0 +1,250 @@
+//! Cache of the parsed .cfg files, kept between runs
+//!
+//! Once every archive is loaded, each .cfg is parsed for the files it lists and the
+//! .ai file of every legacy entity is read for its attributes. The result only depends
+//! on the loaded archives and their load order, so it is stored in
+//! `openzt_parse_cache.json` next to openzt.toml and reused on the next start.
+//!
+//! The cache is keyed by a hash of the OpenZT version, whether it was built with the
+//! `experimental` feature, the `[mod_loading]` and `[mod_settings]` settings, every
+//! archive in load order (its size and modification time, and the SHA-256 of archives
+//! in /mods/ with a configured checksum), so adding, removing, reordering or changing
+//! any archive invalidates it. Set `cache_parsed_configs = false` in `[resource_cache]` to always parse.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+use walkdir::WalkDir;
+
+use crate::resource_manager::{
+    checksums,
+    mod_config::{get_openzt_config, OpenZTConfig},
+    openzt_mods::legacy_attributes::{add_legacy_entity, LegacyEntityAttributes, LegacyEntityType},
+};
+
+const CACHE_FILE_NAME: &str = "openzt_parse_cache.json";
+
+/// Files and legacy entities found by parsing the .cfg files
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ParsedConfigs {
+    /// Files passed to the `AfterFiltering` handlers, in order
+    pub files: Vec<String>,
+    /// Legacy entities read from .ai files, in the order they were registered
+    pub entities: Vec<(LegacyEntityType, String, LegacyEntityAttributes)>,
+}
+
+impl ParsedConfigs {
+    /// Register the legacy entities
+    pub fn register_entities(&self) {
+        for (entity_type, entity_name, attributes) in &self.entities {
+            if let Err(e) = add_legacy_entity(*entity_type, entity_name.clone(), attributes.clone()) {
+                warn!("Failed to register legacy entity '{}': {}", entity_name, e);
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CacheFile<'a> {
+    key: &'a str,
+    parsed: &'a ParsedConfigs,
+}
+
+#[derive(Deserialize)]
+struct CachedFile {
+    key: String,
+    parsed: ParsedConfigs,
+}
+
+/// Size and modification time of an archive, or of every file in an unpacked mod directory
+fn stamp(path: &Path) -> String {
+    let file_stamp = |path: &Path| match std::fs::metadata(path) {
+        Ok(metadata) => {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |modified| modified.as_nanos());
+            format!("{} {}", metadata.len(), modified)
+        }
+        Err(_) => "missing".to_string(),
+    };
+
+    if !path.is_dir() {
+        return file_stamp(path);
+    }
+    let mut stamp = String::new();
+    for entry in WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+        let _ = write!(stamp, "{}={};", relative.display(), file_stamp(entry.path()));
+    }
+    stamp
+}
+
+/// Hash everything the parsed .cfg files depend on
+///
+/// `archives` are the loaded archives in load order, as a label (name and whether it is
+/// disabled) and path. `checksums` are the (archive, SHA-256) of the archives in /mods/ that were hashed.
+pub fn cache_key(settings: &str, archives: &[(String, PathBuf)], checksums: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("openzt {}\n{}\n", env!("CARGO_PKG_VERSION"), settings));
+    for (label, path) in archives {
+        hasher.update(format!("{} {} {}\n", label, path.display(), stamp(path)));
+    }
+    for (archive, sha256) in checksums {
+        hasher.update(format!("{} {}\n", archive, sha256));
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// The settings the parsed .cfg files depend on, including the mod settings patches substitute
+/// and whether the `experimental` features that change what is loaded are built in
+fn settings(config: &OpenZTConfig) -> String {
+    format!(
+        "experimental = {}\n{}patch_dry_run = {}\n{}",
+        cfg!(feature = "experimental"),
+        toml::to_string(&config.mod_loading).unwrap_or_default(),
+        config.dev.patch_dry_run,
+        toml::to_string(&config.mod_settings).unwrap_or_default()
+    )
+}
+
+/// The cache key for the current settings and loaded archives
+pub fn current_key(archives: &[(String, PathBuf)]) -> String {
+    cache_key(&settings(&get_openzt_config()), archives, &checksums::verified_archives())
+}
+
+fn cache_path() -> PathBuf {
+    crate::util::get_base_path().join(CACHE_FILE_NAME)
+}
+
+fn read_from(path: &Path, key: &str) -> Option<ParsedConfigs> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str::<CachedFile>(&contents) {
+        Ok(cache) if cache.key == key => Some(cache.parsed),
+        Ok(_) => {
+            debug!("Parse cache is out of date");
+            None
+        }
+        Err(e) => {
+            warn!("Ignoring unreadable parse cache {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn write_to(path: &Path, key: &str, parsed: &ParsedConfigs) -> anyhow::Result<()> {
+    let cache = CacheFile { key, parsed };
+    let contents = serde_json::to_string(&cache).context("Failed to serialize the parse cache")?;
+    std::fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Use the cached result if it is still valid, otherwise parse and cache the result
+pub fn load_or_parse(archives: &[(String, PathBuf)], parse: impl FnOnce() -> ParsedConfigs) -> ParsedConfigs {
+    if !get_openzt_config().resource_cache.cache_parsed_configs {
+        return parse();
+    }
+
+    let key = current_key(archives);
+    let path = cache_path();
+    if let Some(parsed) = read_from(&path, &key) {
+        info!("Using cached .cfg parse results from {}", path.display());
+        return parsed;
+    }
+
+    let parsed = parse();
+    match write_to(&path, &key, &parsed) {
+        Ok(()) => info!("Cached .cfg parse results in {}", path.display()),
+        Err(e) => warn!("{:#}", e),
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openzt-parse-cache-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let dir = temp_dir("key");
+        let vanilla = dir.join("vanilla.ztd");
+        let moon = dir.join("finn.moon");
+        std::fs::write(&vanilla, b"vanilla").unwrap();
+        std::fs::create_dir_all(moon.join("defs")).unwrap();
+        std::fs::write(moon.join("defs/moon.toml"), b"[habitats]").unwrap();
+
+        let archives = vec![("vanilla.ztd".to_string(), vanilla.clone()), ("finn.moon".to_string(), moon.clone())];
+        let checksums = vec![("finn.moon".to_string(), "abc".to_string())];
+        let key = cache_key("order = []", &archives, &checksums);
+        assert_eq!(key, cache_key("order = []", &archives, &checksums));
+
+        let reordered = vec![archives[1].clone(), archives[0].clone()];
+        assert_ne!(key, cache_key("order = []", &reordered, &checksums));
+        assert_ne!(key, cache_key("order = [\"finn.moon\"]", &archives, &checksums));
+        assert_ne!(key, cache_key("order = []", &archives, &[("finn.moon".to_string(), "def".to_string())]));
+
+        let disabled = vec![archives[0].clone(), ("finn.moon (disabled)".to_string(), moon.clone())];
+        assert_ne!(key, cache_key("order = []", &disabled, &checksums));
+
+        std::fs::write(moon.join("defs/moon.toml"), b"[habitats.moon]").unwrap();
+        assert_ne!(key, cache_key("order = []", &archives, &checksums));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_mod_settings_change_key() {
+        let mut config = OpenZTConfig::default();
+        let key = cache_key(&settings(&config), &[], &[]);
+
+        config
+            .mod_settings
+            .entry("finn.moon".to_string())
+            .or_default()
+            .insert("litter_size".to_string(), toml::Value::Integer(3));
+        let with_setting = cache_key(&settings(&config), &[], &[]);
+        assert_ne!(key, with_setting);
+
+        config.mod_settings["finn.moon"]["litter_size"] = toml::Value::Integer(4);
+        assert_ne!(with_setting, cache_key(&settings(&config), &[], &[]));
+    }
+
+    #[test]
+    fn test_read_and_write() {
+        let dir = temp_dir("file");
+        let path = dir.join(CACHE_FILE_NAME);
+        assert!(read_from(&path, "key").is_none());
+
+        let parsed = ParsedConfigs {
+            files: vec!["animals/elephant.ai".to_string(), "ui/sharedui/listbk.uca".to_string()],
+            entities: vec![(LegacyEntityType::Animal, "elephant".to_string(), LegacyEntityAttributes::new("elephant".to_string()))],
+        };
+        write_to(&path, "key", &parsed).unwrap();
+
+        let cached = read_from(&path, "key").unwrap();
+        assert_eq!(cached.files, parsed.files);
+        assert_eq!(cached.entities.len(), 1);
+        assert_eq!(cached.entities[0].0, LegacyEntityType::Animal);
+        assert_eq!(cached.entities[0].1, "elephant");
+        assert!(read_from(&path, "other key").is_none());
+
+        std::fs::write(&path, b"not json").unwrap();
+        assert!(read_from(&path, "key").is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,358 @@
+//! The mods a zoo was saved with
+//!
+//! When a zoo is saved, the loaded OpenZT mods (id, version and archive SHA-256)
+//! and the legacy archives in /mods/ are written to `<save file>.mods.toml` next
+//! to the save. When a zoo with such a file is loaded, the mods are compared with
+//! the ones loaded now and any mod that was removed or changed since the zoo was
+//! saved is logged as a warning, as entities from those mods may be missing or
+//! behave differently. The `saved_mods()` console command shows the comparison for
+//! the current zoo. Saving and loading only use the hashes already computed by
+//! [`checksums`], archives are never hashed on the game thread.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use openzt_detour_macro::detour_mod;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{resource_manager::checksums, runtime_state};
+
+/// Appended to the save file's name to get the mod set file
+const MOD_SET_EXTENSION: &str = ".mods.toml";
+
+/// `runtime_state` key holding the path of the zoo last picked in the save or load dialog
+const ZOO_FILE_KEY: &str = "zoo_file";
+
+/// A mod or legacy archive that was loaded when a zoo was saved
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ModRecord {
+    /// Mod ID, or the archive name for legacy archives
+    pub id: String,
+    /// `None` for legacy archives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Archive file name, lowercased
+    pub archive: String,
+    /// `None` when the archive hadn't been hashed yet when the zoo was saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Every mod loaded when a zoo was saved
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct ModSet {
+    #[serde(default)]
+    pub mods: Vec<ModRecord>,
+}
+
+/// A difference between the mods a zoo was saved with and the current ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModSetChange {
+    Removed {
+        id: String,
+    },
+    VersionChanged {
+        id: String,
+        saved: String,
+        current: String,
+    },
+    /// Same version (or a legacy archive), but the archive's contents changed
+    ArchiveChanged {
+        id: String,
+    },
+}
+
+/// OpenZT mod ID -> (version, archive file name)
+static MOD_VERSIONS: LazyLock<Mutex<HashMap<String, (String, String)>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Record the version and archive of a loaded OpenZT mod
+pub fn register_mod(mod_id: &str, version: &str, archive: &str) {
+    MOD_VERSIONS
+        .lock()
+        .unwrap()
+        .insert(mod_id.to_string(), (version.to_string(), archive_file_name(archive)));
+}
+
+/// An archive's lowercased file name, as recorded by [`checksums`]
+fn archive_file_name(archive: &str) -> String {
+    Path::new(archive).file_name().and_then(|name| name.to_str()).unwrap_or(archive).to_lowercase()
+}
+
+/// Build a mod set from the loaded OpenZT mods and the hashed /mods/ archives, as (archive, sha256)
+pub fn build_mod_set(mod_versions: &HashMap<String, (String, String)>, archives: &[(String, String)]) -> ModSet {
+    let sha256_of = |archive: &str| archives.iter().find(|(name, _)| name == archive).map(|(_, sha256)| sha256.clone());
+
+    let mut mods: Vec<ModRecord> = mod_versions
+        .iter()
+        .map(|(mod_id, (version, archive))| ModRecord {
+            id: mod_id.clone(),
+            version: Some(version.clone()),
+            archive: archive.clone(),
+            sha256: sha256_of(archive),
+        })
+        .collect();
+    for (archive, sha256) in archives {
+        if !mods.iter().any(|record| record.archive == *archive) {
+            mods.push(ModRecord {
+                id: archive.clone(),
+                version: None,
+                archive: archive.clone(),
+                sha256: Some(sha256.clone()),
+            });
+        }
+    }
+    mods.sort_by(|a, b| a.id.cmp(&b.id));
+    ModSet { mods }
+}
+
+/// The mods loaded now
+pub fn current_mod_set() -> ModSet {
+    build_mod_set(&MOD_VERSIONS.lock().unwrap(), &checksums::loaded_archives())
+}
+
+/// The mods that were removed or changed since `saved`; mods added since don't affect the zoo
+pub fn compare(saved: &ModSet, current: &ModSet) -> Vec<ModSetChange> {
+    let mut changes = Vec::new();
+    for record in &saved.mods {
+        let Some(now) = current.mods.iter().find(|now| now.id == record.id) else {
+            changes.push(ModSetChange::Removed { id: record.id.clone() });
+            continue;
+        };
+        match (&record.version, &now.version) {
+            (Some(saved), Some(current)) if saved != current => {
+                changes.push(ModSetChange::VersionChanged {
+                    id: record.id.clone(),
+                    saved: saved.clone(),
+                    current: current.clone(),
+                });
+            }
+            _ => {
+                if let (Some(saved), Some(current)) = (&record.sha256, &now.sha256)
+                    && saved != current
+                {
+                    changes.push(ModSetChange::ArchiveChanged { id: record.id.clone() });
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// Describe the changes found when loading a zoo
+pub fn format_changes(changes: &[ModSetChange]) -> String {
+    if changes.is_empty() {
+        return "All mods this zoo was saved with are loaded and unchanged".to_string();
+    }
+
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "{} mods changed since this zoo was saved, parts of the zoo may be missing or broken:",
+        changes.len()
+    );
+    for change in changes {
+        let _ = match change {
+            ModSetChange::Removed { id } => writeln!(report, "  {}: no longer loaded", id),
+            ModSetChange::VersionChanged { id, saved, current } => writeln!(report, "  {}: saved with {}, now {}", id, saved, current),
+            ModSetChange::ArchiveChanged { id } => writeln!(report, "  {}: archive contents changed", id),
+        };
+    }
+    report
+}
+
+/// The mod set file next to a save
+pub fn mod_set_path(zoo_file: &Path) -> PathBuf {
+    let mut path = zoo_file.as_os_str().to_owned();
+    path.push(MOD_SET_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Remember the zoo picked in the save or load dialog
+pub fn set_zoo_file(zoo_file: &str) {
+    runtime_state::set_string(ZOO_FILE_KEY, zoo_file.to_string());
+}
+
+fn zoo_file() -> Option<PathBuf> {
+    let zoo_file = runtime_state::get_string(ZOO_FILE_KEY);
+    (!zoo_file.is_empty()).then(|| PathBuf::from(zoo_file))
+}
+
+/// Write the current mod set next to the zoo that was just saved
+pub fn game_saved() {
+    let Some(zoo_file) = zoo_file() else {
+        return;
+    };
+    let path = mod_set_path(&zoo_file);
+    let contents = match toml::to_string(&current_mod_set()) {
+        Ok(toml) => format!("# Mods loaded when this zoo was saved, written by OpenZT\n{}", toml),
+        Err(e) => {
+            error!("Failed to serialize the mod set for {}: {}", zoo_file.display(), e);
+            return;
+        }
+    };
+    match std::fs::write(&path, contents) {
+        Ok(()) => info!("Mod set written to {}", path.display()),
+        Err(e) => error!("Failed to write mod set to {}: {}", path.display(), e),
+    }
+}
+
+/// Compare the mods the current zoo was saved with against the loaded ones, `None` if it has no mod set file
+pub fn check_zoo() -> Option<anyhow::Result<Vec<ModSetChange>>> {
+    let path = mod_set_path(&zoo_file()?);
+    if !path.exists() {
+        return None;
+    }
+    let saved = std::fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|contents| toml::from_str::<ModSet>(&contents).map_err(anyhow::Error::from));
+    Some(saved.map(|saved| compare(&saved, &current_mod_set())))
+}
+
+/// Warn about mods that changed since the zoo that was just loaded was saved
+pub fn game_loaded() {
+    match check_zoo() {
+        Some(Ok(changes)) if changes.is_empty() => info!("{}", format_changes(&changes)),
+        Some(Ok(changes)) => warn!("{}", format_changes(&changes).trim_end()),
+        Some(Err(e)) => error!("Failed to read the mods this zoo was saved with: {:#}", e),
+        None => {}
+    }
+}
+
+pub fn init() {
+    if unsafe { save_hooks::init_detours() }.is_err() {
+        error!("Error initialising save game mod set detours");
+    }
+}
+
+#[detour_mod]
+mod save_hooks {
+    use openzt_detour::generated::standalone::{GET_OPEN_FILE_NAME_A, GET_SAVE_FILE_NAME_A};
+    use openzt_detour::generated::ztui_gameopts::LOAD_GAME;
+
+    use crate::util::{get_from_memory, get_string_from_memory};
+
+    /// Offset of `lpstrFile` in OPENFILENAMEA
+    const OFN_FILE_OFFSET: u32 = 0x1c;
+
+    fn remember_zoo_file(open_file_name: u32) {
+        let file_ptr = get_from_memory::<u32>(open_file_name + OFN_FILE_OFFSET);
+        if file_ptr != 0 {
+            super::set_zoo_file(&get_string_from_memory(file_ptr));
+        }
+    }
+
+    #[detour(GET_SAVE_FILE_NAME_A)]
+    unsafe extern "stdcall" fn get_save_file_name(open_file_name: u32) -> bool {
+        let picked = unsafe { GET_SAVE_FILE_NAME_A_DETOUR.call(open_file_name) };
+        if picked {
+            remember_zoo_file(open_file_name);
+        }
+        picked
+    }
+
+    #[detour(GET_OPEN_FILE_NAME_A)]
+    unsafe extern "stdcall" fn get_open_file_name(open_file_name: u32) -> bool {
+        let picked = unsafe { GET_OPEN_FILE_NAME_A_DETOUR.call(open_file_name) };
+        if picked {
+            remember_zoo_file(open_file_name);
+        }
+        picked
+    }
+
+    #[detour(LOAD_GAME)]
+    unsafe extern "stdcall" fn load_game() -> u32 {
+        let result = unsafe { LOAD_GAME_DETOUR.call() };
+        super::game_loaded();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mod_versions(entries: &[(&str, &str, &str)]) -> HashMap<String, (String, String)> {
+        entries
+            .iter()
+            .map(|(mod_id, version, archive)| (mod_id.to_string(), (version.to_string(), archive.to_string())))
+            .collect()
+    }
+
+    fn archives(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries.iter().map(|(archive, sha256)| (archive.to_string(), sha256.to_string())).collect()
+    }
+
+    #[test]
+    fn test_build_mod_set() {
+        let set = build_mod_set(
+            &mod_versions(&[("finn.moon", "1.0.0", "moon.ztd")]),
+            &archives(&[("moon.ztd", "aa"), ("fence.ztd", "bb")]),
+        );
+        let toml = toml::to_string(&set).unwrap();
+        assert_eq!(toml::from_str::<ModSet>(&toml).unwrap(), set);
+        assert_eq!(
+            set.mods,
+            vec![
+                ModRecord {
+                    id: "fence.ztd".to_string(),
+                    version: None,
+                    archive: "fence.ztd".to_string(),
+                    sha256: Some("bb".to_string()),
+                },
+                ModRecord {
+                    id: "finn.moon".to_string(),
+                    version: Some("1.0.0".to_string()),
+                    archive: "moon.ztd".to_string(),
+                    sha256: Some("aa".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_mod_sets() {
+        let saved = build_mod_set(
+            &mod_versions(&[
+                ("finn.moon", "1.0.0", "moon.ztd"),
+                ("finn.sun", "1.0.0", "sun.ztd"),
+                ("finn.star", "2.0.0", "star.ztd"),
+            ]),
+            &archives(&[("moon.ztd", "aa"), ("sun.ztd", "bb"), ("star.ztd", "cc"), ("fence.ztd", "dd")]),
+        );
+        let current = build_mod_set(
+            &mod_versions(&[
+                ("finn.moon", "1.1.0", "moon.ztd"),
+                ("finn.star", "2.0.0", "star.ztd"),
+                ("finn.comet", "1.0.0", "comet.ztd"),
+            ]),
+            &archives(&[("moon.ztd", "ee"), ("star.ztd", "ff"), ("fence.ztd", "dd")]),
+        );
+
+        let changes = compare(&saved, &current);
+        assert_eq!(
+            changes,
+            vec![
+                ModSetChange::VersionChanged {
+                    id: "finn.moon".to_string(),
+                    saved: "1.0.0".to_string(),
+                    current: "1.1.0".to_string(),
+                },
+                ModSetChange::ArchiveChanged { id: "finn.star".to_string() },
+                ModSetChange::Removed { id: "finn.sun".to_string() },
+            ]
+        );
+        assert_eq!(
+            format_changes(&changes),
+            "3 mods changed since this zoo was saved, parts of the zoo may be missing or broken:\n  finn.moon: saved with 1.0.0, now 1.1.0\n  finn.star: archive contents changed\n  finn.sun: no longer loaded\n"
+        );
+        assert!(compare(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_mod_set_path() {
+        assert_eq!(mod_set_path(Path::new("saves/My Zoo.zoo")), PathBuf::from("saves/My Zoo.zoo.mods.toml"));
+    }
+}
@@ -0,0 +1,121 @@
+//! Signature checks for archives in /mods/
+//!
+//! OpenZT mods can carry an Ed25519 signature over their files, added with
+//! `openzt-mod build --sign`. When `signature_policy` in the `[mod_loading]`
+//! section of openzt.toml is set, each mod's signature is checked against
+//! `trusted_keys` before the mod is loaded, and mods not signed by a trusted key
+//! are logged (`warn`) or skipped (`refuse`). Legacy archives can't carry a
+//! signature, so `refuse` skips those in /mods/ as well.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use openzt_mod::signing::{self, Signature};
+use tracing::{error, info, warn};
+
+use crate::resource_manager::mod_config::{get_openzt_config, SignaturePolicy};
+
+/// Whether an archive is signed by a trusted key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trust {
+    Trusted {
+        signer: String,
+    },
+    /// Validly signed, but with a key that isn't in `trusted_keys`
+    Untrusted {
+        key: String,
+    },
+    Unsigned,
+    /// signature.toml is malformed or doesn't match the archive's files
+    Invalid(String),
+}
+
+/// Look up the key of a valid signature in `trusted_keys`
+fn trust(signature: Signature, trusted_keys: &IndexMap<String, String>) -> Trust {
+    match signature {
+        Signature::Valid { key } => match trusted_keys.iter().find(|(_, trusted)| trusted.trim().eq_ignore_ascii_case(&key)) {
+            Some((signer, _)) => Trust::Trusted { signer: signer.clone() },
+            None => Trust::Untrusted { key },
+        },
+        Signature::Unsigned => Trust::Unsigned,
+        Signature::Invalid(reason) => Trust::Invalid(reason),
+    }
+}
+
+/// Decide whether an archive with `signature` is loaded under `policy`
+pub fn evaluate(signature: Signature, trusted_keys: &IndexMap<String, String>, policy: SignaturePolicy) -> (Trust, bool) {
+    let trust = trust(signature, trusted_keys);
+    let loaded = policy != SignaturePolicy::Refuse || matches!(trust, Trust::Trusted { .. });
+    (trust, loaded)
+}
+
+/// Check the signature of an archive from /mods/ using the configured policy
+///
+/// `mod_files` are the files of an OpenZT mod, `None` for a legacy archive.
+/// Returns whether the archive should be loaded.
+pub fn check(name: &str, mod_files: Option<&HashMap<String, Arc<[u8]>>>) -> bool {
+    let config = get_openzt_config().mod_loading;
+    if config.signature_policy == SignaturePolicy::Off {
+        return true;
+    }
+
+    let signature = match mod_files {
+        Some(files) => signing::verify(files.iter().map(|(file_name, data)| (file_name.as_str(), &**data))),
+        None => Signature::Unsigned,
+    };
+    let (trust, loaded) = evaluate(signature, &config.trusted_keys, config.signature_policy);
+
+    let problem = match trust {
+        Trust::Trusted { signer } => {
+            info!("'{}' is signed by trusted key '{}'", name, signer);
+            return true;
+        }
+        Trust::Untrusted { key } => format!("'{}' is signed with a key that isn't in trusted_keys: {}", name, key),
+        Trust::Unsigned if mod_files.is_none() => format!("'{}' is a legacy archive, which can't be signed", name),
+        Trust::Unsigned => format!("'{}' is not signed", name),
+        Trust::Invalid(reason) => format!("'{}' has an invalid signature: {}", name, reason),
+    };
+    if loaded {
+        warn!("{}", problem);
+    } else {
+        error!("Skipping {} (signature_policy = \"refuse\")", problem);
+    }
+    loaded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_manager::mod_config::SignaturePolicy::{Refuse, Warn};
+
+    const KEY: &str = "4a6adfb81deae050ea94e16094689da09acd75e7daeacc7d03af0894c69e1788";
+
+    fn trusted_keys() -> IndexMap<String, String> {
+        IndexMap::from([("finn".to_string(), KEY.to_uppercase())])
+    }
+
+    fn signed_with(key: &str) -> Signature {
+        Signature::Valid { key: key.to_string() }
+    }
+
+    #[test]
+    fn test_trusted_keys() {
+        let (trust, loaded) = evaluate(signed_with(KEY), &trusted_keys(), Refuse);
+        assert_eq!(trust, Trust::Trusted { signer: "finn".to_string() });
+        assert!(loaded);
+
+        let other = "0".repeat(64);
+        assert_eq!(evaluate(signed_with(&other), &trusted_keys(), Warn), (Trust::Untrusted { key: other.clone() }, true));
+        assert_eq!(evaluate(signed_with(&other), &IndexMap::new(), Refuse), (Trust::Untrusted { key: other }, false));
+    }
+
+    #[test]
+    fn test_refuse_skips_unsigned_and_invalid() {
+        assert_eq!(evaluate(Signature::Unsigned, &trusted_keys(), Warn), (Trust::Unsigned, true));
+        assert_eq!(evaluate(Signature::Unsigned, &trusted_keys(), Refuse), (Trust::Unsigned, false));
+
+        let invalid = Signature::Invalid("the signature does not match the mod's files".to_string());
+        assert!(!evaluate(invalid, &trusted_keys(), Refuse).1);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::mods::{DependencyIdentifier, Meta, Ordering};
-use std::collections::HashMap;
+use crate::resource_manager::mod_config::ConflictPolicy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use tracing::{info, warn};
 
@@ -162,6 +164,100 @@ pub fn validate_load_order(order: &[String], mods: &HashMap<String, Meta>, pure_
     ValidationResult { is_valid, warnings, errors }
 }
 
+/// A conflict declared in meta.toml between two enabled mods
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModConflict {
+    /// Mod that declared the conflict
+    pub mod_id: String,
+    pub conflicts_with: String,
+    pub conflicts_with_version: String,
+    pub reason: Option<String>,
+}
+
+/// Find declared conflicts that apply between mods in the enabled load order
+pub fn find_conflicts(enabled_order: &[String], mods: &HashMap<String, Meta>) -> Vec<ModConflict> {
+    let enabled: HashSet<&String> = enabled_order.iter().collect();
+    let mut conflicts = Vec::new();
+
+    for mod_id in enabled_order {
+        let Some(meta) = mods.get(mod_id) else {
+            continue;
+        };
+
+        for conflict in meta.conflicts() {
+            let Some(other) = mods.get(conflict.mod_id()) else {
+                continue;
+            };
+            if !enabled.contains(conflict.mod_id()) || !conflict.applies_to(other.version()) {
+                continue;
+            }
+
+            conflicts.push(ModConflict {
+                mod_id: mod_id.clone(),
+                conflicts_with: conflict.mod_id().clone(),
+                conflicts_with_version: other.version().to_string(),
+                reason: conflict.reason().clone(),
+            });
+        }
+    }
+
+    conflicts
+}
+
+/// Log all conflicts as a single report and apply the policy to the load order
+///
+/// With `ConflictPolicy::Block`, each mod that declared an applicable conflict
+/// is removed from the returned order, along with the mods that require it.
+pub fn apply_conflict_policy(enabled_order: Vec<String>, conflicts: &[ModConflict], mods: &HashMap<String, Meta>, policy: ConflictPolicy) -> Vec<String> {
+    if conflicts.is_empty() {
+        return enabled_order;
+    }
+
+    let mut report = format!("{} mod conflict(s) found:", conflicts.len());
+    for conflict in conflicts {
+        report.push_str(&format!(
+            "\n  '{}' conflicts with '{}' ({})",
+            conflict.mod_id, conflict.conflicts_with, conflict.conflicts_with_version
+        ));
+        if let Some(reason) = &conflict.reason {
+            report.push_str(&format!(": {}", reason));
+        }
+    }
+
+    match policy {
+        ConflictPolicy::Warn => {
+            warn!("{}\nLoading all mods anyway (conflict_policy = \"warn\")", report);
+            enabled_order
+        }
+        ConflictPolicy::Block => {
+            let mut blocked: HashSet<String> = conflicts.iter().map(|conflict| conflict.mod_id.clone()).collect();
+            warn!("{}\nNot loading: {:?} (conflict_policy = \"block\")", report, blocked);
+
+            // A mod can't load without the mods it requires, which may in turn be required by others
+            loop {
+                let dependents: Vec<String> = enabled_order
+                    .iter()
+                    .filter(|mod_id| !blocked.contains(*mod_id) && mods.get(*mod_id).is_some_and(|meta| requires_any(meta, &blocked)))
+                    .cloned()
+                    .collect();
+                if dependents.is_empty() {
+                    break;
+                }
+                warn!("Not loading: {:?}, they require a mod that was blocked (conflict_policy = \"block\")", dependents);
+                blocked.extend(dependents);
+            }
+            enabled_order.into_iter().filter(|mod_id| !blocked.contains(mod_id)).collect()
+        }
+    }
+}
+
+/// Whether a mod has a required dependency on any of `mod_ids`
+fn requires_any(meta: &Meta, mod_ids: &HashSet<String>) -> bool {
+    meta.dependencies()
+        .iter()
+        .any(|dep| !*dep.optional() && matches!(dep.identifier(), DependencyIdentifier::ModId(id) if mod_ids.contains(id)))
+}
+
 /// Log validation warnings and errors
 pub fn log_validation_result(result: &ValidationResult) {
     if result.warnings.is_empty() && result.errors.is_empty() {
@@ -234,4 +330,90 @@ mod tests {
         assert!(result.warnings.is_empty());
         assert!(result.errors.is_empty());
     }
+
+    fn create_test_meta(mod_id: &str, version: &str, conflicts: &str) -> Meta {
+        toml::from_str(&format!(
+            "name = \"{mod_id}\"\ndescription = \"Test\"\nauthors = [\"Test\"]\nmod_id = \"{mod_id}\"\nversion = \"{version}\"\nconflicts = [{conflicts}]"
+        ))
+        .expect("Failed to parse test TOML")
+    }
+
+    #[test]
+    fn test_conflicts_between_enabled_mods() {
+        let mut mods = HashMap::new();
+        mods.insert(
+            "test.regions_a".to_string(),
+            create_test_meta(
+                "test.regions_a",
+                "1.0.0",
+                r#"{ mod_id = "test.regions_b", max_version = "2.0.0", reason = "Both rewrite regions" }"#,
+            ),
+        );
+        mods.insert("test.regions_b".to_string(), create_test_meta("test.regions_b", "1.5.0", ""));
+        mods.insert("test.other".to_string(), create_test_meta("test.other", "1.0.0", ""));
+
+        let order = vec!["test.regions_a".to_string(), "test.regions_b".to_string(), "test.other".to_string()];
+        let conflicts = find_conflicts(&order, &mods);
+        assert_eq!(
+            conflicts,
+            vec![ModConflict {
+                mod_id: "test.regions_a".to_string(),
+                conflicts_with: "test.regions_b".to_string(),
+                conflicts_with_version: "1.5.0".to_string(),
+                reason: Some("Both rewrite regions".to_string()),
+            }]
+        );
+
+        assert_eq!(apply_conflict_policy(order.clone(), &conflicts, &mods, ConflictPolicy::Warn), order);
+        assert_eq!(
+            apply_conflict_policy(order, &conflicts, &mods, ConflictPolicy::Block),
+            vec!["test.regions_b".to_string(), "test.other".to_string()]
+        );
+
+        // Disabled (absent from the enabled order) mods don't conflict
+        assert!(find_conflicts(&["test.regions_a".to_string()], &mods).is_empty());
+    }
+
+    #[test]
+    fn test_block_skips_mods_requiring_a_blocked_mod() {
+        let with_dependency = |mod_id: &str, dependency: &str, optional: bool| -> Meta {
+            toml::from_str(&format!(
+                "name = \"{mod_id}\"\ndescription = \"Test\"\nauthors = [\"Test\"]\nmod_id = \"{mod_id}\"\nversion = \"1.0.0\"\n\
+                 dependencies = [{{ mod_id = \"{dependency}\", name = \"{dependency}\", optional = {optional} }}]"
+            ))
+            .expect("Failed to parse test TOML")
+        };
+        let mut mods = HashMap::new();
+        mods.insert(
+            "test.regions_a".to_string(),
+            create_test_meta("test.regions_a", "1.0.0", r#"{ mod_id = "test.regions_b" }"#),
+        );
+        mods.insert("test.regions_b".to_string(), create_test_meta("test.regions_b", "1.0.0", ""));
+        mods.insert("test.addon".to_string(), with_dependency("test.addon", "test.regions_a", false));
+        mods.insert("test.addon_patch".to_string(), with_dependency("test.addon_patch", "test.addon", false));
+        mods.insert("test.optional".to_string(), with_dependency("test.optional", "test.regions_a", true));
+
+        let order: Vec<String> = ["test.regions_a", "test.regions_b", "test.addon", "test.addon_patch", "test.optional"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let conflicts = find_conflicts(&order, &mods);
+        assert_eq!(
+            apply_conflict_policy(order, &conflicts, &mods, ConflictPolicy::Block),
+            vec!["test.regions_b".to_string(), "test.optional".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_conflict_outside_version_range() {
+        let mut mods = HashMap::new();
+        mods.insert(
+            "test.regions_a".to_string(),
+            create_test_meta("test.regions_a", "1.0.0", r#"{ mod_id = "test.regions_b", max_version = "2.0.0" }"#),
+        );
+        mods.insert("test.regions_b".to_string(), create_test_meta("test.regions_b", "2.1.0", ""));
+
+        let order = vec!["test.regions_a".to_string(), "test.regions_b".to_string()];
+        assert!(find_conflicts(&order, &mods).is_empty());
+    }
 }
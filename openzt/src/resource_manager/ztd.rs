@@ -1,29 +1,129 @@
 use std::{
     fs::File,
-    io::{BufReader, Read},
+    io::{BufReader, Cursor, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
     str,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
-use anyhow::Context;
-use zip::ZipArchive;
+use anyhow::{anyhow, Context};
+use memmap2::Mmap;
+use tracing::debug;
+use zip::{result::ZipError, ZipArchive};
 
+use crate::resource_manager::checksums;
+
+/// Largest archive that is memory-mapped, larger ones are read through a buffered file
+///
+/// Mappings stay for as long as the archive is open, and the game is a 32-bit process,
+/// so mapping large archives would use up its address space.
+const MAX_MAPPED_ARCHIVE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Most bytes of archives that are memory-mapped at once, across all open archives
+///
+/// Once reached, archives are read through a buffered file until mapped archives are closed.
+const MAPPED_BUDGET: u64 = 128 * 1024 * 1024;
+
+/// Bytes of the archives currently memory-mapped
+static MAPPED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Count `size` more bytes in `mapped`, unless that would take it over `budget`
+fn reserve(mapped: &AtomicU64, size: u64, budget: u64) -> bool {
+    mapped
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |mapped| {
+            mapped.checked_add(size).filter(|total| *total <= budget)
+        })
+        .is_ok()
+}
+
+/// Bytes counted against [`MAPPED_BUDGET`], given back when dropped
+struct MappingReservation(u64);
+
+impl MappingReservation {
+    fn new(size: u64) -> Option<Self> {
+        reserve(&MAPPED_BYTES, size, MAPPED_BUDGET).then_some(MappingReservation(size))
+    }
+}
+
+impl Drop for MappingReservation {
+    fn drop(&mut self) {
+        MAPPED_BYTES.fetch_sub(self.0, Ordering::Relaxed);
+    }
+}
+
+/// A memory-mapped archive, counted against [`MAPPED_BUDGET`] while it is open
+pub struct MappedArchive {
+    map: Mmap,
+    _reservation: MappingReservation,
+}
+
+impl AsRef<[u8]> for MappedArchive {
+    fn as_ref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+/// Opens .ztd archives, including zip64 archives over 4 GB or with more than 65535 entries
+///
+/// Only the central directory is read up front, files are decompressed one at a time
+/// when they are read. Small archives are memory-mapped, up to [`MAPPED_BUDGET`] in total,
+/// so reading many files from them doesn't go through a syscall for every buffer refill.
 pub struct ZtdArchive {
-    archive: ZipArchive<BufReader<File>>,
+    archive: ZipArchive<ArchiveReader>,
     archive_name: String,
     archive_path: PathBuf,
 }
 
 impl ZtdArchive {
     pub fn new(archive_path: &Path) -> anyhow::Result<Self> {
+        Self::open(archive_path, MAX_MAPPED_ARCHIVE_SIZE)
+    }
+
+    /// Open an archive and compute the SHA-256 of its bytes
+    ///
+    /// The hash is read through the same file handle or mapping the archive's files are
+    /// decompressed from, so it covers the bytes that are loaded.
+    pub fn new_hashed(archive_path: &Path) -> anyhow::Result<(Self, String)> {
+        let mut reader = Self::reader(archive_path, MAX_MAPPED_ARCHIVE_SIZE)?;
+        let sha256 = checksums::sha256_reader(&mut reader).with_context(|| format!("Failed to hash archive {}", archive_path.display()))?;
+        reader.rewind().with_context(|| format!("Failed to read archive {}", archive_path.display()))?;
+        Ok((Self::from_reader(archive_path, reader)?, sha256))
+    }
+
+    /// Open an archive, mapping it if it is at most `max_mapped_size` bytes
+    fn open(archive_path: &Path, max_mapped_size: u64) -> anyhow::Result<Self> {
+        Self::from_reader(archive_path, Self::reader(archive_path, max_mapped_size)?)
+    }
+
+    /// Where to read an archive from, a mapping if it is at most `max_mapped_size` bytes and
+    /// fits in the mapping budget, or else the file
+    fn reader(archive_path: &Path, max_mapped_size: u64) -> anyhow::Result<ArchiveReader> {
+        let file = File::open(archive_path).with_context(|| format!("Failed to open archive {}", archive_path.display()))?;
+        let size = file.metadata().with_context(|| format!("Failed to read archive {}", archive_path.display()))?.len();
+        if size > max_mapped_size {
+            return Ok(ArchiveReader::File(BufReader::new(file)));
+        }
+        let Some(reservation) = MappingReservation::new(size) else {
+            debug!(
+                "Mapped archives have reached {} bytes, reading {} from the file",
+                MAPPED_BUDGET,
+                archive_path.display()
+            );
+            return Ok(ArchiveReader::File(BufReader::new(file)));
+        };
+
+        // SAFETY: archives aren't written to while the game is running, and on Windows the
+        // mapping stops other processes from truncating the file underneath it
+        let map = unsafe { Mmap::map(&file) }.with_context(|| format!("Failed to map archive {}", archive_path.display()))?;
+        Ok(ArchiveReader::Mapped(Cursor::new(MappedArchive { map, _reservation: reservation })))
+    }
+
+    fn from_reader(archive_path: &Path, reader: ArchiveReader) -> anyhow::Result<Self> {
         let archive_name = archive_path
             .to_str()
             .with_context(|| format!("Error reading archive path {}", archive_path.display()))?
             .to_string();
-        let archive = ZipArchive::new(BufReader::new(
-            File::open(archive_path).with_context(|| format!("Failed to open archive {}", archive_path.display()))?,
-        ))
-        .with_context(|| format!("Failed to read archive {}", archive_path.display()))?;
+        let archive = ZipArchive::new(reader).map_err(|e| open_error(archive_path, e))?;
 
         Ok(Self {
             archive,
@@ -61,7 +161,40 @@ impl ZtdArchive {
     }
 }
 
-pub struct ZtdFile<'a, R: Read = BufReader<File>> {
+/// Where an archive's bytes are read from
+pub enum ArchiveReader {
+    File(BufReader<File>),
+    Mapped(Cursor<MappedArchive>),
+}
+
+impl Read for ArchiveReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            ArchiveReader::File(reader) => reader.read(buf),
+            ArchiveReader::Mapped(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl Seek for ArchiveReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            ArchiveReader::File(reader) => reader.seek(pos),
+            ArchiveReader::Mapped(reader) => reader.seek(pos),
+        }
+    }
+}
+
+/// Describe why an archive couldn't be read, telling corrupt archives apart from I/O errors
+fn open_error(archive_path: &Path, error: ZipError) -> anyhow::Error {
+    match error {
+        ZipError::InvalidArchive(reason) => anyhow!("{} is corrupt or not a zip archive: {}", archive_path.display(), reason),
+        ZipError::UnsupportedArchive(reason) => anyhow!("{} uses a zip feature that isn't supported: {}", archive_path.display(), reason),
+        error => anyhow::Error::new(error).context(format!("Failed to read archive {}", archive_path.display())),
+    }
+}
+
+pub struct ZtdFile<'a, R: Read = ArchiveReader> {
     inner: zip::read::ZipFile<'a, R>,
 }
 
@@ -76,9 +209,33 @@ impl<R: Read> ZtdFile<'_, R> {
         self.inner.name()
     }
 
-    pub fn read_exact(&mut self, buffer: &mut [u8]) -> anyhow::Result<()> {
-        self.inner.read_exact(buffer).with_context(|| format!("Error reading file: {}", self.inner.name()))?;
-        Ok(())
+    /// Decompress the whole file
+    ///
+    /// Fails instead of aborting when the file is too large to fit in memory, and
+    /// checks the file's size and CRC so a corrupt entry is an error rather than garbage.
+    pub fn read_all(&mut self) -> anyhow::Result<Box<[u8]>> {
+        let name = self.inner.name().to_string();
+        let size = self.inner.size();
+        let mut buffer = Vec::new();
+        usize::try_from(size)
+            .ok()
+            .and_then(|capacity| buffer.try_reserve_exact(capacity).ok())
+            .with_context(|| format!("{} is too large to load ({} bytes)", name, size))?;
+
+        // Reading one byte past the expected size reaches the end of the entry, where the CRC is checked
+        (&mut self.inner)
+            .take(size.saturating_add(1))
+            .read_to_end(&mut buffer)
+            .with_context(|| format!("Error reading file: {}, the archive may be corrupt", name))?;
+        if buffer.len() as u64 != size {
+            return Err(anyhow!(
+                "Error reading file: {}, expected {} bytes but found {}, the archive may be corrupt",
+                name,
+                size,
+                buffer.len()
+            ));
+        }
+        Ok(buffer.into_boxed_slice())
     }
 
     pub fn size(&self) -> u64 {
@@ -90,11 +247,7 @@ impl<R: Read> ZtdFile<'_, R> {
     }
 
     pub fn read_to_string(&mut self) -> anyhow::Result<String> {
-        let mut buffer = vec![0u8; self.inner.size() as usize].into_boxed_slice();
-        self.inner
-            .read_exact(&mut buffer)
-            .with_context(|| format!("Error reading file: {}", self.inner.name()))?;
-
+        let buffer = self.read_all()?;
         Ok(crate::encoding_utils::decode_game_text(&buffer))
     }
 }
@@ -103,11 +256,94 @@ impl<R: Read> TryFrom<ZtdFile<'_, R>> for String {
     type Error = anyhow::Error;
 
     fn try_from(mut file: ZtdFile<'_, R>) -> Result<String, Self::Error> {
-        let mut buffer = vec![0u8; file.size() as usize].into_boxed_slice();
-        file.inner
-            .read_exact(&mut buffer)
-            .with_context(|| format!("Error reading file: {}", file.inner.name()))?;
+        file.read_to_string()
+    }
+}
 
-        Ok(crate::encoding_utils::decode_game_text(&buffer))
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    use super::*;
+
+    const ELEPHANT: &[u8] = b"[Global]\nType = elephant\n";
+
+    fn write_ztd(name: &str, options: SimpleFileOptions) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("openzt-ztd-test-{}-{}.ztd", name, std::process::id()));
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("animals/elephant.ai", options).unwrap();
+        zip.write_all(ELEPHANT).unwrap();
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_zip64_archive() {
+        let path = write_ztd("zip64", SimpleFileOptions::default().large_file(true));
+
+        let mut archive = ZtdArchive::new(&path).unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive.by_name("animals/elephant.ai").unwrap().read_all().unwrap().as_ref(), ELEPHANT);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_read_buffered_and_mapped_archives() {
+        let path = write_ztd("buffered", SimpleFileOptions::default());
+
+        // Larger than the mapping limit, and within it
+        for max_mapped_size in [0, u64::MAX] {
+            let mut archive = ZtdArchive::open(&path, max_mapped_size).unwrap();
+            assert_eq!(archive.file_names().collect::<Vec<_>>(), ["animals/elephant.ai"]);
+            assert_eq!(archive.by_index(0).unwrap().read_all().unwrap().as_ref(), ELEPHANT);
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_mapping_budget() {
+        let mapped = AtomicU64::new(0);
+        assert!(reserve(&mapped, 60, 100));
+        assert!(!reserve(&mapped, 50, 100));
+        assert!(reserve(&mapped, 40, 100));
+        assert_eq!(mapped.load(Ordering::Relaxed), 100);
+        assert!(!reserve(&mapped, 1, 100));
+        assert!(!reserve(&mapped, u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn test_read_hashed_archive() {
+        let path = write_ztd("hashed", SimpleFileOptions::default());
+
+        let (mut archive, sha256) = ZtdArchive::new_hashed(&path).unwrap();
+        assert_eq!(sha256, checksums::sha256_file(&path).unwrap());
+        assert_eq!(archive.by_name("animals/elephant.ai").unwrap().read_all().unwrap().as_ref(), ELEPHANT);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_corrupt_archives() {
+        let path = std::env::temp_dir().join(format!("openzt-ztd-test-garbage-{}.ztd", std::process::id()));
+        std::fs::write(&path, b"not a zip archive").unwrap();
+        let error = ZtdArchive::new(&path).err().unwrap();
+        assert!(error.to_string().contains("is corrupt or not a zip archive"), "{:#}", error);
+        let _ = std::fs::remove_file(&path);
+
+        // Flip a byte of a stored file, the archive still opens but the CRC no longer matches
+        let path = write_ztd("crc", SimpleFileOptions::default().compression_method(CompressionMethod::Stored));
+        let mut data = std::fs::read(&path).unwrap();
+        let offset = data.windows(ELEPHANT.len()).position(|window| window == ELEPHANT).unwrap();
+        data[offset] ^= 0xff;
+        std::fs::write(&path, data).unwrap();
+
+        let mut archive = ZtdArchive::new(&path).unwrap();
+        let error = archive.by_name("animals/elephant.ai").unwrap().read_all().unwrap_err();
+        assert!(error.to_string().contains("the archive may be corrupt"), "{:#}", error);
+        let _ = std::fs::remove_file(&path);
     }
 }
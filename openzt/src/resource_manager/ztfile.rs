@@ -1,4 +1,11 @@
-use std::{ffi::CString, fmt, path::Path, str};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fmt,
+    path::Path,
+    str,
+    sync::{LazyLock, Mutex},
+};
 
 use anyhow::{anyhow, Context};
 use openzt_configparser::ini::{Ini, WriteOptions};
@@ -35,6 +42,8 @@ pub enum ZTFileType {
     Lle,
     Bmp,
     Zoo,
+    /// A type registered with [register_file_type], or an unknown extension kept as raw bytes
+    Other(&'static str),
 }
 
 impl fmt::Display for ZTFileType {
@@ -58,16 +67,15 @@ impl fmt::Display for ZTFileType {
             ZTFileType::Lle => write!(f, "lle"),
             ZTFileType::Bmp => write!(f, "bmp"),
             ZTFileType::Zoo => write!(f, "zoo"),
+            ZTFileType::Other(extension) => write!(f, "{}", extension),
         }
     }
 }
 
-impl TryFrom<&Path> for ZTFileType {
-    type Error = &'static str;
-
-    fn try_from(path: &Path) -> Result<Self, Self::Error> {
-        let extension = path.extension().unwrap_or_default().to_ascii_lowercase();
-        Ok(match extension.to_str().unwrap_or_default() {
+impl ZTFileType {
+    /// The game's own file type for an extension
+    fn builtin(extension: &str) -> Option<ZTFileType> {
+        Some(match extension {
             "ai" => ZTFileType::Ai,
             "ani" => ZTFileType::Ani,
             "cfg" => ZTFileType::Cfg,
@@ -86,9 +94,116 @@ impl TryFrom<&Path> for ZTFileType {
             "pal" => ZTFileType::Palette,
             "zoo" => ZTFileType::Zoo,
             "" => ZTFileType::Animation,
-            _ => return Err("Invalid file type"),
+            _ => return None,
         })
     }
+
+    /// Whether files of this type are text rather than raw bytes
+    pub fn is_text(&self) -> bool {
+        match self {
+            ZTFileType::Ini
+            | ZTFileType::Ai
+            | ZTFileType::Ani
+            | ZTFileType::Cfg
+            | ZTFileType::Lyt
+            | ZTFileType::Scn
+            | ZTFileType::Uca
+            | ZTFileType::Ucs
+            | ZTFileType::Ucb
+            | ZTFileType::Txt
+            | ZTFileType::Toml => true,
+            ZTFileType::Other(extension) => FILE_TYPES
+                .lock()
+                .unwrap()
+                .get(*extension)
+                .is_some_and(|file_type| file_type.format == ZTFileFormat::Text),
+            _ => false,
+        }
+    }
+
+    /// Convert a file's bytes with the loader registered for its type, unchanged for other types
+    pub fn load(&self, file_name: &str, data: Box<[u8]>) -> anyhow::Result<Box<[u8]>> {
+        let ZTFileType::Other(extension) = self else {
+            return Ok(data);
+        };
+        let loader = FILE_TYPES.lock().unwrap().get(*extension).and_then(|file_type| file_type.loader);
+        match loader {
+            Some(loader) => loader(file_name, data).with_context(|| format!("Error loading {} as a .{} file", file_name, extension)),
+            None => Ok(data),
+        }
+    }
+}
+
+impl TryFrom<&Path> for ZTFileType {
+    type Error = &'static str;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let extension = path.extension().unwrap_or_default().to_ascii_lowercase();
+        let extension = extension.to_str().ok_or("Invalid file type")?;
+        Ok(ZTFileType::builtin(extension).unwrap_or_else(|| registered_or_raw(extension)))
+    }
+}
+
+/// How the data of a registered file type is stored in the resource map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZTFileFormat {
+    /// Text, patched and shown like the game's config files
+    Text,
+    RawBytes,
+}
+
+/// Converts a file's bytes when it is read into the resource map, given its file name
+pub type FileTypeLoader = fn(&str, Box<[u8]>) -> anyhow::Result<Box<[u8]>>;
+
+struct RegisteredFileType {
+    format: ZTFileFormat,
+    loader: Option<FileTypeLoader>,
+    /// Not registered by anyone, added the first time a file with the extension was seen
+    fallback: bool,
+}
+
+/// File types for extensions the game doesn't have, keyed by lowercase extension
+static FILE_TYPES: LazyLock<Mutex<HashMap<&'static str, RegisteredFileType>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The registered type for an extension, registering it as raw bytes if nobody has
+fn registered_or_raw(extension: &str) -> ZTFileType {
+    let mut file_types = FILE_TYPES.lock().unwrap();
+    if let Some((extension, _)) = file_types.get_key_value(extension) {
+        return ZTFileType::Other(extension);
+    }
+
+    // Leaked once per extension, so the type can stay Copy
+    let extension: &'static str = Box::leak(extension.to_string().into_boxed_str());
+    file_types.insert(
+        extension,
+        RegisteredFileType {
+            format: ZTFileFormat::RawBytes,
+            loader: None,
+            fallback: true,
+        },
+    );
+    ZTFileType::Other(extension)
+}
+
+/// Register a file type for an extension the game doesn't have
+///
+/// Files with the extension are stored in `format`, converted by `loader` when they are
+/// read from an archive. Register before mods are loaded; extensions nobody registers are
+/// kept as raw bytes. Fails for the game's own extensions and ones already registered.
+pub fn register_file_type(extension: &str, format: ZTFileFormat, loader: Option<FileTypeLoader>) -> anyhow::Result<ZTFileType> {
+    let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+    if ZTFileType::builtin(&extension).is_some() {
+        return Err(anyhow!("'{}' is one of the game's file types", extension));
+    }
+
+    let mut file_types = FILE_TYPES.lock().unwrap();
+    let extension: &'static str = match file_types.get_key_value(extension.as_str()) {
+        Some((_, file_type)) if !file_type.fallback => return Err(anyhow!("'{}' is already registered", extension)),
+        Some((key, _)) => key,
+        None => Box::leak(extension.into_boxed_str()),
+    };
+    file_types.insert(extension, RegisteredFileType { format, loader, fallback: false });
+    Ok(ZTFileType::Other(extension))
 }
 
 impl From<BFResourcePtr> for ZTFile {
@@ -133,11 +248,19 @@ impl From<BFResourcePtr> for ZTFile {
                 ZTFileType::Bmp,
                 file_size,
             ),
-            _ => ZTFile::RawBytes(
-                unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(data as *mut _, file_size as usize)) },
-                ZTFileType::Animation,
-                file_size,
-            ),
+            _ => match ZTFileType::try_from(Path::new(&filename)) {
+                Ok(type_ @ ZTFileType::Other(_)) if type_.is_text() => ZTFile::Text(unsafe { CString::from_raw(data as *mut i8) }, type_, file_size),
+                Ok(type_ @ ZTFileType::Other(_)) => ZTFile::RawBytes(
+                    unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(data as *mut _, file_size as usize)) },
+                    type_,
+                    file_size,
+                ),
+                _ => ZTFile::RawBytes(
+                    unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(data as *mut _, file_size as usize)) },
+                    ZTFileType::Animation,
+                    file_size,
+                ),
+            },
         }
     }
 }
@@ -290,6 +413,11 @@ where
 pub fn ztfile_to_raw_resource(path: &str, file_name: String, ztfile: ZTFile) -> anyhow::Result<(String, ZTFileType, u32)> {
     let mut ztd_path = path.to_string();
     ztd_path = ztd_path.replace('\\', "/").replace("./", "zip::./");
+    raw_resource_in_zip(ztd_path, file_name, ztfile)
+}
+
+/// Like `ztfile_to_raw_resource`, with the zip name the game sees already formatted, e.g. "zip::./mods/moon.ztd"
+pub fn raw_resource_in_zip(ztd_path: String, file_name: String, ztfile: ZTFile) -> anyhow::Result<(String, ZTFileType, u32)> {
     let lowercase_filename = file_name.to_lowercase();
 
     let bf_zip_name = CString::new(ztd_path.clone()).with_context(|| format!("Error converting zip name to CString: {}", ztd_path))?;
@@ -324,3 +452,51 @@ pub fn ztfile_to_raw_resource(path: &str, file_name: String, ztfile: ZTFile) ->
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_type(path: &str) -> ZTFileType {
+        ZTFileType::try_from(Path::new(path)).unwrap()
+    }
+
+    fn reverse(_file_name: &str, data: Box<[u8]>) -> anyhow::Result<Box<[u8]>> {
+        let mut data = data.into_vec();
+        data.reverse();
+        Ok(data.into_boxed_slice())
+    }
+
+    #[test]
+    fn test_register_file_type() {
+        let registered = register_file_type(".ZTS", ZTFileFormat::Text, Some(reverse)).unwrap();
+        assert!(matches!(registered, ZTFileType::Other("zts")));
+        assert!(file_type("scripts/moon.zts").is_text());
+        assert_eq!(&*file_type("scripts/moon.ZTS").load("scripts/moon.zts", Box::new([1, 2, 3])).unwrap(), [3, 2, 1]);
+
+        assert_eq!(
+            register_file_type("zts", ZTFileFormat::RawBytes, None).unwrap_err().to_string(),
+            "'zts' is already registered"
+        );
+        assert_eq!(
+            register_file_type("cfg", ZTFileFormat::Text, None).unwrap_err().to_string(),
+            "'cfg' is one of the game's file types"
+        );
+    }
+
+    #[test]
+    fn test_unknown_extensions_are_raw_bytes() {
+        assert!(matches!(file_type("animals/elephant.ai"), ZTFileType::Ai));
+        assert!(matches!(file_type("animals/elephant/N"), ZTFileType::Animation));
+
+        let unknown = file_type("objects/ddogstnd/fancyblg_icons.zip");
+        assert!(matches!(unknown, ZTFileType::Other("zip")));
+        assert!(!unknown.is_text());
+        assert_eq!(&*unknown.load("fancyblg_icons.zip", Box::new([1, 2])).unwrap(), [1, 2]);
+
+        // Registering an extension already seen takes over from the fallback
+        assert!(!file_type("objects/moon/moon.zipx").is_text());
+        register_file_type("zipx", ZTFileFormat::Text, None).unwrap();
+        assert!(file_type("objects/moon/moon.zipx").is_text());
+    }
+}
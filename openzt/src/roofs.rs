@@ -79,6 +79,9 @@ pub mod roof_detours {
             hide_roofs();
         }
 
+        // Shared with the save game mod set, only one detour can hook SAVE_GAME
+        crate::resource_manager::save_mods::game_saved();
+
         result
     }
 }
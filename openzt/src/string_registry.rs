@@ -25,6 +25,15 @@ pub fn add_override_string_to_registry(string_id: u32, string_val: String) {
     data_mutex.insert(string_id, string_val);
 }
 
+/// Remove an override, restoring OpenZT's built-in override for the ID if it has one
+pub fn remove_override_string_from_registry(string_id: u32) {
+    let mut data_mutex = STRING_OVERRIDES.lock().unwrap();
+    match DEFAULT_OVERRIDES.iter().find(|(id, _)| *id == string_id) {
+        Some((_, default_override)) => data_mutex.insert(string_id, default_override.to_string()),
+        None => data_mutex.remove(&string_id),
+    };
+}
+
 pub fn get_override_string_from_registry(string_id: u32) -> Option<String> {
     // info!("Getting override string from registry: {}", string_id);
     let data_mutex = STRING_OVERRIDES.lock().unwrap();
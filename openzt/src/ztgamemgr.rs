@@ -55,6 +55,26 @@ struct Systemtime {
 }
 
 impl ZTGameMgr {
+    pub fn cash(&self) -> f32 {
+        self.cash
+    }
+
+    pub fn add_cash(&mut self, amount: f32) {
+        self.cash += amount;
+    }
+
+    pub fn num_animals(&self) -> u16 {
+        self.num_animals
+    }
+
+    pub fn num_species(&self) -> u16 {
+        self.num_species
+    }
+
+    pub fn num_guests(&self) -> u16 {
+        self.num_guests
+    }
+
     /// enables or disables dev mode
     fn enable_dev_mode(enable: bool) {
         let enable_dev_mode_address = 0x63858A;
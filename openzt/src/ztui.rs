@@ -1,12 +1,13 @@
 use std::fmt;
 
-use openzt_detour::generated::bfuimgr::GET_ELEMENT_0;
+use openzt_detour::generated::bfuimgr::{DISPLAY_MESSAGE_0, GET_ELEMENT_0};
 use openzt_detour::generated::ztui_general::GET_SELECTED_ENTITY;
 use tracing::info;
 
 use crate::{
     command_console::CommandError,
     lua_fn,
+    string_registry::add_string_to_registry,
     util::{get_from_memory, get_string_from_memory_bounded, ZTBufferString},
     ztworldmgr::read_zt_entity_from_memory,
 };
@@ -170,6 +171,13 @@ fn get_element(id: UIElementId) -> Option<UIElement> {
     Some(get_from_memory(ui_element_addr))
 }
 
+/// Show a message in the game's message bar, without a tile or entity to jump to
+pub fn display_message(message: &str) {
+    let string_id = add_string_to_registry(message.to_string());
+    let display_message_fn = unsafe { DISPLAY_MESSAGE_0.original() };
+    unsafe { display_message_fn(BFUIMGR_PTR, string_id, 0, 0, 0, false, false) };
+}
+
 fn command_get_current_buy_tab(_args: Vec<&str>) -> Result<String, CommandError> {
     let tab = get_current_buy_tab();
     Ok(format!("{:?}", tab))